@@ -0,0 +1,112 @@
+//! Encode and decode costs of the wire types every request and record batch goes through.
+
+use bytes::{Bytes, BytesMut};
+use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
+
+use forge::core::domain::record::{Header, Record};
+use forge::core::domain::record_batch::RecordBatch;
+use forge::protocol::types::{ByteStr, CompactString, Type, Varint, Varlong};
+
+fn encoded<T: Type>(value: &T) -> Bytes {
+    let mut buf = BytesMut::new();
+    value.encode(&mut buf);
+    buf.freeze()
+}
+
+fn varints(c: &mut Criterion) {
+    let mut group = c.benchmark_group("varint");
+    for value in [0, 300, i32::MAX] {
+        let bytes = encoded(&Varint(value));
+        group.bench_with_input(BenchmarkId::new("encode", value), &value, |b, &value| {
+            let mut buf = BytesMut::with_capacity(16);
+            b.iter(|| {
+                buf.clear();
+                Varint(black_box(value)).encode(&mut buf);
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("decode", value), &bytes, |b, bytes| {
+            b.iter(|| Varint::decode(&mut black_box(bytes.clone())).unwrap())
+        });
+    }
+    let bytes = encoded(&Varlong(i64::MAX));
+    group.bench_function("decode_varlong_max", |b| {
+        b.iter(|| Varlong::decode(&mut black_box(bytes.clone())).unwrap())
+    });
+    group.finish();
+}
+
+fn strings(c: &mut Criterion) {
+    let mut group = c.benchmark_group("string");
+    let value = "orders-eu-west-1".to_string();
+    let bytes = encoded(&value);
+    group.bench_function("encode", |b| {
+        let mut buf = BytesMut::with_capacity(64);
+        b.iter(|| {
+            buf.clear();
+            black_box(&value).encode(&mut buf);
+        })
+    });
+    group.bench_function("decode", |b| {
+        b.iter(|| String::decode(&mut black_box(bytes.clone())).unwrap())
+    });
+    group.bench_function("decode_bytes_backed", |b| {
+        b.iter(|| ByteStr::decode(&mut black_box(bytes.clone())).unwrap())
+    });
+    let compact = encoded(&CompactString(value.clone()));
+    group.bench_function("decode_compact", |b| {
+        b.iter(|| CompactString::decode(&mut black_box(compact.clone())).unwrap())
+    });
+    group.finish();
+}
+
+/// `count` records of `value_size` bytes, each with `headers` headers.
+fn batch(count: usize, value_size: usize, headers: usize) -> RecordBatch {
+    let records = (0..count)
+        .map(|i| {
+            let mut record = Record::new(
+                i as i32,
+                Some(format!("key-{}", i).into_bytes()),
+                Some(vec![b'x'; value_size]),
+            );
+            record.headers = (0..headers)
+                .map(|h| Header {
+                    key: format!("header-{}", h).into(),
+                    value: Some(b"value".to_vec()),
+                })
+                .collect();
+            record
+        })
+        .collect();
+    RecordBatch::new(1_700_000_000_000, records)
+}
+
+fn record_batches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("record_batch");
+    for (name, batch) in [
+        ("100x100B", batch(100, 100, 0)),
+        ("10x10KiB", batch(10, 10 * 1024, 0)),
+        ("100x100B_8_headers", batch(100, 100, 8)),
+    ] {
+        let bytes = encoded(&batch);
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(BenchmarkId::new("encode", name), &batch, |b, batch| {
+            let mut buf = BytesMut::with_capacity(bytes.len());
+            b.iter(|| {
+                buf.clear();
+                batch.encode_to(&mut buf);
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("decode", name), &bytes, |b, bytes| {
+            b.iter(|| RecordBatch::decode(&mut black_box(bytes.clone())).unwrap())
+        });
+        group.bench_with_input(
+            BenchmarkId::new("verify_checksum", name),
+            &bytes,
+            |b, bytes| b.iter(|| RecordBatch::verify_checksum(black_box(bytes)).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, varints, strings, record_batches);
+criterion_main!(benches);
@@ -0,0 +1,122 @@
+//! Segment append and read costs against real files in the temp directory.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use tokio::runtime::Runtime;
+
+use forge::adapters::driven::storage::segment::Segment;
+use forge::core::domain::record::Record;
+use forge::core::domain::record_batch::RecordBatch;
+
+/// Batches written to the segment the read benchmarks use.
+const BATCHES: i64 = 2_000;
+const RECORDS_PER_BATCH: usize = 10;
+const VALUE_SIZE: usize = 100;
+
+fn bench_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("forge-bench-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn batch_at(base_offset: i64) -> RecordBatch {
+    let records = (0..RECORDS_PER_BATCH)
+        .map(|i| Record::new(i as i32, None, Some(vec![b'x'; VALUE_SIZE])))
+        .collect();
+    let mut batch = RecordBatch::new(1_700_000_000_000, records);
+    batch.base_offset = base_offset;
+    batch
+}
+
+async fn filled_segment(dir: &PathBuf) -> Segment {
+    let mut segment = Segment::new(dir, 0).await.unwrap();
+    for i in 0..BATCHES {
+        segment
+            .append(&batch_at(i * RECORDS_PER_BATCH as i64))
+            .await
+            .unwrap();
+    }
+    segment
+}
+
+fn append(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("segment");
+    group.throughput(Throughput::Elements(RECORDS_PER_BATCH as u64));
+    group.bench_function("append", |b| {
+        b.iter_custom(|iters| {
+            runtime.block_on(async {
+                let dir = bench_dir();
+                let mut segment = Segment::new(&dir, 0).await.unwrap();
+                let batches: Vec<RecordBatch> = (0..iters as i64)
+                    .map(|i| batch_at(i * RECORDS_PER_BATCH as i64))
+                    .collect();
+                let start = Instant::now();
+                for batch in &batches {
+                    segment.append(batch).await.unwrap();
+                }
+                let elapsed = start.elapsed();
+                tokio::fs::remove_dir_all(&dir).await.unwrap();
+                elapsed
+            })
+        })
+    });
+    group.finish();
+}
+
+fn read(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let dir = bench_dir();
+    let mut segment = runtime.block_on(filled_segment(&dir));
+    let last_offset = BATCHES * RECORDS_PER_BATCH as i64;
+
+    let mut group = c.benchmark_group("segment");
+    group.bench_function("read", |b| {
+        let mut offset = 0;
+        b.iter_custom(|iters| {
+            runtime.block_on(async {
+                let mut elapsed = Duration::ZERO;
+                for _ in 0..iters {
+                    // Strides across the segment so each lookup lands somewhere new
+                    offset = (offset + 7_919) % last_offset;
+                    let start = Instant::now();
+                    segment.read(offset).await.unwrap().unwrap();
+                    elapsed += start.elapsed();
+                }
+                elapsed
+            })
+        })
+    });
+
+    let max_bytes = 1024 * 1024;
+    group.throughput(Throughput::Bytes(max_bytes as u64));
+    group.bench_function("read_sequential_1MiB", |b| {
+        let mut offset = 0;
+        b.iter_custom(|iters| {
+            runtime.block_on(async {
+                let mut elapsed = Duration::ZERO;
+                for _ in 0..iters {
+                    let start = Instant::now();
+                    let batches = segment.read_sequential(offset, max_bytes).await.unwrap();
+                    elapsed += start.elapsed();
+                    // Picks up where the last read stopped, as a consumer would
+                    offset = match batches.last() {
+                        Some(last) if last.last_offset() + 1 < last_offset => {
+                            last.last_offset() + 1
+                        }
+                        _ => 0,
+                    };
+                }
+                elapsed
+            })
+        })
+    });
+    group.finish();
+
+    runtime.block_on(tokio::fs::remove_dir_all(&dir)).unwrap();
+}
+
+criterion_group!(benches, append, read);
+criterion_main!(benches);
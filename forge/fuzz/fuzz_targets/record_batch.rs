@@ -0,0 +1,22 @@
+#![no_main]
+
+use forge::protocol::fuzzing::{decode_record_batch, decode_record_batches};
+use libfuzzer_sys::fuzz_target;
+
+/// Where the CRC-covered part of a batch starts.
+const ATTRIBUTES_OFFSET: usize = 8 + 4 + 4 + 1 + 4;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_record_batch(data);
+    let _ = decode_record_batches(data);
+
+    // Random bytes almost never carry a valid CRC; stamp one on so the records get decoded too
+    if data.len() >= ATTRIBUTES_OFFSET {
+        let mut batch = data.to_vec();
+        let crc = crc32c::crc32c(&batch[ATTRIBUTES_OFFSET..]);
+        let length = (batch.len() - 12) as i32;
+        batch[8..12].copy_from_slice(&length.to_be_bytes());
+        batch[17..21].copy_from_slice(&crc.to_be_bytes());
+        let _ = decode_record_batch(&batch);
+    }
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// A request frame after its size prefix: header, then the body it selects
+fuzz_target!(|data: &[u8]| {
+    let _ = forge::protocol::fuzzing::decode_request(data);
+});
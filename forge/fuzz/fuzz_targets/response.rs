@@ -0,0 +1,14 @@
+#![no_main]
+
+use forge::protocol::fuzzing::{APIS, decode_response};
+use libfuzzer_sys::fuzz_target;
+
+// The first two bytes pick the api and version, the rest is the response frame
+fuzz_target!(|data: &[u8]| {
+    let [api, version, frame @ ..] = data else {
+        return;
+    };
+    let (api_key, min, max) = APIS[*api as usize % APIS.len()];
+    let version = min + (*version as i16 % (max - min + 1));
+    let _ = decode_response(api_key, version, frame);
+});
@@ -1 +1,8 @@
+pub mod acl_authorizer;
+pub mod broker_client;
+pub mod credential_store;
+pub mod meta_properties;
+pub mod producer_id;
 pub mod storage;
+#[cfg(feature = "wasm")]
+pub mod wasm_interceptor;
@@ -0,0 +1,240 @@
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+use crate::core::domain::acl::{AclBinding, AclOperation, AclPermissionType, Resource};
+use crate::core::domain::principal::KafkaPrincipal;
+use crate::core::ports::driven::Authorizer;
+
+/// Keeps ACLs in memory and persists them to a text file, one binding per line. Lines that are
+/// empty or start with `#` are ignored, so the file can also be edited by hand while the broker
+/// is down.
+pub struct FileAclAuthorizer {
+    path: PathBuf,
+    super_users: Vec<KafkaPrincipal>,
+    /// When false, a resource without any ACL is denied to everyone but super users.
+    allow_everyone_if_no_acl_found: bool,
+    acls: RwLock<Vec<AclBinding>>,
+}
+
+impl FileAclAuthorizer {
+    pub async fn load(
+        path: impl AsRef<Path>,
+        super_users: Vec<KafkaPrincipal>,
+        allow_everyone_if_no_acl_found: bool,
+    ) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+        let acls = match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::parse)
+                .collect::<Result<Vec<AclBinding>, String>>()?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                return Err(format!("IO error when reading {}: {}", path.display(), e));
+            }
+        };
+        tracing::info!("Loaded {} ACLs from {}", acls.len(), path.display());
+
+        Ok(Self {
+            path,
+            super_users,
+            allow_everyone_if_no_acl_found,
+            acls: RwLock::new(acls),
+        })
+    }
+
+    async fn persist(&self, acls: &[AclBinding]) -> Result<(), String> {
+        let mut contents = String::new();
+        for acl in acls {
+            contents.push_str(&acl.to_string());
+            contents.push('\n');
+        }
+
+        // Write to a temporary file and rename so a crash never leaves a torn ACL file behind
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = tokio::fs::File::create(&tmp_path)
+            .await
+            .map_err(|e| e.to_string())?;
+        file.write_all(contents.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        file.sync_all().await.map_err(|e| e.to_string())?;
+        tokio::fs::rename(&tmp_path, &self.path)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[async_trait]
+impl Authorizer for FileAclAuthorizer {
+    /// Super users may do anything. Otherwise a matching Deny wins over any Allow, and an
+    /// Allow for Read, Write, Delete or Alter also allows Describe.
+    async fn authorize(
+        &self,
+        principal: &KafkaPrincipal,
+        host: &str,
+        operation: AclOperation,
+        resource: &Resource,
+    ) -> bool {
+        if self.super_users.contains(principal) {
+            return true;
+        }
+
+        let acls = self.acls.read().await;
+        let mut matching = acls
+            .iter()
+            .filter(|acl| acl.pattern.matches(resource))
+            .peekable();
+        if matching.peek().is_none() {
+            return self.allow_everyone_if_no_acl_found;
+        }
+
+        let applicable: Vec<&AclBinding> = matching
+            .filter(|acl| acl.applies_to(principal, host))
+            .collect();
+        let denied = applicable.iter().any(|acl| {
+            acl.permission_type == AclPermissionType::Deny
+                && (acl.operation == AclOperation::All || acl.operation == operation)
+        });
+        if denied {
+            return false;
+        }
+        applicable.iter().any(|acl| {
+            acl.permission_type == AclPermissionType::Allow && acl.operation.implies(operation)
+        })
+    }
+
+    async fn acls(&self) -> Vec<AclBinding> {
+        self.acls.read().await.clone()
+    }
+
+    async fn create_acls(&self, bindings: Vec<AclBinding>) -> Result<(), String> {
+        let mut acls = self.acls.write().await;
+        let mut updated = acls.clone();
+        for binding in bindings {
+            if !updated.contains(&binding) {
+                updated.push(binding);
+            }
+        }
+        self.persist(&updated).await?;
+        *acls = updated;
+        Ok(())
+    }
+
+    async fn delete_acls(&self, bindings: &[AclBinding]) -> Result<usize, String> {
+        let mut acls = self.acls.write().await;
+        let updated: Vec<AclBinding> = acls
+            .iter()
+            .filter(|acl| !bindings.contains(acl))
+            .cloned()
+            .collect();
+        let removed = acls.len() - updated.len();
+        if removed > 0 {
+            self.persist(&updated).await?;
+            *acls = updated;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::domain::acl::{PatternType, ResourcePattern, ResourceType, WILDCARD};
+
+    fn binding(
+        principal: &str,
+        operation: AclOperation,
+        permission_type: AclPermissionType,
+        name: &str,
+        pattern_type: PatternType,
+    ) -> AclBinding {
+        AclBinding {
+            pattern: ResourcePattern {
+                resource_type: ResourceType::Topic,
+                name: name.to_string(),
+                pattern_type,
+            },
+            principal: principal.to_string(),
+            host: WILDCARD.to_string(),
+            operation,
+            permission_type,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_acls_persist_and_deny_wins() {
+        let dir = std::env::temp_dir().join(format!("forge-acl-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("acls");
+
+        let authorizer = FileAclAuthorizer::load(&path, vec![], false).await.unwrap();
+        authorizer
+            .create_acls(vec![
+                binding(
+                    "User:*",
+                    AclOperation::Read,
+                    AclPermissionType::Allow,
+                    "orders-",
+                    PatternType::Prefixed,
+                ),
+                binding(
+                    "User:mallory",
+                    AclOperation::Read,
+                    AclPermissionType::Deny,
+                    "orders-eu",
+                    PatternType::Literal,
+                ),
+            ])
+            .await
+            .unwrap();
+
+        let authorizer = FileAclAuthorizer::load(&path, vec![], false).await.unwrap();
+        let alice = KafkaPrincipal::user("alice");
+        let mallory = KafkaPrincipal::user("mallory");
+        let topic = |name: &str| Resource::new(ResourceType::Topic, name);
+
+        assert!(
+            authorizer
+                .authorize(&alice, "10.0.0.1", AclOperation::Read, &topic("orders-eu"))
+                .await
+        );
+        assert!(
+            authorizer
+                .authorize(
+                    &alice,
+                    "10.0.0.1",
+                    AclOperation::Describe,
+                    &topic("orders-eu")
+                )
+                .await
+        );
+        assert!(
+            !authorizer
+                .authorize(&alice, "10.0.0.1", AclOperation::Write, &topic("orders-eu"))
+                .await
+        );
+        assert!(
+            !authorizer
+                .authorize(
+                    &mallory,
+                    "10.0.0.1",
+                    AclOperation::Read,
+                    &topic("orders-eu")
+                )
+                .await
+        );
+        // No ACL at all on the resource and not allowing everyone
+        assert!(
+            !authorizer
+                .authorize(&alice, "10.0.0.1", AclOperation::Read, &topic("payments"))
+                .await
+        );
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
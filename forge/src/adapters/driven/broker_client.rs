@@ -0,0 +1,276 @@
+use async_trait::async_trait;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::application::sasl::create_client;
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::FetchClient;
+use crate::protocol::fetch::{FETCH_API_KEY, FETCH_MAX_VERSION, FetchRequest, FetchResponse};
+use crate::protocol::request::RequestHeader;
+use crate::protocol::response::ResponseHeader;
+use crate::protocol::sasl_authenticate::{
+    SASL_AUTHENTICATE_API_KEY, SASL_AUTHENTICATE_MAX_VERSION, SaslAuthenticateRequest,
+    SaslAuthenticateResponse,
+};
+use crate::protocol::sasl_handshake::{
+    SASL_HANDSHAKE_API_KEY, SASL_HANDSHAKE_MAX_VERSION, SaslHandshakeRequest, SaslHandshakeResponse,
+};
+use crate::protocol::types::{TaggedFields, Type};
+
+/// How each new connection logs in.
+#[derive(Clone)]
+struct SaslLogin {
+    mechanism: String,
+    username: String,
+    password: String,
+}
+
+/// A single connection to another broker, reconnecting lazily after any IO failure.
+pub struct BrokerClient {
+    pub address: String,
+    pub client_id: String,
+    sasl: Option<SaslLogin>,
+    stream: Option<TcpStream>,
+    next_correlation_id: i32,
+}
+
+impl BrokerClient {
+    pub fn new(address: impl Into<String>, client_id: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            client_id: client_id.into(),
+            sasl: None,
+            stream: None,
+            next_correlation_id: 0,
+        }
+    }
+
+    /// Authenticates each new connection with SASL `mechanism` before using it.
+    pub fn with_sasl(mut self, mechanism: String, username: String, password: String) -> Self {
+        self.sasl = Some(SaslLogin {
+            mechanism,
+            username,
+            password,
+        });
+        self
+    }
+
+    /// Encodes a size-prefixed request and returns it with its correlation id.
+    fn frame_request(
+        &mut self,
+        api_key: i16,
+        api_version: i16,
+        encode_body: impl FnOnce(&mut BytesMut),
+    ) -> (i32, BytesMut) {
+        let correlation_id = self.next_correlation_id;
+        self.next_correlation_id = self.next_correlation_id.wrapping_add(1);
+
+        let mut body = BytesMut::new();
+        RequestHeader {
+            api_key,
+            api_version,
+            correlation_id,
+            client_id: Some(self.client_id.clone()),
+        }
+        .encode(&mut body);
+        encode_body(&mut body);
+
+        let mut frame = BytesMut::with_capacity(body.len() + 4);
+        frame.put_i32(body.len() as i32);
+        frame.put_slice(&body);
+        (correlation_id, frame)
+    }
+
+    /// Strips the response header, checking it answers `correlation_id`.
+    fn read_response_header(
+        &self,
+        correlation_id: i32,
+        response: &mut Bytes,
+    ) -> Result<(), String> {
+        let header = ResponseHeader::decode(response)?;
+        if header.correlation_id != correlation_id {
+            return Err(format!(
+                "Correlation id mismatch from {}: expected {}, got {}",
+                self.address, correlation_id, header.correlation_id
+            ));
+        }
+        Ok(())
+    }
+
+    /// Sends one request and returns the response body that follows the response header.
+    pub async fn send_request(
+        &mut self,
+        api_key: i16,
+        api_version: i16,
+        encode_body: impl FnOnce(&mut BytesMut),
+    ) -> Result<Bytes, String> {
+        let (correlation_id, frame) = self.frame_request(api_key, api_version, encode_body);
+        let result = self.round_trip(&frame).await;
+        if result.is_err() {
+            self.stream = None;
+        }
+        let mut response = result?;
+
+        if let Err(e) = self.read_response_header(correlation_id, &mut response) {
+            self.stream = None;
+            return Err(e);
+        }
+        Ok(response)
+    }
+
+    /// Like `send_request`, for APIs only known in their flexible versions, whose request and
+    /// response headers end in tagged fields.
+    pub async fn send_flexible_request(
+        &mut self,
+        api_key: i16,
+        api_version: i16,
+        encode_body: impl FnOnce(&mut BytesMut),
+    ) -> Result<Bytes, String> {
+        let mut response = self
+            .send_request(api_key, api_version, |buf| {
+                TaggedFields.encode(buf);
+                encode_body(buf);
+            })
+            .await?;
+        TaggedFields::decode(&mut response)?;
+        Ok(response)
+    }
+
+    /// Sends a request the broker does not answer, such as a produce with acks=0.
+    pub async fn send_without_response(
+        &mut self,
+        api_key: i16,
+        api_version: i16,
+        encode_body: impl FnOnce(&mut BytesMut),
+    ) -> Result<(), String> {
+        let (_, frame) = self.frame_request(api_key, api_version, encode_body);
+        let result = match self.connect().await {
+            Ok(stream) => stream.write_all(&frame).await.map_err(|e| e.to_string()),
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            self.stream = None;
+        }
+        result
+    }
+
+    async fn round_trip(&mut self, frame: &[u8]) -> Result<Bytes, String> {
+        let stream = self.connect().await?;
+        Self::exchange(stream, frame).await
+    }
+
+    /// The open connection, connecting and logging in first if there is none.
+    async fn connect(&mut self) -> Result<&mut TcpStream, String> {
+        if self.stream.is_none() {
+            let mut stream = TcpStream::connect(&self.address)
+                .await
+                .map_err(|e| format!("Failed to connect to {}: {}", self.address, e))?;
+            if let Some(login) = self.sasl.clone() {
+                self.authenticate(&mut stream, login).await?;
+            }
+            self.stream = Some(stream);
+        }
+        self.stream
+            .as_mut()
+            .ok_or_else(|| format!("No connection to {}", self.address))
+    }
+
+    /// Runs the SASL exchange before the connection carries any other request.
+    async fn authenticate(
+        &mut self,
+        stream: &mut TcpStream,
+        login: SaslLogin,
+    ) -> Result<(), String> {
+        let Some(mut client) = create_client(&login.mechanism, login.username, login.password)
+        else {
+            return Err(format!("Unsupported SASL mechanism {}", login.mechanism));
+        };
+
+        let (correlation_id, frame) =
+            self.frame_request(SASL_HANDSHAKE_API_KEY, SASL_HANDSHAKE_MAX_VERSION, |buf| {
+                SaslHandshakeRequest {
+                    mechanism: login.mechanism.clone(),
+                }
+                .encode(buf, SASL_HANDSHAKE_MAX_VERSION)
+            });
+        let mut response = Self::exchange(stream, &frame).await?;
+        self.read_response_header(correlation_id, &mut response)?;
+        let handshake = SaslHandshakeResponse::decode(&mut response, SASL_HANDSHAKE_MAX_VERSION)?;
+        if handshake.error_code != ErrorCode::None.code() {
+            return Err(format!(
+                "{} does not accept SASL {}; it offers {:?}",
+                self.address, login.mechanism, handshake.mechanisms
+            ));
+        }
+
+        let mut challenge = Vec::new();
+        while !client.is_complete() {
+            let Some(auth_bytes) = client.evaluate_challenge(&challenge)? else {
+                break;
+            };
+            let (correlation_id, frame) = self.frame_request(
+                SASL_AUTHENTICATE_API_KEY,
+                SASL_AUTHENTICATE_MAX_VERSION,
+                |buf| {
+                    SaslAuthenticateRequest { auth_bytes }
+                        .encode(buf, SASL_AUTHENTICATE_MAX_VERSION)
+                },
+            );
+            let mut response = Self::exchange(stream, &frame).await?;
+            self.read_response_header(correlation_id, &mut response)?;
+            let authenticate =
+                SaslAuthenticateResponse::decode(&mut response, SASL_AUTHENTICATE_MAX_VERSION)?;
+            if authenticate.error_code != ErrorCode::None.code() {
+                return Err(format!(
+                    "Failed to authenticate to {}: {}",
+                    self.address,
+                    authenticate.error_message.unwrap_or_default()
+                ));
+            }
+            challenge = authenticate.auth_bytes;
+        }
+        if !client.is_complete() {
+            return Err(format!(
+                "SASL {} exchange with {} ended early",
+                login.mechanism, self.address
+            ));
+        }
+        Ok(())
+    }
+
+    async fn exchange(stream: &mut TcpStream, frame: &[u8]) -> Result<Bytes, String> {
+        stream.write_all(frame).await.map_err(|e| e.to_string())?;
+
+        let size = stream.read_i32().await.map_err(|e| e.to_string())?;
+        if size < 0 {
+            return Err(format!("Invalid response size {}", size));
+        }
+        let mut response = vec![0u8; size as usize];
+        stream
+            .read_exact(&mut response)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(Bytes::from(response))
+    }
+}
+
+#[async_trait]
+impl FetchClient for BrokerClient {
+    async fn fetch(&mut self, request: &FetchRequest) -> Result<FetchResponse, String> {
+        let mut response = self
+            .send_request(FETCH_API_KEY, FETCH_MAX_VERSION, |buf| {
+                request.encode(buf, FETCH_MAX_VERSION)
+            })
+            .await?;
+        let fetch_response = FetchResponse::decode(&mut response, FETCH_MAX_VERSION)?;
+        if response.has_remaining() {
+            tracing::debug!(
+                "Ignoring {} trailing bytes in fetch response from {}",
+                response.remaining(),
+                self.address
+            );
+        }
+        Ok(fetch_response)
+    }
+}
@@ -0,0 +1,93 @@
+use async_trait::async_trait;
+use std::path::Path;
+
+use crate::application::sasl::PLAIN_MECHANISM;
+use crate::application::sasl::scram::ScramMechanism;
+use crate::core::domain::scram_credential::ScramCredential;
+use crate::core::ports::driven::CredentialStore;
+use crate::shared::byte::constant_time_eq;
+use crate::shared::collections::FlatMap;
+
+/// Reads credentials from a text file with one `<mechanism> <username> <secret>` line per
+/// credential, e.g. `PLAIN alice alice-secret`. The secret runs to the end of the line; for
+/// SCRAM it is the salted credential (see `ScramCredential`), never the password. Lines that
+/// are empty or start with `#` are ignored. The file is read once at startup.
+pub struct FileCredentialStore {
+    plain: FlatMap<String, String>,
+    /// Keyed by mechanism name and username.
+    scram: FlatMap<(String, String), ScramCredential>,
+}
+
+impl FileCredentialStore {
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = match tokio::fs::read_to_string(path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(format!("IO error when reading {}: {}", path.display(), e));
+            }
+        };
+
+        let mut plain = FlatMap::new();
+        let mut scram = FlatMap::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.splitn(3, char::is_whitespace);
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(PLAIN_MECHANISM), Some(username), Some(password)) => {
+                    plain.insert(username.to_string(), password.trim_start().to_string());
+                }
+                (Some(mechanism), Some(username), Some(secret))
+                    if ScramMechanism::from_name(mechanism).is_some() =>
+                {
+                    let credential = secret.trim_start().parse().map_err(|e| {
+                        format!("{} on line {} of {}", e, index + 1, path.display())
+                    })?;
+                    scram.insert((mechanism.to_string(), username.to_string()), credential);
+                }
+                (Some(mechanism), Some(_), Some(_)) => {
+                    return Err(format!(
+                        "Unsupported mechanism {} on line {} of {}",
+                        mechanism,
+                        index + 1,
+                        path.display()
+                    ));
+                }
+                _ => {
+                    return Err(format!(
+                        "Invalid credential on line {} of {}: expected <mechanism> <username> <secret>",
+                        index + 1,
+                        path.display()
+                    ));
+                }
+            }
+        }
+        tracing::info!(
+            "Loaded {} PLAIN and {} SCRAM credentials from {}",
+            plain.len(),
+            scram.len(),
+            path.display()
+        );
+
+        Ok(Self { plain, scram })
+    }
+}
+
+#[async_trait]
+impl CredentialStore for FileCredentialStore {
+    async fn verify_plain(&self, username: &str, password: &str) -> bool {
+        self.plain
+            .get(&username.to_string())
+            .is_some_and(|expected| constant_time_eq(expected.as_bytes(), password.as_bytes()))
+    }
+
+    async fn scram_credential(&self, mechanism: &str, username: &str) -> Option<ScramCredential> {
+        self.scram
+            .get(&(mechanism.to_string(), username.to_string()))
+            .cloned()
+    }
+}
@@ -0,0 +1,128 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use std::path::Path;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::parse_properties;
+use crate::shared::constants::META_PROPERTIES_FILE;
+
+pub const META_PROPERTIES_VERSION: u32 = 1;
+
+/// Identifies the cluster and node a log directory was formatted for, kept in the
+/// `meta.properties` file at its root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaProperties {
+    pub version: u32,
+    pub cluster_id: String,
+    pub node_id: i32,
+}
+
+impl MetaProperties {
+    pub fn new(cluster_id: String, node_id: i32) -> Self {
+        Self {
+            version: META_PROPERTIES_VERSION,
+            cluster_id,
+            node_id,
+        }
+    }
+
+    /// A random cluster id, written as Kafka does: a v4 uuid in unpadded URL-safe base64.
+    pub fn random_cluster_id() -> String {
+        URL_SAFE_NO_PAD.encode(uuid::Uuid::new_v4().as_bytes())
+    }
+
+    /// Cluster ids must decode to the 16 bytes of a uuid.
+    pub fn validate_cluster_id(cluster_id: &str) -> Result<(), String> {
+        match URL_SAFE_NO_PAD.decode(cluster_id) {
+            Ok(bytes) if bytes.len() == 16 => Ok(()),
+            _ => Err(format!(
+                "Cluster id {} is not a base64-encoded uuid",
+                cluster_id
+            )),
+        }
+    }
+
+    /// Reads the file in `log_dir`; `None` when the directory was never formatted.
+    pub async fn read(log_dir: &Path) -> Result<Option<Self>, String> {
+        let path = log_dir.join(META_PROPERTIES_FILE);
+        let contents = match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("IO error when reading {}: {}", path.display(), e)),
+        };
+        let properties =
+            parse_properties(&contents).map_err(|e| format!("{} (in {})", e, path.display()))?;
+        let get = |name: &str| {
+            properties
+                .iter()
+                .rev()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+                .ok_or_else(|| format!("{} has no {}", path.display(), name))
+        };
+        let invalid = |name: &str, value: &str| {
+            format!("{} has an invalid {}: {}", path.display(), name, value)
+        };
+
+        let version = get("version")?;
+        let version: u32 = version.parse().map_err(|_| invalid("version", version))?;
+        if version != META_PROPERTIES_VERSION {
+            return Err(format!(
+                "{} has unsupported version {}",
+                path.display(),
+                version
+            ));
+        }
+        let node_id = get("node.id")?;
+        Ok(Some(Self {
+            version,
+            cluster_id: get("cluster.id")?.to_string(),
+            node_id: node_id.parse().map_err(|_| invalid("node.id", node_id))?,
+        }))
+    }
+
+    /// Creates `log_dir` if needed and writes the file, replacing any existing one.
+    pub async fn write(&self, log_dir: &Path) -> Result<(), String> {
+        tokio::fs::create_dir_all(log_dir)
+            .await
+            .map_err(|e| format!("Failed to create {}: {}", log_dir.display(), e))?;
+        let contents = format!(
+            "version={}\ncluster.id={}\nnode.id={}\n",
+            self.version, self.cluster_id, self.node_id
+        );
+
+        // Write to a temporary file and rename so a crash never leaves a torn file behind
+        let path = log_dir.join(META_PROPERTIES_FILE);
+        let tmp_path = path.with_extension("tmp");
+        let mut file = tokio::fs::File::create(&tmp_path)
+            .await
+            .map_err(|e| e.to_string())?;
+        file.write_all(contents.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        file.sync_all().await.map_err(|e| e.to_string())?;
+        tokio::fs::rename(&tmp_path, &path)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_write_then_read() {
+        let dir = std::env::temp_dir().join(format!("forge-meta-{}", uuid::Uuid::new_v4()));
+        assert_eq!(MetaProperties::read(&dir).await.unwrap(), None);
+
+        let cluster_id = MetaProperties::random_cluster_id();
+        MetaProperties::validate_cluster_id(&cluster_id).unwrap();
+        let meta = MetaProperties::new(cluster_id, 3);
+        meta.write(&dir).await.unwrap();
+        assert_eq!(MetaProperties::read(&dir).await.unwrap(), Some(meta));
+
+        assert!(MetaProperties::validate_cluster_id("not-a-uuid").is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use bytes::BytesMut;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+use crate::core::domain::producer_id_block::ProducerIdBlock;
+use crate::core::ports::driven::ProducerIdBlockSource;
+use crate::protocol::types::Type;
+use crate::shared::constants::PRODUCER_ID_BLOCK_FILE;
+
+/// Keeps the last allocated block in a file under the log directory. Only safe while a
+/// single broker allocates producer ids.
+pub struct LocalProducerIdBlockSource {
+    path: PathBuf,
+}
+
+impl LocalProducerIdBlockSource {
+    pub fn new(log_dir: impl AsRef<Path>) -> Self {
+        Self {
+            path: log_dir.as_ref().join(PRODUCER_ID_BLOCK_FILE),
+        }
+    }
+
+    async fn read_last_block(&self) -> Result<Option<ProducerIdBlock>, String> {
+        match tokio::fs::read(&self.path).await {
+            Ok(bytes) => ProducerIdBlock::decode(&mut bytes.as_slice()).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!(
+                "IO error when reading {}: {}",
+                self.path.display(),
+                e
+            )),
+        }
+    }
+
+    async fn write_block(&self, block: &ProducerIdBlock) -> Result<(), String> {
+        let mut buffer = BytesMut::new();
+        block.encode(&mut buffer);
+
+        // Write to a temporary file and rename so a crash never leaves a torn block behind
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = tokio::fs::File::create(&tmp_path)
+            .await
+            .map_err(|e| e.to_string())?;
+        file.write_all(&buffer).await.map_err(|e| e.to_string())?;
+        file.sync_all().await.map_err(|e| e.to_string())?;
+        tokio::fs::rename(&tmp_path, &self.path)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[async_trait]
+impl ProducerIdBlockSource for LocalProducerIdBlockSource {
+    async fn allocate_block(
+        &mut self,
+        broker_id: i32,
+        block_size: i64,
+    ) -> Result<ProducerIdBlock, String> {
+        let first_producer_id = self
+            .read_last_block()
+            .await?
+            .map_or(0, |block| block.next_block_first_id());
+
+        if first_producer_id > i64::MAX - block_size {
+            return Err("Producer id space exhausted".to_string());
+        }
+
+        let block = ProducerIdBlock {
+            assigned_broker_id: broker_id,
+            first_producer_id,
+            size: block_size,
+        };
+        self.write_block(&block).await?;
+        Ok(block)
+    }
+}
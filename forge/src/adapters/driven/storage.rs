@@ -1,3 +1,7 @@
+pub mod blocking_file_system;
 pub mod compaction;
+pub mod fault_injection;
+pub mod file_system;
 pub mod log;
 pub mod segment;
+pub mod verifier;
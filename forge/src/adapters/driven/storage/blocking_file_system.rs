@@ -0,0 +1,577 @@
+//! Segment files through std::fs on tokio's blocking pool. Every read, append and sync is a
+//! single blocking call, where tokio::fs splits large writes through a bounded buffer of its
+//! own. With `direct` the files are opened with O_DIRECT and bypass the page cache.
+
+use async_trait::async_trait;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{self, SeekFrom, Write};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, ready};
+use std::time::SystemTime;
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+use tokio::task::{JoinHandle, spawn_blocking};
+
+use crate::adapters::driven::storage::file_system::{FileSystem, SegmentFile};
+
+/// The most a single read asks the disk for, whatever the caller's buffer.
+const MAX_READ_SIZE: usize = 4 * 1024 * 1024;
+
+/// What O_DIRECT transfers must be aligned to, in memory, on disk and in length. Covers the
+/// logical block size of any disk in use.
+const DIRECT_IO_ALIGNMENT: usize = 4096;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlockingFileSystem {
+    direct: bool,
+}
+
+impl BlockingFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens segment files with O_DIRECT. Only on Linux, and only on file systems that take
+    /// it; opening fails otherwise.
+    pub fn direct() -> Self {
+        Self { direct: true }
+    }
+}
+
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> io::Result<T> + Send + 'static,
+) -> io::Result<T> {
+    spawn_blocking(f).await.map_err(io::Error::other)?
+}
+
+#[async_trait]
+impl FileSystem for BlockingFileSystem {
+    async fn open_append(&self, path: &Path) -> io::Result<Box<dyn SegmentFile>> {
+        let path = path.to_path_buf();
+        if self.direct {
+            let file = blocking(move || DirectFile::open(&path)).await?;
+            return Ok(Box::new(BlockingFile::new(file)));
+        }
+        let file = blocking(move || {
+            OpenOptions::new()
+                .create(true)
+                .read(true)
+                .append(true)
+                .open(path)
+        })
+        .await?;
+        Ok(Box::new(BlockingFile::new(file)))
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let path = path.to_path_buf();
+        blocking(move || std::fs::create_dir_all(path)).await
+    }
+
+    async fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let path = path.to_path_buf();
+        blocking(move || {
+            std::fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect()
+        })
+        .await
+    }
+
+    async fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let path = path.to_path_buf();
+        blocking(move || std::fs::remove_file(path)).await
+    }
+
+    async fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        let path = path.to_path_buf();
+        blocking(move || std::fs::remove_dir_all(path)).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let (from, to) = (from.to_path_buf(), to.to_path_buf());
+        blocking(move || std::fs::rename(from, to)).await
+    }
+
+    async fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        let path = path.to_path_buf();
+        blocking(move || std::fs::metadata(path)?.modified()).await
+    }
+
+    async fn sync_dir(&self, path: &Path) -> io::Result<()> {
+        let path = path.to_path_buf();
+        blocking(move || File::open(path)?.sync_all()).await
+    }
+}
+
+/// The blocking calls an open segment file makes.
+trait BlockingIo: Send + Sync + 'static {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize>;
+    /// Writes all of `buf` at the end of the file.
+    fn append(&self, buf: &[u8]) -> io::Result<()>;
+    fn size(&self) -> io::Result<u64>;
+    fn set_len(&self, size: u64) -> io::Result<()>;
+    fn sync_data(&self) -> io::Result<()>;
+}
+
+/// Opened for appending, so every write lands at the end.
+impl BlockingIo for File {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        FileExt::read_at(self, buf, offset)
+    }
+
+    fn append(&self, buf: &[u8]) -> io::Result<()> {
+        (&*self).write_all(buf)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+
+    fn set_len(&self, size: u64) -> io::Result<()> {
+        File::set_len(self, size)
+    }
+
+    fn sync_data(&self) -> io::Result<()> {
+        File::sync_data(self)
+    }
+}
+
+/// A file opened with O_DIRECT. Every transfer covers whole aligned blocks, so an append
+/// rewrites the last, partial block with the new bytes after it and cuts the file back to
+/// its real size.
+struct DirectFile {
+    file: File,
+    tail: Mutex<Tail>,
+}
+
+struct Tail {
+    size: u64,
+    /// The bytes of the last, partial block, as on disk.
+    block: Vec<u8>,
+}
+
+impl DirectFile {
+    #[cfg(target_os = "linux")]
+    fn open(path: &Path) -> io::Result<Self> {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        // Not O_APPEND: positioned writes would land at the end regardless
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_DIRECT)
+            .open(path)?;
+        let size = file.metadata()?.len();
+        let block = read_tail_block(&file, size)?;
+        Ok(Self {
+            file,
+            tail: Mutex::new(Tail { size, block }),
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn open(_path: &Path) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "O_DIRECT is only supported on Linux",
+        ))
+    }
+
+    fn tail(&self) -> std::sync::MutexGuard<'_, Tail> {
+        self.tail.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl BlockingIo for DirectFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let size = self.tail().size;
+        let end = (offset + buf.len() as u64).min(size);
+        if offset >= end {
+            return Ok(0);
+        }
+        let start = align_down(offset);
+        let mut aligned = AlignedBuf::new((align_up(end) - start) as usize);
+        let read = read_full_at(&self.file, aligned.as_mut(), start)?;
+        let available = (start + read as u64).min(end).saturating_sub(offset) as usize;
+        let from = (offset - start) as usize;
+        buf[..available].copy_from_slice(&aligned.as_ref()[from..from + available]);
+        Ok(available)
+    }
+
+    fn append(&self, buf: &[u8]) -> io::Result<()> {
+        let mut tail = self.tail();
+        let block_start = tail.size - tail.block.len() as u64;
+        let len = tail.block.len() + buf.len();
+        let mut aligned = AlignedBuf::new(align_up(len as u64) as usize);
+        aligned.as_mut()[..tail.block.len()].copy_from_slice(&tail.block);
+        aligned.as_mut()[tail.block.len()..len].copy_from_slice(buf);
+        self.file.write_all_at(aligned.as_ref(), block_start)?;
+
+        tail.size += buf.len() as u64;
+        if !len.is_multiple_of(DIRECT_IO_ALIGNMENT) {
+            self.file.set_len(tail.size)?;
+        }
+        tail.block = aligned.as_ref()[align_down(len as u64) as usize..len].to_vec();
+        Ok(())
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.tail().size)
+    }
+
+    fn set_len(&self, size: u64) -> io::Result<()> {
+        let mut tail = self.tail();
+        self.file.set_len(size)?;
+        tail.block = read_tail_block(&self.file, size)?;
+        tail.size = size;
+        Ok(())
+    }
+
+    fn sync_data(&self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+}
+
+fn align_down(position: u64) -> u64 {
+    position - position % DIRECT_IO_ALIGNMENT as u64
+}
+
+fn align_up(position: u64) -> u64 {
+    align_down(position + DIRECT_IO_ALIGNMENT as u64 - 1)
+}
+
+/// The bytes of the partial block at the end of a file of `size` bytes.
+fn read_tail_block(file: &File, size: u64) -> io::Result<Vec<u8>> {
+    let start = align_down(size);
+    let len = (size - start) as usize;
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    let mut aligned = AlignedBuf::new(DIRECT_IO_ALIGNMENT);
+    if read_full_at(file, aligned.as_mut(), start)? < len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "File shorter than its size",
+        ));
+    }
+    Ok(aligned.as_ref()[..len].to_vec())
+}
+
+/// Reads until `buf` is full or the file ends.
+fn read_full_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match FileExt::read_at(file, &mut buf[read..], offset + read as u64) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(read)
+}
+
+/// Zeroed memory starting on a `DIRECT_IO_ALIGNMENT` boundary.
+struct AlignedBuf {
+    bytes: Vec<u8>,
+    start: usize,
+    len: usize,
+}
+
+impl AlignedBuf {
+    fn new(len: usize) -> Self {
+        let bytes = vec![0; len + DIRECT_IO_ALIGNMENT];
+        let start = bytes.as_ptr().align_offset(DIRECT_IO_ALIGNMENT);
+        Self { bytes, start, len }
+    }
+
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes[self.start..self.start + self.len]
+    }
+
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes[self.start..self.start + self.len]
+    }
+}
+
+enum Done {
+    Read(Vec<u8>, io::Result<usize>),
+    Write(Vec<u8>, io::Result<()>),
+    Seek(io::Result<u64>),
+}
+
+/// Runs a `BlockingIo` file one call at a time on the blocking pool. Like tokio::fs::File, a
+/// write is accepted once its bytes are copied, and an error writing them comes back from the
+/// next call.
+struct BlockingFile<F> {
+    file: Arc<F>,
+    state: tokio::sync::Mutex<State>,
+}
+
+struct State {
+    operation: Option<JoinHandle<Done>>,
+    /// Reused by reads and writes.
+    buf: Vec<u8>,
+    position: u64,
+    seek: Option<SeekFrom>,
+    write_error: Option<io::Error>,
+}
+
+impl<F: BlockingIo> BlockingFile<F> {
+    fn new(file: F) -> Self {
+        Self {
+            file: Arc::new(file),
+            state: tokio::sync::Mutex::new(State {
+                operation: None,
+                buf: Vec::new(),
+                position: 0,
+                seek: None,
+                write_error: None,
+            }),
+        }
+    }
+
+    /// Waits out the call in flight, then runs `f`.
+    async fn call<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&F) -> io::Result<T> + Send + 'static,
+    ) -> io::Result<T> {
+        let mut state = self.state.lock().await;
+        std::future::poll_fn(|cx| state.poll_flush(cx)).await?;
+        let file = self.file.clone();
+        blocking(move || f(&file)).await
+    }
+}
+
+impl State {
+    fn poll_done(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Done>> {
+        let Some(operation) = self.operation.as_mut() else {
+            return Poll::Ready(Err(io::Error::other("No file operation in flight")));
+        };
+        let done = ready!(Pin::new(operation).poll(cx));
+        self.operation = None;
+        Poll::Ready(done.map_err(io::Error::other))
+    }
+
+    /// Keeps the buffer and any write error of a call nobody waits on anymore.
+    fn settle(&mut self, done: Done) {
+        match done {
+            Done::Read(buf, _) => self.buf = buf,
+            Done::Write(buf, result) => {
+                self.buf = buf;
+                if let Err(e) = result {
+                    self.write_error = Some(e);
+                }
+            }
+            Done::Seek(_) => {}
+        }
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.operation.is_some() {
+            let done = ready!(self.poll_done(cx))?;
+            self.settle(done);
+        }
+        Poll::Ready(self.write_error.take().map_or(Ok(()), Err))
+    }
+}
+
+impl<F: BlockingIo> AsyncRead for BlockingFile<F> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        out: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let state = this.state.get_mut();
+        loop {
+            if state.operation.is_some() {
+                match ready!(state.poll_done(cx))? {
+                    // A read at this position whose caller went away serves this one
+                    Done::Read(buf, result) => {
+                        let read = result.map(|read| read.min(out.remaining()));
+                        if let Ok(read) = read {
+                            out.put_slice(&buf[..read]);
+                            state.position += read as u64;
+                        }
+                        state.buf = buf;
+                        return Poll::Ready(read.map(drop));
+                    }
+                    done => state.settle(done),
+                }
+            }
+            if let Some(e) = state.write_error.take() {
+                return Poll::Ready(Err(e));
+            }
+            if out.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+
+            let mut buf = std::mem::take(&mut state.buf);
+            buf.resize(out.remaining().min(MAX_READ_SIZE), 0);
+            let file = this.file.clone();
+            let position = state.position;
+            state.operation = Some(spawn_blocking(move || {
+                let result = file.read_at(&mut buf, position);
+                Done::Read(buf, result)
+            }));
+        }
+    }
+}
+
+impl<F: BlockingIo> AsyncWrite for BlockingFile<F> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let state = this.state.get_mut();
+        ready!(state.poll_flush(cx))?;
+        if data.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let mut buf = std::mem::take(&mut state.buf);
+        buf.clear();
+        buf.extend_from_slice(data);
+        let file = this.file.clone();
+        state.operation = Some(spawn_blocking(move || {
+            let result = file.append(&buf);
+            Done::Write(buf, result)
+        }));
+        Poll::Ready(Ok(data.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().state.get_mut().poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl<F: BlockingIo> AsyncSeek for BlockingFile<F> {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        self.get_mut().state.get_mut().seek = Some(position);
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        let state = this.state.get_mut();
+        loop {
+            if state.operation.is_some() {
+                match ready!(state.poll_done(cx))? {
+                    Done::Seek(result) => {
+                        state.position = result?;
+                        return Poll::Ready(Ok(state.position));
+                    }
+                    done => state.settle(done),
+                }
+            }
+            let delta = match state.seek.take() {
+                None => return Poll::Ready(Ok(state.position)),
+                Some(SeekFrom::Start(position)) => {
+                    state.position = position;
+                    continue;
+                }
+                Some(SeekFrom::Current(delta)) => {
+                    state.position = offset_by(state.position, delta)?;
+                    continue;
+                }
+                Some(SeekFrom::End(delta)) => delta,
+            };
+            let file = this.file.clone();
+            state.operation = Some(spawn_blocking(move || {
+                Done::Seek(file.size().and_then(|size| offset_by(size, delta)))
+            }));
+        }
+    }
+}
+
+fn offset_by(position: u64, delta: i64) -> io::Result<u64> {
+    position.checked_add_signed(delta).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Seek to a negative or overflowing position",
+        )
+    })
+}
+
+#[async_trait]
+impl<F: BlockingIo> SegmentFile for BlockingFile<F> {
+    async fn size(&self) -> io::Result<u64> {
+        self.call(|file| file.size()).await
+    }
+
+    async fn set_len(&self, size: u64) -> io::Result<()> {
+        self.call(move |file| file.set_len(size)).await
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        self.call(|file| file.sync_data()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::driven::storage::log::PartitionLog;
+    use crate::core::domain::record::Record;
+    use crate::core::domain::record_batch::{RecordBatch, Records};
+
+    async fn append_read_truncate_and_reopen(fs: BlockingFileSystem) {
+        let dir = std::env::temp_dir().join(format!("forge-blocking-fs-{}", uuid::Uuid::new_v4()));
+        let fs: Arc<dyn FileSystem> = Arc::new(fs);
+        let mut log = PartitionLog::create(fs.clone(), &dir, 1024 * 1024, 0, 0)
+            .await
+            .unwrap();
+        for offset in 0..20 {
+            // Sizes that straddle block boundaries
+            let value = vec![b'a' + offset as u8; 100 + 397 * offset];
+            let mut batch = RecordBatch::new(0, vec![Record::new(0, None, Some(value))]);
+            batch.base_offset = offset as i64;
+            log.append(&batch).await.unwrap();
+        }
+        log.truncate_from_index(15).await.unwrap();
+        log.flush().await.unwrap();
+        drop(log);
+
+        let mut log = PartitionLog::open(fs, &dir, 1024 * 1024, 0, 0, false)
+            .await
+            .unwrap();
+        assert_eq!(log.get_last_log_index(), 14);
+        let batches = Records::from_raw(
+            log.read_sequential_raw(3, usize::MAX, i64::MAX)
+                .await
+                .unwrap(),
+        )
+        .batches()
+        .unwrap();
+        assert_eq!(batches.len(), 12);
+        assert_eq!(
+            batches[4].records[0].value.as_deref(),
+            Some(&vec![b'a' + 7; 100 + 397 * 7][..])
+        );
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_blocking_file_system_round_trip() {
+        append_read_truncate_and_reopen(BlockingFileSystem::new()).await;
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_direct_file_system_round_trip() {
+        append_read_truncate_and_reopen(BlockingFileSystem::direct()).await;
+    }
+}
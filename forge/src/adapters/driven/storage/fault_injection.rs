@@ -0,0 +1,577 @@
+use crate::adapters::driven::storage::file_system::{FileSystem, SegmentFile};
+use crate::adapters::driven::storage::log::PartitionLog;
+use crate::core::domain::record::Record;
+use crate::core::domain::record_batch::RecordBatch;
+use async_trait::async_trait;
+use rand::rngs::StdRng;
+use rand::{RngExt, SeedableRng};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{self, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::SystemTime;
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+
+/// What goes wrong while `SimulatedFileSystem` writes. Each chance is drawn from the seeded
+/// generator, so a seed replays the same failures.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Faults {
+    /// Chance that a write stores a random prefix of its bytes and then fails.
+    pub torn_write: f64,
+    /// Chance that an fsync fails, leaving everything since the last good one unsynced.
+    pub fsync_failure: f64,
+    /// Chance that a read fails, leaving the file as it is.
+    pub read_failure: f64,
+    /// Bytes the disk holds; a write past them stores what fits, then fails with ENOSPC.
+    pub capacity: Option<u64>,
+}
+
+struct Inode {
+    data: Vec<u8>,
+    /// How much of `data` an fsync has made durable.
+    synced: usize,
+    modified: SystemTime,
+}
+
+struct Disk {
+    rng: StdRng,
+    faults: Faults,
+    dirs: BTreeSet<PathBuf>,
+    files: BTreeMap<PathBuf, u64>,
+    /// Unlinked inodes stay until the next crash, for handles that still have them open.
+    inodes: HashMap<u64, Inode>,
+    next_inode: u64,
+}
+
+impl Disk {
+    fn used(&self) -> u64 {
+        self.inodes
+            .values()
+            .map(|inode| inode.data.len() as u64)
+            .sum()
+    }
+
+    fn inode(&mut self, path: &Path) -> io::Result<&mut Inode> {
+        let id = self.files.get(path).ok_or_else(|| not_found(path))?;
+        Ok(self.inodes.get_mut(id).expect("Linked inodes exist"))
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} does not exist", path.display()),
+    )
+}
+
+/// An in-memory disk that tears writes, fails fsyncs and runs out of space on cue, and that
+/// can crash: `crash` keeps each file's synced bytes plus a random part of the rest.
+/// Directory changes are durable as soon as they return, as on a filesystem mounted with
+/// `dirsync`.
+#[derive(Clone)]
+pub struct SimulatedFileSystem {
+    disk: Arc<Mutex<Disk>>,
+}
+
+impl SimulatedFileSystem {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            disk: Arc::new(Mutex::new(Disk {
+                rng: StdRng::seed_from_u64(seed),
+                faults: Faults::default(),
+                dirs: BTreeSet::from([PathBuf::from("/")]),
+                files: BTreeMap::new(),
+                inodes: HashMap::new(),
+                next_inode: 0,
+            })),
+        }
+    }
+
+    pub fn set_faults(&self, faults: Faults) {
+        self.disk.lock().unwrap().faults = faults;
+    }
+
+    /// Loses power. Unsynced bytes survive only in part, and independently per file, so a
+    /// later write to one file can outlive an earlier one to another; within what survives,
+    /// a range may read back as zeros, as when pages reach the disk out of order. Files must
+    /// be reopened afterwards.
+    pub fn crash(&self) {
+        let mut disk = self.disk.lock().unwrap();
+        let Disk {
+            rng, files, inodes, ..
+        } = &mut *disk;
+        let linked: BTreeSet<u64> = files.values().copied().collect();
+        inodes.retain(|id, _| linked.contains(id));
+
+        for id in files.values() {
+            let inode = inodes.get_mut(id).expect("Linked inodes exist");
+            let unsynced = inode.data.len() - inode.synced;
+            let kept = rng.random_range(0..=unsynced);
+            inode.data.truncate(inode.synced + kept);
+            if kept > 0 && rng.random_bool(0.5) {
+                let start = inode.synced + rng.random_range(0..kept);
+                let end = rng.random_range(start..=inode.synced + kept);
+                inode.data[start..end].fill(0);
+            }
+            inode.synced = inode.data.len();
+        }
+    }
+}
+
+#[async_trait]
+impl FileSystem for SimulatedFileSystem {
+    async fn open_append(&self, path: &Path) -> io::Result<Box<dyn SegmentFile>> {
+        let mut disk = self.disk.lock().unwrap();
+        let parent = path.parent().unwrap_or(Path::new("/"));
+        if !disk.dirs.contains(parent) {
+            return Err(not_found(parent));
+        }
+        let id = match disk.files.get(path) {
+            Some(id) => *id,
+            None => {
+                let id = disk.next_inode;
+                disk.next_inode += 1;
+                disk.inodes.insert(
+                    id,
+                    Inode {
+                        data: Vec::new(),
+                        synced: 0,
+                        modified: SystemTime::now(),
+                    },
+                );
+                disk.files.insert(path.to_path_buf(), id);
+                id
+            }
+        };
+        Ok(Box::new(SimulatedFile {
+            disk: self.disk.clone(),
+            inode: id,
+            position: 0,
+        }))
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut disk = self.disk.lock().unwrap();
+        disk.dirs.extend(path.ancestors().map(Path::to_path_buf));
+        Ok(())
+    }
+
+    async fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let disk = self.disk.lock().unwrap();
+        if !disk.dirs.contains(path) {
+            return Err(not_found(path));
+        }
+        let files = disk.files.keys();
+        let dirs = disk.dirs.iter();
+        Ok(files
+            .chain(dirs)
+            .filter(|entry| entry.parent() == Some(path))
+            .cloned()
+            .collect())
+    }
+
+    async fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let mut disk = self.disk.lock().unwrap();
+        disk.files
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| not_found(path))
+    }
+
+    async fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut disk = self.disk.lock().unwrap();
+        if !disk.dirs.contains(path) {
+            return Err(not_found(path));
+        }
+        disk.dirs.retain(|dir| !dir.starts_with(path));
+        disk.files.retain(|file, _| !file.starts_with(path));
+        Ok(())
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut disk = self.disk.lock().unwrap();
+        let id = disk.files.remove(from).ok_or_else(|| not_found(from))?;
+        disk.files.insert(to.to_path_buf(), id);
+        Ok(())
+    }
+
+    async fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        let mut disk = self.disk.lock().unwrap();
+        Ok(disk.inode(path)?.modified)
+    }
+
+    /// Directory changes are durable already.
+    async fn sync_dir(&self, path: &Path) -> io::Result<()> {
+        let disk = self.disk.lock().unwrap();
+        if !disk.dirs.contains(path) {
+            return Err(not_found(path));
+        }
+        Ok(())
+    }
+}
+
+struct SimulatedFile {
+    disk: Arc<Mutex<Disk>>,
+    inode: u64,
+    position: u64,
+}
+
+impl SimulatedFile {
+    fn with_inode<T>(&self, f: impl FnOnce(&mut Inode) -> T) -> io::Result<T> {
+        let mut disk = self.disk.lock().unwrap();
+        match disk.inodes.get_mut(&self.inode) {
+            Some(inode) => Ok(f(inode)),
+            None => Err(io::Error::other("File was lost in a crash")),
+        }
+    }
+}
+
+impl AsyncRead for SimulatedFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let position = self.position as usize;
+        let mut disk = self.disk.lock().unwrap();
+        let read_failure = disk.faults.read_failure;
+        let failed = disk.rng.random_bool(read_failure);
+        let Some(inode) = disk.inodes.get(&self.inode) else {
+            return Poll::Ready(Err(io::Error::other("File was lost in a crash")));
+        };
+        if failed {
+            return Poll::Ready(Err(io::Error::other("Injected read failure")));
+        }
+        let available = inode.data.get(position..).unwrap_or_default();
+        let read = available.len().min(buf.remaining());
+        buf.put_slice(&available[..read]);
+        drop(disk);
+        self.position += read as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for SimulatedFile {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut disk = self.disk.lock().unwrap();
+        let faults = disk.faults;
+        let free = faults.capacity.map_or(usize::MAX, |capacity| {
+            capacity.saturating_sub(disk.used()) as usize
+        });
+        let torn = disk.rng.random_bool(faults.torn_write);
+        let torn_at = disk.rng.random_range(0..=buf.len());
+        let Some(inode) = disk.inodes.get_mut(&self.inode) else {
+            return Poll::Ready(Err(io::Error::other("File was lost in a crash")));
+        };
+        inode.modified = SystemTime::now();
+
+        if free == 0 && !buf.is_empty() {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::StorageFull,
+                "Injected ENOSPC",
+            )));
+        }
+        if torn {
+            inode.data.extend_from_slice(&buf[..torn_at.min(free)]);
+            return Poll::Ready(Err(io::Error::other("Injected torn write")));
+        }
+        let written = buf.len().min(free);
+        inode.data.extend_from_slice(&buf[..written]);
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for SimulatedFile {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let size = self.with_inode(|inode| inode.data.len() as i64)?;
+        let position = match position {
+            SeekFrom::Start(position) => position as i64,
+            SeekFrom::End(delta) => size + delta,
+            SeekFrom::Current(delta) => self.position as i64 + delta,
+        };
+        if position < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Seek before the start of the file",
+            ));
+        }
+        self.position = position as u64;
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.position))
+    }
+}
+
+#[async_trait]
+impl SegmentFile for SimulatedFile {
+    async fn size(&self) -> io::Result<u64> {
+        self.with_inode(|inode| inode.data.len() as u64)
+    }
+
+    async fn set_len(&self, size: u64) -> io::Result<()> {
+        self.with_inode(|inode| {
+            inode.data.resize(size as usize, 0);
+            inode.synced = inode.synced.min(inode.data.len());
+        })
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        let mut disk = self.disk.lock().unwrap();
+        let fsync_failure = disk.faults.fsync_failure;
+        let failed = disk.rng.random_bool(fsync_failure);
+        let inode = disk
+            .inodes
+            .get_mut(&self.inode)
+            .ok_or_else(|| io::Error::other("File was lost in a crash"))?;
+        if failed {
+            return Err(io::Error::other("Injected fsync failure"));
+        }
+        inode.synced = inode.data.len();
+        Ok(())
+    }
+}
+
+/// Drives a partition log on a `SimulatedFileSystem` through rounds of appends and flushes
+/// under `faults`, which go on past failed writes and fsyncs, each round ending in a crash
+/// and recovery with `PartitionLog::open`. Everything is drawn from `seed`, so a failing seed
+/// replays exactly.
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub seed: u64,
+    pub rounds: usize,
+    pub appends_per_round: usize,
+    pub segment_bytes: u32,
+    pub faults: Faults,
+}
+
+/// What a scenario did, for checking that its faults actually fired.
+#[derive(Debug, Default)]
+pub struct ScenarioReport {
+    pub appended_batches: usize,
+    pub failed_writes: usize,
+    pub failed_flushes: usize,
+    /// Batches appended after a write or fsync of the same round had failed.
+    pub appended_after_failure: usize,
+    /// Recoveries that found less than had been appended.
+    pub lossy_recoveries: usize,
+}
+
+const SCENARIO_DIR: &str = "/data/topic-0";
+
+impl Scenario {
+    /// Fails with the seed and the broken guarantee when recovery loses a flushed batch,
+    /// returns a batch that differs from what was appended at its offset, or leaves batches
+    /// out of order or unreachable through the index.
+    pub async fn run(&self) -> Result<ScenarioReport, String> {
+        let fail = |message: String| format!("Seed {}: {}", self.seed, message);
+        let fs = SimulatedFileSystem::new(self.seed);
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut report = ScenarioReport::default();
+        // Base offset -> values of every batch the log may still hold
+        let mut written: BTreeMap<i64, Vec<Vec<u8>>> = BTreeMap::new();
+        let mut flushed_through = -1;
+
+        for _ in 0..self.rounds {
+            let mut log = self.open(&fs).await.map_err(fail)?;
+            fs.set_faults(self.faults);
+            let mut failed = false;
+
+            for _ in 0..self.appends_per_round {
+                if rng.random_bool(0.2) {
+                    if log.flush().await.is_err() {
+                        report.failed_flushes += 1;
+                        failed = true;
+                    } else {
+                        flushed_through = log.get_last_log_index();
+                    }
+                    continue;
+                }
+
+                let base_offset = log.get_last_log_index() + 1;
+                let values: Vec<Vec<u8>> = (0..rng.random_range(1..5))
+                    .map(|_| {
+                        let value = rng.random_range(0..=u8::MAX);
+                        vec![value; rng.random_range(0..64)]
+                    })
+                    .collect();
+                let records = values
+                    .iter()
+                    .enumerate()
+                    .map(|(delta, value)| Record::new(delta as i32, None, Some(value.clone())))
+                    .collect();
+                let mut batch = RecordBatch::new(base_offset, records);
+                batch.base_offset = base_offset;
+                // Replaces a batch whose append failed at this offset and was cut back
+                written.insert(base_offset, values);
+                if log.append(&batch).await.is_err() {
+                    report.failed_writes += 1;
+                    failed = true;
+                    continue;
+                }
+                report.appended_batches += 1;
+                if failed {
+                    report.appended_after_failure += 1;
+                }
+            }
+
+            let appended_through = log.get_last_log_index();
+            drop(log);
+            fs.set_faults(Faults::default());
+            fs.crash();
+
+            let mut log = self.open(&fs).await.map_err(fail)?;
+            let recovered = Self::read_all(&mut log).await.map_err(fail)?;
+            if log.get_last_log_index() < appended_through {
+                report.lossy_recoveries += 1;
+            }
+
+            for (base_offset, values) in &recovered {
+                if written.get(base_offset) != Some(values) {
+                    return Err(fail(format!(
+                        "Recovered a batch at {} that was never appended there",
+                        base_offset
+                    )));
+                }
+                let indexed = log.read(*base_offset).await.map_err(fail)?;
+                if indexed.map(|batch| batch.base_offset) != Some(*base_offset) {
+                    return Err(fail(format!("The index lost the batch at {}", base_offset)));
+                }
+            }
+            for (base_offset, values) in &written {
+                let last_offset = base_offset + values.len() as i64 - 1;
+                if last_offset <= flushed_through && !recovered.contains_key(base_offset) {
+                    return Err(fail(format!(
+                        "Lost the batch at {} though it was flushed",
+                        base_offset
+                    )));
+                }
+            }
+            written = recovered;
+        }
+        Ok(report)
+    }
+
+    async fn open(&self, fs: &SimulatedFileSystem) -> Result<PartitionLog, String> {
+        PartitionLog::open(
+            Arc::new(fs.clone()),
+            SCENARIO_DIR,
+            self.segment_bytes,
+            0,
+            0,
+            false,
+        )
+        .await
+    }
+
+    async fn read_all(log: &mut PartitionLog) -> Result<BTreeMap<i64, Vec<Vec<u8>>>, String> {
+        let mut batches = BTreeMap::new();
+        let mut previous_last_offset = -1;
+        for index in 0..log.segments.len() {
+            let segment = &mut log.segments[index];
+            let base_offset = segment.base_offset;
+            for batch in segment.read_sequential(base_offset, usize::MAX).await? {
+                if batch.base_offset <= previous_last_offset {
+                    return Err(format!(
+                        "Batch at {} follows one ending at {}",
+                        batch.base_offset, previous_last_offset
+                    ));
+                }
+                previous_last_offset = batch.last_offset();
+                let values = batch
+                    .records
+                    .into_iter()
+                    .map(|record| record.value.unwrap_or_default())
+                    .collect();
+                batches.insert(batch.base_offset, values);
+            }
+        }
+        Ok(batches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_recovery_keeps_flushed_batches_under_faults() {
+        let faults = [
+            Faults::default(),
+            Faults {
+                torn_write: 0.05,
+                ..Faults::default()
+            },
+            Faults {
+                fsync_failure: 0.2,
+                ..Faults::default()
+            },
+            Faults {
+                capacity: Some(8 * 1024),
+                ..Faults::default()
+            },
+        ];
+        let mut report = ScenarioReport::default();
+        for seed in 0..40 {
+            let scenario = Scenario {
+                seed,
+                rounds: 5,
+                appends_per_round: 30,
+                segment_bytes: 1024,
+                faults: faults[seed as usize % faults.len()],
+            };
+            let run = scenario.run().await.unwrap();
+            report.appended_batches += run.appended_batches;
+            report.failed_writes += run.failed_writes;
+            report.failed_flushes += run.failed_flushes;
+            report.appended_after_failure += run.appended_after_failure;
+            report.lossy_recoveries += run.lossy_recoveries;
+        }
+        assert!(report.appended_batches > 0);
+        assert!(report.failed_writes > 0);
+        assert!(report.failed_flushes > 0);
+        assert!(report.appended_after_failure > 0);
+        assert!(report.lossy_recoveries > 0);
+    }
+
+    #[tokio::test]
+    async fn test_rolled_segments_survive_a_crash_without_a_flush() {
+        for seed in 0..10 {
+            let fs = SimulatedFileSystem::new(seed);
+            let mut log = PartitionLog::open(Arc::new(fs.clone()), SCENARIO_DIR, 1024, 0, 0, false)
+                .await
+                .unwrap();
+            let mut offset = 0;
+            while log.segments.len() < 3 {
+                let record = Record::new(0, None, Some(vec![b'x'; 200]));
+                let mut batch = RecordBatch::new(0, vec![record]);
+                batch.base_offset = offset;
+                log.append(&batch).await.unwrap();
+                offset += 1;
+            }
+            let rolled = log.segments[2].base_offset - 1;
+            drop(log);
+
+            fs.crash();
+            let log = PartitionLog::open(Arc::new(fs.clone()), SCENARIO_DIR, 1024, 0, 0, false)
+                .await
+                .unwrap();
+            assert!(log.get_last_log_index() >= rolled, "seed {}", seed);
+        }
+    }
+}
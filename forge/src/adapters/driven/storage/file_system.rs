@@ -0,0 +1,111 @@
+use async_trait::async_trait;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite};
+
+use crate::adapters::driven::storage::blocking_file_system::BlockingFileSystem;
+use crate::config::IoExecutor;
+
+/// A log or index file of a segment. It is opened for appending: writes land at the end of
+/// the file whatever the read position.
+#[async_trait]
+pub trait SegmentFile: AsyncRead + AsyncWrite + AsyncSeek + Send + Sync + Unpin {
+    async fn size(&self) -> io::Result<u64>;
+    async fn set_len(&self, size: u64) -> io::Result<()>;
+    async fn sync_data(&self) -> io::Result<()>;
+}
+
+/// Where segments and partition logs keep their files, so tests can swap the disk for one
+/// that fails on cue.
+#[async_trait]
+pub trait FileSystem: Send + Sync {
+    /// Opens `path` for reading and appending, creating it if missing.
+    async fn open_append(&self, path: &Path) -> io::Result<Box<dyn SegmentFile>>;
+    async fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+    /// The paths of the entries directly inside `path`.
+    async fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+    async fn remove_file(&self, path: &Path) -> io::Result<()>;
+    async fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+    async fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    async fn modified(&self, path: &Path) -> io::Result<SystemTime>;
+    /// Fsyncs the directory `path`, so files created, renamed or removed in it stay that way
+    /// across a power loss.
+    async fn sync_dir(&self, path: &Path) -> io::Result<()>;
+}
+
+/// The file system `log.io.executor` picks.
+pub fn for_executor(executor: IoExecutor) -> Arc<dyn FileSystem> {
+    match executor {
+        IoExecutor::Tokio => Arc::new(TokioFileSystem),
+        IoExecutor::Blocking => Arc::new(BlockingFileSystem::new()),
+        IoExecutor::Direct => Arc::new(BlockingFileSystem::direct()),
+    }
+}
+
+/// The real disk, through tokio::fs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioFileSystem;
+
+#[async_trait]
+impl SegmentFile for File {
+    async fn size(&self) -> io::Result<u64> {
+        Ok(self.metadata().await?.len())
+    }
+
+    async fn set_len(&self, size: u64) -> io::Result<()> {
+        File::set_len(self, size).await
+    }
+
+    async fn sync_data(&self) -> io::Result<()> {
+        File::sync_data(self).await
+    }
+}
+
+#[async_trait]
+impl FileSystem for TokioFileSystem {
+    async fn open_append(&self, path: &Path) -> io::Result<Box<dyn SegmentFile>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .await?;
+        Ok(Box::new(file))
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        tokio::fs::create_dir_all(path).await
+    }
+
+    async fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut entries = tokio::fs::read_dir(path).await?;
+        let mut paths = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            paths.push(entry.path());
+        }
+        Ok(paths)
+    }
+
+    async fn remove_file(&self, path: &Path) -> io::Result<()> {
+        tokio::fs::remove_file(path).await
+    }
+
+    async fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        tokio::fs::remove_dir_all(path).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        tokio::fs::rename(from, to).await
+    }
+
+    async fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        tokio::fs::metadata(path).await?.modified()
+    }
+
+    async fn sync_dir(&self, path: &Path) -> io::Result<()> {
+        File::open(path).await?.sync_all().await
+    }
+}
@@ -1,14 +1,23 @@
+use crate::adapters::driven::storage::file_system::{FileSystem, TokioFileSystem};
 use crate::core::domain::record_batch::RecordBatch;
 use crate::shared::constants::{INDEX_EXTENSION, LOG_EXTENSION, TIMEINDEX_EXTENSION};
 use crate::{adapters::driven::storage::segment::Segment, shared::fs::segment_file_path};
+use bytes::Bytes;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 
 pub struct PartitionLog {
     pub dir: PathBuf,
+    pub fs: Arc<dyn FileSystem>,
     pub max_segment_size: u32,
     pub segments: Vec<Segment>,
     pub retention_bytes: u64,
     pub retention_ms: u64,
+    flush_interval_messages: Option<u64>,
+    flush_interval_ms: Option<u64>,
+    unflushed_messages: u64,
+    last_flush: Instant,
 }
 
 impl PartitionLog {
@@ -17,34 +26,213 @@ impl PartitionLog {
         max_segment_size: u32,
         retention_bytes: u64,
         retention_ms: u64,
+    ) -> std::io::Result<Self> {
+        Self::create(
+            Arc::new(TokioFileSystem),
+            dir,
+            max_segment_size,
+            retention_bytes,
+            retention_ms,
+        )
+        .await
+    }
+
+    pub async fn create(
+        fs: Arc<dyn FileSystem>,
+        dir: impl AsRef<Path>,
+        max_segment_size: u32,
+        retention_bytes: u64,
+        retention_ms: u64,
     ) -> std::io::Result<Self> {
         let dir_path = PathBuf::from(dir.as_ref());
-        tokio::fs::create_dir_all(&dir_path).await?;
+        fs.create_dir_all(&dir_path).await?;
+        // The partition directory is an entry of its parent
+        if let Some(parent) = dir_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs.sync_dir(parent).await?;
+        }
+
+        let initial_segment = Segment::create(fs.clone(), &dir_path, 0).await?;
+        Ok(Self::with_segments(
+            fs,
+            dir_path,
+            vec![initial_segment],
+            max_segment_size,
+            retention_bytes,
+            retention_ms,
+        ))
+    }
+
+    /// Loads every segment in `dir`, recovering each as `Segment::open` does, or, after a
+    /// `clean_shutdown`, as `Segment::open_clean` does. Once a segment has been cut, the ones
+    /// after it are deleted, since their offsets no longer follow.
+    pub async fn open(
+        fs: Arc<dyn FileSystem>,
+        dir: impl AsRef<Path>,
+        max_segment_size: u32,
+        retention_bytes: u64,
+        retention_ms: u64,
+        clean_shutdown: bool,
+    ) -> Result<Self, String> {
+        let dir_path = PathBuf::from(dir.as_ref());
+        fs.create_dir_all(&dir_path)
+            .await
+            .map_err(|e| format!("Failed to create {}: {}", dir_path.display(), e))?;
+        let mut base_offsets: Vec<i64> = fs
+            .read_dir(&dir_path)
+            .await
+            .map_err(|e| format!("Failed to list {}: {}", dir_path.display(), e))?
+            .iter()
+            .filter(|path| path.extension().is_some_and(|ext| ext == LOG_EXTENSION))
+            .filter_map(|path| path.file_stem()?.to_str()?.parse().ok())
+            .collect();
+        base_offsets.sort_unstable();
+
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut truncated = false;
+        let mut deleted = false;
+        for base_offset in base_offsets {
+            let follows = segments
+                .last()
+                .is_none_or(|last| base_offset > last.last_offset);
+            if truncated || !follows {
+                for extension in [LOG_EXTENSION, INDEX_EXTENSION, TIMEINDEX_EXTENSION] {
+                    let path = segment_file_path(&dir_path, base_offset, extension);
+                    let _ = fs.remove_file(&path).await;
+                }
+                tracing::warn!("Deleted segment {} of {}", base_offset, dir_path.display());
+                deleted = true;
+                continue;
+            }
+
+            let (segment, cut) = if clean_shutdown {
+                Segment::open_clean(fs.clone(), &dir_path, base_offset).await?
+            } else {
+                Segment::open(fs.clone(), &dir_path, base_offset).await?
+            };
+            if cut {
+                tracing::warn!(
+                    "Truncated segment {} of {} after offset {}",
+                    base_offset,
+                    dir_path.display(),
+                    segment.last_offset
+                );
+            }
+            truncated = cut;
+            segments.push(segment);
+        }
+        if deleted {
+            fs.sync_dir(&dir_path)
+                .await
+                .map_err(|e| format!("Failed to sync {}: {}", dir_path.display(), e))?;
+        }
+        if segments.is_empty() {
+            let segment = Segment::create(fs.clone(), &dir_path, 0)
+                .await
+                .map_err(|e| e.to_string())?;
+            segments.push(segment);
+        }
 
-        let initial_segment = Segment::new(&dir_path, 0).await?;
+        Ok(Self::with_segments(
+            fs,
+            dir_path,
+            segments,
+            max_segment_size,
+            retention_bytes,
+            retention_ms,
+        ))
+    }
 
-        Ok(Self {
-            dir: dir_path,
+    fn with_segments(
+        fs: Arc<dyn FileSystem>,
+        dir: PathBuf,
+        segments: Vec<Segment>,
+        max_segment_size: u32,
+        retention_bytes: u64,
+        retention_ms: u64,
+    ) -> Self {
+        Self {
+            dir,
+            fs,
             max_segment_size,
-            segments: vec![initial_segment],
+            segments,
             retention_bytes,
             retention_ms,
-        })
+            flush_interval_messages: None,
+            flush_interval_ms: None,
+            unflushed_messages: 0,
+            last_flush: Instant::now(),
+        }
+    }
+
+    /// Fsyncs the active segment every `interval_messages` appended messages, or on the first
+    /// append `interval_ms` after the last flush. With neither, flushing is left to the OS.
+    pub fn set_flush_policy(&mut self, interval_messages: Option<u64>, interval_ms: Option<u64>) {
+        self.flush_interval_messages = interval_messages;
+        self.flush_interval_ms = interval_ms;
+    }
+
+    fn flush_due(&self) -> bool {
+        self.flush_interval_messages
+            .is_some_and(|interval| self.unflushed_messages >= interval)
+            || self
+                .flush_interval_ms
+                .is_some_and(|interval| self.last_flush.elapsed().as_millis() as u64 >= interval)
+    }
+
+    /// Fsyncs every segment, rolled ones included: without a flush policy, nothing else has.
+    pub async fn flush(&mut self) -> Result<(), String> {
+        for segment in &mut self.segments {
+            segment
+                .flush()
+                .await
+                .map_err(|e| format!("Failed to flush {}: {}", self.dir.display(), e))?;
+        }
+        self.unflushed_messages = 0;
+        self.last_flush = Instant::now();
+        Ok(())
     }
 
     pub async fn append(&mut self, batch: &RecordBatch) -> Result<(), String> {
+        self.append_batches(std::slice::from_ref(batch))
+            .await
+            .map(drop)
+    }
+
+    /// Appends `batches`, which must follow each other, with one write to the active segment,
+    /// which rolls once they are in. Returns the bytes each took.
+    pub async fn append_batches(&mut self, batches: &[RecordBatch]) -> Result<Vec<u32>, String> {
+        let Some(last) = batches.last() else {
+            return Ok(Vec::new());
+        };
+        self.unflushed_messages += batches
+            .iter()
+            .map(|batch| batch.records_count.max(0) as u64)
+            .sum::<u64>();
+        let flush_due = self.flush_due();
+
         let active_segment = self.segments.last_mut().ok_or("No active segment found")?;
-        active_segment.append(batch).await?;
+        let sizes = active_segment.append_batches(batches).await?;
+
+        if flush_due {
+            active_segment.flush().await.map_err(|e| e.to_string())?;
+            self.unflushed_messages = 0;
+            self.last_flush = Instant::now();
+        }
 
         if active_segment.current_size >= self.max_segment_size {
-            let next_offset = batch.base_offset + batch.records_count as i64;
-            let new_segment = Segment::new(&self.dir, next_offset)
+            // A rolled segment takes no more appends, so nothing would flush it later
+            active_segment
+                .flush()
+                .await
+                .map_err(|e| format!("Failed to flush {}: {}", self.dir.display(), e))?;
+            let next_offset = last.base_offset + last.records_count as i64;
+            let new_segment = Segment::create(self.fs.clone(), &self.dir, next_offset)
                 .await
                 .map_err(|e| e.to_string())?;
             self.segments.push(new_segment);
         }
 
-        Ok(())
+        Ok(sizes)
     }
 
     fn find_segment_index(&self, offset: i64) -> Option<usize> {
@@ -77,18 +265,35 @@ impl PartitionLog {
         active_segment.read(offset).await
     }
 
-    pub async fn read_sequential(
+    pub async fn read_sequential_raw(
         &mut self,
         offset: i64,
         max_bytes: usize,
-    ) -> Result<Vec<RecordBatch>, String> {
+        max_offset: i64,
+    ) -> Result<Vec<Bytes>, String> {
         let segment_index = match self.find_segment_index(offset) {
             Some(index) => index,
             None => return Ok(vec![]),
         };
 
         let active_segment = &mut self.segments[segment_index];
-        active_segment.read_sequential(offset, max_bytes).await
+        active_segment
+            .read_sequential_raw(offset, max_bytes, max_offset)
+            .await
+    }
+
+    /// The first offset whose record timestamp is at or after `timestamp`, with that
+    /// timestamp, or `None` when every record is older.
+    pub async fn offset_for_timestamp(
+        &mut self,
+        timestamp: i64,
+    ) -> Result<Option<(i64, i64)>, String> {
+        for segment in &mut self.segments {
+            if let Some(found) = segment.offset_for_timestamp(timestamp).await? {
+                return Ok(Some(found));
+            }
+        }
+        Ok(None)
     }
 
     pub async fn remove_segment(&mut self, index: usize) -> Result<(), String> {
@@ -143,12 +348,8 @@ impl PartitionLog {
 
             let old_segment = &self.segments[0];
             let file_path = segment_file_path(&self.dir, old_segment.base_offset, LOG_EXTENSION);
-            let is_expired = match tokio::fs::metadata(&file_path).await {
-                Ok(metadata) => {
-                    let Ok(modified_time) = metadata.modified() else {
-                        return Err("Failed to get modified time".to_string());
-                    };
-
+            let is_expired = match self.fs.modified(&file_path).await {
+                Ok(modified_time) => {
                     let Ok(duration) = modified_time.elapsed() else {
                         return Err("Failed to get duration".to_string());
                     };
@@ -259,19 +460,20 @@ impl PartitionLog {
             for ext in extensions {
                 let temp_file = segment_file_path(&temp_dir, base_offset, ext);
                 let final_file = segment_file_path(&self.dir, base_offset, ext);
-                tokio::fs::rename(temp_file, final_file)
+                self.fs
+                    .rename(&temp_file, &final_file)
                     .await
                     .map_err(|e| e.to_string())?;
             }
 
-            let new_seg = Segment::new(&self.dir, base_offset)
+            let new_seg = Segment::create(self.fs.clone(), &self.dir, base_offset)
                 .await
                 .map_err(|e| e.to_string())?;
             new_segments.push(new_seg);
         }
 
-        if temp_dir.exists() {
-            let _ = tokio::fs::remove_dir_all(&temp_dir).await;
+        if !temp_dir.as_os_str().is_empty() {
+            let _ = self.fs.remove_dir_all(&temp_dir).await;
         }
 
         self.segments.splice(0..0, new_segments);
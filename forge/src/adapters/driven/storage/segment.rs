@@ -1,18 +1,25 @@
 use crate::{
+    adapters::driven::storage::file_system::{FileSystem, SegmentFile, TokioFileSystem},
     core::domain::record_batch::{BATCH_HEADER_SIZE, BATCH_LENGTH_OFFSET, RecordBatch},
     protocol::types::Type,
-    shared::constants::{INDEX_EXTENSION, LOG_EXTENSION, TIMEINDEX_EXTENSION},
-    shared::fs::{delete_file, open_append_file, write_encoded_structure},
+    shared::constants::{INDEX_EXTENSION, LOG_EXTENSION, TIMEINDEX_EXTENSION, UPGRADE_EXTENSION},
+    shared::fs::{segment_file_path, write_to_file},
 };
-use bytes::{BufMut, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 use std::{
+    collections::VecDeque,
     io::SeekFrom,
     path::{Path, PathBuf},
+    sync::Arc,
 };
-use tokio::{
-    fs::File,
-    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
-};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// The least the log is read in at a time when looking for the next batch.
+const READ_AHEAD: usize = 64 * 1024;
+/// A larger write buffer, left by an unusually big batch, is freed rather than kept.
+const MAX_RETAINED_WRITE_BUFFER: usize = 1024 * 1024;
+/// Sequential readers of one segment whose positions are kept.
+const MAX_READ_POSITIONS: usize = 16;
 
 pub struct IndexEntry {
     pub relative_offset: i32,
@@ -74,81 +81,483 @@ impl TimeIndexEntry {
 pub struct Segment {
     pub base_offset: i64,
     pub dir: PathBuf,
-    pub log_file: File,
-    pub index_file: File,
-    pub timeindex_file: File,
+    pub fs: Arc<dyn FileSystem>,
+    pub log_file: Box<dyn SegmentFile>,
+    pub index_file: Box<dyn SegmentFile>,
+    pub timeindex_file: Box<dyn SegmentFile>,
     pub current_size: u32,
+    /// Bytes of the index and time index that hold whole entries, which a failed append
+    /// cuts them back to.
+    index_size: u64,
+    timeindex_size: u64,
     pub last_offset: i64,
     pub last_term: u64,
+    /// The timestamp of the last time index entry. A batch whose base timestamp is below it
+    /// gets no entry, so the entries stay sorted by timestamp for `offset_for_timestamp`.
+    last_indexed_timestamp: i64,
+    /// Log bytes read past the last batch returned, where the next one starts. Seeking the
+    /// log through `seek_log` drops them.
+    read_ahead: BytesMut,
+    /// Where `append` encodes batches, kept between appends.
+    write_buffer: BytesMut,
+    read_positions: ReadPositions,
+    /// Why appends are refused: an append failed and its bytes could not be cut from the
+    /// files. Reopening the segment recovers it.
+    broken: Option<String>,
+}
+
+/// Why the next batch could not be read.
+enum ReadError {
+    /// The file failed to read.
+    Io(String),
+    /// What the file holds there is not a whole batch with a valid checksum, as at a torn
+    /// tail.
+    Corrupt(String),
+}
+
+impl From<ReadError> for String {
+    fn from(error: ReadError) -> Self {
+        match error {
+            ReadError::Io(e) | ReadError::Corrupt(e) => e,
+        }
+    }
+}
+
+/// Where recent sequential reads stopped, by the offset each reader will ask for next, so a
+/// consumer fetching along the segment skips the index search. The least recently used
+/// position goes first once there are `MAX_READ_POSITIONS`.
+#[derive(Default)]
+struct ReadPositions(VecDeque<(i64, u32)>);
+
+impl ReadPositions {
+    /// Takes the position out; the read puts its own end back.
+    fn take(&mut self, offset: i64) -> Option<u32> {
+        let index = self.0.iter().position(|(next, _)| *next == offset)?;
+        self.0.remove(index).map(|(_, position)| position)
+    }
+
+    fn insert(&mut self, offset: i64, position: u32) {
+        self.0.retain(|(next, _)| *next != offset);
+        if self.0.len() == MAX_READ_POSITIONS {
+            self.0.pop_front();
+        }
+        self.0.push_back((offset, position));
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
 }
 
 impl Segment {
     pub async fn new(dir: impl AsRef<Path>, base_offset: i64) -> std::io::Result<Self> {
-        let log_file = open_append_file(&dir, base_offset, LOG_EXTENSION).await?;
-        let index_file = open_append_file(&dir, base_offset, INDEX_EXTENSION).await?;
-        let timeindex_file = open_append_file(&dir, base_offset, TIMEINDEX_EXTENSION).await?;
+        Self::create(Arc::new(TokioFileSystem), dir, base_offset).await
+    }
+
+    /// Opens the segment's files, creating any that are missing, and trusts what they hold.
+    /// Syncs the directory, so the files survive a power loss.
+    pub async fn create(
+        fs: Arc<dyn FileSystem>,
+        dir: impl AsRef<Path>,
+        base_offset: i64,
+    ) -> std::io::Result<Self> {
+        let segment = Self::open_files(fs, &dir, base_offset).await?;
+        // Files just created are not durable until their directory entries are
+        segment.fs.sync_dir(&segment.dir).await?;
+        Ok(segment)
+    }
+
+    async fn open_files(
+        fs: Arc<dyn FileSystem>,
+        dir: impl AsRef<Path>,
+        base_offset: i64,
+    ) -> std::io::Result<Self> {
+        let dir = dir.as_ref();
+        let log_file = fs
+            .open_append(&segment_file_path(dir, base_offset, LOG_EXTENSION))
+            .await?;
+        let index_file = fs
+            .open_append(&segment_file_path(dir, base_offset, INDEX_EXTENSION))
+            .await?;
+        let timeindex_file = fs
+            .open_append(&segment_file_path(dir, base_offset, TIMEINDEX_EXTENSION))
+            .await?;
 
-        let metadata = log_file.metadata().await?;
-        let current_size = metadata.len() as u32;
+        let current_size = log_file.size().await? as u32;
+        let index_size = index_file.size().await?;
+        let timeindex_size = timeindex_file.size().await?;
 
         Ok(Self {
             base_offset,
-            dir: PathBuf::from(dir.as_ref()),
+            dir: PathBuf::from(dir),
+            fs,
             log_file,
             index_file,
             timeindex_file,
             current_size,
+            index_size,
+            timeindex_size,
             last_offset: base_offset - 1,
             last_term: 0,
+            last_indexed_timestamp: i64::MIN,
+            read_ahead: BytesMut::new(),
+            write_buffer: BytesMut::new(),
+            read_positions: ReadPositions::default(),
+            broken: None,
         })
     }
 
-    pub async fn append(&mut self, batch: &RecordBatch) -> Result<(), String> {
-        let mut buffer = BytesMut::new();
-        batch.encode(&mut buffer);
+    /// Opens a segment left by an earlier run, which may have crashed mid-write: the log is
+    /// cut at the first batch that is torn, fails its CRC or does not follow the one before
+    /// it, and both indexes are rewritten from the batches that remain. The flag tells
+    /// whether the log was cut. Batches with the CRC-32 checksums of older versions are
+    /// rewritten with CRC-32C rather than cut.
+    pub async fn open(
+        fs: Arc<dyn FileSystem>,
+        dir: impl AsRef<Path>,
+        base_offset: i64,
+    ) -> Result<(Self, bool), String> {
+        let mut segment = Self::open_files(fs, &dir, base_offset)
+            .await
+            .map_err(|e| format!("Failed to open segment {}: {}", base_offset, e))?;
+        let truncated = segment.recover().await?;
+        Ok((segment, truncated))
+    }
 
-        self.log_file
-            .write_all(&buffer)
+    /// Like `open`, for a segment the broker closed cleanly: the batch the last index entry
+    /// points at gives the last offset, and nothing else is read. Falls back to `open`'s scan
+    /// when that batch does not end the log or fails its CRC, as every batch an older version
+    /// wrote does.
+    pub async fn open_clean(
+        fs: Arc<dyn FileSystem>,
+        dir: impl AsRef<Path>,
+        base_offset: i64,
+    ) -> Result<(Self, bool), String> {
+        let mut segment = Self::open_files(fs, &dir, base_offset)
+            .await
+            .map_err(|e| format!("Failed to open segment {}: {}", base_offset, e))?;
+        if segment.load_last_batch().await? {
+            let entries = segment.timeindex_entries().await?;
+            segment.last_indexed_timestamp = segment
+                .read_timeindex_from(entries.saturating_sub(1))
+                .await?
+                .last()
+                .map_or(i64::MIN, |entry| entry.timestamp);
+            return Ok((segment, false));
+        }
+        tracing::warn!(
+            "Segment {} of {} does not match its index, recovering it",
+            base_offset,
+            segment.dir.display()
+        );
+        let truncated = segment.recover().await?;
+        Ok((segment, truncated))
+    }
+
+    async fn load_last_batch(&mut self) -> Result<bool, String> {
+        let index_size = self
+            .index_file
+            .size()
+            .await
+            .map_err(|e| format!("IO error when getting index file metadata: {}", e))?;
+        if index_size == 0 || index_size % IndexEntry::SIZE as u64 != 0 {
+            return Ok(index_size == 0 && self.current_size == 0);
+        }
+
+        let mut entry = [0u8; IndexEntry::SIZE];
+        self.index_file
+            .seek(SeekFrom::Start(index_size - IndexEntry::SIZE as u64))
             .await
-            .map_err(|e| format!("IO error when writing log file: {}", e))?;
+            .map_err(|e| format!("IO error when seeking index file: {}", e))?;
+        self.index_file
+            .read_exact(&mut entry)
+            .await
+            .map_err(|e| format!("IO error when reading index file: {}", e))?;
+        let entry = IndexEntry::decode(&entry);
+
+        self.seek_log(entry.physical_position as u64).await?;
+        let Ok(Some(batch)) = self.read_next_raw_batch().await else {
+            return Ok(false);
+        };
+        let ends_log =
+            entry.physical_position as u64 + batch.len() as u64 == self.current_size as u64;
+        match (
+            RecordBatch::peek_offsets(&batch),
+            RecordBatch::peek_partition_leader_epoch(&batch),
+        ) {
+            (Ok((base_offset, last_offset)), Ok(epoch))
+                if ends_log
+                    && base_offset == self.base_offset + entry.relative_offset as i64
+                    && RecordBatch::verify_checksum(&batch).is_ok() =>
+            {
+                self.last_offset = last_offset;
+                self.last_term = epoch as u64;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
 
-        let relative_offset = (batch.base_offset - self.base_offset) as i32;
-        let physical_position = self.current_size;
+    async fn recover(&mut self) -> Result<bool, String> {
+        let log_size = self.current_size as u64;
+        self.upgrade_legacy_checksums().await?;
+        self.seek_log(0).await?;
 
-        write_encoded_structure(
-            &mut self.index_file,
-            IndexEntry::SIZE,
-            |buf| {
-                IndexEntry {
+        let mut index = BytesMut::new();
+        let mut timeindex = BytesMut::new();
+        let mut valid_size = 0u64;
+        loop {
+            let (batch, size) = match self.read_next_batch().await {
+                Ok(Some(next)) => next,
+                // A bad tail is cut below, but a read that failed says nothing about the bytes
+                Ok(None) | Err(ReadError::Corrupt(_)) => break,
+                Err(ReadError::Io(e)) => return Err(e),
+            };
+            if batch.base_offset <= self.last_offset || batch.last_offset() < batch.base_offset {
+                break;
+            }
+            let relative_offset = (batch.base_offset - self.base_offset) as i32;
+            IndexEntry {
+                relative_offset,
+                physical_position: valid_size as u32,
+            }
+            .encode(&mut index);
+            if batch.base_timestamp >= self.last_indexed_timestamp {
+                TimeIndexEntry {
+                    timestamp: batch.base_timestamp,
                     relative_offset,
-                    physical_position,
                 }
-                .encode(buf);
-            },
-            "index",
-        )
-        .await?;
-
-        write_encoded_structure(
-            &mut self.timeindex_file,
-            TimeIndexEntry::SIZE,
-            |buf| {
+                .encode(&mut timeindex);
+                self.last_indexed_timestamp = batch.base_timestamp;
+            }
+            valid_size += size as u64;
+            self.last_offset = batch.last_offset();
+            self.last_term = batch.partition_leader_epoch as u64;
+        }
+
+        let truncated = valid_size < log_size;
+        if truncated {
+            self.log_file
+                .set_len(valid_size)
+                .await
+                .map_err(|e| format!("IO error when truncating log file: {}", e))?;
+            self.current_size = valid_size as u32;
+        }
+        let index_rebuilt = Self::rewrite_if_changed(&mut self.index_file, &index).await?;
+        let timeindex_rebuilt =
+            Self::rewrite_if_changed(&mut self.timeindex_file, &timeindex).await?;
+        self.index_size = index.len() as u64;
+        self.timeindex_size = timeindex.len() as u64;
+        if truncated || index_rebuilt || timeindex_rebuilt {
+            self.flush()
+                .await
+                .map_err(|e| format!("Failed to flush recovered segment: {}", e))?;
+        }
+        Ok(truncated)
+    }
+
+    /// Rewrites the log with CRC-32C checksums if any batch still carries the CRC-32 of older
+    /// versions. The copy ends where a batch fails both checks, leaving the cut to `recover`;
+    /// a read that fails stops the rewrite before anything replaces the log.
+    async fn upgrade_legacy_checksums(&mut self) -> Result<(), String> {
+        self.seek_log(0).await?;
+        let mut legacy = 0;
+        while let Some(batch) = self.read_next_whole_batch().await? {
+            if RecordBatch::verify_checksum(&batch).is_ok() {
+                continue;
+            }
+            if !RecordBatch::upgrade_legacy_checksum(&mut batch.to_vec()) {
+                break;
+            }
+            legacy += 1;
+        }
+        if legacy == 0 {
+            return Ok(());
+        }
+
+        let log_path = segment_file_path(&self.dir, self.base_offset, LOG_EXTENSION);
+        let upgrade_path = segment_file_path(&self.dir, self.base_offset, UPGRADE_EXTENSION);
+        // Left by a rewrite that crashed; the log it was copied from is still whole
+        let _ = self.fs.remove_file(&upgrade_path).await;
+        let mut upgraded = self
+            .fs
+            .open_append(&upgrade_path)
+            .await
+            .map_err(|e| format!("IO error when creating {}: {}", upgrade_path.display(), e))?;
+        self.seek_log(0).await?;
+        let mut size = 0u64;
+        while let Some(batch) = self.read_next_whole_batch().await? {
+            let mut batch = batch.to_vec();
+            if RecordBatch::verify_checksum(&batch).is_err()
+                && !RecordBatch::upgrade_legacy_checksum(&mut batch)
+            {
+                break;
+            }
+            upgraded
+                .write_all(&batch)
+                .await
+                .map_err(|e| format!("IO error when upgrading log file: {}", e))?;
+            size += batch.len() as u64;
+        }
+        upgraded
+            .flush()
+            .await
+            .map_err(|e| format!("IO error when flushing upgraded log file: {}", e))?;
+        upgraded
+            .sync_data()
+            .await
+            .map_err(|e| format!("IO error when syncing upgraded log file: {}", e))?;
+        drop(upgraded);
+        self.fs
+            .rename(&upgrade_path, &log_path)
+            .await
+            .map_err(|e| format!("IO error when replacing {}: {}", log_path.display(), e))?;
+        self.fs
+            .sync_dir(&self.dir)
+            .await
+            .map_err(|e| format!("Failed to sync {}: {}", self.dir.display(), e))?;
+        self.log_file = self
+            .fs
+            .open_append(&log_path)
+            .await
+            .map_err(|e| format!("IO error when reopening {}: {}", log_path.display(), e))?;
+        self.current_size = size as u32;
+        tracing::warn!(
+            "Rewrote {} batches of segment {} of {} from CRC-32 to CRC-32C checksums",
+            legacy,
+            self.base_offset,
+            self.dir.display()
+        );
+        Ok(())
+    }
+
+    async fn rewrite_if_changed(
+        file: &mut Box<dyn SegmentFile>,
+        expected: &[u8],
+    ) -> Result<bool, String> {
+        let mut current = Vec::new();
+        file.seek(SeekFrom::Start(0))
+            .await
+            .map_err(|e| format!("IO error when seeking index file: {}", e))?;
+        file.read_to_end(&mut current)
+            .await
+            .map_err(|e| format!("IO error when reading index file: {}", e))?;
+        if current == expected {
+            return Ok(false);
+        }
+        file.set_len(0)
+            .await
+            .map_err(|e| format!("IO error when truncating index file: {}", e))?;
+        file.write_all(expected)
+            .await
+            .map_err(|e| format!("IO error when rebuilding index file: {}", e))?;
+        Ok(true)
+    }
+
+    pub async fn append(&mut self, batch: &RecordBatch) -> Result<(), String> {
+        self.append_batches(std::slice::from_ref(batch))
+            .await
+            .map(drop)
+    }
+
+    /// Writes `batches`, which must follow each other, to the log and then their entries to
+    /// both indexes, each file in one write. The log goes first so an index entry never points
+    /// past it; the indexes do not depend on each other and are written together. Returns the
+    /// bytes each batch took. Nothing is written unless every base offset is past the offsets
+    /// before it, and a write that fails leaves the files as they were; see `cut_back`.
+    pub async fn append_batches(&mut self, batches: &[RecordBatch]) -> Result<Vec<u32>, String> {
+        if let Some(e) = &self.broken {
+            return Err(format!(
+                "Segment {} takes no more appends: {}",
+                self.base_offset, e
+            ));
+        }
+        let mut last_offset = self.last_offset;
+        for batch in batches {
+            if batch.base_offset <= last_offset {
+                return Err(format!(
+                    "Batch at offset {} does not follow offset {} in segment {}",
+                    batch.base_offset, last_offset, self.base_offset
+                ));
+            }
+            last_offset = batch.last_offset();
+        }
+
+        self.write_buffer.clear();
+        let mut index = Vec::with_capacity(batches.len() * IndexEntry::SIZE);
+        let mut timeindex = Vec::with_capacity(batches.len() * TimeIndexEntry::SIZE);
+        let mut sizes = Vec::with_capacity(batches.len());
+        let mut last_indexed_timestamp = self.last_indexed_timestamp;
+        for batch in batches {
+            let start = self.write_buffer.len();
+            batch.encode_to(&mut self.write_buffer);
+            sizes.push((self.write_buffer.len() - start) as u32);
+            let physical_position = self.current_size + start as u32;
+
+            let relative_offset = (batch.base_offset - self.base_offset) as i32;
+            IndexEntry {
+                relative_offset,
+                physical_position,
+            }
+            .encode(&mut index);
+            if batch.base_timestamp >= last_indexed_timestamp {
                 TimeIndexEntry {
                     timestamp: batch.base_timestamp,
                     relative_offset,
                 }
-                .encode(buf);
-            },
-            "timeindex",
-        )
-        .await?;
+                .encode(&mut timeindex);
+                last_indexed_timestamp = batch.base_timestamp;
+            }
+        }
 
-        self.current_size += buffer.len() as u32;
+        let mut written = write_to_file(&mut self.log_file, &self.write_buffer, "log").await;
+        let size = self.write_buffer.len() as u32;
+        if self.write_buffer.capacity() > MAX_RETAINED_WRITE_BUFFER {
+            self.write_buffer = BytesMut::new();
+        }
+        if written.is_ok() {
+            written = tokio::try_join!(
+                write_to_file(&mut self.index_file, &index, "index"),
+                write_to_file(&mut self.timeindex_file, &timeindex, "timeindex"),
+            )
+            .map(drop);
+        }
+        if let Err(e) = written {
+            self.cut_back(&e).await;
+            return Err(e);
+        }
 
-        self.last_offset = batch.base_offset + batch.last_offset_delta as i64;
-        self.last_term = batch.partition_leader_epoch as u64;
+        self.current_size += size;
+        self.index_size += index.len() as u64;
+        self.timeindex_size += timeindex.len() as u64;
+        self.last_indexed_timestamp = last_indexed_timestamp;
 
-        Ok(())
+        if let Some(last) = batches.last() {
+            self.last_offset = last.last_offset();
+            self.last_term = last.partition_leader_epoch as u64;
+        }
+
+        Ok(sizes)
+    }
+
+    /// Cuts the files back to where a failed append found them. Any of its writes may have
+    /// stored part of its bytes, which the next append would write after while indexing from
+    /// `current_size`. A segment whose files cannot be cut takes no more appends.
+    async fn cut_back(&mut self, error: &str) {
+        let cut = tokio::try_join!(
+            self.log_file.set_len(self.current_size as u64),
+            self.index_file.set_len(self.index_size),
+            self.timeindex_file.set_len(self.timeindex_size),
+        );
+        if let Err(e) = cut {
+            tracing::error!(
+                "Failed to cut segment {} of {} back after a failed append: {}",
+                self.base_offset,
+                self.dir.display(),
+                e
+            );
+            self.broken = Some(format!("{}, and cutting it back failed: {}", error, e));
+        }
     }
 
     pub async fn flush(&mut self) -> std::io::Result<()> {
@@ -164,12 +573,12 @@ impl Segment {
         }
 
         let relative_offset = (offset - self.base_offset) as i32;
-        let metadata = self
+        let file_size = self
             .index_file
-            .metadata()
+            .size()
             .await
-            .map_err(|e| format!("IO error when getting index file metadata: {}", e))?;
-        let file_size = metadata.len() as usize;
+            .map_err(|e| format!("IO error when getting index file metadata: {}", e))?
+            as usize;
 
         if file_size == 0 {
             return Ok(None);
@@ -216,12 +625,17 @@ impl Segment {
             None => return Ok(None),
         };
 
+        self.seek_log(physical_position).await?;
+        Ok(Some(physical_position))
+    }
+
+    async fn seek_log(&mut self, position: u64) -> Result<(), String> {
+        self.read_ahead.clear();
         self.log_file
-            .seek(SeekFrom::Start(physical_position))
+            .seek(SeekFrom::Start(position))
             .await
             .map_err(|e| format!("IO error when seeking log file: {}", e))?;
-
-        Ok(Some(physical_position))
+        Ok(())
     }
 
     async fn find_index_byte_offset_by_physical_position(
@@ -268,49 +682,173 @@ impl Segment {
         Ok(index_byte_offset)
     }
 
+    /// The batch holding `offset`. The index only bounds where it starts, so the batches
+    /// before it are read past.
     pub async fn read(&mut self, offset: i64) -> Result<Option<RecordBatch>, String> {
         if self.seek_to_offset(offset).await?.is_none() {
             return Ok(None);
         }
 
-        let result = self.read_next_batch().await?;
-        Ok(result.map(|(batch, _)| batch))
+        while let Some((batch, _)) = self.read_next_batch().await? {
+            if batch.last_offset() >= offset {
+                return Ok(Some(batch));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The first offset in this segment whose record timestamp is at or after `timestamp`,
+    /// with that record's timestamp. The time index holds the first timestamp of the batches
+    /// that did not go back in time, so the batches from the entry before the first one at or
+    /// after `timestamp` up to that one are read to find a match among them.
+    pub async fn offset_for_timestamp(
+        &mut self,
+        timestamp: i64,
+    ) -> Result<Option<(i64, i64)>, String> {
+        let entries = self.read_timeindex_from(0).await?;
+
+        let first_at_or_after = entries
+            .iter()
+            .position(|entry| entry.timestamp >= timestamp);
+        let previous = match first_at_or_after {
+            Some(index) => index.checked_sub(1),
+            None => entries.len().checked_sub(1),
+        };
+        let next = first_at_or_after.map(|index| {
+            let entry = &entries[index];
+            (
+                self.base_offset + entry.relative_offset as i64,
+                entry.timestamp,
+            )
+        });
+        let Some(previous) = previous else {
+            return Ok(next);
+        };
+
+        let offset = self.base_offset + entries[previous].relative_offset as i64;
+        let end = next.map_or(i64::MAX, |(offset, _)| offset);
+        if self.seek_to_offset(offset).await?.is_none() {
+            return Ok(next);
+        }
+        while let Some((batch, _)) = self.read_next_batch().await? {
+            if batch.base_offset >= end {
+                break;
+            }
+            let found = batch
+                .records
+                .iter()
+                .find(|record| batch.base_timestamp + record.timestamp_delta.0 >= timestamp);
+            if let Some(record) = found {
+                return Ok(Some((
+                    batch.base_offset + record.offset_delta.0 as i64,
+                    batch.base_timestamp + record.timestamp_delta.0,
+                )));
+            }
+        }
+        Ok(next)
+    }
+
+    async fn timeindex_entries(&mut self) -> Result<u64, String> {
+        let size = self
+            .timeindex_file
+            .size()
+            .await
+            .map_err(|e| format!("IO error when getting timeindex file metadata: {}", e))?;
+        Ok(size / TimeIndexEntry::SIZE as u64)
+    }
+
+    /// The time index entries from the `first`th on.
+    async fn read_timeindex_from(&mut self, first: u64) -> Result<Vec<TimeIndexEntry>, String> {
+        let mut timeindex = Vec::new();
+        self.timeindex_file
+            .seek(SeekFrom::Start(first * TimeIndexEntry::SIZE as u64))
+            .await
+            .map_err(|e| format!("IO error when seeking timeindex file: {}", e))?;
+        self.timeindex_file
+            .read_to_end(&mut timeindex)
+            .await
+            .map_err(|e| format!("IO error when reading timeindex file: {}", e))?;
+        Ok(timeindex
+            .chunks_exact(TimeIndexEntry::SIZE)
+            .map(TimeIndexEntry::decode)
+            .collect())
     }
 
+    /// Batches from the one holding `offset` on, up to `max_bytes` but at least one, decoded.
+    /// Stops at the first batch that does not decode.
     pub async fn read_sequential(
         &mut self,
         offset: i64,
         max_bytes: usize,
     ) -> Result<Vec<RecordBatch>, String> {
-        if self.seek_to_offset(offset).await?.is_none() {
-            return Ok(vec![]);
+        let mut batches: Vec<RecordBatch> = self
+            .read_sequential_raw(offset, max_bytes, i64::MAX)
+            .await?
+            .into_iter()
+            .map_while(|mut batch| RecordBatch::decode(&mut batch).ok())
+            .collect();
+        if let Some(first) = batches.first_mut() {
+            first.drop_records_before(offset);
         }
+        Ok(batches)
+    }
+
+    /// Like `read_sequential`, but leaves the batches as they are in the file, unchecked, and
+    /// stops before the first batch at or past `max_offset`.
+    pub async fn read_sequential_raw(
+        &mut self,
+        offset: i64,
+        max_bytes: usize,
+        max_offset: i64,
+    ) -> Result<Vec<Bytes>, String> {
+        // A reader that picks up where its last read stopped needs no index lookup
+        let start = match self.read_positions.take(offset) {
+            Some(position) => {
+                self.seek_log(position as u64).await?;
+                position
+            }
+            None => match self.seek_to_offset(offset).await? {
+                Some(position) => position as u32,
+                None => return Ok(vec![]),
+            },
+        };
 
         let mut batches = Vec::new();
         let mut bytes_read_total = 0;
+        // Past the batches returned and any before `offset` that the index pointed at
+        let mut position = start as usize;
+        let mut next_offset = offset;
 
         loop {
             if bytes_read_total >= max_bytes {
                 break;
             }
 
-            match self.read_next_batch().await {
-                Ok(Some((batch, size))) => {
-                    if bytes_read_total > 0 && bytes_read_total + size > max_bytes {
-                        let _ = self.log_file.seek(SeekFrom::Current(-(size as i64))).await;
-                        break;
-                    }
-
-                    if batch.base_offset + batch.records_count as i64 > offset {
-                        batches.push(batch);
+            match self.read_next_raw_batch().await {
+                Ok(Some(batch)) => {
+                    let size = batch.len();
+                    match RecordBatch::peek_offsets(&batch) {
+                        Ok((base_offset, _)) if base_offset >= max_offset => break,
+                        Ok((_, last_offset)) if last_offset >= offset => {
+                            if bytes_read_total > 0 && bytes_read_total + size > max_bytes {
+                                break;
+                            }
+                            next_offset = last_offset + 1;
+                            batches.push(batch);
+                            bytes_read_total += size;
+                        }
+                        // Ends before `offset`, so it counts against no limit
+                        Ok(_) => {}
+                        Err(_) => break,
                     }
-                    bytes_read_total += size;
+                    position += size;
                 }
                 Ok(None) => break,
                 Err(_) => break,
             }
         }
 
+        self.read_positions.insert(next_offset, position as u32);
         Ok(batches)
     }
 
@@ -332,12 +870,13 @@ impl Segment {
                     }
                 }
                 Ok(None) => return Ok(None),
-                Err(e) => return Err(e),
+                Err(e) => return Err(e.into()),
             }
         }
     }
 
     pub async fn truncate(&mut self, offset: i64) -> Result<(), String> {
+        self.read_positions.clear();
         if offset <= self.base_offset {
             self.log_file.set_len(0).await.map_err(|e| e.to_string())?;
             self.index_file
@@ -349,8 +888,11 @@ impl Segment {
                 .await
                 .map_err(|e| e.to_string())?;
             self.current_size = 0;
+            self.index_size = 0;
+            self.timeindex_size = 0;
             self.last_offset = self.base_offset - 1;
             self.last_term = 0;
+            self.last_indexed_timestamp = i64::MIN;
             return Ok(());
         }
 
@@ -364,17 +906,17 @@ impl Segment {
         let mut new_last_term = 0;
 
         loop {
-            match self.read_next_batch().await {
-                Ok(Some((batch, size))) => {
-                    if batch.base_offset >= offset {
-                        break;
-                    }
-                    truncate_pos += size as u64;
-                    new_last_offset = batch.base_offset + batch.last_offset_delta as i64;
-                    new_last_term = batch.partition_leader_epoch as u64;
-                }
-                _ => break,
+            let (batch, size) = match self.read_next_batch().await {
+                Ok(Some(next)) => next,
+                Ok(None) | Err(ReadError::Corrupt(_)) => break,
+                Err(ReadError::Io(e)) => return Err(e),
+            };
+            if batch.base_offset >= offset {
+                break;
             }
+            truncate_pos += size as u64;
+            new_last_offset = batch.base_offset + batch.last_offset_delta as i64;
+            new_last_term = batch.partition_leader_epoch as u64;
         }
 
         self.log_file
@@ -385,76 +927,440 @@ impl Segment {
         self.last_offset = new_last_offset;
         self.last_term = new_last_term;
 
-        let metadata = self
-            .index_file
-            .metadata()
-            .await
-            .map_err(|e| e.to_string())?;
-        let entries_count = metadata.len() / IndexEntry::SIZE as u64;
+        let index_size = self.index_file.size().await.map_err(|e| e.to_string())?;
+        let entries_count = index_size / IndexEntry::SIZE as u64;
 
         let index_truncate_pos = self
-            .find_index_byte_offset_by_physical_position(
-                truncate_pos,
-                entries_count,
-                metadata.len(),
-            )
+            .find_index_byte_offset_by_physical_position(truncate_pos, entries_count, index_size)
             .await?;
 
         self.index_file
             .set_len(index_truncate_pos)
             .await
             .map_err(|e| e.to_string())?;
+        self.index_size = index_truncate_pos;
+
+        // Batches without a time index entry leave it shorter than the index, so it is cut at
+        // its first entry for a batch that is gone
+        let entries = self.read_timeindex_from(0).await?;
+        let kept = entries
+            .iter()
+            .take_while(|entry| self.base_offset + (entry.relative_offset as i64) < offset)
+            .count();
         self.timeindex_file
-            .set_len(index_truncate_pos)
+            .set_len((kept * TimeIndexEntry::SIZE) as u64)
             .await
             .map_err(|e| e.to_string())?;
+        self.timeindex_size = (kept * TimeIndexEntry::SIZE) as u64;
+        self.last_indexed_timestamp = kept
+            .checked_sub(1)
+            .map_or(i64::MIN, |last| entries[last].timestamp);
 
         Ok(())
     }
 
-    async fn read_next_batch(&mut self) -> Result<Option<(RecordBatch, usize)>, String> {
-        let mut header_buf = vec![0u8; BATCH_HEADER_SIZE];
-        let bytes_read = self
-            .log_file
-            .read(&mut header_buf)
-            .await
-            .map_err(|e| format!("IO error when reading record batch header: {}", e))?;
+    /// Reads until `read_ahead` holds `needed` bytes or the log ends. Each read asks for at
+    /// least `READ_AHEAD` bytes, so small batches that follow each other come from one read.
+    async fn fill_read_ahead(&mut self, needed: usize) -> Result<(), ReadError> {
+        if self.read_ahead.len() >= needed {
+            return Ok(());
+        }
+        self.read_ahead
+            .reserve(needed.max(READ_AHEAD) - self.read_ahead.len());
+        while self.read_ahead.len() < needed {
+            // Reads into the spare capacity, which is never zeroed first
+            let read = self
+                .log_file
+                .read_buf(&mut self.read_ahead)
+                .await
+                .map_err(|e| ReadError::Io(format!("IO error when reading log file: {}", e)))?;
+            if read == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    async fn read_next_batch(&mut self) -> Result<Option<(RecordBatch, usize)>, ReadError> {
+        let Some(batch) = self.read_next_raw_batch().await? else {
+            return Ok(None);
+        };
+        let decoded = RecordBatch::decode(&mut batch.clone())
+            .map_err(|e| ReadError::Corrupt(format!("Failed to decode record batch: {}", e)))?;
+        Ok(Some((decoded, batch.len())))
+    }
+
+    /// Like `read_next_raw_batch`, but ends the log at bytes that are not a whole batch rather
+    /// than failing.
+    async fn read_next_whole_batch(&mut self) -> Result<Option<Bytes>, String> {
+        match self.read_next_raw_batch().await {
+            Ok(batch) => Ok(batch),
+            Err(ReadError::Corrupt(_)) => Ok(None),
+            Err(ReadError::Io(e)) => Err(e),
+        }
+    }
 
-        if bytes_read == 0 {
+    /// The next whole batch in the file, checked only for a length that fits the log.
+    async fn read_next_raw_batch(&mut self) -> Result<Option<Bytes>, ReadError> {
+        self.fill_read_ahead(BATCH_HEADER_SIZE).await?;
+        if self.read_ahead.is_empty() {
             return Ok(None);
         }
 
-        if bytes_read < BATCH_HEADER_SIZE {
-            return Err("Corrupted file: Lacking header size".to_string());
+        if self.read_ahead.len() < BATCH_HEADER_SIZE {
+            return Err(ReadError::Corrupt(
+                "Corrupted file: Lacking header size".to_string(),
+            ));
         }
 
         let batch_length = i32::from_be_bytes(
-            header_buf[BATCH_LENGTH_OFFSET..BATCH_HEADER_SIZE]
+            self.read_ahead[BATCH_LENGTH_OFFSET..BATCH_HEADER_SIZE]
                 .try_into()
                 .unwrap(),
-        ) as usize;
+        );
+        // A torn or garbled header must not size the buffer past the end of the log
+        if batch_length < 0
+            || BATCH_HEADER_SIZE + batch_length as usize > self.current_size as usize
+        {
+            return Err(ReadError::Corrupt(format!(
+                "Corrupted file: Invalid batch length {}",
+                batch_length
+            )));
+        }
+
+        let total_size = BATCH_HEADER_SIZE + batch_length as usize;
+        self.fill_read_ahead(total_size).await?;
+        if self.read_ahead.len() < total_size {
+            return Err(ReadError::Corrupt(
+                "Corrupted file: Batch runs past the end of the log".to_string(),
+            ));
+        }
 
-        let total_size = BATCH_HEADER_SIZE + batch_length;
+        Ok(Some(self.read_ahead.split_to(total_size).freeze()))
+    }
 
-        let mut full_batch_buf = BytesMut::zeroed(total_size);
-        full_batch_buf[0..BATCH_HEADER_SIZE].copy_from_slice(&header_buf);
+    pub async fn delete(self) -> Result<(), String> {
+        for extension in [LOG_EXTENSION, INDEX_EXTENSION, TIMEINDEX_EXTENSION] {
+            let path = segment_file_path(&self.dir, self.base_offset, extension);
+            let _ = self.fs.remove_file(&path).await;
+        }
 
-        self.log_file
-            .read_exact(&mut full_batch_buf[BATCH_HEADER_SIZE..])
+        self.fs
+            .sync_dir(&self.dir)
             .await
-            .map_err(|e| format!("IO error when reading record batch payload: {}", e))?;
+            .map_err(|e| format!("Failed to sync {}: {}", self.dir.display(), e))
+    }
+}
 
-        let batch = RecordBatch::decode(&mut full_batch_buf)
-            .map_err(|e| format!("Failed to decode record batch: {}", e))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::driven::storage::fault_injection::{Faults, SimulatedFileSystem};
+    use crate::core::domain::record::Record;
 
-        Ok(Some((batch, total_size)))
+    fn batch_at(base_offset: i64, value: &[u8]) -> RecordBatch {
+        let mut batch = RecordBatch::new(0, vec![Record::new(0, None, Some(value.to_vec()))]);
+        batch.base_offset = base_offset;
+        batch
     }
 
-    pub async fn delete(self) -> Result<(), String> {
-        let _ = delete_file(&self.dir, self.base_offset, LOG_EXTENSION).await;
-        let _ = delete_file(&self.dir, self.base_offset, INDEX_EXTENSION).await;
-        let _ = delete_file(&self.dir, self.base_offset, TIMEINDEX_EXTENSION).await;
+    /// Stamps every batch in `log` with the CRC-32 older versions wrote.
+    fn stamp_legacy_checksums(log: &mut [u8]) {
+        let mut position = 0;
+        while position < log.len() {
+            let batch_length = i32::from_be_bytes(
+                log[position + BATCH_LENGTH_OFFSET..position + BATCH_HEADER_SIZE]
+                    .try_into()
+                    .unwrap(),
+            ) as usize;
+            let end = position + BATCH_HEADER_SIZE + batch_length;
+            let crc_offset = position + BATCH_HEADER_SIZE + 4 + 1;
+            let crc = crc32fast::hash(&log[crc_offset + 4..end]);
+            log[crc_offset..crc_offset + 4].copy_from_slice(&crc.to_be_bytes());
+            position = end;
+        }
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn test_sequential_reads_resume_where_the_last_one_stopped() {
+        let dir = std::env::temp_dir().join(format!("forge-segment-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let mut segment = Segment::new(&dir, 0).await.unwrap();
+        for offset in 0..3 {
+            segment.append(&batch_at(offset, b"old")).await.unwrap();
+        }
+
+        let first = segment.read_sequential_raw(0, 1, i64::MAX).await.unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!(segment.read_positions.0, [(1, first[0].len() as u32)]);
+        // Stops short of the max offset, so the reader resumes from there
+        let second = segment.read_sequential_raw(1, usize::MAX, 2).await.unwrap();
+        assert_eq!(RecordBatch::peek_offsets(&second[0]).unwrap(), (1, 1));
+        assert_eq!(second.len(), 1);
+        assert_eq!(segment.read_positions.0.len(), 1);
+
+        // Positions past the truncation point no longer hold the batches they did
+        segment.truncate(1).await.unwrap();
+        segment.append(&batch_at(1, b"longer")).await.unwrap();
+        segment.append(&batch_at(2, b"new")).await.unwrap();
+        let batches = segment.read_sequential(2, usize::MAX).await.unwrap();
+        assert_eq!(batches[0].records[0].value.as_deref(), Some(&b"new"[..]));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_clean_open_trusts_the_index_unless_the_log_runs_past_it() {
+        let dir = std::env::temp_dir().join(format!("forge-segment-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let fs: Arc<dyn FileSystem> = Arc::new(TokioFileSystem);
+        let mut segment = Segment::create(fs.clone(), &dir, 10).await.unwrap();
+        for offset in 10..13 {
+            let mut batch = batch_at(offset, b"value");
+            batch.partition_leader_epoch = 4;
+            segment.append(&batch).await.unwrap();
+        }
+        drop(segment);
+
+        let (segment, cut) = Segment::open_clean(fs.clone(), &dir, 10).await.unwrap();
+        assert!(!cut);
+        assert_eq!((segment.last_offset, segment.last_term), (12, 4));
+        drop(segment);
+
+        // A torn write the index does not know about sends it through recovery
+        let log = segment_file_path(&dir, 10, LOG_EXTENSION);
+        let mut file = fs.open_append(&log).await.unwrap();
+        file.write_all(b"torn").await.unwrap();
+        file.flush().await.unwrap();
+        let (segment, cut) = Segment::open_clean(fs, &dir, 10).await.unwrap();
+        assert!(cut);
+        assert_eq!(segment.last_offset, 12);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_legacy_checksums_are_rewritten_not_cut() {
+        let dir = std::env::temp_dir().join(format!("forge-segment-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let fs: Arc<dyn FileSystem> = Arc::new(TokioFileSystem);
+        let mut segment = Segment::create(fs.clone(), &dir, 0).await.unwrap();
+        for offset in 0..3 {
+            segment.append(&batch_at(offset, b"value")).await.unwrap();
+        }
+        drop(segment);
+
+        let log = segment_file_path(&dir, 0, LOG_EXTENSION);
+        let mut bytes = tokio::fs::read(&log).await.unwrap();
+        stamp_legacy_checksums(&mut bytes);
+        tokio::fs::write(&log, &bytes).await.unwrap();
+
+        let (mut segment, cut) = Segment::open_clean(fs.clone(), &dir, 0).await.unwrap();
+        assert!(!cut);
+        assert_eq!(segment.last_offset, 2);
+        let batches = segment.read_sequential(0, usize::MAX).await.unwrap();
+        assert_eq!(batches.len(), 3);
+        assert_ne!(tokio::fs::read(&log).await.unwrap(), bytes);
+        drop(segment);
+
+        let (segment, cut) = Segment::open(fs, &dir, 0).await.unwrap();
+        assert!(!cut);
+        assert_eq!(segment.last_offset, 2);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reads_start_at_the_batch_holding_the_offset() {
+        let dir = std::env::temp_dir().join(format!("forge-segment-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let mut segment = Segment::new(&dir, 0).await.unwrap();
+        let records = (0..3)
+            .map(|delta| Record::new(delta, None, Some(vec![b'a' + delta as u8])))
+            .collect();
+        segment.append(&RecordBatch::new(0, records)).await.unwrap();
+        for offset in 3..6 {
+            segment.append(&batch_at(offset, b"value")).await.unwrap();
+        }
+        // Index only the first batch, so every lookup lands before the batch it wants
+        segment
+            .index_file
+            .set_len(IndexEntry::SIZE as u64)
+            .await
+            .unwrap();
+
+        assert_eq!(segment.read(4).await.unwrap().unwrap().base_offset, 4);
+        // Batches read past on the way do not count against the limit
+        let raw = segment.read_sequential_raw(5, 1, i64::MAX).await.unwrap();
+        assert_eq!(RecordBatch::peek_offsets(&raw[0]).unwrap(), (5, 5));
+
+        let batches = segment.read_sequential(1, usize::MAX).await.unwrap();
+        let offsets: Vec<i64> = batches[0]
+            .records
+            .iter()
+            .map(|record| batches[0].base_offset + record.offset_delta.0 as i64)
+            .collect();
+        assert_eq!(offsets, [1, 2]);
+        assert_eq!(batches.len(), 4);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_batches_going_back_in_time_are_left_out_of_the_time_index() {
+        let dir = std::env::temp_dir().join(format!("forge-segment-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let fs: Arc<dyn FileSystem> = Arc::new(TokioFileSystem);
+        let mut segment = Segment::create(fs.clone(), &dir, 0).await.unwrap();
+        let mut late = Record::new(1, None, Some(b"late".to_vec()));
+        late.timestamp_delta.0 = 200;
+        let mut batches = vec![
+            RecordBatch::new(100, vec![Record::new(0, None, None)]),
+            RecordBatch::new(300, vec![Record::new(0, None, None)]),
+            RecordBatch::new(200, vec![Record::new(0, None, None), late]),
+            RecordBatch::new(500, vec![Record::new(0, None, None)]),
+        ];
+        for (batch, offset) in batches.iter_mut().zip([0, 1, 2, 4]) {
+            batch.base_offset = offset;
+        }
+        segment.append_batches(&batches).await.unwrap();
+        assert!(segment.append(&batch_at(4, b"again")).await.is_err());
+        assert_eq!(
+            segment.current_size,
+            segment.log_file.size().await.unwrap() as u32
+        );
+
+        let indexed = |entries: Vec<TimeIndexEntry>| -> Vec<(i32, i64)> {
+            entries
+                .iter()
+                .map(|entry| (entry.relative_offset, entry.timestamp))
+                .collect()
+        };
+        let entries = segment.read_timeindex_from(0).await.unwrap();
+        assert_eq!(indexed(entries), [(0, 100), (1, 300), (4, 500)]);
+        // The unindexed batch between two entries is still searched
+        assert_eq!(
+            segment.offset_for_timestamp(350).await.unwrap(),
+            Some((3, 400))
+        );
+        drop(segment);
+
+        // Recovery rebuilds the same entries
+        let (mut segment, _) = Segment::open(fs, &dir, 0).await.unwrap();
+        let entries = segment.read_timeindex_from(0).await.unwrap();
+        assert_eq!(indexed(entries), [(0, 100), (1, 300), (4, 500)]);
+
+        segment.truncate(4).await.unwrap();
+        let entries = segment.read_timeindex_from(0).await.unwrap();
+        assert_eq!(indexed(entries), [(0, 100), (1, 300)]);
+        let mut batch = RecordBatch::new(250, vec![Record::new(0, None, None)]);
+        batch.base_offset = 4;
+        segment.append(&batch).await.unwrap();
+        assert_eq!(segment.timeindex_entries().await.unwrap(), 2);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_appends_leave_the_files_as_they_were() {
+        let fs = SimulatedFileSystem::new(0);
+        let dir = Path::new("/data/topic-0");
+        fs.create_dir_all(dir).await.unwrap();
+        let mut segment = Segment::create(Arc::new(fs.clone()), dir, 0).await.unwrap();
+        segment.append(&batch_at(0, b"first")).await.unwrap();
+
+        fs.set_faults(Faults {
+            torn_write: 1.0,
+            ..Faults::default()
+        });
+        assert!(segment.append(&batch_at(1, b"torn")).await.is_err());
+        // Room for the log write but not the index entries
+        let mut encoded = BytesMut::new();
+        batch_at(1, b"unindexed").encode_to(&mut encoded);
+        let used = segment.current_size as u64 + segment.index_size + segment.timeindex_size;
+        fs.set_faults(Faults {
+            capacity: Some(used + encoded.len() as u64 + 3),
+            ..Faults::default()
+        });
+        assert!(segment.append(&batch_at(1, b"unindexed")).await.is_err());
+        fs.set_faults(Faults::default());
+
+        let sizes = (
+            segment.log_file.size().await.unwrap(),
+            segment.index_file.size().await.unwrap(),
+            segment.timeindex_file.size().await.unwrap(),
+        );
+        assert_eq!(
+            sizes,
+            (
+                segment.current_size as u64,
+                IndexEntry::SIZE as u64,
+                TimeIndexEntry::SIZE as u64
+            )
+        );
+
+        segment.append(&batch_at(1, b"second")).await.unwrap();
+        let batch = segment.read(1).await.unwrap().unwrap();
+        assert_eq!(batch.records[0].value.as_deref(), Some(&b"second"[..]));
+        drop(segment);
+
+        let (segment, cut) = Segment::open(Arc::new(fs), dir, 0).await.unwrap();
+        assert!(!cut);
+        assert_eq!(segment.last_offset, 1);
+    }
+
+    #[tokio::test]
+    async fn test_recovery_fails_on_read_errors_instead_of_cutting() {
+        let fs = SimulatedFileSystem::new(0);
+        let dir = Path::new("/data/topic-0");
+        fs.create_dir_all(dir).await.unwrap();
+        let mut segment = Segment::create(Arc::new(fs.clone()), dir, 0).await.unwrap();
+        for offset in 0..3 {
+            segment.append(&batch_at(offset, b"value")).await.unwrap();
+        }
+        drop(segment);
+
+        fs.set_faults(Faults {
+            read_failure: 1.0,
+            ..Faults::default()
+        });
+        assert!(Segment::open(Arc::new(fs.clone()), dir, 0).await.is_err());
+        fs.set_faults(Faults::default());
+
+        let (segment, cut) = Segment::open(Arc::new(fs), dir, 0).await.unwrap();
+        assert!(!cut);
+        assert_eq!(segment.last_offset, 2);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_upgrade_keeps_every_batch_when_reads_fail() {
+        let dir = Path::new("/data/topic-0");
+        for seed in 0..20 {
+            let fs = SimulatedFileSystem::new(seed);
+            fs.create_dir_all(dir).await.unwrap();
+            let mut segment = Segment::create(Arc::new(fs.clone()), dir, 0).await.unwrap();
+            for offset in 0..3 {
+                segment.append(&batch_at(offset, b"value")).await.unwrap();
+            }
+            let mut log = Vec::new();
+            segment.log_file.seek(SeekFrom::Start(0)).await.unwrap();
+            segment.log_file.read_to_end(&mut log).await.unwrap();
+            stamp_legacy_checksums(&mut log);
+            segment.log_file.set_len(0).await.unwrap();
+            segment.log_file.write_all(&log).await.unwrap();
+            drop(segment);
+
+            // Fails in either pass over the log, or not at all
+            fs.set_faults(Faults {
+                read_failure: 0.5,
+                ..Faults::default()
+            });
+            let _ = Segment::open(Arc::new(fs.clone()), dir, 0).await;
+            fs.set_faults(Faults::default());
+
+            let (segment, cut) = Segment::open(Arc::new(fs), dir, 0).await.unwrap();
+            assert!(!cut, "seed {}", seed);
+            assert_eq!(segment.last_offset, 2, "seed {}", seed);
+        }
     }
 }
@@ -0,0 +1,647 @@
+use crate::{
+    adapters::driven::storage::segment::{IndexEntry, TimeIndexEntry},
+    core::domain::record_batch::{BATCH_HEADER_SIZE, BATCH_LENGTH_OFFSET, RecordBatch},
+    protocol::types::Type,
+    shared::constants::{INDEX_EXTENSION, LOG_EXTENSION, TIMEINDEX_EXTENSION},
+    shared::fs::segment_file_path,
+};
+use bytes::BytesMut;
+use std::path::{Path, PathBuf};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncReadExt, BufReader},
+};
+
+/// Partition leader epoch through records count: the shortest batch length that decodes.
+const MIN_BATCH_LENGTH: usize = 4 + 1 + 4 + 2 + 4 + 8 + 8 + 8 + 2 + 4 + 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Legal but unusual, such as a batch timestamp earlier than the one before it.
+    Warning,
+    Error,
+}
+
+#[derive(Debug)]
+pub struct Issue {
+    pub severity: Severity,
+    pub file: PathBuf,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub struct SegmentSummary {
+    pub base_offset: i64,
+    pub batches: usize,
+    pub records: usize,
+    pub first_offset: Option<i64>,
+    pub last_offset: Option<i64>,
+    pub log_bytes: u64,
+    /// Where the first batch that failed a check starts; `log_bytes` when all of them passed.
+    pub valid_bytes: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub segments: Vec<SegmentSummary>,
+    pub issues: Vec<Issue>,
+    /// What `--repair` changed on disk, in the order it happened.
+    pub repairs: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|i| i.severity == Severity::Error)
+    }
+
+    fn error(&mut self, file: &Path, message: String) {
+        self.issues.push(Issue {
+            severity: Severity::Error,
+            file: file.to_path_buf(),
+            message,
+        });
+    }
+
+    fn warning(&mut self, file: &Path, message: String) {
+        self.issues.push(Issue {
+            severity: Severity::Warning,
+            file: file.to_path_buf(),
+            message,
+        });
+    }
+}
+
+/// A batch in the valid prefix of a log file.
+pub struct BatchInfo {
+    pub position: u32,
+    pub size: u32,
+    pub base_offset: i64,
+    pub last_offset: i64,
+    pub base_timestamp: i64,
+    pub records: usize,
+}
+
+/// Checks every segment of a partition directory in offset order; the broker must not have
+/// the directory open. With `repair`, a log is truncated at its first bad batch, the
+/// segments after it are deleted since their offsets no longer follow, and indexes that
+/// disagree with the log are rebuilt from it.
+///
+/// `on_batch` sees every batch that passes its checks, for printing them.
+pub async fn verify_partition(
+    dir: impl AsRef<Path>,
+    repair: bool,
+    mut on_batch: impl FnMut(&BatchInfo),
+) -> Result<VerifyReport, String> {
+    let dir = dir.as_ref();
+    let mut report = VerifyReport::default();
+    let base_offsets = list_segments(dir, &mut report).await?;
+
+    let mut last_offset: Option<i64> = None;
+    let mut last_timestamp: Option<i64> = None;
+    let mut truncated = false;
+
+    for base_offset in base_offsets {
+        let log_path = segment_file_path(dir, base_offset, LOG_EXTENSION);
+        if truncated {
+            delete_segment(dir, base_offset).await?;
+            report.repairs.push(format!(
+                "Deleted segment {} after the truncated one",
+                log_path.display()
+            ));
+            continue;
+        }
+
+        let (batches, log_bytes, valid_bytes) = scan_log(
+            &log_path,
+            base_offset,
+            &mut last_offset,
+            &mut last_timestamp,
+            &mut report,
+        )
+        .await?;
+        batches.iter().for_each(&mut on_batch);
+
+        let index_ok = check_index(dir, base_offset, &batches, &mut report).await?;
+        let timeindex_ok = check_timeindex(dir, base_offset, &batches, &mut report).await?;
+
+        if repair {
+            if valid_bytes < log_bytes {
+                let file = OpenOptions::new()
+                    .write(true)
+                    .open(&log_path)
+                    .await
+                    .map_err(|e| format!("Failed to open {}: {}", log_path.display(), e))?;
+                file.set_len(valid_bytes)
+                    .await
+                    .map_err(|e| format!("Failed to truncate {}: {}", log_path.display(), e))?;
+                report.repairs.push(format!(
+                    "Truncated {} from {} to {} bytes",
+                    log_path.display(),
+                    log_bytes,
+                    valid_bytes
+                ));
+                truncated = true;
+            }
+            if truncated || !index_ok || !timeindex_ok {
+                rebuild_indexes(dir, base_offset, &batches).await?;
+                report.repairs.push(format!(
+                    "Rebuilt the indexes of segment {} from {} batches",
+                    base_offset,
+                    batches.len()
+                ));
+            }
+        }
+
+        report.segments.push(SegmentSummary {
+            base_offset,
+            batches: batches.len(),
+            records: batches.iter().map(|b| b.records).sum(),
+            first_offset: batches.first().map(|b| b.base_offset),
+            last_offset: batches.last().map(|b| b.last_offset),
+            log_bytes,
+            valid_bytes,
+        });
+    }
+
+    Ok(report)
+}
+
+/// Base offsets of the directory's log files, ascending. Index files without a log are
+/// reported, since the broker never reads them.
+async fn list_segments(dir: &Path, report: &mut VerifyReport) -> Result<Vec<i64>, String> {
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+
+    let mut logs = Vec::new();
+    let mut indexes = Vec::new();
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+    {
+        let path = entry.path();
+        let (Some(stem), Some(extension)) = (
+            path.file_stem().and_then(|s| s.to_str()),
+            path.extension().and_then(|e| e.to_str()),
+        ) else {
+            continue;
+        };
+        let Ok(base_offset) = stem.parse::<i64>() else {
+            continue;
+        };
+        if extension == LOG_EXTENSION {
+            logs.push(base_offset);
+        } else if extension == INDEX_EXTENSION || extension == TIMEINDEX_EXTENSION {
+            indexes.push((base_offset, path));
+        }
+    }
+
+    for (base_offset, path) in indexes {
+        if !logs.contains(&base_offset) {
+            report.warning(&path, "Index file without a log segment".to_string());
+        }
+    }
+
+    logs.sort_unstable();
+    Ok(logs)
+}
+
+/// Decodes the log batch by batch, stopping at the first one that is cut short, fails its
+/// CRC or does not continue the partition's offsets. Returns the batches before it, the
+/// file size and where the bad batch starts.
+async fn scan_log(
+    path: &Path,
+    base_offset: i64,
+    last_offset: &mut Option<i64>,
+    last_timestamp: &mut Option<i64>,
+    report: &mut VerifyReport,
+) -> Result<(Vec<BatchInfo>, u64, u64), String> {
+    let file = File::open(path)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let log_bytes = file
+        .metadata()
+        .await
+        .map_err(|e| format!("Failed to stat {}: {}", path.display(), e))?
+        .len();
+    let mut reader = BufReader::new(file);
+
+    let mut batches = Vec::new();
+    let mut position = 0u64;
+    while position < log_bytes {
+        let remaining = log_bytes - position;
+        if remaining < BATCH_HEADER_SIZE as u64 {
+            report.error(
+                path,
+                format!(
+                    "Batch header at position {} is cut short after {} bytes",
+                    position, remaining
+                ),
+            );
+            break;
+        }
+
+        let mut buf = BytesMut::zeroed(BATCH_HEADER_SIZE);
+        reader
+            .read_exact(&mut buf)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let batch_length = i32::from_be_bytes(buf[BATCH_LENGTH_OFFSET..].try_into().unwrap());
+        if batch_length < MIN_BATCH_LENGTH as i32 {
+            report.error(
+                path,
+                format!(
+                    "Batch at position {} has an invalid length of {}",
+                    position, batch_length
+                ),
+            );
+            break;
+        }
+        let size = BATCH_HEADER_SIZE as u64 + batch_length as u64;
+        if size > remaining {
+            report.error(
+                path,
+                format!(
+                    "Batch at position {} needs {} bytes but only {} remain",
+                    position, size, remaining
+                ),
+            );
+            break;
+        }
+
+        buf.resize(size as usize, 0);
+        reader
+            .read_exact(&mut buf[BATCH_HEADER_SIZE..])
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let batch = match RecordBatch::decode(&mut buf.freeze()) {
+            Ok(batch) => batch,
+            Err(e) => {
+                report.error(path, format!("Batch at position {}: {}", position, e));
+                break;
+            }
+        };
+
+        if let Err(e) = check_batch(&batch, base_offset, *last_offset) {
+            report.error(path, format!("Batch at position {}: {}", position, e));
+            break;
+        }
+        if let Some(previous) = *last_timestamp
+            && batch.base_timestamp < previous
+        {
+            report.warning(
+                path,
+                format!(
+                    "Batch at offset {} has timestamp {}, earlier than the previous {}",
+                    batch.base_offset, batch.base_timestamp, previous
+                ),
+            );
+        }
+
+        *last_offset = Some(batch.last_offset());
+        *last_timestamp = Some(batch.base_timestamp);
+        batches.push(BatchInfo {
+            position: position as u32,
+            size: size as u32,
+            base_offset: batch.base_offset,
+            last_offset: batch.last_offset(),
+            base_timestamp: batch.base_timestamp,
+            records: batch.records.len(),
+        });
+        position += size;
+    }
+
+    Ok((batches, log_bytes, position))
+}
+
+fn check_batch(
+    batch: &RecordBatch,
+    base_offset: i64,
+    last_offset: Option<i64>,
+) -> Result<(), String> {
+    if batch.magic != 2 {
+        return Err(format!("unsupported magic {}", batch.magic));
+    }
+    if batch.base_offset < base_offset {
+        return Err(format!(
+            "offset {} is below the segment's base offset {}",
+            batch.base_offset, base_offset
+        ));
+    }
+    if batch.base_offset - base_offset > i32::MAX as i64 {
+        return Err(format!(
+            "offset {} is too far past the segment's base offset {} to index",
+            batch.base_offset, base_offset
+        ));
+    }
+    if let Some(last_offset) = last_offset
+        && batch.base_offset <= last_offset
+    {
+        return Err(format!(
+            "offset {} does not follow the previous batch's last offset {}",
+            batch.base_offset, last_offset
+        ));
+    }
+    if batch.last_offset_delta < 0 {
+        return Err(format!(
+            "negative last offset delta {}",
+            batch.last_offset_delta
+        ));
+    }
+
+    let mut previous_delta = -1;
+    for record in &batch.records {
+        let delta = record.offset_delta.0;
+        if delta <= previous_delta || delta > batch.last_offset_delta {
+            return Err(format!(
+                "record offset delta {} is out of order (previous {}, last offset delta {})",
+                delta, previous_delta, batch.last_offset_delta
+            ));
+        }
+        previous_delta = delta;
+    }
+    Ok(())
+}
+
+/// Every entry must name the relative offset and position of a batch in the valid prefix,
+/// in ascending order. Returns whether the index needs no repair.
+async fn check_index(
+    dir: &Path,
+    base_offset: i64,
+    batches: &[BatchInfo],
+    report: &mut VerifyReport,
+) -> Result<bool, String> {
+    let path = segment_file_path(dir, base_offset, INDEX_EXTENSION);
+    let Some(buf) = read_index_file(&path, report).await? else {
+        return Ok(false);
+    };
+
+    let mut ok = true;
+    if buf.len() % IndexEntry::SIZE != 0 {
+        report.error(
+            &path,
+            format!(
+                "Size {} is not a multiple of the {}-byte entry",
+                buf.len(),
+                IndexEntry::SIZE
+            ),
+        );
+        ok = false;
+    }
+
+    let valid_bytes = batches.last().map_or(0, |b| b.position + b.size);
+    let mut previous: Option<i32> = None;
+    for (i, chunk) in buf.chunks_exact(IndexEntry::SIZE).enumerate() {
+        let entry = IndexEntry::decode(chunk);
+        if let Some(previous) = previous
+            && entry.relative_offset <= previous
+        {
+            report.error(
+                &path,
+                format!(
+                    "Entry {} has relative offset {}, not above the previous {}",
+                    i, entry.relative_offset, previous
+                ),
+            );
+            ok = false;
+        }
+        previous = Some(entry.relative_offset);
+
+        match batches.binary_search_by_key(&entry.physical_position, |b| b.position) {
+            Ok(found) => {
+                let expected = (batches[found].base_offset - base_offset) as i32;
+                if entry.relative_offset != expected {
+                    report.error(
+                        &path,
+                        format!(
+                            "Entry {} maps relative offset {} to position {}, where the batch \
+                             at relative offset {} starts",
+                            i, entry.relative_offset, entry.physical_position, expected
+                        ),
+                    );
+                    ok = false;
+                }
+            }
+            Err(_) => {
+                let problem = if entry.physical_position >= valid_bytes {
+                    "past the last valid batch"
+                } else {
+                    "not to the start of a batch"
+                };
+                report.error(
+                    &path,
+                    format!(
+                        "Entry {} points to position {}, {}",
+                        i, entry.physical_position, problem
+                    ),
+                );
+                ok = false;
+            }
+        }
+    }
+    Ok(ok)
+}
+
+/// Every entry must name a batch in the valid prefix by its relative offset and carry that
+/// batch's base timestamp, and there must be one for each batch whose base timestamp is not
+/// below the entry before it, which is what `Segment::append` writes.
+async fn check_timeindex(
+    dir: &Path,
+    base_offset: i64,
+    batches: &[BatchInfo],
+    report: &mut VerifyReport,
+) -> Result<bool, String> {
+    let path = segment_file_path(dir, base_offset, TIMEINDEX_EXTENSION);
+    let Some(buf) = read_index_file(&path, report).await? else {
+        return Ok(false);
+    };
+
+    let mut ok = true;
+    if buf.len() % TimeIndexEntry::SIZE != 0 {
+        report.error(
+            &path,
+            format!(
+                "Size {} is not a multiple of the {}-byte entry",
+                buf.len(),
+                TimeIndexEntry::SIZE
+            ),
+        );
+        ok = false;
+    }
+
+    let mut expected = indexed_by_time(batches).peekable();
+    for (i, chunk) in buf.chunks_exact(TimeIndexEntry::SIZE).enumerate() {
+        let entry = TimeIndexEntry::decode(chunk);
+        let offset = base_offset + entry.relative_offset as i64;
+        while let Some(batch) = expected.next_if(|batch| batch.base_offset < offset) {
+            report.error(
+                &path,
+                format!("No entry for the batch at offset {}", batch.base_offset),
+            );
+            ok = false;
+        }
+        let is_expected = expected
+            .next_if(|batch| batch.base_offset == offset)
+            .is_some();
+        match batches.binary_search_by_key(&offset, |b| b.base_offset) {
+            Ok(found) if batches[found].base_timestamp != entry.timestamp => {
+                report.error(
+                    &path,
+                    format!(
+                        "Entry {} has timestamp {} for offset {}, whose batch has {}",
+                        i, entry.timestamp, offset, batches[found].base_timestamp
+                    ),
+                );
+                ok = false;
+            }
+            Ok(_) if !is_expected => {
+                report.error(
+                    &path,
+                    format!(
+                        "Entry {} points to offset {}, whose timestamp is below the entry before it",
+                        i, offset
+                    ),
+                );
+                ok = false;
+            }
+            Ok(_) => {}
+            Err(_) => {
+                report.error(
+                    &path,
+                    format!(
+                        "Entry {} points to offset {}, where no valid batch starts",
+                        i, offset
+                    ),
+                );
+                ok = false;
+            }
+        }
+    }
+    for batch in expected {
+        report.error(
+            &path,
+            format!("No entry for the batch at offset {}", batch.base_offset),
+        );
+        ok = false;
+    }
+    Ok(ok)
+}
+
+/// The batches that get a time index entry: those whose base timestamp is not below the
+/// last one that got an entry.
+fn indexed_by_time(batches: &[BatchInfo]) -> impl Iterator<Item = &BatchInfo> {
+    let mut last_timestamp = i64::MIN;
+    batches.iter().filter(move |batch| {
+        let indexed = batch.base_timestamp >= last_timestamp;
+        if indexed {
+            last_timestamp = batch.base_timestamp;
+        }
+        indexed
+    })
+}
+
+async fn read_index_file(
+    path: &Path,
+    report: &mut VerifyReport,
+) -> Result<Option<Vec<u8>>, String> {
+    match tokio::fs::read(path).await {
+        Ok(buf) => Ok(Some(buf)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            report.error(path, "Missing".to_string());
+            Ok(None)
+        }
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Writes the entries `Segment::append` would for `batches` to both indexes.
+async fn rebuild_indexes(
+    dir: &Path,
+    base_offset: i64,
+    batches: &[BatchInfo],
+) -> Result<(), String> {
+    let mut index = BytesMut::with_capacity(batches.len() * IndexEntry::SIZE);
+    let mut timeindex = BytesMut::with_capacity(batches.len() * TimeIndexEntry::SIZE);
+    for batch in batches {
+        let relative_offset = (batch.base_offset - base_offset) as i32;
+        IndexEntry {
+            relative_offset,
+            physical_position: batch.position,
+        }
+        .encode(&mut index);
+    }
+    for batch in indexed_by_time(batches) {
+        TimeIndexEntry {
+            timestamp: batch.base_timestamp,
+            relative_offset: (batch.base_offset - base_offset) as i32,
+        }
+        .encode(&mut timeindex);
+    }
+
+    for (extension, buf) in [(INDEX_EXTENSION, index), (TIMEINDEX_EXTENSION, timeindex)] {
+        let path = segment_file_path(dir, base_offset, extension);
+        tokio::fs::write(&path, &buf)
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+async fn delete_segment(dir: &Path, base_offset: i64) -> Result<(), String> {
+    for extension in [LOG_EXTENSION, INDEX_EXTENSION, TIMEINDEX_EXTENSION] {
+        let path = segment_file_path(dir, base_offset, extension);
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to delete {}: {}", path.display(), e)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::driven::storage::segment::Segment;
+    use crate::core::domain::record::Record;
+
+    #[tokio::test]
+    async fn test_repair_truncates_at_corrupt_batch() {
+        let dir = std::env::temp_dir().join(format!("forge-verify-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+
+        let mut segment = Segment::new(&dir, 0).await.unwrap();
+        for offset in 0..3 {
+            let mut batch = RecordBatch::new(
+                1_000 + offset,
+                vec![Record::new(0, None, Some(b"v".to_vec()))],
+            );
+            batch.base_offset = offset;
+            segment.append(&batch).await.unwrap();
+        }
+        segment.flush().await.unwrap();
+        let second_batch = segment.current_size as u64 / 3;
+        drop(segment);
+
+        let report = verify_partition(&dir, false, |_| {}).await.unwrap();
+        assert!(report.issues.is_empty());
+        assert_eq!(report.segments[0].last_offset, Some(2));
+
+        // Flip the last byte of the second batch's payload so its CRC no longer matches.
+        let log_path = segment_file_path(&dir, 0, LOG_EXTENSION);
+        let mut log = tokio::fs::read(&log_path).await.unwrap();
+        log[2 * second_batch as usize - 1] ^= 0xFF;
+        tokio::fs::write(&log_path, &log).await.unwrap();
+
+        let report = verify_partition(&dir, true, |_| {}).await.unwrap();
+        assert!(report.has_errors());
+        assert_eq!(report.segments[0].valid_bytes, second_batch);
+
+        let report = verify_partition(&dir, false, |_| {}).await.unwrap();
+        assert!(report.issues.is_empty());
+        assert_eq!(report.segments[0].last_offset, Some(0));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
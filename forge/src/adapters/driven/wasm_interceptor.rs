@@ -0,0 +1,427 @@
+//! Record interceptors written as WebAssembly modules, run in an interpreter with fuel and
+//! memory limits so a plugin cannot stall or exhaust the broker.
+//!
+//! A module exports its `memory`, an `alloc(len: i32) -> i32` the broker writes its inputs
+//! through, and either or both of
+//!
+//! ```text
+//! on_produce(topic_ptr: i32, topic_len: i32, record_ptr: i32, record_len: i32) -> i64
+//! on_fetch(topic_ptr: i32, topic_len: i32, record_ptr: i32, record_len: i32) -> i64
+//! ```
+//!
+//! called once per record. A record is passed as little-endian i32 lengths followed by the
+//! bytes: key, value, the header count, then each header's key and value; -1 stands for a
+//! null key or value. The result is 0 to keep the record, -1 to refuse it (the whole batch on
+//! produce, the record alone on fetch), or `ptr << 32 | len` of a replacement record in the
+//! same layout. Each batch gets a fresh instance, so nothing carries over from one to the
+//! next and nothing the plugin allocates needs freeing. Hooks run on a worker thread the
+//! runtime has been told blocks, so a slow plugin does not hold up the tasks queued behind it.
+
+use std::path::Path;
+use tokio::runtime::{Handle, RuntimeFlavor};
+use wasmi::{Config, Engine, Linker, Memory, Module, Store, StoreLimits};
+use wasmi::{StoreLimitsBuilder, TypedFunc};
+
+use crate::application::record_interceptor::RecordInterceptor;
+use crate::core::domain::record::{Header, Record};
+use crate::core::domain::record_batch::RecordBatch;
+
+const KEEP: i64 = 0;
+const REFUSE: i64 = -1;
+const NULL_LENGTH: i32 = -1;
+
+type Hook = TypedFunc<(i32, i32, i32, i32), i64>;
+
+pub struct WasmInterceptor {
+    /// The module's file name, for errors.
+    name: String,
+    engine: Engine,
+    module: Module,
+    has_on_produce: bool,
+    has_on_fetch: bool,
+    /// Instructions, roughly, each call may run.
+    fuel: u64,
+    max_memory_bytes: usize,
+}
+
+impl WasmInterceptor {
+    /// Compiles the module at `path` and checks it exports what the broker calls.
+    pub fn load(path: &Path, fuel: u64, max_memory_bytes: usize) -> Result<Self, String> {
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into(),
+        );
+        let wasm = std::fs::read(path)
+            .map_err(|e| format!("Failed to read WASM plugin {}: {}", path.display(), e))?;
+        Self::new(name, &wasm, fuel, max_memory_bytes)
+    }
+
+    pub fn new(
+        name: String,
+        wasm: &[u8],
+        fuel: u64,
+        max_memory_bytes: usize,
+    ) -> Result<Self, String> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm)
+            .map_err(|e| format!("Invalid WASM plugin {}: {}", name, e))?;
+
+        let exports = |wanted: &str| module.exports().any(|export| export.name() == wanted);
+        for required in ["memory", "alloc"] {
+            if !exports(required) {
+                return Err(format!("WASM plugin {} does not export {}", name, required));
+            }
+        }
+        let has_on_produce = exports("on_produce");
+        let has_on_fetch = exports("on_fetch");
+        if !has_on_produce && !has_on_fetch {
+            return Err(format!(
+                "WASM plugin {} exports neither on_produce nor on_fetch",
+                name
+            ));
+        }
+
+        Ok(Self {
+            name,
+            engine,
+            module,
+            has_on_produce,
+            has_on_fetch,
+            fuel,
+            max_memory_bytes,
+        })
+    }
+
+    fn instantiate(&self, hook: &str) -> Result<Plugin, String> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.max_memory_bytes)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.fuel).map_err(|e| e.to_string())?;
+
+        let instance = Linker::new(&self.engine)
+            .instantiate(&mut store, &self.module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|e| self.error(e))?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| format!("WASM plugin {} exports no memory", self.name))?;
+        let alloc = instance
+            .get_typed_func(&store, "alloc")
+            .map_err(|e| self.error(e))?;
+        let hook = instance
+            .get_typed_func(&store, hook)
+            .map_err(|e| self.error(e))?;
+        Ok(Plugin {
+            store,
+            memory,
+            alloc,
+            hook,
+        })
+    }
+
+    /// Runs `hook` over each record of `batch`, refilling the fuel before every call, and
+    /// returns what became of them.
+    fn run(&self, hook: &str, topic: &str, batch: &RecordBatch) -> Result<Vec<Outcome>, String> {
+        let multi_thread = Handle::try_current()
+            .is_ok_and(|handle| handle.runtime_flavor() == RuntimeFlavor::MultiThread);
+        if multi_thread {
+            tokio::task::block_in_place(|| self.interpret(hook, topic, batch))
+        } else {
+            self.interpret(hook, topic, batch)
+        }
+    }
+
+    fn interpret(
+        &self,
+        hook: &str,
+        topic: &str,
+        batch: &RecordBatch,
+    ) -> Result<Vec<Outcome>, String> {
+        let mut plugin = self.instantiate(hook)?;
+        let (topic_ptr, topic_len) = plugin
+            .write(topic.as_bytes(), self.fuel)
+            .map_err(|e| self.error(e))?;
+        batch
+            .records
+            .iter()
+            .map(|record| {
+                let (record_ptr, record_len) = plugin
+                    .write(&encode_record(record), self.fuel)
+                    .map_err(|e| self.error(e))?;
+                plugin.set_fuel(self.fuel)?;
+                let result = plugin
+                    .hook
+                    .call(
+                        &mut plugin.store,
+                        (topic_ptr, topic_len, record_ptr, record_len),
+                    )
+                    .map_err(|e| self.error(e))?;
+                match result {
+                    KEEP => Ok(Outcome::Keep),
+                    REFUSE => Ok(Outcome::Refuse),
+                    result if result > 0 => {
+                        let ptr = (result >> 32) as usize;
+                        let len = (result & 0xFFFF_FFFF) as usize;
+                        let memory = plugin.memory.data(&plugin.store);
+                        let encoded = ptr
+                            .checked_add(len)
+                            .and_then(|end| memory.get(ptr..end))
+                            .ok_or_else(|| {
+                                format!(
+                                    "WASM plugin {} returned {} bytes at {}, past the end of its \
+                                     {} bytes of memory",
+                                    self.name,
+                                    len,
+                                    ptr,
+                                    memory.len()
+                                )
+                            })?;
+                        decode_record(encoded)
+                            .map(Outcome::Replace)
+                            .map_err(|e| format!("WASM plugin {} returned {}", self.name, e))
+                    }
+                    result => Err(format!(
+                        "WASM plugin {} returned {} from {}",
+                        self.name, result, hook
+                    )),
+                }
+            })
+            .collect()
+    }
+
+    fn error(&self, e: impl std::fmt::Display) -> String {
+        format!("WASM plugin {} failed: {}", self.name, e)
+    }
+}
+
+impl RecordInterceptor for WasmInterceptor {
+    fn on_produce(&self, topic: &str, batch: &mut RecordBatch) -> Result<(), String> {
+        if !self.has_on_produce {
+            return Ok(());
+        }
+        let outcomes = self.run("on_produce", topic, batch)?;
+        for (record, outcome) in batch.records.iter_mut().zip(outcomes) {
+            match outcome {
+                Outcome::Keep => {}
+                Outcome::Refuse => {
+                    return Err(format!(
+                        "WASM plugin {} refused the record at offset delta {}",
+                        self.name, record.offset_delta.0
+                    ));
+                }
+                Outcome::Replace(replacement) => replace(record, replacement),
+            }
+        }
+        Ok(())
+    }
+
+    fn on_fetch(&self, topic: &str, batch: &mut RecordBatch) -> Result<(), String> {
+        if !self.has_on_fetch {
+            return Ok(());
+        }
+        let outcomes = self.run("on_fetch", topic, batch)?;
+        let records = std::mem::take(&mut batch.records);
+        batch.records = records
+            .into_iter()
+            .zip(outcomes)
+            .filter_map(|(mut record, outcome)| match outcome {
+                Outcome::Keep => Some(record),
+                Outcome::Refuse => None,
+                Outcome::Replace(replacement) => {
+                    replace(&mut record, replacement);
+                    Some(record)
+                }
+            })
+            .collect();
+        Ok(())
+    }
+}
+
+enum Outcome {
+    Keep,
+    Refuse,
+    /// Key, value and headers; the offset and timestamp stay.
+    Replace(Record),
+}
+
+struct Plugin {
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    hook: Hook,
+}
+
+impl Plugin {
+    fn set_fuel(&mut self, fuel: u64) -> Result<(), String> {
+        self.store.set_fuel(fuel).map_err(|e| e.to_string())
+    }
+
+    /// Copies `bytes` into memory the plugin allocates; returns the pointer and length.
+    fn write(&mut self, bytes: &[u8], fuel: u64) -> Result<(i32, i32), String> {
+        let len = i32::try_from(bytes.len()).map_err(|_| "Input too large".to_string())?;
+        self.set_fuel(fuel)?;
+        let ptr = self
+            .alloc
+            .call(&mut self.store, len)
+            .map_err(|e| e.to_string())?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, bytes)
+            .map_err(|e| e.to_string())?;
+        Ok((ptr, len))
+    }
+}
+
+fn replace(record: &mut Record, replacement: Record) {
+    record.key = replacement.key;
+    record.value = replacement.value;
+    record.headers = replacement.headers;
+}
+
+fn encode_record(record: &Record) -> Vec<u8> {
+    let mut buf = Vec::new();
+    put_bytes(&mut buf, record.key.as_deref());
+    put_bytes(&mut buf, record.value.as_deref());
+    buf.extend((record.headers.len() as i32).to_le_bytes());
+    for header in &record.headers {
+        put_bytes(&mut buf, Some(header.key.as_bytes()));
+        put_bytes(&mut buf, header.value.as_deref());
+    }
+    buf
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: Option<&[u8]>) {
+    match bytes {
+        Some(bytes) => {
+            buf.extend((bytes.len() as i32).to_le_bytes());
+            buf.extend(bytes);
+        }
+        None => buf.extend(NULL_LENGTH.to_le_bytes()),
+    }
+}
+
+fn decode_record(mut buf: &[u8]) -> Result<Record, String> {
+    let key = take_bytes(&mut buf)?;
+    let value = take_bytes(&mut buf)?;
+    let mut record = Record::new(0, key, value);
+    let count = take_i32(&mut buf)?;
+    for _ in 0..count.max(0) {
+        let key = take_bytes(&mut buf)?.ok_or("a header without a key")?;
+        let key = String::from_utf8(key).map_err(|_| "a header key that is not UTF-8")?;
+        record.headers.push(Header {
+            key: key.into(),
+            value: take_bytes(&mut buf)?,
+        });
+    }
+    if !buf.is_empty() {
+        return Err(format!("a record with {} bytes too many", buf.len()));
+    }
+    Ok(record)
+}
+
+fn take_i32(buf: &mut &[u8]) -> Result<i32, String> {
+    let (int, rest) = buf.split_first_chunk().ok_or("a truncated record")?;
+    *buf = rest;
+    Ok(i32::from_le_bytes(*int))
+}
+
+fn take_bytes(buf: &mut &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let len = take_i32(buf)?;
+    if len == NULL_LENGTH {
+        return Ok(None);
+    }
+    let len = usize::try_from(len).map_err(|_| format!("a length of {}", len))?;
+    if buf.len() < len {
+        return Err("a truncated record".to_string());
+    }
+    let (bytes, rest) = buf.split_at(len);
+    *buf = rest;
+    Ok(Some(bytes.to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Drops fetched records with an empty value and hands the others back as replacements;
+    /// never returns from on_produce.
+    const PLUGIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func (export "on_fetch")
+            (param $topic i32) (param $topic_len i32) (param $record i32) (param $len i32)
+            (result i64)
+            (local $key_len i32)
+            (local.set $key_len (i32.load (local.get $record)))
+            (if (i32.lt_s (local.get $key_len) (i32.const 0))
+              (then (local.set $key_len (i32.const 0))))
+            (if (i32.eqz (i32.load (i32.add (i32.add (local.get $record) (i32.const 4))
+                                            (local.get $key_len))))
+              (then (return (i64.const -1))))
+            (i64.or (i64.shl (i64.extend_i32_u (local.get $record)) (i64.const 32))
+                    (i64.extend_i32_u (local.get $len))))
+          (func (export "on_produce") (param i32 i32 i32 i32) (result i64)
+            (loop $forever (br $forever))
+            (i64.const 0)))
+    "#;
+
+    #[test]
+    fn test_fetch_drops_records_and_produce_runs_out_of_fuel() {
+        let wasm = wat::parse_str(PLUGIN).unwrap();
+        let interceptor =
+            WasmInterceptor::new("test.wasm".into(), &wasm, 100_000, 1 << 20).unwrap();
+
+        let mut kept = Record::new(0, Some(b"k".to_vec()), Some(b"v".to_vec()));
+        kept.headers.push(Header {
+            key: "h".into(),
+            value: None,
+        });
+        let empty = Record::new(1, None, Some(vec![]));
+        let mut batch = RecordBatch::new(0, vec![kept.clone(), empty]);
+        interceptor.on_fetch("events", &mut batch).unwrap();
+        assert_eq!(batch.records, [kept]);
+
+        let error = interceptor.on_produce("events", &mut batch).unwrap_err();
+        assert!(error.contains("test.wasm"), "{}", error);
+
+        assert!(
+            WasmInterceptor::new(
+                "none.wasm".into(),
+                &wat::parse_str("(module)").unwrap(),
+                1,
+                1
+            )
+            .is_err()
+        );
+    }
+
+    /// Runs where the hook may block its worker thread.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_replacement_past_the_end_of_memory_is_an_error() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+              (memory (export "memory") 1)
+              (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+              (func (export "on_fetch") (param i32 i32 i32 i32) (result i64)
+                (i64.const 0x0000fff000001000)))
+            "#,
+        )
+        .unwrap();
+        let interceptor = WasmInterceptor::new("oob.wasm".into(), &wasm, 100_000, 1 << 20).unwrap();
+
+        let mut batch = RecordBatch::new(0, vec![Record::new(0, None, Some(b"v".to_vec()))]);
+        let error = interceptor.on_fetch("events", &mut batch).unwrap_err();
+        assert!(error.contains("past the end"), "{}", error);
+    }
+}
@@ -1 +1,11 @@
+pub mod admin_server;
+pub mod connection_quotas;
+pub mod connection_registry;
+pub mod frame_codec;
+pub mod mqtt_bridge;
+pub mod proxy_protocol;
+pub mod request_dispatcher;
+pub mod request_metrics;
+pub mod rest_proxy;
+pub mod sasl_authenticator;
 pub mod tcp_server;
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::adapters::driving::connection_registry::ConnectionRegistry;
+use crate::shared::logging::LogLevelHandle;
+use crate::shared::metrics::{MetricsSource, MetricsWriter};
+use crate::shared::scheduler::spawn_named;
+
+/// Larger request heads are refused; the admin endpoints take no parameters.
+const MAX_REQUEST_HEAD_BYTES: usize = 8 * 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A plain-HTTP endpoint for operators, on its own listener:
+/// - `GET /metrics`: every `MetricsSource` in the Prometheus text format
+/// - `GET /connections`: the open client connections, busiest first
+/// - `GET /loggers`: the active log filter, including logger levels set through AlterConfigs
+///
+/// Each request gets one response and the connection is closed.
+pub struct AdminServer {
+    connections: Arc<ConnectionRegistry>,
+    metrics: Vec<Arc<dyn MetricsSource>>,
+    log_level: LogLevelHandle,
+}
+
+impl AdminServer {
+    pub fn new(
+        connections: Arc<ConnectionRegistry>,
+        metrics: Vec<Arc<dyn MetricsSource>>,
+        log_level: LogLevelHandle,
+    ) -> Self {
+        Self {
+            connections,
+            metrics,
+            log_level,
+        }
+    }
+
+    /// Binds `address` and serves from a background task until `cancel_token` is cancelled.
+    pub async fn start(
+        self,
+        address: &str,
+        cancel_token: CancellationToken,
+    ) -> Result<JoinHandle<()>, String> {
+        let listener = TcpListener::bind(address)
+            .await
+            .map_err(|e| format!("Failed to bind admin listener {}: {}", address, e))?;
+        tracing::info!("Admin server started on {}", address);
+
+        let server = Arc::new(self);
+        Ok(spawn_named("admin-server", async move {
+            loop {
+                tokio::select! {
+                    accept_result = listener.accept() => match accept_result {
+                        Ok((socket, peer_address)) => {
+                            let server = server.clone();
+                            spawn_named("admin-request", async move {
+                                let served = tokio::time::timeout(
+                                    REQUEST_TIMEOUT,
+                                    server.serve(socket),
+                                )
+                                .await;
+                                match served {
+                                    Ok(Ok(())) => {}
+                                    Ok(Err(e)) => tracing::warn!(
+                                        "Admin request from {} failed: {}",
+                                        peer_address,
+                                        e
+                                    ),
+                                    Err(_) => tracing::warn!(
+                                        "Admin request from {} timed out",
+                                        peer_address
+                                    ),
+                                }
+                            });
+                        }
+                        Err(e) => tracing::error!("Failed to accept admin connection: {}", e),
+                    },
+                    _ = cancel_token.cancelled() => break,
+                }
+            }
+        }))
+    }
+
+    async fn serve(&self, mut socket: TcpStream) -> Result<(), String> {
+        let head = Self::read_request_head(&mut socket).await?;
+        let request_line = head.lines().next().unwrap_or_default();
+        let mut parts = request_line.split_whitespace();
+        let (status, body) = match (parts.next(), parts.next()) {
+            (Some("GET"), Some("/metrics")) => ("200 OK", self.render_metrics().await),
+            (Some("GET"), Some("/connections")) => ("200 OK", self.connections.describe()),
+            (Some("GET"), Some("/loggers")) => {
+                ("200 OK", format!("{}\n", self.log_level.directives()))
+            }
+            (Some("GET"), Some(_)) => ("404 Not Found", "Not found\n".to_string()),
+            _ => (
+                "405 Method Not Allowed",
+                "Only GET is supported\n".to_string(),
+            ),
+        };
+
+        let response = format!(
+            "HTTP/1.1 {}\r\n\
+             Content-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        socket
+            .write_all(response.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        socket.shutdown().await.map_err(|e| e.to_string())
+    }
+
+    /// Reads up to the blank line ending the request head; any body is ignored.
+    async fn read_request_head(socket: &mut TcpStream) -> Result<String, String> {
+        let mut head = Vec::new();
+        let mut buffer = [0u8; 1024];
+        while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+            if head.len() > MAX_REQUEST_HEAD_BYTES {
+                return Err("Request head too large".to_string());
+            }
+            let read = socket.read(&mut buffer).await.map_err(|e| e.to_string())?;
+            if read == 0 {
+                return Err("Connection closed before the request was complete".to_string());
+            }
+            head.extend_from_slice(&buffer[..read]);
+        }
+        String::from_utf8(head).map_err(|e| e.to_string())
+    }
+
+    async fn render_metrics(&self) -> String {
+        let mut metrics = MetricsWriter::new();
+        for source in &self.metrics {
+            source.write_metrics(&mut metrics).await;
+        }
+        metrics.finish()
+    }
+}
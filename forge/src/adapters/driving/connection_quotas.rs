@@ -0,0 +1,306 @@
+use async_trait::async_trait;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::SocketConfig;
+use crate::shared::collections::FlatMap;
+use crate::shared::metrics::{MetricType, MetricsSource, MetricsWriter};
+
+/// Holds up to one second's worth of tokens, refilled at `rate` per second.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u32, now: Instant) -> Self {
+        Self {
+            rate: rate as f64,
+            tokens: rate as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.updated = now;
+    }
+
+    fn try_take(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+
+    /// Takes a token even when none is left, and returns how long until the bucket is out of
+    /// debt again.
+    fn take(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+
+    fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.rate
+    }
+}
+
+#[derive(Debug, Default)]
+struct ConnectionCounts {
+    total: usize,
+    per_ip: FlatMap<IpAddr, usize>,
+    /// Only IPs that connected recently: full buckets are dropped.
+    creation_rate_per_ip: FlatMap<IpAddr, TokenBucket>,
+}
+
+/// Caps open connections overall (max.connections) and per client IP
+/// (max.connections.per.ip), and how fast new ones are accepted
+/// (max.connection.creation.rate and max.connection.creation.rate.per.ip). It also counts what
+/// it turned away.
+#[derive(Debug)]
+pub struct ConnectionQuotas {
+    max_connections: usize,
+    max_connections_per_ip: usize,
+    max_connection_creation_rate_per_ip: u32,
+    /// A std mutex: permits release their slot on drop, which cannot await.
+    counts: Mutex<ConnectionCounts>,
+    creation_rate: Mutex<TokenBucket>,
+    rejected_total: AtomicU64,
+    rejected_per_ip: AtomicU64,
+    rejected_rate_per_ip: AtomicU64,
+    accept_throttle_ms: AtomicU64,
+}
+
+/// Holds a connection slot until dropped.
+#[derive(Debug)]
+pub struct ConnectionPermit {
+    quotas: Arc<ConnectionQuotas>,
+    ip: IpAddr,
+}
+
+impl ConnectionQuotas {
+    pub fn new(config: &SocketConfig) -> Self {
+        Self {
+            max_connections: config.max_connections,
+            max_connections_per_ip: config.max_connections_per_ip,
+            max_connection_creation_rate_per_ip: config.max_connection_creation_rate_per_ip,
+            counts: Mutex::new(ConnectionCounts::default()),
+            creation_rate: Mutex::new(TokenBucket::new(
+                config.max_connection_creation_rate,
+                Instant::now(),
+            )),
+            rejected_total: AtomicU64::new(0),
+            rejected_per_ip: AtomicU64::new(0),
+            rejected_rate_per_ip: AtomicU64::new(0),
+            accept_throttle_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// Records an accepted connection against max.connection.creation.rate and returns how
+    /// long the listener should wait before accepting the next one.
+    pub fn record_connection_creation(&self) -> Duration {
+        let delay = self
+            .creation_rate
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take(Instant::now());
+        self.accept_throttle_ms
+            .fetch_add(delay.as_millis() as u64, Ordering::Relaxed);
+        delay
+    }
+
+    /// Takes a slot for a new connection from `ip`, or explains which limit it hit.
+    pub fn try_acquire(self: &Arc<Self>, ip: IpAddr) -> Result<ConnectionPermit, String> {
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        if !self.take_creation_token(&mut counts, ip, Instant::now()) {
+            self.rejected_rate_per_ip.fetch_add(1, Ordering::Relaxed);
+            return Err(format!(
+                "max.connection.creation.rate.per.ip {} reached for {}",
+                self.max_connection_creation_rate_per_ip, ip
+            ));
+        }
+        if counts.total >= self.max_connections {
+            self.rejected_total.fetch_add(1, Ordering::Relaxed);
+            return Err(format!("max.connections {} reached", self.max_connections));
+        }
+        let ip_count = counts.per_ip.get(&ip).copied().unwrap_or(0);
+        if ip_count >= self.max_connections_per_ip {
+            self.rejected_per_ip.fetch_add(1, Ordering::Relaxed);
+            return Err(format!(
+                "max.connections.per.ip {} reached for {}",
+                self.max_connections_per_ip, ip
+            ));
+        }
+
+        counts.total += 1;
+        counts.per_ip.insert(ip, ip_count + 1);
+        Ok(ConnectionPermit {
+            quotas: self.clone(),
+            ip,
+        })
+    }
+
+    fn take_creation_token(&self, counts: &mut ConnectionCounts, ip: IpAddr, now: Instant) -> bool {
+        let idle: Vec<_> = counts
+            .creation_rate_per_ip
+            .iter_mut()
+            .filter_map(|(ip, bucket)| bucket.is_full(now).then_some(*ip))
+            .collect();
+        for ip in idle {
+            counts.creation_rate_per_ip.remove(&ip);
+        }
+
+        let rate = self.max_connection_creation_rate_per_ip;
+        if counts.creation_rate_per_ip.get(&ip).is_none() {
+            counts
+                .creation_rate_per_ip
+                .insert(ip, TokenBucket::new(rate, now));
+        }
+        counts
+            .creation_rate_per_ip
+            .get_mut(&ip)
+            .is_some_and(|bucket| bucket.try_take(now))
+    }
+
+    fn release(&self, ip: IpAddr) {
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        counts.total = counts.total.saturating_sub(1);
+        match counts.per_ip.get(&ip).copied() {
+            Some(count) if count > 1 => {
+                counts.per_ip.insert(ip, count - 1);
+            }
+            _ => {
+                counts.per_ip.remove(&ip);
+            }
+        }
+    }
+
+    pub fn connection_count(&self) -> usize {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner()).total
+    }
+
+    pub fn connection_count_for(&self, ip: IpAddr) -> usize {
+        let counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        counts.per_ip.get(&ip).copied().unwrap_or(0)
+    }
+
+    /// Connections closed because max.connections was reached.
+    pub fn rejected_total(&self) -> u64 {
+        self.rejected_total.load(Ordering::Relaxed)
+    }
+
+    /// Connections closed because their IP was at max.connections.per.ip.
+    pub fn rejected_per_ip(&self) -> u64 {
+        self.rejected_per_ip.load(Ordering::Relaxed)
+    }
+
+    /// Connections closed because their IP connected faster than
+    /// max.connection.creation.rate.per.ip.
+    pub fn rejected_rate_per_ip(&self) -> u64 {
+        self.rejected_rate_per_ip.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl MetricsSource for ConnectionQuotas {
+    async fn write_metrics(&self, metrics: &mut MetricsWriter) {
+        let name = "forge_connections_rejected_total";
+        metrics.family(
+            name,
+            MetricType::Counter,
+            "Connections closed on accept because a connection limit was reached.",
+        );
+        metrics.sample(
+            name,
+            &[("limit", "max.connections")],
+            self.rejected_total() as f64,
+        );
+        metrics.sample(
+            name,
+            &[("limit", "max.connections.per.ip")],
+            self.rejected_per_ip() as f64,
+        );
+        metrics.sample(
+            name,
+            &[("limit", "max.connection.creation.rate.per.ip")],
+            self.rejected_rate_per_ip() as f64,
+        );
+        metrics.single(
+            "forge_connection_accept_throttle_seconds_total",
+            MetricType::Counter,
+            "Time the listener stopped accepting to stay under max.connection.creation.rate.",
+            self.accept_throttle_ms.load(Ordering::Relaxed) as f64 / 1000.0,
+        );
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        self.quotas.release(self.ip);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_connections_overall_and_per_ip() {
+        let quotas = Arc::new(ConnectionQuotas::new(&SocketConfig {
+            max_connections: 3,
+            max_connections_per_ip: 2,
+            ..SocketConfig::default()
+        }));
+        let first: IpAddr = "10.0.0.1".parse().unwrap();
+        let second: IpAddr = "10.0.0.2".parse().unwrap();
+
+        let a = quotas.try_acquire(first).unwrap();
+        let _b = quotas.try_acquire(first).unwrap();
+        assert!(quotas.try_acquire(first).is_err());
+        let _c = quotas.try_acquire(second).unwrap();
+        assert!(quotas.try_acquire(second).is_err());
+        assert_eq!(quotas.rejected_per_ip(), 1);
+        assert_eq!(quotas.rejected_total(), 1);
+
+        drop(a);
+        assert_eq!(quotas.connection_count(), 2);
+        assert_eq!(quotas.connection_count_for(first), 1);
+        assert!(quotas.try_acquire(first).is_ok());
+    }
+
+    #[test]
+    fn test_limits_connection_creation_rate_per_ip() {
+        let quotas = Arc::new(ConnectionQuotas::new(&SocketConfig {
+            max_connection_creation_rate_per_ip: 2,
+            ..SocketConfig::default()
+        }));
+        let first: IpAddr = "10.0.0.1".parse().unwrap();
+        let second: IpAddr = "10.0.0.2".parse().unwrap();
+
+        // Closing connections does not give their tokens back
+        drop(quotas.try_acquire(first).unwrap());
+        drop(quotas.try_acquire(first).unwrap());
+        assert!(quotas.try_acquire(first).is_err());
+        assert!(quotas.try_acquire(second).is_ok());
+        assert_eq!(quotas.rejected_rate_per_ip(), 1);
+
+        let mut bucket = TokenBucket::new(2, Instant::now());
+        let now = Instant::now();
+        assert_eq!(bucket.take(now), Duration::ZERO);
+        assert_eq!(bucket.take(now), Duration::ZERO);
+        assert!(bucket.take(now) > Duration::from_millis(400));
+    }
+}
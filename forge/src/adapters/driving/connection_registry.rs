@@ -0,0 +1,231 @@
+use async_trait::async_trait;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::core::domain::principal::KafkaPrincipal;
+use crate::shared::collections::FlatMap;
+use crate::shared::metrics::{MetricType, MetricsSource, MetricsWriter};
+
+/// Traffic and identity of one open connection, updated by its reader, handler and writer.
+#[derive(Debug)]
+pub struct ConnectionStats {
+    /// `<peer address>-<sequence number>`, unique for the life of the broker.
+    pub id: String,
+    pub peer_address: SocketAddr,
+    started: Instant,
+    bytes_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    requests: AtomicU64,
+    /// A std mutex: held only to copy the value in or out.
+    identity: Mutex<ConnectionIdentity>,
+}
+
+#[derive(Debug, Clone)]
+struct ConnectionIdentity {
+    client_id: String,
+    principal: KafkaPrincipal,
+}
+
+impl ConnectionStats {
+    pub fn record_received(&self, bytes: usize) {
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_sent(&self, bytes: usize) {
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Counts a request and remembers who sent it; the client id may differ per request.
+    pub fn record_request(&self, client_id: &str, principal: &KafkaPrincipal) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let mut identity = self.identity.lock().unwrap_or_else(|e| e.into_inner());
+        if identity.client_id != client_id {
+            identity.client_id = client_id.to_string();
+        }
+        if identity.principal != *principal {
+            identity.principal = principal.clone();
+        }
+    }
+
+    pub fn snapshot(&self) -> ConnectionSnapshot {
+        let identity = self
+            .identity
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        ConnectionSnapshot {
+            id: self.id.clone(),
+            peer_address: self.peer_address,
+            client_id: identity.client_id,
+            principal: identity.principal,
+            age: self.started.elapsed(),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            requests: self.requests.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectionSnapshot {
+    pub id: String,
+    pub peer_address: SocketAddr,
+    pub client_id: String,
+    pub principal: KafkaPrincipal,
+    pub age: Duration,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    pub requests: u64,
+}
+
+/// The open connections, for metrics and the admin connection list.
+#[derive(Debug, Default)]
+pub struct ConnectionRegistry {
+    next_sequence: AtomicU64,
+    /// A std mutex: registrations are removed on drop, which cannot await.
+    connections: Mutex<FlatMap<u64, Arc<ConnectionStats>>>,
+}
+
+/// Keeps a connection listed until dropped.
+#[derive(Debug)]
+pub struct ConnectionRegistration {
+    registry: Arc<ConnectionRegistry>,
+    sequence: u64,
+    pub stats: Arc<ConnectionStats>,
+}
+
+impl ConnectionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(self: &Arc<Self>, peer_address: SocketAddr) -> ConnectionRegistration {
+        let sequence = self.next_sequence.fetch_add(1, Ordering::Relaxed);
+        let stats = Arc::new(ConnectionStats {
+            id: format!("{}-{}", peer_address, sequence),
+            peer_address,
+            started: Instant::now(),
+            bytes_received: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            requests: AtomicU64::new(0),
+            identity: Mutex::new(ConnectionIdentity {
+                client_id: String::new(),
+                principal: KafkaPrincipal::anonymous(),
+            }),
+        });
+        self.connections
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(sequence, stats.clone());
+        ConnectionRegistration {
+            registry: self.clone(),
+            sequence,
+            stats,
+        }
+    }
+
+    /// Every open connection, busiest (most bytes in and out) first.
+    pub fn snapshot(&self) -> Vec<ConnectionSnapshot> {
+        let mut snapshots: Vec<_> = self
+            .connections
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .map(|stats| stats.snapshot())
+            .collect();
+        snapshots.sort_by_key(|snapshot| {
+            std::cmp::Reverse(snapshot.bytes_received + snapshot.bytes_sent)
+        });
+        snapshots
+    }
+
+    /// The connection list as a table, one connection per line.
+    pub fn describe(&self) -> String {
+        let mut output =
+            "ID\tCLIENT-ID\tPRINCIPAL\tAGE-MS\tREQUESTS\tBYTES-IN\tBYTES-OUT\n".to_string();
+        for connection in self.snapshot() {
+            let _ = writeln!(
+                output,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                connection.id,
+                if connection.client_id.is_empty() {
+                    "-"
+                } else {
+                    &connection.client_id
+                },
+                connection.principal,
+                connection.age.as_millis(),
+                connection.requests,
+                connection.bytes_received,
+                connection.bytes_sent
+            );
+        }
+        output
+    }
+}
+
+#[async_trait]
+impl MetricsSource for ConnectionRegistry {
+    async fn write_metrics(&self, metrics: &mut MetricsWriter) {
+        let connections = self.snapshot();
+        metrics.single(
+            "forge_connections",
+            MetricType::Gauge,
+            "Open client connections.",
+            connections.len() as f64,
+        );
+
+        // One series per connection: max.connections bounds how many there are
+        let per_connection: [(&str, &str, fn(&ConnectionSnapshot) -> f64); 4] = [
+            (
+                "forge_connection_received_bytes",
+                "Bytes read from the connection.",
+                |c| c.bytes_received as f64,
+            ),
+            (
+                "forge_connection_sent_bytes",
+                "Bytes written to the connection.",
+                |c| c.bytes_sent as f64,
+            ),
+            (
+                "forge_connection_requests",
+                "Requests read from the connection.",
+                |c| c.requests as f64,
+            ),
+            (
+                "forge_connection_age_seconds",
+                "Time since the connection was accepted.",
+                |c| c.age.as_secs_f64(),
+            ),
+        ];
+        for (name, help, value) in per_connection {
+            metrics.family(name, MetricType::Gauge, help);
+            for connection in &connections {
+                let principal = connection.principal.to_string();
+                metrics.sample(
+                    name,
+                    &[
+                        ("connection", &connection.id),
+                        ("client_id", &connection.client_id),
+                        ("principal", &principal),
+                    ],
+                    value(connection),
+                );
+            }
+        }
+    }
+}
+
+impl Drop for ConnectionRegistration {
+    fn drop(&mut self) {
+        self.registry
+            .connections
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.sequence);
+    }
+}
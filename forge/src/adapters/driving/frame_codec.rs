@@ -0,0 +1,340 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::collections::VecDeque;
+use std::io::{self, IoSlice};
+use std::sync::{Arc, Mutex};
+use tokio::time::Instant;
+use tokio_util::codec::Decoder;
+
+use crate::protocol::response::{ResponseBody, ResponseHeader};
+use crate::shared::buffer_pool;
+use crate::shared::collections::FlatMap;
+
+pub const SIZE_PREFIX_LENGTH: usize = 4;
+/// API key, API version and correlation id: enough to answer a request that is not read.
+const HEADER_PREFIX_LENGTH: usize = 8;
+
+/// A decoded request frame.
+#[derive(Debug, PartialEq)]
+pub enum Frame {
+    /// The whole request, header included.
+    Request(BytesMut),
+    /// A request over its size limit, answered from its header alone. Its body is skipped as
+    /// it arrives rather than buffered, so the connection can go on.
+    TooLarge {
+        api_key: i16,
+        api_version: i16,
+        correlation_id: i32,
+        size: u32,
+        limit: u32,
+    },
+}
+
+/// When the frame being read started to arrive, shared by a connection's codec and reader so
+/// the reader can time out peers that stall mid-frame.
+#[derive(Debug, Clone, Default)]
+pub struct PartialFrame {
+    /// A std mutex: held only to copy the value in or out.
+    started: Arc<Mutex<Option<Instant>>>,
+}
+
+impl PartialFrame {
+    fn update(&self, mid_frame: bool) {
+        let mut started = self.started.lock().unwrap_or_else(|e| e.into_inner());
+        if !mid_frame {
+            *started = None;
+        } else if started.is_none() {
+            *started = Some(Instant::now());
+        }
+    }
+
+    /// `None` between frames.
+    pub fn started(&self) -> Option<Instant> {
+        *self.started.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Restarts the clock of a frame being read, for when reading it was paused on purpose.
+    pub fn restart(&self) {
+        let mut started = self.started.lock().unwrap_or_else(|e| e.into_inner());
+        if started.is_some() {
+            *started = Some(Instant::now());
+        }
+    }
+}
+
+/// Splits the connection into size-prefixed Kafka request frames and writes size-prefixed
+/// responses. Each decoded frame is split off the read buffer, so no per-frame copy is made.
+#[derive(Debug, Clone)]
+pub struct FrameCodec {
+    max_frame_size: u32,
+    /// Stricter limits for some API keys.
+    max_frame_size_per_api: FlatMap<i16, u32>,
+    /// Bytes of a too large frame still to be skipped.
+    discarding: usize,
+    partial_frame: PartialFrame,
+}
+
+impl FrameCodec {
+    pub fn new(max_frame_size: u32, max_frame_size_per_api: FlatMap<i16, u32>) -> Self {
+        Self {
+            max_frame_size,
+            max_frame_size_per_api,
+            discarding: 0,
+            partial_frame: PartialFrame::default(),
+        }
+    }
+
+    pub fn partial_frame(&self) -> PartialFrame {
+        self.partial_frame.clone()
+    }
+
+    fn discard(&mut self, src: &mut BytesMut) {
+        let skipped = self.discarding.min(src.len());
+        src.advance(skipped);
+        self.discarding -= skipped;
+    }
+
+    fn decode_frame(&mut self, src: &mut BytesMut) -> Result<Option<Frame>, io::Error> {
+        self.discard(src);
+        if self.discarding > 0 || src.len() < SIZE_PREFIX_LENGTH {
+            return Ok(None);
+        }
+
+        let size = u32::from_be_bytes([src[0], src[1], src[2], src[3]]);
+        if size < HEADER_PREFIX_LENGTH as u32 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Request size {} is too small for a request header", size),
+            ));
+        }
+        if size > self.max_frame_size
+            || self
+                .max_frame_size_per_api
+                .values()
+                .any(|limit| size > *limit)
+        {
+            // The API key decides which limit applies
+            if src.len() < SIZE_PREFIX_LENGTH + HEADER_PREFIX_LENGTH {
+                return Ok(None);
+            }
+            let mut header = &src[SIZE_PREFIX_LENGTH..SIZE_PREFIX_LENGTH + HEADER_PREFIX_LENGTH];
+            let api_key = header.get_i16();
+            let limit = self
+                .max_frame_size_per_api
+                .get(&api_key)
+                .map_or(self.max_frame_size, |limit| {
+                    (*limit).min(self.max_frame_size)
+                });
+            if size > limit {
+                let frame = Frame::TooLarge {
+                    api_key,
+                    api_version: header.get_i16(),
+                    correlation_id: header.get_i32(),
+                    size,
+                    limit,
+                };
+                src.advance(SIZE_PREFIX_LENGTH);
+                self.discarding = size as usize;
+                self.discard(src);
+                return Ok(Some(frame));
+            }
+        }
+
+        let frame_length = SIZE_PREFIX_LENGTH + size as usize;
+        if src.len() < frame_length {
+            // Room for the rest of the frame, so the next read need not grow the buffer
+            src.reserve(frame_length - src.len());
+            return Ok(None);
+        }
+
+        src.advance(SIZE_PREFIX_LENGTH);
+        Ok(Some(Frame::Request(src.split_to(size as usize))))
+    }
+}
+
+impl Decoder for FrameCodec {
+    type Item = Frame;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let frame = self.decode_frame(src)?;
+        self.partial_frame
+            .update(frame.is_none() && (self.discarding > 0 || !src.is_empty()));
+        Ok(frame)
+    }
+}
+
+/// Response frames waiting to be written, readable as one `Buf`. Encoded fields are copied
+/// into a shared buffer while the record chunks of a body stay where they are, so writing the
+/// frames out copies no records and takes a few vectored writes.
+#[derive(Default)]
+pub struct OutgoingFrames {
+    buf: BytesMut,
+    /// Written before `buf`.
+    chunks: VecDeque<Bytes>,
+    chunks_len: usize,
+}
+
+impl OutgoingFrames {
+    /// Queues a frame and returns its size, size prefix included.
+    pub fn push(&mut self, header: ResponseHeader, body: ResponseBody) -> usize {
+        let size = size_of::<i32>() + body.len();
+        self.buf
+            .reserve(SIZE_PREFIX_LENGTH + size_of::<i32>() + body.buf.len());
+        self.buf.put_u32(size as u32);
+        header.encode(&mut self.buf);
+
+        let mut copied = 0;
+        for (at, chunk) in body.chunks {
+            self.buf.put_slice(&body.buf[copied..at]);
+            copied = at;
+            if !self.buf.is_empty() {
+                let fields = self.buf.split().freeze();
+                self.push_chunk(fields);
+            }
+            self.push_chunk(chunk);
+        }
+        self.buf.put_slice(&body.buf[copied..]);
+        buffer_pool::recycle(body.buf);
+        SIZE_PREFIX_LENGTH + size
+    }
+
+    fn push_chunk(&mut self, chunk: Bytes) {
+        self.chunks_len += chunk.len();
+        self.chunks.push_back(chunk);
+    }
+}
+
+impl Buf for OutgoingFrames {
+    fn remaining(&self) -> usize {
+        self.chunks_len + self.buf.len()
+    }
+
+    fn chunk(&self) -> &[u8] {
+        self.chunks
+            .front()
+            .map_or(&self.buf[..], |chunk| &chunk[..])
+    }
+
+    fn chunks_vectored<'a>(&'a self, dst: &mut [IoSlice<'a>]) -> usize {
+        let slices = self
+            .chunks
+            .iter()
+            .map(|chunk| &chunk[..])
+            .chain(Some(&self.buf[..]).filter(|buf| !buf.is_empty()));
+        let mut filled = 0;
+        for (slot, slice) in dst.iter_mut().zip(slices) {
+            *slot = IoSlice::new(slice);
+            filled += 1;
+        }
+        filled
+    }
+
+    fn advance(&mut self, mut cnt: usize) {
+        while let Some(chunk) = self.chunks.front_mut() {
+            if cnt < chunk.len() {
+                chunk.advance(cnt);
+                self.chunks_len -= cnt;
+                return;
+            }
+            cnt -= chunk.len();
+            self.chunks_len -= chunk.len();
+            self.chunks.pop_front();
+        }
+        self.buf.advance(cnt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decodes_frames_split_across_reads() {
+        let mut codec = FrameCodec::new(16, FlatMap::new());
+        let mut src = BytesMut::new();
+        src.put_u32(9);
+        src.put_slice(b"abcdefgh");
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        assert!(codec.partial_frame().started().is_some());
+
+        src.put_slice(b"i");
+        src.put_u32(8);
+        assert_eq!(
+            codec.decode(&mut src).unwrap(),
+            Some(Frame::Request(BytesMut::from(&b"abcdefghi"[..])))
+        );
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        src.put_slice(b"jklmnopq");
+        assert_eq!(
+            codec.decode(&mut src).unwrap(),
+            Some(Frame::Request(BytesMut::from(&b"jklmnopq"[..])))
+        );
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        assert!(codec.partial_frame().started().is_none());
+
+        src.put_u32(7);
+        assert!(codec.decode(&mut src).is_err());
+    }
+
+    #[test]
+    fn test_skips_frames_over_the_limit_of_their_api() {
+        let mut per_api = FlatMap::new();
+        per_api.insert(3, 10);
+        let mut codec = FrameCodec::new(16, per_api);
+        let mut src = BytesMut::new();
+        // Within the overall limit, over the limit for API key 3
+        src.put_u32(12);
+        src.put_i16(3);
+        src.put_i16(1);
+        src.put_i32(42);
+        src.put_slice(b"ab");
+        assert_eq!(
+            codec.decode(&mut src).unwrap(),
+            Some(Frame::TooLarge {
+                api_key: 3,
+                api_version: 1,
+                correlation_id: 42,
+                size: 12,
+                limit: 10,
+            })
+        );
+
+        // The rest of the body is skipped, and the next frame read as usual
+        src.put_slice(b"cd");
+        src.put_u32(12);
+        src.put_i16(0);
+        src.put_slice(b"0123456789");
+        assert!(matches!(
+            codec.decode(&mut src).unwrap(),
+            Some(Frame::Request(frame)) if frame.len() == 12
+        ));
+    }
+
+    #[test]
+    fn test_outgoing_frames_chain_record_chunks_between_fields() {
+        let mut frames = OutgoingFrames::default();
+        let plain = BytesMut::from(&b"ab"[..]);
+        assert_eq!(
+            frames.push(ResponseHeader { correlation_id: 1 }, plain.into()),
+            10
+        );
+
+        let mut body = ResponseBody::from(BytesMut::from(&b"cdef"[..]));
+        body.chunks.push((1, Bytes::from_static(b"XY")));
+        body.chunks.push((3, Bytes::from_static(b"Z")));
+        assert_eq!(frames.push(ResponseHeader { correlation_id: 2 }, body), 15);
+
+        let mut slices = [IoSlice::new(&[]); 8];
+        assert_eq!(frames.chunks_vectored(&mut slices), 5);
+
+        let mut expected = BytesMut::new();
+        expected.put_u32(6);
+        expected.put_i32(1);
+        expected.put_slice(b"ab");
+        expected.put_u32(11);
+        expected.put_i32(2);
+        expected.put_slice(b"cXYdeZf");
+        frames.advance(3);
+        assert_eq!(frames.copy_to_bytes(frames.remaining()), expected[3..]);
+    }
+}
@@ -0,0 +1,563 @@
+use bytes::{Buf, Bytes, BytesMut};
+use futures::StreamExt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio_util::codec::{Decoder, FramedRead};
+use tokio_util::sync::CancellationToken;
+
+use crate::application::metadata_listener::BrokerMetadataListener;
+use crate::application::produce_handler::ProduceHandler;
+use crate::application::replica_manager::{ACKS_ALL, ACKS_LEADER};
+use crate::application::request_context::RequestContext;
+use crate::client::partitioner::partition_for_key;
+use crate::config::{MqttTopicMapping, SecurityProtocol};
+use crate::core::domain::principal::KafkaPrincipal;
+use crate::core::domain::record::{Header, Record};
+use crate::core::domain::record_batch::RecordBatch;
+use crate::core::error::ErrorCode;
+use crate::protocol::produce::{PartitionProduceData, ProduceRequest, TopicProduceData};
+use crate::shared::collections::FlatSet;
+use crate::shared::scheduler::spawn_named;
+use crate::shared::time::current_time_ms;
+
+/// The record header holding the MQTT topic a message was published to.
+pub const MQTT_TOPIC_HEADER: &str = "mqtt_topic";
+
+const CONNECT: u8 = 1;
+const CONNACK: u8 = 2;
+const PUBLISH: u8 = 3;
+const PUBACK: u8 = 4;
+const PUBREC: u8 = 5;
+const PUBREL: u8 = 6;
+const PUBCOMP: u8 = 7;
+const SUBSCRIBE: u8 = 8;
+const SUBACK: u8 = 9;
+const UNSUBSCRIBE: u8 = 10;
+const UNSUBACK: u8 = 11;
+const PINGREQ: u8 = 12;
+const PINGRESP: u8 = 13;
+const DISCONNECT: u8 = 14;
+
+const CONNACK_ACCEPTED: u8 = 0x00;
+const CONNACK_UNACCEPTABLE_PROTOCOL_VERSION: u8 = 0x01;
+const SUBACK_FAILURE: u8 = 0x80;
+/// MQTT 3.1 and 3.1.1; 5 is answered with a refusal.
+const SUPPORTED_PROTOCOL_LEVELS: [u8; 2] = [3, 4];
+
+const MAX_PACKET_BYTES: usize = 1024 * 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const PRODUCE_TIMEOUT_MS: i32 = 30 * 1000;
+const CLIENT_ID: &str = "mqtt-bridge";
+
+/// A decoded MQTT 3.1.1 control packet, as far as an ingress-only server needs it.
+#[derive(Debug, PartialEq)]
+pub enum Packet {
+    Connect {
+        protocol_level: u8,
+        keep_alive: u16,
+        client_id: String,
+    },
+    Publish {
+        topic: String,
+        qos: u8,
+        /// Present for QoS 1 and 2.
+        packet_id: Option<u16>,
+        payload: Bytes,
+    },
+    PubRel {
+        packet_id: u16,
+    },
+    Subscribe {
+        packet_id: u16,
+        filters: usize,
+    },
+    Unsubscribe {
+        packet_id: u16,
+    },
+    PingReq,
+    Disconnect,
+    /// Packets a client has no reason to send to this server, such as acknowledgements of
+    /// publishes it never received.
+    Ignored(u8),
+}
+
+/// Splits the connection into MQTT control packets.
+#[derive(Debug, Default)]
+pub struct MqttCodec;
+
+impl Decoder for MqttCodec {
+    type Item = Packet;
+    type Error = std::io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Packet>, Self::Error> {
+        // The remaining length is a varint of up to four bytes after the first header byte
+        let mut remaining_length = 0usize;
+        let mut header_length = 1;
+        loop {
+            let Some(&byte) = src.get(header_length) else {
+                return Ok(None);
+            };
+            remaining_length |= ((byte & 0x7F) as usize) << (7 * (header_length - 1));
+            header_length += 1;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            if header_length > 4 {
+                return Err(invalid("Remaining length longer than four bytes"));
+            }
+        }
+        if remaining_length > MAX_PACKET_BYTES {
+            return Err(invalid(format!(
+                "Packet of {} bytes exceeds the limit of {}",
+                remaining_length, MAX_PACKET_BYTES
+            )));
+        }
+        if src.len() < header_length + remaining_length {
+            src.reserve(header_length + remaining_length - src.len());
+            return Ok(None);
+        }
+
+        let first = src[0];
+        let mut body = src.split_to(header_length + remaining_length).freeze();
+        body.advance(header_length);
+        decode_packet(first >> 4, first & 0x0F, body).map(Some)
+    }
+}
+
+fn decode_packet(packet_type: u8, flags: u8, mut body: Bytes) -> std::io::Result<Packet> {
+    let packet = match packet_type {
+        CONNECT => {
+            let _protocol_name = read_string(&mut body)?;
+            let protocol_level = read_u8(&mut body)?;
+            let _connect_flags = read_u8(&mut body)?;
+            let keep_alive = read_u16(&mut body)?;
+            // A refused protocol version may lay out the rest differently
+            let client_id = if SUPPORTED_PROTOCOL_LEVELS.contains(&protocol_level) {
+                read_string(&mut body)?
+            } else {
+                String::new()
+            };
+            Packet::Connect {
+                protocol_level,
+                keep_alive,
+                client_id,
+            }
+        }
+        PUBLISH => {
+            let qos = (flags >> 1) & 0x03;
+            if qos == 3 {
+                return Err(invalid("PUBLISH with QoS 3"));
+            }
+            let topic = read_string(&mut body)?;
+            let packet_id = if qos > 0 {
+                Some(read_u16(&mut body)?)
+            } else {
+                None
+            };
+            Packet::Publish {
+                topic,
+                qos,
+                packet_id,
+                payload: body,
+            }
+        }
+        PUBREL => Packet::PubRel {
+            packet_id: read_u16(&mut body)?,
+        },
+        SUBSCRIBE => {
+            let packet_id = read_u16(&mut body)?;
+            let mut filters = 0;
+            while body.has_remaining() {
+                read_string(&mut body)?;
+                read_u8(&mut body)?;
+                filters += 1;
+            }
+            Packet::Subscribe { packet_id, filters }
+        }
+        UNSUBSCRIBE => Packet::Unsubscribe {
+            packet_id: read_u16(&mut body)?,
+        },
+        PINGREQ => Packet::PingReq,
+        DISCONNECT => Packet::Disconnect,
+        other => Packet::Ignored(other),
+    };
+    Ok(packet)
+}
+
+fn invalid(message: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.into())
+}
+
+fn read_u8(buf: &mut Bytes) -> std::io::Result<u8> {
+    if !buf.has_remaining() {
+        return Err(invalid("Packet ended early"));
+    }
+    Ok(buf.get_u8())
+}
+
+fn read_u16(buf: &mut Bytes) -> std::io::Result<u16> {
+    if buf.remaining() < 2 {
+        return Err(invalid("Packet ended early"));
+    }
+    Ok(buf.get_u16())
+}
+
+fn read_string(buf: &mut Bytes) -> std::io::Result<String> {
+    let length = read_u16(buf)? as usize;
+    if buf.remaining() < length {
+        return Err(invalid("Packet ended early"));
+    }
+    String::from_utf8(buf.split_to(length).to_vec()).map_err(|e| invalid(e.to_string()))
+}
+
+/// An acknowledgement made of a packet type, its flags and a packet id.
+fn ack(packet_type: u8, flags: u8, packet_id: u16) -> [u8; 4] {
+    let [high, low] = packet_id.to_be_bytes();
+    [packet_type << 4 | flags, 2, high, low]
+}
+
+/// Whether `topic` matches the MQTT topic `filter`: `+` stands for one level and a trailing
+/// `#` for any number, none included. Topics starting with `$` only match filters that do.
+pub fn filter_matches(filter: &str, topic: &str) -> bool {
+    if topic.starts_with('$') && !filter.starts_with('$') {
+        return false;
+    }
+    let mut topic_levels = topic.split('/');
+    for level in filter.split('/') {
+        match (level, topic_levels.next()) {
+            ("#", _) => return true,
+            ("+", Some(_)) => {}
+            (level, Some(topic_level)) if level == topic_level => {}
+            _ => return false,
+        }
+    }
+    topic_levels.next().is_none()
+}
+
+/// Accepts MQTT 3.1.1 connections and appends every message published on them to the Forge
+/// topic its MQTT topic maps to, with the payload as the value and the MQTT topic in the
+/// `mqtt_topic` header. Messages of one MQTT topic go to one partition, keeping their order,
+/// except the message that auto-creates a topic, which lands on partition 0.
+///
+/// Ingress only: subscriptions are refused, retained and will messages are not kept, and
+/// sessions end with the connection. QoS 1 and 2 publishes are acknowledged once appended
+/// with acks=all; a failed append closes the connection so the client sends them again.
+/// Publishes no mapping matches are acknowledged and dropped. Connections act as the
+/// anonymous principal, whatever credentials they carry.
+pub struct MqttBridge {
+    produce_handler: ProduceHandler,
+    /// Gives the partition count of each topic.
+    metadata: Arc<Mutex<BrokerMetadataListener>>,
+    mappings: Vec<MqttTopicMapping>,
+}
+
+impl MqttBridge {
+    pub fn new(
+        produce_handler: ProduceHandler,
+        metadata: Arc<Mutex<BrokerMetadataListener>>,
+        mappings: Vec<MqttTopicMapping>,
+    ) -> Self {
+        Self {
+            produce_handler,
+            metadata,
+            mappings,
+        }
+    }
+
+    /// Binds `address` and serves from a background task until `cancel_token` is cancelled.
+    pub async fn start(
+        self,
+        address: &str,
+        cancel_token: CancellationToken,
+    ) -> Result<JoinHandle<()>, String> {
+        let listener = TcpListener::bind(address)
+            .await
+            .map_err(|e| format!("Failed to bind MQTT listener {}: {}", address, e))?;
+        tracing::info!("MQTT bridge started on {}", address);
+
+        let bridge = Arc::new(self);
+        Ok(spawn_named("mqtt-bridge", async move {
+            loop {
+                tokio::select! {
+                    accept_result = listener.accept() => match accept_result {
+                        Ok((socket, peer_address)) => {
+                            let bridge = bridge.clone();
+                            let cancel_token = cancel_token.clone();
+                            spawn_named("mqtt-connection", async move {
+                                tokio::select! {
+                                    served = bridge.serve(socket, peer_address) => {
+                                        if let Err(e) = served {
+                                            tracing::warn!(
+                                                "MQTT connection from {} failed: {}",
+                                                peer_address,
+                                                e
+                                            );
+                                        }
+                                    }
+                                    _ = cancel_token.cancelled() => {}
+                                }
+                            });
+                        }
+                        Err(e) => tracing::error!("Failed to accept MQTT connection: {}", e),
+                    },
+                    _ = cancel_token.cancelled() => break,
+                }
+            }
+        }))
+    }
+
+    async fn serve(&self, mut socket: TcpStream, peer_address: SocketAddr) -> Result<(), String> {
+        let (reader, mut writer) = socket.split();
+        let mut packets = FramedRead::new(reader, MqttCodec);
+
+        let connect = tokio::time::timeout(CONNECT_TIMEOUT, packets.next())
+            .await
+            .map_err(|_| "No CONNECT in time".to_string())?;
+        let keep_alive = match connect {
+            Some(Ok(Packet::Connect {
+                protocol_level,
+                keep_alive,
+                client_id,
+            })) => {
+                if !SUPPORTED_PROTOCOL_LEVELS.contains(&protocol_level) {
+                    write(
+                        &mut writer,
+                        &[CONNACK << 4, 2, 0, CONNACK_UNACCEPTABLE_PROTOCOL_VERSION],
+                    )
+                    .await?;
+                    return Err(format!(
+                        "Unsupported MQTT protocol level {}",
+                        protocol_level
+                    ));
+                }
+                tracing::debug!("MQTT client {} connected from {}", client_id, peer_address);
+                write(&mut writer, &[CONNACK << 4, 2, 0, CONNACK_ACCEPTED]).await?;
+                keep_alive
+            }
+            Some(Ok(packet)) => return Err(format!("Expected CONNECT, got {:?}", packet)),
+            Some(Err(e)) => return Err(e.to_string()),
+            None => return Ok(()),
+        };
+
+        let context = RequestContext {
+            principal: KafkaPrincipal::anonymous(),
+            client_host: peer_address.ip().to_string(),
+            client_id: CLIENT_ID.to_string(),
+            listener: SecurityProtocol::Plaintext,
+        };
+        // A client silent for one and a half keep-alive periods is gone
+        let idle_timeout = if keep_alive > 0 {
+            Duration::from_millis(keep_alive as u64 * 1500)
+        } else {
+            Duration::MAX
+        };
+        // QoS 2 packet ids received but not yet released, whose resends are not appended again
+        let mut unreleased = FlatSet::new();
+
+        loop {
+            let packet = match tokio::time::timeout(idle_timeout, packets.next()).await {
+                Ok(Some(packet)) => packet.map_err(|e| e.to_string())?,
+                Ok(None) => return Ok(()),
+                Err(_) => return Err("Keep-alive expired".to_string()),
+            };
+            match packet {
+                Packet::Publish {
+                    topic,
+                    qos,
+                    packet_id,
+                    payload,
+                } => {
+                    let resent = packet_id.is_some_and(|id| qos == 2 && unreleased.contains(&id));
+                    if !resent {
+                        self.append(&context, &topic, qos, payload).await?;
+                    }
+                    match (qos, packet_id) {
+                        (1, Some(id)) => write(&mut writer, &ack(PUBACK, 0, id)).await?,
+                        (2, Some(id)) => {
+                            unreleased.insert(id);
+                            write(&mut writer, &ack(PUBREC, 0, id)).await?
+                        }
+                        _ => {}
+                    }
+                }
+                Packet::PubRel { packet_id } => {
+                    unreleased.remove(&packet_id);
+                    write(&mut writer, &ack(PUBCOMP, 0, packet_id)).await?;
+                }
+                Packet::Subscribe { packet_id, filters } => {
+                    let [high, low] = packet_id.to_be_bytes();
+                    let mut suback = vec![SUBACK << 4];
+                    suback.extend(encode_remaining_length(2 + filters));
+                    suback.extend([high, low]);
+                    suback.extend(std::iter::repeat_n(SUBACK_FAILURE, filters));
+                    write(&mut writer, &suback).await?;
+                }
+                Packet::Unsubscribe { packet_id } => {
+                    write(&mut writer, &ack(UNSUBACK, 0, packet_id)).await?
+                }
+                Packet::PingReq => write(&mut writer, &[PINGRESP << 4, 0]).await?,
+                Packet::Disconnect => return Ok(()),
+                Packet::Connect { .. } => return Err("Second CONNECT".to_string()),
+                Packet::Ignored(packet_type) => {
+                    tracing::debug!("Ignoring MQTT packet type {}", packet_type)
+                }
+            }
+        }
+    }
+
+    /// Appends one message. Fails only for QoS 1 and 2, whose publishers resend.
+    async fn append(
+        &self,
+        context: &RequestContext,
+        mqtt_topic: &str,
+        qos: u8,
+        payload: Bytes,
+    ) -> Result<(), String> {
+        let Some(mapping) = self
+            .mappings
+            .iter()
+            .find(|mapping| filter_matches(&mapping.filter, mqtt_topic))
+        else {
+            tracing::warn!(
+                "Dropped MQTT message to {}: no topic mapping matches",
+                mqtt_topic
+            );
+            return Ok(());
+        };
+
+        let partition_count = self
+            .metadata
+            .lock()
+            .await
+            .metadata
+            .topics
+            .get(&mapping.topic)
+            .map_or(0, |topic| topic.partitions.len());
+        let partition = if partition_count > 0 {
+            partition_for_key(mqtt_topic.as_bytes(), partition_count)
+        } else {
+            // Left for the produce path to reject, or to create the topic
+            0
+        };
+
+        let mut record = Record::new(0, None, Some(payload.to_vec()));
+        record.headers.push(Header {
+            key: MQTT_TOPIC_HEADER.into(),
+            value: Some(mqtt_topic.as_bytes().to_vec()),
+        });
+        let request = ProduceRequest {
+            transactional_id: None,
+            acks: if qos == 0 { ACKS_LEADER } else { ACKS_ALL },
+            timeout_ms: PRODUCE_TIMEOUT_MS,
+            topics: vec![TopicProduceData {
+                name: mapping.topic.clone(),
+                partitions: vec![PartitionProduceData {
+                    index: partition,
+                    records: vec![RecordBatch::new(current_time_ms(), vec![record])],
+                    corrupt: None,
+                }],
+            }],
+        };
+        let error_code = self
+            .produce_handler
+            .handle(context, request)
+            .await
+            .and_then(|response| {
+                let topic = response.responses.into_iter().next()?;
+                topic.partitions.into_iter().next()
+            })
+            .map_or(ErrorCode::UnknownServerError.code(), |partition| {
+                partition.error_code
+            });
+        if error_code == ErrorCode::None.code() {
+            return Ok(());
+        }
+
+        let message = format!(
+            "Appending MQTT message from {} to {}-{} failed with error code {}",
+            mqtt_topic, mapping.topic, partition, error_code
+        );
+        if qos == 0 {
+            tracing::warn!("{}", message);
+            Ok(())
+        } else {
+            Err(message)
+        }
+    }
+}
+
+fn encode_remaining_length(mut length: usize) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(4);
+    loop {
+        let mut byte = (length & 0x7F) as u8;
+        length >>= 7;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        encoded.push(byte);
+        if length == 0 {
+            return encoded;
+        }
+    }
+}
+
+async fn write(writer: &mut (impl AsyncWriteExt + Unpin), packet: &[u8]) -> Result<(), String> {
+    writer.write_all(packet).await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decodes_publishes_split_across_reads() {
+        let mut publish = vec![PUBLISH << 4 | 0x02, 0];
+        publish.extend([0, 9]);
+        publish.extend(b"sensors/1");
+        publish.extend([0, 7]);
+        publish.extend(b"21.5C");
+        publish[1] = (publish.len() - 2) as u8;
+
+        let mut codec = MqttCodec;
+        let mut buf = BytesMut::from(&publish[..5]);
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        buf.extend_from_slice(&publish[5..]);
+        buf.extend_from_slice(&[PINGREQ << 4, 0]);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Packet::Publish {
+                topic: "sensors/1".to_string(),
+                qos: 1,
+                packet_id: Some(7),
+                payload: Bytes::from_static(b"21.5C"),
+            })
+        );
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(Packet::PingReq));
+
+        // A remaining length of five bytes
+        let mut buf = BytesMut::from(&[PUBLISH << 4, 0xFF, 0xFF, 0xFF, 0xFF, 0x01][..]);
+        assert!(codec.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_filters_match_by_level() {
+        assert!(filter_matches(
+            "sensors/+/temperature",
+            "sensors/a/temperature"
+        ));
+        assert!(!filter_matches(
+            "sensors/+/temperature",
+            "sensors/a/b/temperature"
+        ));
+        assert!(filter_matches("sensors/#", "sensors"));
+        assert!(filter_matches("sensors/#", "sensors/a/b"));
+        assert!(!filter_matches("sensors", "sensors/a"));
+        assert!(!filter_matches("#", "$SYS/uptime"));
+        assert_eq!(encode_remaining_length(321), [0xC1, 0x02]);
+    }
+}
@@ -0,0 +1,99 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+const SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+/// Signature, version and command, address family and transport, and the address length.
+const HEADER_LENGTH: usize = 16;
+const VERSION_2: u8 = 0x20;
+const COMMAND_LOCAL: u8 = 0x00;
+const COMMAND_PROXY: u8 = 0x01;
+const FAMILY_INET: u8 = 0x10;
+const FAMILY_INET6: u8 = 0x20;
+const INET_ADDRESSES_LENGTH: usize = 12;
+const INET6_ADDRESSES_LENGTH: usize = 36;
+
+/// Reads the PROXY protocol v2 header a load balancer sends ahead of the client's bytes and
+/// returns the client's address. `None` means the header carries no client address: health
+/// checks from the balancer itself (LOCAL) or families other than TCP over IPv4 and IPv6.
+/// Trailing TLVs are skipped.
+pub async fn read_proxy_header<R: AsyncRead + Unpin>(
+    stream: &mut R,
+) -> Result<Option<SocketAddr>, String> {
+    let mut header = [0u8; HEADER_LENGTH];
+    stream
+        .read_exact(&mut header)
+        .await
+        .map_err(|e| format!("Failed to read PROXY header: {}", e))?;
+    if header[..SIGNATURE.len()] != SIGNATURE {
+        return Err("Connection did not start with a PROXY protocol v2 header".to_string());
+    }
+    if header[12] & 0xF0 != VERSION_2 {
+        return Err(format!(
+            "Unsupported PROXY protocol version {}",
+            header[12] >> 4
+        ));
+    }
+    let command = header[12] & 0x0F;
+    let family = header[13] & 0xF0;
+    let length = u16::from_be_bytes([header[14], header[15]]) as usize;
+
+    let mut addresses = vec![0u8; length];
+    stream
+        .read_exact(&mut addresses)
+        .await
+        .map_err(|e| format!("Failed to read PROXY header addresses: {}", e))?;
+
+    match command {
+        COMMAND_LOCAL => return Ok(None),
+        COMMAND_PROXY => {}
+        command => return Err(format!("Unsupported PROXY protocol command {}", command)),
+    }
+    let address = match family {
+        FAMILY_INET if length >= INET_ADDRESSES_LENGTH => {
+            let ip = Ipv4Addr::new(addresses[0], addresses[1], addresses[2], addresses[3]);
+            let port = u16::from_be_bytes([addresses[8], addresses[9]]);
+            Some(SocketAddr::new(IpAddr::V4(ip), port))
+        }
+        FAMILY_INET6 if length >= INET6_ADDRESSES_LENGTH => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&addresses[..16]);
+            let port = u16::from_be_bytes([addresses[32], addresses[33]]);
+            Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(octets)), port))
+        }
+        FAMILY_INET | FAMILY_INET6 => {
+            return Err(format!(
+                "PROXY header addresses too short: {} bytes",
+                length
+            ));
+        }
+        _ => None,
+    };
+    Ok(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reads_the_client_address_and_leaves_the_rest() {
+        let mut input = SIGNATURE.to_vec();
+        input.extend_from_slice(&[0x21, 0x11, 0, 15]);
+        input.extend_from_slice(&[192, 0, 2, 7, 10, 0, 0, 1]);
+        input.extend_from_slice(&[0xC3, 0x50, 0x23, 0x84]);
+        // A TLV, then the client's first bytes
+        input.extend_from_slice(&[0x04, 0, 0]);
+        input.extend_from_slice(b"kafka");
+
+        let mut stream = &input[..];
+        let address = read_proxy_header(&mut stream).await.unwrap();
+        assert_eq!(address, Some("192.0.2.7:50000".parse().unwrap()));
+        assert_eq!(stream, b"kafka");
+
+        assert!(
+            read_proxy_header(&mut &b"PROXY TCP4 192.0.2.7\r\n"[..])
+                .await
+                .is_err()
+        );
+    }
+}
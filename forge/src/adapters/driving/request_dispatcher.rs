@@ -0,0 +1,695 @@
+use bytes::Buf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use crate::adapters::driving::request_dispatcher::chaos::Chaos;
+use crate::adapters::driving::request_metrics::RequestMetrics;
+use crate::application::admin_handler::AdminHandler;
+use crate::application::fetch_handler::FetchHandler;
+use crate::application::group_handler::GroupHandler;
+use crate::application::list_offsets_handler::ListOffsetsHandler;
+use crate::application::metadata_handler::MetadataHandler;
+use crate::application::produce_handler::ProduceHandler;
+use crate::application::quota_manager::{QuotaManager, QuotaType};
+use crate::application::request_context::RequestContext;
+use crate::application::txn_handler::TxnHandler;
+use crate::config::ChaosConfig;
+use crate::core::error::ErrorCode;
+use crate::protocol::add_offsets_to_txn::{
+    ADD_OFFSETS_TO_TXN_API_KEY, ADD_OFFSETS_TO_TXN_MAX_VERSION, ADD_OFFSETS_TO_TXN_MIN_VERSION,
+    AddOffsetsToTxnRequest,
+};
+use crate::protocol::add_partitions_to_txn::{
+    ADD_PARTITIONS_TO_TXN_API_KEY, ADD_PARTITIONS_TO_TXN_MAX_VERSION,
+    ADD_PARTITIONS_TO_TXN_MIN_VERSION, AddPartitionsToTxnRequest,
+};
+use crate::protocol::alter_configs::{
+    ALTER_CONFIGS_API_KEY, ALTER_CONFIGS_MAX_VERSION, ALTER_CONFIGS_MIN_VERSION,
+    AlterConfigsRequest,
+};
+use crate::protocol::alter_partition_reassignments::{
+    ALTER_PARTITION_REASSIGNMENTS_API_KEY, ALTER_PARTITION_REASSIGNMENTS_MAX_VERSION,
+    ALTER_PARTITION_REASSIGNMENTS_MIN_VERSION, AlterPartitionReassignmentsRequest,
+};
+use crate::protocol::api_versions::{
+    API_VERSIONS_API_KEY, API_VERSIONS_MAX_VERSION, API_VERSIONS_MIN_VERSION, ApiVersion,
+    ApiVersionsResponse,
+};
+use crate::protocol::create_acls::{
+    CREATE_ACLS_API_KEY, CREATE_ACLS_MAX_VERSION, CREATE_ACLS_MIN_VERSION, CreateAclsRequest,
+};
+use crate::protocol::delete_acls::{
+    DELETE_ACLS_API_KEY, DELETE_ACLS_MAX_VERSION, DELETE_ACLS_MIN_VERSION, DeleteAclsRequest,
+};
+use crate::protocol::describe_acls::{
+    DESCRIBE_ACLS_API_KEY, DESCRIBE_ACLS_MAX_VERSION, DESCRIBE_ACLS_MIN_VERSION,
+    DescribeAclsRequest,
+};
+use crate::protocol::describe_configs::{
+    DESCRIBE_CONFIGS_API_KEY, DESCRIBE_CONFIGS_MAX_VERSION, DESCRIBE_CONFIGS_MIN_VERSION,
+    DescribeConfigsRequest,
+};
+use crate::protocol::describe_groups::{
+    DESCRIBE_GROUPS_API_KEY, DESCRIBE_GROUPS_MAX_VERSION, DESCRIBE_GROUPS_MIN_VERSION,
+    DescribeGroupsRequest,
+};
+use crate::protocol::elect_leaders::{
+    ELECT_LEADERS_API_KEY, ELECT_LEADERS_MAX_VERSION, ELECT_LEADERS_MIN_VERSION,
+    ElectLeadersRequest,
+};
+use crate::protocol::end_txn::{
+    END_TXN_API_KEY, END_TXN_MAX_VERSION, END_TXN_MIN_VERSION, EndTxnRequest,
+};
+use crate::protocol::fetch::{FETCH_API_KEY, FETCH_MAX_VERSION, FETCH_MIN_VERSION, FetchRequest};
+use crate::protocol::find_coordinator::{
+    FIND_COORDINATOR_API_KEY, FIND_COORDINATOR_MAX_VERSION, FIND_COORDINATOR_MIN_VERSION,
+    FindCoordinatorRequest,
+};
+use crate::protocol::heartbeat::{
+    HEARTBEAT_API_KEY, HEARTBEAT_MAX_VERSION, HEARTBEAT_MIN_VERSION, HeartbeatRequest,
+};
+use crate::protocol::init_producer_id::{
+    INIT_PRODUCER_ID_API_KEY, INIT_PRODUCER_ID_MAX_VERSION, INIT_PRODUCER_ID_MIN_VERSION,
+    InitProducerIdRequest,
+};
+use crate::protocol::join_group::{
+    JOIN_GROUP_API_KEY, JOIN_GROUP_MAX_VERSION, JOIN_GROUP_MIN_VERSION, JoinGroupRequest,
+};
+use crate::protocol::leave_group::{
+    LEAVE_GROUP_API_KEY, LEAVE_GROUP_MAX_VERSION, LEAVE_GROUP_MIN_VERSION, LeaveGroupRequest,
+};
+use crate::protocol::list_groups::{
+    LIST_GROUPS_API_KEY, LIST_GROUPS_MAX_VERSION, LIST_GROUPS_MIN_VERSION, ListGroupsRequest,
+};
+use crate::protocol::list_offsets::{
+    LIST_OFFSETS_API_KEY, LIST_OFFSETS_MAX_VERSION, LIST_OFFSETS_MIN_VERSION, ListOffsetsRequest,
+};
+use crate::protocol::list_partition_reassignments::{
+    LIST_PARTITION_REASSIGNMENTS_API_KEY, LIST_PARTITION_REASSIGNMENTS_MAX_VERSION,
+    LIST_PARTITION_REASSIGNMENTS_MIN_VERSION, ListPartitionReassignmentsRequest,
+};
+use crate::protocol::metadata::{
+    METADATA_API_KEY, METADATA_MAX_VERSION, METADATA_MIN_VERSION, MetadataRequest,
+};
+use crate::protocol::offset_commit::{
+    OFFSET_COMMIT_API_KEY, OFFSET_COMMIT_MAX_VERSION, OFFSET_COMMIT_MIN_VERSION,
+    OffsetCommitRequest,
+};
+use crate::protocol::offset_fetch::{
+    OFFSET_FETCH_API_KEY, OFFSET_FETCH_MAX_VERSION, OFFSET_FETCH_MIN_VERSION, OffsetFetchRequest,
+};
+use crate::protocol::produce::{
+    PRODUCE_API_KEY, PRODUCE_MAX_VERSION, PRODUCE_MIN_VERSION, ProduceRequest,
+};
+use crate::protocol::request::RequestHeader;
+use crate::protocol::response::ResponseBody;
+use crate::protocol::sasl_authenticate::{
+    SASL_AUTHENTICATE_API_KEY, SASL_AUTHENTICATE_MAX_VERSION, SASL_AUTHENTICATE_MIN_VERSION,
+};
+use crate::protocol::sasl_handshake::{
+    SASL_HANDSHAKE_API_KEY, SASL_HANDSHAKE_MAX_VERSION, SASL_HANDSHAKE_MIN_VERSION,
+};
+use crate::protocol::sync_group::{
+    SYNC_GROUP_API_KEY, SYNC_GROUP_MAX_VERSION, SYNC_GROUP_MIN_VERSION, SyncGroupRequest,
+};
+use crate::protocol::txn_offset_commit::{
+    TXN_OFFSET_COMMIT_API_KEY, TXN_OFFSET_COMMIT_MAX_VERSION, TXN_OFFSET_COMMIT_MIN_VERSION,
+    TxnOffsetCommitRequest,
+};
+use crate::protocol::types::{TaggedFields, Type};
+use crate::shared::buffer_pool;
+use crate::shared::time::current_time_ms;
+use crate::shared::timing::measure_busy_time;
+
+mod chaos;
+mod too_large;
+
+/// Routes decoded requests to the application handlers and encodes their responses.
+pub struct RequestDispatcher {
+    produce_handler: ProduceHandler,
+    fetch_handler: FetchHandler,
+    metadata_handler: MetadataHandler,
+    admin_handler: AdminHandler,
+    list_offsets_handler: ListOffsetsHandler,
+    group_handler: GroupHandler,
+    txn_handler: TxnHandler,
+    /// Shared with the dynamic broker config, which updates the default quotas.
+    quota_manager: Arc<Mutex<QuotaManager>>,
+    /// Filled in by the connections, which see every phase of a request.
+    request_metrics: Arc<RequestMetrics>,
+    chaos: Chaos,
+}
+
+impl RequestDispatcher {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        produce_handler: ProduceHandler,
+        fetch_handler: FetchHandler,
+        metadata_handler: MetadataHandler,
+        admin_handler: AdminHandler,
+        list_offsets_handler: ListOffsetsHandler,
+        group_handler: GroupHandler,
+        txn_handler: TxnHandler,
+        quota_manager: Arc<Mutex<QuotaManager>>,
+    ) -> Self {
+        Self {
+            produce_handler,
+            fetch_handler,
+            metadata_handler,
+            admin_handler,
+            list_offsets_handler,
+            group_handler,
+            txn_handler,
+            quota_manager,
+            request_metrics: Arc::new(RequestMetrics::new()),
+            chaos: Chaos::new(ChaosConfig::default()),
+        }
+    }
+
+    /// Injects the faults of `chaos` into request handling.
+    pub fn with_chaos(mut self, chaos: ChaosConfig) -> Self {
+        self.chaos = Chaos::new(chaos);
+        self
+    }
+
+    pub fn request_metrics(&self) -> Arc<RequestMetrics> {
+        self.request_metrics.clone()
+    }
+
+    pub fn supported_apis() -> Vec<ApiVersion> {
+        vec![
+            ApiVersion {
+                api_key: PRODUCE_API_KEY,
+                min_version: PRODUCE_MIN_VERSION,
+                max_version: PRODUCE_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: FETCH_API_KEY,
+                min_version: FETCH_MIN_VERSION,
+                max_version: FETCH_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: LIST_OFFSETS_API_KEY,
+                min_version: LIST_OFFSETS_MIN_VERSION,
+                max_version: LIST_OFFSETS_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: METADATA_API_KEY,
+                min_version: METADATA_MIN_VERSION,
+                max_version: METADATA_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: OFFSET_COMMIT_API_KEY,
+                min_version: OFFSET_COMMIT_MIN_VERSION,
+                max_version: OFFSET_COMMIT_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: OFFSET_FETCH_API_KEY,
+                min_version: OFFSET_FETCH_MIN_VERSION,
+                max_version: OFFSET_FETCH_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: FIND_COORDINATOR_API_KEY,
+                min_version: FIND_COORDINATOR_MIN_VERSION,
+                max_version: FIND_COORDINATOR_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: JOIN_GROUP_API_KEY,
+                min_version: JOIN_GROUP_MIN_VERSION,
+                max_version: JOIN_GROUP_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: HEARTBEAT_API_KEY,
+                min_version: HEARTBEAT_MIN_VERSION,
+                max_version: HEARTBEAT_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: LEAVE_GROUP_API_KEY,
+                min_version: LEAVE_GROUP_MIN_VERSION,
+                max_version: LEAVE_GROUP_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: SYNC_GROUP_API_KEY,
+                min_version: SYNC_GROUP_MIN_VERSION,
+                max_version: SYNC_GROUP_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: DESCRIBE_GROUPS_API_KEY,
+                min_version: DESCRIBE_GROUPS_MIN_VERSION,
+                max_version: DESCRIBE_GROUPS_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: LIST_GROUPS_API_KEY,
+                min_version: LIST_GROUPS_MIN_VERSION,
+                max_version: LIST_GROUPS_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: INIT_PRODUCER_ID_API_KEY,
+                min_version: INIT_PRODUCER_ID_MIN_VERSION,
+                max_version: INIT_PRODUCER_ID_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: ADD_PARTITIONS_TO_TXN_API_KEY,
+                min_version: ADD_PARTITIONS_TO_TXN_MIN_VERSION,
+                max_version: ADD_PARTITIONS_TO_TXN_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: ADD_OFFSETS_TO_TXN_API_KEY,
+                min_version: ADD_OFFSETS_TO_TXN_MIN_VERSION,
+                max_version: ADD_OFFSETS_TO_TXN_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: END_TXN_API_KEY,
+                min_version: END_TXN_MIN_VERSION,
+                max_version: END_TXN_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: TXN_OFFSET_COMMIT_API_KEY,
+                min_version: TXN_OFFSET_COMMIT_MIN_VERSION,
+                max_version: TXN_OFFSET_COMMIT_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: ALTER_CONFIGS_API_KEY,
+                min_version: ALTER_CONFIGS_MIN_VERSION,
+                max_version: ALTER_CONFIGS_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: DESCRIBE_CONFIGS_API_KEY,
+                min_version: DESCRIBE_CONFIGS_MIN_VERSION,
+                max_version: DESCRIBE_CONFIGS_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: DESCRIBE_ACLS_API_KEY,
+                min_version: DESCRIBE_ACLS_MIN_VERSION,
+                max_version: DESCRIBE_ACLS_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: CREATE_ACLS_API_KEY,
+                min_version: CREATE_ACLS_MIN_VERSION,
+                max_version: CREATE_ACLS_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: DELETE_ACLS_API_KEY,
+                min_version: DELETE_ACLS_MIN_VERSION,
+                max_version: DELETE_ACLS_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: ELECT_LEADERS_API_KEY,
+                min_version: ELECT_LEADERS_MIN_VERSION,
+                max_version: ELECT_LEADERS_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: ALTER_PARTITION_REASSIGNMENTS_API_KEY,
+                min_version: ALTER_PARTITION_REASSIGNMENTS_MIN_VERSION,
+                max_version: ALTER_PARTITION_REASSIGNMENTS_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: LIST_PARTITION_REASSIGNMENTS_API_KEY,
+                min_version: LIST_PARTITION_REASSIGNMENTS_MIN_VERSION,
+                max_version: LIST_PARTITION_REASSIGNMENTS_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: SASL_HANDSHAKE_API_KEY,
+                min_version: SASL_HANDSHAKE_MIN_VERSION,
+                max_version: SASL_HANDSHAKE_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: SASL_AUTHENTICATE_API_KEY,
+                min_version: SASL_AUTHENTICATE_MIN_VERSION,
+                max_version: SASL_AUTHENTICATE_MAX_VERSION,
+            },
+            ApiVersion {
+                api_key: API_VERSIONS_API_KEY,
+                min_version: API_VERSIONS_MIN_VERSION,
+                max_version: API_VERSIONS_MAX_VERSION,
+            },
+        ]
+    }
+
+    /// Kafka's name for a supported API, e.g. for metric labels.
+    pub fn api_name(api_key: i16) -> Option<&'static str> {
+        match api_key {
+            PRODUCE_API_KEY => Some("Produce"),
+            FETCH_API_KEY => Some("Fetch"),
+            LIST_OFFSETS_API_KEY => Some("ListOffsets"),
+            METADATA_API_KEY => Some("Metadata"),
+            OFFSET_COMMIT_API_KEY => Some("OffsetCommit"),
+            OFFSET_FETCH_API_KEY => Some("OffsetFetch"),
+            FIND_COORDINATOR_API_KEY => Some("FindCoordinator"),
+            JOIN_GROUP_API_KEY => Some("JoinGroup"),
+            HEARTBEAT_API_KEY => Some("Heartbeat"),
+            LEAVE_GROUP_API_KEY => Some("LeaveGroup"),
+            SYNC_GROUP_API_KEY => Some("SyncGroup"),
+            DESCRIBE_GROUPS_API_KEY => Some("DescribeGroups"),
+            LIST_GROUPS_API_KEY => Some("ListGroups"),
+            INIT_PRODUCER_ID_API_KEY => Some("InitProducerId"),
+            ADD_PARTITIONS_TO_TXN_API_KEY => Some("AddPartitionsToTxn"),
+            ADD_OFFSETS_TO_TXN_API_KEY => Some("AddOffsetsToTxn"),
+            END_TXN_API_KEY => Some("EndTxn"),
+            TXN_OFFSET_COMMIT_API_KEY => Some("TxnOffsetCommit"),
+            ALTER_CONFIGS_API_KEY => Some("AlterConfigs"),
+            DESCRIBE_CONFIGS_API_KEY => Some("DescribeConfigs"),
+            DESCRIBE_ACLS_API_KEY => Some("DescribeAcls"),
+            CREATE_ACLS_API_KEY => Some("CreateAcls"),
+            DELETE_ACLS_API_KEY => Some("DeleteAcls"),
+            ELECT_LEADERS_API_KEY => Some("ElectLeaders"),
+            ALTER_PARTITION_REASSIGNMENTS_API_KEY => Some("AlterPartitionReassignments"),
+            LIST_PARTITION_REASSIGNMENTS_API_KEY => Some("ListPartitionReassignments"),
+            SASL_HANDSHAKE_API_KEY => Some("SaslHandshake"),
+            SASL_AUTHENTICATE_API_KEY => Some("SaslAuthenticate"),
+            API_VERSIONS_API_KEY => Some("ApiVersions"),
+            _ => None,
+        }
+    }
+
+    /// Records bandwidth and handler time against the client's quotas and returns the longer
+    /// of the two throttle times.
+    async fn record_quotas(
+        &self,
+        context: &RequestContext,
+        quota_type: QuotaType,
+        bytes: usize,
+        busy_time: Duration,
+    ) -> i32 {
+        let user = &context.principal.name;
+        let client_id = &context.client_id;
+        let now_ms = current_time_ms();
+        let mut quota_manager = self.quota_manager.lock().await;
+        let bandwidth_throttle_ms =
+            quota_manager.record(quota_type, user, client_id, bytes as f64, now_ms);
+        let request_throttle_ms = quota_manager.record(
+            QuotaType::Request,
+            user,
+            client_id,
+            busy_time.as_secs_f64() * 1000.0,
+            now_ms,
+        );
+        bandwidth_throttle_ms.max(request_throttle_ms)
+    }
+
+    /// Returns the encoded response body, or `None` for requests that expect no response.
+    /// An error means the request could not be understood and the connection should close.
+    /// Clients over quota get `throttle_time_ms` set and their response held back that long.
+    pub async fn dispatch<B: Buf>(
+        &self,
+        context: &RequestContext,
+        header: &RequestHeader,
+        body: &mut B,
+    ) -> Result<Option<ResponseBody>, String> {
+        let faults = self.chaos.roll(header.api_key);
+        if let Some(latency) = faults.latency {
+            tracing::debug!("Chaos: delaying request by {:?}", latency);
+            tokio::time::sleep(latency).await;
+        }
+        let supported = Self::supported_apis().into_iter().any(|api| {
+            api.api_key == header.api_key
+                && (api.min_version..=api.max_version).contains(&header.api_version)
+        });
+        if let Some(error_code) = faults.error_code.filter(|_| supported) {
+            tracing::debug!("Chaos: failing request with error code {}", error_code);
+            let response = self
+                .error_response(context, header, body, error_code)
+                .await?;
+            return Ok(response.map(ResponseBody::from));
+        }
+
+        let response = self.handle(context, header, body).await?;
+        if faults.drop {
+            tracing::debug!("Chaos: dropping response");
+            return Ok(None);
+        }
+        Ok(response)
+    }
+
+    async fn handle<B: Buf>(
+        &self,
+        context: &RequestContext,
+        header: &RequestHeader,
+        body: &mut B,
+    ) -> Result<Option<ResponseBody>, String> {
+        let version = header.api_version;
+        let supported = Self::supported_apis()
+            .into_iter()
+            .find(|api| api.api_key == header.api_key);
+
+        let mut response = buffer_pool::take();
+        let mut throttle_time_ms = 0;
+        match supported {
+            Some(api) if header.api_key == API_VERSIONS_API_KEY => {
+                // Clients probe with their newest version; answer in v0 so they can fall back
+                let (error, version) = if version > api.max_version {
+                    (ErrorCode::UnsupportedVersion, 0)
+                } else {
+                    (ErrorCode::None, version)
+                };
+                ApiVersionsResponse {
+                    error_code: error.code(),
+                    api_keys: Self::supported_apis(),
+                    throttle_time_ms: 0,
+                }
+                .encode(&mut response, version);
+            }
+            Some(api) if version < api.min_version || version > api.max_version => {
+                return Err(format!(
+                    "Unsupported version {} for API key {}",
+                    version, header.api_key
+                ));
+            }
+            Some(_) if header.api_key == PRODUCE_API_KEY => {
+                let request_size = body.remaining();
+                let request = ProduceRequest::decode(body, version)?;
+                let (produce_response, busy_time) =
+                    measure_busy_time(self.produce_handler.handle(context, request)).await;
+                throttle_time_ms = self
+                    .record_quotas(context, QuotaType::Produce, request_size, busy_time)
+                    .await;
+
+                match produce_response {
+                    Some(mut produce_response) => {
+                        produce_response.throttle_time_ms = throttle_time_ms;
+                        produce_response.encode(&mut response, version);
+                    }
+                    None => {
+                        Self::throttle(throttle_time_ms).await;
+                        return Ok(None);
+                    }
+                }
+            }
+            Some(_) if header.api_key == FETCH_API_KEY => {
+                let request = FetchRequest::decode(body, version)?;
+                let from_follower = request.replica_id >= 0;
+                let (mut fetch_response, busy_time) =
+                    measure_busy_time(self.fetch_handler.handle(context, request)).await;
+                let mut response = ResponseBody::from(response);
+                fetch_response.encode_chained(&mut response, version);
+
+                // Replication is not subject to client quotas
+                if !from_follower {
+                    throttle_time_ms = self
+                        .record_quotas(context, QuotaType::Fetch, response.len(), busy_time)
+                        .await;
+                    if throttle_time_ms > 0 {
+                        fetch_response.throttle_time_ms = throttle_time_ms;
+                        response.clear();
+                        fetch_response.encode_chained(&mut response, version);
+                    }
+                }
+                Self::throttle(throttle_time_ms).await;
+                return Ok(Some(response));
+            }
+            Some(_) if header.api_key == LIST_OFFSETS_API_KEY => {
+                let request = ListOffsetsRequest::decode(body, version)?;
+                self.list_offsets_handler
+                    .handle(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == METADATA_API_KEY => {
+                let request = MetadataRequest::decode(body, version)?;
+                self.metadata_handler
+                    .handle(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == OFFSET_COMMIT_API_KEY => {
+                let request = OffsetCommitRequest::decode(body, version)?;
+                self.group_handler
+                    .offset_commit(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == OFFSET_FETCH_API_KEY => {
+                let request = OffsetFetchRequest::decode(body, version)?;
+                self.group_handler
+                    .offset_fetch(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == FIND_COORDINATOR_API_KEY => {
+                let request = FindCoordinatorRequest::decode(body, version)?;
+                self.group_handler
+                    .find_coordinator(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == JOIN_GROUP_API_KEY => {
+                let request = JoinGroupRequest::decode(body, version)?;
+                self.group_handler
+                    .join_group(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == HEARTBEAT_API_KEY => {
+                let request = HeartbeatRequest::decode(body, version)?;
+                self.group_handler
+                    .heartbeat(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == LEAVE_GROUP_API_KEY => {
+                let request = LeaveGroupRequest::decode(body, version)?;
+                self.group_handler
+                    .leave_group(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == SYNC_GROUP_API_KEY => {
+                let request = SyncGroupRequest::decode(body, version)?;
+                self.group_handler
+                    .sync_group(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == DESCRIBE_GROUPS_API_KEY => {
+                let request = DescribeGroupsRequest::decode(body, version)?;
+                self.group_handler
+                    .describe_groups(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == LIST_GROUPS_API_KEY => {
+                let request = ListGroupsRequest::decode(body, version)?;
+                self.group_handler
+                    .list_groups(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == INIT_PRODUCER_ID_API_KEY => {
+                let request = InitProducerIdRequest::decode(body, version)?;
+                self.txn_handler
+                    .init_producer_id(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == ADD_PARTITIONS_TO_TXN_API_KEY => {
+                let request = AddPartitionsToTxnRequest::decode(body, version)?;
+                self.txn_handler
+                    .add_partitions_to_txn(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == ADD_OFFSETS_TO_TXN_API_KEY => {
+                let request = AddOffsetsToTxnRequest::decode(body, version)?;
+                self.txn_handler
+                    .add_offsets_to_txn(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == END_TXN_API_KEY => {
+                let request = EndTxnRequest::decode(body, version)?;
+                self.txn_handler
+                    .end_txn(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == TXN_OFFSET_COMMIT_API_KEY => {
+                let request = TxnOffsetCommitRequest::decode(body, version)?;
+                self.group_handler
+                    .txn_offset_commit(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == ALTER_CONFIGS_API_KEY => {
+                let request = AlterConfigsRequest::decode(body, version)?;
+                self.admin_handler
+                    .alter_configs(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == DESCRIBE_CONFIGS_API_KEY => {
+                let request = DescribeConfigsRequest::decode(body, version)?;
+                self.admin_handler
+                    .describe_configs(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == DESCRIBE_ACLS_API_KEY => {
+                let request = DescribeAclsRequest::decode(body, version)?;
+                self.admin_handler
+                    .describe_acls(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == CREATE_ACLS_API_KEY => {
+                let request = CreateAclsRequest::decode(body, version)?;
+                self.admin_handler
+                    .create_acls(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == DELETE_ACLS_API_KEY => {
+                let request = DeleteAclsRequest::decode(body, version)?;
+                self.admin_handler
+                    .delete_acls(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == ELECT_LEADERS_API_KEY => {
+                let request = ElectLeadersRequest::decode(body, version)?;
+                self.admin_handler
+                    .elect_leaders(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            // Flexible-only APIs: header tagged fields follow the client id, and the response
+            // header carries its own empty set
+            Some(_) if header.api_key == ALTER_PARTITION_REASSIGNMENTS_API_KEY => {
+                TaggedFields::decode(body)?;
+                let request = AlterPartitionReassignmentsRequest::decode(body, version)?;
+                TaggedFields.encode(&mut response);
+                self.admin_handler
+                    .alter_partition_reassignments(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            Some(_) if header.api_key == LIST_PARTITION_REASSIGNMENTS_API_KEY => {
+                TaggedFields::decode(body)?;
+                let request = ListPartitionReassignmentsRequest::decode(body, version)?;
+                TaggedFields.encode(&mut response);
+                self.admin_handler
+                    .list_partition_reassignments(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            // The connection's authenticator answers these until authentication completes
+            Some(_)
+                if header.api_key == SASL_HANDSHAKE_API_KEY
+                    || header.api_key == SASL_AUTHENTICATE_API_KEY =>
+            {
+                return Err("SASL request on a connection that is not authenticating".to_string());
+            }
+            _ => return Err(format!("Unsupported API key {}", header.api_key)),
+        }
+
+        Self::throttle(throttle_time_ms).await;
+        Ok(Some(response.into()))
+    }
+
+    async fn throttle(throttle_time_ms: i32) {
+        if throttle_time_ms > 0 {
+            tracing::debug!("Throttling response by {} ms", throttle_time_ms);
+            tokio::time::sleep(Duration::from_millis(throttle_time_ms as u64)).await;
+        }
+    }
+}
@@ -0,0 +1,478 @@
+use bytes::{Buf, BytesMut};
+use rand::RngExt;
+use std::time::Duration;
+
+use super::RequestDispatcher;
+use crate::application::request_context::RequestContext;
+use crate::config::ChaosConfig;
+use crate::core::domain::record_batch::Records;
+use crate::core::error::ErrorCode;
+use crate::protocol::add_offsets_to_txn::{
+    ADD_OFFSETS_TO_TXN_API_KEY, AddOffsetsToTxnRequest, AddOffsetsToTxnResponse,
+};
+use crate::protocol::add_partitions_to_txn::{
+    ADD_PARTITIONS_TO_TXN_API_KEY, AddPartitionsToTxnPartitionResult, AddPartitionsToTxnRequest,
+    AddPartitionsToTxnResponse, AddPartitionsToTxnTopicResult,
+};
+use crate::protocol::describe_groups::{
+    DESCRIBE_GROUPS_API_KEY, DescribeGroupsRequest, DescribeGroupsResponse, DescribedGroup,
+};
+use crate::protocol::end_txn::{END_TXN_API_KEY, EndTxnRequest, EndTxnResponse};
+use crate::protocol::fetch::{
+    FETCH_API_KEY, FetchRequest, FetchResponse, FetchableTopicResponse, PartitionData,
+};
+use crate::protocol::find_coordinator::{
+    FIND_COORDINATOR_API_KEY, FindCoordinatorRequest, FindCoordinatorResponse,
+};
+use crate::protocol::heartbeat::{HEARTBEAT_API_KEY, HeartbeatRequest, HeartbeatResponse};
+use crate::protocol::init_producer_id::{
+    INIT_PRODUCER_ID_API_KEY, InitProducerIdRequest, InitProducerIdResponse,
+};
+use crate::protocol::join_group::{JOIN_GROUP_API_KEY, JoinGroupRequest, JoinGroupResponse};
+use crate::protocol::leave_group::{LEAVE_GROUP_API_KEY, LeaveGroupRequest, LeaveGroupResponse};
+use crate::protocol::list_groups::{LIST_GROUPS_API_KEY, ListGroupsRequest, ListGroupsResponse};
+use crate::protocol::list_offsets::{
+    LIST_OFFSETS_API_KEY, ListOffsetsPartitionResponse, ListOffsetsRequest, ListOffsetsResponse,
+    ListOffsetsTopicResponse,
+};
+use crate::protocol::metadata::{METADATA_API_KEY, MetadataRequest};
+use crate::protocol::offset_commit::{
+    OFFSET_COMMIT_API_KEY, OffsetCommitPartitionResponse, OffsetCommitRequest,
+    OffsetCommitResponse, OffsetCommitTopicResponse,
+};
+use crate::protocol::offset_fetch::{
+    OFFSET_FETCH_API_KEY, OffsetFetchPartitionResponse, OffsetFetchRequest, OffsetFetchResponse,
+    OffsetFetchTopicResponse,
+};
+use crate::protocol::produce::{
+    PRODUCE_API_KEY, PartitionProduceResponse, ProduceRequest, ProduceResponse,
+    TopicProduceResponse,
+};
+use crate::protocol::request::RequestHeader;
+use crate::protocol::sync_group::{SYNC_GROUP_API_KEY, SyncGroupRequest, SyncGroupResponse};
+use crate::protocol::txn_offset_commit::{
+    TXN_OFFSET_COMMIT_API_KEY, TxnOffsetCommitRequest, TxnOffsetCommitResponse,
+};
+
+/// The faults that hit one request.
+#[derive(Debug, Default, PartialEq)]
+pub struct Faults {
+    pub latency: Option<Duration>,
+    pub error_code: Option<i16>,
+    pub drop: bool,
+}
+
+/// Rolls the configured faults for each request.
+pub struct Chaos {
+    config: ChaosConfig,
+}
+
+impl Chaos {
+    pub fn new(config: ChaosConfig) -> Self {
+        for api_key in config.errors.keys().filter(|api_key| !can_fail(**api_key)) {
+            tracing::warn!(
+                "chaos.error.codes: API key {} has no error response, so its requests are handled normally",
+                api_key
+            );
+        }
+        Self { config }
+    }
+
+    pub fn roll(&self, api_key: i16) -> Faults {
+        if self.config.is_empty() {
+            return Faults::default();
+        }
+        let mut rng = rand::rng();
+        let mut hits = |percent: f64| rng.random_bool((percent / 100.0).clamp(0.0, 1.0));
+        Faults {
+            latency: self
+                .config
+                .latency
+                .get(&api_key)
+                .filter(|(percent, _)| hits(*percent))
+                .map(|(_, ms)| Duration::from_millis(*ms)),
+            error_code: self
+                .config
+                .errors
+                .get(&api_key)
+                .filter(|(percent, _)| can_fail(api_key) && hits(*percent))
+                .map(|(_, error_code)| *error_code),
+            drop: self
+                .config
+                .drops
+                .get(&api_key)
+                .is_some_and(|percent| hits(*percent)),
+        }
+    }
+}
+
+/// Whether `error_response` can answer the API. The rest, such as ApiVersions or the admin
+/// APIs, have no error path clients would retry on.
+pub fn can_fail(api_key: i16) -> bool {
+    matches!(
+        api_key,
+        PRODUCE_API_KEY
+            | FETCH_API_KEY
+            | LIST_OFFSETS_API_KEY
+            | METADATA_API_KEY
+            | OFFSET_COMMIT_API_KEY
+            | OFFSET_FETCH_API_KEY
+            | FIND_COORDINATOR_API_KEY
+            | JOIN_GROUP_API_KEY
+            | HEARTBEAT_API_KEY
+            | LEAVE_GROUP_API_KEY
+            | SYNC_GROUP_API_KEY
+            | DESCRIBE_GROUPS_API_KEY
+            | LIST_GROUPS_API_KEY
+            | INIT_PRODUCER_ID_API_KEY
+            | ADD_PARTITIONS_TO_TXN_API_KEY
+            | ADD_OFFSETS_TO_TXN_API_KEY
+            | END_TXN_API_KEY
+            | TXN_OFFSET_COMMIT_API_KEY
+    )
+}
+
+impl RequestDispatcher {
+    /// The response to the request with `error_code` in each of its error fields, built
+    /// without handling it. Metadata is the exception: it is served, with every topic failed,
+    /// as clients need the brokers it lists. `None` for produce requests with acks=0.
+    pub(super) async fn error_response<B: Buf>(
+        &self,
+        context: &RequestContext,
+        header: &RequestHeader,
+        body: &mut B,
+        error_code: i16,
+    ) -> Result<Option<BytesMut>, String> {
+        let version = header.api_version;
+        let mut response = BytesMut::new();
+        match header.api_key {
+            PRODUCE_API_KEY => {
+                let request = ProduceRequest::decode(body, version)?;
+                if request.acks == 0 {
+                    return Ok(None);
+                }
+                ProduceResponse {
+                    responses: request
+                        .topics
+                        .into_iter()
+                        .map(|topic| TopicProduceResponse {
+                            name: topic.name,
+                            partitions: topic
+                                .partitions
+                                .into_iter()
+                                .map(|partition| PartitionProduceResponse {
+                                    index: partition.index,
+                                    error_code,
+                                    base_offset: -1,
+                                    log_append_time_ms: -1,
+                                    log_start_offset: -1,
+                                    record_errors: vec![],
+                                    error_message: None,
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                    throttle_time_ms: 0,
+                }
+                .encode(&mut response, version);
+            }
+            FETCH_API_KEY => {
+                let request = FetchRequest::decode(body, version)?;
+                FetchResponse {
+                    throttle_time_ms: 0,
+                    error_code: ErrorCode::None.code(),
+                    session_id: 0,
+                    responses: request
+                        .topics
+                        .into_iter()
+                        .map(|topic| FetchableTopicResponse {
+                            topic: topic.topic,
+                            partitions: topic
+                                .partitions
+                                .into_iter()
+                                .map(|partition| PartitionData {
+                                    partition_index: partition.partition,
+                                    error_code,
+                                    high_watermark: -1,
+                                    last_stable_offset: -1,
+                                    log_start_offset: -1,
+                                    aborted_transactions: None,
+                                    preferred_read_replica: -1,
+                                    records: Records::default(),
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                }
+                .encode(&mut response, version);
+            }
+            LIST_OFFSETS_API_KEY => {
+                let request = ListOffsetsRequest::decode(body, version)?;
+                ListOffsetsResponse {
+                    throttle_time_ms: 0,
+                    topics: request
+                        .topics
+                        .into_iter()
+                        .map(|topic| ListOffsetsTopicResponse {
+                            name: topic.name,
+                            partitions: topic
+                                .partitions
+                                .into_iter()
+                                .map(|partition| ListOffsetsPartitionResponse {
+                                    partition_index: partition.partition_index,
+                                    error_code,
+                                    timestamp: -1,
+                                    offset: -1,
+                                    leader_epoch: -1,
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                }
+                .encode(&mut response, version);
+            }
+            METADATA_API_KEY => {
+                let request = MetadataRequest::decode(body, version)?;
+                let mut metadata = self.metadata_handler.handle(context, request).await;
+                for topic in &mut metadata.topics {
+                    topic.error_code = error_code;
+                    topic.partitions.clear();
+                }
+                metadata.encode(&mut response, version);
+            }
+            OFFSET_COMMIT_API_KEY => {
+                let request = OffsetCommitRequest::decode(body, version)?;
+                OffsetCommitResponse {
+                    throttle_time_ms: 0,
+                    topics: request
+                        .topics
+                        .into_iter()
+                        .map(|topic| OffsetCommitTopicResponse {
+                            name: topic.name,
+                            partitions: topic
+                                .partitions
+                                .into_iter()
+                                .map(|partition| OffsetCommitPartitionResponse {
+                                    partition_index: partition.partition_index,
+                                    error_code,
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                }
+                .encode(&mut response, version);
+            }
+            OFFSET_FETCH_API_KEY => {
+                let request = OffsetFetchRequest::decode(body, version)?;
+                OffsetFetchResponse {
+                    throttle_time_ms: 0,
+                    topics: request
+                        .topics
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|topic| OffsetFetchTopicResponse {
+                            name: topic.name,
+                            partitions: topic
+                                .partition_indexes
+                                .into_iter()
+                                .map(|partition_index| OffsetFetchPartitionResponse {
+                                    partition_index,
+                                    committed_offset: -1,
+                                    committed_leader_epoch: -1,
+                                    metadata: None,
+                                    error_code,
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                    error_code,
+                }
+                .encode(&mut response, version);
+            }
+            FIND_COORDINATOR_API_KEY => {
+                FindCoordinatorRequest::decode(body, version)?;
+                FindCoordinatorResponse {
+                    throttle_time_ms: 0,
+                    error_code,
+                    error_message: None,
+                    node_id: -1,
+                    host: String::new(),
+                    port: -1,
+                }
+                .encode(&mut response, version);
+            }
+            JOIN_GROUP_API_KEY => {
+                let request = JoinGroupRequest::decode(body, version)?;
+                JoinGroupResponse {
+                    throttle_time_ms: 0,
+                    error_code,
+                    generation_id: -1,
+                    protocol_name: String::new(),
+                    leader: String::new(),
+                    member_id: request.member_id,
+                    members: vec![],
+                }
+                .encode(&mut response, version);
+            }
+            HEARTBEAT_API_KEY => {
+                HeartbeatRequest::decode(body, version)?;
+                HeartbeatResponse {
+                    throttle_time_ms: 0,
+                    error_code,
+                }
+                .encode(&mut response, version);
+            }
+            LEAVE_GROUP_API_KEY => {
+                LeaveGroupRequest::decode(body, version)?;
+                LeaveGroupResponse {
+                    throttle_time_ms: 0,
+                    error_code,
+                }
+                .encode(&mut response, version);
+            }
+            SYNC_GROUP_API_KEY => {
+                SyncGroupRequest::decode(body, version)?;
+                SyncGroupResponse {
+                    throttle_time_ms: 0,
+                    error_code,
+                    assignment: vec![],
+                }
+                .encode(&mut response, version);
+            }
+            DESCRIBE_GROUPS_API_KEY => {
+                let request = DescribeGroupsRequest::decode(body, version)?;
+                DescribeGroupsResponse {
+                    throttle_time_ms: 0,
+                    groups: request
+                        .groups
+                        .into_iter()
+                        .map(|group_id| DescribedGroup {
+                            error_code,
+                            group_id,
+                            group_state: String::new(),
+                            protocol_type: String::new(),
+                            protocol_data: String::new(),
+                            members: vec![],
+                            authorized_operations: i32::MIN,
+                        })
+                        .collect(),
+                }
+                .encode(&mut response, version);
+            }
+            LIST_GROUPS_API_KEY => {
+                ListGroupsRequest::decode(body, version)?;
+                ListGroupsResponse {
+                    throttle_time_ms: 0,
+                    error_code,
+                    groups: vec![],
+                }
+                .encode(&mut response, version);
+            }
+            INIT_PRODUCER_ID_API_KEY => {
+                InitProducerIdRequest::decode(body, version)?;
+                InitProducerIdResponse {
+                    throttle_time_ms: 0,
+                    error_code,
+                    producer_id: -1,
+                    producer_epoch: -1,
+                }
+                .encode(&mut response, version);
+            }
+            ADD_PARTITIONS_TO_TXN_API_KEY => {
+                let request = AddPartitionsToTxnRequest::decode(body, version)?;
+                AddPartitionsToTxnResponse {
+                    throttle_time_ms: 0,
+                    results: request
+                        .topics
+                        .into_iter()
+                        .map(|topic| AddPartitionsToTxnTopicResult {
+                            name: topic.name,
+                            results: topic
+                                .partitions
+                                .into_iter()
+                                .map(|partition_index| AddPartitionsToTxnPartitionResult {
+                                    partition_index,
+                                    error_code,
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                }
+                .encode(&mut response, version);
+            }
+            ADD_OFFSETS_TO_TXN_API_KEY => {
+                AddOffsetsToTxnRequest::decode(body, version)?;
+                AddOffsetsToTxnResponse {
+                    throttle_time_ms: 0,
+                    error_code,
+                }
+                .encode(&mut response, version);
+            }
+            END_TXN_API_KEY => {
+                EndTxnRequest::decode(body, version)?;
+                EndTxnResponse {
+                    throttle_time_ms: 0,
+                    error_code,
+                }
+                .encode(&mut response, version);
+            }
+            TXN_OFFSET_COMMIT_API_KEY => {
+                let request = TxnOffsetCommitRequest::decode(body, version)?;
+                TxnOffsetCommitResponse {
+                    throttle_time_ms: 0,
+                    topics: request
+                        .topics
+                        .into_iter()
+                        .map(|topic| OffsetCommitTopicResponse {
+                            name: topic.name,
+                            partitions: topic
+                                .partitions
+                                .into_iter()
+                                .map(|partition| OffsetCommitPartitionResponse {
+                                    partition_index: partition.partition_index,
+                                    error_code,
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                }
+                .encode(&mut response, version);
+            }
+            _ => return Err(format!("No error response for API key {}", header.api_key)),
+        }
+        Ok(Some(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BrokerConfig;
+
+    #[test]
+    fn test_faults_hit_their_api_at_their_rate() {
+        let config = BrokerConfig::from_properties(
+            "chaos.latency.ms=0:100:250\n\
+             chaos.error.codes=0:100:6, 18:100:6\n\
+             chaos.dropped.responses=1:100,3:0\n",
+        )
+        .unwrap();
+        let chaos = Chaos::new(config.chaos);
+
+        let produce = chaos.roll(PRODUCE_API_KEY);
+        assert_eq!(produce.latency, Some(Duration::from_millis(250)));
+        assert_eq!(
+            produce.error_code,
+            Some(ErrorCode::NotLeaderOrFollower.code())
+        );
+        assert!(!produce.drop);
+        assert!(chaos.roll(FETCH_API_KEY).drop);
+        // ApiVersions has no error response, and 0% never hits
+        assert_eq!(chaos.roll(18), Faults::default());
+        assert_eq!(chaos.roll(METADATA_API_KEY), Faults::default());
+
+        assert!(BrokerConfig::from_properties("chaos.error.codes=0:100").is_err());
+        let config = BrokerConfig::from_properties("chaos.dropped.responses=0:150").unwrap();
+        assert!(!config.check().is_empty());
+    }
+}
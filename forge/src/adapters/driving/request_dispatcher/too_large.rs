@@ -0,0 +1,168 @@
+use bytes::BytesMut;
+
+use super::RequestDispatcher;
+use crate::application::request_context::RequestContext;
+use crate::core::error::ErrorCode;
+use crate::protocol::add_offsets_to_txn::{ADD_OFFSETS_TO_TXN_API_KEY, AddOffsetsToTxnResponse};
+use crate::protocol::add_partitions_to_txn::{
+    ADD_PARTITIONS_TO_TXN_API_KEY, AddPartitionsToTxnResponse,
+};
+use crate::protocol::api_versions::{API_VERSIONS_API_KEY, ApiVersionsResponse};
+use crate::protocol::describe_groups::{DESCRIBE_GROUPS_API_KEY, DescribeGroupsResponse};
+use crate::protocol::end_txn::{END_TXN_API_KEY, EndTxnResponse};
+use crate::protocol::fetch::{FETCH_API_KEY, FetchResponse};
+use crate::protocol::find_coordinator::{FIND_COORDINATOR_API_KEY, FindCoordinatorResponse};
+use crate::protocol::heartbeat::{HEARTBEAT_API_KEY, HeartbeatResponse};
+use crate::protocol::init_producer_id::{INIT_PRODUCER_ID_API_KEY, InitProducerIdResponse};
+use crate::protocol::join_group::{JOIN_GROUP_API_KEY, JoinGroupResponse};
+use crate::protocol::leave_group::{LEAVE_GROUP_API_KEY, LeaveGroupResponse};
+use crate::protocol::list_groups::{LIST_GROUPS_API_KEY, ListGroupsResponse};
+use crate::protocol::list_offsets::{LIST_OFFSETS_API_KEY, ListOffsetsResponse};
+use crate::protocol::metadata::{METADATA_API_KEY, MetadataRequest};
+use crate::protocol::offset_commit::{OFFSET_COMMIT_API_KEY, OffsetCommitResponse};
+use crate::protocol::offset_fetch::{OFFSET_FETCH_API_KEY, OffsetFetchResponse};
+use crate::protocol::request::RequestHeader;
+use crate::protocol::sync_group::{SYNC_GROUP_API_KEY, SyncGroupResponse};
+use crate::protocol::txn_offset_commit::{TXN_OFFSET_COMMIT_API_KEY, TxnOffsetCommitResponse};
+
+impl RequestDispatcher {
+    /// The response to a request over its size limit, built from its header alone as its
+    /// body was skipped. APIs with a top-level error code get MESSAGE_TOO_LARGE; the rest
+    /// answer with empty lists, and Metadata with the brokers but no topics. `None` when no
+    /// response can be built, as for Produce, where acks=0 requests expect none and acks is
+    /// in the body.
+    pub async fn too_large_response(
+        &self,
+        context: &RequestContext,
+        header: &RequestHeader,
+    ) -> Option<BytesMut> {
+        let version = header.api_version;
+        let supported = Self::supported_apis().into_iter().any(|api| {
+            api.api_key == header.api_key && (api.min_version..=api.max_version).contains(&version)
+        });
+        if !supported {
+            return None;
+        }
+
+        let error_code = ErrorCode::MessageTooLarge.code();
+        let mut response = BytesMut::new();
+        match header.api_key {
+            FETCH_API_KEY => FetchResponse {
+                throttle_time_ms: 0,
+                error_code,
+                session_id: 0,
+                responses: vec![],
+            }
+            .encode(&mut response, version),
+            LIST_OFFSETS_API_KEY => ListOffsetsResponse {
+                throttle_time_ms: 0,
+                topics: vec![],
+            }
+            .encode(&mut response, version),
+            METADATA_API_KEY => {
+                let request = MetadataRequest {
+                    topics: Some(vec![]),
+                    allow_auto_topic_creation: false,
+                    include_cluster_authorized_operations: false,
+                    include_topic_authorized_operations: false,
+                };
+                self.metadata_handler
+                    .handle(context, request)
+                    .await
+                    .encode(&mut response, version);
+            }
+            OFFSET_COMMIT_API_KEY => OffsetCommitResponse {
+                throttle_time_ms: 0,
+                topics: vec![],
+            }
+            .encode(&mut response, version),
+            OFFSET_FETCH_API_KEY => OffsetFetchResponse {
+                throttle_time_ms: 0,
+                topics: vec![],
+                error_code,
+            }
+            .encode(&mut response, version),
+            FIND_COORDINATOR_API_KEY => FindCoordinatorResponse {
+                throttle_time_ms: 0,
+                error_code,
+                error_message: None,
+                node_id: -1,
+                host: String::new(),
+                port: -1,
+            }
+            .encode(&mut response, version),
+            JOIN_GROUP_API_KEY => JoinGroupResponse {
+                throttle_time_ms: 0,
+                error_code,
+                generation_id: -1,
+                protocol_name: String::new(),
+                leader: String::new(),
+                member_id: String::new(),
+                members: vec![],
+            }
+            .encode(&mut response, version),
+            HEARTBEAT_API_KEY => HeartbeatResponse {
+                throttle_time_ms: 0,
+                error_code,
+            }
+            .encode(&mut response, version),
+            LEAVE_GROUP_API_KEY => LeaveGroupResponse {
+                throttle_time_ms: 0,
+                error_code,
+            }
+            .encode(&mut response, version),
+            SYNC_GROUP_API_KEY => SyncGroupResponse {
+                throttle_time_ms: 0,
+                error_code,
+                assignment: vec![],
+            }
+            .encode(&mut response, version),
+            DESCRIBE_GROUPS_API_KEY => DescribeGroupsResponse {
+                throttle_time_ms: 0,
+                groups: vec![],
+            }
+            .encode(&mut response, version),
+            LIST_GROUPS_API_KEY => ListGroupsResponse {
+                throttle_time_ms: 0,
+                error_code,
+                groups: vec![],
+            }
+            .encode(&mut response, version),
+            API_VERSIONS_API_KEY => ApiVersionsResponse {
+                error_code,
+                api_keys: Self::supported_apis(),
+                throttle_time_ms: 0,
+            }
+            .encode(&mut response, version),
+            INIT_PRODUCER_ID_API_KEY => InitProducerIdResponse {
+                throttle_time_ms: 0,
+                error_code,
+                producer_id: -1,
+                producer_epoch: -1,
+            }
+            .encode(&mut response, version),
+            ADD_PARTITIONS_TO_TXN_API_KEY => AddPartitionsToTxnResponse {
+                throttle_time_ms: 0,
+                results: vec![],
+            }
+            .encode(&mut response, version),
+            ADD_OFFSETS_TO_TXN_API_KEY => AddOffsetsToTxnResponse {
+                throttle_time_ms: 0,
+                error_code,
+            }
+            .encode(&mut response, version),
+            END_TXN_API_KEY => EndTxnResponse {
+                throttle_time_ms: 0,
+                error_code,
+            }
+            .encode(&mut response, version),
+            TXN_OFFSET_COMMIT_API_KEY => TxnOffsetCommitResponse {
+                throttle_time_ms: 0,
+                topics: vec![],
+            }
+            .encode(&mut response, version),
+            _ => return None,
+        }
+        Some(response)
+    }
+}
@@ -0,0 +1,121 @@
+use async_trait::async_trait;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::adapters::driving::request_dispatcher::RequestDispatcher;
+use crate::shared::collections::FlatMap;
+use crate::shared::metrics::{
+    Histogram, LATENCY_BUCKETS, MetricType, MetricsSource, MetricsWriter,
+};
+
+/// Where a request spent its time, in Kafka's breakdown.
+const PHASES: [&str; 6] = [
+    "request_queue",
+    "local",
+    "remote",
+    "response_queue",
+    "response_send",
+    "total",
+];
+
+/// Timestamps a request collects on its way through a connection's reader, handler and
+/// writer.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestTiming {
+    pub api_key: i16,
+    /// When the reader finished reading the request.
+    pub received: Instant,
+    /// When the handler took it off the request queue.
+    pub dequeued: Instant,
+    /// Of the time in the handler, how long it was busy rather than waiting for purgatory or
+    /// throttling.
+    pub local: Duration,
+    /// When the handler was done with it.
+    pub handled: Instant,
+}
+
+impl RequestTiming {
+    pub fn new(api_key: i16, received: Instant) -> Self {
+        Self {
+            api_key,
+            received,
+            dequeued: received,
+            local: Duration::ZERO,
+            handled: received,
+        }
+    }
+}
+
+/// Latency histograms per API and phase.
+#[derive(Debug, Default)]
+pub struct RequestMetrics {
+    /// A std mutex: held only to record or copy histograms.
+    latencies: Mutex<FlatMap<i16, Vec<Histogram>>>,
+}
+
+impl RequestMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a finished request. `sent` is when the writer started and finished sending its
+    /// response, `None` for requests that get no response.
+    pub fn record(&self, timing: &RequestTiming, sent: Option<(Instant, Instant)>) {
+        let handler_time = timing.handled.saturating_duration_since(timing.dequeued);
+        let (response_queue, response_send, done) = match sent {
+            Some((started, finished)) => (
+                started.saturating_duration_since(timing.handled),
+                finished.saturating_duration_since(started),
+                finished,
+            ),
+            None => (Duration::ZERO, Duration::ZERO, timing.handled),
+        };
+        let phases = [
+            timing.dequeued.saturating_duration_since(timing.received),
+            timing.local,
+            handler_time.saturating_sub(timing.local),
+            response_queue,
+            response_send,
+            done.saturating_duration_since(timing.received),
+        ];
+
+        let mut latencies = self.latencies.lock().unwrap_or_else(|e| e.into_inner());
+        if latencies.get(&timing.api_key).is_none() {
+            latencies.insert(
+                timing.api_key,
+                vec![Histogram::new(&LATENCY_BUCKETS); PHASES.len()],
+            );
+        }
+        if let Some(histograms) = latencies.get_mut(&timing.api_key) {
+            for (histogram, duration) in histograms.iter_mut().zip(phases) {
+                histogram.observe(duration.as_secs_f64());
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl MetricsSource for RequestMetrics {
+    async fn write_metrics(&self, metrics: &mut MetricsWriter) {
+        let latencies = self
+            .latencies
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let name = "forge_request_time_seconds";
+        metrics.family(
+            name,
+            MetricType::Histogram,
+            "Request latency by API and phase: request_queue, local (busy in the handler), \
+             remote (waiting in purgatory or throttled), response_queue, response_send and total.",
+        );
+        for (api_key, histograms) in latencies.iter() {
+            let api =
+                RequestDispatcher::api_name(*api_key).map_or(api_key.to_string(), str::to_string);
+            for (phase, histogram) in PHASES.iter().zip(histograms) {
+                metrics.histogram(name, &[("api", &api), ("phase", phase)], histogram);
+            }
+        }
+    }
+}
@@ -0,0 +1,1303 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::application::audit;
+use crate::application::fetch_handler::FetchHandler;
+use crate::application::group_handler::GroupHandler;
+use crate::application::list_offsets_handler::ListOffsetsHandler;
+use crate::application::metadata_listener::BrokerMetadataListener;
+use crate::application::produce_handler::ProduceHandler;
+use crate::application::replica_manager::ACKS_ALL;
+use crate::application::request_context::RequestContext;
+use crate::application::sasl::scram::ScramMechanism;
+use crate::client::partitioner::partition_for_key;
+use crate::config::SecurityProtocol;
+use crate::core::domain::principal::KafkaPrincipal;
+use crate::core::domain::record::Record;
+use crate::core::domain::record_batch::RecordBatch;
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::CredentialStore;
+use crate::protocol::fetch::{FetchPartition, FetchRequest, FetchTopic};
+use crate::protocol::list_offsets::{
+    EARLIEST_TIMESTAMP, LATEST_TIMESTAMP, ListOffsetsPartition, ListOffsetsRequest,
+    ListOffsetsTopic,
+};
+use crate::protocol::offset_commit::{
+    OffsetCommitPartition, OffsetCommitRequest, OffsetCommitTopic,
+};
+use crate::protocol::offset_fetch::{OffsetFetchRequest, OffsetFetchTopic};
+use crate::protocol::produce::{PartitionProduceData, ProduceRequest, TopicProduceData};
+use crate::shared::collections::FlatMap;
+use crate::shared::constants::DEFAULT_REST_MAX_CONNECTIONS;
+use crate::shared::scheduler::spawn_named;
+use crate::shared::time::current_time_ms;
+
+const MAX_REQUEST_HEAD_BYTES: usize = 8 * 1024;
+const MAX_REQUEST_BODY_BYTES: usize = 8 * 1024 * 1024;
+/// Covers the longest fetch a consumer may ask to wait for, plus the time to answer it.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+const PRODUCE_TIMEOUT_MS: i32 = 30 * 1000;
+const DEFAULT_FETCH_WAIT_MS: i32 = 1000;
+const MAX_FETCH_WAIT_MS: i32 = 30 * 1000;
+const DEFAULT_FETCH_MAX_BYTES: i32 = 1024 * 1024;
+const CLIENT_ID: &str = "rest-proxy";
+/// The mechanism audit events name for requests carrying HTTP Basic credentials.
+const BASIC_AUTH_MECHANISM: &str = "BASIC";
+
+/// An HTTP/JSON front to the produce and fetch paths, for curl and services without a Kafka
+/// client. Keys and values are base64 in both directions.
+/// - `POST /topics/{topic}`: `{"records": [{"key", "value", "partition"}]}`, each field
+///   optional; records without a partition go by key, or round-robin without one
+/// - `POST /consumers/{group}`: creates a consumer instance, optionally
+///   `{"name", "auto.offset.reset": "earliest" | "latest"}`
+/// - `POST|GET /consumers/{group}/instances/{id}/assignments`: `{"partitions": [{"topic",
+///   "partition"}]}` to read from; instances do not join the group, so nothing rebalances
+/// - `GET /consumers/{group}/instances/{id}/records?timeout=&max_bytes=`: the next records,
+///   starting from the group's committed offsets
+/// - `POST /consumers/{group}/instances/{id}/offsets`: commits where the instance is, or the
+///   `{"offsets": [{"topic", "partition", "offset"}]}` given, each the next offset to read
+/// - `DELETE /consumers/{group}/instances/{id}`
+///
+/// Requests act as the anonymous principal, or with `with_basic_auth` as the user whose HTTP
+/// Basic credentials they carry, and must reach the broker that leads the partition or
+/// coordinates the group, as Kafka requests would. Each request gets one response and the
+/// connection is closed; connections beyond `with_max_connections` are closed unanswered.
+pub struct RestProxy {
+    produce_handler: ProduceHandler,
+    fetch_handler: FetchHandler,
+    list_offsets_handler: ListOffsetsHandler,
+    group_handler: GroupHandler,
+    /// Gives the partition count of each topic.
+    metadata: Arc<Mutex<BrokerMetadataListener>>,
+    consumers: std::sync::Mutex<FlatMap<(String, String), ConsumerEntry>>,
+    instance_timeout: Duration,
+    next_partition: AtomicUsize,
+    /// Checks Basic credentials when set; requests without valid ones are refused.
+    credentials: Option<Arc<dyn CredentialStore>>,
+    /// How long a request with invalid credentials waits for its refusal.
+    failed_authentication_delay: Duration,
+    max_connections: usize,
+}
+
+struct ConsumerEntry {
+    instance: Arc<Mutex<ConsumerInstance>>,
+    last_used: Instant,
+}
+
+struct ConsumerInstance {
+    /// `EARLIEST_TIMESTAMP` or `LATEST_TIMESTAMP`, where reading starts without a committed
+    /// offset.
+    reset_timestamp: i64,
+    /// The next offset to read from each assigned partition, once looked up.
+    positions: FlatMap<TopicPartition, Option<i64>>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    body: String,
+}
+
+impl Response {
+    fn json<T: Serialize>(value: &T) -> Self {
+        Self {
+            status: 200,
+            body: serde_json::to_string(value).expect("responses always serialize"),
+        }
+    }
+
+    fn no_content() -> Self {
+        Self {
+            status: 204,
+            body: String::new(),
+        }
+    }
+
+    /// The Confluent REST proxy's error body, with the HTTP status as the error code.
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        #[derive(Serialize)]
+        struct Error {
+            error_code: u16,
+            message: String,
+        }
+        Self {
+            status,
+            body: serde_json::to_string(&Error {
+                error_code: status,
+                message: message.into(),
+            })
+            .expect("responses always serialize"),
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            204 => "No Content",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            411 => "Length Required",
+            413 => "Payload Too Large",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ProduceBody {
+    records: Vec<ProduceRecord>,
+}
+
+#[derive(Deserialize)]
+struct ProduceRecord {
+    key: Option<String>,
+    value: Option<String>,
+    partition: Option<i32>,
+}
+
+#[derive(Serialize)]
+struct ProduceOffsets {
+    offsets: Vec<ProduceOffset>,
+}
+
+#[derive(Serialize)]
+struct ProduceOffset {
+    partition: i32,
+    offset: i64,
+    error_code: i16,
+    error: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct CreateConsumerBody {
+    name: Option<String>,
+    #[serde(rename = "auto.offset.reset")]
+    auto_offset_reset: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CreatedConsumer {
+    instance_id: String,
+    base_uri: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Assignments {
+    partitions: Vec<PartitionRef>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PartitionRef {
+    topic: String,
+    partition: i32,
+}
+
+#[derive(Deserialize, Default)]
+struct CommitBody {
+    offsets: Option<Vec<PartitionOffset>>,
+}
+
+#[derive(Deserialize)]
+struct PartitionOffset {
+    topic: String,
+    partition: i32,
+    offset: i64,
+}
+
+#[derive(Serialize)]
+struct ConsumerRecord {
+    topic: String,
+    partition: i32,
+    offset: i64,
+    timestamp: i64,
+    key: Option<String>,
+    value: Option<String>,
+}
+
+impl RestProxy {
+    pub fn new(
+        produce_handler: ProduceHandler,
+        fetch_handler: FetchHandler,
+        list_offsets_handler: ListOffsetsHandler,
+        group_handler: GroupHandler,
+        metadata: Arc<Mutex<BrokerMetadataListener>>,
+        instance_timeout: Duration,
+    ) -> Self {
+        Self {
+            produce_handler,
+            fetch_handler,
+            list_offsets_handler,
+            group_handler,
+            metadata,
+            consumers: std::sync::Mutex::new(FlatMap::new()),
+            instance_timeout,
+            next_partition: AtomicUsize::new(0),
+            credentials: None,
+            failed_authentication_delay: Duration::ZERO,
+            max_connections: DEFAULT_REST_MAX_CONNECTIONS,
+        }
+    }
+
+    pub fn with_basic_auth(
+        mut self,
+        credentials: Arc<dyn CredentialStore>,
+        failed_authentication_delay: Duration,
+    ) -> Self {
+        self.credentials = Some(credentials);
+        self.failed_authentication_delay = failed_authentication_delay;
+        self
+    }
+
+    pub fn with_max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    /// Binds `address` and serves from a background task until `cancel_token` is cancelled.
+    pub async fn start(
+        self,
+        address: &str,
+        cancel_token: CancellationToken,
+    ) -> Result<JoinHandle<()>, String> {
+        let listener = TcpListener::bind(address)
+            .await
+            .map_err(|e| format!("Failed to bind REST listener {}: {}", address, e))?;
+        tracing::info!("REST proxy started on {}", address);
+
+        let connections = Arc::new(Semaphore::new(self.max_connections));
+        let proxy = Arc::new(self);
+        Ok(spawn_named("rest-proxy", async move {
+            loop {
+                tokio::select! {
+                    accept_result = listener.accept() => match accept_result {
+                        Ok((socket, peer_address)) => {
+                            let Ok(permit) = connections.clone().try_acquire_owned() else {
+                                tracing::warn!(
+                                    "Closing REST connection from {}: {} connections are open",
+                                    peer_address,
+                                    proxy.max_connections
+                                );
+                                continue;
+                            };
+                            let proxy = proxy.clone();
+                            spawn_named("rest-request", async move {
+                                let _permit = permit;
+                                let served = tokio::time::timeout(
+                                    REQUEST_TIMEOUT,
+                                    proxy.serve(socket, peer_address.ip()),
+                                )
+                                .await;
+                                match served {
+                                    Ok(Ok(())) => {}
+                                    Ok(Err(e)) => tracing::warn!(
+                                        "REST request from {} failed: {}",
+                                        peer_address,
+                                        e
+                                    ),
+                                    Err(_) => tracing::warn!(
+                                        "REST request from {} timed out",
+                                        peer_address
+                                    ),
+                                }
+                            });
+                        }
+                        Err(e) => tracing::error!("Failed to accept REST connection: {}", e),
+                    },
+                    _ = cancel_token.cancelled() => break,
+                }
+            }
+        }))
+    }
+
+    async fn serve(&self, mut socket: TcpStream, peer: IpAddr) -> Result<(), String> {
+        let response = match Self::read_request(&mut socket).await? {
+            Ok(request) => match self.authenticate(&request, peer).await {
+                Ok(principal) => {
+                    let context = RequestContext {
+                        principal,
+                        client_host: peer.to_string(),
+                        client_id: CLIENT_ID.to_string(),
+                        listener: SecurityProtocol::Plaintext,
+                    };
+                    self.route(&context, &request)
+                        .await
+                        .unwrap_or_else(|error| error)
+                }
+                Err(error) => error,
+            },
+            Err(error) => error,
+        };
+
+        let mut headers = String::new();
+        if !response.body.is_empty() {
+            headers.push_str("Content-Type: application/json\r\n");
+        }
+        if response.status == 401 {
+            headers.push_str("WWW-Authenticate: Basic realm=\"forge\"\r\n");
+        }
+        let head = format!(
+            "HTTP/1.1 {} {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+            response.status,
+            response.reason(),
+            headers,
+            response.body.len()
+        );
+        socket
+            .write_all(head.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        socket
+            .write_all(response.body.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        socket.shutdown().await.map_err(|e| e.to_string())
+    }
+
+    /// Reads the request head and the body its Content-Length announces. The inner error is
+    /// a request that can be answered, the outer one a connection that cannot.
+    async fn read_request<S: AsyncRead + Unpin>(
+        socket: &mut S,
+    ) -> Result<Result<Request, Response>, String> {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        let head_end = loop {
+            let end = buf.windows(4).position(|window| window == b"\r\n\r\n");
+            if end.unwrap_or(buf.len()) > MAX_REQUEST_HEAD_BYTES {
+                return Err("Request head too large".to_string());
+            }
+            if let Some(end) = end {
+                break end;
+            }
+            let read = socket.read(&mut chunk).await.map_err(|e| e.to_string())?;
+            if read == 0 {
+                return Err("Connection closed before the request was complete".to_string());
+            }
+            buf.extend_from_slice(&chunk[..read]);
+        };
+        let head = String::from_utf8(buf[..head_end].to_vec()).map_err(|e| e.to_string())?;
+        let mut lines = head.split("\r\n");
+        let mut parts = lines.next().unwrap_or_default().split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let target = parts.next().unwrap_or_default();
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+        let mut content_length = 0;
+        let mut authorization = None;
+        for line in lines {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = match value.parse::<usize>() {
+                    Ok(length) => length,
+                    Err(_) => return Ok(Err(Response::error(400, "Invalid Content-Length"))),
+                };
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                return Ok(Err(Response::error(
+                    411,
+                    "Send the body with a Content-Length",
+                )));
+            }
+        }
+        if content_length > MAX_REQUEST_BODY_BYTES {
+            return Ok(Err(Response::error(
+                413,
+                format!("Bodies are limited to {} bytes", MAX_REQUEST_BODY_BYTES),
+            )));
+        }
+
+        let mut body = buf.split_off(head_end + 4);
+        if body.len() < content_length {
+            let start = body.len();
+            body.resize(content_length, 0);
+            socket
+                .read_exact(&mut body[start..])
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        body.truncate(content_length);
+        Ok(Ok(Request {
+            method,
+            path: path.to_string(),
+            query: query.to_string(),
+            authorization,
+            body,
+        }))
+    }
+
+    /// The principal a request acts as: the user of its Basic credentials when they are
+    /// required, which may be a PLAIN password or a SCRAM user's. Outcomes are audited as
+    /// SASL logins are, and refusals held back by the failed authentication delay.
+    async fn authenticate(
+        &self,
+        request: &Request,
+        peer: IpAddr,
+    ) -> Result<KafkaPrincipal, Response> {
+        let Some(credentials) = &self.credentials else {
+            return Ok(KafkaPrincipal::anonymous());
+        };
+        let client_host = peer.to_string();
+        let Some((username, password)) = request
+            .authorization
+            .as_deref()
+            .and_then(|value| value.split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("basic"))
+            .and_then(|(_, encoded)| STANDARD.decode(encoded.trim()).ok())
+            .and_then(|decoded| String::from_utf8(decoded).ok())
+            .and_then(|decoded| {
+                let (username, password) = decoded.split_once(':')?;
+                Some((username.to_string(), password.to_string()))
+            })
+            .filter(|(username, _)| !username.is_empty())
+        else {
+            return Err(self
+                .refuse(&client_host, "Missing or malformed Basic credentials")
+                .await);
+        };
+
+        if Self::verify(credentials.as_ref(), &username, password).await {
+            let principal = KafkaPrincipal::user(username);
+            audit::authentication_succeeded(
+                SecurityProtocol::Plaintext,
+                &client_host,
+                BASIC_AUTH_MECHANISM,
+                &principal,
+            );
+            return Ok(principal);
+        }
+        Err(self
+            .refuse(
+                &client_host,
+                &format!("Invalid credentials for user {}", username),
+            )
+            .await)
+    }
+
+    /// Checks `password` as a PLAIN password, then as the password of a SCRAM user. SCRAM
+    /// derives the key on the blocking pool, as its iterated hashing would stall the runtime.
+    async fn verify(credentials: &dyn CredentialStore, username: &str, password: String) -> bool {
+        if credentials.verify_plain(username, &password).await {
+            return true;
+        }
+        let password = Arc::new(password);
+        for mechanism in [ScramMechanism::Sha256, ScramMechanism::Sha512] {
+            let Some(credential) = credentials
+                .scram_credential(mechanism.name(), username)
+                .await
+            else {
+                continue;
+            };
+            let password = password.clone();
+            let verified = tokio::task::spawn_blocking(move || {
+                mechanism.verify_password(&credential, &password)
+            })
+            .await
+            .unwrap_or(false);
+            if verified {
+                return true;
+            }
+        }
+        false
+    }
+
+    async fn refuse(&self, client_host: &str, reason: &str) -> Response {
+        audit::authentication_failed(
+            SecurityProtocol::Plaintext,
+            client_host,
+            Some(BASIC_AUTH_MECHANISM),
+            reason,
+        );
+        tokio::time::sleep(self.failed_authentication_delay).await;
+        Response::error(401, "Valid Basic credentials are required")
+    }
+
+    async fn route(
+        &self,
+        context: &RequestContext,
+        request: &Request,
+    ) -> Result<Response, Response> {
+        let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("POST", ["topics", topic]) => self.produce(context, topic, &request.body).await,
+            ("POST", ["consumers", group]) => self.create_consumer(group, &request.body),
+            ("DELETE", ["consumers", group, "instances", id]) => {
+                self.consumer(group, id)?;
+                self.lock_consumers()
+                    .remove(&(group.to_string(), id.to_string()));
+                Ok(Response::no_content())
+            }
+            ("POST", ["consumers", group, "instances", id, "assignments"]) => {
+                let assignments: Assignments = parse_body(&request.body)?;
+                let consumer = self.consumer(group, id)?;
+                let mut consumer = consumer.lock().await;
+                consumer.positions = FlatMap::new();
+                for partition in assignments.partitions {
+                    consumer.positions.insert(
+                        TopicPartition::new(partition.topic, partition.partition),
+                        None,
+                    );
+                }
+                Ok(Response::no_content())
+            }
+            ("GET", ["consumers", group, "instances", id, "assignments"]) => {
+                let consumer = self.consumer(group, id)?;
+                let partitions = consumer
+                    .lock()
+                    .await
+                    .positions
+                    .keys()
+                    .map(|partition| PartitionRef {
+                        topic: partition.topic.clone(),
+                        partition: partition.partition,
+                    })
+                    .collect();
+                Ok(Response::json(&Assignments { partitions }))
+            }
+            ("GET", ["consumers", group, "instances", id, "records"]) => {
+                let consumer = self.consumer(group, id)?;
+                let mut consumer = consumer.lock().await;
+                self.consume(context, group, &mut consumer, &request.query)
+                    .await
+            }
+            ("POST", ["consumers", group, "instances", id, "offsets"]) => {
+                let body: CommitBody = if request.body.is_empty() {
+                    CommitBody::default()
+                } else {
+                    parse_body(&request.body)?
+                };
+                let consumer = self.consumer(group, id)?;
+                let consumer = consumer.lock().await;
+                self.commit(context, group, &consumer, body).await
+            }
+            (
+                _,
+                ["topics", _]
+                | ["consumers", _]
+                | ["consumers", _, "instances", _]
+                | [
+                    "consumers",
+                    _,
+                    "instances",
+                    _,
+                    "assignments" | "records" | "offsets",
+                ],
+            ) => Err(Response::error(405, "Method not allowed")),
+            _ => Err(Response::error(404, "Not found")),
+        }
+    }
+
+    async fn produce(
+        &self,
+        context: &RequestContext,
+        topic: &str,
+        body: &[u8],
+    ) -> Result<Response, Response> {
+        let body: ProduceBody = parse_body(body)?;
+        let partition_count = self
+            .metadata
+            .lock()
+            .await
+            .metadata
+            .topics
+            .get(&topic.to_string())
+            .map(|topic| topic.partitions.len());
+
+        // Records keep their order within each partition's batch
+        let mut batches: FlatMap<i32, Vec<Record>> = FlatMap::new();
+        let mut placements = Vec::with_capacity(body.records.len());
+        for record in body.records {
+            let key = decode_base64(record.key, "key")?;
+            let value = decode_base64(record.value, "value")?;
+            let partition = match (record.partition, partition_count, &key) {
+                (Some(partition), _, _) => partition,
+                (None, Some(count), Some(key)) if count > 0 => partition_for_key(key, count),
+                (None, Some(count), None) if count > 0 => {
+                    (self.next_partition.fetch_add(1, Ordering::Relaxed) % count) as i32
+                }
+                // Left for the produce path to reject, or to create the topic
+                (None, _, _) => 0,
+            };
+            if batches.get(&partition).is_none() {
+                batches.insert(partition, Vec::new());
+            }
+            let records = batches.get_mut(&partition).expect("inserted above");
+            placements.push((partition, records.len() as i64));
+            records.push(Record::new(records.len() as i32, key, value));
+        }
+
+        let timestamp = current_time_ms();
+        let partitions = batches
+            .iter()
+            .map(|(partition, records)| PartitionProduceData {
+                index: *partition,
+                records: vec![RecordBatch::new(timestamp, records.clone())],
+                corrupt: None,
+            })
+            .collect();
+        let request = ProduceRequest {
+            transactional_id: None,
+            acks: ACKS_ALL,
+            timeout_ms: PRODUCE_TIMEOUT_MS,
+            topics: vec![TopicProduceData {
+                name: topic.to_string(),
+                partitions,
+            }],
+        };
+        let response = self
+            .produce_handler
+            .handle(context, request)
+            .await
+            .ok_or_else(|| Response::error(500, "No produce response"))?;
+
+        let results: Vec<_> = response
+            .responses
+            .iter()
+            .flat_map(|topic| &topic.partitions)
+            .collect();
+        let offsets = placements
+            .into_iter()
+            .map(|(partition, index)| {
+                let (error_code, base_offset) = results
+                    .iter()
+                    .find(|result| result.index == partition)
+                    .map_or((ErrorCode::UnknownServerError.code(), -1), |result| {
+                        (result.error_code, result.base_offset)
+                    });
+                let failed = error_code != ErrorCode::None.code();
+                ProduceOffset {
+                    partition,
+                    offset: if failed { -1 } else { base_offset + index },
+                    error_code,
+                    error: failed.then(|| error_name(error_code)),
+                }
+            })
+            .collect();
+        Ok(Response::json(&ProduceOffsets { offsets }))
+    }
+
+    fn create_consumer(&self, group: &str, body: &[u8]) -> Result<Response, Response> {
+        let body: CreateConsumerBody = if body.is_empty() {
+            CreateConsumerBody::default()
+        } else {
+            parse_body(body)?
+        };
+        let reset_timestamp = match body.auto_offset_reset.as_deref() {
+            None | Some("latest") => LATEST_TIMESTAMP,
+            Some("earliest") => EARLIEST_TIMESTAMP,
+            Some(other) => {
+                return Err(Response::error(
+                    400,
+                    format!(
+                        "auto.offset.reset must be earliest or latest, not {}",
+                        other
+                    ),
+                ));
+            }
+        };
+        let instance_id = body
+            .name
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+        let key = (group.to_string(), instance_id.clone());
+        let mut consumers = self.lock_consumers();
+        if consumers.contains_key(&key) {
+            return Err(Response::error(
+                400,
+                format!("Consumer instance {} already exists", instance_id),
+            ));
+        }
+        consumers.insert(
+            key,
+            ConsumerEntry {
+                instance: Arc::new(Mutex::new(ConsumerInstance {
+                    reset_timestamp,
+                    positions: FlatMap::new(),
+                })),
+                last_used: Instant::now(),
+            },
+        );
+        Ok(Response::json(&CreatedConsumer {
+            base_uri: format!("/consumers/{}/instances/{}", group, instance_id),
+            instance_id,
+        }))
+    }
+
+    fn lock_consumers(
+        &self,
+    ) -> std::sync::MutexGuard<'_, FlatMap<(String, String), ConsumerEntry>> {
+        self.consumers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The instance, marked as used; instances unused for longer than the timeout are dropped
+    /// first.
+    fn consumer(&self, group: &str, id: &str) -> Result<Arc<Mutex<ConsumerInstance>>, Response> {
+        let mut consumers = self.lock_consumers();
+        let now = Instant::now();
+        let expired: Vec<(String, String)> = consumers
+            .iter()
+            .filter(|(_, entry)| now.duration_since(entry.last_used) > self.instance_timeout)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            tracing::info!("Deleted idle REST consumer {} of group {}", key.1, key.0);
+            consumers.remove(&key);
+        }
+
+        let entry = consumers
+            .get_mut(&(group.to_string(), id.to_string()))
+            .ok_or_else(|| Response::error(404, format!("Consumer instance {} not found", id)))?;
+        entry.last_used = now;
+        Ok(entry.instance.clone())
+    }
+
+    async fn consume(
+        &self,
+        context: &RequestContext,
+        group: &str,
+        consumer: &mut ConsumerInstance,
+        query: &str,
+    ) -> Result<Response, Response> {
+        let mut max_wait_ms = DEFAULT_FETCH_WAIT_MS;
+        let mut max_bytes = DEFAULT_FETCH_MAX_BYTES;
+        for (name, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            let parsed = value.parse::<i32>().ok().filter(|value| *value >= 0);
+            match (name, parsed) {
+                ("timeout", Some(value)) => max_wait_ms = value.min(MAX_FETCH_WAIT_MS),
+                ("max_bytes", Some(value)) => max_bytes = value,
+                ("timeout" | "max_bytes", None) => {
+                    return Err(Response::error(400, format!("Invalid {}", name)));
+                }
+                _ => {}
+            }
+        }
+
+        self.look_up_positions(context, group, consumer).await;
+        let mut topics: Vec<FetchTopic> = Vec::new();
+        for (partition, position) in consumer.positions.iter() {
+            let Some(fetch_offset) = position else {
+                continue;
+            };
+            let fetch_partition = FetchPartition {
+                partition: partition.partition,
+                current_leader_epoch: -1,
+                fetch_offset: *fetch_offset,
+                log_start_offset: -1,
+                partition_max_bytes: max_bytes,
+            };
+            match topics.last_mut() {
+                Some(topic) if topic.topic == partition.topic => {
+                    topic.partitions.push(fetch_partition)
+                }
+                _ => topics.push(FetchTopic {
+                    topic: partition.topic.clone(),
+                    partitions: vec![fetch_partition],
+                }),
+            }
+        }
+        if topics.is_empty() {
+            return Ok(Response::json(&Vec::<ConsumerRecord>::new()));
+        }
+
+        let request = FetchRequest {
+            replica_id: -1,
+            max_wait_ms,
+            min_bytes: 1,
+            max_bytes,
+            isolation_level: 0,
+            session_id: 0,
+            session_epoch: -1,
+            topics,
+            forgotten_topics: vec![],
+            rack_id: String::new(),
+        };
+        let response = self.fetch_handler.handle(context, request).await;
+
+        let mut records = Vec::new();
+        let mut out_of_range = Vec::new();
+        for topic in &response.responses {
+            for data in &topic.partitions {
+                let partition = TopicPartition::new(topic.topic.clone(), data.partition_index);
+                if data.error_code == ErrorCode::OffsetOutOfRange.code() {
+                    out_of_range.push(partition);
+                    continue;
+                }
+                if data.error_code != ErrorCode::None.code() {
+                    tracing::debug!(
+                        "REST fetch of {} failed: {}",
+                        partition,
+                        error_name(data.error_code)
+                    );
+                    continue;
+                }
+                let Some(Some(position)) = consumer.positions.get_mut(&partition) else {
+                    continue;
+                };
+                let batches = data
+                    .records
+                    .batches()
+                    .map_err(|e| Response::error(500, e))?;
+                for batch in batches {
+                    let next = batch.last_offset() + 1;
+                    if !batch.is_control_batch() {
+                        for record in &batch.records {
+                            let offset = batch.base_offset + record.offset_delta.0 as i64;
+                            if offset < *position {
+                                continue;
+                            }
+                            records.push(ConsumerRecord {
+                                topic: topic.topic.clone(),
+                                partition: data.partition_index,
+                                offset,
+                                timestamp: batch.base_timestamp + record.timestamp_delta.0,
+                                key: record.key.as_ref().map(|key| STANDARD.encode(key)),
+                                value: record.value.as_ref().map(|value| STANDARD.encode(value)),
+                            });
+                        }
+                    }
+                    *position = (*position).max(next);
+                }
+            }
+        }
+
+        if !out_of_range.is_empty() {
+            let reset = self
+                .list_offsets(context, &out_of_range, consumer.reset_timestamp)
+                .await;
+            for (partition, offset) in reset {
+                tracing::info!("Reset REST consumer of {} to offset {}", partition, offset);
+                consumer.positions.insert(partition, Some(offset));
+            }
+        }
+        Ok(Response::json(&records))
+    }
+
+    /// Starts the partitions without a position at the group's committed offset, or where the
+    /// reset policy says when it has none. Partitions that cannot be looked up stay without.
+    async fn look_up_positions(
+        &self,
+        context: &RequestContext,
+        group: &str,
+        consumer: &mut ConsumerInstance,
+    ) {
+        let unknown: Vec<TopicPartition> = consumer
+            .positions
+            .iter()
+            .filter(|(_, position)| position.is_none())
+            .map(|(partition, _)| partition.clone())
+            .collect();
+        if unknown.is_empty() {
+            return;
+        }
+
+        let request = OffsetFetchRequest {
+            group_id: group.to_string(),
+            topics: Some(
+                group_by_topic(&unknown)
+                    .into_iter()
+                    .map(|(name, partition_indexes)| OffsetFetchTopic {
+                        name,
+                        partition_indexes,
+                    })
+                    .collect(),
+            ),
+        };
+        let response = self.group_handler.offset_fetch(context, request).await;
+        for topic in &response.topics {
+            for committed in &topic.partitions {
+                if committed.error_code == ErrorCode::None.code() && committed.committed_offset >= 0
+                {
+                    consumer.positions.insert(
+                        TopicPartition::new(topic.name.clone(), committed.partition_index),
+                        Some(committed.committed_offset),
+                    );
+                }
+            }
+        }
+
+        let uncommitted: Vec<TopicPartition> = unknown
+            .into_iter()
+            .filter(|partition| matches!(consumer.positions.get(partition), Some(None)))
+            .collect();
+        if uncommitted.is_empty() {
+            return;
+        }
+        let reset = self
+            .list_offsets(context, &uncommitted, consumer.reset_timestamp)
+            .await;
+        for (partition, offset) in reset {
+            consumer.positions.insert(partition, Some(offset));
+        }
+    }
+
+    async fn list_offsets(
+        &self,
+        context: &RequestContext,
+        partitions: &[TopicPartition],
+        timestamp: i64,
+    ) -> Vec<(TopicPartition, i64)> {
+        let request = ListOffsetsRequest {
+            replica_id: -1,
+            isolation_level: 0,
+            topics: group_by_topic(partitions)
+                .into_iter()
+                .map(|(name, indexes)| ListOffsetsTopic {
+                    name,
+                    partitions: indexes
+                        .into_iter()
+                        .map(|partition_index| ListOffsetsPartition {
+                            partition_index,
+                            current_leader_epoch: -1,
+                            timestamp,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        };
+        let response = self.list_offsets_handler.handle(context, request).await;
+        response
+            .topics
+            .iter()
+            .flat_map(|topic| {
+                topic
+                    .partitions
+                    .iter()
+                    .filter(|partition| partition.error_code == ErrorCode::None.code())
+                    .map(|partition| {
+                        (
+                            TopicPartition::new(topic.name.clone(), partition.partition_index),
+                            partition.offset,
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    async fn commit(
+        &self,
+        context: &RequestContext,
+        group: &str,
+        consumer: &ConsumerInstance,
+        body: CommitBody,
+    ) -> Result<Response, Response> {
+        let offsets: Vec<(TopicPartition, i64)> = match body.offsets {
+            Some(offsets) => offsets
+                .into_iter()
+                .map(|offset| {
+                    (
+                        TopicPartition::new(offset.topic, offset.partition),
+                        offset.offset,
+                    )
+                })
+                .collect(),
+            None => consumer
+                .positions
+                .iter()
+                .filter_map(|(partition, position)| Some((partition.clone(), (*position)?)))
+                .collect(),
+        };
+
+        let mut topics: Vec<OffsetCommitTopic> = Vec::new();
+        for (partition, offset) in offsets {
+            let committed = OffsetCommitPartition {
+                partition_index: partition.partition,
+                committed_offset: offset,
+                committed_leader_epoch: -1,
+                committed_metadata: None,
+            };
+            match topics
+                .iter_mut()
+                .find(|topic| topic.name == partition.topic)
+            {
+                Some(topic) => topic.partitions.push(committed),
+                None => topics.push(OffsetCommitTopic {
+                    name: partition.topic,
+                    partitions: vec![committed],
+                }),
+            }
+        }
+        // Instances are not group members, so they commit as a group without any
+        let request = OffsetCommitRequest {
+            group_id: group.to_string(),
+            generation_id: -1,
+            member_id: String::new(),
+            retention_time_ms: -1,
+            group_instance_id: None,
+            topics,
+        };
+        let response = self.group_handler.offset_commit(context, request).await;
+        let failed = response.topics.iter().find_map(|topic| {
+            topic
+                .partitions
+                .iter()
+                .find(|partition| partition.error_code != ErrorCode::None.code())
+                .map(|partition| (topic.name.clone(), partition))
+        });
+        match failed {
+            Some((topic, partition)) => Err(Response::error(
+                500,
+                format!(
+                    "Commit for {}-{} failed: {}",
+                    topic,
+                    partition.partition_index,
+                    error_name(partition.error_code)
+                ),
+            )),
+            None => Ok(Response::no_content()),
+        }
+    }
+}
+
+fn parse_body<'a, T: Deserialize<'a>>(body: &'a [u8]) -> Result<T, Response> {
+    serde_json::from_slice(body).map_err(|e| Response::error(400, format!("Invalid JSON: {}", e)))
+}
+
+fn decode_base64(field: Option<String>, name: &str) -> Result<Option<Vec<u8>>, Response> {
+    field
+        .map(|encoded| {
+            STANDARD
+                .decode(encoded)
+                .map_err(|e| Response::error(400, format!("Invalid base64 {}: {}", name, e)))
+        })
+        .transpose()
+}
+
+fn error_name(code: i16) -> String {
+    format!("error code {}", code)
+}
+
+/// The partition indexes of each topic, in the order the topics first appear.
+fn group_by_topic(partitions: &[TopicPartition]) -> Vec<(String, Vec<i32>)> {
+    let mut topics: Vec<(String, Vec<i32>)> = Vec::new();
+    for partition in partitions {
+        match topics.iter_mut().find(|(name, _)| *name == partition.topic) {
+            Some((_, indexes)) => indexes.push(partition.partition),
+            None => topics.push((partition.topic.clone(), vec![partition.partition])),
+        }
+    }
+    topics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    use crate::application::group_coordinator::GroupCoordinator;
+    use crate::application::replica_manager::ReplicaManager;
+    use crate::core::domain::metadata_records::RegisterBrokerRecord;
+    use crate::core::domain::scram_credential::ScramCredential;
+    use crate::core::ports::driven::FetchClient;
+
+    /// alice has a PLAIN password, bob a SCRAM-SHA-512 credential.
+    struct Users(ScramCredential);
+
+    #[async_trait]
+    impl CredentialStore for Users {
+        async fn verify_plain(&self, username: &str, password: &str) -> bool {
+            username == "alice" && password == "alice-secret"
+        }
+
+        async fn scram_credential(
+            &self,
+            mechanism: &str,
+            username: &str,
+        ) -> Option<ScramCredential> {
+            (mechanism == ScramMechanism::Sha512.name() && username == "bob")
+                .then(|| self.0.clone())
+        }
+    }
+
+    fn proxy(dir: &std::path::Path) -> RestProxy {
+        let replica_manager = Arc::new(Mutex::new(ReplicaManager::new(1, dir, 1)));
+        let metadata = Arc::new(Mutex::new(BrokerMetadataListener::new(
+            1,
+            replica_manager.clone(),
+            Box::new(|_: &RegisterBrokerRecord| -> Box<dyn FetchClient> {
+                unreachable!("the proxy fetches nothing from other brokers")
+            }),
+        )));
+        RestProxy::new(
+            ProduceHandler::new(replica_manager.clone(), None, None),
+            FetchHandler::new(replica_manager.clone(), None),
+            ListOffsetsHandler::new(replica_manager.clone(), None),
+            GroupHandler::new(
+                Arc::new(Mutex::new(GroupCoordinator::new(1))),
+                replica_manager,
+                metadata.clone(),
+                None,
+            ),
+            metadata,
+            Duration::from_secs(60),
+        )
+    }
+
+    fn request(method: &str, path: &str, authorization: Option<&str>, body: &[u8]) -> Request {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: String::new(),
+            authorization: authorization.map(String::from),
+            body: body.to_vec(),
+        }
+    }
+
+    /// The status of a request the proxy answers without routing it.
+    async fn refusal(raw: &[u8]) -> Option<u16> {
+        match RestProxy::read_request(&mut &raw[..]).await {
+            Ok(Err(response)) => Some(response.status),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reads_requests_within_limits() {
+        let raw = b"POST /topics/events?partition=1 HTTP/1.1\r\nHost: forge\r\n\
+            authorization: Basic YTpi\r\nContent-Length: 2\r\n\r\n{}trailing";
+        let Ok(Ok(parsed)) = RestProxy::read_request(&mut &raw[..]).await else {
+            panic!("a well-formed request is read");
+        };
+        assert_eq!(
+            (
+                parsed.method.as_str(),
+                parsed.path.as_str(),
+                parsed.query.as_str()
+            ),
+            ("POST", "/topics/events", "partition=1")
+        );
+        assert_eq!(parsed.authorization.as_deref(), Some("Basic YTpi"));
+        assert_eq!(parsed.body, b"{}");
+
+        // Without a Content-Length there is no body
+        let raw = b"POST /topics/events HTTP/1.1\r\n\r\n{}";
+        let Ok(Ok(parsed)) = RestProxy::read_request(&mut &raw[..]).await else {
+            panic!("a request without a body is read");
+        };
+        assert!(parsed.body.is_empty());
+
+        assert_eq!(
+            refusal(b"POST /topics/events HTTP/1.1\r\nContent-Length: two\r\n\r\n").await,
+            Some(400)
+        );
+        assert_eq!(
+            refusal(b"POST /topics/events HTTP/1.1\r\nContent-Length: -1\r\n\r\n").await,
+            Some(400)
+        );
+        assert_eq!(
+            refusal(b"POST /topics/events HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n").await,
+            Some(411)
+        );
+        let oversized_body = format!(
+            "POST /topics/events HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_REQUEST_BODY_BYTES + 1
+        );
+        assert_eq!(refusal(oversized_body.as_bytes()).await, Some(413));
+
+        // Connections that cannot be answered are dropped
+        let oversized_head = format!(
+            "GET / HTTP/1.1\r\nX-Padding: {}\r\n\r\n",
+            "a".repeat(MAX_REQUEST_HEAD_BYTES)
+        );
+        assert!(
+            RestProxy::read_request(&mut oversized_head.as_bytes())
+                .await
+                .is_err()
+        );
+        let short_body = b"POST /topics/events HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}";
+        assert!(RestProxy::read_request(&mut &short_body[..]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_routes_and_basic_auth() {
+        let dir = std::env::temp_dir().join(format!("forge-rest-{}", uuid::Uuid::new_v4()));
+        let context = RequestContext {
+            principal: KafkaPrincipal::anonymous(),
+            client_host: "127.0.0.1".to_string(),
+            client_id: CLIENT_ID.to_string(),
+            listener: SecurityProtocol::Plaintext,
+        };
+        let status = |result: Result<Response, Response>| match result {
+            Ok(response) | Err(response) => response.status,
+        };
+
+        let open = proxy(&dir);
+        for (method, path, body, expected) in [
+            ("GET", "/", &b""[..], 404),
+            ("GET", "/brokers", b"", 404),
+            ("POST", "/consumers/g/instances/c/unknown", b"", 404),
+            ("GET", "/topics/events", b"", 405),
+            ("PUT", "/consumers/g/instances/c", b"", 405),
+            ("POST", "/topics/events", b"{\"records\": [", 400),
+            ("POST", "/topics/events", b"{\"records\": 1}", 400),
+            ("POST", "/consumers/g/instances/c/assignments", b"[]", 400),
+            ("POST", "/consumers/g/instances/c/offsets", b"{", 400),
+        ] {
+            let response = open
+                .route(&context, &request(method, path, None, body))
+                .await;
+            assert_eq!(status(response), expected, "{} {}", method, path);
+        }
+        assert_eq!(
+            open.authenticate(&request("GET", "/", None, b""), [127, 0, 0, 1].into())
+                .await
+                .ok(),
+            Some(KafkaPrincipal::anonymous())
+        );
+
+        let secured = proxy(&dir).with_basic_auth(
+            Arc::new(Users(ScramMechanism::Sha512.credential("bob-secret", 16))),
+            Duration::ZERO,
+        );
+        let basic = |credentials: &str| format!("Basic {}", STANDARD.encode(credentials));
+        for (authorization, expected) in [
+            (None, None),
+            (Some("Bearer token".to_string()), None),
+            (Some("Basic !!!".to_string()), None),
+            (Some(basic("alice")), None),
+            (Some(basic("alice:wrong")), None),
+            (Some(basic("bob:alice-secret")), None),
+            (Some(basic(":alice-secret")), None),
+            (Some(basic("alice:alice-secret")), Some("alice")),
+            (Some(basic("bob:bob-secret")), Some("bob")),
+            (
+                Some(format!("basic {}", STANDARD.encode("bob:bob-secret"))),
+                Some("bob"),
+            ),
+        ] {
+            let result = secured
+                .authenticate(
+                    &request("GET", "/", authorization.as_deref(), b""),
+                    [127, 0, 0, 1].into(),
+                )
+                .await;
+            match expected {
+                Some(user) => assert_eq!(result.ok(), Some(KafkaPrincipal::user(user))),
+                None => assert_eq!(result.err().map(|response| response.status), Some(401)),
+            }
+        }
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+}
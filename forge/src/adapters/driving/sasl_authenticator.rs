@@ -0,0 +1,191 @@
+use bytes::{Buf, BytesMut};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::application::audit;
+use crate::application::sasl::{SaslServer, create_server};
+use crate::config::SecurityProtocol;
+use crate::core::domain::principal::KafkaPrincipal;
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::CredentialStore;
+use crate::protocol::api_versions::API_VERSIONS_API_KEY;
+use crate::protocol::request::RequestHeader;
+use crate::protocol::sasl_authenticate::{
+    SASL_AUTHENTICATE_API_KEY, SASL_AUTHENTICATE_MAX_VERSION, SASL_AUTHENTICATE_MIN_VERSION,
+    SaslAuthenticateRequest, SaslAuthenticateResponse,
+};
+use crate::protocol::sasl_handshake::{
+    SASL_HANDSHAKE_API_KEY, SASL_HANDSHAKE_MAX_VERSION, SASL_HANDSHAKE_MIN_VERSION,
+    SaslHandshakeRequest, SaslHandshakeResponse,
+};
+
+/// What a SASL listener needs to authenticate its connections.
+#[derive(Clone)]
+pub struct SaslListenerConfig {
+    pub enabled_mechanisms: Vec<String>,
+    pub credentials: Arc<dyn CredentialStore>,
+    /// Held before answering a failed authentication.
+    pub failed_authentication_delay: Duration,
+}
+
+enum SaslState {
+    Handshake,
+    Authenticate(Box<dyn SaslServer>),
+    Complete(KafkaPrincipal),
+    Failed,
+}
+
+/// Runs the SASL exchange of one connection: SaslHandshake picks the mechanism, then
+/// SaslAuthenticate requests carry its messages until it completes. Only ApiVersions is
+/// allowed alongside, so clients can discover the SASL APIs.
+pub struct SaslServerAuthenticator {
+    config: SaslListenerConfig,
+    client_host: String,
+    /// Chosen by the handshake.
+    mechanism: Option<String>,
+    state: SaslState,
+}
+
+impl SaslServerAuthenticator {
+    pub fn new(config: SaslListenerConfig, client_host: String) -> Self {
+        Self {
+            config,
+            client_host,
+            mechanism: None,
+            state: SaslState::Handshake,
+        }
+    }
+
+    /// The authenticated principal, once the exchange succeeded.
+    pub fn principal(&self) -> Option<&KafkaPrincipal> {
+        match &self.state {
+            SaslState::Complete(principal) => Some(principal),
+            _ => None,
+        }
+    }
+
+    /// Whether the exchange failed; the connection closes once the response is sent.
+    pub fn is_failed(&self) -> bool {
+        matches!(self.state, SaslState::Failed)
+    }
+
+    /// Handles a request that arrived before authentication completed. Returns the response
+    /// to SaslHandshake and SaslAuthenticate, and `None` for ApiVersions, which the dispatcher
+    /// answers. Anything else is an error, and the connection should close.
+    pub async fn authenticate<B: Buf>(
+        &mut self,
+        header: &RequestHeader,
+        body: &mut B,
+    ) -> Result<Option<BytesMut>, String> {
+        let version = header.api_version;
+        let mut response = BytesMut::new();
+        match header.api_key {
+            API_VERSIONS_API_KEY => return Ok(None),
+            SASL_HANDSHAKE_API_KEY => {
+                if !(SASL_HANDSHAKE_MIN_VERSION..=SASL_HANDSHAKE_MAX_VERSION).contains(&version) {
+                    return Err(format!("Unsupported SaslHandshake version {}", version));
+                }
+                let request = SaslHandshakeRequest::decode(body, version)?;
+                self.handshake(request).encode(&mut response, version);
+            }
+            SASL_AUTHENTICATE_API_KEY => {
+                if !(SASL_AUTHENTICATE_MIN_VERSION..=SASL_AUTHENTICATE_MAX_VERSION)
+                    .contains(&version)
+                {
+                    return Err(format!("Unsupported SaslAuthenticate version {}", version));
+                }
+                let request = SaslAuthenticateRequest::decode(body, version)?;
+                self.evaluate(request).await.encode(&mut response, version);
+            }
+            api_key => {
+                return Err(format!(
+                    "Unexpected request with API key {} before SASL authentication",
+                    api_key
+                ));
+            }
+        }
+        Ok(Some(response))
+    }
+
+    fn handshake(&mut self, request: SaslHandshakeRequest) -> SaslHandshakeResponse {
+        let error = if !matches!(self.state, SaslState::Handshake) {
+            self.fail("repeated SaslHandshake");
+            ErrorCode::IllegalSaslState
+        } else if !self.config.enabled_mechanisms.contains(&request.mechanism) {
+            self.fail(&format!("unsupported mechanism {}", request.mechanism));
+            ErrorCode::UnsupportedSaslMechanism
+        } else {
+            match create_server(&request.mechanism, self.config.credentials.clone()) {
+                Some(server) => {
+                    self.mechanism = Some(request.mechanism);
+                    self.state = SaslState::Authenticate(server);
+                    ErrorCode::None
+                }
+                None => {
+                    self.fail(&format!("unsupported mechanism {}", request.mechanism));
+                    ErrorCode::UnsupportedSaslMechanism
+                }
+            }
+        };
+
+        SaslHandshakeResponse {
+            error_code: error.code(),
+            mechanisms: self.config.enabled_mechanisms.clone(),
+        }
+    }
+
+    async fn evaluate(&mut self, request: SaslAuthenticateRequest) -> SaslAuthenticateResponse {
+        let SaslState::Authenticate(server) = &mut self.state else {
+            self.fail("SaslAuthenticate before a successful SaslHandshake");
+            return Self::failure(
+                ErrorCode::IllegalSaslState,
+                "SaslAuthenticate before a successful SaslHandshake".to_string(),
+            );
+        };
+
+        match server.evaluate_response(&request.auth_bytes).await {
+            Ok(auth_bytes) => {
+                if let Some(user) = server.authorization_id() {
+                    let principal = KafkaPrincipal::user(user);
+                    audit::authentication_succeeded(
+                        SecurityProtocol::SaslPlaintext,
+                        &self.client_host,
+                        self.mechanism.as_deref().unwrap_or_default(),
+                        &principal,
+                    );
+                    self.state = SaslState::Complete(principal);
+                }
+                SaslAuthenticateResponse {
+                    error_code: ErrorCode::None.code(),
+                    error_message: None,
+                    auth_bytes,
+                    session_lifetime_ms: 0,
+                }
+            }
+            Err(e) => {
+                self.fail(&e);
+                tokio::time::sleep(self.config.failed_authentication_delay).await;
+                Self::failure(ErrorCode::SaslAuthenticationFailed, e)
+            }
+        }
+    }
+
+    fn fail(&mut self, reason: &str) {
+        audit::authentication_failed(
+            SecurityProtocol::SaslPlaintext,
+            &self.client_host,
+            self.mechanism.as_deref(),
+            reason,
+        );
+        self.state = SaslState::Failed;
+    }
+
+    fn failure(error: ErrorCode, message: String) -> SaslAuthenticateResponse {
+        SaslAuthenticateResponse {
+            error_code: error.code(),
+            error_message: Some(message),
+            auth_bytes: Vec::new(),
+            session_lifetime_ms: 0,
+        }
+    }
+}
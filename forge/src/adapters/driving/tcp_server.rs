@@ -1,25 +1,118 @@
+use crate::adapters::driving::connection_quotas::{ConnectionPermit, ConnectionQuotas};
+use crate::adapters::driving::connection_registry::{ConnectionRegistry, ConnectionStats};
+use crate::adapters::driving::frame_codec::{
+    Frame, FrameCodec, OutgoingFrames, SIZE_PREFIX_LENGTH,
+};
+use crate::adapters::driving::proxy_protocol::read_proxy_header;
+use crate::adapters::driving::request_dispatcher::RequestDispatcher;
+use crate::adapters::driving::request_metrics::{RequestMetrics, RequestTiming};
+use crate::adapters::driving::sasl_authenticator::{SaslListenerConfig, SaslServerAuthenticator};
+use crate::application::request_context::RequestContext;
+use crate::config::{SecurityProtocol, SocketConfig};
+use crate::core::domain::principal::KafkaPrincipal;
 use crate::protocol::request::RequestHeader;
-use crate::protocol::response::ResponseHeader;
-use bytes::{BufMut, BytesMut};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpListener;
+use crate::protocol::response::{ResponseBody, ResponseHeader};
+use crate::shared::scheduler::{spawn_named, spawn_named_on};
+use crate::shared::timing::measure_busy_time;
+use bytes::BytesMut;
+use futures::StreamExt;
+use socket2::SockRef;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::time::Instant;
+use tokio_util::codec::FramedRead;
 use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{Instrument, Span, field};
+
+/// How long a connection may take to send its PROXY header.
+const PROXY_HEADER_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct TcpServer;
 
-const MAX_MESSAGE_SIZE: u32 = 100 * 1024 * 1024;
-const API_VERSIONS_KEY: i16 = 18;
-const UNSUPPORTED_VERSION_ERROR: i16 = 35;
+/// What the reader queues for the handler, with when it was read.
+struct QueuedRequest {
+    header: RequestHeader,
+    body: BytesMut,
+    received: Instant,
+    /// Over its size limit: `body` is empty, as it was skipped.
+    too_large: bool,
+    /// Open until the response is written, so it covers the request end to end.
+    span: Span,
+}
+
+/// What the handler queues for the writer.
+struct QueuedResponse {
+    header: ResponseHeader,
+    body: ResponseBody,
+    timing: RequestTiming,
+    span: Span,
+}
+
+/// When a connection last read a request or wrote a response, shared by its reader and
+/// writer.
+#[derive(Clone)]
+struct ConnectionActivity {
+    started: Instant,
+    /// Milliseconds after `started`.
+    last_active_ms: Arc<AtomicU64>,
+}
+
+impl ConnectionActivity {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            last_active_ms: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    fn touch(&self) {
+        self.last_active_ms
+            .store(self.started.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    fn last_active(&self) -> Instant {
+        self.started + Duration::from_millis(self.last_active_ms.load(Ordering::Relaxed))
+    }
+}
 
 impl TcpServer {
-    pub async fn listen(address: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// Serves requests until Ctrl+C, then stops accepting connections and waits up to
+    /// `socket_config.shutdown_drain_timeout_ms` for requests already read to be answered.
+    /// Requests over their size limit get an error response where one can be built from their
+    /// header, and close the connection otherwise. Connections over the `connection_quotas`
+    /// limits are closed, or wait to be accepted when they arrive too fast.
+    /// With `socket_config.proxy_protocol`, the client address is taken from the PROXY header
+    /// instead of the socket. With `sasl` set, connections must authenticate before anything
+    /// but ApiVersions is served. Requests are handled on `handler_runtime`, so slow handlers
+    /// never hold up the threads doing socket IO. Open connections are listed in `connections`
+    /// with their traffic.
+    pub async fn listen(
+        address: &str,
+        socket_config: SocketConfig,
+        connection_quotas: Arc<ConnectionQuotas>,
+        connections: Arc<ConnectionRegistry>,
+        sasl: Option<SaslListenerConfig>,
+        dispatcher: Arc<RequestDispatcher>,
+        handler_runtime: Handle,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let listener = TcpListener::bind(address).await?;
         tracing::info!("Server started on {}", address);
 
         let cancel_token = CancellationToken::new();
+        let connection_tasks = TaskTracker::new();
         let cancel_token_clone = cancel_token.clone();
 
-        tokio::spawn(async move {
+        spawn_named("shutdown-signal", async move {
             tokio::signal::ctrl_c().await.unwrap();
             tracing::info!("Ctrl+C received, shutting down...");
             cancel_token_clone.cancel();
@@ -29,15 +122,48 @@ impl TcpServer {
             tokio::select! {
                 accept_result = listener.accept() => {
                     match accept_result {
-                        Ok((mut socket, _)) => {
-                            tracing::info!("New connection from {}", socket.peer_addr()?);
+                        Ok((mut socket, peer_address)) => {
                             let token = cancel_token.clone();
-                            tokio::spawn(async move {
-                                Self::handle_connection(&mut socket, token).await;
-                            });
+                            let dispatcher = dispatcher.clone();
+                            let socket_config = socket_config.clone();
+                            let connection_quotas = connection_quotas.clone();
+                            let connections = connections.clone();
+                            let sasl = sasl.clone();
+                            let handler_runtime = handler_runtime.clone();
+                            let name = format!("connection {}", peer_address);
+                            spawn_named(&name, connection_tasks.track_future(async move {
+                                // Admitted here rather than in the accept loop, so a slow load
+                                // balancer cannot hold up other connections
+                                let Some((peer_address, permit)) = Self::admit(
+                                    &mut socket,
+                                    peer_address,
+                                    &socket_config,
+                                    &connection_quotas,
+                                )
+                                .await
+                                else {
+                                    return;
+                                };
+                                tracing::info!("New connection from {}", peer_address);
+                                let registration = connections.register(peer_address);
+                                let stats = registration.stats.clone();
+                                Self::handle_connection(
+                                    socket,
+                                    socket_config,
+                                    sasl,
+                                    dispatcher,
+                                    handler_runtime,
+                                    stats,
+                                    token,
+                                )
+                                .await;
+                                drop(registration);
+                                drop(permit);
+                            }));
                         }
                         Err(e) => {
                             tracing::error!("Failed to accept connection: {}", e);
+                            continue;
                         }
                     }
                 }
@@ -47,106 +173,792 @@ impl TcpServer {
                     break;
                 }
             }
+
+            // Over max.connection.creation.rate, new connections wait in the accept backlog
+            let delay = connection_quotas.record_connection_creation();
+            if !delay.is_zero() {
+                tracing::debug!("Throttling accepts for {} ms", delay.as_millis());
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = cancel_token.cancelled() => {
+                        tracing::info!("Server shutting down...");
+                        break;
+                    }
+                }
+            }
         }
 
+        drop(listener);
+        connection_tasks.close();
+        let drain_timeout = Duration::from_millis(socket_config.shutdown_drain_timeout_ms);
+        if tokio::time::timeout(drain_timeout, connection_tasks.wait())
+            .await
+            .is_err()
+        {
+            tracing::warn!(
+                "{} connections still busy after {} ms, dropping them",
+                connection_tasks.len(),
+                socket_config.shutdown_drain_timeout_ms
+            );
+        }
         tracing::info!("Server shut down gracefully");
         Ok(())
     }
 
+    /// Takes the client address from the PROXY header when enabled, then a connection slot
+    /// for it. `None` means the connection should be closed.
+    async fn admit(
+        socket: &mut TcpStream,
+        peer_address: SocketAddr,
+        socket_config: &SocketConfig,
+        connection_quotas: &Arc<ConnectionQuotas>,
+    ) -> Option<(SocketAddr, ConnectionPermit)> {
+        let mut client_address = peer_address;
+        if socket_config.proxy_protocol {
+            let header = tokio::time::timeout(PROXY_HEADER_TIMEOUT, read_proxy_header(socket))
+                .await
+                .unwrap_or_else(|_| Err("Timed out reading PROXY header".to_string()));
+            match header {
+                Ok(Some(address)) => client_address = address,
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!("Closing connection from {}: {}", peer_address, e);
+                    return None;
+                }
+            }
+        }
+
+        match connection_quotas.try_acquire(client_address.ip()) {
+            Ok(permit) => {
+                if let Err(e) = Self::configure_socket(socket, socket_config) {
+                    tracing::warn!("Failed to configure socket from {}: {}", client_address, e);
+                }
+                Some((client_address, permit))
+            }
+            Err(e) => {
+                tracing::warn!("Closing connection from {}: {}", client_address, e);
+                None
+            }
+        }
+    }
+
+    /// Applies the buffer sizes, TCP_NODELAY and keepalive settings to an accepted socket.
+    fn configure_socket(socket: &TcpStream, socket_config: &SocketConfig) -> io::Result<()> {
+        let socket = SockRef::from(socket);
+        if let Some(size) = socket_config.send_buffer_bytes {
+            socket.set_send_buffer_size(size as usize)?;
+        }
+        if let Some(size) = socket_config.receive_buffer_bytes {
+            socket.set_recv_buffer_size(size as usize)?;
+        }
+        socket.set_tcp_nodelay(socket_config.tcp_nodelay)?;
+        socket.set_keepalive(socket_config.keepalive)
+    }
+
+    /// Runs a connection as three stages so a client can pipeline requests: this task reads
+    /// and frames requests, a handler task on `handler_runtime` processes them one at a time in
+    /// arrival order, and a writer task sends the responses. Both queues between the stages hold
+    /// at most `max_queued_requests_per_connection` entries. Handling stays sequential per
+    /// connection, as in Kafka, so produce requests are appended in the order they were sent.
+    /// Connections with nothing read or written for `connections_max_idle_ms` are closed.
     async fn handle_connection(
-        socket: &mut tokio::net::TcpStream,
+        socket: TcpStream,
+        socket_config: SocketConfig,
+        sasl: Option<SaslListenerConfig>,
+        dispatcher: Arc<RequestDispatcher>,
+        handler_runtime: Handle,
+        stats: Arc<ConnectionStats>,
         cancel_token: CancellationToken,
     ) {
+        let client_host = stats.peer_address.ip().to_string();
+        let codec = FrameCodec::new(
+            socket_config.request_max_bytes,
+            socket_config.request_max_bytes_per_api.clone(),
+        );
+        let partial_frame = codec.partial_frame();
+        let read_timeout = Duration::from_millis(socket_config.request_read_timeout_ms);
+        let (read_half, write_half) = socket.into_split();
+        let mut frames = FramedRead::new(read_half, codec);
+        let activity = ConnectionActivity::new();
+        let idle_timeout = Duration::from_millis(socket_config.connections_max_idle_ms);
+
+        let (request_tx, request_rx) =
+            mpsc::channel(socket_config.max_queued_requests_per_connection);
+        // Bounded too, so a client that stops reading responses eventually stops the handler
+        let (response_tx, response_rx) =
+            mpsc::channel(socket_config.max_queued_requests_per_connection);
+        let writer = spawn_named(
+            "connection-writer",
+            Self::write_responses(
+                write_half,
+                response_rx,
+                activity.clone(),
+                stats.clone(),
+                dispatcher.request_metrics(),
+            ),
+        );
+        let handler = spawn_named_on(
+            "request-handler",
+            Self::handle_requests(
+                client_host.clone(),
+                sasl.map(|sasl| SaslServerAuthenticator::new(sasl, client_host.clone())),
+                stats.clone(),
+                dispatcher,
+                request_rx,
+                response_tx,
+            ),
+            &handler_runtime,
+        );
+
         loop {
-            tokio::select! {
-                read_result = Self::read_frame(socket) => {
-                    match read_result {
-                        Ok(Some(body)) => {
-                            let mut cursor = std::io::Cursor::new(body);
-                            match RequestHeader::decode(&mut cursor) {
-                                Ok(header) => {
-                                    tracing::info!(
-                                        "Received Request - API Key: {}, Version: {}, Correlation ID: {}",
-                                        header.api_key,
-                                        header.api_version,
-                                        header.correlation_id
-                                    );
-
-                                    let response_header = ResponseHeader {
-                                        correlation_id: header.correlation_id,
-                                    };
-
-                                    let mut response_body = BytesMut::new();
-                                    response_header.encode(&mut response_body);
-
-                                    match header.api_key {
-                                        API_VERSIONS_KEY => {
-                                            tracing::info!("Received API Versions request");
-                                            response_body.put_i16(0);
-                                        }
-                                        _ => {
-                                            tracing::info!("Unsupported API Key: {}", header.api_key);
-                                            response_body.put_i16(UNSUPPORTED_VERSION_ERROR);
-                                        }
-                                    }
-
-                                    let mut final_packet = BytesMut::new();
-                                    final_packet.put_i32(response_body.len() as i32);
-                                    final_packet.put_slice(&response_body);
-
-                                    if let Err(e) = socket.write_all(&final_packet).await {
-                                        tracing::error!("Failed to write response: {}", e);
-                                        break;
-                                    }
-                                }
-                                Err(e) => {
-                                    tracing::error!("Failed to decode message: {}", e);
-                                    break;
-                                }
+            // A full queue stops reads until the handler catches up, leaving pipelined requests
+            // in the socket buffers and, once those fill, pushing back on the client through TCP
+            let permit = match request_tx.try_reserve() {
+                Ok(permit) => permit,
+                Err(TrySendError::Full(())) => {
+                    tracing::debug!(
+                        "Pausing reads from {}: {} requests queued",
+                        client_host,
+                        socket_config.max_queued_requests_per_connection
+                    );
+                    tokio::select! {
+                        permit = request_tx.reserve() => match permit {
+                            Ok(permit) => {
+                                // The peer did not stall while nothing was read
+                                partial_frame.restart();
+                                permit
                             }
+                            Err(_) => break,
+                        },
+                        _ = cancel_token.cancelled() => {
+                            tracing::info!("Connection shut down gracefully");
+                            break;
                         }
-                        Ok(None) => {
+                    }
+                }
+                // The handler stopped, e.g. after a failed authentication
+                Err(TrySendError::Closed(())) => break,
+            };
+
+            tokio::select! {
+                frame = frames.next() => {
+                    let mut frame = match frame {
+                        Some(Ok(Frame::Request(frame))) => {
+                            activity.touch();
+                            stats.record_received(SIZE_PREFIX_LENGTH + frame.len());
+                            frame
+                        }
+                        Some(Ok(Frame::TooLarge {
+                            api_key,
+                            api_version,
+                            correlation_id,
+                            size,
+                            limit,
+                        })) => {
+                            activity.touch();
+                            stats.record_received(SIZE_PREFIX_LENGTH + size as usize);
+                            tracing::warn!(
+                                "Request from {} with API key {} v{} and correlation id {} has \
+                                 size {}, over the limit of {}",
+                                client_host,
+                                api_key,
+                                api_version,
+                                correlation_id,
+                                size,
+                                limit
+                            );
+                            let span = tracing::info_span!(
+                                "request",
+                                api = RequestDispatcher::api_name(api_key).unwrap_or("Unknown"),
+                                api_version,
+                                correlation_id,
+                            );
+                            permit.send(QueuedRequest {
+                                header: RequestHeader {
+                                    api_key,
+                                    api_version,
+                                    correlation_id,
+                                    client_id: None,
+                                },
+                                body: BytesMut::new(),
+                                received: Instant::now(),
+                                too_large: true,
+                                span,
+                            });
+                            continue;
+                        }
+                        Some(Err(e)) => {
+                            tracing::error!("Failed to read frame: {}", e);
+                            break;
+                        }
+                        None => {
                             tracing::info!("Connection closed by client");
                             break;
                         }
+                    };
+
+                    let span = tracing::info_span!(
+                        "request",
+                        api = field::Empty,
+                        api_version = field::Empty,
+                        correlation_id = field::Empty,
+                        client_id = field::Empty,
+                    );
+                    let header = tracing::debug_span!(parent: &span, "decode")
+                        .in_scope(|| RequestHeader::decode(&mut frame));
+                    let header = match header {
+                        Ok(header) => header,
                         Err(e) => {
-                            tracing::error!("Failed to read frame: {}", e);
+                            tracing::error!("Failed to decode message: {}", e);
                             break;
                         }
+                    };
+                    span.record(
+                        "api",
+                        RequestDispatcher::api_name(header.api_key).unwrap_or("Unknown"),
+                    );
+                    span.record("api_version", header.api_version);
+                    span.record("correlation_id", header.correlation_id);
+                    if let Some(client_id) = &header.client_id {
+                        span.record("client_id", client_id.as_str());
                     }
+                    permit.send(QueuedRequest {
+                        header,
+                        body: frame,
+                        received: Instant::now(),
+                        too_large: false,
+                        span,
+                    });
                 }
 
+                // Without a partial frame yet, this only re-arms the check; once one starts
+                // arriving, the next iteration waits for its own deadline
+                _ = tokio::time::sleep_until(
+                    partial_frame.started().unwrap_or_else(Instant::now) + read_timeout
+                ) => {
+                    let stalled = partial_frame
+                        .started()
+                        .is_some_and(|started| started.elapsed() >= read_timeout);
+                    if stalled {
+                        tracing::warn!(
+                            "Closing connection from {}: no complete request within {} ms",
+                            client_host,
+                            socket_config.request_read_timeout_ms
+                        );
+                        break;
+                    }
+                }
+
+                // Re-armed every iteration, so activity on the writer side pushes it back
+                _ = tokio::time::sleep_until(activity.last_active() + idle_timeout) => {
+                    if activity.last_active().elapsed() >= idle_timeout {
+                        tracing::info!(
+                            "Closing connection idle for more than {} ms",
+                            socket_config.connections_max_idle_ms
+                        );
+                        break;
+                    }
+                }
+
+                // The handler stopped, e.g. after a failed authentication
+                _ = request_tx.closed() => break,
+
                 _ = cancel_token.cancelled() => {
                     tracing::info!("Connection shut down gracefully");
                     break;
                 }
             }
         }
+
+        // Requests already read are still answered before the connection closes
+        drop(request_tx);
+        let _ = handler.await;
+        let _ = writer.await;
     }
 
-    async fn read_frame(
-        socket: &mut tokio::net::TcpStream,
-    ) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error + Send + Sync>> {
-        let mut size_buf = [0u8; 4];
-        if socket.read_exact(&mut size_buf).await.is_err() {
-            return Ok(None);
-        }
+    /// Without an `authenticator` every request runs as the anonymous principal.
+    async fn handle_requests(
+        client_host: String,
+        mut authenticator: Option<SaslServerAuthenticator>,
+        stats: Arc<ConnectionStats>,
+        dispatcher: Arc<RequestDispatcher>,
+        mut requests: mpsc::Receiver<QueuedRequest>,
+        responses: mpsc::Sender<QueuedResponse>,
+    ) {
+        // The listener's name is its security protocol, as there is one listener per protocol
+        let listener = if authenticator.is_some() {
+            SecurityProtocol::SaslPlaintext
+        } else {
+            SecurityProtocol::Plaintext
+        };
+        let respond = async |header: &RequestHeader,
+                             body: ResponseBody,
+                             mut timing: RequestTiming,
+                             span: Span| {
+            timing.handled = Instant::now();
+            let header = ResponseHeader {
+                correlation_id: header.correlation_id,
+            };
+            responses
+                .send(QueuedResponse {
+                    header,
+                    body,
+                    timing,
+                    span,
+                })
+                .await
+                .is_ok()
+        };
 
-        let size = u32::from_be_bytes(size_buf);
-        if size > MAX_MESSAGE_SIZE {
-            tracing::warn!(
-                "Request size {} exceeds max allowed size {}",
-                size,
-                MAX_MESSAGE_SIZE
+        while let Some(request) = requests.recv().await {
+            let QueuedRequest {
+                header,
+                mut body,
+                received,
+                too_large,
+                span,
+            } = request;
+            let mut timing = RequestTiming::new(header.api_key, received);
+            timing.dequeued = Instant::now();
+            let handle_span = tracing::info_span!(parent: &span, "handle");
+            tracing::info!(
+                "Received Request - API Key: {}, Version: {}, Correlation ID: {}",
+                header.api_key,
+                header.api_version,
+                header.correlation_id
             );
-            return Err("Request size exceeds max allowed size".into());
+            let principal = authenticator
+                .as_ref()
+                .and_then(|authenticator| authenticator.principal())
+                .cloned()
+                .unwrap_or_else(KafkaPrincipal::anonymous);
+            let client_id = header.client_id.clone().unwrap_or_default();
+            stats.record_request(&client_id, &principal);
+
+            if too_large {
+                let authenticated = authenticator
+                    .as_ref()
+                    .is_none_or(|authenticator| authenticator.principal().is_some());
+                let context = RequestContext {
+                    principal,
+                    client_host: client_host.clone(),
+                    client_id,
+                    listener,
+                };
+                let response = if authenticated {
+                    dispatcher.too_large_response(&context, &header).await
+                } else {
+                    None
+                };
+                match response {
+                    Some(body) => {
+                        if !respond(&header, body.into(), timing, span).await {
+                            break;
+                        }
+                        continue;
+                    }
+                    None => {
+                        tracing::warn!(
+                            "Closing connection from {}: no response to API key {} v{} can be \
+                             built without its body",
+                            client_host,
+                            header.api_key,
+                            header.api_version
+                        );
+                        break;
+                    }
+                }
+            }
+
+            if let Some(authenticator) = authenticator
+                .as_mut()
+                .filter(|authenticator| authenticator.principal().is_none())
+            {
+                let authenticate = authenticator
+                    .authenticate(&header, &mut body)
+                    .instrument(handle_span.clone());
+                let (result, local) = measure_busy_time(authenticate).await;
+                timing.local = local;
+                match result {
+                    Ok(Some(body)) => {
+                        if !respond(&header, body.into(), timing, span).await
+                            || authenticator.is_failed()
+                        {
+                            break;
+                        }
+                        continue;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        tracing::warn!("Closing connection from {}: {}", client_host, e);
+                        break;
+                    }
+                }
+            }
+
+            let context = RequestContext {
+                principal,
+                client_host: client_host.clone(),
+                client_id,
+                listener,
+            };
+            let dispatch = dispatcher
+                .dispatch(&context, &header, &mut body)
+                .instrument(handle_span);
+            let (result, local) = measure_busy_time(dispatch).await;
+            timing.local = local;
+            let body = match result {
+                Ok(Some(body)) => body,
+                Ok(None) => {
+                    timing.handled = Instant::now();
+                    dispatcher.request_metrics().record(&timing, None);
+                    continue;
+                }
+                Err(e) => {
+                    tracing::error!("Failed to handle request: {}", e);
+                    break;
+                }
+            };
+            if !respond(&header, body, timing, span).await {
+                break;
+            }
+        }
+    }
+
+    /// Writes responses in the order they were queued. Responses that are already waiting are
+    /// written together, so a pipelining client gets them in as few writes as possible.
+    async fn write_responses(
+        mut socket: OwnedWriteHalf,
+        mut responses: mpsc::Receiver<QueuedResponse>,
+        activity: ConnectionActivity,
+        stats: Arc<ConnectionStats>,
+        request_metrics: Arc<RequestMetrics>,
+    ) {
+        let mut frames = OutgoingFrames::default();
+        let mut sent = Vec::new();
+        while let Some(response) = responses.recv().await {
+            let started = Instant::now();
+            let mut bytes = 0;
+            let mut next = Some(response);
+            while let Some(response) = next.take() {
+                bytes += frames.push(response.header, response.body);
+                sent.push((
+                    response.timing,
+                    tracing::info_span!(parent: &response.span, "send"),
+                ));
+                next = responses.try_recv().ok();
+            }
+            if let Err(e) = socket.write_all_buf(&mut frames).await {
+                tracing::error!("Failed to write response: {}", e);
+                break;
+            }
+            activity.touch();
+            stats.record_sent(bytes);
+            let finished = Instant::now();
+            // Dropping the spans ends the requests' traces
+            for (timing, _span) in sent.drain(..) {
+                request_metrics.record(&timing, Some((started, finished)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::driven::producer_id::LocalProducerIdBlockSource;
+    use crate::adapters::driven::storage::log::PartitionLog;
+    use crate::application::admin_handler::AdminHandler;
+    use crate::application::alter_configs_handler::AlterConfigsHandler;
+    use crate::application::controller::QuorumController;
+    use crate::application::describe_configs_handler::DescribeConfigsHandler;
+    use crate::application::fetch_handler::FetchHandler;
+    use crate::application::group_coordinator::GroupCoordinator;
+    use crate::application::group_handler::GroupHandler;
+    use crate::application::list_offsets_handler::ListOffsetsHandler;
+    use crate::application::metadata_handler::MetadataHandler;
+    use crate::application::metadata_listener::BrokerMetadataListener;
+    use crate::application::produce_handler::ProduceHandler;
+    use crate::application::producer_id_manager::ProducerIdManager;
+    use crate::application::quota_manager::QuotaManager;
+    use crate::application::replica_manager::ReplicaManager;
+    use crate::application::txn_coordinator::TransactionCoordinator;
+    use crate::application::txn_handler::TxnHandler;
+    use crate::config::ChaosConfig;
+    use crate::consensus::node::Node;
+    use crate::core::domain::metadata_records::RegisterBrokerRecord;
+    use crate::core::error::ErrorCode;
+    use crate::core::ports::driven::FetchClient;
+    use crate::protocol::api_versions::{API_VERSIONS_API_KEY, ApiVersionsResponse};
+    use crate::protocol::list_groups::{LIST_GROUPS_API_KEY, ListGroupsResponse};
+    use crate::shared::constants::{DEFAULT_QUOTA_WINDOW_NUM, DEFAULT_QUOTA_WINDOW_SIZE_MS};
+    use bytes::{Buf, BufMut};
+    use tokio::io::{AsyncRead, AsyncReadExt};
+    use tokio::net::TcpSocket;
+    use tokio::sync::Mutex;
+
+    /// A single broker with no topics, holding back every ApiVersions request for
+    /// `api_versions_delay`.
+    async fn dispatcher(api_versions_delay: Duration) -> Arc<RequestDispatcher> {
+        let dir = std::env::temp_dir().join(format!("forge-tcp-server-{}", uuid::Uuid::new_v4()));
+        let replica_manager = Arc::new(Mutex::new(ReplicaManager::new(1, &dir, 1)));
+        let metadata = Arc::new(Mutex::new(BrokerMetadataListener::new(
+            1,
+            replica_manager.clone(),
+            Box::new(|_: &RegisterBrokerRecord| -> Box<dyn FetchClient> {
+                unreachable!("a single broker fetches from no other")
+            }),
+        )));
+        let log = PartitionLog::new(dir.join("metadata"), 1024 * 1024, 0, u64::MAX)
+            .await
+            .unwrap();
+        let controller = Arc::new(Mutex::new(QuorumController::new(
+            Node::new(0, vec![], log),
+            60_000,
+        )));
+        let group_coordinator = Arc::new(Mutex::new(GroupCoordinator::new(1)));
+        let txn_coordinator = TransactionCoordinator::new(
+            1,
+            60_000,
+            ProducerIdManager::new(1, 10, Box::new(LocalProducerIdBlockSource::new(&dir))),
+        );
+        let mut chaos = ChaosConfig::default();
+        chaos.latency.insert(
+            API_VERSIONS_API_KEY,
+            (100.0, api_versions_delay.as_millis() as u64),
+        );
+        Arc::new(
+            RequestDispatcher::new(
+                ProduceHandler::new(replica_manager.clone(), None, None),
+                FetchHandler::new(replica_manager.clone(), None),
+                MetadataHandler::new(1, None, metadata.clone(), None, None),
+                AdminHandler::new(
+                    AlterConfigsHandler::new(1, Box::new(controller.clone()), None, None),
+                    DescribeConfigsHandler::new(1, metadata.clone(), None, None),
+                    Box::new(controller),
+                    metadata.clone(),
+                    None,
+                ),
+                ListOffsetsHandler::new(replica_manager.clone(), None),
+                GroupHandler::new(
+                    group_coordinator.clone(),
+                    replica_manager.clone(),
+                    metadata,
+                    None,
+                ),
+                TxnHandler::new(
+                    Arc::new(Mutex::new(txn_coordinator)),
+                    group_coordinator,
+                    replica_manager,
+                    None,
+                ),
+                Arc::new(Mutex::new(QuotaManager::new(
+                    DEFAULT_QUOTA_WINDOW_SIZE_MS,
+                    DEFAULT_QUOTA_WINDOW_NUM,
+                ))),
+            )
+            .with_chaos(chaos),
+        )
+    }
+
+    /// Serves one connection from `client` with `dispatcher`, returning the server side's
+    /// stats.
+    async fn serve(
+        client: &TcpStream,
+        listener: TcpListener,
+        socket_config: SocketConfig,
+        dispatcher: Arc<RequestDispatcher>,
+    ) -> Arc<ConnectionStats> {
+        let (socket, peer_address) = listener.accept().await.unwrap();
+        assert_eq!(peer_address, client.local_addr().unwrap());
+        TcpServer::configure_socket(&socket, &socket_config).unwrap();
+        let stats = Arc::new(ConnectionRegistry::new())
+            .register(peer_address)
+            .stats
+            .clone();
+        tokio::spawn(TcpServer::handle_connection(
+            socket,
+            socket_config,
+            None,
+            dispatcher,
+            Handle::current(),
+            stats.clone(),
+            CancellationToken::new(),
+        ));
+        stats
+    }
+
+    /// A v0 request with an empty body, as ApiVersions and ListGroups take.
+    fn request(api_key: i16, correlation_id: i32) -> BytesMut {
+        let mut frame = BytesMut::new();
+        RequestHeader {
+            api_key,
+            api_version: 0,
+            correlation_id,
+            client_id: Some("pipelining-client".to_string()),
+        }
+        .encode(&mut frame);
+        let mut request = BytesMut::new();
+        request.put_u32(frame.len() as u32);
+        request.put(frame);
+        request
+    }
+
+    /// Reads one response, returning its correlation id and body.
+    async fn read_response(client: &mut (impl AsyncRead + Unpin)) -> (i32, BytesMut) {
+        let size = client.read_u32().await.unwrap() as usize;
+        let mut frame = BytesMut::zeroed(size);
+        client.read_exact(&mut frame).await.unwrap();
+        let correlation_id = frame.get_i32();
+        (correlation_id, frame)
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_responses_keep_request_order() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let dispatcher = dispatcher(Duration::from_millis(200)).await;
+        serve(&client, listener, SocketConfig::default(), dispatcher).await;
+
+        // Each slow ApiVersions is followed by a ListGroups that alone is answered at once
+        let mut requests = BytesMut::new();
+        for correlation_id in 0..4 {
+            let api_key = if correlation_id % 2 == 0 {
+                API_VERSIONS_API_KEY
+            } else {
+                LIST_GROUPS_API_KEY
+            };
+            requests.put(request(api_key, correlation_id));
+        }
+        client.write_all(&requests).await.unwrap();
+
+        for expected in 0..4 {
+            let (correlation_id, mut body) = read_response(&mut client).await;
+            assert_eq!(correlation_id, expected);
+            if expected % 2 == 0 {
+                let response = ApiVersionsResponse::decode(&mut body, 0).unwrap();
+                assert!(!response.api_keys.is_empty());
+            } else {
+                let response = ListGroupsResponse::decode(&mut body, 0).unwrap();
+                assert!(response.groups.is_empty());
+            }
+            assert!(!body.has_remaining());
         }
+    }
 
-        let mut body = vec![0u8; size as usize];
-        if socket.read_exact(&mut body).await.is_err() {
-            return Err("Failed to read request body".into());
+    #[tokio::test]
+    async fn test_reads_pause_while_the_request_queue_is_full() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let dispatcher = dispatcher(Duration::from_millis(300)).await;
+        let socket_config = SocketConfig {
+            max_queued_requests_per_connection: 2,
+            ..SocketConfig::default()
+        };
+        let stats = serve(&client, listener, socket_config, dispatcher).await;
+
+        let mut requests = BytesMut::new();
+        for correlation_id in 0..6 {
+            requests.put(request(API_VERSIONS_API_KEY, correlation_id));
         }
+        let frame_len = (requests.len() / 6) as u64;
+        client.write_all(&requests).await.unwrap();
+
+        // One request is being handled and two are queued; the rest stay in the socket
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.requests, 1);
+        assert_eq!(snapshot.bytes_received, 3 * frame_len);
 
-        Ok(Some(body))
+        // Handling one request frees a slot for exactly one more frame
+        assert_eq!(read_response(&mut client).await.0, 0);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.requests, 2);
+        assert_eq!(snapshot.bytes_received, 4 * frame_len);
+
+        for expected in 1..6 {
+            assert_eq!(read_response(&mut client).await.0, expected);
+        }
+        assert_eq!(stats.snapshot().bytes_received, 6 * frame_len);
+    }
+
+    #[tokio::test]
+    async fn test_a_slow_reader_stops_the_handler_at_the_response_queue_bound() {
+        const REQUESTS: i32 = 2000;
+        const QUEUED: usize = 4;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        // Small buffers on both ends, so unread responses soon block the writer
+        let socket = TcpSocket::new_v4().unwrap();
+        socket.set_recv_buffer_size(4096).unwrap();
+        let client = socket
+            .connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let socket_config = SocketConfig {
+            send_buffer_bytes: Some(4096),
+            max_queued_requests_per_connection: QUEUED,
+            ..SocketConfig::default()
+        };
+        let stats = serve(
+            &client,
+            listener,
+            socket_config,
+            dispatcher(Duration::ZERO).await,
+        )
+        .await;
+
+        let (mut read_half, mut write_half) = client.into_split();
+        let writer = tokio::spawn(async move {
+            for correlation_id in 0..REQUESTS {
+                let request = request(API_VERSIONS_API_KEY, correlation_id);
+                write_half.write_all(&request).await.unwrap();
+            }
+            write_half
+        });
+
+        // Wait for handling to stop while nothing is read
+        let mut handled = 0;
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                let requests = stats.snapshot().requests;
+                if requests > 0 && requests == handled {
+                    break;
+                }
+                handled = requests;
+            }
+        })
+        .await
+        .unwrap();
+        let snapshot = stats.snapshot();
+        assert!(snapshot.requests < REQUESTS as u64);
+
+        // Beyond the responses written, at most the writer's batch, the queue and the one the
+        // handler waits to queue were handled
+        let mut probe = BytesMut::new();
+        ApiVersionsResponse {
+            error_code: ErrorCode::None.code(),
+            api_keys: RequestDispatcher::supported_apis(),
+            throttle_time_ms: 0,
+        }
+        .encode(&mut probe, 0);
+        let response_len = (SIZE_PREFIX_LENGTH + size_of::<i32>() + probe.len()) as u64;
+        let written = snapshot.bytes_sent / response_len;
+        assert!(snapshot.requests - written <= 2 * QUEUED as u64 + 2);
+
+        for expected in 0..REQUESTS {
+            let (correlation_id, mut body) = read_response(&mut read_half).await;
+            assert_eq!(correlation_id, expected);
+            let response = ApiVersionsResponse::decode(&mut body, 0).unwrap();
+            assert_eq!(response.error_code, ErrorCode::None.code());
+            assert!(!body.has_remaining());
+        }
+        writer.await.unwrap();
+        assert_eq!(stats.snapshot().bytes_sent, REQUESTS as u64 * response_len);
     }
 }
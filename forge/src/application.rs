@@ -1 +1,37 @@
+pub mod admin_handler;
+pub mod alter_configs_handler;
+pub mod assignor;
+pub mod audit;
+pub mod auto_topic_creation;
+pub mod broker_lifecycle;
 pub mod controller;
+pub mod dead_letter;
+pub mod delayed_fetch;
+pub mod delayed_produce;
+pub mod describe_configs_handler;
+pub mod dynamic_config;
+pub mod fetch_handler;
+pub mod group;
+pub mod group_coordinator;
+pub mod group_handler;
+pub mod group_metadata_manager;
+pub mod list_offsets_handler;
+pub mod log_actor;
+pub mod log_metrics;
+pub mod metadata_handler;
+pub mod metadata_listener;
+pub mod partition;
+pub mod produce_handler;
+pub mod producer_id_manager;
+pub mod producer_state;
+pub mod purgatory;
+pub mod quota_manager;
+pub mod record_interceptor;
+pub mod record_validator;
+pub mod replica_fetcher;
+pub mod replica_manager;
+pub mod replica_selector;
+pub mod request_context;
+pub mod sasl;
+pub mod txn_coordinator;
+pub mod txn_handler;
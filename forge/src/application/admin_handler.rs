@@ -0,0 +1,618 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::application::alter_configs_handler::AlterConfigsHandler;
+use crate::application::describe_configs_handler::DescribeConfigsHandler;
+use crate::application::metadata_listener::BrokerMetadataListener;
+use crate::application::request_context::RequestContext;
+use crate::core::domain::acl::{
+    ACL_FILTER_ANY, AclBinding, AclBindingFilter, AclOperation, AclPermissionType,
+    PATTERN_TYPE_FILTER_MATCH, PatternType, PatternTypeFilter, Resource, ResourcePattern,
+    ResourceType,
+};
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::{Authorizer, ControllerChannel};
+use crate::protocol::alter_configs::{AlterConfigsRequest, AlterConfigsResponse};
+use crate::protocol::alter_partition_reassignments::{
+    AlterPartitionReassignmentsRequest, AlterPartitionReassignmentsResponse,
+    ReassignablePartitionResponse, ReassignableTopicResponse,
+};
+use crate::protocol::create_acls::{
+    AclCreation, AclCreationResult, CreateAclsRequest, CreateAclsResponse,
+};
+use crate::protocol::delete_acls::{
+    DeleteAclsFilter, DeleteAclsFilterResult, DeleteAclsMatchingAcl, DeleteAclsRequest,
+    DeleteAclsResponse,
+};
+use crate::protocol::describe_acls::{
+    AclDescription, DescribeAclsRequest, DescribeAclsResource, DescribeAclsResponse,
+};
+use crate::protocol::describe_configs::{DescribeConfigsRequest, DescribeConfigsResponse};
+use crate::protocol::elect_leaders::{
+    ELECTION_TYPE_PREFERRED, ELECTION_TYPE_UNCLEAN, ElectLeadersRequest, ElectLeadersResponse,
+    PartitionResult, ReplicaElectionResult, TopicPartitions,
+};
+use crate::protocol::list_partition_reassignments::{
+    ListPartitionReassignmentsRequest, ListPartitionReassignmentsResponse,
+    OngoingPartitionReassignment, OngoingTopicReassignment,
+};
+
+const SECURITY_DISABLED_MESSAGE: &str = "No authorizer is configured on the broker";
+
+/// Serves the cluster administration APIs: configs, through their own handlers, the ACLs of
+/// the broker's authorizer, partition reassignments and leader elections.
+pub struct AdminHandler {
+    alter_configs: AlterConfigsHandler,
+    describe_configs: DescribeConfigsHandler,
+    controller: Mutex<Box<dyn ControllerChannel>>,
+    metadata: Arc<Mutex<BrokerMetadataListener>>,
+    authorizer: Option<Arc<dyn Authorizer>>,
+}
+
+impl AdminHandler {
+    pub fn new(
+        alter_configs: AlterConfigsHandler,
+        describe_configs: DescribeConfigsHandler,
+        controller: Box<dyn ControllerChannel>,
+        metadata: Arc<Mutex<BrokerMetadataListener>>,
+        authorizer: Option<Arc<dyn Authorizer>>,
+    ) -> Self {
+        Self {
+            alter_configs,
+            describe_configs,
+            controller: Mutex::new(controller),
+            metadata,
+            authorizer,
+        }
+    }
+
+    pub async fn alter_configs(
+        &self,
+        context: &RequestContext,
+        request: AlterConfigsRequest,
+    ) -> AlterConfigsResponse {
+        self.alter_configs.handle(context, request).await
+    }
+
+    pub async fn describe_configs(
+        &self,
+        context: &RequestContext,
+        request: DescribeConfigsRequest,
+    ) -> DescribeConfigsResponse {
+        self.describe_configs.handle(context, request).await
+    }
+
+    /// Lists the matching bindings grouped by resource pattern. Needs Describe on the cluster.
+    pub async fn describe_acls(
+        &self,
+        context: &RequestContext,
+        request: DescribeAclsRequest,
+    ) -> DescribeAclsResponse {
+        let mut response = DescribeAclsResponse {
+            throttle_time_ms: 0,
+            error_code: ErrorCode::None.code(),
+            error_message: None,
+            resources: Vec::new(),
+        };
+        let result = self
+            .acl_authorizer(context, AclOperation::Describe)
+            .await
+            .and_then(|authorizer| {
+                let filter = binding_filter(
+                    request.resource_type_filter,
+                    request.resource_name_filter,
+                    request.pattern_type_filter,
+                    request.principal_filter,
+                    request.host_filter,
+                    request.operation,
+                    request.permission_type,
+                )
+                .map_err(|e| (ErrorCode::InvalidRequest, Some(e)))?;
+                Ok((authorizer, filter))
+            });
+        let (authorizer, filter) = match result {
+            Ok(found) => found,
+            Err((error, message)) => {
+                response.error_code = error.code();
+                response.error_message = message;
+                return response;
+            }
+        };
+
+        let mut bindings: Vec<AclBinding> = authorizer
+            .acls()
+            .await
+            .into_iter()
+            .filter(|binding| filter.matches(binding))
+            .collect();
+        bindings.sort();
+        for binding in bindings {
+            let acl = AclDescription {
+                principal: binding.principal,
+                host: binding.host,
+                operation: binding.operation.code(),
+                permission_type: binding.permission_type.code(),
+            };
+            let pattern = &binding.pattern;
+            match response.resources.last_mut() {
+                Some(resource)
+                    if resource.resource_type == pattern.resource_type.code()
+                        && resource.resource_name == pattern.name
+                        && resource.pattern_type == pattern.pattern_type.code() =>
+                {
+                    resource.acls.push(acl)
+                }
+                _ => response.resources.push(DescribeAclsResource {
+                    resource_type: pattern.resource_type.code(),
+                    resource_name: pattern.name.clone(),
+                    pattern_type: pattern.pattern_type.code(),
+                    acls: vec![acl],
+                }),
+            }
+        }
+        response
+    }
+
+    /// Adds the bindings; one that already exists is left as is. Needs Alter on the cluster.
+    pub async fn create_acls(
+        &self,
+        context: &RequestContext,
+        request: CreateAclsRequest,
+    ) -> CreateAclsResponse {
+        let authorizer = match self.acl_authorizer(context, AclOperation::Alter).await {
+            Ok(authorizer) => authorizer,
+            Err((error, message)) => {
+                return CreateAclsResponse {
+                    throttle_time_ms: 0,
+                    results: request
+                        .creations
+                        .iter()
+                        .map(|_| AclCreationResult {
+                            error_code: error.code(),
+                            error_message: message.clone(),
+                        })
+                        .collect(),
+                };
+            }
+        };
+
+        let parsed: Vec<Result<AclBinding, String>> =
+            request.creations.into_iter().map(acl_binding).collect();
+        let valid: Vec<AclBinding> = parsed
+            .iter()
+            .filter_map(|binding| binding.as_ref().ok().cloned())
+            .collect();
+        let created = if valid.is_empty() {
+            Ok(())
+        } else {
+            authorizer.create_acls(valid).await
+        };
+        if let Err(e) = &created {
+            tracing::warn!("Failed to create ACLs: {}", e);
+        }
+
+        let results = parsed
+            .into_iter()
+            .map(|binding| {
+                let (error, error_message) = match (binding, &created) {
+                    (Err(e), _) => (ErrorCode::InvalidRequest, Some(e)),
+                    (Ok(_), Err(e)) => (ErrorCode::UnknownServerError, Some(e.clone())),
+                    (Ok(binding), Ok(())) => {
+                        tracing::info!("Created ACL {}", binding);
+                        (ErrorCode::None, None)
+                    }
+                };
+                AclCreationResult {
+                    error_code: error.code(),
+                    error_message,
+                }
+            })
+            .collect();
+        CreateAclsResponse {
+            throttle_time_ms: 0,
+            results,
+        }
+    }
+
+    /// Removes the bindings each filter matches, in request order, and returns them. Needs
+    /// Alter on the cluster.
+    pub async fn delete_acls(
+        &self,
+        context: &RequestContext,
+        request: DeleteAclsRequest,
+    ) -> DeleteAclsResponse {
+        let authorizer = self.acl_authorizer(context, AclOperation::Alter).await;
+        let mut filter_results = Vec::with_capacity(request.filters.len());
+        for filter in request.filters {
+            let result = match &authorizer {
+                Ok(authorizer) => Self::delete_matching(authorizer.as_ref(), filter).await,
+                Err((error, message)) => Err((*error, message.clone())),
+            };
+            filter_results.push(match result {
+                Ok(matching_acls) => DeleteAclsFilterResult {
+                    error_code: ErrorCode::None.code(),
+                    error_message: None,
+                    matching_acls,
+                },
+                Err((error, error_message)) => DeleteAclsFilterResult {
+                    error_code: error.code(),
+                    error_message,
+                    matching_acls: Vec::new(),
+                },
+            });
+        }
+        DeleteAclsResponse {
+            throttle_time_ms: 0,
+            filter_results,
+        }
+    }
+
+    async fn delete_matching(
+        authorizer: &dyn Authorizer,
+        filter: DeleteAclsFilter,
+    ) -> Result<Vec<DeleteAclsMatchingAcl>, (ErrorCode, Option<String>)> {
+        let filter = binding_filter(
+            filter.resource_type_filter,
+            filter.resource_name_filter,
+            filter.pattern_type_filter,
+            filter.principal_filter,
+            filter.host_filter,
+            filter.operation,
+            filter.permission_type,
+        )
+        .map_err(|e| (ErrorCode::InvalidRequest, Some(e)))?;
+        let mut matching: Vec<AclBinding> = authorizer
+            .acls()
+            .await
+            .into_iter()
+            .filter(|binding| filter.matches(binding))
+            .collect();
+        matching.sort();
+        if !matching.is_empty() {
+            authorizer.delete_acls(&matching).await.map_err(|e| {
+                tracing::warn!("Failed to delete ACLs: {}", e);
+                (ErrorCode::UnknownServerError, Some(e))
+            })?;
+        }
+
+        Ok(matching
+            .into_iter()
+            .map(|binding| {
+                tracing::info!("Deleted ACL {}", binding);
+                DeleteAclsMatchingAcl {
+                    error_code: ErrorCode::None.code(),
+                    error_message: None,
+                    resource_type: binding.pattern.resource_type.code(),
+                    resource_name: binding.pattern.name,
+                    pattern_type: binding.pattern.pattern_type.code(),
+                    principal: binding.principal,
+                    host: binding.host,
+                    operation: binding.operation.code(),
+                    permission_type: binding.permission_type.code(),
+                }
+            })
+            .collect())
+    }
+
+    /// Starts or cancels the reassignment of each partition on the controller. Needs Alter on
+    /// the cluster.
+    pub async fn alter_partition_reassignments(
+        &self,
+        context: &RequestContext,
+        request: AlterPartitionReassignmentsRequest,
+    ) -> AlterPartitionReassignmentsResponse {
+        let mut response = AlterPartitionReassignmentsResponse {
+            throttle_time_ms: 0,
+            error_code: ErrorCode::None.code(),
+            error_message: None,
+            responses: Vec::new(),
+        };
+        if !context
+            .authorize(
+                self.authorizer.as_ref(),
+                AclOperation::Alter,
+                &Resource::cluster(),
+            )
+            .await
+        {
+            response.error_code = ErrorCode::ClusterAuthorizationFailed.code();
+            return response;
+        }
+
+        let mut controller = self.controller.lock().await;
+        for topic in request.topics {
+            let mut partitions = Vec::with_capacity(topic.partitions.len());
+            for partition in topic.partitions {
+                let result = controller
+                    .alter_partition_reassignment(
+                        topic.name.clone(),
+                        partition.partition_index,
+                        partition.replicas,
+                    )
+                    .await;
+                let (error, error_message) = match result {
+                    Ok(error) => (error, None),
+                    Err(e) => (ErrorCode::UnknownServerError, Some(e)),
+                };
+                partitions.push(ReassignablePartitionResponse {
+                    partition_index: partition.partition_index,
+                    error_code: error.code(),
+                    error_message,
+                });
+            }
+            response.responses.push(ReassignableTopicResponse {
+                name: topic.name,
+                partitions,
+            });
+        }
+        response
+    }
+
+    /// Lists the reassignments in progress among the requested partitions, or all of them.
+    /// Needs Describe on the cluster.
+    pub async fn list_partition_reassignments(
+        &self,
+        context: &RequestContext,
+        request: ListPartitionReassignmentsRequest,
+    ) -> ListPartitionReassignmentsResponse {
+        let mut response = ListPartitionReassignmentsResponse {
+            throttle_time_ms: 0,
+            error_code: ErrorCode::None.code(),
+            error_message: None,
+            topics: Vec::new(),
+        };
+        if !context
+            .authorize(
+                self.authorizer.as_ref(),
+                AclOperation::Describe,
+                &Resource::cluster(),
+            )
+            .await
+        {
+            response.error_code = ErrorCode::ClusterAuthorizationFailed.code();
+            return response;
+        }
+
+        let listener = self.metadata.lock().await;
+        for topic in listener.metadata.topics.values() {
+            let requested = match &request.topics {
+                Some(topics) => match topics.iter().find(|t| t.name == topic.name) {
+                    Some(requested) => Some(&requested.partition_indexes),
+                    None => continue,
+                },
+                None => None,
+            };
+            let partitions: Vec<OngoingPartitionReassignment> = topic
+                .partitions
+                .values()
+                .filter(|partition| partition.is_reassigning())
+                .filter(|partition| {
+                    requested.is_none_or(|indexes| indexes.contains(&partition.partition_index))
+                })
+                .map(|partition| OngoingPartitionReassignment {
+                    partition_index: partition.partition_index,
+                    replicas: partition.replicas.clone(),
+                    adding_replicas: partition.adding_replicas.clone(),
+                    removing_replicas: partition.removing_replicas.clone(),
+                })
+                .collect();
+            if !partitions.is_empty() {
+                response.topics.push(OngoingTopicReassignment {
+                    name: topic.name.clone(),
+                    partitions,
+                });
+            }
+        }
+        response
+    }
+
+    /// Runs a leader election for each requested partition on the controller. When every
+    /// partition is requested, those that already have the desired leader are left out of the
+    /// response. Needs Alter on the cluster.
+    pub async fn elect_leaders(
+        &self,
+        context: &RequestContext,
+        request: ElectLeadersRequest,
+    ) -> ElectLeadersResponse {
+        let mut response = ElectLeadersResponse {
+            throttle_time_ms: 0,
+            error_code: ErrorCode::None.code(),
+            replica_election_results: Vec::new(),
+        };
+        let requested_error = if !context
+            .authorize(
+                self.authorizer.as_ref(),
+                AclOperation::Alter,
+                &Resource::cluster(),
+            )
+            .await
+        {
+            Some(ErrorCode::ClusterAuthorizationFailed)
+        } else if request.election_type != ELECTION_TYPE_PREFERRED
+            && request.election_type != ELECTION_TYPE_UNCLEAN
+        {
+            Some(ErrorCode::InvalidRequest)
+        } else {
+            None
+        };
+        if let Some(error) = requested_error {
+            // v0 has no top-level error, so every partition carries it as well
+            response.error_code = error.code();
+            response.replica_election_results = request
+                .topic_partitions
+                .unwrap_or_default()
+                .into_iter()
+                .map(|topic| ReplicaElectionResult {
+                    topic: topic.topic,
+                    partition_result: topic
+                        .partition_ids
+                        .into_iter()
+                        .map(|partition_id| PartitionResult {
+                            partition_id,
+                            error_code: error.code(),
+                            error_message: None,
+                        })
+                        .collect(),
+                })
+                .collect();
+            return response;
+        }
+
+        let all_partitions = request.topic_partitions.is_none();
+        let topic_partitions = match request.topic_partitions {
+            Some(topic_partitions) => topic_partitions,
+            None => {
+                let listener = self.metadata.lock().await;
+                listener
+                    .metadata
+                    .topics
+                    .values()
+                    .map(|topic| TopicPartitions {
+                        topic: topic.name.clone(),
+                        partition_ids: topic.partitions.keys().copied().collect(),
+                    })
+                    .collect()
+            }
+        };
+
+        let unclean = request.election_type == ELECTION_TYPE_UNCLEAN;
+        let mut controller = self.controller.lock().await;
+        for topic in topic_partitions {
+            let mut partition_result = Vec::with_capacity(topic.partition_ids.len());
+            for partition_id in topic.partition_ids {
+                let result = controller
+                    .elect_partition_leader(topic.topic.clone(), partition_id, unclean)
+                    .await;
+                let (error, error_message) = match result {
+                    Ok(ErrorCode::ElectionNotNeeded) if all_partitions => continue,
+                    Ok(error) => (error, None),
+                    Err(e) => (ErrorCode::UnknownServerError, Some(e)),
+                };
+                partition_result.push(PartitionResult {
+                    partition_id,
+                    error_code: error.code(),
+                    error_message,
+                });
+            }
+            if !partition_result.is_empty() || !all_partitions {
+                response
+                    .replica_election_results
+                    .push(ReplicaElectionResult {
+                        topic: topic.topic,
+                        partition_result,
+                    });
+            }
+        }
+        response
+    }
+
+    /// The authorizer, once `context` may perform `operation` on the cluster.
+    async fn acl_authorizer(
+        &self,
+        context: &RequestContext,
+        operation: AclOperation,
+    ) -> Result<&Arc<dyn Authorizer>, (ErrorCode, Option<String>)> {
+        let Some(authorizer) = self.authorizer.as_ref() else {
+            return Err((
+                ErrorCode::SecurityDisabled,
+                Some(SECURITY_DISABLED_MESSAGE.to_string()),
+            ));
+        };
+        if !context
+            .authorize(Some(authorizer), operation, &Resource::cluster())
+            .await
+        {
+            return Err((ErrorCode::ClusterAuthorizationFailed, None));
+        }
+        Ok(authorizer)
+    }
+}
+
+/// Decodes an optional wire code, where `ACL_FILTER_ANY` means no constraint.
+fn filter_code<T>(
+    code: i8,
+    from_code: fn(i8) -> Option<T>,
+    what: &str,
+) -> Result<Option<T>, String> {
+    if code == ACL_FILTER_ANY {
+        return Ok(None);
+    }
+    from_code(code)
+        .map(Some)
+        .ok_or_else(|| format!("Unknown {} {}", what, code))
+}
+
+fn binding_filter(
+    resource_type: i8,
+    name: Option<String>,
+    pattern_type: i8,
+    principal: Option<String>,
+    host: Option<String>,
+    operation: i8,
+    permission_type: i8,
+) -> Result<AclBindingFilter, String> {
+    let pattern_type = match pattern_type {
+        ACL_FILTER_ANY => PatternTypeFilter::Any,
+        PATTERN_TYPE_FILTER_MATCH => PatternTypeFilter::Match,
+        code => PatternType::from_code(code)
+            .map(PatternTypeFilter::Exact)
+            .ok_or_else(|| format!("Unknown pattern type {}", code))?,
+    };
+    Ok(AclBindingFilter {
+        resource_type: filter_code(resource_type, ResourceType::from_code, "resource type")?,
+        name,
+        pattern_type,
+        principal,
+        host,
+        operation: filter_code(operation, AclOperation::from_code, "operation")?,
+        permission_type: filter_code(
+            permission_type,
+            AclPermissionType::from_code,
+            "permission type",
+        )?,
+    })
+}
+
+/// Validates a creation; unlike a filter it must name one value for every field.
+fn acl_binding(creation: AclCreation) -> Result<AclBinding, String> {
+    let resource_type = ResourceType::from_code(creation.resource_type)
+        .ok_or_else(|| format!("Invalid resource type {}", creation.resource_type))?;
+    let pattern_type = PatternType::from_code(creation.resource_pattern_type)
+        .ok_or_else(|| format!("Invalid pattern type {}", creation.resource_pattern_type))?;
+    let operation = AclOperation::from_code(creation.operation)
+        .ok_or_else(|| format!("Invalid operation {}", creation.operation))?;
+    let permission_type = AclPermissionType::from_code(creation.permission_type)
+        .ok_or_else(|| format!("Invalid permission type {}", creation.permission_type))?;
+    if creation.resource_name.is_empty() {
+        return Err("The resource name must not be empty".to_string());
+    }
+    if !creation
+        .principal
+        .split_once(':')
+        .is_some_and(|(principal_type, name)| !principal_type.is_empty() && !name.is_empty())
+    {
+        return Err(format!(
+            "Invalid principal {}: expected type:name, such as User:alice",
+            creation.principal
+        ));
+    }
+    if creation.host.is_empty() {
+        return Err("The host must not be empty; use * for any host".to_string());
+    }
+    // The ACL file separates fields with commas, leaving only the name free to contain them
+    if creation.principal.contains(',') || creation.host.contains(',') {
+        return Err("The principal and host must not contain commas".to_string());
+    }
+
+    Ok(AclBinding {
+        pattern: ResourcePattern {
+            resource_type,
+            name: creation.resource_name,
+            pattern_type,
+        },
+        principal: creation.principal,
+        host: creation.host,
+        operation,
+        permission_type,
+    })
+}
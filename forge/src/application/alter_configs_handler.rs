@@ -0,0 +1,157 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::application::request_context::RequestContext;
+use crate::core::domain::acl::{AclOperation, Resource, ResourceType};
+use crate::core::domain::metadata_records::{
+    CONFIG_RESOURCE_BROKER, CONFIG_RESOURCE_BROKER_LOGGER, CONFIG_RESOURCE_TOPIC,
+};
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::{Authorizer, ControllerChannel};
+use crate::protocol::alter_configs::{
+    AlterConfigsRequest, AlterConfigsResource, AlterConfigsResourceResponse, AlterConfigsResponse,
+};
+use crate::shared::collections::FlatMap;
+use crate::shared::logging::LogLevelHandle;
+
+/// Forwards AlterConfigs to the controller, which records the overrides in the metadata log;
+/// brokers pick up their own from there. Logger levels are the exception: they are set on the
+/// broker named by the resource, which must be this one, and last until it restarts.
+pub struct AlterConfigsHandler {
+    node_id: i32,
+    channel: Mutex<Box<dyn ControllerChannel>>,
+    authorizer: Option<Arc<dyn Authorizer>>,
+    log_level: Option<LogLevelHandle>,
+}
+
+impl AlterConfigsHandler {
+    pub fn new(
+        node_id: i32,
+        channel: Box<dyn ControllerChannel>,
+        authorizer: Option<Arc<dyn Authorizer>>,
+        log_level: Option<LogLevelHandle>,
+    ) -> Self {
+        Self {
+            node_id,
+            channel: Mutex::new(channel),
+            authorizer,
+            log_level,
+        }
+    }
+
+    pub async fn handle(
+        &self,
+        context: &RequestContext,
+        request: AlterConfigsRequest,
+    ) -> AlterConfigsResponse {
+        let mut responses = Vec::with_capacity(request.resources.len());
+        for resource in request.resources {
+            let resource_type = resource.resource_type;
+            let resource_name = resource.resource_name.clone();
+            let (error, error_message) = match self
+                .alter_resource(context, resource, request.validate_only)
+                .await
+            {
+                Ok(error) => (error, None),
+                Err(e) => {
+                    tracing::warn!("Failed to alter configs of {}: {}", resource_name, e);
+                    (ErrorCode::UnknownServerError, Some(e))
+                }
+            };
+            responses.push(AlterConfigsResourceResponse {
+                error_code: error.code(),
+                error_message,
+                resource_type,
+                resource_name,
+            });
+        }
+
+        AlterConfigsResponse {
+            throttle_time_ms: 0,
+            responses,
+        }
+    }
+
+    async fn alter_resource(
+        &self,
+        context: &RequestContext,
+        resource: AlterConfigsResource,
+        validate_only: bool,
+    ) -> Result<ErrorCode, String> {
+        let (acl_resource, denied) = match resource.resource_type {
+            CONFIG_RESOURCE_TOPIC => (
+                Resource::new(ResourceType::Topic, resource.resource_name.as_str()),
+                ErrorCode::TopicAuthorizationFailed,
+            ),
+            CONFIG_RESOURCE_BROKER | CONFIG_RESOURCE_BROKER_LOGGER => {
+                (Resource::cluster(), ErrorCode::ClusterAuthorizationFailed)
+            }
+            _ => return Ok(ErrorCode::InvalidRequest),
+        };
+        if !context
+            .authorize(
+                self.authorizer.as_ref(),
+                AclOperation::AlterConfigs,
+                &acl_resource,
+            )
+            .await
+        {
+            return Ok(denied);
+        }
+        if resource.resource_type == CONFIG_RESOURCE_BROKER_LOGGER {
+            return Ok(self.alter_loggers(resource, validate_only));
+        }
+
+        let configs = resource
+            .configs
+            .into_iter()
+            .map(|config| (config.name, config.value))
+            .collect();
+        self.channel
+            .lock()
+            .await
+            .alter_configs(
+                resource.resource_type,
+                resource.resource_name,
+                configs,
+                validate_only,
+            )
+            .await
+    }
+
+    /// Replaces this broker's logger levels with the listed ones.
+    fn alter_loggers(&self, resource: AlterConfigsResource, validate_only: bool) -> ErrorCode {
+        let Some(log_level) = self
+            .log_level
+            .as_ref()
+            .filter(|_| resource.resource_name == self.node_id.to_string())
+        else {
+            return ErrorCode::InvalidRequest;
+        };
+        let mut loggers = FlatMap::new();
+        for config in resource.configs {
+            let Some(level) = config.value else {
+                return ErrorCode::InvalidConfig;
+            };
+            loggers.insert(config.name, level.to_lowercase());
+        }
+
+        let result = if validate_only {
+            LogLevelHandle::validate_loggers(&loggers)
+        } else {
+            log_level.set_loggers(loggers)
+        };
+        match result {
+            Ok(()) => {
+                if !validate_only {
+                    tracing::info!("Updated log filter to {}", log_level.directives());
+                }
+                ErrorCode::None
+            }
+            Err(e) => {
+                tracing::warn!("Refused logger levels: {}", e);
+                ErrorCode::InvalidConfig
+            }
+        }
+    }
+}
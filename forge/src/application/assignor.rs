@@ -0,0 +1,4 @@
+pub mod partition_assignor;
+pub mod range;
+pub mod round_robin;
+pub mod sticky;
@@ -0,0 +1,192 @@
+use bytes::BytesMut;
+
+use crate::application::assignor::range::RangeAssignor;
+use crate::application::assignor::round_robin::RoundRobinAssignor;
+use crate::application::assignor::sticky::{CooperativeStickyAssignor, StickyAssignor};
+use crate::core::domain::consumer_protocol::{Assignment, Subscription};
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::protocol::types::Type;
+use crate::shared::collections::FlatMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RebalanceProtocol {
+    /// Members revoke everything before rejoining.
+    Eager,
+    /// Members keep owned partitions; moved partitions are revoked first and reassigned in a follow-up rebalance.
+    Cooperative,
+}
+
+pub trait PartitionAssignor: Send + Sync {
+    /// Protocol name advertised in JoinGroup and selected by the coordinator.
+    fn name(&self) -> &'static str;
+
+    fn rebalance_protocol(&self) -> RebalanceProtocol {
+        RebalanceProtocol::Eager
+    }
+
+    /// Maps every member id in `subscriptions` to the partitions it should consume.
+    fn assign(
+        &self,
+        partitions_per_topic: &FlatMap<String, i32>,
+        subscriptions: &FlatMap<String, Subscription>,
+    ) -> FlatMap<String, Vec<TopicPartition>>;
+}
+
+pub fn assignor_for(protocol_name: &str) -> Option<Box<dyn PartitionAssignor>> {
+    let assignors: [Box<dyn PartitionAssignor>; 4] = [
+        Box::new(RangeAssignor),
+        Box::new(RoundRobinAssignor),
+        Box::new(StickyAssignor),
+        Box::new(CooperativeStickyAssignor),
+    ];
+    assignors.into_iter().find(|a| a.name() == protocol_name)
+}
+
+/// Runs the assignor selected for the group over the raw JoinGroup member metadata and
+/// returns the encoded assignment per member, ready to send in the leader's SyncGroup.
+pub fn perform_assignment(
+    protocol_name: &str,
+    members: &[(String, Vec<u8>)],
+    partitions_per_topic: &FlatMap<String, i32>,
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let assignor = assignor_for(protocol_name)
+        .ok_or_else(|| format!("Unsupported partition assignor: {}", protocol_name))?;
+
+    let mut subscriptions = FlatMap::new();
+    for (member_id, metadata) in members {
+        let subscription = Subscription::decode(&mut metadata.as_slice())
+            .map_err(|e| format!("Invalid subscription from {}: {}", member_id, e))?;
+        subscriptions.insert(member_id.clone(), subscription);
+    }
+
+    let assignment = assignor.assign(partitions_per_topic, &subscriptions);
+
+    Ok(subscriptions
+        .keys()
+        .map(|member_id| {
+            let mut buf = BytesMut::new();
+            Assignment {
+                partitions: assignment.get(member_id).cloned().unwrap_or_default(),
+                user_data: None,
+            }
+            .encode(&mut buf);
+            (member_id.clone(), buf.to_vec())
+        })
+        .collect())
+}
+
+/// Every partition of every topic some member subscribed to, sorted by topic then partition.
+pub fn subscribed_partitions(
+    partitions_per_topic: &FlatMap<String, i32>,
+    subscriptions: &FlatMap<String, Subscription>,
+) -> Vec<TopicPartition> {
+    partitions_per_topic
+        .iter()
+        .filter(|(topic, _)| subscriptions.values().any(|s| s.topics.contains(topic)))
+        .flat_map(|(topic, &count)| (0..count).map(move |p| TopicPartition::new(topic.clone(), p)))
+        .collect()
+}
+
+pub fn empty_assignment(
+    subscriptions: &FlatMap<String, Subscription>,
+) -> FlatMap<String, Vec<TopicPartition>> {
+    let mut assignment = FlatMap::new();
+    for member_id in subscriptions.keys() {
+        assignment.insert(member_id.clone(), Vec::new());
+    }
+    assignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(topics: &[&str], owned: &[(&str, i32)], generation_id: i32) -> Subscription {
+        Subscription {
+            topics: topics.iter().map(|t| t.to_string()).collect(),
+            user_data: None,
+            owned_partitions: owned
+                .iter()
+                .map(|(t, p)| TopicPartition::new(*t, *p))
+                .collect(),
+            generation_id,
+        }
+    }
+
+    fn partitions(entries: &[(&str, i32)]) -> FlatMap<String, i32> {
+        let mut map = FlatMap::new();
+        for (topic, count) in entries {
+            map.insert(topic.to_string(), *count);
+        }
+        map
+    }
+
+    fn counts(assignment: &FlatMap<String, Vec<TopicPartition>>) -> Vec<usize> {
+        assignment.values().map(|p| p.len()).collect()
+    }
+
+    #[test]
+    fn test_range_assigns_contiguous_ranges_per_topic() {
+        let mut subscriptions = FlatMap::new();
+        subscriptions.insert("a".to_string(), subscription(&["t0", "t1"], &[], -1));
+        subscriptions.insert("b".to_string(), subscription(&["t0", "t1"], &[], -1));
+
+        let assignment = assignor_for("range")
+            .unwrap()
+            .assign(&partitions(&[("t0", 3), ("t1", 3)]), &subscriptions);
+
+        // Range assignment is per topic, so the first member gets the extra partition of both
+        assert_eq!(
+            assignment.get(&"a".to_string()).unwrap(),
+            &vec![
+                TopicPartition::new("t0", 0),
+                TopicPartition::new("t0", 1),
+                TopicPartition::new("t1", 0),
+                TopicPartition::new("t1", 1),
+            ]
+        );
+        assert_eq!(counts(&assignment), vec![4, 2]);
+    }
+
+    #[test]
+    fn test_round_robin_spreads_across_topics() {
+        let mut subscriptions = FlatMap::new();
+        subscriptions.insert("a".to_string(), subscription(&["t0", "t1"], &[], -1));
+        subscriptions.insert("b".to_string(), subscription(&["t0", "t1"], &[], -1));
+
+        let assignment = assignor_for("roundrobin")
+            .unwrap()
+            .assign(&partitions(&[("t0", 3), ("t1", 3)]), &subscriptions);
+
+        assert_eq!(counts(&assignment), vec![3, 3]);
+    }
+
+    #[test]
+    fn test_sticky_keeps_owned_partitions_when_member_joins() {
+        let mut subscriptions = FlatMap::new();
+        subscriptions.insert(
+            "a".to_string(),
+            subscription(&["t0"], &[("t0", 0), ("t0", 1), ("t0", 2), ("t0", 3)], 1),
+        );
+        subscriptions.insert("b".to_string(), subscription(&["t0"], &[], -1));
+        let topics = partitions(&[("t0", 4)]);
+
+        let sticky = assignor_for("sticky")
+            .unwrap()
+            .assign(&topics, &subscriptions);
+        assert_eq!(counts(&sticky), vec![2, 2]);
+        assert!(
+            sticky
+                .get(&"a".to_string())
+                .unwrap()
+                .iter()
+                .all(|tp| tp.partition < 4)
+        );
+
+        // Cooperative sticky revokes moved partitions first and assigns them next round
+        let cooperative = assignor_for("cooperative-sticky")
+            .unwrap()
+            .assign(&topics, &subscriptions);
+        assert_eq!(counts(&cooperative), vec![2, 0]);
+    }
+}
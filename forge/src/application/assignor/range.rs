@@ -0,0 +1,49 @@
+use crate::application::assignor::partition_assignor::{PartitionAssignor, empty_assignment};
+use crate::core::domain::consumer_protocol::Subscription;
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::shared::collections::FlatMap;
+
+/// Splits each topic's partitions into contiguous ranges over the members subscribed to it,
+/// giving the first members one extra partition when the count does not divide evenly.
+pub struct RangeAssignor;
+
+impl PartitionAssignor for RangeAssignor {
+    fn name(&self) -> &'static str {
+        "range"
+    }
+
+    fn assign(
+        &self,
+        partitions_per_topic: &FlatMap<String, i32>,
+        subscriptions: &FlatMap<String, Subscription>,
+    ) -> FlatMap<String, Vec<TopicPartition>> {
+        let mut assignment = empty_assignment(subscriptions);
+
+        for (topic, &num_partitions) in partitions_per_topic.iter() {
+            let consumers: Vec<&String> = subscriptions
+                .iter()
+                .filter(|(_, s)| s.topics.contains(topic))
+                .map(|(member_id, _)| member_id)
+                .collect();
+            if consumers.is_empty() {
+                continue;
+            }
+
+            let per_consumer = num_partitions / consumers.len() as i32;
+            let extra = num_partitions % consumers.len() as i32;
+
+            for (i, member_id) in consumers.into_iter().enumerate() {
+                let i = i as i32;
+                let start = per_consumer * i + i.min(extra);
+                let len = per_consumer + i32::from(i < extra);
+                if let Some(partitions) = assignment.get_mut(member_id) {
+                    partitions.extend(
+                        (start..start + len).map(|p| TopicPartition::new(topic.clone(), p)),
+                    );
+                }
+            }
+        }
+
+        assignment
+    }
+}
@@ -0,0 +1,44 @@
+use crate::application::assignor::partition_assignor::{
+    PartitionAssignor, empty_assignment, subscribed_partitions,
+};
+use crate::core::domain::consumer_protocol::Subscription;
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::shared::collections::FlatMap;
+
+/// Deals all subscribed partitions out one at a time over the members, skipping members
+/// that are not subscribed to the partition's topic.
+pub struct RoundRobinAssignor;
+
+impl PartitionAssignor for RoundRobinAssignor {
+    fn name(&self) -> &'static str {
+        "roundrobin"
+    }
+
+    fn assign(
+        &self,
+        partitions_per_topic: &FlatMap<String, i32>,
+        subscriptions: &FlatMap<String, Subscription>,
+    ) -> FlatMap<String, Vec<TopicPartition>> {
+        let mut assignment = empty_assignment(subscriptions);
+        let members: Vec<(&String, &Subscription)> = subscriptions.iter().collect();
+        if members.is_empty() {
+            return assignment;
+        }
+
+        let mut cursor = 0;
+        for tp in subscribed_partitions(partitions_per_topic, subscriptions) {
+            for _ in 0..members.len() {
+                let (member_id, subscription) = members[cursor % members.len()];
+                cursor += 1;
+                if subscription.topics.contains(&tp.topic) {
+                    if let Some(partitions) = assignment.get_mut(member_id) {
+                        partitions.push(tp);
+                    }
+                    break;
+                }
+            }
+        }
+
+        assignment
+    }
+}
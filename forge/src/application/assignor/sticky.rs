@@ -0,0 +1,160 @@
+use crate::application::assignor::partition_assignor::{
+    PartitionAssignor, RebalanceProtocol, empty_assignment, subscribed_partitions,
+};
+use crate::core::domain::consumer_protocol::Subscription;
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::shared::collections::FlatMap;
+
+/// Balances partitions across members while moving as few previously owned partitions as possible.
+pub struct StickyAssignor;
+
+/// Sticky assignment for the cooperative protocol: a partition changing owner is left
+/// unassigned this round so its previous owner can revoke it first.
+pub struct CooperativeStickyAssignor;
+
+impl PartitionAssignor for StickyAssignor {
+    fn name(&self) -> &'static str {
+        "sticky"
+    }
+
+    fn assign(
+        &self,
+        partitions_per_topic: &FlatMap<String, i32>,
+        subscriptions: &FlatMap<String, Subscription>,
+    ) -> FlatMap<String, Vec<TopicPartition>> {
+        sticky_assign(partitions_per_topic, subscriptions)
+    }
+}
+
+impl PartitionAssignor for CooperativeStickyAssignor {
+    fn name(&self) -> &'static str {
+        "cooperative-sticky"
+    }
+
+    fn rebalance_protocol(&self) -> RebalanceProtocol {
+        RebalanceProtocol::Cooperative
+    }
+
+    fn assign(
+        &self,
+        partitions_per_topic: &FlatMap<String, i32>,
+        subscriptions: &FlatMap<String, Subscription>,
+    ) -> FlatMap<String, Vec<TopicPartition>> {
+        let mut assignment = sticky_assign(partitions_per_topic, subscriptions);
+
+        for (member_id, partitions) in assignment.iter_mut() {
+            partitions.retain(|tp| {
+                subscriptions
+                    .iter()
+                    .all(|(owner, s)| owner == member_id || !s.owned_partitions.contains(tp))
+            });
+        }
+
+        assignment
+    }
+}
+
+fn sticky_assign(
+    partitions_per_topic: &FlatMap<String, i32>,
+    subscriptions: &FlatMap<String, Subscription>,
+) -> FlatMap<String, Vec<TopicPartition>> {
+    let mut assignment = empty_assignment(subscriptions);
+    let all_partitions = subscribed_partitions(partitions_per_topic, subscriptions);
+
+    // Keep still-valid owned partitions; on conflicting claims the highest generation wins
+    let mut owners: Vec<(&String, &Subscription)> = subscriptions.iter().collect();
+    owners.sort_by_key(|(_, s)| std::cmp::Reverse(s.generation_id));
+
+    let mut claimed: FlatMap<TopicPartition, String> = FlatMap::new();
+    for (member_id, subscription) in owners {
+        for tp in &subscription.owned_partitions {
+            if subscription.topics.contains(&tp.topic)
+                && all_partitions.contains(tp)
+                && !claimed.contains_key(tp)
+            {
+                claimed.insert(tp.clone(), member_id.clone());
+                if let Some(partitions) = assignment.get_mut(member_id) {
+                    partitions.push(tp.clone());
+                }
+            }
+        }
+    }
+
+    for tp in all_partitions {
+        if claimed.contains_key(&tp) {
+            continue;
+        }
+        if let Some(member_id) = least_loaded_member(&assignment, subscriptions, &tp.topic)
+            && let Some(partitions) = assignment.get_mut(&member_id)
+        {
+            partitions.push(tp);
+        }
+    }
+
+    rebalance(&mut assignment, subscriptions);
+
+    for partitions in assignment.values_mut() {
+        partitions.sort();
+    }
+    assignment
+}
+
+fn least_loaded_member(
+    assignment: &FlatMap<String, Vec<TopicPartition>>,
+    subscriptions: &FlatMap<String, Subscription>,
+    topic: &str,
+) -> Option<String> {
+    assignment
+        .iter()
+        .filter(|(member_id, _)| {
+            subscriptions
+                .get(member_id)
+                .is_some_and(|s| s.topics.iter().any(|t| t == topic))
+        })
+        .min_by_key(|(_, partitions)| partitions.len())
+        .map(|(member_id, _)| member_id.clone())
+}
+
+/// Moves partitions from the most to the least loaded eligible members until no single
+/// move can shrink the gap any further.
+fn rebalance(
+    assignment: &mut FlatMap<String, Vec<TopicPartition>>,
+    subscriptions: &FlatMap<String, Subscription>,
+) {
+    let total: usize = assignment.values().map(|p| p.len()).sum();
+    let max_moves = total * assignment.len().max(1);
+
+    for _ in 0..max_moves {
+        let mut members: Vec<(String, usize)> = assignment
+            .iter()
+            .map(|(member_id, partitions)| (member_id.clone(), partitions.len()))
+            .collect();
+        members.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mut moved = false;
+        'donors: for (donor, donor_load) in &members {
+            let donor_partitions = assignment.get(donor).cloned().unwrap_or_default();
+            for tp in donor_partitions.iter().rev() {
+                let Some(receiver) = least_loaded_member(assignment, subscriptions, &tp.topic)
+                else {
+                    continue;
+                };
+                let receiver_load = assignment.get(&receiver).map_or(0, |p| p.len());
+                if receiver_load + 1 < *donor_load {
+                    if let Some(partitions) = assignment.get_mut(donor) {
+                        partitions.retain(|p| p != tp);
+                    }
+                    if let Some(partitions) = assignment.get_mut(&receiver) {
+                        partitions.push(tp.clone());
+                    }
+                    moved = true;
+                    break 'donors;
+                }
+            }
+        }
+
+        if !moved {
+            break;
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::application::request_context::RequestContext;
+use crate::config::SecurityProtocol;
+use crate::core::domain::acl::{AclOperation, Resource};
+use crate::core::domain::principal::KafkaPrincipal;
+use crate::shared::logging::AUDIT_TARGET;
+
+pub fn authentication_succeeded(
+    listener: SecurityProtocol,
+    client_host: &str,
+    mechanism: &str,
+    principal: &KafkaPrincipal,
+) {
+    tracing::info!(
+        target: AUDIT_TARGET,
+        event = "authentication",
+        outcome = "success",
+        %listener,
+        client_host,
+        mechanism,
+        %principal,
+        "Authenticated {} from {}",
+        principal,
+        client_host
+    );
+}
+
+/// `mechanism` is `None` when the client asked for one that is not enabled.
+pub fn authentication_failed(
+    listener: SecurityProtocol,
+    client_host: &str,
+    mechanism: Option<&str>,
+    reason: &str,
+) {
+    tracing::info!(
+        target: AUDIT_TARGET,
+        event = "authentication",
+        outcome = "failure",
+        %listener,
+        client_host,
+        mechanism,
+        reason,
+        "Failed authentication from {}: {}",
+        client_host,
+        reason
+    );
+}
+
+pub fn authorization_denied(
+    context: &RequestContext,
+    operation: AclOperation,
+    resource: &Resource,
+) {
+    tracing::info!(
+        target: AUDIT_TARGET,
+        event = "authorization",
+        outcome = "denied",
+        listener = %context.listener,
+        client_host = context.client_host,
+        client_id = context.client_id,
+        principal = %context.principal,
+        %operation,
+        resource_type = ?resource.resource_type,
+        resource = resource.name,
+        "Denied {} on {:?} {} to {}",
+        operation,
+        resource.resource_type,
+        resource.name,
+        context.principal
+    );
+}
@@ -0,0 +1,74 @@
+use tokio::sync::Mutex;
+
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::ControllerChannel;
+use crate::shared::collections::FlatSet;
+
+/// Asks the controller to create topics that clients produce to before anyone created them
+/// (auto.create.topics.enable), using the broker's default partition count and replication
+/// factor.
+pub struct AutoTopicCreationManager {
+    channel: Mutex<Box<dyn ControllerChannel>>,
+    num_partitions: i32,
+    replication_factor: i16,
+    /// Topics the controller already knows about, so they are not requested again.
+    known_topics: Mutex<FlatSet<String>>,
+}
+
+impl AutoTopicCreationManager {
+    pub fn new(
+        channel: Box<dyn ControllerChannel>,
+        num_partitions: i32,
+        replication_factor: i16,
+    ) -> Self {
+        Self {
+            channel: Mutex::new(channel),
+            num_partitions,
+            replication_factor,
+            known_topics: Mutex::new(FlatSet::new()),
+        }
+    }
+
+    /// Whether the controller was asked to create the topic, or already had it.
+    pub async fn is_known(&self, topic: &str) -> bool {
+        self.known_topics.lock().await.contains(&topic.to_string())
+    }
+
+    /// Creates each topic the controller does not know yet. Failures are logged and left to the
+    /// client's retry, which will see the topic as unknown.
+    pub async fn create_topics(&self, topics: Vec<String>) {
+        let mut known_topics = self.known_topics.lock().await;
+        let mut channel = self.channel.lock().await;
+        for topic in topics {
+            if known_topics.contains(&topic) {
+                continue;
+            }
+
+            let result = channel
+                .create_topic(topic.clone(), self.num_partitions, self.replication_factor)
+                .await;
+            match result {
+                Ok(ErrorCode::None) => {
+                    tracing::info!(
+                        "Auto-created topic {} with {} partitions and replication factor {}",
+                        topic,
+                        self.num_partitions,
+                        self.replication_factor
+                    );
+                    known_topics.insert(topic);
+                }
+                Ok(ErrorCode::TopicAlreadyExists) => {
+                    known_topics.insert(topic);
+                }
+                Ok(error) => {
+                    tracing::warn!(
+                        "Controller refused to auto-create topic {}: {}",
+                        topic,
+                        error
+                    )
+                }
+                Err(e) => tracing::warn!("Failed to auto-create topic {}: {}", topic, e),
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::core::domain::metadata_records::RegisterBrokerRecord;
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::ControllerChannel;
+
+/// Keeps this broker registered with the controller: registers under a fresh incarnation id
+/// at startup, then heartbeats until cancelled, registering again whenever it gets fenced.
+pub struct BrokerLifecycleManager {
+    registration: RegisterBrokerRecord,
+    heartbeat_interval: Duration,
+    channel: Box<dyn ControllerChannel>,
+    registered: bool,
+}
+
+impl BrokerLifecycleManager {
+    pub fn new(
+        broker_id: i32,
+        host: String,
+        port: i32,
+        rack: Option<String>,
+        heartbeat_interval_ms: u64,
+        channel: Box<dyn ControllerChannel>,
+    ) -> Self {
+        Self {
+            registration: RegisterBrokerRecord {
+                broker_id,
+                incarnation_id: Uuid::new_v4(),
+                host,
+                port,
+                rack,
+            },
+            heartbeat_interval: Duration::from_millis(heartbeat_interval_ms),
+            channel,
+            registered: false,
+        }
+    }
+
+    pub fn incarnation_id(&self) -> Uuid {
+        self.registration.incarnation_id
+    }
+
+    pub fn is_registered(&self) -> bool {
+        self.registered
+    }
+
+    pub async fn run(mut self, cancel_token: CancellationToken) {
+        loop {
+            self.tick().await;
+            tokio::select! {
+                _ = tokio::time::sleep(self.heartbeat_interval) => {}
+                _ = cancel_token.cancelled() => break,
+            }
+        }
+        tracing::info!(
+            "Stopped lifecycle manager for broker {}",
+            self.registration.broker_id
+        );
+    }
+
+    /// Registers if needed, otherwise heartbeats.
+    pub async fn tick(&mut self) {
+        let broker_id = self.registration.broker_id;
+
+        if !self.registered {
+            match self
+                .channel
+                .register_broker(self.registration.clone())
+                .await
+            {
+                Ok(ErrorCode::None) => {
+                    tracing::info!(
+                        "Broker {} registered with incarnation {}",
+                        broker_id,
+                        self.registration.incarnation_id
+                    );
+                    self.registered = true;
+                }
+                // The previous incarnation's session has not lapsed yet; keep retrying
+                Ok(error_code) => {
+                    tracing::warn!("Broker {} registration rejected: {}", broker_id, error_code)
+                }
+                Err(e) => tracing::warn!("Broker {} failed to register: {}", broker_id, e),
+            }
+            return;
+        }
+
+        match self
+            .channel
+            .broker_heartbeat(broker_id, self.registration.incarnation_id)
+            .await
+        {
+            Ok(ErrorCode::None) => {}
+            Ok(ErrorCode::BrokerIdNotRegistered | ErrorCode::StaleBrokerEpoch) => {
+                tracing::warn!("Broker {} was fenced, registering again", broker_id);
+                self.registered = false;
+            }
+            Ok(error_code) => {
+                tracing::warn!("Broker {} heartbeat rejected: {}", broker_id, error_code)
+            }
+            Err(e) => tracing::warn!("Broker {} failed to heartbeat: {}", broker_id, e),
+        }
+    }
+}
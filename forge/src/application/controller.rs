@@ -1,90 +1,1537 @@
+use async_trait::async_trait;
 use bytes::BytesMut;
-use std::time::{SystemTime, UNIX_EPOCH};
+use rand::RngExt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
 
+use crate::application::record_validator::{
+    JSON_VALIDATOR, REQUIRED_HEADERS_VALIDATOR, validator_names,
+};
+use crate::config::BrokerConfig;
+use crate::consensus::metadata_cache::ClusterMetadataCache;
 use crate::consensus::node::Node;
+use crate::consensus::state::Role;
 use crate::core::domain::metadata_records::{
-    MetadataRecord, PartitionRecord, RegisterBrokerRecord, TopicRecord,
+    CONFIG_RESOURCE_BROKER, CONFIG_RESOURCE_TOPIC, ConfigRecord, FenceBrokerRecord, MetadataRecord,
+    NO_LEADER, PartitionChangeRecord, PartitionRecord, RegisterBrokerRecord, TopicRecord,
 };
 use crate::core::domain::record::Record;
 use crate::core::domain::record_batch::RecordBatch;
-use crate::protocol::types::{Type, Varint, Varlong};
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::{ControllerChannel, MetadataPublisher};
+use crate::protocol::types::Type;
+use crate::shared::collections::{FlatMap, FlatSet};
+use crate::shared::constants::{
+    CLEANUP_POLICY_COMPACT, CLEANUP_POLICY_CONFIG, CONSUMER_OFFSETS_TOPIC,
+    DEFAULT_OFFSETS_TOPIC_PARTITIONS, DEFAULT_TRANSACTION_STATE_PARTITIONS,
+    DEFAULT_UNCLEAN_LEADER_ELECTION_ENABLE, RECORD_VALIDATORS_CONFIG, TRANSACTION_STATE_TOPIC,
+    UNCLEAN_LEADER_ELECTION_ENABLE_CONFIG,
+};
+use crate::shared::scheduler::spawn_periodic;
+use crate::shared::time::current_time_ms;
 
+/// Owns cluster metadata on the active controller: assigns replicas to new topics, elects
+/// partition leaders as brokers come and go, and publishes every change to the brokers.
 pub struct QuorumController {
     pub raft_node: Node,
+    pub metadata: ClusterMetadataCache,
+    /// A live broker that has not heartbeated for this long is fenced.
+    pub broker_session_timeout_ms: i64,
+    /// Last heartbeat time of each live broker. Not persisted: a new active controller starts
+    /// every live broker's session afresh.
+    broker_heartbeats: FlatMap<i32, i64>,
+    publishers: FlatMap<i32, Box<dyn MetadataPublisher>>,
+    /// The record validators brokers have, which a topic's record.validators may name.
+    record_validators: FlatSet<String>,
 }
 
 impl QuorumController {
-    pub fn new(raft_node: Node) -> Self {
-        Self { raft_node }
+    pub fn new(raft_node: Node, broker_session_timeout_ms: i64) -> Self {
+        let mut controller = Self {
+            raft_node,
+            metadata: ClusterMetadataCache::new(),
+            broker_session_timeout_ms,
+            broker_heartbeats: FlatMap::new(),
+            publishers: FlatMap::new(),
+            record_validators: FlatSet::new(),
+        };
+        controller.set_record_validators(vec![
+            JSON_VALIDATOR.to_string(),
+            REQUIRED_HEADERS_VALIDATOR.to_string(),
+        ]);
+        controller
+    }
+
+    pub fn set_record_validators(&mut self, names: Vec<String>) {
+        self.record_validators = FlatSet::new();
+        for name in names {
+            self.record_validators.insert(name);
+        }
+    }
+
+    /// Rejects a topic config naming a record validator brokers do not have, which would
+    /// refuse every produce to the topic.
+    fn check_topic_config(&self, topic: &str, name: &str, value: &str) -> Result<(), ErrorCode> {
+        if name != RECORD_VALIDATORS_CONFIG {
+            return Ok(());
+        }
+        match validator_names(value)
+            .find(|validator| !self.record_validators.contains(&validator.to_string()))
+        {
+            Some(unknown) => {
+                tracing::warn!(
+                    "Rejected config of topic '{}': unknown record validator {}",
+                    topic,
+                    unknown
+                );
+                Err(ErrorCode::InvalidConfig)
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(self.raft_node.role, Role::Leader { .. })
+    }
+
+    /// Starts publishing to `broker_id`, first bringing it up to date with a snapshot.
+    pub async fn add_publisher(
+        &mut self,
+        broker_id: i32,
+        mut publisher: Box<dyn MetadataPublisher>,
+    ) -> Result<(), String> {
+        let snapshot = self.metadata.generate_snapshot_records();
+        if !snapshot.is_empty() {
+            publisher
+                .publish(self.metadata.last_applied_offset, &snapshot)
+                .await?;
+        }
+        self.publishers.insert(broker_id, publisher);
+        Ok(())
+    }
+
+    fn has_live_session(&self, broker_id: i32, now_ms: i64) -> bool {
+        self.metadata.is_broker_alive(broker_id)
+            && self
+                .broker_heartbeats
+                .get(&broker_id)
+                .is_some_and(|last| now_ms - last < self.broker_session_timeout_ms)
     }
 
+    /// Registers (or re-registers) a broker and hands it leadership of any offline partition
+    /// it was the last in-sync replica of, or merely a replica of when the topic allows unclean
+    /// leader election. A different incarnation may only take over the
+    /// broker id once the previous one's session has lapsed.
     pub async fn register_broker(
+        &mut self,
+        registration: RegisterBrokerRecord,
+    ) -> Result<i64, ErrorCode> {
+        if !self.is_active() {
+            return Err(ErrorCode::NotController);
+        }
+
+        let broker_id = registration.broker_id;
+        let now_ms = current_time_ms();
+        if let Some(existing) = self.metadata.brokers.get(&broker_id)
+            && self.has_live_session(broker_id, now_ms)
+        {
+            if existing.incarnation_id != registration.incarnation_id {
+                return Err(ErrorCode::DuplicateBrokerRegistration);
+            }
+            if *existing == registration {
+                self.broker_heartbeats.insert(broker_id, now_ms);
+                return Ok(self.metadata.last_applied_offset);
+            }
+        }
+
+        let mut records = vec![MetadataRecord::RegisterBroker(registration)];
+
+        for topic in self.metadata.topics.values() {
+            for partition in topic.partitions.values() {
+                if partition.leader != NO_LEADER {
+                    continue;
+                }
+                if partition.isr.contains(&broker_id) {
+                    records.push(MetadataRecord::PartitionChange(
+                        PartitionChangeRecord::leader_change(
+                            partition,
+                            partition.isr.clone(),
+                            broker_id,
+                            partition.leader_epoch + 1,
+                        ),
+                    ));
+                } else if self.unclean_leader_election_enabled(&topic.name)
+                    && let Some(change) =
+                        Self::elect_unclean_leader(partition, |id| id == broker_id)
+                {
+                    records.push(MetadataRecord::PartitionChange(change));
+                }
+            }
+        }
+
+        let offset = self.append_metadata_records(records).await.map_err(|e| {
+            tracing::error!("Failed to register broker {}: {}", broker_id, e);
+            ErrorCode::UnknownServerError
+        })?;
+        self.broker_heartbeats.insert(broker_id, now_ms);
+        tracing::info!("Registered broker {}", broker_id);
+        Ok(offset)
+    }
+
+    /// Extends a broker's session. A fenced broker must register again before heartbeating.
+    pub fn broker_heartbeat(
         &mut self,
         broker_id: i32,
-        host: String,
-        port: i32,
-    ) -> Result<i64, String> {
-        let record = MetadataRecord::RegisterBroker(RegisterBrokerRecord {
-            broker_id,
-            host,
-            port,
-        });
+        incarnation_id: Uuid,
+    ) -> Result<(), ErrorCode> {
+        if !self.is_active() {
+            return Err(ErrorCode::NotController);
+        }
 
-        self.append_metadata_record(record).await
+        let Some(registration) = self.metadata.brokers.get(&broker_id) else {
+            return Err(ErrorCode::BrokerIdNotRegistered);
+        };
+        if registration.incarnation_id != incarnation_id {
+            return Err(ErrorCode::StaleBrokerEpoch);
+        }
+        if !self.metadata.is_broker_alive(broker_id) {
+            return Err(ErrorCode::BrokerIdNotRegistered);
+        }
+
+        self.broker_heartbeats.insert(broker_id, current_time_ms());
+        Ok(())
+    }
+
+    /// Fences every live broker whose session has lapsed. Returns how many were fenced.
+    pub async fn fence_expired_brokers(&mut self) -> usize {
+        if !self.is_active() {
+            return 0;
+        }
+
+        let now_ms = current_time_ms();
+        let mut expired = Vec::new();
+        for broker_id in self.metadata.live_brokers() {
+            match self.broker_heartbeats.get(&broker_id) {
+                Some(last) if now_ms - last >= self.broker_session_timeout_ms => {
+                    expired.push(broker_id)
+                }
+                Some(_) => {}
+                None => {
+                    self.broker_heartbeats.insert(broker_id, now_ms);
+                }
+            }
+        }
+
+        for broker_id in &expired {
+            tracing::warn!(
+                "Fencing broker {} after {} ms without a heartbeat",
+                broker_id,
+                self.broker_session_timeout_ms
+            );
+            self.broker_heartbeats.remove(broker_id);
+            if let Err(e) = self.handle_broker_failure(*broker_id).await {
+                tracing::error!("Failed to fence broker {}: {}", broker_id, e);
+            }
+        }
+        expired.len()
+    }
+
+    pub fn start_broker_session_expiration(
+        controller: Arc<Mutex<QuorumController>>,
+        session_timeout_ms: i64,
+        cancel_token: CancellationToken,
+    ) -> JoinHandle<()> {
+        let check_interval = Duration::from_millis((session_timeout_ms / 2).max(1) as u64);
+        spawn_periodic(
+            "broker-session-expiration",
+            check_interval,
+            cancel_token,
+            move || {
+                let controller = controller.clone();
+                async move {
+                    controller.lock().await.fence_expired_brokers().await;
+                }
+            },
+        )
+    }
+
+    /// Fences a failed broker, removes it from every ISR and re-elects leaders for the
+    /// partitions it led. A partition whose whole ISR is gone keeps the failed broker as its
+    /// only ISR member and goes offline until that broker returns.
+    pub async fn handle_broker_failure(&mut self, broker_id: i32) -> Result<i64, String> {
+        if !self.metadata.brokers.contains_key(&broker_id) {
+            return Err(format!("Broker {} is not registered", broker_id));
+        }
+
+        let mut records = vec![MetadataRecord::FenceBroker(FenceBrokerRecord { broker_id })];
+        let is_alive = |id: i32| id != broker_id && self.metadata.is_broker_alive(id);
+
+        for topic in self.metadata.topics.values() {
+            for partition in topic.partitions.values() {
+                if partition.leader != broker_id && !partition.isr.contains(&broker_id) {
+                    continue;
+                }
+
+                let mut isr: Vec<i32> = partition
+                    .isr
+                    .iter()
+                    .copied()
+                    .filter(|id| *id != broker_id)
+                    .collect();
+                if isr.is_empty() {
+                    isr = partition.isr.clone();
+                }
+
+                let (leader, leader_epoch) = if partition.leader == broker_id {
+                    match Self::elect_leader(&partition.replicas, &isr, is_alive) {
+                        Some(leader) => (leader, partition.leader_epoch + 1),
+                        None => {
+                            if self.unclean_leader_election_enabled(&topic.name)
+                                && let Some(change) =
+                                    Self::elect_unclean_leader(partition, is_alive)
+                            {
+                                records.push(MetadataRecord::PartitionChange(change));
+                                continue;
+                            }
+                            (NO_LEADER, partition.leader_epoch + 1)
+                        }
+                    }
+                } else {
+                    (partition.leader, partition.leader_epoch)
+                };
+
+                records.push(MetadataRecord::PartitionChange(
+                    PartitionChangeRecord::leader_change(partition, isr, leader, leader_epoch),
+                ));
+            }
+        }
+
+        self.append_metadata_records(records).await
     }
 
+    /// Creates a topic, spreading replicas round-robin over the live brokers from a random
+    /// starting broker so leadership is balanced across topics.
     pub async fn create_topic(
         &mut self,
         topic_name: String,
-        partitions: Vec<PartitionRecord>,
-    ) -> Result<i64, String> {
-        let record = MetadataRecord::Topic(TopicRecord {
-            topic_name,
+        num_partitions: i32,
+        replication_factor: i16,
+    ) -> Result<i64, ErrorCode> {
+        self.create_topic_with_configs(topic_name, num_partitions, replication_factor, vec![])
+            .await
+    }
+
+    /// Like `create_topic`, with config overrides written in the same batch as the topic.
+    pub async fn create_topic_with_configs(
+        &mut self,
+        topic_name: String,
+        num_partitions: i32,
+        replication_factor: i16,
+        configs: Vec<(String, String)>,
+    ) -> Result<i64, ErrorCode> {
+        if !self.is_active() {
+            return Err(ErrorCode::NotController);
+        }
+        if self.metadata.topics.contains_key(&topic_name) {
+            return Err(ErrorCode::TopicAlreadyExists);
+        }
+        if num_partitions <= 0 {
+            return Err(ErrorCode::InvalidPartitions);
+        }
+        for (name, value) in &configs {
+            self.check_topic_config(&topic_name, name, value)?;
+        }
+
+        let live_brokers = self.metadata.live_brokers();
+        if replication_factor <= 0 || replication_factor as usize > live_brokers.len() {
+            return Err(ErrorCode::InvalidReplicationFactor);
+        }
+
+        let start_index = rand::rng().random_range(0..live_brokers.len());
+        let partitions = (0..num_partitions)
+            .map(|partition_index| {
+                let replicas: Vec<i32> = (0..replication_factor as usize)
+                    .map(|i| {
+                        live_brokers
+                            [(start_index + partition_index as usize + i) % live_brokers.len()]
+                    })
+                    .collect();
+                PartitionRecord {
+                    topic_name: topic_name.clone(),
+                    partition_index,
+                    leader: replicas[0],
+                    isr: replicas.clone(),
+                    replicas,
+                    leader_epoch: 0,
+                    adding_replicas: Vec::new(),
+                    removing_replicas: Vec::new(),
+                }
+            })
+            .collect();
+
+        let mut records = vec![MetadataRecord::Topic(TopicRecord {
+            topic_name: topic_name.clone(),
             partitions,
-        });
+        })];
+        records.extend(configs.into_iter().map(|(name, value)| {
+            MetadataRecord::Config(ConfigRecord {
+                resource_type: CONFIG_RESOURCE_TOPIC,
+                resource_name: topic_name.clone(),
+                name,
+                value: Some(value),
+            })
+        }));
 
-        self.append_metadata_record(record).await
+        self.append_metadata_records(records).await.map_err(|e| {
+            tracing::error!("Failed to create topic {}: {}", topic_name, e);
+            ErrorCode::UnknownServerError
+        })
     }
 
-    async fn append_metadata_record(
+    /// Creates the compacted internal topics backing group offsets and transaction state if
+    /// they do not exist yet; run once the first brokers have registered.
+    pub async fn bootstrap_internal_topics(
         &mut self,
-        metadata_record: MetadataRecord,
-    ) -> Result<i64, String> {
-        let mut value_buf = BytesMut::new();
-        metadata_record.encode(&mut value_buf);
-
-        let data_record = Record {
-            length: Varint(0),
-            attributes: 0,
-            timestamp_delta: Varlong(0),
-            offset_delta: Varint(0),
-            key: None,
-            value: Some(value_buf.to_vec()),
-            headers: vec![],
+        offsets_replication_factor: i16,
+        transaction_state_replication_factor: i16,
+    ) -> Result<(), ErrorCode> {
+        let internal_topics = [
+            (
+                CONSUMER_OFFSETS_TOPIC,
+                DEFAULT_OFFSETS_TOPIC_PARTITIONS,
+                offsets_replication_factor,
+            ),
+            (
+                TRANSACTION_STATE_TOPIC,
+                DEFAULT_TRANSACTION_STATE_PARTITIONS,
+                transaction_state_replication_factor,
+            ),
+        ];
+
+        for (topic_name, num_partitions, replication_factor) in internal_topics {
+            if self.metadata.topics.contains_key(&topic_name.to_string()) {
+                continue;
+            }
+            self.create_topic_with_configs(
+                topic_name.to_string(),
+                num_partitions,
+                replication_factor,
+                vec![(
+                    CLEANUP_POLICY_CONFIG.to_string(),
+                    CLEANUP_POLICY_COMPACT.to_string(),
+                )],
+            )
+            .await?;
+            tracing::info!(
+                "Created internal topic {} with {} partitions",
+                topic_name,
+                num_partitions
+            );
+        }
+        Ok(())
+    }
+
+    /// Sets or removes a topic config override. Enabling unclean leader election immediately
+    /// brings back offline partitions that have a live replica.
+    pub async fn set_topic_config(
+        &mut self,
+        topic_name: String,
+        name: String,
+        value: Option<String>,
+    ) -> Result<i64, ErrorCode> {
+        if !self.is_active() {
+            return Err(ErrorCode::NotController);
+        }
+        let Some(topic) = self.metadata.topics.get(&topic_name) else {
+            return Err(ErrorCode::UnknownTopicOrPartition);
+        };
+
+        let mut records = Vec::new();
+        if name == UNCLEAN_LEADER_ELECTION_ENABLE_CONFIG {
+            let enable = match value.as_deref() {
+                None => DEFAULT_UNCLEAN_LEADER_ELECTION_ENABLE,
+                Some(value) => value
+                    .parse::<bool>()
+                    .map_err(|_| ErrorCode::InvalidConfig)?,
+            };
+            if enable {
+                let is_alive = |id: i32| self.metadata.is_broker_alive(id);
+                records.extend(
+                    topic
+                        .partitions
+                        .values()
+                        .filter(|partition| partition.leader == NO_LEADER)
+                        .filter_map(|partition| Self::elect_unclean_leader(partition, is_alive))
+                        .map(MetadataRecord::PartitionChange),
+                );
+            }
+        }
+        records.insert(
+            0,
+            MetadataRecord::Config(ConfigRecord {
+                resource_type: CONFIG_RESOURCE_TOPIC,
+                resource_name: topic_name.clone(),
+                name,
+                value,
+            }),
+        );
+
+        self.append_metadata_records(records).await.map_err(|e| {
+            tracing::error!("Failed to update config of topic {}: {}", topic_name, e);
+            ErrorCode::UnknownServerError
+        })
+    }
+
+    /// Replaces the config overrides of a topic or broker (an empty broker name means the
+    /// cluster-wide default): listed configs are set, `None` values and unlisted overrides are
+    /// removed. Broker configs must be dynamic and valid.
+    pub async fn alter_configs(
+        &mut self,
+        resource_type: i8,
+        resource_name: String,
+        configs: Vec<(String, Option<String>)>,
+        validate_only: bool,
+    ) -> Result<(), ErrorCode> {
+        if !self.is_active() {
+            return Err(ErrorCode::NotController);
+        }
+
+        let mut changes: Vec<(String, Option<String>)> = self
+            .metadata
+            .configs
+            .get(&(resource_type, resource_name.clone()))
+            .map(|existing| {
+                existing
+                    .keys()
+                    .filter(|name| !configs.iter().any(|(listed, _)| listed == *name))
+                    .map(|name| (name.clone(), None))
+                    .collect()
+            })
+            .unwrap_or_default();
+        changes.extend(configs);
+
+        match resource_type {
+            CONFIG_RESOURCE_TOPIC => {
+                if !self.metadata.topics.contains_key(&resource_name) {
+                    return Err(ErrorCode::UnknownTopicOrPartition);
+                }
+                for (name, value) in &changes {
+                    if let Some(value) = value {
+                        self.check_topic_config(&resource_name, name, value)?;
+                    }
+                }
+                if validate_only {
+                    return Ok(());
+                }
+                for (name, value) in changes {
+                    self.set_topic_config(resource_name.clone(), name, value)
+                        .await?;
+                }
+                Ok(())
+            }
+            CONFIG_RESOURCE_BROKER => {
+                if !resource_name.is_empty() && resource_name.parse::<i32>().is_err() {
+                    return Err(ErrorCode::InvalidRequest);
+                }
+                let mut overrides = FlatMap::new();
+                for (name, value) in &changes {
+                    if let Some(value) = value {
+                        overrides.insert(name.clone(), value.clone());
+                    }
+                }
+                if let Err(e) = BrokerConfig::default().with_overrides(&overrides) {
+                    tracing::warn!("Rejected config of broker '{}': {}", resource_name, e);
+                    return Err(ErrorCode::InvalidConfig);
+                }
+                if validate_only {
+                    return Ok(());
+                }
+
+                let records = changes
+                    .into_iter()
+                    .map(|(name, value)| {
+                        MetadataRecord::Config(ConfigRecord {
+                            resource_type,
+                            resource_name: resource_name.clone(),
+                            name,
+                            value,
+                        })
+                    })
+                    .collect();
+                self.append_metadata_records(records)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| {
+                        tracing::error!(
+                            "Failed to update config of broker '{}': {}",
+                            resource_name,
+                            e
+                        );
+                        ErrorCode::UnknownServerError
+                    })
+            }
+            _ => Err(ErrorCode::InvalidRequest),
+        }
+    }
+
+    /// Starts moving a partition onto the `target` replicas, or with `None` cancels the
+    /// reassignment in progress. Until every new replica has joined the ISR the partition keeps
+    /// its original replicas as well; `alter_partition` then completes the move.
+    pub async fn alter_partition_reassignment(
+        &mut self,
+        topic_name: &str,
+        partition_index: i32,
+        target: Option<Vec<i32>>,
+    ) -> Result<(), ErrorCode> {
+        if !self.is_active() {
+            return Err(ErrorCode::NotController);
+        }
+        let Some(partition) = self.metadata.partition(topic_name, partition_index) else {
+            return Err(ErrorCode::UnknownTopicOrPartition);
+        };
+
+        // The replicas before any reassignment in progress
+        let original: Vec<i32> = partition
+            .replicas
+            .iter()
+            .copied()
+            .filter(|id| !partition.adding_replicas.contains(id))
+            .collect();
+        let target = match target {
+            Some(target) => {
+                let mut distinct = target.clone();
+                distinct.sort_unstable();
+                distinct.dedup();
+                if target.is_empty()
+                    || distinct.len() != target.len()
+                    || !target
+                        .iter()
+                        .all(|id| self.metadata.brokers.contains_key(id))
+                {
+                    return Err(ErrorCode::InvalidReplicaAssignment);
+                }
+                target
+            }
+            None if partition.is_reassigning() => original.clone(),
+            None => return Err(ErrorCode::NoReassignmentInProgress),
+        };
+        if target == original && !partition.is_reassigning() {
+            return Ok(());
+        }
+
+        let mut replicas = target.clone();
+        replicas.extend(original.iter().filter(|id| !target.contains(id)));
+        let mut change = PartitionChangeRecord {
+            replicas: Some(replicas),
+            adding_replicas: Some(
+                target
+                    .iter()
+                    .copied()
+                    .filter(|id| !original.contains(id))
+                    .collect(),
+            ),
+            removing_replicas: Some(
+                original
+                    .iter()
+                    .copied()
+                    .filter(|id| !target.contains(id))
+                    .collect(),
+            ),
+            ..PartitionChangeRecord::leader_change(
+                partition,
+                partition.isr.clone(),
+                partition.leader,
+                partition.leader_epoch,
+            )
+        };
+        let completed = self.maybe_complete_reassignment(&mut change);
+        if !completed && change.adding_replicas.as_ref().is_some_and(Vec::is_empty) {
+            // Only replicas out of the ISR would be left to lead
+            return Err(ErrorCode::InvalidReplicaAssignment);
+        }
+
+        self.append_metadata_records(vec![MetadataRecord::PartitionChange(change)])
+            .await
+            .map_err(|e| {
+                tracing::error!(
+                    "Failed to reassign {}-{}: {}",
+                    topic_name,
+                    partition_index,
+                    e
+                );
+                ErrorCode::UnknownServerError
+            })?;
+        tracing::info!(
+            "Reassigning {}-{} to {:?}{}",
+            topic_name,
+            partition_index,
+            target,
+            if completed { " (completed)" } else { "" }
+        );
+        Ok(())
+    }
+
+    /// Replaces the ISR of a partition with the one its leader `broker_id` arrived at during
+    /// `leader_epoch`, completing a reassignment that was waiting for replicas to catch up.
+    pub async fn alter_partition(
+        &mut self,
+        broker_id: i32,
+        topic_name: &str,
+        partition_index: i32,
+        leader_epoch: i32,
+        isr: Vec<i32>,
+    ) -> Result<(), ErrorCode> {
+        if !self.is_active() {
+            return Err(ErrorCode::NotController);
+        }
+        let Some(partition) = self.metadata.partition(topic_name, partition_index) else {
+            return Err(ErrorCode::UnknownTopicOrPartition);
+        };
+        if partition.leader != broker_id {
+            return Err(ErrorCode::NotLeaderOrFollower);
+        }
+        if partition.leader_epoch != leader_epoch {
+            return Err(ErrorCode::FencedLeaderEpoch);
+        }
+        if !isr.contains(&broker_id) || isr.iter().any(|id| !partition.replicas.contains(id)) {
+            return Err(ErrorCode::InvalidRequest);
+        }
+
+        let mut change = PartitionChangeRecord::leader_change(
+            partition,
+            isr,
+            partition.leader,
+            partition.leader_epoch,
+        );
+        let completed = self.maybe_complete_reassignment(&mut change);
+        if !completed && change.isr == partition.isr {
+            return Ok(());
+        }
+
+        self.append_metadata_records(vec![MetadataRecord::PartitionChange(change)])
+            .await
+            .map_err(|e| {
+                tracing::error!(
+                    "Failed to update ISR of {}-{}: {}",
+                    topic_name,
+                    partition_index,
+                    e
+                );
+                ErrorCode::UnknownServerError
+            })?;
+        if completed {
+            tracing::info!(
+                "Completed reassignment of {}-{}",
+                topic_name,
+                partition_index
+            );
+        }
+        Ok(())
+    }
+
+    /// Runs a leader election requested by an administrator. A preferred election hands
+    /// leadership back to the first replica once it is in sync; an unclean one elects a leader
+    /// for a partition that has none, falling back to an out-of-sync replica whatever the
+    /// topic's unclean election config says.
+    pub async fn elect_partition_leader(
+        &mut self,
+        topic_name: &str,
+        partition_index: i32,
+        unclean: bool,
+    ) -> Result<(), ErrorCode> {
+        if !self.is_active() {
+            return Err(ErrorCode::NotController);
+        }
+        let Some(partition) = self.metadata.partition(topic_name, partition_index) else {
+            return Err(ErrorCode::UnknownTopicOrPartition);
         };
+        let is_alive = |id: i32| self.metadata.is_broker_alive(id);
 
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| e.to_string())?
-            .as_millis() as i64;
-
-        let batch = RecordBatch {
-            base_offset: 0,
-            batch_length: 0,
-            partition_leader_epoch: 0,
-            magic: 2,
-            crc: 0,
-            attributes: 0,
-            last_offset_delta: 0,
-            base_timestamp: now,
-            max_timestamp: now,
-            producer_id: -1,
-            producer_epoch: -1,
-            base_sequence: -1,
-            records_count: 1,
-            records: vec![data_record],
+        let change = if unclean {
+            if partition.leader != NO_LEADER {
+                return Err(ErrorCode::ElectionNotNeeded);
+            }
+            match Self::elect_leader(&partition.replicas, &partition.isr, is_alive) {
+                Some(leader) => PartitionChangeRecord::leader_change(
+                    partition,
+                    partition.isr.clone(),
+                    leader,
+                    partition.leader_epoch + 1,
+                ),
+                None => Self::elect_unclean_leader(partition, is_alive)
+                    .ok_or(ErrorCode::EligibleLeadersNotAvailable)?,
+            }
+        } else {
+            let preferred = partition.replicas[0];
+            if partition.leader == preferred {
+                return Err(ErrorCode::ElectionNotNeeded);
+            }
+            if !partition.isr.contains(&preferred) || !is_alive(preferred) {
+                return Err(ErrorCode::PreferredLeaderNotAvailable);
+            }
+            PartitionChangeRecord::leader_change(
+                partition,
+                partition.isr.clone(),
+                preferred,
+                partition.leader_epoch + 1,
+            )
         };
 
-        self.raft_node.client_append_local(batch).await
+        let leader = change.leader;
+        self.append_metadata_records(vec![MetadataRecord::PartitionChange(change)])
+            .await
+            .map_err(|e| {
+                tracing::error!(
+                    "Failed to elect a leader for {}-{}: {}",
+                    topic_name,
+                    partition_index,
+                    e
+                );
+                ErrorCode::UnknownServerError
+            })?;
+        tracing::info!(
+            "Elected broker {} leader of {}-{}",
+            leader,
+            topic_name,
+            partition_index
+        );
+        Ok(())
+    }
+
+    /// Finishes the reassignment `change` leaves the partition in once every adding replica is
+    /// in its ISR: drops the removing replicas, and moves leadership off them. Returns whether
+    /// it did.
+    fn maybe_complete_reassignment(&self, change: &mut PartitionChangeRecord) -> bool {
+        let Some(partition) = self
+            .metadata
+            .partition(&change.topic_name, change.partition_index)
+        else {
+            return false;
+        };
+        let replicas = change.replicas.as_ref().unwrap_or(&partition.replicas);
+        let adding_replicas = change
+            .adding_replicas
+            .as_ref()
+            .unwrap_or(&partition.adding_replicas);
+        let removing_replicas = change
+            .removing_replicas
+            .as_ref()
+            .unwrap_or(&partition.removing_replicas);
+        if adding_replicas.iter().any(|id| !change.isr.contains(id)) {
+            return false;
+        }
+
+        let target: Vec<i32> = replicas
+            .iter()
+            .copied()
+            .filter(|id| !removing_replicas.contains(id))
+            .collect();
+        let isr: Vec<i32> = change
+            .isr
+            .iter()
+            .copied()
+            .filter(|id| target.contains(id))
+            .collect();
+        if isr.is_empty() {
+            return false;
+        }
+        let (leader, leader_epoch) = if target.contains(&change.leader) {
+            (change.leader, change.leader_epoch)
+        } else {
+            let is_alive = |id: i32| self.metadata.is_broker_alive(id);
+            match Self::elect_leader(&target, &isr, is_alive) {
+                Some(leader) => (leader, change.leader_epoch + 1),
+                None => return false,
+            }
+        };
+
+        change.replicas = Some(target);
+        change.adding_replicas = Some(Vec::new());
+        change.removing_replicas = Some(Vec::new());
+        change.isr = isr;
+        change.leader = leader;
+        change.leader_epoch = leader_epoch;
+        true
+    }
+
+    fn unclean_leader_election_enabled(&self, topic_name: &str) -> bool {
+        self.metadata
+            .topic_config(topic_name, UNCLEAN_LEADER_ELECTION_ENABLE_CONFIG)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_UNCLEAN_LEADER_ELECTION_ENABLE)
+    }
+
+    /// Makes the first live replica leader of an offline partition even though it is out of
+    /// sync, shrinking the ISR to just that replica. Records it never received are lost.
+    fn elect_unclean_leader(
+        partition: &PartitionRecord,
+        is_alive: impl Fn(i32) -> bool,
+    ) -> Option<PartitionChangeRecord> {
+        let leader = partition
+            .replicas
+            .iter()
+            .copied()
+            .find(|id| is_alive(*id))?;
+        tracing::warn!(
+            "Electing out-of-sync replica {} as leader of {}-{}; records it lacks are lost",
+            leader,
+            partition.topic_name,
+            partition.partition_index
+        );
+        Some(PartitionChangeRecord::leader_change(
+            partition,
+            vec![leader],
+            leader,
+            partition.leader_epoch + 1,
+        ))
+    }
+
+    /// The first replica in preference order that is in the ISR and alive.
+    fn elect_leader(replicas: &[i32], isr: &[i32], is_alive: impl Fn(i32) -> bool) -> Option<i32> {
+        replicas
+            .iter()
+            .copied()
+            .find(|id| isr.contains(id) && is_alive(*id))
+    }
+
+    /// Writes `records` as one batch, applies them to the controller's view and publishes them.
+    async fn append_metadata_records(
+        &mut self,
+        metadata_records: Vec<MetadataRecord>,
+    ) -> Result<i64, String> {
+        let data_records = metadata_records
+            .iter()
+            .enumerate()
+            .map(|(offset_delta, metadata_record)| {
+                let mut value_buf = BytesMut::new();
+                metadata_record.encode(&mut value_buf);
+                Record::new(offset_delta as i32, None, Some(value_buf.to_vec()))
+            })
+            .collect();
+        let batch = RecordBatch::new(current_time_ms(), data_records);
+
+        let offset = self.raft_node.client_append_local(batch).await?;
+        self.metadata.replay_records(offset, &metadata_records);
+
+        for (broker_id, publisher) in self.publishers.iter_mut() {
+            if let Err(e) = publisher.publish(offset, &metadata_records).await {
+                tracing::warn!("Failed to publish metadata to broker {}: {}", broker_id, e);
+            }
+        }
+
+        Ok(offset)
+    }
+}
+
+#[async_trait]
+impl ControllerChannel for Arc<Mutex<QuorumController>> {
+    async fn register_broker(
+        &mut self,
+        registration: RegisterBrokerRecord,
+    ) -> Result<ErrorCode, String> {
+        match self.lock().await.register_broker(registration).await {
+            Ok(_) => Ok(ErrorCode::None),
+            Err(error_code) => Ok(error_code),
+        }
+    }
+
+    async fn broker_heartbeat(
+        &mut self,
+        broker_id: i32,
+        incarnation_id: Uuid,
+    ) -> Result<ErrorCode, String> {
+        match self
+            .lock()
+            .await
+            .broker_heartbeat(broker_id, incarnation_id)
+        {
+            Ok(()) => Ok(ErrorCode::None),
+            Err(error_code) => Ok(error_code),
+        }
+    }
+
+    async fn create_topic(
+        &mut self,
+        topic_name: String,
+        num_partitions: i32,
+        replication_factor: i16,
+    ) -> Result<ErrorCode, String> {
+        match self
+            .lock()
+            .await
+            .create_topic(topic_name, num_partitions, replication_factor)
+            .await
+        {
+            Ok(_) => Ok(ErrorCode::None),
+            Err(error_code) => Ok(error_code),
+        }
+    }
+
+    async fn alter_configs(
+        &mut self,
+        resource_type: i8,
+        resource_name: String,
+        configs: Vec<(String, Option<String>)>,
+        validate_only: bool,
+    ) -> Result<ErrorCode, String> {
+        match self
+            .lock()
+            .await
+            .alter_configs(resource_type, resource_name, configs, validate_only)
+            .await
+        {
+            Ok(()) => Ok(ErrorCode::None),
+            Err(error_code) => Ok(error_code),
+        }
+    }
+
+    async fn alter_partition_reassignment(
+        &mut self,
+        topic_name: String,
+        partition_index: i32,
+        target: Option<Vec<i32>>,
+    ) -> Result<ErrorCode, String> {
+        match self
+            .lock()
+            .await
+            .alter_partition_reassignment(&topic_name, partition_index, target)
+            .await
+        {
+            Ok(()) => Ok(ErrorCode::None),
+            Err(error_code) => Ok(error_code),
+        }
+    }
+
+    async fn alter_partition(
+        &mut self,
+        broker_id: i32,
+        topic_name: String,
+        partition_index: i32,
+        leader_epoch: i32,
+        isr: Vec<i32>,
+    ) -> Result<ErrorCode, String> {
+        match self
+            .lock()
+            .await
+            .alter_partition(broker_id, &topic_name, partition_index, leader_epoch, isr)
+            .await
+        {
+            Ok(()) => Ok(ErrorCode::None),
+            Err(error_code) => Ok(error_code),
+        }
+    }
+
+    async fn elect_partition_leader(
+        &mut self,
+        topic_name: String,
+        partition_index: i32,
+        unclean: bool,
+    ) -> Result<ErrorCode, String> {
+        match self
+            .lock()
+            .await
+            .elect_partition_leader(&topic_name, partition_index, unclean)
+            .await
+        {
+            Ok(()) => Ok(ErrorCode::None),
+            Err(error_code) => Ok(error_code),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::driven::storage::log::PartitionLog;
+    use crate::application::fetch_handler::FetchHandler;
+    use crate::application::metadata_listener::BrokerMetadataListener;
+    use crate::application::replica_manager::ReplicaManager;
+    use crate::application::request_context::RequestContext;
+    use crate::config::SecurityProtocol;
+    use crate::core::domain::principal::KafkaPrincipal;
+    use crate::core::domain::topic_partition::TopicPartition;
+    use crate::core::ports::driven::FetchClient;
+    use crate::protocol::fetch::{FetchRequest, FetchResponse};
+    use async_trait::async_trait;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    struct InProcessFetchClient(FetchHandler);
+
+    #[async_trait]
+    impl FetchClient for InProcessFetchClient {
+        async fn fetch(&mut self, request: &FetchRequest) -> Result<FetchResponse, String> {
+            let context = RequestContext {
+                principal: KafkaPrincipal::anonymous(),
+                client_host: "127.0.0.1".to_string(),
+                client_id: String::new(),
+                listener: SecurityProtocol::Plaintext,
+            };
+            Ok(self.0.handle(&context, request.clone()).await)
+        }
+    }
+
+    async fn active_controller(dir: &std::path::Path, session_timeout_ms: i64) -> QuorumController {
+        let log = PartitionLog::new(dir.join("metadata"), 1024 * 1024, 0, u64::MAX)
+            .await
+            .unwrap();
+        let mut node = Node::new(0, vec![], log);
+        node.role = Role::Leader {
+            next_index: FlatMap::new(),
+            match_index: FlatMap::new(),
+        };
+        QuorumController::new(node, session_timeout_ms)
+    }
+
+    fn registration(broker_id: i32) -> RegisterBrokerRecord {
+        RegisterBrokerRecord {
+            broker_id,
+            incarnation_id: Uuid::new_v4(),
+            host: "localhost".into(),
+            port: 9091 + broker_id,
+            rack: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_leader_reelected_when_broker_fails() {
+        let dir = std::env::temp_dir().join(format!("forge-controller-{}", uuid::Uuid::new_v4()));
+        let mut controller = active_controller(&dir, 60_000).await;
+
+        let mut replica_managers = FlatMap::new();
+        for broker_id in 1..=3 {
+            let replica_manager =
+                ReplicaManager::new(broker_id, dir.join(broker_id.to_string()), 1);
+            replica_managers.insert(broker_id, Arc::new(Mutex::new(replica_manager)));
+        }
+        let mut listeners = Vec::new();
+        for broker_id in 1..=3 {
+            let leaders = replica_managers.clone();
+            let listener = Arc::new(Mutex::new(BrokerMetadataListener::new(
+                broker_id,
+                replica_managers.get(&broker_id).unwrap().clone(),
+                Box::new(move |broker: &RegisterBrokerRecord| {
+                    let leader = leaders.get(&broker.broker_id).unwrap().clone();
+                    Box::new(InProcessFetchClient(FetchHandler::new(leader, None)))
+                }),
+            )));
+            controller
+                .add_publisher(broker_id, Box::new(listener.clone()))
+                .await
+                .unwrap();
+            controller
+                .register_broker(registration(broker_id))
+                .await
+                .unwrap();
+            listeners.push(listener);
+        }
+
+        assert_eq!(
+            controller.create_topic("events".into(), 1, 4).await,
+            Err(ErrorCode::InvalidReplicationFactor)
+        );
+        controller
+            .create_topic("events".into(), 1, 3)
+            .await
+            .unwrap();
+        assert_eq!(
+            controller.create_topic("events".into(), 1, 3).await,
+            Err(ErrorCode::TopicAlreadyExists)
+        );
+
+        let topic_partition = TopicPartition::new("events", 0);
+        let old_leader = controller.metadata.partition("events", 0).unwrap().leader;
+        for (broker_id, replica_manager) in replica_managers.iter() {
+            let replica_manager = replica_manager.lock().await;
+            let partition = replica_manager.get_partition(&topic_partition).unwrap();
+            assert_eq!(
+                partition.leader_id(),
+                Some(old_leader),
+                "broker {}",
+                broker_id
+            );
+        }
+
+        controller.handle_broker_failure(old_leader).await.unwrap();
+
+        let partition = controller.metadata.partition("events", 0).unwrap().clone();
+        assert_ne!(partition.leader, old_leader);
+        assert_eq!(partition.leader_epoch, 1);
+        assert!(!partition.isr.contains(&old_leader));
+
+        let new_leader = replica_managers
+            .get(&partition.leader)
+            .unwrap()
+            .lock()
+            .await;
+        let replica = new_leader.get_partition(&topic_partition).unwrap();
+        assert!(replica.is_leader());
+        assert_eq!(replica.leader_epoch, 1);
+        drop(new_leader);
+
+        for listener in listeners {
+            listener.lock().await.shutdown().await;
+        }
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_broker_fenced_when_session_lapses() {
+        let dir = std::env::temp_dir().join(format!("forge-controller-{}", uuid::Uuid::new_v4()));
+        let mut controller = active_controller(&dir, 50).await;
+
+        let first = registration(1);
+        controller.register_broker(first.clone()).await.unwrap();
+        assert_eq!(
+            controller.register_broker(registration(1)).await,
+            Err(ErrorCode::DuplicateBrokerRegistration)
+        );
+        assert_eq!(
+            controller.broker_heartbeat(1, Uuid::new_v4()),
+            Err(ErrorCode::StaleBrokerEpoch)
+        );
+        controller
+            .broker_heartbeat(1, first.incarnation_id)
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(controller.fence_expired_brokers().await, 1);
+        assert!(!controller.metadata.is_broker_alive(1));
+        assert_eq!(
+            controller.broker_heartbeat(1, first.incarnation_id),
+            Err(ErrorCode::BrokerIdNotRegistered)
+        );
+
+        let restarted = registration(1);
+        controller.register_broker(restarted.clone()).await.unwrap();
+        assert!(controller.metadata.is_broker_alive(1));
+        controller
+            .broker_heartbeat(1, restarted.incarnation_id)
+            .unwrap();
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_internal_topics_bootstrapped_once_and_compacted() {
+        let dir = std::env::temp_dir().join(format!("forge-controller-{}", uuid::Uuid::new_v4()));
+        let mut controller = active_controller(&dir, 60_000).await;
+        controller.register_broker(registration(1)).await.unwrap();
+
+        controller.bootstrap_internal_topics(1, 1).await.unwrap();
+        let offset = controller.metadata.last_applied_offset;
+        controller.bootstrap_internal_topics(1, 1).await.unwrap();
+        assert_eq!(controller.metadata.last_applied_offset, offset);
+
+        for topic in [CONSUMER_OFFSETS_TOPIC, TRANSACTION_STATE_TOPIC] {
+            let metadata = &controller.metadata;
+            let partitions = &metadata.topics.get(&topic.to_string()).unwrap().partitions;
+            assert_eq!(partitions.len(), 50);
+            assert_eq!(
+                metadata
+                    .topic_config(topic, CLEANUP_POLICY_CONFIG)
+                    .map(String::as_str),
+                Some(CLEANUP_POLICY_COMPACT)
+            );
+        }
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_alter_broker_configs_replaces_dynamic_overrides() {
+        let dir = std::env::temp_dir().join(format!("forge-controller-{}", uuid::Uuid::new_v4()));
+        let mut controller = active_controller(&dir, 60_000).await;
+        controller.register_broker(registration(1)).await.unwrap();
+
+        let retention = ("log.retention.ms".to_string(), Some("60000".to_string()));
+        let quota = (
+            "quota.producer.default".to_string(),
+            Some("1024".to_string()),
+        );
+        controller
+            .alter_configs(
+                CONFIG_RESOURCE_BROKER,
+                "1".into(),
+                vec![retention.clone(), quota],
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(controller.metadata.broker_configs(1).len(), 2);
+
+        // Static settings and bad values are refused
+        for config in [
+            ("node.id".to_string(), Some("2".to_string())),
+            ("log.retention.ms".to_string(), Some("soon".to_string())),
+        ] {
+            assert_eq!(
+                controller
+                    .alter_configs(CONFIG_RESOURCE_BROKER, "1".into(), vec![config], false)
+                    .await,
+                Err(ErrorCode::InvalidConfig)
+            );
+        }
+
+        // Overrides left out of the request are removed
+        controller
+            .alter_configs(CONFIG_RESOURCE_BROKER, "1".into(), vec![retention], false)
+            .await
+            .unwrap();
+        let configs = controller.metadata.broker_configs(1);
+        assert_eq!(configs.len(), 1);
+        assert_eq!(
+            configs
+                .get(&"log.retention.ms".to_string())
+                .map(String::as_str),
+            Some("60000")
+        );
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_topic_configs_naming_unknown_validators_refused() {
+        let dir = std::env::temp_dir().join(format!("forge-controller-{}", uuid::Uuid::new_v4()));
+        let mut controller = active_controller(&dir, 60_000).await;
+        controller.register_broker(registration(1)).await.unwrap();
+        let validators = |value: &str| (RECORD_VALIDATORS_CONFIG.to_string(), value.to_string());
+
+        assert_eq!(
+            controller
+                .create_topic_with_configs("events".into(), 1, 1, vec![validators("json, avro")])
+                .await,
+            Err(ErrorCode::InvalidConfig)
+        );
+        controller
+            .create_topic_with_configs("events".into(), 1, 1, vec![validators("json")])
+            .await
+            .unwrap();
+
+        for validate_only in [true, false] {
+            assert_eq!(
+                controller
+                    .alter_configs(
+                        CONFIG_RESOURCE_TOPIC,
+                        "events".into(),
+                        vec![(RECORD_VALIDATORS_CONFIG.to_string(), Some("avro".into()))],
+                        validate_only,
+                    )
+                    .await,
+                Err(ErrorCode::InvalidConfig)
+            );
+        }
+        controller.set_record_validators(vec!["avro".to_string()]);
+        controller
+            .alter_configs(
+                CONFIG_RESOURCE_TOPIC,
+                "events".into(),
+                vec![(RECORD_VALIDATORS_CONFIG.to_string(), Some("avro".into()))],
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            controller
+                .metadata
+                .topic_config("events", RECORD_VALIDATORS_CONFIG)
+                .map(String::as_str),
+            Some("avro")
+        );
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unclean_leader_election_revives_offline_partition() {
+        let dir = std::env::temp_dir().join(format!("forge-controller-{}", uuid::Uuid::new_v4()));
+        let mut controller = active_controller(&dir, 60_000).await;
+        for broker_id in 1..=2 {
+            controller
+                .register_broker(registration(broker_id))
+                .await
+                .unwrap();
+        }
+        controller
+            .create_topic("events".into(), 1, 2)
+            .await
+            .unwrap();
+
+        let leader = controller.metadata.partition("events", 0).unwrap().leader;
+        let follower = 3 - leader;
+        controller.handle_broker_failure(follower).await.unwrap();
+        controller.handle_broker_failure(leader).await.unwrap();
+        let partition = controller.metadata.partition("events", 0).unwrap();
+        assert_eq!(partition.leader, NO_LEADER);
+        assert_eq!(partition.isr, vec![leader]);
+
+        // The out-of-sync follower returning is not enough on its own
+        controller
+            .register_broker(registration(follower))
+            .await
+            .unwrap();
+        assert_eq!(
+            controller.metadata.partition("events", 0).unwrap().leader,
+            NO_LEADER
+        );
+
+        controller
+            .set_topic_config(
+                "events".into(),
+                UNCLEAN_LEADER_ELECTION_ENABLE_CONFIG.into(),
+                Some("true".into()),
+            )
+            .await
+            .unwrap();
+        let partition = controller.metadata.partition("events", 0).unwrap();
+        assert_eq!(partition.leader, follower);
+        assert_eq!(partition.isr, vec![follower]);
+        assert_eq!(partition.leader_epoch, 2);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reassignment_completes_once_new_replica_joins_isr() {
+        let dir = std::env::temp_dir().join(format!("forge-controller-{}", uuid::Uuid::new_v4()));
+        let mut controller = active_controller(&dir, 60_000).await;
+        for broker_id in 1..=3 {
+            controller
+                .register_broker(registration(broker_id))
+                .await
+                .unwrap();
+        }
+        controller
+            .create_topic("events".into(), 1, 2)
+            .await
+            .unwrap();
+
+        let partition = controller.metadata.partition("events", 0).unwrap().clone();
+        let (leader, follower) = (partition.leader, partition.replicas[1]);
+        let spare = 6 - leader - follower;
+        assert_eq!(
+            controller
+                .alter_partition_reassignment("events", 0, Some(vec![spare, 9]))
+                .await,
+            Err(ErrorCode::InvalidReplicaAssignment)
+        );
+        assert_eq!(
+            controller
+                .alter_partition_reassignment("events", 0, None)
+                .await,
+            Err(ErrorCode::NoReassignmentInProgress)
+        );
+
+        // Move off the leader onto the spare broker
+        controller
+            .alter_partition_reassignment("events", 0, Some(vec![follower, spare]))
+            .await
+            .unwrap();
+        let partition = controller.metadata.partition("events", 0).unwrap().clone();
+        assert_eq!(partition.replicas, vec![follower, spare, leader]);
+        assert_eq!(partition.adding_replicas, vec![spare]);
+        assert_eq!(partition.removing_replicas, vec![leader]);
+        assert_eq!(partition.leader, leader);
+
+        assert_eq!(
+            controller
+                .alter_partition(follower, "events", 0, 0, vec![follower, spare])
+                .await,
+            Err(ErrorCode::NotLeaderOrFollower)
+        );
+        controller
+            .alter_partition(leader, "events", 0, 0, vec![leader, follower, spare])
+            .await
+            .unwrap();
+        let partition = controller.metadata.partition("events", 0).unwrap();
+        assert_eq!(partition.replicas, vec![follower, spare]);
+        assert!(!partition.is_reassigning());
+        assert_eq!(partition.leader, follower);
+        assert_eq!(partition.leader_epoch, 1);
+        assert_eq!(partition.isr, vec![follower, spare]);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_admin_leader_elections() {
+        let dir = std::env::temp_dir().join(format!("forge-controller-{}", uuid::Uuid::new_v4()));
+        let mut controller = active_controller(&dir, 60_000).await;
+        for broker_id in 1..=2 {
+            controller
+                .register_broker(registration(broker_id))
+                .await
+                .unwrap();
+        }
+        controller
+            .create_topic("events".into(), 1, 2)
+            .await
+            .unwrap();
+        assert_eq!(
+            controller.elect_partition_leader("events", 0, false).await,
+            Err(ErrorCode::ElectionNotNeeded)
+        );
+        assert_eq!(
+            controller.elect_partition_leader("events", 1, false).await,
+            Err(ErrorCode::UnknownTopicOrPartition)
+        );
+
+        // Leadership moves off the preferred replica while it is down
+        let preferred = controller.metadata.partition("events", 0).unwrap().replicas[0];
+        let other = 3 - preferred;
+        controller.handle_broker_failure(preferred).await.unwrap();
+        assert_eq!(
+            controller.elect_partition_leader("events", 0, false).await,
+            Err(ErrorCode::PreferredLeaderNotAvailable)
+        );
+        controller
+            .register_broker(registration(preferred))
+            .await
+            .unwrap();
+        // Back, but out of the ISR until it catches up
+        assert_eq!(
+            controller.elect_partition_leader("events", 0, false).await,
+            Err(ErrorCode::PreferredLeaderNotAvailable)
+        );
+        controller
+            .alter_partition(other, "events", 0, 1, vec![other, preferred])
+            .await
+            .unwrap();
+        controller
+            .elect_partition_leader("events", 0, false)
+            .await
+            .unwrap();
+        let partition = controller.metadata.partition("events", 0).unwrap();
+        assert_eq!(partition.leader, preferred);
+        assert_eq!(partition.leader_epoch, 2);
+
+        // An unclean election revives the partition even though the topic does not allow it
+        controller.handle_broker_failure(other).await.unwrap();
+        controller.handle_broker_failure(preferred).await.unwrap();
+        controller
+            .register_broker(registration(other))
+            .await
+            .unwrap();
+        assert_eq!(
+            controller.metadata.partition("events", 0).unwrap().leader,
+            NO_LEADER
+        );
+        controller
+            .elect_partition_leader("events", 0, true)
+            .await
+            .unwrap();
+        let partition = controller.metadata.partition("events", 0).unwrap();
+        assert_eq!(partition.leader, other);
+        assert_eq!(partition.isr, vec![other]);
+        assert_eq!(
+            controller.elect_partition_leader("events", 0, true).await,
+            Err(ErrorCode::ElectionNotNeeded)
+        );
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
     }
 }
@@ -0,0 +1,277 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::application::auto_topic_creation::AutoTopicCreationManager;
+use crate::application::replica_manager::{ACKS_LEADER, ReplicaManager};
+use crate::core::domain::record::{Header, Record};
+use crate::core::domain::record_batch::{RecordBatch, Records};
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::shared::time::current_time_ms;
+
+pub const DLQ_TOPIC_HEADER: &str = "dlq.topic";
+pub const DLQ_PARTITION_HEADER: &str = "dlq.partition";
+pub const DLQ_ERROR_CODE_HEADER: &str = "dlq.error.code";
+pub const DLQ_ERROR_MESSAGE_HEADER: &str = "dlq.error.message";
+pub const DLQ_DROPPED_BYTES_HEADER: &str = "dlq.dropped.bytes";
+
+/// How long a route waits for a dead letter topic it just created to reach this broker.
+const TOPIC_CREATION_WAIT: Duration = Duration::from_secs(2);
+const TOPIC_CREATION_POLL: Duration = Duration::from_millis(50);
+
+/// What a producer sent that was refused.
+pub enum Rejected {
+    Batches(Vec<RecordBatch>),
+    /// Bytes that did not decode, e.g. for a CRC mismatch.
+    Corrupt(Records),
+}
+
+/// Keeps the batches refused on the produce path (dead.letter.enable) in
+/// `<topic><suffix>`, each record with headers naming where it was sent and why it was
+/// refused, so they can be inspected and replayed. Records keep their key, value, headers and
+/// timestamp; corrupt bytes become the value of a single record. A dead letter batch over
+/// message.max.bytes, such as one refused as too large, is replaced by a single record with
+/// just the headers and the size of what was dropped.
+///
+/// The dead letter records are appended to a partition of the dead letter topic this broker
+/// leads, picked by the source partition; when it leads none they are logged and dropped.
+/// The producer gets its error either way.
+pub struct DeadLetterQueue {
+    replica_manager: Arc<Mutex<ReplicaManager>>,
+    auto_topic_creation: Option<Arc<AutoTopicCreationManager>>,
+    suffix: String,
+    message_max_bytes: usize,
+}
+
+impl DeadLetterQueue {
+    pub fn new(
+        replica_manager: Arc<Mutex<ReplicaManager>>,
+        auto_topic_creation: Option<Arc<AutoTopicCreationManager>>,
+        suffix: String,
+        message_max_bytes: usize,
+    ) -> Self {
+        Self {
+            replica_manager,
+            auto_topic_creation,
+            suffix,
+            message_max_bytes,
+        }
+    }
+
+    /// The dead letter topic of `topic`, or `None` for one that already is.
+    pub fn topic(&self, topic: &str) -> Option<String> {
+        (!topic.ends_with(&self.suffix)).then(|| format!("{}{}", topic, self.suffix))
+    }
+
+    pub async fn route(
+        &self,
+        topic_partition: &TopicPartition,
+        error: ErrorCode,
+        reason: &str,
+        rejected: Rejected,
+    ) {
+        let Some(topic) = self.topic(&topic_partition.topic) else {
+            return;
+        };
+        let headers = [
+            (DLQ_TOPIC_HEADER, topic_partition.topic.clone()),
+            (DLQ_PARTITION_HEADER, topic_partition.partition.to_string()),
+            (DLQ_ERROR_CODE_HEADER, error.code().to_string()),
+            (DLQ_ERROR_MESSAGE_HEADER, reason.to_string()),
+        ];
+        let tag = |mut record: Record| {
+            record
+                .headers
+                .extend(headers.iter().map(|(key, value)| Header {
+                    key: (*key).into(),
+                    value: Some(value.as_bytes().to_vec()),
+                }));
+            record
+        };
+        let batches: Vec<RecordBatch> = match rejected {
+            Rejected::Batches(batches) => batches
+                .into_iter()
+                .filter(|batch| !batch.is_control_batch() && !batch.records.is_empty())
+                .map(|batch| {
+                    let records = batch.records.into_iter().map(tag).collect();
+                    let mut dead_letter = RecordBatch::new(batch.base_timestamp, records);
+                    dead_letter.max_timestamp = batch.max_timestamp;
+                    dead_letter
+                })
+                .collect(),
+            Rejected::Corrupt(records) => {
+                let record = tag(Record::new(0, None, Some(records.raw().concat())));
+                vec![RecordBatch::new(current_time_ms(), vec![record])]
+            }
+        };
+        if batches.is_empty() {
+            return;
+        }
+        let batches = batches
+            .into_iter()
+            .map(|batch| {
+                let size = Records::encode(std::slice::from_ref(&batch)).size_in_bytes();
+                if size <= self.message_max_bytes {
+                    return batch;
+                }
+                let mut record = tag(Record::new(0, None, None));
+                record.headers.push(Header {
+                    key: DLQ_DROPPED_BYTES_HEADER.into(),
+                    value: Some(size.to_string().into_bytes()),
+                });
+                RecordBatch::new(batch.base_timestamp, vec![record])
+            })
+            .collect::<Vec<_>>();
+
+        let Some(partition) = self.partition(&topic, topic_partition.partition).await else {
+            tracing::warn!(
+                "Dropped {} refused batches of {}: this broker leads no partition of {}",
+                batches.len(),
+                topic_partition,
+                topic
+            );
+            return;
+        };
+        let dead_letter_partition = TopicPartition::new(topic, partition);
+        for batch in batches {
+            if let Err(e) = ReplicaManager::append_records_concurrently(
+                &self.replica_manager,
+                &dead_letter_partition,
+                ACKS_LEADER,
+                batch,
+            )
+            .await
+            {
+                tracing::warn!(
+                    "Failed to append a refused batch of {} to {}: {}",
+                    topic_partition,
+                    dead_letter_partition,
+                    e
+                );
+            }
+        }
+    }
+
+    /// A partition of `topic` this broker leads, creating the topic first if it may.
+    async fn partition(&self, topic: &str, source_partition: i32) -> Option<i32> {
+        let exists = self.replica_manager.lock().await.has_topic(topic);
+        if !exists && let Some(auto_topic_creation) = &self.auto_topic_creation {
+            auto_topic_creation
+                .create_topics(vec![topic.to_string()])
+                .await;
+            let deadline = Instant::now() + TOPIC_CREATION_WAIT;
+            while !self.replica_manager.lock().await.has_topic(topic) && Instant::now() < deadline {
+                tokio::time::sleep(TOPIC_CREATION_POLL).await;
+            }
+        }
+
+        let led = self.replica_manager.lock().await.led_partitions(topic);
+        if led.is_empty() {
+            return None;
+        }
+        Some(led[source_partition.max(0) as usize % led.len()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_refused_batch_lands_in_dead_letter_topic_with_reason() {
+        let dir = std::env::temp_dir().join(format!("forge-dlq-{}", uuid::Uuid::new_v4()));
+        let dead_letter_partition = TopicPartition::new("events.dlq", 0);
+
+        let mut replica_manager = ReplicaManager::new(1, &dir, 1);
+        replica_manager
+            .create_partition(dead_letter_partition.clone(), vec![1])
+            .await
+            .unwrap();
+        replica_manager
+            .become_leader(&dead_letter_partition, 0, vec![1])
+            .unwrap();
+        let replica_manager = Arc::new(Mutex::new(replica_manager));
+        let dead_letter_queue =
+            DeadLetterQueue::new(replica_manager.clone(), None, ".dlq".to_string(), 1024);
+        assert_eq!(dead_letter_queue.topic("events.dlq"), None);
+
+        let batch = RecordBatch::new(
+            1000,
+            vec![Record::new(0, Some(b"k".to_vec()), Some(b"{".to_vec()))],
+        );
+        dead_letter_queue
+            .route(
+                &TopicPartition::new("events", 3),
+                ErrorCode::InvalidRecord,
+                "not JSON",
+                Rejected::Batches(vec![batch]),
+            )
+            .await;
+
+        let batches = replica_manager
+            .lock()
+            .await
+            .fetch_records(&dead_letter_partition, None, 0, 1024 * 1024, 0)
+            .await
+            .unwrap()
+            .batches()
+            .unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].base_timestamp, 1000);
+        let record = &batches[0].records[0];
+        assert_eq!(record.value.as_deref(), Some(&b"{"[..]));
+        let headers: Vec<(&str, &[u8])> = record
+            .headers
+            .iter()
+            .map(|header| (&*header.key, header.value.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            headers,
+            vec![
+                (DLQ_TOPIC_HEADER, &b"events"[..]),
+                (DLQ_PARTITION_HEADER, b"3"),
+                (DLQ_ERROR_CODE_HEADER, b"87"),
+                (DLQ_ERROR_MESSAGE_HEADER, b"not JSON"),
+            ]
+        );
+
+        // A batch over the limit leaves only its headers behind
+        let oversized = RecordBatch::new(
+            2000,
+            vec![Record::new(0, Some(b"k".to_vec()), Some(vec![b'x'; 4096]))],
+        );
+        let size = Records::encode(std::slice::from_ref(&oversized)).size_in_bytes();
+        dead_letter_queue
+            .route(
+                &TopicPartition::new("events", 3),
+                ErrorCode::MessageTooLarge,
+                "too large",
+                Rejected::Batches(vec![oversized]),
+            )
+            .await;
+
+        let records = replica_manager
+            .lock()
+            .await
+            .fetch_records(&dead_letter_partition, None, 1, 1024 * 1024, 0)
+            .await
+            .unwrap();
+        assert!(records.records.size_in_bytes() <= 1024);
+        let batches = records.batches().unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].base_timestamp, 2000);
+        let record = &batches[0].records[0];
+        assert_eq!((&record.key, &record.value), (&None, &None));
+        let dropped = record.headers.last().unwrap();
+        assert_eq!(&*dropped.key, DLQ_DROPPED_BYTES_HEADER);
+        let dropped: usize = std::str::from_utf8(dropped.value.as_deref().unwrap())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(dropped > size);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -0,0 +1,73 @@
+use tokio::sync::oneshot;
+
+use crate::application::purgatory::DelayedOperation;
+
+/// Bytes that just became readable on the partition being checked.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NewBytes {
+    /// Appended to the log; visible to followers.
+    pub appended: usize,
+    /// Moved below the high watermark; visible to consumers.
+    pub committed: usize,
+}
+
+/// A fetch parked until `min_bytes` of new data is readable on one of its partitions or
+/// `max_wait_ms` passes. Completion only wakes the waiting handler, which then re-reads.
+pub struct DelayedFetch {
+    from_follower: bool,
+    min_bytes: usize,
+    accumulated_bytes: usize,
+    completion: Option<oneshot::Sender<()>>,
+}
+
+impl DelayedFetch {
+    pub fn new(
+        from_follower: bool,
+        min_bytes: usize,
+        accumulated_bytes: usize,
+    ) -> (Self, oneshot::Receiver<()>) {
+        let (tx, rx) = oneshot::channel();
+        let delayed_fetch = Self {
+            from_follower,
+            min_bytes,
+            accumulated_bytes,
+            completion: Some(tx),
+        };
+        (delayed_fetch, rx)
+    }
+
+    fn complete(&mut self) {
+        if let Some(tx) = self.completion.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+impl DelayedOperation for DelayedFetch {
+    type Context = NewBytes;
+
+    fn try_complete(&mut self, new_bytes: &mut NewBytes) -> bool {
+        let Some(tx) = &self.completion else {
+            return true;
+        };
+        // The handler stopped waiting on its own timer
+        if tx.is_closed() {
+            return true;
+        }
+
+        self.accumulated_bytes += if self.from_follower {
+            new_bytes.appended
+        } else {
+            new_bytes.committed
+        };
+        if self.accumulated_bytes < self.min_bytes {
+            return false;
+        }
+        self.complete();
+        true
+    }
+
+    fn on_expiration(&mut self, _: &mut NewBytes) {
+        self.complete();
+    }
+}
@@ -0,0 +1,60 @@
+use tokio::sync::oneshot;
+
+use crate::application::partition::Partition;
+use crate::application::purgatory::DelayedOperation;
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::shared::collections::FlatMap;
+
+/// An acks=all produce parked until the high watermark of every partition it wrote to
+/// covers the appended batches. Completion only wakes the waiting handler.
+pub struct DelayedProduce {
+    /// Partitions and the high watermark each one must reach.
+    required_offsets: Vec<(TopicPartition, i64)>,
+    completion: Option<oneshot::Sender<()>>,
+}
+
+impl DelayedProduce {
+    pub fn new(required_offsets: Vec<(TopicPartition, i64)>) -> (Self, oneshot::Receiver<()>) {
+        let (tx, rx) = oneshot::channel();
+        let delayed_produce = Self {
+            required_offsets,
+            completion: Some(tx),
+        };
+        (delayed_produce, rx)
+    }
+
+    fn complete(&mut self) {
+        if let Some(tx) = self.completion.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+impl DelayedOperation for DelayedProduce {
+    type Context = FlatMap<TopicPartition, Partition>;
+
+    fn try_complete(&mut self, partitions: &mut Self::Context) -> bool {
+        let Some(tx) = &self.completion else {
+            return true;
+        };
+        if tx.is_closed() {
+            return true;
+        }
+
+        // A partition that moved away or went offline completes the operation with an error
+        let satisfied = self.required_offsets.iter().all(|(tp, required_offset)| {
+            partitions
+                .get(tp)
+                .is_none_or(|p| !p.is_leader() || p.high_watermark() >= *required_offset)
+        });
+        if !satisfied {
+            return false;
+        }
+        self.complete();
+        true
+    }
+
+    fn on_expiration(&mut self, _: &mut Self::Context) {
+        self.complete();
+    }
+}
@@ -0,0 +1,346 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::application::metadata_listener::BrokerMetadataListener;
+use crate::application::request_context::RequestContext;
+use crate::config::BrokerConfig;
+use crate::core::domain::acl::{AclOperation, Resource, ResourceType};
+use crate::core::domain::metadata_records::{
+    CONFIG_RESOURCE_BROKER, CONFIG_RESOURCE_BROKER_LOGGER, CONFIG_RESOURCE_TOPIC,
+};
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::Authorizer;
+use crate::protocol::describe_configs::{
+    CONFIG_SOURCE_DEFAULT, CONFIG_SOURCE_DYNAMIC_BROKER, CONFIG_SOURCE_DYNAMIC_BROKER_LOGGER,
+    CONFIG_SOURCE_DYNAMIC_DEFAULT_BROKER, CONFIG_SOURCE_DYNAMIC_TOPIC, CONFIG_SOURCE_STATIC_BROKER,
+    CONFIG_TYPE_UNKNOWN, DescribeConfigsRequest, DescribeConfigsResource,
+    DescribeConfigsResourceResult, DescribeConfigsResponse, DescribeConfigsResult,
+    DescribeConfigsSynonym,
+};
+use crate::shared::collections::FlatMap;
+use crate::shared::constants::{
+    CLEANUP_POLICY_CONFIG, CLEANUP_POLICY_DELETE, DEFAULT_UNCLEAN_LEADER_ELECTION_ENABLE,
+    RECORD_VALIDATORS_CONFIG, REQUIRED_HEADERS_CONFIG, UNCLEAN_LEADER_ELECTION_ENABLE_CONFIG,
+};
+use crate::shared::logging::LogLevelHandle;
+
+/// Serves DescribeConfigs from this broker's view of the metadata: topic overrides, the
+/// effective config of this broker with where each value comes from, the cluster-wide broker
+/// defaults and this broker's logger levels.
+pub struct DescribeConfigsHandler {
+    node_id: i32,
+    metadata: Arc<Mutex<BrokerMetadataListener>>,
+    authorizer: Option<Arc<dyn Authorizer>>,
+    log_level: Option<LogLevelHandle>,
+}
+
+/// A config's effective value, and every value it could take by source, the highest
+/// precedence first.
+struct DescribedConfig {
+    name: String,
+    value: String,
+    read_only: bool,
+    synonyms: Vec<(i8, String)>,
+}
+
+impl DescribeConfigsHandler {
+    pub fn new(
+        node_id: i32,
+        metadata: Arc<Mutex<BrokerMetadataListener>>,
+        authorizer: Option<Arc<dyn Authorizer>>,
+        log_level: Option<LogLevelHandle>,
+    ) -> Self {
+        Self {
+            node_id,
+            metadata,
+            authorizer,
+            log_level,
+        }
+    }
+
+    pub async fn handle(
+        &self,
+        context: &RequestContext,
+        request: DescribeConfigsRequest,
+    ) -> DescribeConfigsResponse {
+        let mut results = Vec::with_capacity(request.resources.len());
+        for resource in request.resources {
+            let (error, error_message, configs) = match self.describe(context, &resource).await {
+                Ok(configs) => (ErrorCode::None, None, configs),
+                Err((error, message)) => (error, Some(message), vec![]),
+            };
+            results.push(DescribeConfigsResult {
+                error_code: error.code(),
+                error_message,
+                resource_type: resource.resource_type,
+                resource_name: resource.resource_name,
+                configs: configs
+                    .into_iter()
+                    .filter(|config| {
+                        resource
+                            .configuration_keys
+                            .as_ref()
+                            .is_none_or(|keys| keys.contains(&config.name))
+                    })
+                    .map(|config| Self::encode(config, request.include_synonyms))
+                    .collect(),
+            });
+        }
+
+        DescribeConfigsResponse {
+            throttle_time_ms: 0,
+            results,
+        }
+    }
+
+    async fn describe(
+        &self,
+        context: &RequestContext,
+        resource: &DescribeConfigsResource,
+    ) -> Result<Vec<DescribedConfig>, (ErrorCode, String)> {
+        let (acl_resource, denied) = match resource.resource_type {
+            CONFIG_RESOURCE_TOPIC => (
+                Resource::new(ResourceType::Topic, resource.resource_name.as_str()),
+                ErrorCode::TopicAuthorizationFailed,
+            ),
+            CONFIG_RESOURCE_BROKER | CONFIG_RESOURCE_BROKER_LOGGER => {
+                (Resource::cluster(), ErrorCode::ClusterAuthorizationFailed)
+            }
+            resource_type => {
+                return Err((
+                    ErrorCode::InvalidRequest,
+                    format!("Unknown resource type {}", resource_type),
+                ));
+            }
+        };
+        if !context
+            .authorize(
+                self.authorizer.as_ref(),
+                AclOperation::DescribeConfigs,
+                &acl_resource,
+            )
+            .await
+        {
+            return Err((denied, "Not authorized to describe configs".to_string()));
+        }
+
+        let name = resource.resource_name.as_str();
+        let this_broker = name == self.node_id.to_string();
+        match resource.resource_type {
+            CONFIG_RESOURCE_TOPIC => self.describe_topic(name).await,
+            CONFIG_RESOURCE_BROKER if name.is_empty() => Ok(self.describe_broker_defaults().await),
+            CONFIG_RESOURCE_BROKER if this_broker => self.describe_broker().await,
+            CONFIG_RESOURCE_BROKER_LOGGER if this_broker => Ok(self.describe_loggers()),
+            _ => Err((
+                ErrorCode::InvalidRequest,
+                format!("Broker {} must be asked for its own configs", name),
+            )),
+        }
+    }
+
+    /// The overrides set on the topic, and the defaults of the topic configs the broker acts
+    /// on.
+    async fn describe_topic(
+        &self,
+        topic: &str,
+    ) -> Result<Vec<DescribedConfig>, (ErrorCode, String)> {
+        let listener = self.metadata.lock().await;
+        if !listener.metadata.topics.contains_key(&topic.to_string()) {
+            return Err((
+                ErrorCode::UnknownTopicOrPartition,
+                format!("Topic {} does not exist", topic),
+            ));
+        }
+        let overrides = listener
+            .metadata
+            .configs
+            .get(&(CONFIG_RESOURCE_TOPIC, topic.to_string()))
+            .cloned()
+            .unwrap_or_default();
+
+        let defaults = [
+            (CLEANUP_POLICY_CONFIG, CLEANUP_POLICY_DELETE.to_string()),
+            (
+                UNCLEAN_LEADER_ELECTION_ENABLE_CONFIG,
+                DEFAULT_UNCLEAN_LEADER_ELECTION_ENABLE.to_string(),
+            ),
+            (RECORD_VALIDATORS_CONFIG, String::new()),
+            (REQUIRED_HEADERS_CONFIG, String::new()),
+        ];
+        let mut configs: Vec<DescribedConfig> = defaults
+            .into_iter()
+            .map(|(name, default)| DescribedConfig {
+                name: name.to_string(),
+                value: overrides
+                    .get(&name.to_string())
+                    .cloned()
+                    .unwrap_or_else(|| default.clone()),
+                read_only: false,
+                synonyms: [
+                    overrides
+                        .get(&name.to_string())
+                        .map(|value| (CONFIG_SOURCE_DYNAMIC_TOPIC, value.clone())),
+                    Some((CONFIG_SOURCE_DEFAULT, default)),
+                ]
+                .into_iter()
+                .flatten()
+                .collect(),
+            })
+            .collect();
+        for (name, value) in overrides.iter() {
+            if !configs.iter().any(|config| config.name == *name) {
+                configs.push(DescribedConfig {
+                    name: name.clone(),
+                    value: value.clone(),
+                    read_only: false,
+                    synonyms: vec![(CONFIG_SOURCE_DYNAMIC_TOPIC, value.clone())],
+                });
+            }
+        }
+        Ok(configs)
+    }
+
+    /// Every setting of this broker, with the overrides set on it, the cluster-wide defaults,
+    /// its static config and the built-in defaults as synonyms, in that order of precedence.
+    async fn describe_broker(&self) -> Result<Vec<DescribedConfig>, (ErrorCode, String)> {
+        let listener = self.metadata.lock().await;
+        let Some(dynamic_config) = listener.dynamic_config() else {
+            return Err((
+                ErrorCode::UnknownServerError,
+                "The broker config is not loaded".to_string(),
+            ));
+        };
+        let broker_overrides = Self::broker_overrides(&listener, self.node_id.to_string());
+        let default_overrides = Self::broker_overrides(&listener, String::new());
+        let static_properties = dynamic_config.static_config().to_properties();
+        let default_properties = BrokerConfig::default().to_properties();
+
+        let mut configs = Vec::new();
+        for (name, value) in dynamic_config.current().to_properties() {
+            let key = name.to_string();
+            let mut synonyms = Vec::new();
+            if let Some(value) = broker_overrides.get(&key) {
+                synonyms.push((CONFIG_SOURCE_DYNAMIC_BROKER, value.clone()));
+            }
+            if let Some(value) = default_overrides.get(&key) {
+                synonyms.push((CONFIG_SOURCE_DYNAMIC_DEFAULT_BROKER, value.clone()));
+            }
+            let static_value = static_properties.iter().find(|(n, _)| *n == name);
+            let default_value = default_properties.iter().find(|(n, _)| *n == name);
+            match (static_value, default_value) {
+                (Some((_, s)), Some((_, d))) if s == d => {
+                    synonyms.push((CONFIG_SOURCE_DEFAULT, d.clone()))
+                }
+                (Some((_, s)), Some((_, d))) => {
+                    synonyms.push((CONFIG_SOURCE_STATIC_BROKER, s.clone()));
+                    synonyms.push((CONFIG_SOURCE_DEFAULT, d.clone()));
+                }
+                _ => synonyms.push((CONFIG_SOURCE_STATIC_BROKER, value.clone())),
+            }
+            configs.push(DescribedConfig {
+                name: key,
+                value,
+                read_only: !BrokerConfig::is_dynamic(name),
+                synonyms,
+            });
+        }
+
+        // Aliases such as log.retention.hours are only known by the override that set them
+        for (source, overrides) in [
+            (CONFIG_SOURCE_DYNAMIC_BROKER, &broker_overrides),
+            (CONFIG_SOURCE_DYNAMIC_DEFAULT_BROKER, &default_overrides),
+        ] {
+            for (name, value) in overrides.iter() {
+                match configs.iter_mut().find(|config| config.name == *name) {
+                    Some(config) => {
+                        if !config.synonyms.iter().any(|(s, _)| *s == source) {
+                            config.synonyms.push((source, value.clone()));
+                        }
+                    }
+                    None => configs.push(DescribedConfig {
+                        name: name.clone(),
+                        value: value.clone(),
+                        read_only: false,
+                        synonyms: vec![(source, value.clone())],
+                    }),
+                }
+            }
+        }
+        Ok(configs)
+    }
+
+    /// The cluster-wide broker overrides, which are all a default resource has.
+    async fn describe_broker_defaults(&self) -> Vec<DescribedConfig> {
+        let listener = self.metadata.lock().await;
+        Self::broker_overrides(&listener, String::new())
+            .iter()
+            .map(|(name, value)| DescribedConfig {
+                name: name.clone(),
+                value: value.clone(),
+                read_only: false,
+                synonyms: vec![(CONFIG_SOURCE_DYNAMIC_DEFAULT_BROKER, value.clone())],
+            })
+            .collect()
+    }
+
+    fn describe_loggers(&self) -> Vec<DescribedConfig> {
+        let Some(log_level) = &self.log_level else {
+            return vec![];
+        };
+        log_level
+            .loggers()
+            .iter()
+            .map(|(logger, level)| DescribedConfig {
+                name: logger.clone(),
+                value: level.clone(),
+                read_only: false,
+                synonyms: vec![(CONFIG_SOURCE_DYNAMIC_BROKER_LOGGER, level.clone())],
+            })
+            .collect()
+    }
+
+    fn broker_overrides(
+        listener: &BrokerMetadataListener,
+        resource_name: String,
+    ) -> FlatMap<String, String> {
+        listener
+            .metadata
+            .configs
+            .get(&(CONFIG_RESOURCE_BROKER, resource_name))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Sensitive values are left out, and unset ones (rendered empty) sent as null.
+    fn encode(config: DescribedConfig, include_synonyms: bool) -> DescribeConfigsResourceResult {
+        let is_sensitive = config.name.contains("password");
+        let shown = |value: String| (!is_sensitive && !value.is_empty()).then_some(value);
+        let config_source = config
+            .synonyms
+            .first()
+            .map_or(CONFIG_SOURCE_DEFAULT, |(source, _)| *source);
+        let synonyms = if include_synonyms {
+            config
+                .synonyms
+                .into_iter()
+                .map(|(source, value)| DescribeConfigsSynonym {
+                    name: config.name.clone(),
+                    value: shown(value),
+                    source,
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        DescribeConfigsResourceResult {
+            name: config.name,
+            value: shown(config.value),
+            read_only: config.read_only,
+            config_source,
+            is_sensitive,
+            synonyms,
+            config_type: CONFIG_TYPE_UNKNOWN,
+            documentation: None,
+        }
+    }
+}
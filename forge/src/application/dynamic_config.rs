@@ -0,0 +1,217 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::application::quota_manager::{QuotaEntity, QuotaManager, QuotaType};
+use crate::application::replica_manager::ReplicaManager;
+use crate::config::BrokerConfig;
+use crate::shared::collections::FlatMap;
+use crate::shared::logging::LogLevelHandle;
+
+/// The broker's effective configuration: the static file plus the dynamic overrides published
+/// by the controller. Pushes every change to the components that read those settings.
+pub struct DynamicBrokerConfig {
+    static_config: BrokerConfig,
+    current: BrokerConfig,
+    replica_manager: Arc<Mutex<ReplicaManager>>,
+    quota_manager: Arc<Mutex<QuotaManager>>,
+    log_level: Option<LogLevelHandle>,
+}
+
+impl DynamicBrokerConfig {
+    pub fn new(
+        static_config: BrokerConfig,
+        replica_manager: Arc<Mutex<ReplicaManager>>,
+        quota_manager: Arc<Mutex<QuotaManager>>,
+        log_level: Option<LogLevelHandle>,
+    ) -> Self {
+        Self {
+            current: static_config.clone(),
+            static_config,
+            replica_manager,
+            quota_manager,
+            log_level,
+        }
+    }
+
+    pub fn current(&self) -> &BrokerConfig {
+        &self.current
+    }
+
+    /// The config file, environment and command line settings, without dynamic overrides.
+    pub fn static_config(&self) -> &BrokerConfig {
+        &self.static_config
+    }
+
+    /// Replaces the dynamic overrides and applies what changed. An invalid set, including one
+    /// that contradicts the static settings, is rejected whole, leaving the previous
+    /// configuration in place.
+    pub async fn update(&mut self, overrides: &FlatMap<String, String>) -> Result<(), String> {
+        let config = self.static_config.with_overrides(overrides)?;
+        let problems = config.check();
+        if !problems.is_empty() {
+            return Err(problems.join("; "));
+        }
+        if config == self.current {
+            return Ok(());
+        }
+
+        if config.log != self.current.log {
+            tracing::info!("Updating log config to {:?}", config.log);
+            self.replica_manager
+                .lock()
+                .await
+                .set_log_config(config.log.clone());
+        }
+
+        if config.quota_producer_default != self.current.quota_producer_default
+            || config.quota_consumer_default != self.current.quota_consumer_default
+        {
+            tracing::info!(
+                "Updating default quotas to produce {:?} B/s, fetch {:?} B/s",
+                config.quota_producer_default,
+                config.quota_consumer_default
+            );
+            Self::apply_default_quotas(&mut *self.quota_manager.lock().await, &config);
+        }
+
+        if config.log_level != self.current.log_level
+            && let Some(log_level) = &self.log_level
+        {
+            log_level.set(config.log_level.as_deref())?;
+            tracing::info!("Updated log level to {:?}", config.log_level);
+        }
+
+        self.current = config;
+        Ok(())
+    }
+
+    /// Takes the reloadable settings of a re-read static config (`file_config`) and reapplies
+    /// `overrides` on top, logging what changed and what only a restart would apply. On
+    /// error the previous static config stays in place.
+    pub async fn reload(
+        &mut self,
+        file_config: BrokerConfig,
+        overrides: &FlatMap<String, String>,
+    ) -> Result<(), String> {
+        let before = self.static_config.to_properties();
+        let after = file_config.to_properties();
+        let mut changed = 0;
+        for ((name, old), (_, new)) in before.iter().zip(&after) {
+            if old == new {
+                continue;
+            }
+            changed += 1;
+            if !BrokerConfig::is_dynamic(name) {
+                tracing::warn!(
+                    "{} changed from '{}' to '{}' but only takes effect after a restart",
+                    name,
+                    old,
+                    new
+                );
+            } else if overrides.contains_key(&name.to_string()) {
+                tracing::info!(
+                    "{} changed from '{}' to '{}' but a dynamic override still applies",
+                    name,
+                    old,
+                    new
+                );
+            } else {
+                tracing::info!("Reloading {}: '{}' -> '{}'", name, old, new);
+            }
+        }
+        if changed == 0 {
+            tracing::info!("Reloaded config; nothing changed");
+            return Ok(());
+        }
+
+        let mut static_config = self.static_config.clone();
+        static_config.copy_dynamic_from(&file_config);
+        let previous = std::mem::replace(&mut self.static_config, static_config);
+        if let Err(e) = self.update(overrides).await {
+            self.static_config = previous;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Installs the `quota.*.default` settings of `config` as the quotas of the default entity.
+    pub fn apply_default_quotas(quota_manager: &mut QuotaManager, config: &BrokerConfig) {
+        let default_entity = QuotaEntity {
+            user: None,
+            client_id: None,
+        };
+        quota_manager.set_quota(
+            QuotaType::Produce,
+            default_entity.clone(),
+            config.quota_producer_default,
+        );
+        quota_manager.set_quota(
+            QuotaType::Fetch,
+            default_entity,
+            config.quota_consumer_default,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::domain::topic_partition::TopicPartition;
+
+    #[tokio::test]
+    async fn test_overrides_reach_live_logs_and_revert_to_static_config() {
+        let dir =
+            std::env::temp_dir().join(format!("forge-dynamic-config-{}", uuid::Uuid::new_v4()));
+        let replica_manager = Arc::new(Mutex::new(ReplicaManager::new(1, &dir, 1)));
+        let topic_partition = TopicPartition::new("events".to_string(), 0);
+        replica_manager
+            .lock()
+            .await
+            .create_partition(topic_partition.clone(), vec![1])
+            .await
+            .unwrap();
+
+        let static_config = BrokerConfig::default();
+        let mut dynamic_config = DynamicBrokerConfig::new(
+            static_config.clone(),
+            replica_manager.clone(),
+            Arc::new(Mutex::new(QuotaManager::new(1000, 11))),
+            None,
+        );
+
+        let mut overrides = FlatMap::new();
+        overrides.insert("log.retention.ms".to_string(), "60000".to_string());
+        overrides.insert("quota.producer.default".to_string(), "1024".to_string());
+        dynamic_config.update(&overrides).await.unwrap();
+        assert_eq!(
+            dynamic_config.current().quota_producer_default,
+            Some(1024.0)
+        );
+        {
+            let replica_manager = replica_manager.lock().await;
+            let partition = replica_manager.get_partition(&topic_partition).unwrap();
+            assert_eq!(partition.log.config().retention_ms, 60000);
+        }
+
+        // Static settings and bad values are refused without touching the current config
+        overrides.insert("node.id".to_string(), "7".to_string());
+        assert!(dynamic_config.update(&overrides).await.is_err());
+        overrides.remove(&"node.id".to_string());
+        overrides.insert("log.retention.ms".to_string(), "soon".to_string());
+        assert!(dynamic_config.update(&overrides).await.is_err());
+        assert_eq!(dynamic_config.current().log.retention_ms, 60000);
+
+        dynamic_config.update(&FlatMap::new()).await.unwrap();
+        assert_eq!(dynamic_config.current(), &static_config);
+        {
+            let replica_manager = replica_manager.lock().await;
+            let partition = replica_manager.get_partition(&topic_partition).unwrap();
+            assert_eq!(
+                partition.log.config().retention_ms,
+                static_config.log.retention_ms
+            );
+        }
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+}
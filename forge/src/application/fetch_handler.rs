@@ -0,0 +1,488 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::application::record_interceptor::RecordInterceptor;
+use crate::application::replica_manager::ReplicaManager;
+use crate::application::replica_selector::ClientMetadata;
+use crate::application::request_context::RequestContext;
+use crate::core::domain::acl::{AclOperation, Resource, ResourceType};
+use crate::core::domain::record_batch::Records;
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::Authorizer;
+use crate::protocol::fetch::{
+    AbortedTransaction, FetchRequest, FetchResponse, FetchableTopicResponse,
+    ISOLATION_READ_COMMITTED, PartitionData,
+};
+use crate::shared::constants::{CONSUMER_OFFSETS_TOPIC, TRANSACTION_STATE_TOPIC};
+
+pub struct FetchHandler {
+    replica_manager: Arc<Mutex<ReplicaManager>>,
+    authorizer: Option<Arc<dyn Authorizer>>,
+    interceptor: Option<Arc<dyn RecordInterceptor>>,
+}
+
+struct FetchResult {
+    response: FetchResponse,
+    bytes_read: usize,
+    has_error: bool,
+    /// Some partition told the consumer to fetch from another replica instead.
+    has_redirect: bool,
+    /// `ReplicaManager::fetch_positions` of the fetched partitions as the read was prepared.
+    positions: Vec<Option<(i64, i64)>>,
+}
+
+impl FetchHandler {
+    pub fn new(
+        replica_manager: Arc<Mutex<ReplicaManager>>,
+        authorizer: Option<Arc<dyn Authorizer>>,
+    ) -> Self {
+        Self {
+            replica_manager,
+            authorizer,
+            interceptor: None,
+        }
+    }
+
+    /// Passes every batch a consumer fetches through `interceptor`; followers still get the
+    /// log as it is.
+    pub fn with_interceptor(mut self, interceptor: Arc<dyn RecordInterceptor>) -> Self {
+        self.interceptor = Some(interceptor);
+        self
+    }
+
+    /// Followers need ClusterAction on the cluster; consumers need Read on each topic.
+    /// Returns, per requested topic, whether it may be fetched.
+    async fn authorize_topics(
+        &self,
+        context: &RequestContext,
+        request: &FetchRequest,
+    ) -> Vec<bool> {
+        let authorizer = self.authorizer.as_ref();
+        if request.replica_id >= 0 {
+            let allowed = context
+                .authorize(
+                    authorizer,
+                    AclOperation::ClusterAction,
+                    &Resource::cluster(),
+                )
+                .await;
+            return vec![allowed; request.topics.len()];
+        }
+
+        let mut authorized = Vec::with_capacity(request.topics.len());
+        for topic in &request.topics {
+            let resource = Resource::new(ResourceType::Topic, topic.topic.as_str());
+            authorized.push(
+                context
+                    .authorize(authorizer, AclOperation::Read, &resource)
+                    .await,
+            );
+        }
+        authorized
+    }
+
+    /// Answers right away when `min_bytes` is available, a partition errored or `max_wait_ms`
+    /// is zero; otherwise parks the fetch until enough data is appended or the wait elapses.
+    pub async fn handle(&self, context: &RequestContext, request: FetchRequest) -> FetchResponse {
+        let from_follower = request.replica_id >= 0;
+        let mut response = self.fetch(context, request).await;
+        if let Some(interceptor) = &self.interceptor
+            && !from_follower
+        {
+            intercept(interceptor.as_ref(), &mut response);
+        }
+        response
+    }
+
+    async fn fetch(&self, context: &RequestContext, request: FetchRequest) -> FetchResponse {
+        let authorized = self.authorize_topics(context, &request).await;
+        let max_wait = Duration::from_millis(request.max_wait_ms.max(0) as u64);
+        let deadline = Instant::now() + max_wait;
+        let min_bytes = request.min_bytes.max(0) as usize;
+
+        let partitions: Vec<TopicPartition> = request
+            .topics
+            .iter()
+            .flat_map(|topic| {
+                topic
+                    .partitions
+                    .iter()
+                    .map(|p| TopicPartition::new(topic.topic.clone(), p.partition))
+            })
+            .collect();
+
+        let wakeup = loop {
+            let result =
+                Self::read(&self.replica_manager, &request, &authorized, &partitions).await;
+            if max_wait.is_zero()
+                || result.has_error
+                || result.has_redirect
+                || result.bytes_read >= min_bytes
+            {
+                return result.response;
+            }
+
+            // The read ran without the lock; an append that slipped in since it was prepared
+            // would not wake a fetch parked now, so read again instead
+            let mut replica_manager = self.replica_manager.lock().await;
+            if replica_manager.fetch_positions(&partitions) != result.positions {
+                continue;
+            }
+            break replica_manager.watch_fetch(
+                request.replica_id >= 0,
+                partitions.clone(),
+                min_bytes,
+                result.bytes_read,
+                max_wait,
+            );
+        };
+
+        let _ = tokio::time::timeout_at(deadline, wakeup).await;
+
+        Self::read(&self.replica_manager, &request, &authorized, &partitions)
+            .await
+            .response
+    }
+
+    /// Checks every partition under the replica manager lock, then reads their logs without
+    /// it, so a slow disk under one partition does not stall requests for others.
+    async fn read(
+        replica_manager: &Mutex<ReplicaManager>,
+        request: &FetchRequest,
+        authorized: &[bool],
+        partitions: &[TopicPartition],
+    ) -> FetchResult {
+        let mut has_error = false;
+        let mut has_redirect = false;
+        let client =
+            (request.replica_id < 0 && !request.rack_id.is_empty()).then(|| ClientMetadata {
+                rack_id: request.rack_id.clone(),
+            });
+
+        let (prepared, positions) = {
+            let mut replica_manager = replica_manager.lock().await;
+            let mut prepared = Vec::with_capacity(request.topics.len());
+            for (topic, &topic_authorized) in request.topics.iter().zip(authorized) {
+                let mut topic_prepared = Vec::with_capacity(topic.partitions.len());
+                for fetch_partition in &topic.partitions {
+                    if !topic_authorized {
+                        has_error = true;
+                        topic_prepared.push(Err(error_data(
+                            fetch_partition.partition,
+                            ErrorCode::TopicAuthorizationFailed,
+                        )));
+                        continue;
+                    }
+
+                    let topic_partition =
+                        TopicPartition::new(topic.topic.clone(), fetch_partition.partition);
+
+                    // -1 means the client did not send an epoch (pre-v9 requests)
+                    let current_leader_epoch = (fetch_partition.current_leader_epoch >= 0)
+                        .then_some(fetch_partition.current_leader_epoch);
+
+                    // A redirected consumer gets offsets but no records from the leader
+                    if let Some(client) = &client
+                        && let Some(preferred_read_replica) =
+                            replica_manager.preferred_read_replica(&topic_partition, client)
+                        && let Some(partition) = replica_manager.get_partition(&topic_partition)
+                    {
+                        has_redirect = true;
+                        topic_prepared.push(Err(PartitionData {
+                            partition_index: fetch_partition.partition,
+                            error_code: ErrorCode::None.code(),
+                            high_watermark: partition.high_watermark(),
+                            last_stable_offset: partition.last_stable_offset(),
+                            log_start_offset: partition.log_start_offset(),
+                            aborted_transactions: None,
+                            preferred_read_replica,
+                            records: Records::default(),
+                        }));
+                        continue;
+                    }
+
+                    // Followers identify themselves with their broker id and read past the high watermark
+                    let fetch = if request.replica_id >= 0 {
+                        replica_manager.prepare_follower_fetch(
+                            &topic_partition,
+                            request.replica_id,
+                            current_leader_epoch,
+                            fetch_partition.fetch_offset,
+                        )
+                    } else {
+                        replica_manager.prepare_fetch(
+                            &topic_partition,
+                            current_leader_epoch,
+                            fetch_partition.fetch_offset,
+                            request.isolation_level,
+                        )
+                    };
+                    topic_prepared.push(fetch.map_err(|error| {
+                        has_error = true;
+                        error_data(fetch_partition.partition, error)
+                    }));
+                }
+                prepared.push(topic_prepared);
+            }
+            (prepared, replica_manager.fetch_positions(partitions))
+        };
+
+        let mut remaining_bytes = request.max_bytes.max(0) as usize;
+        let mut bytes_read = 0;
+        let mut responses = Vec::with_capacity(request.topics.len());
+        for (topic, topic_prepared) in request.topics.iter().zip(prepared) {
+            let mut partitions = Vec::with_capacity(topic.partitions.len());
+            for (fetch_partition, fetch) in topic.partitions.iter().zip(topic_prepared) {
+                let fetch = match fetch {
+                    Ok(fetch) => fetch,
+                    Err(partition_data) => {
+                        partitions.push(partition_data);
+                        continue;
+                    }
+                };
+
+                let max_bytes =
+                    (fetch_partition.partition_max_bytes.max(0) as usize).min(remaining_bytes);
+                let partition_data = match fetch.read(max_bytes).await {
+                    Ok(data) => {
+                        let size = data.records.size_in_bytes();
+                        bytes_read += size;
+                        remaining_bytes = remaining_bytes.saturating_sub(size);
+
+                        PartitionData {
+                            partition_index: fetch_partition.partition,
+                            error_code: ErrorCode::None.code(),
+                            high_watermark: data.high_watermark,
+                            last_stable_offset: data.last_stable_offset,
+                            log_start_offset: data.log_start_offset,
+                            aborted_transactions: (request.isolation_level
+                                == ISOLATION_READ_COMMITTED)
+                                .then(|| {
+                                    data.aborted_transactions
+                                        .iter()
+                                        .map(|txn| AbortedTransaction {
+                                            producer_id: txn.producer_id,
+                                            first_offset: txn.first_offset,
+                                        })
+                                        .collect()
+                                }),
+                            preferred_read_replica: -1,
+                            records: data.records,
+                        }
+                    }
+                    Err(error) => {
+                        has_error = true;
+                        error_data(fetch_partition.partition, error)
+                    }
+                };
+                partitions.push(partition_data);
+            }
+
+            responses.push(FetchableTopicResponse {
+                topic: topic.topic.clone(),
+                partitions,
+            });
+        }
+
+        FetchResult {
+            response: FetchResponse {
+                throttle_time_ms: 0,
+                error_code: ErrorCode::None.code(),
+                session_id: 0,
+                responses,
+            },
+            bytes_read,
+            has_error,
+            has_redirect,
+            positions,
+        }
+    }
+}
+
+/// Rewrites the records of each partition read as `interceptor` has them. A partition it
+/// fails on is answered with an error rather than with records it did not see.
+fn intercept(interceptor: &dyn RecordInterceptor, response: &mut FetchResponse) {
+    for topic in &mut response.responses {
+        if topic.topic == CONSUMER_OFFSETS_TOPIC || topic.topic == TRANSACTION_STATE_TOPIC {
+            continue;
+        }
+        for partition in &mut topic.partitions {
+            if partition.error_code != ErrorCode::None.code() || partition.records.is_empty() {
+                continue;
+            }
+            let intercepted = partition.records.batches().and_then(|mut batches| {
+                for batch in batches.iter_mut().filter(|batch| !batch.is_control_batch()) {
+                    interceptor.on_fetch(&topic.topic, batch)?;
+                    batch.records_count = batch.records.len() as i32;
+                }
+                Ok(Records::encode(&batches))
+            });
+            match intercepted {
+                Ok(records) => partition.records = records,
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to intercept fetched records of {}-{}: {}",
+                        topic.topic,
+                        partition.partition_index,
+                        e
+                    );
+                    partition.error_code = ErrorCode::UnknownServerError.code();
+                    partition.records = Records::default();
+                }
+            }
+        }
+    }
+}
+
+fn error_data(partition_index: i32, error: ErrorCode) -> PartitionData {
+    PartitionData {
+        partition_index,
+        error_code: error.code(),
+        high_watermark: -1,
+        last_stable_offset: -1,
+        log_start_offset: -1,
+        aborted_transactions: None,
+        preferred_read_replica: -1,
+        records: Records::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::replica_manager::ACKS_LEADER;
+    use crate::config::SecurityProtocol;
+    use crate::core::domain::principal::KafkaPrincipal;
+    use crate::core::domain::record::Record;
+    use crate::core::domain::record_batch::RecordBatch;
+    use crate::protocol::fetch::{FetchPartition, FetchTopic};
+
+    #[tokio::test]
+    async fn test_parked_fetch_completes_on_append() {
+        let dir = std::env::temp_dir().join(format!("forge-fetch-{}", uuid::Uuid::new_v4()));
+        let topic_partition = TopicPartition::new("events", 0);
+
+        let mut replica_manager = ReplicaManager::new(1, &dir, 1);
+        replica_manager
+            .create_partition(topic_partition.clone(), vec![1])
+            .await
+            .unwrap();
+        replica_manager
+            .become_leader(&topic_partition, 0, vec![1])
+            .unwrap();
+        let replica_manager = Arc::new(Mutex::new(replica_manager));
+
+        let handler = FetchHandler::new(replica_manager.clone(), None);
+        let request = FetchRequest {
+            replica_id: -1,
+            max_wait_ms: 30_000,
+            min_bytes: 1,
+            max_bytes: 1024 * 1024,
+            isolation_level: 0,
+            session_id: 0,
+            session_epoch: -1,
+            topics: vec![FetchTopic {
+                topic: "events".to_string(),
+                partitions: vec![FetchPartition {
+                    partition: 0,
+                    current_leader_epoch: -1,
+                    fetch_offset: 0,
+                    log_start_offset: -1,
+                    partition_max_bytes: 1024 * 1024,
+                }],
+            }],
+            forgotten_topics: vec![],
+            rack_id: String::new(),
+        };
+        let context = RequestContext {
+            principal: KafkaPrincipal::anonymous(),
+            client_host: "127.0.0.1".to_string(),
+            client_id: "consumer".to_string(),
+            listener: SecurityProtocol::Plaintext,
+        };
+        let fetch = tokio::spawn(async move { handler.handle(&context, request).await });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!fetch.is_finished());
+
+        let batch = RecordBatch::new(0, vec![Record::new(0, None, Some(b"hello".to_vec()))]);
+        replica_manager
+            .lock()
+            .await
+            .append_records(&topic_partition, ACKS_LEADER, batch)
+            .await
+            .unwrap();
+
+        let response = tokio::time::timeout(Duration::from_secs(5), fetch)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.responses[0].partitions[0].records.len(), 1);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_checks_the_leader_epoch_it_was_sent() {
+        let dir = std::env::temp_dir().join(format!("forge-fetch-{}", uuid::Uuid::new_v4()));
+        let topic_partition = TopicPartition::new("events", 0);
+
+        let mut replica_manager = ReplicaManager::new(1, &dir, 1);
+        replica_manager
+            .create_partition(topic_partition.clone(), vec![1])
+            .await
+            .unwrap();
+        replica_manager
+            .become_leader(&topic_partition, 5, vec![1])
+            .unwrap();
+        let handler = FetchHandler::new(Arc::new(Mutex::new(replica_manager)), None);
+        let context = RequestContext {
+            principal: KafkaPrincipal::anonymous(),
+            client_host: "127.0.0.1".to_string(),
+            client_id: "consumer".to_string(),
+            listener: SecurityProtocol::Plaintext,
+        };
+
+        // -1 is sent by clients that do not know the epoch, and skips the check
+        for (current_leader_epoch, expected) in [
+            (4, ErrorCode::FencedLeaderEpoch),
+            (6, ErrorCode::UnknownLeaderEpoch),
+            (5, ErrorCode::None),
+            (-1, ErrorCode::None),
+        ] {
+            let request = FetchRequest {
+                replica_id: -1,
+                max_wait_ms: 0,
+                min_bytes: 0,
+                max_bytes: 1024 * 1024,
+                isolation_level: 0,
+                session_id: 0,
+                session_epoch: -1,
+                topics: vec![FetchTopic {
+                    topic: "events".to_string(),
+                    partitions: vec![FetchPartition {
+                        partition: 0,
+                        current_leader_epoch,
+                        fetch_offset: 0,
+                        log_start_offset: -1,
+                        partition_max_bytes: 1024 * 1024,
+                    }],
+                }],
+                forgotten_topics: vec![],
+                rack_id: String::new(),
+            };
+            let response = handler.handle(&context, request).await;
+            assert_eq!(
+                response.responses[0].partitions[0].error_code,
+                expected.code(),
+                "leader epoch {}",
+                current_leader_epoch
+            );
+        }
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -0,0 +1,272 @@
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+use crate::core::error::ErrorCode;
+use crate::shared::collections::FlatMap;
+use crate::shared::time::current_time_ms;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupState {
+    /// No members; the group may still hold committed offsets.
+    Empty,
+    /// Waiting for every known member to (re)send JoinGroup before the rebalance deadline.
+    PreparingRebalance,
+    /// Generation bumped; waiting for the leader's SyncGroup with the assignment.
+    CompletingRebalance,
+    Stable,
+    /// Group has been removed; any handle to it must be discarded.
+    Dead,
+}
+
+impl GroupState {
+    /// The state's name in DescribeGroups responses.
+    pub fn name(self) -> &'static str {
+        match self {
+            GroupState::Empty => "Empty",
+            GroupState::PreparingRebalance => "PreparingRebalance",
+            GroupState::CompletingRebalance => "CompletingRebalance",
+            GroupState::Stable => "Stable",
+            GroupState::Dead => "Dead",
+        }
+    }
+
+    fn valid_previous_states(self) -> &'static [GroupState] {
+        match self {
+            GroupState::Empty => &[GroupState::PreparingRebalance],
+            GroupState::PreparingRebalance => &[
+                GroupState::Empty,
+                GroupState::CompletingRebalance,
+                GroupState::Stable,
+            ],
+            GroupState::CompletingRebalance => &[GroupState::PreparingRebalance],
+            GroupState::Stable => &[GroupState::CompletingRebalance],
+            GroupState::Dead => &[
+                GroupState::Empty,
+                GroupState::PreparingRebalance,
+                GroupState::CompletingRebalance,
+                GroupState::Stable,
+                GroupState::Dead,
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinGroupResult {
+    pub error: ErrorCode,
+    pub generation_id: i32,
+    pub protocol_name: Option<String>,
+    pub leader_id: String,
+    pub member_id: String,
+    /// Member subscriptions for the selected protocol; only populated for the leader.
+    pub members: Vec<(String, Vec<u8>)>,
+}
+
+impl JoinGroupResult {
+    pub fn error(member_id: String, error: ErrorCode) -> Self {
+        Self {
+            error,
+            generation_id: -1,
+            protocol_name: None,
+            leader_id: String::new(),
+            member_id,
+            members: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncGroupResult {
+    pub error: ErrorCode,
+    pub protocol_name: Option<String>,
+    pub assignment: Vec<u8>,
+}
+
+impl SyncGroupResult {
+    pub fn error(error: ErrorCode) -> Self {
+        Self {
+            error,
+            protocol_name: None,
+            assignment: vec![],
+        }
+    }
+}
+
+pub struct MemberMetadata {
+    pub member_id: String,
+    pub client_id: String,
+    pub client_host: String,
+    pub session_timeout: Duration,
+    pub rebalance_timeout: Duration,
+    pub protocol_type: String,
+    /// Supported protocols in the member's order of preference, with their metadata.
+    pub protocols: Vec<(String, Vec<u8>)>,
+    pub assignment: Vec<u8>,
+    pub last_heartbeat: Instant,
+    pub awaiting_join: Option<oneshot::Sender<JoinGroupResult>>,
+    pub awaiting_sync: Option<oneshot::Sender<SyncGroupResult>>,
+}
+
+impl MemberMetadata {
+    pub fn protocol_metadata(&self, protocol_name: &str) -> Option<&[u8]> {
+        self.protocols
+            .iter()
+            .find(|(name, _)| name == protocol_name)
+            .map(|(_, metadata)| metadata.as_slice())
+    }
+
+    pub fn matches_protocols(&self, protocols: &[(String, Vec<u8>)]) -> bool {
+        self.protocols == protocols
+    }
+
+    pub fn has_expired(&self, now: Instant) -> bool {
+        self.awaiting_join.is_none()
+            && now.duration_since(self.last_heartbeat) >= self.session_timeout
+    }
+}
+
+pub struct GroupMetadata {
+    pub group_id: String,
+    pub state: GroupState,
+    /// Wall-clock time (ms) of the last state transition; drives offset expiration for empty groups.
+    pub current_state_timestamp: i64,
+    pub generation_id: i32,
+    pub protocol_type: Option<String>,
+    pub protocol_name: Option<String>,
+    pub leader_id: Option<String>,
+    pub members: FlatMap<String, MemberMetadata>,
+}
+
+impl GroupMetadata {
+    pub fn new(group_id: String) -> Self {
+        Self {
+            group_id,
+            state: GroupState::Empty,
+            current_state_timestamp: current_time_ms(),
+            generation_id: 0,
+            protocol_type: None,
+            protocol_name: None,
+            leader_id: None,
+            members: FlatMap::new(),
+        }
+    }
+
+    pub fn is(&self, state: GroupState) -> bool {
+        self.state == state
+    }
+
+    pub fn transition_to(&mut self, target: GroupState) {
+        debug_assert!(
+            target.valid_previous_states().contains(&self.state),
+            "Group {} cannot transition from {:?} to {:?}",
+            self.group_id,
+            self.state,
+            target
+        );
+        tracing::debug!(
+            "Group {} transitioned from {:?} to {:?}",
+            self.group_id,
+            self.state,
+            target
+        );
+        self.state = target;
+        self.current_state_timestamp = current_time_ms();
+    }
+
+    pub fn can_rebalance(&self) -> bool {
+        GroupState::PreparingRebalance
+            .valid_previous_states()
+            .contains(&self.state)
+    }
+
+    pub fn supports_protocols(&self, protocol_type: &str, protocols: &[(String, Vec<u8>)]) -> bool {
+        if self.members.is_empty() {
+            return !protocol_type.is_empty() && !protocols.is_empty();
+        }
+
+        self.protocol_type.as_deref() == Some(protocol_type)
+            && !self.candidate_protocols(protocols).is_empty()
+    }
+
+    /// Protocols supported by every current member and by `extra`, if any.
+    fn candidate_protocols(&self, extra: &[(String, Vec<u8>)]) -> Vec<String> {
+        let mut candidates: Vec<String> = extra.iter().map(|(name, _)| name.clone()).collect();
+        for member in self.members.values() {
+            candidates.retain(|name| member.protocol_metadata(name).is_some());
+        }
+        candidates
+    }
+
+    /// Picks the protocol supported by all members that receives the most first-preference votes.
+    pub fn select_protocol(&self) -> Option<String> {
+        let first_member = self.members.values().next()?;
+        let candidates = self.candidate_protocols(&first_member.protocols);
+
+        let mut votes: Vec<(String, usize)> = candidates.iter().map(|c| (c.clone(), 0)).collect();
+        for member in self.members.values() {
+            if let Some((name, _)) = member
+                .protocols
+                .iter()
+                .find(|(name, _)| candidates.contains(name))
+                && let Some(entry) = votes.iter_mut().find(|(c, _)| c == name)
+            {
+                entry.1 += 1;
+            }
+        }
+
+        votes
+            .into_iter()
+            .fold(
+                None,
+                |best: Option<(String, usize)>, (name, count)| match best {
+                    Some((_, best_count)) if best_count >= count => best,
+                    _ => Some((name, count)),
+                },
+            )
+            .map(|(name, _)| name)
+    }
+
+    pub fn max_rebalance_timeout(&self) -> Duration {
+        self.members
+            .values()
+            .map(|m| m.rebalance_timeout)
+            .max()
+            .unwrap_or_default()
+    }
+
+    pub fn has_all_members_joined(&self) -> bool {
+        self.members.values().all(|m| m.awaiting_join.is_some())
+    }
+
+    pub fn add_member(&mut self, member: MemberMetadata) {
+        if self.members.is_empty() {
+            self.protocol_type = Some(member.protocol_type.clone());
+        }
+        if self.leader_id.is_none() {
+            self.leader_id = Some(member.member_id.clone());
+        }
+        self.members.insert(member.member_id.clone(), member);
+    }
+
+    pub fn remove_member(&mut self, member_id: &str) -> Option<MemberMetadata> {
+        let removed = self.members.remove(&member_id.to_string());
+        if self.leader_id.as_deref() == Some(member_id) {
+            self.leader_id = self.members.keys().next().cloned();
+        }
+        if self.members.is_empty() {
+            self.protocol_type = None;
+        }
+        removed
+    }
+
+    pub fn init_next_generation(&mut self) {
+        self.generation_id += 1;
+        if self.members.is_empty() {
+            self.protocol_name = None;
+            self.transition_to(GroupState::Empty);
+        } else {
+            self.protocol_name = self.select_protocol();
+            self.transition_to(GroupState::CompletingRebalance);
+        }
+    }
+}
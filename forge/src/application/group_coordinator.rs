@@ -0,0 +1,1036 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, oneshot};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::application::group::{
+    GroupMetadata, GroupState, JoinGroupResult, MemberMetadata, SyncGroupResult,
+};
+use crate::application::group_metadata_manager::GroupMetadataManager;
+use crate::application::purgatory::{DelayedOperation, DelayedOperationPurgatory};
+use crate::application::replica_manager::ReplicaManager;
+use crate::core::domain::group_records::{GroupMetadataValue, OffsetAndMetadata};
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::shared::collections::FlatMap;
+use crate::shared::constants::CONSUMER_OFFSETS_TOPIC;
+use crate::shared::scheduler::spawn_periodic;
+use crate::shared::time::current_time_ms;
+
+pub const GROUP_MIN_SESSION_TIMEOUT_MS: i32 = 6_000;
+pub const GROUP_MAX_SESSION_TIMEOUT_MS: i32 = 1_800_000;
+
+#[derive(Debug, Clone)]
+pub struct JoinGroupParams {
+    pub group_id: String,
+    /// Empty on the first join; the coordinator assigns one.
+    pub member_id: String,
+    pub client_id: String,
+    pub client_host: String,
+    pub session_timeout_ms: i32,
+    pub rebalance_timeout_ms: i32,
+    pub protocol_type: String,
+    pub protocols: Vec<(String, Vec<u8>)>,
+}
+
+/// Completes a group's join phase once every member has rejoined or the rebalance timeout passed.
+pub struct DelayedJoin {
+    group_id: String,
+}
+
+impl DelayedOperation for DelayedJoin {
+    type Context = FlatMap<String, GroupMetadata>;
+
+    fn try_complete(&mut self, groups: &mut Self::Context) -> bool {
+        let Some(group) = groups.get_mut(&self.group_id) else {
+            return true;
+        };
+        if !group.is(GroupState::PreparingRebalance) {
+            return true;
+        }
+        if !group.has_all_members_joined() {
+            return false;
+        }
+        GroupCoordinator::complete_join(group);
+        true
+    }
+
+    fn on_expiration(&mut self, groups: &mut Self::Context) {
+        if let Some(group) = groups.get_mut(&self.group_id)
+            && group.is(GroupState::PreparingRebalance)
+        {
+            GroupCoordinator::complete_join(group);
+        }
+    }
+}
+
+pub struct GroupCoordinator {
+    groups: FlatMap<String, GroupMetadata>,
+    join_purgatory: DelayedOperationPurgatory<String, DelayedJoin>,
+    pub metadata_manager: GroupMetadataManager,
+    /// The leader epoch each `__consumer_offsets` partition was last loaded at.
+    loaded_partitions: FlatMap<i32, i32>,
+}
+
+impl GroupCoordinator {
+    pub fn new(offsets_topic_partitions: i32) -> Self {
+        Self {
+            groups: FlatMap::new(),
+            join_purgatory: DelayedOperationPurgatory::new("Rebalance"),
+            metadata_manager: GroupMetadataManager::new(offsets_topic_partitions),
+            loaded_partitions: FlatMap::new(),
+        }
+    }
+
+    pub fn group(&self, group_id: &str) -> Option<&GroupMetadata> {
+        self.groups.get(&group_id.to_string())
+    }
+
+    /// Restores a group loaded from `__consumer_offsets`; members must rejoin to resume.
+    pub fn load_group(&mut self, group_id: &str, value: &GroupMetadataValue) {
+        if self.groups.contains_key(&group_id.to_string()) {
+            return;
+        }
+
+        let mut group = GroupMetadata::new(group_id.to_string());
+        group.generation_id = value.generation_id;
+        group.protocol_type =
+            (!value.protocol_type.is_empty()).then(|| value.protocol_type.clone());
+        group.current_state_timestamp = value.current_state_timestamp;
+        self.groups.insert(group_id.to_string(), group);
+    }
+
+    pub async fn load_partition(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        partition: i32,
+    ) -> Result<(), ErrorCode> {
+        self.metadata_manager
+            .load_partition(replica_manager, partition)
+            .await?;
+
+        let loaded: Vec<(String, GroupMetadataValue)> = self
+            .metadata_manager
+            .groups()
+            .filter(|(group_id, _)| self.metadata_manager.partition_for(group_id) == partition)
+            .map(|(group_id, value)| (group_id.clone(), value.clone()))
+            .collect();
+        for (group_id, value) in loaded {
+            self.load_group(&group_id, &value);
+        }
+        Ok(())
+    }
+
+    /// Loads the `__consumer_offsets` partition owning `group_id` the first time this broker
+    /// serves it as leader, and again after every leadership change.
+    pub async fn ensure_loaded(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        group_id: &str,
+    ) -> Result<(), ErrorCode> {
+        let partition = self.metadata_manager.partition_for(group_id);
+        self.ensure_partition_loaded(replica_manager, partition)
+            .await
+    }
+
+    async fn ensure_partition_loaded(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        partition: i32,
+    ) -> Result<(), ErrorCode> {
+        let topic_partition = TopicPartition::new(CONSUMER_OFFSETS_TOPIC, partition);
+        let leader_epoch = match replica_manager.get_partition(&topic_partition) {
+            Some(partition) if partition.is_leader() => partition.leader_epoch,
+            _ => return Err(ErrorCode::NotCoordinator),
+        };
+        if self.loaded_partitions.get(&partition) == Some(&leader_epoch) {
+            return Ok(());
+        }
+
+        self.load_partition(replica_manager, partition).await?;
+        self.loaded_partitions.insert(partition, leader_epoch);
+        Ok(())
+    }
+
+    /// Loads every `__consumer_offsets` partition this broker leads and returns them.
+    pub async fn ensure_all_loaded(&mut self, replica_manager: &mut ReplicaManager) -> Vec<i32> {
+        let mut led = Vec::new();
+        for partition in 0..self.metadata_manager.offsets_topic_partitions {
+            match self
+                .ensure_partition_loaded(replica_manager, partition)
+                .await
+            {
+                Ok(()) => led.push(partition),
+                Err(ErrorCode::NotCoordinator) => {}
+                Err(error) => tracing::warn!(
+                    "Failed to load {}-{}: {}",
+                    CONSUMER_OFFSETS_TOPIC,
+                    partition,
+                    error
+                ),
+            }
+        }
+        led
+    }
+
+    /// The live groups owned by `partitions`, with their protocol type and state. Groups
+    /// that only hold committed offsets are listed as Empty, with no protocol type.
+    pub fn list_groups(&self, partitions: &[i32]) -> Vec<(String, String, GroupState)> {
+        let owned =
+            |group_id: &str| partitions.contains(&self.metadata_manager.partition_for(group_id));
+        let mut listed: Vec<(String, String, GroupState)> = self
+            .groups
+            .values()
+            .filter(|group| !group.is(GroupState::Dead) && owned(&group.group_id))
+            .map(|group| {
+                (
+                    group.group_id.clone(),
+                    group.protocol_type.clone().unwrap_or_default(),
+                    group.state,
+                )
+            })
+            .collect();
+        for group_id in self.metadata_manager.group_ids_with_offsets() {
+            if owned(&group_id) && self.group(&group_id).is_none() {
+                listed.push((group_id, String::new(), GroupState::Empty));
+            }
+        }
+        listed
+    }
+
+    pub fn has_offsets(&self, group_id: &str) -> bool {
+        !self
+            .metadata_manager
+            .fetch_offsets(group_id, None)
+            .is_empty()
+    }
+
+    pub async fn commit_offsets(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        group_id: &str,
+        generation_id: i32,
+        member_id: &str,
+        offsets: Vec<(TopicPartition, OffsetAndMetadata)>,
+    ) -> Result<(), ErrorCode> {
+        let error = self.validate_offset_commit(group_id, generation_id, member_id);
+        if error != ErrorCode::None {
+            return Err(error);
+        }
+
+        self.metadata_manager
+            .store_offsets(replica_manager, group_id, offsets)
+            .await
+    }
+
+    /// Offsets a transactional producer commits for the group, which need not have members.
+    pub async fn commit_transactional_offsets(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        group_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        offsets: Vec<(TopicPartition, OffsetAndMetadata)>,
+    ) -> Result<(), ErrorCode> {
+        self.metadata_manager
+            .store_transactional_offsets(
+                replica_manager,
+                group_id,
+                producer_id,
+                producer_epoch,
+                offsets,
+            )
+            .await
+    }
+
+    pub fn complete_transactional_offsets(&mut self, producer_id: i64, commit: bool) {
+        self.metadata_manager
+            .complete_transactional_offsets(producer_id, commit);
+    }
+
+    pub fn fetch_offsets(
+        &self,
+        group_id: &str,
+        partitions: Option<&[TopicPartition]>,
+    ) -> Vec<(TopicPartition, OffsetAndMetadata)> {
+        self.metadata_manager.fetch_offsets(group_id, partitions)
+    }
+
+    /// Writes the group's current generation and assignment once it has stabilized.
+    pub async fn persist_group(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        group_id: &str,
+    ) -> Result<(), ErrorCode> {
+        let Some(group) = self.groups.get(&group_id.to_string()) else {
+            return Err(ErrorCode::UnknownMemberId);
+        };
+        self.metadata_manager
+            .store_group(replica_manager, group)
+            .await
+    }
+
+    /// Drops offsets of groups that have been empty for longer than `retention_ms` (or, for
+    /// offsets committed without a group, that were committed longer ago), then deletes empty
+    /// groups left without offsets. Returns the number of offsets removed.
+    pub async fn cleanup_expired_offsets(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        retention_ms: i64,
+    ) -> usize {
+        let now = current_time_ms();
+        let mut removed = 0;
+
+        for group_id in self.metadata_manager.group_ids_with_offsets() {
+            let offsets = self.metadata_manager.fetch_offsets(&group_id, None);
+            let expired: Vec<TopicPartition> = match self.groups.get(&group_id) {
+                Some(group) if group.is(GroupState::Empty) => {
+                    if group.current_state_timestamp + retention_ms <= now {
+                        offsets.into_iter().map(|(tp, _)| tp).collect()
+                    } else {
+                        vec![]
+                    }
+                }
+                Some(_) => vec![],
+                None => offsets
+                    .into_iter()
+                    .filter(|(_, offset)| offset.commit_timestamp + retention_ms <= now)
+                    .map(|(tp, _)| tp)
+                    .collect(),
+            };
+
+            if expired.is_empty() {
+                continue;
+            }
+
+            match self
+                .metadata_manager
+                .remove_offsets(replica_manager, &group_id, &expired)
+                .await
+            {
+                Ok(()) => removed += expired.len(),
+                Err(e) => tracing::warn!(
+                    "Failed to remove expired offsets for group {}: {}",
+                    group_id,
+                    e
+                ),
+            }
+        }
+
+        let dead_groups: Vec<String> = self
+            .groups
+            .values()
+            .filter(|g| {
+                g.is(GroupState::Empty)
+                    && self
+                        .metadata_manager
+                        .fetch_offsets(&g.group_id, None)
+                        .is_empty()
+            })
+            .map(|g| g.group_id.clone())
+            .collect();
+
+        for group_id in dead_groups {
+            if let Err(e) = self
+                .metadata_manager
+                .remove_group(replica_manager, &group_id)
+                .await
+            {
+                tracing::warn!("Failed to delete empty group {}: {}", group_id, e);
+                continue;
+            }
+            if let Some(mut group) = self.groups.remove(&group_id) {
+                group.transition_to(GroupState::Dead);
+            }
+            tracing::info!(
+                "Group {} transitioned to Dead after its offsets expired",
+                group_id
+            );
+        }
+
+        removed
+    }
+
+    pub fn start_offsets_expiration(
+        coordinator: Arc<Mutex<GroupCoordinator>>,
+        replica_manager: Arc<Mutex<ReplicaManager>>,
+        retention_ms: i64,
+        check_interval: Duration,
+        cancel_token: CancellationToken,
+    ) -> JoinHandle<()> {
+        spawn_periodic(
+            "group-offsets-expiration",
+            check_interval,
+            cancel_token,
+            move || {
+                let coordinator = coordinator.clone();
+                let replica_manager = replica_manager.clone();
+                async move {
+                    let mut coordinator = coordinator.lock().await;
+                    let mut replica_manager = replica_manager.lock().await;
+                    let removed = coordinator
+                        .cleanup_expired_offsets(&mut replica_manager, retention_ms)
+                        .await;
+                    if removed > 0 {
+                        tracing::info!("Removed {} expired committed offsets", removed);
+                    }
+                }
+            },
+        )
+    }
+
+    /// Runs `tick` every `check_interval`, so members that stop heartbeating are removed and
+    /// rebalances waiting on them complete.
+    pub fn start_session_expiration(
+        coordinator: Arc<Mutex<GroupCoordinator>>,
+        check_interval: Duration,
+        cancel_token: CancellationToken,
+    ) -> JoinHandle<()> {
+        spawn_periodic(
+            "group-session-expiration",
+            check_interval,
+            cancel_token,
+            move || {
+                let coordinator = coordinator.clone();
+                async move { coordinator.lock().await.tick() }
+            },
+        )
+    }
+
+    pub fn validate_offset_commit(
+        &mut self,
+        group_id: &str,
+        generation_id: i32,
+        member_id: &str,
+    ) -> ErrorCode {
+        let is_simple_commit = generation_id < 0 && member_id.is_empty();
+        if is_simple_commit {
+            return match self.group(group_id) {
+                Some(group) if !group.is(GroupState::Empty) => ErrorCode::IllegalGeneration,
+                _ => ErrorCode::None,
+            };
+        }
+
+        match self.validate_member(group_id, generation_id, member_id) {
+            // Members may still commit for the generation they are leaving, e.g. before giving
+            // up their partitions; the next one has no assignment to commit for yet
+            Ok(group) if group.is(GroupState::CompletingRebalance) => {
+                ErrorCode::RebalanceInProgress
+            }
+            Ok(_) => ErrorCode::None,
+            Err(error) => error,
+        }
+    }
+
+    pub fn join_group(&mut self, params: JoinGroupParams) -> oneshot::Receiver<JoinGroupResult> {
+        let (tx, rx) = oneshot::channel();
+
+        if params.session_timeout_ms < GROUP_MIN_SESSION_TIMEOUT_MS
+            || params.session_timeout_ms > GROUP_MAX_SESSION_TIMEOUT_MS
+        {
+            let _ = tx.send(JoinGroupResult::error(
+                params.member_id,
+                ErrorCode::InvalidSessionTimeout,
+            ));
+            return rx;
+        }
+
+        if !self.groups.contains_key(&params.group_id) {
+            if !params.member_id.is_empty() {
+                let _ = tx.send(JoinGroupResult::error(
+                    params.member_id,
+                    ErrorCode::UnknownMemberId,
+                ));
+                return rx;
+            }
+            self.groups.insert(
+                params.group_id.clone(),
+                GroupMetadata::new(params.group_id.clone()),
+            );
+        }
+
+        let Some(group) = self.groups.get_mut(&params.group_id) else {
+            return rx;
+        };
+
+        if group.is(GroupState::Dead) {
+            let _ = tx.send(JoinGroupResult::error(
+                params.member_id,
+                ErrorCode::UnknownMemberId,
+            ));
+            return rx;
+        }
+
+        if !group.supports_protocols(&params.protocol_type, &params.protocols) {
+            let _ = tx.send(JoinGroupResult::error(
+                params.member_id,
+                ErrorCode::InconsistentGroupProtocol,
+            ));
+            return rx;
+        }
+
+        let group_id = params.group_id.clone();
+        if params.member_id.is_empty() {
+            Self::add_member_and_rebalance(group, &mut self.join_purgatory, params, tx);
+        } else {
+            Self::update_member_and_rebalance(group, &mut self.join_purgatory, params, tx);
+        }
+        self.join_purgatory
+            .check_and_complete(&group_id, &mut self.groups);
+
+        rx
+    }
+
+    fn add_member_and_rebalance(
+        group: &mut GroupMetadata,
+        join_purgatory: &mut DelayedOperationPurgatory<String, DelayedJoin>,
+        params: JoinGroupParams,
+        tx: oneshot::Sender<JoinGroupResult>,
+    ) {
+        let member_id = format!("{}-{}", params.client_id, uuid::Uuid::new_v4());
+        tracing::info!("Adding member {} to group {}", member_id, group.group_id);
+
+        group.add_member(MemberMetadata {
+            member_id,
+            client_id: params.client_id,
+            client_host: params.client_host,
+            session_timeout: Duration::from_millis(params.session_timeout_ms as u64),
+            rebalance_timeout: Duration::from_millis(params.rebalance_timeout_ms.max(0) as u64),
+            protocol_type: params.protocol_type,
+            protocols: params.protocols,
+            assignment: vec![],
+            last_heartbeat: Instant::now(),
+            awaiting_join: Some(tx),
+            awaiting_sync: None,
+        });
+
+        Self::maybe_prepare_rebalance(group, join_purgatory, "new member joined");
+    }
+
+    fn update_member_and_rebalance(
+        group: &mut GroupMetadata,
+        join_purgatory: &mut DelayedOperationPurgatory<String, DelayedJoin>,
+        params: JoinGroupParams,
+        tx: oneshot::Sender<JoinGroupResult>,
+    ) {
+        let is_leader = group.leader_id.as_deref() == Some(params.member_id.as_str());
+        let Some(member) = group.members.get_mut(&params.member_id) else {
+            let _ = tx.send(JoinGroupResult::error(
+                params.member_id,
+                ErrorCode::UnknownMemberId,
+            ));
+            return;
+        };
+
+        let protocols_changed = !member.matches_protocols(&params.protocols);
+        member.protocols = params.protocols;
+        member.session_timeout = Duration::from_millis(params.session_timeout_ms as u64);
+        member.rebalance_timeout = Duration::from_millis(params.rebalance_timeout_ms.max(0) as u64);
+        member.last_heartbeat = Instant::now();
+        member.awaiting_join = Some(tx);
+
+        match group.state {
+            GroupState::PreparingRebalance => {}
+            GroupState::CompletingRebalance if !protocols_changed => {
+                Self::respond_to_rejoin(group, &params.member_id)
+            }
+            GroupState::Stable if !protocols_changed && !is_leader => {
+                Self::respond_to_rejoin(group, &params.member_id)
+            }
+            _ => {
+                Self::maybe_prepare_rebalance(
+                    group,
+                    join_purgatory,
+                    "member rejoined with new metadata",
+                );
+            }
+        }
+    }
+
+    /// Replays the current generation to a member that rejoined without changing anything.
+    fn respond_to_rejoin(group: &mut GroupMetadata, member_id: &str) {
+        let leader_id = group.leader_id.clone().unwrap_or_default();
+        let members = if leader_id == member_id {
+            Self::member_subscriptions(group)
+        } else {
+            vec![]
+        };
+
+        if let Some(member) = group.members.get_mut(&member_id.to_string())
+            && let Some(tx) = member.awaiting_join.take()
+        {
+            let _ = tx.send(JoinGroupResult {
+                error: ErrorCode::None,
+                generation_id: group.generation_id,
+                protocol_name: group.protocol_name.clone(),
+                leader_id,
+                member_id: member_id.to_string(),
+                members,
+            });
+        }
+    }
+
+    fn member_subscriptions(group: &GroupMetadata) -> Vec<(String, Vec<u8>)> {
+        let protocol_name = group.protocol_name.as_deref().unwrap_or_default();
+        group
+            .members
+            .values()
+            .map(|m| {
+                (
+                    m.member_id.clone(),
+                    m.protocol_metadata(protocol_name)
+                        .unwrap_or_default()
+                        .to_vec(),
+                )
+            })
+            .collect()
+    }
+
+    fn maybe_prepare_rebalance(
+        group: &mut GroupMetadata,
+        join_purgatory: &mut DelayedOperationPurgatory<String, DelayedJoin>,
+        reason: &str,
+    ) {
+        if !group.can_rebalance() {
+            return;
+        }
+
+        if group.is(GroupState::CompletingRebalance) {
+            for member in group.members.values_mut() {
+                if let Some(tx) = member.awaiting_sync.take() {
+                    let _ = tx.send(SyncGroupResult::error(ErrorCode::RebalanceInProgress));
+                }
+            }
+        }
+
+        tracing::info!(
+            "Preparing to rebalance group {} in state {:?} with old generation {} (reason: {})",
+            group.group_id,
+            group.state,
+            group.generation_id,
+            reason
+        );
+
+        group.transition_to(GroupState::PreparingRebalance);
+        join_purgatory.watch(
+            DelayedJoin {
+                group_id: group.group_id.clone(),
+            },
+            vec![group.group_id.clone()],
+            group.max_rebalance_timeout(),
+        );
+    }
+
+    fn complete_join(group: &mut GroupMetadata) {
+        let not_rejoined: Vec<String> = group
+            .members
+            .values()
+            .filter(|m| m.awaiting_join.is_none())
+            .map(|m| m.member_id.clone())
+            .collect();
+        for member_id in not_rejoined {
+            tracing::info!(
+                "Removing member {} from group {} because it did not rejoin before the rebalance timeout",
+                member_id,
+                group.group_id
+            );
+            group.remove_member(&member_id);
+        }
+
+        group.init_next_generation();
+        if group.is(GroupState::Empty) {
+            tracing::info!(
+                "Group {} with generation {} is now empty",
+                group.group_id,
+                group.generation_id
+            );
+            return;
+        }
+
+        tracing::info!(
+            "Stabilized group {} generation {} with {} members",
+            group.group_id,
+            group.generation_id,
+            group.members.len()
+        );
+
+        let leader_id = group.leader_id.clone().unwrap_or_default();
+        let subscriptions = Self::member_subscriptions(group);
+        let generation_id = group.generation_id;
+        let protocol_name = group.protocol_name.clone();
+        let now = Instant::now();
+
+        for member in group.members.values_mut() {
+            member.last_heartbeat = now;
+            if let Some(tx) = member.awaiting_join.take() {
+                let members = if member.member_id == leader_id {
+                    subscriptions.clone()
+                } else {
+                    vec![]
+                };
+                let _ = tx.send(JoinGroupResult {
+                    error: ErrorCode::None,
+                    generation_id,
+                    protocol_name: protocol_name.clone(),
+                    leader_id: leader_id.clone(),
+                    member_id: member.member_id.clone(),
+                    members,
+                });
+            }
+        }
+    }
+
+    pub fn sync_group(
+        &mut self,
+        group_id: &str,
+        generation_id: i32,
+        member_id: &str,
+        assignments: Vec<(String, Vec<u8>)>,
+    ) -> oneshot::Receiver<SyncGroupResult> {
+        let (tx, rx) = oneshot::channel();
+
+        let group = match self.validate_member(group_id, generation_id, member_id) {
+            Ok(group) => group,
+            Err(error) => {
+                let _ = tx.send(SyncGroupResult::error(error));
+                return rx;
+            }
+        };
+
+        match group.state {
+            GroupState::Empty | GroupState::Dead => {
+                let _ = tx.send(SyncGroupResult::error(ErrorCode::UnknownMemberId));
+            }
+            GroupState::PreparingRebalance => {
+                let _ = tx.send(SyncGroupResult::error(ErrorCode::RebalanceInProgress));
+            }
+            GroupState::CompletingRebalance => {
+                if let Some(member) = group.members.get_mut(&member_id.to_string()) {
+                    member.awaiting_sync = Some(tx);
+                }
+
+                if group.leader_id.as_deref() == Some(member_id) {
+                    Self::apply_assignments(group, assignments);
+                }
+            }
+            GroupState::Stable => {
+                let assignment = group
+                    .members
+                    .get(&member_id.to_string())
+                    .map(|m| m.assignment.clone())
+                    .unwrap_or_default();
+                let _ = tx.send(SyncGroupResult {
+                    error: ErrorCode::None,
+                    protocol_name: group.protocol_name.clone(),
+                    assignment,
+                });
+            }
+        }
+
+        rx
+    }
+
+    fn apply_assignments(group: &mut GroupMetadata, assignments: Vec<(String, Vec<u8>)>) {
+        for member in group.members.values_mut() {
+            member.assignment.clear();
+        }
+        for (member_id, assignment) in assignments {
+            if let Some(member) = group.members.get_mut(&member_id) {
+                member.assignment = assignment;
+            }
+        }
+
+        let protocol_name = group.protocol_name.clone();
+        for member in group.members.values_mut() {
+            if let Some(tx) = member.awaiting_sync.take() {
+                let _ = tx.send(SyncGroupResult {
+                    error: ErrorCode::None,
+                    protocol_name: protocol_name.clone(),
+                    assignment: member.assignment.clone(),
+                });
+            }
+        }
+
+        group.transition_to(GroupState::Stable);
+        tracing::info!(
+            "Assignment received from leader for group {} for generation {}",
+            group.group_id,
+            group.generation_id
+        );
+    }
+
+    pub fn heartbeat(&mut self, group_id: &str, generation_id: i32, member_id: &str) -> ErrorCode {
+        let group = match self.validate_member(group_id, generation_id, member_id) {
+            Ok(group) => group,
+            Err(error) => return error,
+        };
+
+        if let Some(member) = group.members.get_mut(&member_id.to_string()) {
+            member.last_heartbeat = Instant::now();
+        }
+
+        match group.state {
+            GroupState::Empty | GroupState::Dead => ErrorCode::UnknownMemberId,
+            GroupState::PreparingRebalance => ErrorCode::RebalanceInProgress,
+            GroupState::CompletingRebalance | GroupState::Stable => ErrorCode::None,
+        }
+    }
+
+    pub fn leave_group(&mut self, group_id: &str, member_id: &str) -> ErrorCode {
+        let Some(group) = self.groups.get_mut(&group_id.to_string()) else {
+            return ErrorCode::UnknownMemberId;
+        };
+
+        if group.remove_member(member_id).is_none() {
+            return ErrorCode::UnknownMemberId;
+        }
+
+        tracing::info!("Member {} has left group {}", member_id, group_id);
+        Self::maybe_prepare_rebalance(group, &mut self.join_purgatory, "member left group");
+        self.join_purgatory
+            .check_and_complete(&group_id.to_string(), &mut self.groups);
+        ErrorCode::None
+    }
+
+    fn validate_member(
+        &mut self,
+        group_id: &str,
+        generation_id: i32,
+        member_id: &str,
+    ) -> Result<&mut GroupMetadata, ErrorCode> {
+        let group = self
+            .groups
+            .get_mut(&group_id.to_string())
+            .ok_or(ErrorCode::UnknownMemberId)?;
+
+        if group.is(GroupState::Dead) || !group.members.contains_key(&member_id.to_string()) {
+            return Err(ErrorCode::UnknownMemberId);
+        }
+
+        if generation_id != group.generation_id {
+            return Err(ErrorCode::IllegalGeneration);
+        }
+
+        Ok(group)
+    }
+
+    /// Expires members whose session timed out and completes rebalances past their deadline.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        let mut rebalancing = Vec::new();
+
+        for group in self.groups.values_mut() {
+            let expired: Vec<String> = group
+                .members
+                .values()
+                .filter(|m| m.has_expired(now))
+                .map(|m| m.member_id.clone())
+                .collect();
+
+            for member_id in &expired {
+                tracing::info!(
+                    "Member {} in group {} has failed its session timeout, removing it",
+                    member_id,
+                    group.group_id
+                );
+                group.remove_member(member_id);
+            }
+
+            if !expired.is_empty() {
+                Self::maybe_prepare_rebalance(
+                    group,
+                    &mut self.join_purgatory,
+                    "member session expired",
+                );
+                rebalancing.push(group.group_id.clone());
+            }
+        }
+
+        for group_id in &rebalancing {
+            self.join_purgatory
+                .check_and_complete(group_id, &mut self.groups);
+        }
+        self.join_purgatory.expire_timed_out(&mut self.groups);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn join_params(member_id: &str) -> JoinGroupParams {
+        JoinGroupParams {
+            group_id: "orders".to_string(),
+            member_id: member_id.to_string(),
+            client_id: "consumer".to_string(),
+            client_host: "/127.0.0.1".to_string(),
+            session_timeout_ms: 10_000,
+            rebalance_timeout_ms: 30_000,
+            protocol_type: "consumer".to_string(),
+            protocols: vec![("range".to_string(), b"sub".to_vec())],
+        }
+    }
+
+    #[test]
+    fn test_join_sync_heartbeat_flow() {
+        let mut coordinator = GroupCoordinator::new(1);
+
+        // First member joins alone: the rebalance completes immediately
+        let mut first = coordinator.join_group(join_params(""));
+        let first_join = first.try_recv().expect("first join should complete");
+        assert_eq!(first_join.error, ErrorCode::None);
+        assert_eq!(first_join.generation_id, 1);
+        assert_eq!(first_join.leader_id, first_join.member_id);
+        assert_eq!(first_join.members.len(), 1);
+
+        // A second member forces a new rebalance that waits for the leader to rejoin
+        let mut second = coordinator.join_group(join_params(""));
+        assert!(second.try_recv().is_err());
+        assert_eq!(
+            coordinator.heartbeat("orders", 1, &first_join.member_id),
+            ErrorCode::RebalanceInProgress
+        );
+
+        let mut rejoin = coordinator.join_group(join_params(&first_join.member_id));
+        let leader_join = rejoin.try_recv().expect("leader rejoin should complete");
+        let follower_join = second.try_recv().expect("follower join should complete");
+        assert_eq!(leader_join.generation_id, 2);
+        assert_eq!(leader_join.members.len(), 2);
+        assert!(follower_join.members.is_empty());
+
+        // Follower syncs first and waits for the leader's assignment
+        let mut follower_sync =
+            coordinator.sync_group("orders", 2, &follower_join.member_id, vec![]);
+        assert!(follower_sync.try_recv().is_err());
+
+        let assignments = vec![
+            (leader_join.member_id.clone(), b"p0".to_vec()),
+            (follower_join.member_id.clone(), b"p1".to_vec()),
+        ];
+        let mut leader_sync =
+            coordinator.sync_group("orders", 2, &leader_join.member_id, assignments);
+
+        assert_eq!(leader_sync.try_recv().unwrap().assignment, b"p0".to_vec());
+        assert_eq!(follower_sync.try_recv().unwrap().assignment, b"p1".to_vec());
+        assert_eq!(
+            coordinator.group("orders").unwrap().state,
+            GroupState::Stable
+        );
+        assert_eq!(
+            coordinator.heartbeat("orders", 2, &follower_join.member_id),
+            ErrorCode::None
+        );
+        assert_eq!(
+            coordinator.heartbeat("orders", 1, &follower_join.member_id),
+            ErrorCode::IllegalGeneration
+        );
+    }
+
+    const RETENTION_MS: i64 = 60_000;
+
+    fn offset(commit_timestamp: i64) -> OffsetAndMetadata {
+        OffsetAndMetadata {
+            offset: 42,
+            leader_epoch: -1,
+            metadata: String::new(),
+            commit_timestamp,
+        }
+    }
+
+    /// A broker leading the only `__consumer_offsets` partition.
+    async fn replica_manager() -> ReplicaManager {
+        let dir = std::env::temp_dir().join(format!("forge-offsets-{}", uuid::Uuid::new_v4()));
+        let mut replica_manager = ReplicaManager::new(1, &dir, 1);
+        let tp = TopicPartition::new(CONSUMER_OFFSETS_TOPIC, 0);
+        replica_manager
+            .create_partition(tp.clone(), vec![1])
+            .await
+            .unwrap();
+        replica_manager.become_leader(&tp, 0, vec![1]).unwrap();
+        replica_manager
+    }
+
+    #[tokio::test]
+    async fn test_offsets_without_a_group_expire_by_commit_time() {
+        let mut replica_manager = replica_manager().await;
+        let mut coordinator = GroupCoordinator::new(1);
+        let now = current_time_ms();
+        let (old, recent) = (
+            TopicPartition::new("events", 0),
+            TopicPartition::new("events", 1),
+        );
+        coordinator
+            .commit_offsets(
+                &mut replica_manager,
+                "standalone",
+                -1,
+                "",
+                vec![
+                    (old.clone(), offset(now - 2 * RETENTION_MS)),
+                    (recent.clone(), offset(now)),
+                ],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            coordinator
+                .cleanup_expired_offsets(&mut replica_manager, RETENTION_MS)
+                .await,
+            1
+        );
+        let left: Vec<TopicPartition> = coordinator
+            .fetch_offsets("standalone", None)
+            .into_iter()
+            .map(|(tp, _)| tp)
+            .collect();
+        assert_eq!(left, vec![recent]);
+    }
+
+    #[tokio::test]
+    async fn test_offsets_of_an_empty_group_expire_with_it() {
+        let mut replica_manager = replica_manager().await;
+        let mut coordinator = GroupCoordinator::new(1);
+        let join = coordinator
+            .join_group(join_params(""))
+            .try_recv()
+            .expect("a lone member's join completes");
+        assert_eq!(
+            coordinator.leave_group("orders", &join.member_id),
+            ErrorCode::None
+        );
+        assert!(coordinator.group("orders").unwrap().is(GroupState::Empty));
+        // Committed long ago, but the group only just emptied
+        coordinator
+            .commit_offsets(
+                &mut replica_manager,
+                "orders",
+                -1,
+                "",
+                vec![(TopicPartition::new("events", 0), offset(0))],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            coordinator
+                .cleanup_expired_offsets(&mut replica_manager, RETENTION_MS)
+                .await,
+            0
+        );
+
+        coordinator
+            .groups
+            .get_mut(&"orders".to_string())
+            .unwrap()
+            .current_state_timestamp -= RETENTION_MS;
+        assert_eq!(
+            coordinator
+                .cleanup_expired_offsets(&mut replica_manager, RETENTION_MS)
+                .await,
+            1
+        );
+        assert!(coordinator.fetch_offsets("orders", None).is_empty());
+        assert!(coordinator.group("orders").is_none());
+    }
+}
@@ -0,0 +1,818 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::application::group::GroupState;
+use crate::application::group_coordinator::{GroupCoordinator, JoinGroupParams};
+use crate::application::metadata_listener::BrokerMetadataListener;
+use crate::application::replica_manager::ReplicaManager;
+use crate::application::request_context::RequestContext;
+use crate::core::domain::acl::{AclOperation, Resource, ResourceType};
+use crate::core::domain::group_records::OffsetAndMetadata;
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::Authorizer;
+use crate::protocol::describe_groups::{
+    AUTHORIZED_OPERATIONS_OMITTED, DescribeGroupsRequest, DescribeGroupsResponse, DescribedGroup,
+    DescribedGroupMember,
+};
+use crate::protocol::find_coordinator::{
+    COORDINATOR_TYPE_GROUP, COORDINATOR_TYPE_TRANSACTION, FindCoordinatorRequest,
+    FindCoordinatorResponse,
+};
+use crate::protocol::heartbeat::{HeartbeatRequest, HeartbeatResponse};
+use crate::protocol::join_group::{JoinGroupRequest, JoinGroupResponse, JoinGroupResponseMember};
+use crate::protocol::leave_group::{LeaveGroupRequest, LeaveGroupResponse};
+use crate::protocol::list_groups::{ListGroupsRequest, ListGroupsResponse, ListedGroup};
+use crate::protocol::offset_commit::{
+    OffsetCommitPartitionResponse, OffsetCommitRequest, OffsetCommitResponse,
+    OffsetCommitTopicResponse,
+};
+use crate::protocol::offset_fetch::{
+    OffsetFetchPartitionResponse, OffsetFetchRequest, OffsetFetchResponse, OffsetFetchTopicResponse,
+};
+use crate::protocol::sync_group::{SyncGroupRequest, SyncGroupResponse};
+use crate::protocol::txn_offset_commit::{TxnOffsetCommitRequest, TxnOffsetCommitResponse};
+use crate::shared::constants::{
+    CONSUMER_OFFSETS_TOPIC, DEFAULT_TRANSACTION_STATE_PARTITIONS, TRANSACTION_STATE_TOPIC,
+};
+use crate::shared::hash::internal_topic_partition_for;
+use crate::shared::time::current_time_ms;
+
+/// Serves the group APIs: finding a group's coordinator, membership, listing and describing
+/// groups, and committing and fetching their offsets. Lock order: coordinator, then replica manager.
+pub struct GroupHandler {
+    coordinator: Arc<Mutex<GroupCoordinator>>,
+    replica_manager: Arc<Mutex<ReplicaManager>>,
+    /// Knows which broker leads each internal topic partition, and where it listens.
+    metadata: Arc<Mutex<BrokerMetadataListener>>,
+    authorizer: Option<Arc<dyn Authorizer>>,
+}
+
+impl GroupHandler {
+    pub fn new(
+        coordinator: Arc<Mutex<GroupCoordinator>>,
+        replica_manager: Arc<Mutex<ReplicaManager>>,
+        metadata: Arc<Mutex<BrokerMetadataListener>>,
+        authorizer: Option<Arc<dyn Authorizer>>,
+    ) -> Self {
+        Self {
+            coordinator,
+            replica_manager,
+            metadata,
+            authorizer,
+        }
+    }
+
+    async fn authorize(
+        &self,
+        context: &RequestContext,
+        operation: AclOperation,
+        resource_type: ResourceType,
+        name: &str,
+    ) -> bool {
+        context
+            .authorize(
+                self.authorizer.as_ref(),
+                operation,
+                &Resource::new(resource_type, name),
+            )
+            .await
+    }
+
+    /// The coordinator is the leader of the internal topic partition owning the key. Needs
+    /// Describe on the group or transactional id.
+    pub async fn find_coordinator(
+        &self,
+        context: &RequestContext,
+        request: FindCoordinatorRequest,
+    ) -> FindCoordinatorResponse {
+        let error_response = |error: ErrorCode| FindCoordinatorResponse {
+            throttle_time_ms: 0,
+            error_code: error.code(),
+            error_message: None,
+            node_id: -1,
+            host: String::new(),
+            port: -1,
+        };
+
+        let (resource_type, denied, topic, partitions) = match request.key_type {
+            COORDINATOR_TYPE_GROUP => (
+                ResourceType::Group,
+                ErrorCode::GroupAuthorizationFailed,
+                CONSUMER_OFFSETS_TOPIC,
+                self.coordinator
+                    .lock()
+                    .await
+                    .metadata_manager
+                    .offsets_topic_partitions,
+            ),
+            COORDINATOR_TYPE_TRANSACTION => (
+                ResourceType::TransactionalId,
+                ErrorCode::TransactionalIdAuthorizationFailed,
+                TRANSACTION_STATE_TOPIC,
+                DEFAULT_TRANSACTION_STATE_PARTITIONS,
+            ),
+            _ => return error_response(ErrorCode::InvalidRequest),
+        };
+        if !self
+            .authorize(context, AclOperation::Describe, resource_type, &request.key)
+            .await
+        {
+            return error_response(denied);
+        }
+
+        let partition = internal_topic_partition_for(&request.key, partitions);
+        let listener = self.metadata.lock().await;
+        let metadata = &listener.metadata;
+        let coordinator = metadata
+            .partition(topic, partition)
+            .map(|partition| partition.leader)
+            .filter(|leader| metadata.is_broker_alive(*leader))
+            .and_then(|leader| metadata.brokers.get(&leader));
+        match coordinator {
+            Some(broker) => FindCoordinatorResponse {
+                throttle_time_ms: 0,
+                error_code: ErrorCode::None.code(),
+                error_message: None,
+                node_id: broker.broker_id,
+                host: broker.host.clone(),
+                port: broker.port,
+            },
+            None => error_response(ErrorCode::CoordinatorNotAvailable),
+        }
+    }
+
+    /// Needs Read on the group and on each topic.
+    pub async fn offset_commit(
+        &self,
+        context: &RequestContext,
+        request: OffsetCommitRequest,
+    ) -> OffsetCommitResponse {
+        let group_authorized = self
+            .authorize(
+                context,
+                AclOperation::Read,
+                ResourceType::Group,
+                &request.group_id,
+            )
+            .await;
+
+        let mut errors = Vec::with_capacity(request.topics.len());
+        let mut offsets = Vec::new();
+        let commit_timestamp = current_time_ms();
+        for topic in &request.topics {
+            let error = if !group_authorized {
+                ErrorCode::GroupAuthorizationFailed
+            } else if !self
+                .authorize(
+                    context,
+                    AclOperation::Read,
+                    ResourceType::Topic,
+                    &topic.name,
+                )
+                .await
+            {
+                ErrorCode::TopicAuthorizationFailed
+            } else {
+                offsets.extend(topic.partitions.iter().map(|partition| {
+                    (
+                        TopicPartition::new(topic.name.clone(), partition.partition_index),
+                        OffsetAndMetadata {
+                            offset: partition.committed_offset,
+                            leader_epoch: partition.committed_leader_epoch,
+                            metadata: partition.committed_metadata.clone().unwrap_or_default(),
+                            commit_timestamp,
+                        },
+                    )
+                }));
+                ErrorCode::None
+            };
+            errors.push(error);
+        }
+
+        // Authorized partitions share the outcome of one write
+        let commit_error = if offsets.is_empty() {
+            ErrorCode::None
+        } else {
+            let mut coordinator = self.coordinator.lock().await;
+            let mut replica_manager = self.replica_manager.lock().await;
+            let result = match coordinator
+                .ensure_loaded(&mut replica_manager, &request.group_id)
+                .await
+            {
+                Ok(()) => {
+                    coordinator
+                        .commit_offsets(
+                            &mut replica_manager,
+                            &request.group_id,
+                            request.generation_id,
+                            &request.member_id,
+                            offsets,
+                        )
+                        .await
+                }
+                Err(error) => Err(error),
+            };
+            result.err().unwrap_or(ErrorCode::None)
+        };
+
+        let topics = request
+            .topics
+            .into_iter()
+            .zip(errors)
+            .map(|(topic, error)| {
+                let error = if error == ErrorCode::None {
+                    commit_error
+                } else {
+                    error
+                };
+                OffsetCommitTopicResponse {
+                    name: topic.name,
+                    partitions: topic
+                        .partitions
+                        .iter()
+                        .map(|partition| OffsetCommitPartitionResponse {
+                            partition_index: partition.partition_index,
+                            error_code: error.code(),
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        OffsetCommitResponse {
+            throttle_time_ms: 0,
+            topics,
+        }
+    }
+
+    /// Commits offsets in a producer's transaction. Needs Write on the transactional id, and
+    /// Read on the group and on each topic.
+    pub async fn txn_offset_commit(
+        &self,
+        context: &RequestContext,
+        request: TxnOffsetCommitRequest,
+    ) -> TxnOffsetCommitResponse {
+        let authorization_error = if !self
+            .authorize(
+                context,
+                AclOperation::Write,
+                ResourceType::TransactionalId,
+                &request.transactional_id,
+            )
+            .await
+        {
+            Some(ErrorCode::TransactionalIdAuthorizationFailed)
+        } else if !self
+            .authorize(
+                context,
+                AclOperation::Read,
+                ResourceType::Group,
+                &request.group_id,
+            )
+            .await
+        {
+            Some(ErrorCode::GroupAuthorizationFailed)
+        } else {
+            None
+        };
+
+        let mut errors = Vec::with_capacity(request.topics.len());
+        let mut offsets = Vec::new();
+        let commit_timestamp = current_time_ms();
+        for topic in &request.topics {
+            let error = if let Some(error) = authorization_error {
+                error
+            } else if !self
+                .authorize(
+                    context,
+                    AclOperation::Read,
+                    ResourceType::Topic,
+                    &topic.name,
+                )
+                .await
+            {
+                ErrorCode::TopicAuthorizationFailed
+            } else {
+                offsets.extend(topic.partitions.iter().map(|partition| {
+                    (
+                        TopicPartition::new(topic.name.clone(), partition.partition_index),
+                        OffsetAndMetadata {
+                            offset: partition.committed_offset,
+                            leader_epoch: partition.committed_leader_epoch,
+                            metadata: partition.committed_metadata.clone().unwrap_or_default(),
+                            commit_timestamp,
+                        },
+                    )
+                }));
+                ErrorCode::None
+            };
+            errors.push(error);
+        }
+
+        let commit_error = if offsets.is_empty() {
+            ErrorCode::None
+        } else {
+            let mut coordinator = self.coordinator.lock().await;
+            let mut replica_manager = self.replica_manager.lock().await;
+            let result = match coordinator
+                .ensure_loaded(&mut replica_manager, &request.group_id)
+                .await
+            {
+                Ok(()) => {
+                    coordinator
+                        .commit_transactional_offsets(
+                            &mut replica_manager,
+                            &request.group_id,
+                            request.producer_id,
+                            request.producer_epoch,
+                            offsets,
+                        )
+                        .await
+                }
+                Err(error) => Err(error),
+            };
+            result.err().unwrap_or(ErrorCode::None)
+        };
+
+        let topics = request
+            .topics
+            .into_iter()
+            .zip(errors)
+            .map(|(topic, error)| {
+                let error = if error == ErrorCode::None {
+                    commit_error
+                } else {
+                    error
+                };
+                OffsetCommitTopicResponse {
+                    name: topic.name,
+                    partitions: topic
+                        .partitions
+                        .iter()
+                        .map(|partition| OffsetCommitPartitionResponse {
+                            partition_index: partition.partition_index,
+                            error_code: error.code(),
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        TxnOffsetCommitResponse {
+            throttle_time_ms: 0,
+            topics,
+        }
+    }
+
+    /// Needs Describe on the group and on each topic; topics the principal may not describe
+    /// are left out when every committed offset is requested.
+    pub async fn offset_fetch(
+        &self,
+        context: &RequestContext,
+        request: OffsetFetchRequest,
+    ) -> OffsetFetchResponse {
+        let error_response = |error: ErrorCode| OffsetFetchResponse {
+            throttle_time_ms: 0,
+            topics: vec![],
+            error_code: error.code(),
+        };
+        if !self
+            .authorize(
+                context,
+                AclOperation::Describe,
+                ResourceType::Group,
+                &request.group_id,
+            )
+            .await
+        {
+            return error_response(ErrorCode::GroupAuthorizationFailed);
+        }
+
+        let committed = {
+            let mut coordinator = self.coordinator.lock().await;
+            let mut replica_manager = self.replica_manager.lock().await;
+            if let Err(error) = coordinator
+                .ensure_loaded(&mut replica_manager, &request.group_id)
+                .await
+            {
+                return error_response(error);
+            }
+            coordinator.fetch_offsets(&request.group_id, None)
+        };
+
+        let topics_listed = request.topics.is_some();
+        let requested: Vec<(String, Vec<i32>)> = match request.topics {
+            Some(topics) => topics
+                .into_iter()
+                .map(|topic| (topic.name, topic.partition_indexes))
+                .collect(),
+            None => {
+                let mut topics: Vec<(String, Vec<i32>)> = Vec::new();
+                for (topic_partition, _) in &committed {
+                    match topics.last_mut() {
+                        Some((name, partitions)) if *name == topic_partition.topic => {
+                            partitions.push(topic_partition.partition)
+                        }
+                        _ => topics.push((
+                            topic_partition.topic.clone(),
+                            vec![topic_partition.partition],
+                        )),
+                    }
+                }
+                topics
+            }
+        };
+
+        let mut topics = Vec::with_capacity(requested.len());
+        for (name, partition_indexes) in requested {
+            let error = if self
+                .authorize(context, AclOperation::Describe, ResourceType::Topic, &name)
+                .await
+            {
+                ErrorCode::None
+            } else if topics_listed {
+                ErrorCode::TopicAuthorizationFailed
+            } else {
+                continue;
+            };
+            let partitions = partition_indexes
+                .into_iter()
+                .map(|partition_index| {
+                    let offset = committed
+                        .iter()
+                        .find(|(topic_partition, _)| {
+                            topic_partition.topic == name
+                                && topic_partition.partition == partition_index
+                        })
+                        .map(|(_, offset)| offset)
+                        .filter(|_| error == ErrorCode::None);
+                    OffsetFetchPartitionResponse {
+                        partition_index,
+                        committed_offset: offset.map_or(-1, |offset| offset.offset),
+                        committed_leader_epoch: offset.map_or(-1, |offset| offset.leader_epoch),
+                        metadata: Some(
+                            offset
+                                .map(|offset| offset.metadata.clone())
+                                .unwrap_or_default(),
+                        ),
+                        error_code: error.code(),
+                    }
+                })
+                .collect();
+            topics.push(OffsetFetchTopicResponse { name, partitions });
+        }
+
+        OffsetFetchResponse {
+            throttle_time_ms: 0,
+            topics,
+            error_code: ErrorCode::None.code(),
+        }
+    }
+
+    /// Needs Read on the group. Resolves once the group's rebalance completes, which may be
+    /// as long as the rebalance timeout of its slowest member.
+    pub async fn join_group(
+        &self,
+        context: &RequestContext,
+        request: JoinGroupRequest,
+    ) -> JoinGroupResponse {
+        let error_response = |member_id: String, error: ErrorCode| JoinGroupResponse {
+            throttle_time_ms: 0,
+            error_code: error.code(),
+            generation_id: -1,
+            protocol_name: String::new(),
+            leader: String::new(),
+            member_id,
+            members: vec![],
+        };
+        if !self
+            .authorize(
+                context,
+                AclOperation::Read,
+                ResourceType::Group,
+                &request.group_id,
+            )
+            .await
+        {
+            return error_response(request.member_id, ErrorCode::GroupAuthorizationFailed);
+        }
+
+        // The coordinator answers through the channel, so the lock is not held while waiting
+        let joined = {
+            let mut coordinator = self.coordinator.lock().await;
+            let mut replica_manager = self.replica_manager.lock().await;
+            if let Err(error) = coordinator
+                .ensure_loaded(&mut replica_manager, &request.group_id)
+                .await
+            {
+                return error_response(request.member_id, error);
+            }
+            coordinator.join_group(JoinGroupParams {
+                group_id: request.group_id,
+                member_id: request.member_id.clone(),
+                client_id: context.client_id.clone(),
+                client_host: context.client_host.clone(),
+                session_timeout_ms: request.session_timeout_ms,
+                rebalance_timeout_ms: request.rebalance_timeout_ms,
+                protocol_type: request.protocol_type,
+                protocols: request
+                    .protocols
+                    .into_iter()
+                    .map(|protocol| (protocol.name, protocol.metadata))
+                    .collect(),
+            })
+        };
+        let Ok(result) = joined.await else {
+            return error_response(request.member_id, ErrorCode::UnknownMemberId);
+        };
+        JoinGroupResponse {
+            throttle_time_ms: 0,
+            error_code: result.error.code(),
+            generation_id: result.generation_id,
+            protocol_name: result.protocol_name.unwrap_or_default(),
+            leader: result.leader_id,
+            member_id: result.member_id,
+            members: result
+                .members
+                .into_iter()
+                .map(|(member_id, metadata)| JoinGroupResponseMember {
+                    member_id,
+                    metadata,
+                })
+                .collect(),
+        }
+    }
+
+    /// Needs Read on the group. Followers wait for the leader's assignment, which is written
+    /// to `__consumer_offsets` before the leader is answered.
+    pub async fn sync_group(
+        &self,
+        context: &RequestContext,
+        request: SyncGroupRequest,
+    ) -> SyncGroupResponse {
+        let error_response = |error: ErrorCode| SyncGroupResponse {
+            throttle_time_ms: 0,
+            error_code: error.code(),
+            assignment: vec![],
+        };
+        if !self
+            .authorize(
+                context,
+                AclOperation::Read,
+                ResourceType::Group,
+                &request.group_id,
+            )
+            .await
+        {
+            return error_response(ErrorCode::GroupAuthorizationFailed);
+        }
+
+        let (synced, is_leader) = {
+            let mut coordinator = self.coordinator.lock().await;
+            let mut replica_manager = self.replica_manager.lock().await;
+            if let Err(error) = coordinator
+                .ensure_loaded(&mut replica_manager, &request.group_id)
+                .await
+            {
+                return error_response(error);
+            }
+            let is_leader = coordinator
+                .group(&request.group_id)
+                .is_some_and(|group| group.leader_id.as_ref() == Some(&request.member_id));
+            let synced = coordinator.sync_group(
+                &request.group_id,
+                request.generation_id,
+                &request.member_id,
+                request
+                    .assignments
+                    .into_iter()
+                    .map(|assignment| (assignment.member_id, assignment.assignment))
+                    .collect(),
+            );
+            (synced, is_leader)
+        };
+        let Ok(result) = synced.await else {
+            return error_response(ErrorCode::RebalanceInProgress);
+        };
+
+        if is_leader && result.error == ErrorCode::None {
+            let mut coordinator = self.coordinator.lock().await;
+            let mut replica_manager = self.replica_manager.lock().await;
+            if let Err(error) = coordinator
+                .persist_group(&mut replica_manager, &request.group_id)
+                .await
+            {
+                return error_response(error);
+            }
+        }
+        SyncGroupResponse {
+            throttle_time_ms: 0,
+            error_code: result.error.code(),
+            assignment: result.assignment,
+        }
+    }
+
+    /// Needs Read on the group.
+    pub async fn heartbeat(
+        &self,
+        context: &RequestContext,
+        request: HeartbeatRequest,
+    ) -> HeartbeatResponse {
+        let error = if !self
+            .authorize(
+                context,
+                AclOperation::Read,
+                ResourceType::Group,
+                &request.group_id,
+            )
+            .await
+        {
+            ErrorCode::GroupAuthorizationFailed
+        } else {
+            let mut coordinator = self.coordinator.lock().await;
+            let mut replica_manager = self.replica_manager.lock().await;
+            match coordinator
+                .ensure_loaded(&mut replica_manager, &request.group_id)
+                .await
+            {
+                Ok(()) => coordinator.heartbeat(
+                    &request.group_id,
+                    request.generation_id,
+                    &request.member_id,
+                ),
+                Err(error) => error,
+            }
+        };
+        HeartbeatResponse {
+            throttle_time_ms: 0,
+            error_code: error.code(),
+        }
+    }
+
+    /// Needs Read on the group.
+    pub async fn leave_group(
+        &self,
+        context: &RequestContext,
+        request: LeaveGroupRequest,
+    ) -> LeaveGroupResponse {
+        let error = if !self
+            .authorize(
+                context,
+                AclOperation::Read,
+                ResourceType::Group,
+                &request.group_id,
+            )
+            .await
+        {
+            ErrorCode::GroupAuthorizationFailed
+        } else {
+            let mut coordinator = self.coordinator.lock().await;
+            let mut replica_manager = self.replica_manager.lock().await;
+            match coordinator
+                .ensure_loaded(&mut replica_manager, &request.group_id)
+                .await
+            {
+                Ok(()) => coordinator.leave_group(&request.group_id, &request.member_id),
+                Err(error) => error,
+            }
+        };
+        LeaveGroupResponse {
+            throttle_time_ms: 0,
+            error_code: error.code(),
+        }
+    }
+
+    /// Lists the groups this broker coordinates: all of them with Describe on the cluster,
+    /// otherwise the ones the principal may describe.
+    pub async fn list_groups(
+        &self,
+        context: &RequestContext,
+        _request: ListGroupsRequest,
+    ) -> ListGroupsResponse {
+        let listed = {
+            let mut coordinator = self.coordinator.lock().await;
+            let mut replica_manager = self.replica_manager.lock().await;
+            let partitions = coordinator.ensure_all_loaded(&mut replica_manager).await;
+            coordinator.list_groups(&partitions)
+        };
+
+        let authorizer = self.authorizer.as_ref();
+        let describe_cluster = context
+            .is_authorized(authorizer, AclOperation::Describe, &Resource::cluster())
+            .await;
+        let mut groups = Vec::with_capacity(listed.len());
+        for (group_id, protocol_type, _) in listed {
+            if describe_cluster
+                || context
+                    .is_authorized(
+                        authorizer,
+                        AclOperation::Describe,
+                        &Resource::new(ResourceType::Group, group_id.as_str()),
+                    )
+                    .await
+            {
+                groups.push(ListedGroup {
+                    group_id,
+                    protocol_type,
+                });
+            }
+        }
+        ListGroupsResponse {
+            throttle_time_ms: 0,
+            error_code: ErrorCode::None.code(),
+            groups,
+        }
+    }
+
+    /// Needs Describe on each group. Authorized operations are not computed, so they are
+    /// always reported as omitted.
+    pub async fn describe_groups(
+        &self,
+        context: &RequestContext,
+        request: DescribeGroupsRequest,
+    ) -> DescribeGroupsResponse {
+        let mut groups = Vec::with_capacity(request.groups.len());
+        for group_id in request.groups {
+            let described = if self
+                .authorize(
+                    context,
+                    AclOperation::Describe,
+                    ResourceType::Group,
+                    &group_id,
+                )
+                .await
+            {
+                self.describe_group(group_id).await
+            } else {
+                Self::group_error(group_id, ErrorCode::GroupAuthorizationFailed)
+            };
+            groups.push(described);
+        }
+        DescribeGroupsResponse {
+            throttle_time_ms: 0,
+            groups,
+        }
+    }
+
+    async fn describe_group(&self, group_id: String) -> DescribedGroup {
+        let mut coordinator = self.coordinator.lock().await;
+        {
+            let mut replica_manager = self.replica_manager.lock().await;
+            if let Err(error) = coordinator
+                .ensure_loaded(&mut replica_manager, &group_id)
+                .await
+            {
+                return Self::group_error(group_id, error);
+            }
+        }
+
+        let Some(group) = coordinator.group(&group_id) else {
+            let state = if coordinator.has_offsets(&group_id) {
+                GroupState::Empty
+            } else {
+                GroupState::Dead
+            };
+            return DescribedGroup {
+                group_state: state.name().to_string(),
+                ..Self::group_error(group_id, ErrorCode::None)
+            };
+        };
+
+        let protocol_name = group.protocol_name.clone().unwrap_or_default();
+        let members = group
+            .members
+            .values()
+            .map(|member| DescribedGroupMember {
+                member_id: member.member_id.clone(),
+                group_instance_id: None,
+                client_id: member.client_id.clone(),
+                client_host: member.client_host.clone(),
+                member_metadata: member
+                    .protocol_metadata(&protocol_name)
+                    .map(<[u8]>::to_vec)
+                    .unwrap_or_default(),
+                member_assignment: member.assignment.clone(),
+            })
+            .collect();
+        DescribedGroup {
+            error_code: ErrorCode::None.code(),
+            group_id,
+            group_state: group.state.name().to_string(),
+            protocol_type: group.protocol_type.clone().unwrap_or_default(),
+            protocol_data: protocol_name,
+            members,
+            authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+        }
+    }
+
+    fn group_error(group_id: String, error: ErrorCode) -> DescribedGroup {
+        DescribedGroup {
+            error_code: error.code(),
+            group_id,
+            group_state: String::new(),
+            protocol_type: String::new(),
+            protocol_data: String::new(),
+            members: vec![],
+            authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+        }
+    }
+}
@@ -0,0 +1,390 @@
+use bytes::BytesMut;
+
+use crate::application::group::GroupMetadata;
+use crate::application::replica_manager::{ACKS_ALL, ReplicaManager};
+use crate::core::domain::control_record::{ControlRecordType, EndTransactionMarker};
+use crate::core::domain::group_records::{
+    GroupMetadataValue, GroupRecordKey, MemberMetadataValue, OffsetAndMetadata,
+};
+use crate::core::domain::record::Record;
+use crate::core::domain::record_batch::{RecordBatch, TRANSACTIONAL_FLAG_MASK};
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::protocol::fetch::ISOLATION_READ_UNCOMMITTED;
+use crate::protocol::types::Type;
+use crate::shared::collections::FlatMap;
+use crate::shared::constants::CONSUMER_OFFSETS_TOPIC;
+use crate::shared::hash::internal_topic_partition_for;
+use crate::shared::time::current_time_ms;
+
+const LOAD_FETCH_MAX_BYTES: usize = 5 * 1024 * 1024;
+
+/// Persists committed offsets and group metadata to `__consumer_offsets` and keeps
+/// the latest value per key cached in memory.
+pub struct GroupMetadataManager {
+    pub offsets_topic_partitions: i32,
+    offsets: FlatMap<(String, TopicPartition), OffsetAndMetadata>,
+    /// Offsets committed in a transaction, by producer id, until its marker says whether
+    /// they count.
+    pending_offsets: FlatMap<i64, Vec<(String, TopicPartition, OffsetAndMetadata)>>,
+    groups: FlatMap<String, GroupMetadataValue>,
+}
+
+impl GroupMetadataManager {
+    pub fn new(offsets_topic_partitions: i32) -> Self {
+        Self {
+            offsets_topic_partitions,
+            offsets: FlatMap::new(),
+            pending_offsets: FlatMap::new(),
+            groups: FlatMap::new(),
+        }
+    }
+
+    pub fn partition_for(&self, group_id: &str) -> i32 {
+        internal_topic_partition_for(group_id, self.offsets_topic_partitions)
+    }
+
+    pub fn offsets_topic_partition(&self, group_id: &str) -> TopicPartition {
+        TopicPartition::new(CONSUMER_OFFSETS_TOPIC, self.partition_for(group_id))
+    }
+
+    pub fn group_metadata(&self, group_id: &str) -> Option<&GroupMetadataValue> {
+        self.groups.get(&group_id.to_string())
+    }
+
+    pub fn groups(&self) -> impl Iterator<Item = (&String, &GroupMetadataValue)> {
+        self.groups.iter()
+    }
+
+    pub fn group_ids_with_offsets(&self) -> Vec<String> {
+        let mut group_ids: Vec<String> = self.offsets.keys().map(|(g, _)| g.clone()).collect();
+        group_ids.dedup();
+        group_ids
+    }
+
+    pub fn fetch_offsets(
+        &self,
+        group_id: &str,
+        partitions: Option<&[TopicPartition]>,
+    ) -> Vec<(TopicPartition, OffsetAndMetadata)> {
+        self.offsets
+            .iter()
+            .filter(|((group, tp), _)| {
+                group == group_id && partitions.is_none_or(|filter| filter.contains(tp))
+            })
+            .map(|((_, tp), offset)| (tp.clone(), offset.clone()))
+            .collect()
+    }
+
+    pub async fn store_offsets(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        group_id: &str,
+        offsets: Vec<(TopicPartition, OffsetAndMetadata)>,
+    ) -> Result<(), ErrorCode> {
+        let batch = Self::batch(Self::offset_records(group_id, &offsets));
+        self.append(replica_manager, group_id, batch).await?;
+
+        for (tp, offset) in offsets {
+            self.offsets.insert((group_id.to_string(), tp), offset);
+        }
+        Ok(())
+    }
+
+    /// Writes offsets as part of the producer's transaction; they take effect once the
+    /// transaction commits and are dropped if it aborts.
+    pub async fn store_transactional_offsets(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        group_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        offsets: Vec<(TopicPartition, OffsetAndMetadata)>,
+    ) -> Result<(), ErrorCode> {
+        let mut batch = Self::batch(Self::offset_records(group_id, &offsets));
+        batch.attributes |= TRANSACTIONAL_FLAG_MASK;
+        batch.producer_id = producer_id;
+        batch.producer_epoch = producer_epoch;
+        self.append(replica_manager, group_id, batch).await?;
+
+        let offsets = offsets
+            .into_iter()
+            .map(|(tp, offset)| (group_id.to_string(), tp, offset));
+        match self.pending_offsets.get_mut(&producer_id) {
+            Some(pending) => pending.extend(offsets),
+            None => {
+                self.pending_offsets.insert(producer_id, offsets.collect());
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies or drops the offsets the producer's transaction committed, now that its marker
+    /// is in the log.
+    pub fn complete_transactional_offsets(&mut self, producer_id: i64, commit: bool) {
+        let Some(pending) = self.pending_offsets.remove(&producer_id) else {
+            return;
+        };
+        if commit {
+            for (group_id, tp, offset) in pending {
+                self.offsets.insert((group_id, tp), offset);
+            }
+        }
+    }
+
+    pub async fn store_group(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        group: &GroupMetadata,
+    ) -> Result<(), ErrorCode> {
+        let value = Self::group_metadata_value(group);
+        let key = GroupRecordKey::GroupMetadata {
+            group_id: group.group_id.clone(),
+        };
+
+        self.append(
+            replica_manager,
+            &group.group_id,
+            Self::batch(vec![(key, Some(Self::encode_value(&value)))]),
+        )
+        .await?;
+
+        self.groups.insert(group.group_id.clone(), value);
+        Ok(())
+    }
+
+    pub async fn remove_offsets(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        group_id: &str,
+        partitions: &[TopicPartition],
+    ) -> Result<(), ErrorCode> {
+        let tombstones = partitions
+            .iter()
+            .map(|tp| {
+                let key = GroupRecordKey::OffsetCommit {
+                    group_id: group_id.to_string(),
+                    topic_partition: tp.clone(),
+                };
+                (key, None)
+            })
+            .collect();
+
+        self.append(replica_manager, group_id, Self::batch(tombstones))
+            .await?;
+
+        for tp in partitions {
+            self.offsets.remove(&(group_id.to_string(), tp.clone()));
+        }
+        Ok(())
+    }
+
+    pub async fn remove_group(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        group_id: &str,
+    ) -> Result<(), ErrorCode> {
+        let key = GroupRecordKey::GroupMetadata {
+            group_id: group_id.to_string(),
+        };
+        self.append(replica_manager, group_id, Self::batch(vec![(key, None)]))
+            .await?;
+        self.groups.remove(&group_id.to_string());
+        Ok(())
+    }
+
+    fn group_metadata_value(group: &GroupMetadata) -> GroupMetadataValue {
+        let protocol_name = group.protocol_name.clone().unwrap_or_default();
+        GroupMetadataValue {
+            protocol_type: group.protocol_type.clone().unwrap_or_default(),
+            generation_id: group.generation_id,
+            leader_id: group.leader_id.clone().unwrap_or_default(),
+            current_state_timestamp: current_time_ms(),
+            members: group
+                .members
+                .values()
+                .map(|m| MemberMetadataValue {
+                    member_id: m.member_id.clone(),
+                    client_id: m.client_id.clone(),
+                    client_host: m.client_host.clone(),
+                    rebalance_timeout_ms: m.rebalance_timeout.as_millis() as i32,
+                    session_timeout_ms: m.session_timeout.as_millis() as i32,
+                    subscription: m
+                        .protocol_metadata(&protocol_name)
+                        .unwrap_or_default()
+                        .to_vec(),
+                    assignment: m.assignment.clone(),
+                })
+                .collect(),
+            protocol_name,
+        }
+    }
+
+    fn encode_value<T: Type>(value: &T) -> Vec<u8> {
+        let mut buf = BytesMut::new();
+        value.encode(&mut buf);
+        buf.to_vec()
+    }
+
+    fn offset_records(
+        group_id: &str,
+        offsets: &[(TopicPartition, OffsetAndMetadata)],
+    ) -> Vec<(GroupRecordKey, Option<Vec<u8>>)> {
+        offsets
+            .iter()
+            .map(|(tp, offset)| {
+                let key = GroupRecordKey::OffsetCommit {
+                    group_id: group_id.to_string(),
+                    topic_partition: tp.clone(),
+                };
+                (key, Some(Self::encode_value(offset)))
+            })
+            .collect()
+    }
+
+    fn batch(records: Vec<(GroupRecordKey, Option<Vec<u8>>)>) -> RecordBatch {
+        let records = records
+            .into_iter()
+            .enumerate()
+            .map(|(i, (key, value))| Record::new(i as i32, Some(Self::encode_value(&key)), value))
+            .collect();
+        RecordBatch::new(current_time_ms(), records)
+    }
+
+    async fn append(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        group_id: &str,
+        batch: RecordBatch,
+    ) -> Result<(), ErrorCode> {
+        let topic_partition = self.offsets_topic_partition(group_id);
+
+        match replica_manager
+            .append_records(&topic_partition, ACKS_ALL, batch)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(ErrorCode::UnknownTopicOrPartition | ErrorCode::NotLeaderOrFollower) => {
+                Err(ErrorCode::NotCoordinator)
+            }
+            Err(error) => {
+                tracing::error!(
+                    "Failed to write group {} records to {}: {}",
+                    group_id,
+                    topic_partition,
+                    error
+                );
+                Err(ErrorCode::CoordinatorNotAvailable)
+            }
+        }
+    }
+
+    /// Rebuilds the cache for one `__consumer_offsets` partition after this broker becomes its leader.
+    pub async fn load_partition(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        partition: i32,
+    ) -> Result<(), ErrorCode> {
+        let topic_partition = TopicPartition::new(CONSUMER_OFFSETS_TOPIC, partition);
+        let mut offset = replica_manager
+            .get_partition(&topic_partition)
+            .ok_or(ErrorCode::NotCoordinator)?
+            .log_start_offset();
+
+        loop {
+            let data = replica_manager
+                .fetch_records(
+                    &topic_partition,
+                    None,
+                    offset,
+                    LOAD_FETCH_MAX_BYTES,
+                    ISOLATION_READ_UNCOMMITTED,
+                )
+                .await?;
+            let batches = data.batches()?;
+            if batches.is_empty() {
+                break;
+            }
+
+            for batch in &batches {
+                if batch.is_control_batch() {
+                    if let Some(marker) = EndTransactionMarker::from_batch(batch) {
+                        self.complete_transactional_offsets(
+                            batch.producer_id,
+                            marker.control_type == ControlRecordType::Commit,
+                        );
+                    }
+                    offset = batch.last_offset() + 1;
+                    continue;
+                }
+                let producer_id = batch.is_transactional().then_some(batch.producer_id);
+                for record in &batch.records {
+                    if let Err(e) = self.apply_record(record, producer_id) {
+                        tracing::warn!(
+                            "Skipping malformed record in {} at batch offset {}: {}",
+                            topic_partition,
+                            batch.base_offset,
+                            e
+                        );
+                    }
+                }
+                offset = batch.base_offset + batch.last_offset_delta as i64 + 1;
+            }
+
+            if offset >= data.high_watermark {
+                break;
+            }
+        }
+
+        tracing::info!(
+            "Loaded group metadata from {} up to offset {}",
+            topic_partition,
+            offset
+        );
+        Ok(())
+    }
+
+    /// `producer_id` is set for records written in a transaction.
+    fn apply_record(&mut self, record: &Record, producer_id: Option<i64>) -> Result<(), String> {
+        let Some(key_bytes) = &record.key else {
+            return Err("Missing record key".to_string());
+        };
+        let key = GroupRecordKey::decode(&mut key_bytes.as_slice())?;
+
+        match key {
+            GroupRecordKey::OffsetCommit {
+                group_id,
+                topic_partition,
+            } => match &record.value {
+                Some(value) => {
+                    let offset = OffsetAndMetadata::decode(&mut value.as_slice())?;
+                    match producer_id {
+                        Some(producer_id) => match self.pending_offsets.get_mut(&producer_id) {
+                            Some(pending) => pending.push((group_id, topic_partition, offset)),
+                            None => {
+                                self.pending_offsets
+                                    .insert(producer_id, vec![(group_id, topic_partition, offset)]);
+                            }
+                        },
+                        None => {
+                            self.offsets.insert((group_id, topic_partition), offset);
+                        }
+                    }
+                }
+                None => {
+                    self.offsets.remove(&(group_id, topic_partition));
+                }
+            },
+            GroupRecordKey::GroupMetadata { group_id } => match &record.value {
+                Some(value) => {
+                    let metadata = GroupMetadataValue::decode(&mut value.as_slice())?;
+                    self.groups.insert(group_id, metadata);
+                }
+                None => {
+                    self.groups.remove(&group_id);
+                }
+            },
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::application::replica_manager::ReplicaManager;
+use crate::application::request_context::RequestContext;
+use crate::core::domain::acl::{AclOperation, Resource, ResourceType};
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::Authorizer;
+use crate::protocol::list_offsets::{
+    ListOffsetsPartitionResponse, ListOffsetsRequest, ListOffsetsResponse, ListOffsetsTopicResponse,
+};
+
+/// Resolves timestamps, or the earliest and latest sentinels, to offsets on partition leaders.
+pub struct ListOffsetsHandler {
+    replica_manager: Arc<Mutex<ReplicaManager>>,
+    authorizer: Option<Arc<dyn Authorizer>>,
+}
+
+impl ListOffsetsHandler {
+    pub fn new(
+        replica_manager: Arc<Mutex<ReplicaManager>>,
+        authorizer: Option<Arc<dyn Authorizer>>,
+    ) -> Self {
+        Self {
+            replica_manager,
+            authorizer,
+        }
+    }
+
+    /// Needs Describe on each topic.
+    pub async fn handle(
+        &self,
+        context: &RequestContext,
+        request: ListOffsetsRequest,
+    ) -> ListOffsetsResponse {
+        let mut authorized = Vec::with_capacity(request.topics.len());
+        for topic in &request.topics {
+            let resource = Resource::new(ResourceType::Topic, topic.name.as_str());
+            authorized.push(
+                context
+                    .authorize(self.authorizer.as_ref(), AclOperation::Describe, &resource)
+                    .await,
+            );
+        }
+
+        let mut topics = Vec::with_capacity(request.topics.len());
+        for (topic, topic_authorized) in request.topics.into_iter().zip(authorized) {
+            let mut partitions = Vec::with_capacity(topic.partitions.len());
+            for partition in topic.partitions {
+                let topic_partition =
+                    TopicPartition::new(topic.name.clone(), partition.partition_index);
+                let lookup = if topic_authorized {
+                    let current_leader_epoch = (partition.current_leader_epoch >= 0)
+                        .then_some(partition.current_leader_epoch);
+                    self.replica_manager.lock().await.prepare_list_offset(
+                        &topic_partition,
+                        current_leader_epoch,
+                        request.isolation_level,
+                    )
+                } else {
+                    Err(ErrorCode::TopicAuthorizationFailed)
+                };
+                // Answered from the partition's offsets and log task, without the lock
+                let result = match lookup {
+                    Ok(lookup) => lookup
+                        .resolve(partition.timestamp)
+                        .await
+                        .map(|(timestamp, offset)| (timestamp, offset, lookup.leader_epoch)),
+                    Err(error) => Err(error),
+                };
+
+                partitions.push(match result {
+                    Ok((timestamp, offset, leader_epoch)) => ListOffsetsPartitionResponse {
+                        partition_index: partition.partition_index,
+                        error_code: ErrorCode::None.code(),
+                        timestamp,
+                        offset,
+                        leader_epoch,
+                    },
+                    Err(error) => ListOffsetsPartitionResponse {
+                        partition_index: partition.partition_index,
+                        error_code: error.code(),
+                        timestamp: -1,
+                        offset: -1,
+                        leader_epoch: -1,
+                    },
+                });
+            }
+            topics.push(ListOffsetsTopicResponse {
+                name: topic.name,
+                partitions,
+            });
+        }
+
+        ListOffsetsResponse {
+            throttle_time_ms: 0,
+            topics,
+        }
+    }
+}
@@ -0,0 +1,463 @@
+//! Every partition log runs in a task of its own that takes commands from a bounded queue,
+//! so partitions append and read in parallel, and a slow disk under one partition holds up
+//! only that partition. The replica manager keeps the partition state; the task owns the
+//! files.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering};
+use tokio::sync::{mpsc, oneshot, watch};
+
+use crate::adapters::driven::storage::log::PartitionLog;
+use crate::config::LogConfig;
+use crate::core::domain::record_batch::{RecordBatch, Records};
+use crate::shared::scheduler::spawn_named;
+
+/// Commands a partition queues before senders wait for the log task.
+const QUEUE_CAPACITY: usize = 64;
+
+/// A partition's offsets and log size, readable without locking the partition. The log task
+/// moves all but the high watermark, which the partition owns.
+#[derive(Debug, Default)]
+pub struct LogOffsets {
+    log_start_offset: AtomicI64,
+    log_end_offset: AtomicI64,
+    high_watermark: AtomicI64,
+    size_bytes: AtomicU64,
+    segments: AtomicUsize,
+}
+
+impl LogOffsets {
+    pub fn log_start_offset(&self) -> i64 {
+        self.log_start_offset.load(Ordering::Acquire)
+    }
+
+    /// One past the last offset written.
+    pub fn log_end_offset(&self) -> i64 {
+        self.log_end_offset.load(Ordering::Acquire)
+    }
+
+    pub fn high_watermark(&self) -> i64 {
+        self.high_watermark.load(Ordering::Acquire)
+    }
+
+    pub fn set_high_watermark(&self, high_watermark: i64) {
+        self.high_watermark.store(high_watermark, Ordering::Release);
+    }
+
+    pub fn size_bytes(&self) -> u64 {
+        self.size_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn segments(&self) -> usize {
+        self.segments.load(Ordering::Relaxed)
+    }
+
+    fn update(&self, log: &PartitionLog) {
+        let size_bytes = log
+            .segments
+            .iter()
+            .map(|segment| segment.current_size as u64)
+            .sum();
+        self.size_bytes.store(size_bytes, Ordering::Relaxed);
+        self.segments.store(log.segments.len(), Ordering::Relaxed);
+        self.log_start_offset
+            .store(log.get_first_log_index(), Ordering::Release);
+        self.log_end_offset
+            .store(log.get_last_log_index() + 1, Ordering::Release);
+    }
+}
+
+type Reply<T> = oneshot::Sender<Result<T, String>>;
+
+enum LogCommand {
+    /// Answers with the bytes the batch took in the log.
+    Append {
+        batch: RecordBatch,
+        reply: Reply<u64>,
+    },
+    Read {
+        offset: i64,
+        max_bytes: usize,
+        max_offset: i64,
+        reply: Reply<Records>,
+    },
+    OffsetForTimestamp {
+        timestamp: i64,
+        reply: Reply<Option<(i64, i64)>>,
+    },
+    Truncate {
+        offset: i64,
+        reply: Reply<()>,
+    },
+    Flush {
+        reply: Reply<()>,
+    },
+    /// Stops the task once the commands queued before it are done.
+    Close {
+        reply: Reply<()>,
+    },
+}
+
+/// The answer to a queued command, which comes once the log task gets to it.
+pub struct LogReply<T>(oneshot::Receiver<Result<T, String>>);
+
+impl<T> LogReply<T> {
+    pub async fn wait(self) -> Result<T, String> {
+        self.0
+            .await
+            .unwrap_or_else(|_| Err("The log task stopped".to_string()))
+    }
+}
+
+/// Room for one append in a log's queue.
+pub struct AppendSlot<'a>(mpsc::Permit<'a, LogCommand>);
+
+impl AppendSlot<'_> {
+    /// Queues `batch`, whose base offset must be the log end offset once the commands ahead
+    /// of it are done. After an append fails, every later one does too until the log is
+    /// truncated or reopened.
+    pub fn append(self, batch: RecordBatch) -> LogReply<u64> {
+        let (reply, receiver) = oneshot::channel();
+        self.0.send(LogCommand::Append { batch, reply });
+        LogReply(receiver)
+    }
+}
+
+/// Sends commands to a partition's log task. Clones talk to the same task.
+#[derive(Clone)]
+pub struct LogHandle {
+    pub dir: PathBuf,
+    commands: mpsc::Sender<LogCommand>,
+    offsets: Arc<LogOffsets>,
+    config: Arc<watch::Sender<LogConfig>>,
+}
+
+impl LogHandle {
+    /// Moves `log` into a new task, which runs until `close` or until every handle is gone.
+    pub fn spawn(mut log: PartitionLog, config: LogConfig) -> Self {
+        let dir = log.dir.clone();
+        let (commands, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        let offsets = Arc::new(LogOffsets::default());
+        offsets.update(&log);
+        configure(&mut log, &config);
+        let (config, config_receiver) = watch::channel(config);
+        spawn_named(
+            &format!("log-{}", dir.display()),
+            run(log, receiver, offsets.clone(), config_receiver),
+        );
+        Self {
+            dir,
+            commands,
+            offsets,
+            config: Arc::new(config),
+        }
+    }
+
+    pub fn offsets(&self) -> &Arc<LogOffsets> {
+        &self.offsets
+    }
+
+    pub fn config(&self) -> LogConfig {
+        self.config.borrow().clone()
+    }
+
+    /// Takes effect before the next command the task runs.
+    pub fn set_config(&self, config: LogConfig) {
+        self.config.send_replace(config);
+    }
+
+    /// Room in the queue for one append, or `None` when the queue is full or the task is gone;
+    /// see `wait_for_room`. Taking the room first lets the caller commit to an append before it
+    /// gives the batch away.
+    pub fn try_reserve(&self) -> Option<AppendSlot<'_>> {
+        self.commands.try_reserve().ok().map(AppendSlot)
+    }
+
+    /// Waits until the queue has room.
+    pub async fn wait_for_room(&self) -> Result<(), String> {
+        self.commands
+            .reserve()
+            .await
+            .map(drop)
+            .map_err(|_| "The log task stopped".to_string())
+    }
+
+    /// Batches as they are in the log, from the one holding `offset` on and before
+    /// `max_offset`.
+    pub async fn read(
+        &self,
+        offset: i64,
+        max_bytes: usize,
+        max_offset: i64,
+    ) -> Result<Records, String> {
+        self.request(|reply| LogCommand::Read {
+            offset,
+            max_bytes,
+            max_offset,
+            reply,
+        })
+        .await
+    }
+
+    /// The first offset whose record timestamp is at or after `timestamp`, with that
+    /// timestamp.
+    pub async fn offset_for_timestamp(&self, timestamp: i64) -> Result<Option<(i64, i64)>, String> {
+        self.request(|reply| LogCommand::OffsetForTimestamp { timestamp, reply })
+            .await
+    }
+
+    /// Drops everything from `offset` onward.
+    pub async fn truncate(&self, offset: i64) -> Result<(), String> {
+        self.request(|reply| LogCommand::Truncate { offset, reply })
+            .await
+    }
+
+    pub async fn flush(&self) -> Result<(), String> {
+        self.request(|reply| LogCommand::Flush { reply }).await
+    }
+
+    /// Flushes and stops the task after what is already queued, e.g. before the log is
+    /// deleted.
+    pub async fn close(&self) -> Result<(), String> {
+        self.request(|reply| LogCommand::Close { reply }).await
+    }
+
+    async fn request<T>(&self, command: impl FnOnce(Reply<T>) -> LogCommand) -> Result<T, String> {
+        let (reply, receiver) = oneshot::channel();
+        self.commands
+            .send(command(reply))
+            .await
+            .map_err(|_| "The log task stopped".to_string())?;
+        LogReply(receiver).wait().await
+    }
+}
+
+fn configure(log: &mut PartitionLog, config: &LogConfig) {
+    log.max_segment_size = config.segment_bytes;
+    log.retention_bytes = config.retention_bytes;
+    log.retention_ms = config.retention_ms;
+    log.set_flush_policy(config.flush_interval_messages, config.flush_interval_ms);
+}
+
+async fn run(
+    mut log: PartitionLog,
+    mut commands: mpsc::Receiver<LogCommand>,
+    offsets: Arc<LogOffsets>,
+    mut config: watch::Receiver<LogConfig>,
+) {
+    // A command taken off the queue while gathering appends, to run next
+    let mut pending = None;
+    // Why appends are refused: one failed, and a batch written after it could be cut away
+    // with it by recovery once acknowledged
+    let mut failed: Option<String> = None;
+    loop {
+        let command = match pending.take() {
+            Some(command) => command,
+            None => match commands.recv().await {
+                Some(command) => command,
+                None => return,
+            },
+        };
+        if config.has_changed().unwrap_or(false) {
+            configure(&mut log, &config.borrow_and_update());
+        }
+        match command {
+            LogCommand::Append { batch, reply } => {
+                // Appends that queued up while the last command ran go out in one write
+                let mut appends = vec![(batch, reply)];
+                while appends.len() < QUEUE_CAPACITY {
+                    match commands.try_recv() {
+                        Ok(LogCommand::Append { batch, reply }) => appends.push((batch, reply)),
+                        Ok(command) => {
+                            pending = Some(command);
+                            break;
+                        }
+                        Err(_) => break,
+                    }
+                }
+                append(&mut log, appends, &offsets, &mut failed).await;
+            }
+            LogCommand::Read {
+                offset,
+                max_bytes,
+                max_offset,
+                reply,
+            } => {
+                let result = log.read_sequential_raw(offset, max_bytes, max_offset).await;
+                let _ = reply.send(result.map(Records::from_raw));
+            }
+            LogCommand::OffsetForTimestamp { timestamp, reply } => {
+                let _ = reply.send(log.offset_for_timestamp(timestamp).await);
+            }
+            LogCommand::Truncate { offset, reply } => {
+                let result = log.truncate_from_index(offset).await;
+                offsets.update(&log);
+                if result.is_ok() {
+                    failed = None;
+                }
+                let _ = reply.send(result);
+            }
+            LogCommand::Flush { reply } => {
+                let _ = reply.send(log.flush().await);
+            }
+            LogCommand::Close { reply } => {
+                let _ = reply.send(log.flush().await);
+                return;
+            }
+        }
+    }
+}
+
+async fn append(
+    log: &mut PartitionLog,
+    appends: Vec<(RecordBatch, Reply<u64>)>,
+    offsets: &LogOffsets,
+    failed: &mut Option<String>,
+) {
+    if let Some(e) = failed {
+        for (_, reply) in appends {
+            let _ = reply.send(Err(format!(
+                "Log {} takes no appends after a failed one: {}",
+                log.dir.display(),
+                e
+            )));
+        }
+        return;
+    }
+
+    let mut log_end_offset = log.get_last_log_index() + 1;
+    let mut batches = Vec::with_capacity(appends.len());
+    let mut replies = Vec::with_capacity(appends.len());
+    for (batch, reply) in appends {
+        // An append queued behind one that failed no longer follows the log
+        if batch.base_offset != log_end_offset {
+            let _ = reply.send(Err(format!(
+                "Batch at offset {} does not follow log end offset {}",
+                batch.base_offset, log_end_offset
+            )));
+            continue;
+        }
+        log_end_offset = batch.last_offset() + 1;
+        batches.push(batch);
+        replies.push(reply);
+    }
+    if batches.is_empty() {
+        return;
+    }
+
+    let result = log.append_batches(&batches).await;
+    offsets.update(log);
+    match result {
+        Ok(sizes) => {
+            for (reply, size) in replies.into_iter().zip(sizes) {
+                let _ = reply.send(Ok(size as u64));
+            }
+        }
+        Err(e) => {
+            for reply in replies {
+                let _ = reply.send(Err(e.clone()));
+            }
+            *failed = Some(e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::driven::storage::fault_injection::{Faults, SimulatedFileSystem};
+    use crate::core::domain::record::Record;
+
+    #[tokio::test]
+    async fn test_appends_must_follow_the_log_end() {
+        let dir = std::env::temp_dir().join(format!("forge-log-actor-{}", uuid::Uuid::new_v4()));
+        let log = PartitionLog::new(&dir, 1024 * 1024, 0, 0).await.unwrap();
+        let handle = LogHandle::spawn(log, LogConfig::default());
+
+        let mut batch = RecordBatch::new(0, vec![Record::new(0, None, Some(b"a".to_vec()))]);
+        let first = handle.try_reserve().unwrap().append(batch.clone());
+        batch.base_offset = 5;
+        let gap = handle.try_reserve().unwrap().append(batch.clone());
+        batch.base_offset = 1;
+        let second = handle.try_reserve().unwrap().append(batch);
+
+        assert!(first.wait().await.unwrap() > 0);
+        assert!(gap.wait().await.is_err());
+        second.wait().await.unwrap();
+        assert_eq!(handle.offsets().log_end_offset(), 2);
+        assert_eq!(handle.read(0, usize::MAX, i64::MAX).await.unwrap().len(), 2);
+
+        handle.close().await.unwrap();
+        assert!(handle.flush().await.is_err());
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_queued_appends_are_indexed_batch_by_batch() {
+        let dir = std::env::temp_dir().join(format!("forge-log-actor-{}", uuid::Uuid::new_v4()));
+        let log = PartitionLog::new(&dir, 1024 * 1024, 0, 0).await.unwrap();
+        let handle = LogHandle::spawn(log, LogConfig::default());
+
+        // Nothing runs the log task until the test awaits, so all three go out in one write
+        let replies: Vec<_> = (0..3)
+            .map(|offset| {
+                let record = Record::new(0, None, Some(vec![b'a'; 10 * (offset + 1)]));
+                let mut batch = RecordBatch::new(0, vec![record]);
+                batch.base_offset = offset as i64;
+                handle.try_reserve().unwrap().append(batch)
+            })
+            .collect();
+        let mut sizes = Vec::new();
+        for reply in replies {
+            sizes.push(reply.wait().await.unwrap());
+        }
+        assert!(sizes[0] < sizes[1] && sizes[1] < sizes[2]);
+        assert_eq!(handle.offsets().size_bytes(), sizes.iter().sum::<u64>());
+
+        let records = handle.read(2, usize::MAX, i64::MAX).await.unwrap();
+        assert_eq!(records.batches().unwrap()[0].base_offset, 2);
+
+        handle.close().await.unwrap();
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_appends_fail_after_a_failed_write_until_truncated() {
+        let fs = SimulatedFileSystem::new(0);
+        let log = PartitionLog::create(Arc::new(fs.clone()), "/data/topic-0", 1024 * 1024, 0, 0)
+            .await
+            .unwrap();
+        let handle = LogHandle::spawn(log, LogConfig::default());
+        let batch = RecordBatch::new(0, vec![Record::new(0, None, Some(b"a".to_vec()))]);
+
+        handle
+            .try_reserve()
+            .unwrap()
+            .append(batch.clone())
+            .wait()
+            .await
+            .unwrap();
+        fs.set_faults(Faults {
+            torn_write: 1.0,
+            ..Faults::default()
+        });
+        let mut next = batch.clone();
+        next.base_offset = 1;
+        let torn = handle.try_reserve().unwrap().append(next.clone());
+        assert!(torn.wait().await.is_err());
+        fs.set_faults(Faults::default());
+
+        // Follows the log end offset, which the failed write did not move
+        assert_eq!(handle.offsets().log_end_offset(), 1);
+        let refused = handle.try_reserve().unwrap().append(next.clone());
+        assert!(refused.wait().await.is_err());
+
+        handle.truncate(1).await.unwrap();
+        let accepted = handle.try_reserve().unwrap().append(next);
+        accepted.wait().await.unwrap();
+        assert_eq!(handle.read(0, usize::MAX, i64::MAX).await.unwrap().len(), 2);
+
+        handle.close().await.unwrap();
+    }
+}
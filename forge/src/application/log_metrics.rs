@@ -0,0 +1,103 @@
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::application::replica_manager::SharedOffsets;
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::shared::metrics::{MetricType, MetricsSource, MetricsWriter};
+use crate::shared::scheduler::spawn_periodic;
+
+/// Storage figures of one partition's log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionLogStats {
+    pub topic_partition: TopicPartition,
+    pub size_bytes: u64,
+    pub segments: usize,
+    pub log_start_offset: i64,
+    pub log_end_offset: i64,
+    pub high_watermark: i64,
+}
+
+/// Per-partition storage gauges. They are served from a snapshot taken every refresh
+/// interval, so a scrape never reads every partition's offsets.
+#[derive(Debug, Default)]
+pub struct LogMetrics {
+    /// A std mutex: held only to swap or copy the snapshot.
+    partitions: Mutex<Vec<PartitionLogStats>>,
+}
+
+impl LogMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start_refresh(
+        self: Arc<Self>,
+        offsets: SharedOffsets,
+        interval: Duration,
+        cancel_token: CancellationToken,
+    ) -> JoinHandle<()> {
+        spawn_periodic("log-metrics", interval, cancel_token, move || {
+            let metrics = self.clone();
+            let offsets = offsets.clone();
+            async move {
+                let partitions = offsets.log_stats();
+                *metrics.partitions.lock().unwrap_or_else(|e| e.into_inner()) = partitions;
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl MetricsSource for LogMetrics {
+    async fn write_metrics(&self, metrics: &mut MetricsWriter) {
+        let partitions = self
+            .partitions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let gauges: [(&str, &str, fn(&PartitionLogStats) -> f64); 5] = [
+            (
+                "forge_log_size_bytes",
+                "Bytes in the partition's log segments.",
+                |p| p.size_bytes as f64,
+            ),
+            (
+                "forge_log_segments",
+                "Segments of the partition's log.",
+                |p| p.segments as f64,
+            ),
+            (
+                "forge_log_start_offset",
+                "First offset still in the partition's log.",
+                |p| p.log_start_offset as f64,
+            ),
+            (
+                "forge_log_end_offset",
+                "Offset the next record appended to the partition gets.",
+                |p| p.log_end_offset as f64,
+            ),
+            (
+                "forge_log_high_watermark",
+                "Offset up to which the partition's records are committed.",
+                |p| p.high_watermark as f64,
+            ),
+        ];
+        for (name, help, value) in gauges {
+            metrics.family(name, MetricType::Gauge, help);
+            for partition in &partitions {
+                let index = partition.topic_partition.partition.to_string();
+                metrics.sample(
+                    name,
+                    &[
+                        ("topic", &partition.topic_partition.topic),
+                        ("partition", &index),
+                    ],
+                    value(partition),
+                );
+            }
+        }
+    }
+}
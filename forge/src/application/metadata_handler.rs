@@ -0,0 +1,210 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::application::auto_topic_creation::AutoTopicCreationManager;
+use crate::application::metadata_listener::BrokerMetadataListener;
+use crate::application::request_context::RequestContext;
+use crate::core::domain::acl::{AclOperation, Resource, ResourceType};
+use crate::core::domain::metadata_records::{NO_LEADER, PartitionRecord};
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::Authorizer;
+use crate::protocol::metadata::{
+    AUTHORIZED_OPERATIONS_OMITTED, MetadataBroker, MetadataPartition, MetadataRequest,
+    MetadataResponse, MetadataTopic,
+};
+use crate::shared::constants::{CONSUMER_OFFSETS_TOPIC, TRANSACTION_STATE_TOPIC};
+
+/// Serves Metadata from this broker's view of the cluster: the live brokers and the replicas,
+/// leader and ISR of each topic partition the client may describe.
+pub struct MetadataHandler {
+    node_id: i32,
+    cluster_id: Option<String>,
+    metadata: Arc<Mutex<BrokerMetadataListener>>,
+    /// Set when auto.create.topics.enable is on.
+    auto_topic_creation: Option<Arc<AutoTopicCreationManager>>,
+    authorizer: Option<Arc<dyn Authorizer>>,
+}
+
+impl MetadataHandler {
+    pub fn new(
+        node_id: i32,
+        cluster_id: Option<String>,
+        metadata: Arc<Mutex<BrokerMetadataListener>>,
+        auto_topic_creation: Option<Arc<AutoTopicCreationManager>>,
+        authorizer: Option<Arc<dyn Authorizer>>,
+    ) -> Self {
+        Self {
+            node_id,
+            cluster_id,
+            metadata,
+            auto_topic_creation,
+            authorizer,
+        }
+    }
+
+    /// A requested topic that does not exist is created when the request and the broker allow
+    /// it and the client may create it; otherwise it is reported as unknown.
+    pub async fn handle(
+        &self,
+        context: &RequestContext,
+        request: MetadataRequest,
+    ) -> MetadataResponse {
+        if request.allow_auto_topic_creation
+            && let Some(names) = &request.topics
+        {
+            self.maybe_create_topics(context, names).await;
+        }
+
+        let listener = self.metadata.lock().await;
+        let metadata = &listener.metadata;
+
+        let brokers = metadata
+            .live_brokers()
+            .into_iter()
+            .filter_map(|id| metadata.brokers.get(&id))
+            .map(|broker| MetadataBroker {
+                node_id: broker.broker_id,
+                host: broker.host.clone(),
+                port: broker.port,
+                rack: broker.rack.clone(),
+            })
+            .collect();
+
+        let mut topics = Vec::new();
+        match request.topics {
+            Some(names) => {
+                for name in names {
+                    let resource = Resource::new(ResourceType::Topic, name.as_str());
+                    if !context
+                        .authorize(self.authorizer.as_ref(), AclOperation::Describe, &resource)
+                        .await
+                    {
+                        topics.push(error_topic(name, ErrorCode::TopicAuthorizationFailed));
+                        continue;
+                    }
+                    match metadata.topics.get(&name) {
+                        Some(topic) => {
+                            topics.push(describe_topic(&name, topic.partitions.values(), |id| {
+                                metadata.is_broker_alive(id)
+                            }))
+                        }
+                        // Its partitions may still be on their way from the controller
+                        None if self.created_topic(&name).await => {
+                            topics.push(error_topic(name, ErrorCode::LeaderNotAvailable))
+                        }
+                        None => topics.push(error_topic(name, ErrorCode::UnknownTopicOrPartition)),
+                    }
+                }
+            }
+            None => {
+                for (name, topic) in metadata.topics.iter() {
+                    let resource = Resource::new(ResourceType::Topic, name.as_str());
+                    if context
+                        .is_authorized(self.authorizer.as_ref(), AclOperation::Describe, &resource)
+                        .await
+                    {
+                        topics.push(describe_topic(name, topic.partitions.values(), |id| {
+                            metadata.is_broker_alive(id)
+                        }));
+                    }
+                }
+            }
+        }
+
+        MetadataResponse {
+            throttle_time_ms: 0,
+            brokers,
+            cluster_id: self.cluster_id.clone(),
+            // The controller runs in this process
+            controller_id: self.node_id,
+            topics,
+            cluster_authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+        }
+    }
+}
+
+impl MetadataHandler {
+    /// Runs without the listener lock, which the controller takes to publish the new topics.
+    async fn maybe_create_topics(&self, context: &RequestContext, names: &[String]) {
+        let Some(auto_topic_creation) = &self.auto_topic_creation else {
+            return;
+        };
+
+        let missing: Vec<String> = {
+            let listener = self.metadata.lock().await;
+            names
+                .iter()
+                .filter(|name| !listener.metadata.topics.contains_key(*name))
+                .cloned()
+                .collect()
+        };
+        let mut topics = Vec::with_capacity(missing.len());
+        for topic in missing {
+            let resource = Resource::new(ResourceType::Topic, topic.as_str());
+            if context
+                .is_authorized(self.authorizer.as_ref(), AclOperation::Create, &resource)
+                .await
+            {
+                topics.push(topic);
+            }
+        }
+        if !topics.is_empty() {
+            auto_topic_creation.create_topics(topics).await;
+        }
+    }
+
+    async fn created_topic(&self, name: &str) -> bool {
+        match &self.auto_topic_creation {
+            Some(auto_topic_creation) => auto_topic_creation.is_known(name).await,
+            None => false,
+        }
+    }
+}
+
+fn error_topic(name: String, error: ErrorCode) -> MetadataTopic {
+    MetadataTopic {
+        error_code: error.code(),
+        is_internal: is_internal(&name),
+        name,
+        partitions: Vec::new(),
+        topic_authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+    }
+}
+
+fn is_internal(name: &str) -> bool {
+    name == CONSUMER_OFFSETS_TOPIC || name == TRANSACTION_STATE_TOPIC
+}
+
+fn describe_topic<'a>(
+    name: &str,
+    partitions: impl Iterator<Item = &'a PartitionRecord>,
+    is_alive: impl Fn(i32) -> bool,
+) -> MetadataTopic {
+    let partitions = partitions
+        .map(|partition| MetadataPartition {
+            error_code: if partition.leader == NO_LEADER {
+                ErrorCode::LeaderNotAvailable.code()
+            } else {
+                ErrorCode::None.code()
+            },
+            partition_index: partition.partition_index,
+            leader_id: partition.leader,
+            leader_epoch: partition.leader_epoch,
+            replica_nodes: partition.replicas.clone(),
+            isr_nodes: partition.isr.clone(),
+            offline_replicas: partition
+                .replicas
+                .iter()
+                .copied()
+                .filter(|id| !is_alive(*id))
+                .collect(),
+        })
+        .collect();
+    MetadataTopic {
+        error_code: ErrorCode::None.code(),
+        name: name.to_string(),
+        is_internal: is_internal(name),
+        partitions,
+        topic_authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+    }
+}
@@ -0,0 +1,209 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::application::dynamic_config::DynamicBrokerConfig;
+use crate::application::replica_fetcher::ReplicaFetcherManager;
+use crate::application::replica_manager::ReplicaManager;
+use crate::config::BrokerConfig;
+use crate::consensus::metadata_cache::ClusterMetadataCache;
+use crate::core::domain::metadata_records::{
+    CONFIG_RESOURCE_BROKER, MetadataRecord, NO_LEADER, RegisterBrokerRecord,
+};
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::ports::driven::{FetchClient, MetadataPublisher};
+use crate::shared::collections::FlatSet;
+
+/// Builds the client a follower uses to fetch from the given leader broker.
+pub type FetchClientFactory =
+    Box<dyn Fn(&RegisterBrokerRecord) -> Box<dyn FetchClient> + Send + Sync>;
+
+/// Applies metadata published by the controller to this broker: creates the local replicas it
+/// is assigned, moves them between leader and follower, and runs one replica fetcher per
+/// live leader it follows.
+pub struct BrokerMetadataListener {
+    pub broker_id: i32,
+    pub metadata: ClusterMetadataCache,
+    replica_manager: Arc<Mutex<ReplicaManager>>,
+    fetcher_manager: ReplicaFetcherManager,
+    fetch_client_factory: FetchClientFactory,
+    /// Receives the broker config overrides that apply to this broker.
+    dynamic_config: Option<DynamicBrokerConfig>,
+}
+
+impl BrokerMetadataListener {
+    pub fn new(
+        broker_id: i32,
+        replica_manager: Arc<Mutex<ReplicaManager>>,
+        fetch_client_factory: FetchClientFactory,
+    ) -> Self {
+        Self {
+            broker_id,
+            metadata: ClusterMetadataCache::new(),
+            fetcher_manager: ReplicaFetcherManager::new(broker_id, replica_manager.clone()),
+            replica_manager,
+            fetch_client_factory,
+            dynamic_config: None,
+        }
+    }
+
+    pub fn set_dynamic_config(&mut self, dynamic_config: DynamicBrokerConfig) {
+        self.dynamic_config = Some(dynamic_config);
+    }
+
+    pub fn dynamic_config(&self) -> Option<&DynamicBrokerConfig> {
+        self.dynamic_config.as_ref()
+    }
+
+    /// Hands a re-read static config to the dynamic config, keeping the overrides from the
+    /// controller on top.
+    pub async fn reload_config(&mut self, config: BrokerConfig) -> Result<(), String> {
+        match &mut self.dynamic_config {
+            Some(dynamic_config) => {
+                dynamic_config
+                    .reload(config, &self.metadata.broker_configs(self.broker_id))
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub async fn apply(&mut self, offset: i64, records: &[MetadataRecord]) -> Result<(), String> {
+        let mut changed = Vec::new();
+        let mut racks = Vec::new();
+        let mut broker_configs_changed = false;
+        for record in records {
+            self.metadata.apply_record(offset, record);
+            match record {
+                MetadataRecord::RegisterBroker(broker) => {
+                    racks.push((broker.broker_id, broker.rack.clone()))
+                }
+                MetadataRecord::Topic(topic) => changed.extend(
+                    topic
+                        .partitions
+                        .iter()
+                        .map(|p| TopicPartition::new(p.topic_name.clone(), p.partition_index)),
+                ),
+                MetadataRecord::Partition(partition) => changed.push(TopicPartition::new(
+                    partition.topic_name.clone(),
+                    partition.partition_index,
+                )),
+                MetadataRecord::PartitionChange(change) => changed.push(TopicPartition::new(
+                    change.topic_name.clone(),
+                    change.partition_index,
+                )),
+                MetadataRecord::Config(config)
+                    if config.resource_type == CONFIG_RESOURCE_BROKER =>
+                {
+                    broker_configs_changed |= config.resource_name.is_empty()
+                        || config.resource_name == self.broker_id.to_string()
+                }
+                MetadataRecord::FenceBroker(_) | MetadataRecord::Config(_) => {}
+            }
+        }
+
+        {
+            let mut replica_manager = self.replica_manager.lock().await;
+            for (broker_id, rack) in racks {
+                replica_manager.set_broker_rack(broker_id, rack);
+            }
+            for topic_partition in &changed {
+                self.apply_partition(&mut replica_manager, topic_partition)
+                    .await?;
+            }
+        }
+
+        if broker_configs_changed && let Some(dynamic_config) = &mut self.dynamic_config {
+            // The controller validated the overrides; a failure here leaves the old settings
+            if let Err(e) = dynamic_config
+                .update(&self.metadata.broker_configs(self.broker_id))
+                .await
+            {
+                tracing::error!("Failed to apply dynamic broker config: {}", e);
+            }
+        }
+
+        self.reconcile_fetchers().await;
+        Ok(())
+    }
+
+    async fn apply_partition(
+        &self,
+        replica_manager: &mut ReplicaManager,
+        topic_partition: &TopicPartition,
+    ) -> Result<(), String> {
+        let Some(partition) = self
+            .metadata
+            .partition(&topic_partition.topic, topic_partition.partition)
+        else {
+            return Ok(());
+        };
+        if !partition.replicas.contains(&self.broker_id) {
+            // A reassignment moved the partition off this broker
+            return replica_manager.delete_partition(topic_partition).await;
+        }
+
+        replica_manager
+            .create_partition(topic_partition.clone(), partition.replicas.clone())
+            .await?;
+        replica_manager.update_replicas(topic_partition, partition.replicas.clone());
+
+        if partition.leader == self.broker_id {
+            replica_manager
+                .become_leader(
+                    topic_partition,
+                    partition.leader_epoch,
+                    partition.isr.clone(),
+                )
+                .map_err(|e| e.to_string())?;
+        } else if partition.leader != NO_LEADER {
+            replica_manager
+                .become_follower(topic_partition, partition.leader_epoch, partition.leader)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Keeps exactly one fetcher per live broker leading a partition this broker follows.
+    async fn reconcile_fetchers(&mut self) {
+        let mut leaders = FlatSet::new();
+        for topic in self.metadata.topics.values() {
+            for partition in topic.partitions.values() {
+                if partition.replicas.contains(&self.broker_id)
+                    && partition.leader != self.broker_id
+                    && self.metadata.is_broker_alive(partition.leader)
+                {
+                    leaders.insert(partition.leader);
+                }
+            }
+        }
+
+        for leader_id in self.fetcher_manager.leader_ids() {
+            if !leaders.contains(&leader_id) {
+                self.fetcher_manager.remove_fetcher(leader_id).await;
+            }
+        }
+
+        for leader_id in leaders.iter() {
+            if self.fetcher_manager.has_fetcher(*leader_id) {
+                continue;
+            }
+            if let Some(broker) = self.metadata.brokers.get(leader_id) {
+                let client = (self.fetch_client_factory)(broker);
+                self.fetcher_manager.add_fetcher(*leader_id, client);
+            }
+        }
+    }
+
+    pub async fn shutdown(&mut self) {
+        self.fetcher_manager.shutdown().await;
+    }
+}
+
+#[async_trait]
+impl MetadataPublisher for Arc<Mutex<BrokerMetadataListener>> {
+    async fn publish(&mut self, offset: i64, records: &[MetadataRecord]) -> Result<(), String> {
+        self.lock().await.apply(offset, records).await
+    }
+}
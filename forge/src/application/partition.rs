@@ -0,0 +1,627 @@
+use std::collections::VecDeque;
+
+use crate::application::log_actor::{LogHandle, LogReply};
+use crate::application::producer_state::{AbortedTxn, ProducerStateManager, SequenceCheck};
+use crate::core::domain::record_batch::{RecordBatch, Records};
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::shared::collections::FlatMap;
+use crate::shared::time::current_time_ms;
+
+const PRODUCER_STATE_LOAD_MAX_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplicaRole {
+    /// Not yet assigned a role by the controller; serves neither produce nor fetch.
+    Offline,
+    Leader,
+    Follower {
+        leader_id: i32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogAppendInfo {
+    pub base_offset: i64,
+    pub last_offset: i64,
+    pub size_in_bytes: usize,
+}
+
+/// How far `Partition::start_append_to_leader` or `start_append_to_follower` got.
+pub enum AppendStart {
+    /// A producer retried a batch the partition already has.
+    Duplicate(LogAppendInfo),
+    /// Finish with `Partition::complete_append` once written.
+    Queued(PendingAppend),
+    /// The log's queue is full: wait for room on the handle, then start the batch again.
+    QueueFull(RecordBatch, LogHandle),
+}
+
+/// An append waiting for the log task, which needs no lock on the partition.
+pub struct PendingAppend {
+    written: WrittenAppend,
+    reply: LogReply<u64>,
+}
+
+impl PendingAppend {
+    pub async fn written(self) -> WrittenAppend {
+        WrittenAppend {
+            size: self.reply.wait().await,
+            ..self.written
+        }
+    }
+}
+
+pub struct WrittenAppend {
+    as_leader: bool,
+    leader_epoch: i32,
+    base_offset: i64,
+    last_offset: i64,
+    /// Bytes the batch took in the log.
+    size: Result<u64, String>,
+}
+
+/// A read of a partition's log that holds no borrow of the partition.
+pub struct LogRead {
+    topic_partition: TopicPartition,
+    log: LogHandle,
+    offset: i64,
+    max_offset: i64,
+}
+
+impl LogRead {
+    /// Batches from the read's offset, leaving out those at or past its max offset.
+    pub async fn read(self, max_bytes: usize) -> Result<Records, ErrorCode> {
+        self.log
+            .read(self.offset, max_bytes, self.max_offset)
+            .await
+            .map_err(|e| {
+                tracing::error!(
+                    "Failed to read from partition {}: {}",
+                    self.topic_partition,
+                    e
+                );
+                ErrorCode::KafkaStorageError
+            })
+    }
+
+    /// Like `read`, with the batches decoded and the first cut to start at the read's offset.
+    pub async fn read_batches(self, max_bytes: usize) -> Result<Vec<RecordBatch>, ErrorCode> {
+        let (topic_partition, offset) = (self.topic_partition.clone(), self.offset);
+        let records = self.read(max_bytes).await?;
+        let mut batches = records.batches().map_err(|e| {
+            tracing::error!(
+                "Failed to decode batches of partition {}: {}",
+                topic_partition,
+                e
+            );
+            ErrorCode::KafkaStorageError
+        })?;
+        if let Some(first) = batches.first_mut() {
+            first.drop_records_before(offset);
+        }
+        Ok(batches)
+    }
+}
+
+/// The leader's view of a follower replica.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FollowerState {
+    /// Offset of the follower's last fetch, -1 until it fetches at the current epoch.
+    pub log_end_offset: i64,
+    pub last_caught_up_time_ms: i64,
+}
+
+pub struct Partition {
+    pub topic_partition: TopicPartition,
+    pub broker_id: i32,
+    pub log: LogHandle,
+    pub role: ReplicaRole,
+    /// Bumped by the controller on every leadership change; stamped on batches appended as leader.
+    pub leader_epoch: i32,
+    pub replicas: Vec<i32>,
+    /// In-sync replicas, always including the leader while it is alive.
+    pub isr: Vec<i32>,
+    pub follower_states: FlatMap<i32, FollowerState>,
+    /// Offset the next queued append gets; past the log end offset while appends are queued.
+    next_offset: i64,
+    /// `(last_offset, size)` of batches appended above the high watermark, so a high watermark
+    /// move can report how many bytes it exposed to consumers.
+    unreplicated_batches: VecDeque<(i64, usize)>,
+    producer_state: ProducerStateManager,
+}
+
+impl Partition {
+    pub fn new(
+        topic_partition: TopicPartition,
+        broker_id: i32,
+        log: LogHandle,
+        replicas: Vec<i32>,
+    ) -> Self {
+        let high_watermark = log.offsets().log_end_offset();
+        log.offsets().set_high_watermark(high_watermark);
+        Self {
+            topic_partition,
+            broker_id,
+            log,
+            role: ReplicaRole::Offline,
+            leader_epoch: -1,
+            isr: replicas.clone(),
+            replicas,
+            follower_states: FlatMap::new(),
+            next_offset: high_watermark,
+            unreplicated_batches: VecDeque::new(),
+            producer_state: ProducerStateManager::new(),
+        }
+    }
+
+    /// Replays the log into the producer state, e.g. after opening or truncating it.
+    pub async fn rebuild_producer_state(&mut self) -> Result<(), ErrorCode> {
+        self.producer_state.clear();
+        let mut offset = self.log_start_offset();
+        while offset < self.log_end_offset() {
+            let batches = self
+                .read_records(offset, PRODUCER_STATE_LOAD_MAX_BYTES, i64::MAX)
+                .await?;
+            let Some(last) = batches.last() else {
+                break;
+            };
+            offset = last.last_offset() + 1;
+            for batch in &batches {
+                self.producer_state.update(batch);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_leader(&self) -> bool {
+        matches!(self.role, ReplicaRole::Leader)
+    }
+
+    pub fn leader_id(&self) -> Option<i32> {
+        match self.role {
+            ReplicaRole::Leader => Some(self.broker_id),
+            ReplicaRole::Follower { leader_id } => Some(leader_id),
+            ReplicaRole::Offline => None,
+        }
+    }
+
+    /// Returns `true` if this replica was not already leading at `leader_epoch`.
+    pub fn become_leader(&mut self, leader_epoch: i32, isr: Vec<i32>) -> bool {
+        if leader_epoch < self.leader_epoch {
+            tracing::warn!(
+                "Ignoring stale leader transition for {}: epoch {} is older than current epoch {}",
+                self.topic_partition,
+                leader_epoch,
+                self.leader_epoch
+            );
+            return false;
+        }
+
+        let is_new_leader = !self.is_leader() || leader_epoch > self.leader_epoch;
+
+        self.role = ReplicaRole::Leader;
+        self.leader_epoch = leader_epoch;
+        self.isr = isr;
+        if is_new_leader {
+            let now = current_time_ms();
+            self.follower_states = FlatMap::new();
+            for replica_id in self.replicas.iter().filter(|id| **id != self.broker_id) {
+                self.follower_states.insert(
+                    *replica_id,
+                    FollowerState {
+                        log_end_offset: -1,
+                        last_caught_up_time_ms: now,
+                    },
+                );
+            }
+            self.unreplicated_batches.clear();
+        }
+        self.maybe_increment_high_watermark();
+
+        tracing::info!(
+            "Broker {} became leader of {} at epoch {}",
+            self.broker_id,
+            self.topic_partition,
+            leader_epoch
+        );
+
+        is_new_leader
+    }
+
+    /// Adopts a new replica set, as a reassignment changes it. As leader, starts tracking the
+    /// added followers and forgets the removed ones.
+    pub fn update_replicas(&mut self, replicas: Vec<i32>) {
+        if self.is_leader() {
+            let now = current_time_ms();
+            for replica_id in replicas.iter().filter(|id| **id != self.broker_id) {
+                if !self.follower_states.contains_key(replica_id) {
+                    self.follower_states.insert(
+                        *replica_id,
+                        FollowerState {
+                            log_end_offset: -1,
+                            last_caught_up_time_ms: now,
+                        },
+                    );
+                }
+            }
+            let removed: Vec<i32> = self
+                .follower_states
+                .keys()
+                .copied()
+                .filter(|id| !replicas.contains(id))
+                .collect();
+            for replica_id in removed {
+                self.follower_states.remove(&replica_id);
+            }
+        }
+        self.replicas = replicas;
+    }
+
+    /// Returns `true` if this replica changed leader or epoch.
+    pub fn become_follower(&mut self, leader_epoch: i32, leader_id: i32) -> bool {
+        if leader_epoch < self.leader_epoch {
+            tracing::warn!(
+                "Ignoring stale follower transition for {}: epoch {} is older than current epoch {}",
+                self.topic_partition,
+                leader_epoch,
+                self.leader_epoch
+            );
+            return false;
+        }
+
+        let new_role = ReplicaRole::Follower { leader_id };
+        if self.role == new_role && self.leader_epoch == leader_epoch {
+            return false;
+        }
+
+        self.role = new_role;
+        self.leader_epoch = leader_epoch;
+        self.follower_states = FlatMap::new();
+        self.unreplicated_batches.clear();
+
+        tracing::info!(
+            "Broker {} became follower of {} for leader {} at epoch {}",
+            self.broker_id,
+            self.topic_partition,
+            leader_id,
+            leader_epoch
+        );
+
+        true
+    }
+
+    /// Checks the epoch a client or follower believes is current; `None` skips the check.
+    pub fn validate_leader_epoch(
+        &self,
+        current_leader_epoch: Option<i32>,
+    ) -> Result<(), ErrorCode> {
+        match current_leader_epoch {
+            Some(epoch) if epoch < self.leader_epoch => Err(ErrorCode::FencedLeaderEpoch),
+            Some(epoch) if epoch > self.leader_epoch => Err(ErrorCode::UnknownLeaderEpoch),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn log_start_offset(&self) -> i64 {
+        self.log.offsets().log_start_offset()
+    }
+
+    /// One past the last offset written; appends still queued are not counted.
+    pub fn log_end_offset(&self) -> i64 {
+        self.log.offsets().log_end_offset()
+    }
+
+    /// Offset up to which records are committed and visible to consumers (exclusive).
+    pub fn high_watermark(&self) -> i64 {
+        self.log.offsets().high_watermark()
+    }
+
+    fn set_high_watermark(&self, high_watermark: i64) {
+        self.log.offsets().set_high_watermark(high_watermark);
+    }
+
+    pub fn next_offset(&self) -> i64 {
+        self.next_offset
+    }
+
+    /// Offset below which every transaction is decided; never past the high watermark.
+    pub fn last_stable_offset(&self) -> i64 {
+        self.producer_state
+            .first_unstable_offset()
+            .map_or(self.high_watermark(), |offset| {
+                offset.min(self.high_watermark())
+            })
+    }
+
+    pub fn aborted_txns_in_range(
+        &self,
+        fetch_offset: i64,
+        upper_bound_offset: i64,
+    ) -> Vec<AbortedTxn> {
+        self.producer_state
+            .aborted_txns_in_range(fetch_offset, upper_bound_offset)
+    }
+
+    pub fn remove_expired_producers(&mut self, now_ms: i64, expiration_ms: i64) -> usize {
+        self.producer_state
+            .remove_expired_producers(now_ms, expiration_ms)
+    }
+
+    /// Checks `batch` against its producer's sequence, numbers it and queues it on the log.
+    pub fn start_append_to_leader(&mut self, batch: RecordBatch) -> Result<AppendStart, ErrorCode> {
+        if let SequenceCheck::Duplicate(info) = self.producer_state.check_sequence(&batch)? {
+            tracing::debug!(
+                "Skipping duplicate batch from producer {} for {} at offset {}",
+                batch.producer_id,
+                self.topic_partition,
+                info.base_offset
+            );
+            return Ok(AppendStart::Duplicate(info));
+        }
+
+        let Some(slot) = self.log.try_reserve() else {
+            return Ok(AppendStart::QueueFull(batch, self.log.clone()));
+        };
+        let mut batch = batch;
+        batch.base_offset = self.next_offset;
+        batch.partition_leader_epoch = self.leader_epoch;
+        // Updated now so the producer's next batch checks against this one
+        self.producer_state.update(&batch);
+        self.next_offset = batch.last_offset() + 1;
+        let written = WrittenAppend {
+            as_leader: true,
+            leader_epoch: self.leader_epoch,
+            base_offset: batch.base_offset,
+            last_offset: batch.last_offset(),
+            size: Ok(0),
+        };
+        Ok(AppendStart::Queued(PendingAppend {
+            written,
+            reply: slot.append(batch),
+        }))
+    }
+
+    /// Queues a batch fetched from the leader, keeping the leader's offsets and epoch.
+    pub fn start_append_to_follower(
+        &mut self,
+        batch: RecordBatch,
+    ) -> Result<AppendStart, ErrorCode> {
+        if batch.base_offset != self.next_offset {
+            tracing::warn!(
+                "Follower {} of {} expected batch at offset {} but got {}",
+                self.broker_id,
+                self.topic_partition,
+                self.next_offset,
+                batch.base_offset
+            );
+            return Err(ErrorCode::OffsetOutOfRange);
+        }
+
+        let Some(slot) = self.log.try_reserve() else {
+            return Ok(AppendStart::QueueFull(batch, self.log.clone()));
+        };
+        self.producer_state.update(&batch);
+        self.next_offset = batch.last_offset() + 1;
+        let written = WrittenAppend {
+            as_leader: false,
+            leader_epoch: self.leader_epoch,
+            base_offset: batch.base_offset,
+            last_offset: batch.last_offset(),
+            size: Ok(0),
+        };
+        Ok(AppendStart::Queued(PendingAppend {
+            written,
+            reply: slot.append(batch),
+        }))
+    }
+
+    /// Accounts for an append the log task is done with. Returns what was appended and the
+    /// bytes that became visible to consumers.
+    pub async fn complete_append(
+        &mut self,
+        written: WrittenAppend,
+    ) -> Result<(LogAppendInfo, usize), ErrorCode> {
+        let size_in_bytes = match written.size {
+            Ok(size) => size as usize,
+            Err(e) => {
+                tracing::error!(
+                    "Failed to append to partition {}: {}",
+                    self.topic_partition,
+                    e
+                );
+                // Appends queued behind this one were numbered after it and fail too; once
+                // they are through, number the next append from what reached the log
+                let _ = self.log.flush().await;
+                self.next_offset = self.log_end_offset();
+                self.rebuild_producer_state().await?;
+                return Err(ErrorCode::KafkaStorageError);
+            }
+        };
+        if written.as_leader != self.is_leader() || written.leader_epoch != self.leader_epoch {
+            return Err(ErrorCode::NotLeaderOrFollower);
+        }
+
+        // An append to a partition led by this broker alone may be committed before it is
+        // completed, by one queued after it
+        let mut exposed_bytes = 0;
+        if written.last_offset < self.high_watermark() {
+            exposed_bytes += size_in_bytes;
+        } else {
+            let index = self
+                .unreplicated_batches
+                .partition_point(|(last_offset, _)| *last_offset < written.last_offset);
+            self.unreplicated_batches
+                .insert(index, (written.last_offset, size_in_bytes));
+        }
+        if written.as_leader {
+            exposed_bytes += self.maybe_increment_high_watermark();
+        }
+
+        let info = LogAppendInfo {
+            base_offset: written.base_offset,
+            last_offset: written.last_offset,
+            size_in_bytes,
+        };
+        Ok((info, exposed_bytes))
+    }
+
+    /// A follower's high watermark trails the leader's and never passes its own log end.
+    /// Returns the size of the batches that became visible to consumers fetching from it.
+    pub fn update_follower_high_watermark(&mut self, leader_high_watermark: i64) -> usize {
+        let new_high_watermark = leader_high_watermark.min(self.log_end_offset());
+        if new_high_watermark <= self.high_watermark() {
+            self.set_high_watermark(new_high_watermark);
+            return 0;
+        }
+        self.set_high_watermark(new_high_watermark);
+        self.drain_exposed_bytes()
+    }
+
+    /// Drops everything from `offset` onward, e.g. uncommitted records of a former leader.
+    pub async fn truncate_to(&mut self, offset: i64) -> Result<(), ErrorCode> {
+        if offset >= self.next_offset {
+            return Ok(());
+        }
+
+        tracing::info!(
+            "Truncating {} from offset {} to {}",
+            self.topic_partition,
+            self.next_offset,
+            offset
+        );
+        // Queued behind any appends, so it drops them too
+        self.log.truncate(offset).await.map_err(|e| {
+            tracing::error!("Failed to truncate {}: {}", self.topic_partition, e);
+            ErrorCode::KafkaStorageError
+        })?;
+        self.next_offset = self.log_end_offset();
+        self.set_high_watermark(self.high_watermark().min(offset));
+        self.unreplicated_batches
+            .retain(|(last_offset, _)| *last_offset < offset);
+        self.rebuild_producer_state().await
+    }
+
+    /// Records a follower's fetch offset as its log end offset, adding it back to the ISR once
+    /// it has caught up to the high watermark. Returns the bytes a high watermark move exposed.
+    pub fn update_follower_fetch_state(
+        &mut self,
+        replica_id: i32,
+        fetch_offset: i64,
+    ) -> Result<usize, ErrorCode> {
+        let log_end_offset = self.log_end_offset();
+        let Some(state) = self.follower_states.get_mut(&replica_id) else {
+            return Err(ErrorCode::NotLeaderOrFollower);
+        };
+
+        state.log_end_offset = fetch_offset;
+        if fetch_offset >= log_end_offset {
+            state.last_caught_up_time_ms = current_time_ms();
+        }
+
+        if !self.isr.contains(&replica_id) && fetch_offset >= self.high_watermark() {
+            self.isr.push(replica_id);
+            tracing::info!(
+                "Expanding ISR of {} to {:?} after replica {} caught up",
+                self.topic_partition,
+                self.isr,
+                replica_id
+            );
+        }
+
+        Ok(self.maybe_increment_high_watermark())
+    }
+
+    /// Removes followers that have not caught up within `max_lag_ms` from the ISR. Returns the
+    /// bytes a resulting high watermark move exposed.
+    pub fn maybe_shrink_isr(&mut self, max_lag_ms: i64) -> usize {
+        let now = current_time_ms();
+        let lagging: Vec<i32> = self
+            .isr
+            .iter()
+            .filter(|id| {
+                self.follower_states
+                    .get(id)
+                    .is_some_and(|s| now - s.last_caught_up_time_ms > max_lag_ms)
+            })
+            .copied()
+            .collect();
+
+        if lagging.is_empty() {
+            return 0;
+        }
+
+        self.isr.retain(|id| !lagging.contains(id));
+        tracing::warn!(
+            "Shrinking ISR of {} to {:?}; replicas {:?} fell more than {} ms behind",
+            self.topic_partition,
+            self.isr,
+            lagging,
+            max_lag_ms
+        );
+        self.maybe_increment_high_watermark()
+    }
+
+    /// Checks `offset` against the log and prepares to read from it; batches at or past
+    /// `max_offset` are left out.
+    pub fn start_read(&self, offset: i64, max_offset: i64) -> Result<LogRead, ErrorCode> {
+        if offset < self.log_start_offset() || offset > self.log_end_offset() {
+            return Err(ErrorCode::OffsetOutOfRange);
+        }
+
+        Ok(LogRead {
+            topic_partition: self.topic_partition.clone(),
+            log: self.log.clone(),
+            offset,
+            max_offset,
+        })
+    }
+
+    pub async fn read_records(
+        &self,
+        offset: i64,
+        max_bytes: usize,
+        max_offset: i64,
+    ) -> Result<Vec<RecordBatch>, ErrorCode> {
+        self.start_read(offset, max_offset)?
+            .read_batches(max_bytes)
+            .await
+    }
+
+    /// Advances the high watermark to the smallest log end offset in the ISR and returns the
+    /// size of the batches that became visible.
+    fn maybe_increment_high_watermark(&mut self) -> usize {
+        let log_end_offset = self.log_end_offset();
+        let new_high_watermark = self
+            .isr
+            .iter()
+            .map(|id| {
+                if *id == self.broker_id {
+                    log_end_offset
+                } else {
+                    self.follower_states
+                        .get(id)
+                        .map_or(-1, |state| state.log_end_offset)
+                }
+            })
+            .min()
+            .unwrap_or(log_end_offset);
+
+        if new_high_watermark <= self.high_watermark() {
+            return 0;
+        }
+        self.set_high_watermark(new_high_watermark);
+        self.drain_exposed_bytes()
+    }
+
+    fn drain_exposed_bytes(&mut self) -> usize {
+        let mut exposed_bytes = 0;
+        while let Some((last_offset, size)) = self.unreplicated_batches.front()
+            && *last_offset < self.high_watermark()
+        {
+            exposed_bytes += size;
+            self.unreplicated_batches.pop_front();
+        }
+        exposed_bytes
+    }
+}
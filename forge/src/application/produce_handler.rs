@@ -0,0 +1,643 @@
+use futures::future::join_all;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::application::auto_topic_creation::AutoTopicCreationManager;
+use crate::application::dead_letter::{DeadLetterQueue, Rejected};
+use crate::application::partition::LogAppendInfo;
+use crate::application::record_interceptor::RecordInterceptor;
+use crate::application::record_validator::RecordValidators;
+use crate::application::replica_manager::{ACKS_ALL, ACKS_NONE, ReplicaManager};
+use crate::application::request_context::RequestContext;
+use crate::core::domain::acl::{AclOperation, Resource, ResourceType};
+use crate::core::domain::record_batch::{BATCH_HEADER_SIZE, RecordBatch};
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::Authorizer;
+use crate::protocol::produce::{
+    BatchIndexAndErrorMessage, PartitionProduceResponse, ProduceRequest, ProduceResponse,
+    TopicProduceResponse,
+};
+use crate::shared::constants::{
+    CONSUMER_OFFSETS_TOPIC, DEFAULT_MESSAGE_MAX_BYTES, TRANSACTION_STATE_TOPIC,
+};
+
+pub struct ProduceHandler {
+    replica_manager: Arc<Mutex<ReplicaManager>>,
+    authorizer: Option<Arc<dyn Authorizer>>,
+    /// Set when auto.create.topics.enable is on.
+    auto_topic_creation: Option<Arc<AutoTopicCreationManager>>,
+    interceptor: Option<Arc<dyn RecordInterceptor>>,
+    validators: Option<Arc<RecordValidators>>,
+    message_max_bytes: usize,
+    dead_letter_queue: Option<Arc<DeadLetterQueue>>,
+}
+
+/// A batch the produce path refused: its index in the partition's records, the error the
+/// partition fails with and why.
+type Refusal = (usize, ErrorCode, String);
+
+/// A partition whose acks=all response waits for the high watermark to reach `required_offset`.
+struct PendingAck {
+    topic_index: usize,
+    partition_index: usize,
+    topic_partition: TopicPartition,
+    required_offset: i64,
+}
+
+impl ProduceHandler {
+    pub fn new(
+        replica_manager: Arc<Mutex<ReplicaManager>>,
+        authorizer: Option<Arc<dyn Authorizer>>,
+        auto_topic_creation: Option<Arc<AutoTopicCreationManager>>,
+    ) -> Self {
+        Self {
+            replica_manager,
+            authorizer,
+            auto_topic_creation,
+            interceptor: None,
+            validators: None,
+            message_max_bytes: DEFAULT_MESSAGE_MAX_BYTES,
+            dead_letter_queue: None,
+        }
+    }
+
+    /// Passes every batch through `interceptor` before it is appended.
+    pub fn with_interceptor(mut self, interceptor: Arc<dyn RecordInterceptor>) -> Self {
+        self.interceptor = Some(interceptor);
+        self
+    }
+
+    /// Runs the validators each topic enables before its batches are appended.
+    pub fn with_validators(mut self, validators: Arc<RecordValidators>) -> Self {
+        self.validators = Some(validators);
+        self
+    }
+
+    pub fn with_message_max_bytes(mut self, message_max_bytes: usize) -> Self {
+        self.message_max_bytes = message_max_bytes;
+        self
+    }
+
+    /// Keeps the batches refused by validation in dead letter topics.
+    pub fn with_dead_letter_queue(mut self, dead_letter_queue: Arc<DeadLetterQueue>) -> Self {
+        self.dead_letter_queue = Some(dead_letter_queue);
+        self
+    }
+
+    /// Checks the size of each batch as sent, then runs the interceptor and the topic's
+    /// validators over the batches of a client's topic; returns the first batch refused.
+    async fn validate(&self, topic: &str, batches: &mut [RecordBatch]) -> Option<Refusal> {
+        // Batches built in-process have no length as sent
+        let too_large = batches.iter().position(|batch| {
+            batch.batch_length > 0
+                && batch.batch_length as usize + BATCH_HEADER_SIZE > self.message_max_bytes
+        });
+        if let Some(index) = too_large {
+            let size = batches[index].batch_length as usize + BATCH_HEADER_SIZE;
+            return Some((
+                index,
+                ErrorCode::MessageTooLarge,
+                format!(
+                    "Batch of {} bytes is larger than message.max.bytes {}",
+                    size, self.message_max_bytes
+                ),
+            ));
+        }
+
+        if topic == CONSUMER_OFFSETS_TOPIC || topic == TRANSACTION_STATE_TOPIC {
+            return None;
+        }
+        if let Some(interceptor) = &self.interceptor {
+            let refused = batches
+                .iter_mut()
+                .enumerate()
+                .filter(|(_, batch)| !batch.is_control_batch())
+                .find_map(|(index, batch)| {
+                    interceptor
+                        .on_produce(topic, batch)
+                        .err()
+                        .map(|reason| (index, ErrorCode::InvalidRecord, reason))
+                });
+            if refused.is_some() {
+                return refused;
+            }
+        }
+        self.validators.as_ref()?.validate(topic, batches).await
+    }
+
+    /// Creates the topics of `request` this broker has no replica of, if the client may.
+    /// Runs without the replica manager lock, since the new partitions are created under it.
+    async fn maybe_create_topics(
+        &self,
+        context: &RequestContext,
+        request: &ProduceRequest,
+        authorization_errors: &[Option<ErrorCode>],
+    ) {
+        let Some(auto_topic_creation) = &self.auto_topic_creation else {
+            return;
+        };
+
+        let missing: Vec<String> = {
+            let replica_manager = self.replica_manager.lock().await;
+            request
+                .topics
+                .iter()
+                .zip(authorization_errors)
+                .filter(|(topic, error)| error.is_none() && !replica_manager.has_topic(&topic.name))
+                .map(|(topic, _)| topic.name.clone())
+                .collect()
+        };
+
+        let mut topics = Vec::with_capacity(missing.len());
+        for topic in missing {
+            let resource = Resource::new(ResourceType::Topic, topic.as_str());
+            if context
+                .authorize(self.authorizer.as_ref(), AclOperation::Create, &resource)
+                .await
+            {
+                topics.push(topic);
+            }
+        }
+        if !topics.is_empty() {
+            auto_topic_creation.create_topics(topics).await;
+        }
+    }
+
+    /// The error every partition of `topic` fails with when the client may not write to it.
+    async fn authorization_error(
+        &self,
+        context: &RequestContext,
+        transactional_id: Option<&str>,
+        topic: &str,
+    ) -> Option<ErrorCode> {
+        let authorizer = self.authorizer.as_ref();
+        if let Some(transactional_id) = transactional_id {
+            let resource = Resource::new(ResourceType::TransactionalId, transactional_id);
+            if !context
+                .authorize(authorizer, AclOperation::Write, &resource)
+                .await
+            {
+                return Some(ErrorCode::TransactionalIdAuthorizationFailed);
+            }
+        }
+
+        let resource = Resource::new(ResourceType::Topic, topic);
+        if !context
+            .authorize(authorizer, AclOperation::Write, &resource)
+            .await
+        {
+            return Some(ErrorCode::TopicAuthorizationFailed);
+        }
+        None
+    }
+
+    /// Appends every batch and answers according to `acks`: no response for 0, after the
+    /// local append for 1, and once the high watermark covers the batches for -1. Partitions
+    /// still short of it after `timeout_ms` fail with `RequestTimedOut`, and those whose ISR
+    /// shrank below min.insync.replicas meanwhile with `NotEnoughReplicasAfterAppend`.
+    pub async fn handle(
+        &self,
+        context: &RequestContext,
+        request: ProduceRequest,
+    ) -> Option<ProduceResponse> {
+        let timeout = Duration::from_millis(request.timeout_ms.max(0) as u64);
+        let deadline = Instant::now() + timeout;
+
+        // Checked before any append takes the replica manager lock
+        let mut authorization_errors = Vec::with_capacity(request.topics.len());
+        for topic in &request.topics {
+            authorization_errors.push(
+                self.authorization_error(context, request.transactional_id.as_deref(), &topic.name)
+                    .await,
+            );
+        }
+        self.maybe_create_topics(context, &request, &authorization_errors)
+            .await;
+
+        let acks = request.acks;
+        let mut responses = Vec::with_capacity(request.topics.len());
+        let mut appends = Vec::new();
+        for (topic_index, topic) in request.topics.into_iter().enumerate() {
+            for partition in topic.partitions {
+                let topic_partition = TopicPartition::new(topic.name.clone(), partition.index);
+                appends.push((
+                    topic_index,
+                    topic_partition,
+                    partition.records,
+                    partition.corrupt,
+                ));
+            }
+            responses.push(TopicProduceResponse {
+                name: topic.name,
+                partitions: Vec::new(),
+            });
+        }
+
+        // Partitions append side by side, each holding the lock only to queue and account
+        // for its batches
+        let results = join_all(appends.into_iter().map(
+            |(topic_index, topic_partition, mut batches, corrupt)| {
+                let authorization_error = authorization_errors[topic_index];
+                async move {
+                    if let Some(error) = authorization_error {
+                        return (topic_index, topic_partition, Err(error), None);
+                    }
+                    // Only the leader validates, so a misdirected producer learns where to go
+                    let leadership_error = match self
+                        .replica_manager
+                        .lock()
+                        .await
+                        .get_partition(&topic_partition)
+                    {
+                        None => Some(ErrorCode::UnknownTopicOrPartition),
+                        Some(p) if !p.is_leader() => Some(ErrorCode::NotLeaderOrFollower),
+                        Some(_) => None,
+                    };
+                    if let Some(error) = leadership_error {
+                        return (topic_index, topic_partition, Err(error), None);
+                    }
+                    let (refused, rejected) = match corrupt {
+                        Some((records, reason)) => (
+                            (0, ErrorCode::CorruptMessage, reason),
+                            Rejected::Corrupt(records),
+                        ),
+                        None => match self.validate(&topic_partition.topic, &mut batches).await {
+                            Some(refused) => (refused, Rejected::Batches(batches)),
+                            None => {
+                                let result = Self::append(
+                                    &self.replica_manager,
+                                    &topic_partition,
+                                    acks,
+                                    batches,
+                                )
+                                .await;
+                                return (topic_index, topic_partition, result, None);
+                            }
+                        },
+                    };
+                    // The producer hears of the refusal once the batches are kept. A topic
+                    // misconfiguration is not the records' fault: they stay out of the queue.
+                    let (_, error, reason) = &refused;
+                    if *error != ErrorCode::InvalidConfig
+                        && let Some(dead_letter_queue) = &self.dead_letter_queue
+                    {
+                        dead_letter_queue
+                            .route(&topic_partition, *error, reason, rejected)
+                            .await;
+                    }
+                    (topic_index, topic_partition, Err(refused.1), Some(refused))
+                }
+            },
+        ))
+        .await;
+
+        let mut pending = Vec::new();
+        let wakeup = {
+            let mut replica_manager = self.replica_manager.lock().await;
+
+            for (topic_index, topic_partition, result, refused) in results {
+                let partitions = &mut responses[topic_index].partitions;
+                let index = topic_partition.partition;
+                let log_start_offset = replica_manager
+                    .get_partition(&topic_partition)
+                    .map_or(-1, |p| p.log_start_offset());
+                let (error, base_offset) = match result {
+                    Ok(info) => {
+                        if acks == ACKS_ALL {
+                            pending.push(PendingAck {
+                                topic_index,
+                                partition_index: partitions.len(),
+                                topic_partition,
+                                required_offset: info.last_offset + 1,
+                            });
+                        }
+                        (ErrorCode::None, info.base_offset)
+                    }
+                    Err(error) => (error, -1),
+                };
+
+                partitions.push(PartitionProduceResponse {
+                    index,
+                    error_code: error.code(),
+                    base_offset,
+                    log_append_time_ms: -1,
+                    log_start_offset,
+                    record_errors: refused
+                        .iter()
+                        .map(|(batch_index, _, reason)| BatchIndexAndErrorMessage {
+                            batch_index: *batch_index as i32,
+                            batch_index_error_message: Some(reason.clone()),
+                        })
+                        .collect(),
+                    error_message: refused.map(|(_, _, reason)| reason),
+                });
+            }
+
+            if acks == ACKS_NONE {
+                return None;
+            }
+            if pending.is_empty() {
+                return Some(ProduceResponse {
+                    responses,
+                    throttle_time_ms: 0,
+                });
+            }
+
+            let required_offsets = pending
+                .iter()
+                .map(|p| (p.topic_partition.clone(), p.required_offset))
+                .collect();
+            replica_manager.watch_produce(required_offsets, timeout)
+        };
+
+        let _ = tokio::time::timeout_at(deadline, wakeup).await;
+
+        let replica_manager = self.replica_manager.lock().await;
+        for ack in pending {
+            let error = match replica_manager.get_partition(&ack.topic_partition) {
+                None => ErrorCode::UnknownTopicOrPartition,
+                Some(p) if !p.is_leader() => ErrorCode::NotLeaderOrFollower,
+                // Replicated by fewer than promised, even if the high watermark moved
+                Some(p) if p.isr.len() < replica_manager.min_insync_replicas => {
+                    ErrorCode::NotEnoughReplicasAfterAppend
+                }
+                Some(p) if p.high_watermark() >= ack.required_offset => continue,
+                Some(_) => ErrorCode::RequestTimedOut,
+            };
+
+            let partition = &mut responses[ack.topic_index].partitions[ack.partition_index];
+            partition.error_code = error.code();
+            partition.base_offset = -1;
+        }
+
+        Some(ProduceResponse {
+            responses,
+            throttle_time_ms: 0,
+        })
+    }
+
+    /// Appends a partition's batches in order. A batch that fails stops the rest and fails the
+    /// partition with its error, but the batches before it stay in the log: Kafka clients
+    /// send one batch per partition, and an idempotent producer's retry of the earlier ones
+    /// is answered as a duplicate.
+    async fn append(
+        replica_manager: &Mutex<ReplicaManager>,
+        topic_partition: &TopicPartition,
+        acks: i16,
+        batches: Vec<RecordBatch>,
+    ) -> Result<LogAppendInfo, ErrorCode> {
+        if batches.is_empty() {
+            return Err(ErrorCode::CorruptMessage);
+        }
+
+        let mut appended: Option<LogAppendInfo> = None;
+        for batch in batches {
+            let info = ReplicaManager::append_records_concurrently(
+                replica_manager,
+                topic_partition,
+                acks,
+                batch,
+            )
+            .await?;
+            appended = Some(match appended {
+                Some(first) => LogAppendInfo {
+                    base_offset: first.base_offset,
+                    last_offset: info.last_offset,
+                    size_in_bytes: first.size_in_bytes + info.size_in_bytes,
+                },
+                None => info,
+            });
+        }
+        appended.ok_or(ErrorCode::CorruptMessage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::replica_manager::ACKS_LEADER;
+    use crate::config::SecurityProtocol;
+    use crate::core::domain::principal::KafkaPrincipal;
+    use crate::core::domain::record::Record;
+    use crate::protocol::produce::{PartitionProduceData, TopicProduceData};
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn events() -> TopicPartition {
+        TopicPartition::new("events", 0)
+    }
+
+    /// A replica manager leading "events"-0 on broker 1 with `replicas` all in sync.
+    async fn leader(
+        replicas: Vec<i32>,
+        min_insync_replicas: usize,
+    ) -> (Arc<Mutex<ReplicaManager>>, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("forge-produce-{}", uuid::Uuid::new_v4()));
+        let mut replica_manager = ReplicaManager::new(1, &dir, min_insync_replicas);
+        replica_manager
+            .create_partition(events(), replicas.clone())
+            .await
+            .unwrap();
+        replica_manager
+            .become_leader(&events(), 0, replicas)
+            .unwrap();
+        (Arc::new(Mutex::new(replica_manager)), dir)
+    }
+
+    fn context() -> RequestContext {
+        RequestContext {
+            principal: KafkaPrincipal::anonymous(),
+            client_host: "127.0.0.1".to_string(),
+            client_id: "producer".to_string(),
+            listener: SecurityProtocol::Plaintext,
+        }
+    }
+
+    fn batch(value: &[u8]) -> RecordBatch {
+        RecordBatch::new(0, vec![Record::new(0, None, Some(value.to_vec()))])
+    }
+
+    fn produce_request(acks: i16, timeout_ms: i32, records: Vec<RecordBatch>) -> ProduceRequest {
+        ProduceRequest {
+            transactional_id: None,
+            acks,
+            timeout_ms,
+            topics: vec![TopicProduceData {
+                name: "events".to_string(),
+                partitions: vec![PartitionProduceData {
+                    index: 0,
+                    records,
+                    corrupt: None,
+                }],
+            }],
+        }
+    }
+
+    async fn offsets(replica_manager: &Mutex<ReplicaManager>) -> (i64, i64) {
+        let replica_manager = replica_manager.lock().await;
+        let partition = replica_manager.get_partition(&events()).unwrap();
+        (partition.log_end_offset(), partition.high_watermark())
+    }
+
+    /// Waits for the produce under way to reach the log.
+    async fn appended(replica_manager: &Mutex<ReplicaManager>) {
+        while offsets(replica_manager).await.0 == 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    }
+
+    fn partition_response(response: &ProduceResponse) -> &PartitionProduceResponse {
+        &response.responses[0].partitions[0]
+    }
+
+    #[tokio::test]
+    async fn test_acks_none_appends_without_a_response() {
+        let (replica_manager, dir) = leader(vec![1], 1).await;
+        let handler = ProduceHandler::new(replica_manager.clone(), None, None);
+
+        let request = produce_request(ACKS_NONE, 1000, vec![batch(b"a")]);
+        assert!(handler.handle(&context(), request).await.is_none());
+        assert_eq!(offsets(&replica_manager).await.0, 1);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_acks_leader_answers_before_the_high_watermark_moves() {
+        let (replica_manager, dir) = leader(vec![1, 2], 1).await;
+        let handler = ProduceHandler::new(replica_manager.clone(), None, None);
+
+        let request = produce_request(ACKS_LEADER, 30_000, vec![batch(b"a")]);
+        let response = handler.handle(&context(), request).await.unwrap();
+        let partition = partition_response(&response);
+        assert_eq!(partition.error_code, ErrorCode::None.code());
+        assert_eq!(partition.base_offset, 0);
+        // Replica 2 has not fetched it
+        assert_eq!(offsets(&replica_manager).await, (1, 0));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_acks_all_answers_once_the_high_watermark_covers_the_batch() {
+        let (replica_manager, dir) = leader(vec![1, 2], 1).await;
+        let handler = ProduceHandler::new(replica_manager.clone(), None, None);
+        let replicated = AtomicBool::new(false);
+
+        let request = produce_request(ACKS_ALL, 30_000, vec![batch(b"a"), batch(b"b")]);
+        let produce = async {
+            let response = handler.handle(&context(), request).await.unwrap();
+            assert!(replicated.load(Ordering::SeqCst));
+            response
+        };
+        let follower = async {
+            appended(&replica_manager).await;
+            assert_eq!(offsets(&replica_manager).await, (2, 0));
+            replicated.store(true, Ordering::SeqCst);
+            // Replica 2 fetching from the last offset + 1 says it holds both batches
+            let mut replica_manager = replica_manager.lock().await;
+            replica_manager
+                .prepare_follower_fetch(&events(), 2, None, 2)
+                .unwrap();
+        };
+        let (response, ()) = tokio::join!(produce, follower);
+
+        let partition = partition_response(&response);
+        assert_eq!(partition.error_code, ErrorCode::None.code());
+        assert_eq!(partition.base_offset, 0);
+        assert_eq!(offsets(&replica_manager).await, (2, 2));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_acks_all_times_out_when_followers_do_not_fetch() {
+        let (replica_manager, dir) = leader(vec![1, 2], 1).await;
+        let handler = ProduceHandler::new(replica_manager.clone(), None, None);
+
+        let request = produce_request(ACKS_ALL, 50, vec![batch(b"a")]);
+        let response = handler.handle(&context(), request).await.unwrap();
+        let partition = partition_response(&response);
+        assert_eq!(partition.error_code, ErrorCode::RequestTimedOut.code());
+        assert_eq!(partition.base_offset, -1);
+        assert_eq!(offsets(&replica_manager).await, (1, 0));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_acks_all_fails_when_the_isr_shrinks_below_min_insync_replicas() {
+        let (replica_manager, dir) = leader(vec![1, 2], 2).await;
+        let handler = ProduceHandler::new(replica_manager.clone(), None, None);
+
+        let request = produce_request(ACKS_ALL, 30_000, vec![batch(b"a")]);
+        let context = context();
+        let produce = handler.handle(&context, request);
+        let shrink = async {
+            appended(&replica_manager).await;
+            // Drops replica 2, which leaves the leader alone and moves the high watermark
+            replica_manager.lock().await.shrink_isrs(-1);
+        };
+        let (response, ()) = tokio::join!(produce, shrink);
+
+        let response = response.unwrap();
+        let partition = partition_response(&response);
+        assert_eq!(
+            partition.error_code,
+            ErrorCode::NotEnoughReplicasAfterAppend.code()
+        );
+        assert_eq!(offsets(&replica_manager).await, (1, 1));
+
+        // Refused up front now that the ISR is short
+        let request = produce_request(ACKS_ALL, 30_000, vec![batch(b"b")]);
+        let response = handler.handle(&context, request).await.unwrap();
+        assert_eq!(
+            partition_response(&response).error_code,
+            ErrorCode::NotEnoughReplicas.code()
+        );
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_a_failed_batch_leaves_the_ones_before_it_appended() {
+        let (replica_manager, dir) = leader(vec![1], 1).await;
+        let handler = ProduceHandler::new(replica_manager.clone(), None, None);
+
+        let idempotent = |value: &[u8], base_sequence| {
+            let mut batch = batch(value);
+            batch.producer_id = 7;
+            batch.producer_epoch = 0;
+            batch.base_sequence = base_sequence;
+            batch
+        };
+        let batches = vec![idempotent(b"a", 0), idempotent(b"c", 2)];
+        let response = handler
+            .handle(&context(), produce_request(ACKS_LEADER, 1000, batches))
+            .await
+            .unwrap();
+        let partition = partition_response(&response);
+        assert_eq!(
+            partition.error_code,
+            ErrorCode::OutOfOrderSequenceNumber.code()
+        );
+        assert_eq!(partition.base_offset, -1);
+        assert_eq!(offsets(&replica_manager).await.0, 1);
+
+        // The retry is answered from the first batch already in the log
+        let batches = vec![idempotent(b"a", 0), idempotent(b"b", 1)];
+        let response = handler
+            .handle(&context(), produce_request(ACKS_LEADER, 1000, batches))
+            .await
+            .unwrap();
+        let partition = partition_response(&response);
+        assert_eq!(partition.error_code, ErrorCode::None.code());
+        assert_eq!(partition.base_offset, 0);
+        assert_eq!(offsets(&replica_manager).await.0, 2);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
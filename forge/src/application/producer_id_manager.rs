@@ -0,0 +1,80 @@
+use crate::core::domain::producer_id_block::ProducerIdBlock;
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::ProducerIdBlockSource;
+
+pub struct ProducerIdManager {
+    pub broker_id: i32,
+    pub block_size: i64,
+    source: Box<dyn ProducerIdBlockSource>,
+    current_block: Option<ProducerIdBlock>,
+    next_producer_id: i64,
+}
+
+impl ProducerIdManager {
+    pub fn new(broker_id: i32, block_size: i64, source: Box<dyn ProducerIdBlockSource>) -> Self {
+        Self {
+            broker_id,
+            block_size,
+            source,
+            current_block: None,
+            next_producer_id: 0,
+        }
+    }
+
+    pub async fn generate_producer_id(&mut self) -> Result<i64, ErrorCode> {
+        let exhausted = match &self.current_block {
+            Some(block) => self.next_producer_id > block.last_producer_id(),
+            None => true,
+        };
+
+        if exhausted {
+            let block = self
+                .source
+                .allocate_block(self.broker_id, self.block_size)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to allocate producer id block: {}", e);
+                    ErrorCode::CoordinatorLoadInProgress
+                })?;
+            tracing::info!(
+                "Allocated producer id block [{}, {}] for broker {}",
+                block.first_producer_id,
+                block.last_producer_id(),
+                self.broker_id
+            );
+            self.next_producer_id = block.first_producer_id;
+            self.current_block = Some(block);
+        }
+
+        let producer_id = self.next_producer_id;
+        self.next_producer_id += 1;
+        Ok(producer_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::driven::producer_id::LocalProducerIdBlockSource;
+
+    #[tokio::test]
+    async fn test_producer_ids_are_not_reused_across_restarts() {
+        let dir = std::env::temp_dir().join(format!("forge-pid-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+
+        let mut manager =
+            ProducerIdManager::new(1, 2, Box::new(LocalProducerIdBlockSource::new(&dir)));
+        let ids = [
+            manager.generate_producer_id().await.unwrap(),
+            manager.generate_producer_id().await.unwrap(),
+            manager.generate_producer_id().await.unwrap(),
+        ];
+        assert_eq!(ids, [0, 1, 2]);
+
+        let mut restarted =
+            ProducerIdManager::new(1, 2, Box::new(LocalProducerIdBlockSource::new(&dir)));
+        assert_eq!(restarted.generate_producer_id().await.unwrap(), 4);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::VecDeque;
+
+use crate::application::partition::LogAppendInfo;
+use crate::core::domain::control_record::{ControlRecordType, EndTransactionMarker};
+use crate::core::domain::record_batch::{NO_SEQUENCE, RecordBatch};
+use crate::core::error::ErrorCode;
+use crate::shared::collections::FlatMap;
+
+/// How many of a producer's most recent batches are remembered for duplicate detection; the
+/// same as the maximum number of in-flight requests an idempotent producer may have.
+pub const NUM_BATCHES_TO_RETAIN: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BatchMetadata {
+    first_sequence: i32,
+    last_sequence: i32,
+    base_offset: i64,
+    last_offset: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ProducerStateEntry {
+    producer_epoch: i16,
+    /// Oldest first, at most `NUM_BATCHES_TO_RETAIN`.
+    batches: VecDeque<BatchMetadata>,
+    /// Max timestamp of the producer's last batch, which its expiration counts from.
+    last_timestamp: i64,
+}
+
+impl ProducerStateEntry {
+    fn last_sequence(&self) -> Option<i32> {
+        self.batches.back().map(|batch| batch.last_sequence)
+    }
+}
+
+/// A transaction that was aborted, spanning `first_offset` up to its marker at `last_offset`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AbortedTxn {
+    pub producer_id: i64,
+    pub first_offset: i64,
+    pub last_offset: i64,
+}
+
+/// Outcome of checking a batch from an idempotent producer before it is appended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SequenceCheck {
+    /// The batch is next in sequence and should be appended.
+    Append,
+    /// The batch was already appended; answer with its original offsets.
+    Duplicate(LogAppendInfo),
+}
+
+/// Sequences are 32-bit and wrap around to zero.
+fn next_sequence(sequence: i32) -> i32 {
+    if sequence == i32::MAX {
+        0
+    } else {
+        sequence + 1
+    }
+}
+
+fn last_sequence_of(batch: &RecordBatch) -> i32 {
+    let last = batch.base_sequence as i64 + batch.last_offset_delta as i64;
+    if last > i32::MAX as i64 {
+        (last - i32::MAX as i64 - 1) as i32
+    } else {
+        last as i32
+    }
+}
+
+/// The last few batch sequences each producer appended to one partition, so that retried
+/// batches are appended exactly once and gaps are reported back to the producer.
+#[derive(Debug, Default)]
+pub struct ProducerStateManager {
+    producers: FlatMap<i64, ProducerStateEntry>,
+    /// First offset of each producer's open transaction.
+    ongoing_txns: FlatMap<i64, i64>,
+    /// Oldest first; only transactions still in the log, as the state is rebuilt from it.
+    aborted_txns: Vec<AbortedTxn>,
+}
+
+impl ProducerStateManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validates a batch the leader is about to append. Batches without a producer id are not
+    /// idempotent and, like transaction markers, always pass.
+    pub fn check_sequence(&self, batch: &RecordBatch) -> Result<SequenceCheck, ErrorCode> {
+        if batch.producer_id < 0 || batch.is_control_batch() {
+            return Ok(SequenceCheck::Append);
+        }
+        // First batch we see from this producer, e.g. after its earlier records were deleted
+        let Some(entry) = self.producers.get(&batch.producer_id) else {
+            return Ok(SequenceCheck::Append);
+        };
+
+        if batch.producer_epoch < entry.producer_epoch {
+            return Err(ErrorCode::InvalidProducerEpoch);
+        }
+        // Written by a coordinator on the producer's behalf, e.g. transactional offset commits
+        if batch.base_sequence == NO_SEQUENCE {
+            return Ok(SequenceCheck::Append);
+        }
+        if batch.producer_epoch > entry.producer_epoch {
+            // A bumped epoch restarts the sequence
+            return if batch.base_sequence == 0 {
+                Ok(SequenceCheck::Append)
+            } else {
+                Err(ErrorCode::OutOfOrderSequenceNumber)
+            };
+        }
+
+        let last_sequence = last_sequence_of(batch);
+        if let Some(duplicate) = entry.batches.iter().find(|cached| {
+            cached.first_sequence == batch.base_sequence && cached.last_sequence == last_sequence
+        }) {
+            return Ok(SequenceCheck::Duplicate(LogAppendInfo {
+                base_offset: duplicate.base_offset,
+                last_offset: duplicate.last_offset,
+                size_in_bytes: 0,
+            }));
+        }
+
+        match entry.last_sequence() {
+            Some(last) if batch.base_sequence == next_sequence(last) => Ok(SequenceCheck::Append),
+            // Older than anything still cached, so it must have been appended long ago
+            Some(_)
+                if entry
+                    .batches
+                    .front()
+                    .is_some_and(|oldest| batch.base_sequence < oldest.first_sequence) =>
+            {
+                Err(ErrorCode::DuplicateSequenceNumber)
+            }
+            Some(_) => Err(ErrorCode::OutOfOrderSequenceNumber),
+            None => Ok(SequenceCheck::Append),
+        }
+    }
+
+    /// Remembers a batch that is now in the log, whether appended as leader or replicated.
+    pub fn update(&mut self, batch: &RecordBatch) {
+        if batch.producer_id < 0 {
+            return;
+        }
+        if batch.is_control_batch() {
+            self.complete_txn(batch);
+            self.fence(batch.producer_id, batch.producer_epoch, batch.max_timestamp);
+            return;
+        }
+        if batch.is_transactional() && !self.ongoing_txns.contains_key(&batch.producer_id) {
+            self.ongoing_txns
+                .insert(batch.producer_id, batch.base_offset);
+        }
+
+        let metadata = BatchMetadata {
+            first_sequence: batch.base_sequence,
+            last_sequence: last_sequence_of(batch),
+            base_offset: batch.base_offset,
+            last_offset: batch.last_offset(),
+        };
+        match self.producers.get_mut(&batch.producer_id) {
+            Some(entry) if entry.producer_epoch == batch.producer_epoch => {
+                entry.last_timestamp = batch.max_timestamp;
+                entry.batches.push_back(metadata);
+                while entry.batches.len() > NUM_BATCHES_TO_RETAIN {
+                    entry.batches.pop_front();
+                }
+            }
+            _ => {
+                self.producers.insert(
+                    batch.producer_id,
+                    ProducerStateEntry {
+                        producer_epoch: batch.producer_epoch,
+                        batches: VecDeque::from([metadata]),
+                        last_timestamp: batch.max_timestamp,
+                    },
+                );
+            }
+        }
+    }
+
+    /// A marker written when a newer producer instance fenced an older one carries the bumped
+    /// epoch; batches of the older instance must be rejected from then on.
+    fn fence(&mut self, producer_id: i64, producer_epoch: i16, timestamp: i64) {
+        match self.producers.get_mut(&producer_id) {
+            Some(entry) if entry.producer_epoch >= producer_epoch => {
+                entry.last_timestamp = entry.last_timestamp.max(timestamp);
+            }
+            _ => {
+                self.producers.insert(
+                    producer_id,
+                    ProducerStateEntry {
+                        producer_epoch,
+                        batches: VecDeque::new(),
+                        last_timestamp: timestamp,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Forgets the producers that wrote nothing for `expiration_ms`, unless they have a
+    /// transaction open. A forgotten producer's next batch is taken as its first, so
+    /// retries from before the gap are no longer caught.
+    pub fn remove_expired_producers(&mut self, now_ms: i64, expiration_ms: i64) -> usize {
+        let expired: Vec<i64> = self
+            .producers
+            .iter()
+            .filter(|(producer_id, entry)| {
+                now_ms - entry.last_timestamp >= expiration_ms
+                    && !self.ongoing_txns.contains_key(producer_id)
+            })
+            .map(|(producer_id, _)| *producer_id)
+            .collect();
+        for producer_id in &expired {
+            self.producers.remove(producer_id);
+        }
+        expired.len()
+    }
+
+    fn complete_txn(&mut self, batch: &RecordBatch) {
+        let Some(marker) = EndTransactionMarker::from_batch(batch) else {
+            return;
+        };
+        let Some(first_offset) = self.ongoing_txns.remove(&batch.producer_id) else {
+            return;
+        };
+        if marker.control_type == ControlRecordType::Abort {
+            self.aborted_txns.push(AbortedTxn {
+                producer_id: batch.producer_id,
+                first_offset,
+                last_offset: batch.base_offset,
+            });
+        }
+    }
+
+    /// The first offset of the oldest open transaction, which read_committed consumers may
+    /// not read past.
+    pub fn first_unstable_offset(&self) -> Option<i64> {
+        self.ongoing_txns.values().min().copied()
+    }
+
+    /// Aborted transactions with records in `[fetch_offset, upper_bound_offset)`.
+    pub fn aborted_txns_in_range(
+        &self,
+        fetch_offset: i64,
+        upper_bound_offset: i64,
+    ) -> Vec<AbortedTxn> {
+        self.aborted_txns
+            .iter()
+            .filter(|txn| txn.last_offset >= fetch_offset && txn.first_offset < upper_bound_offset)
+            .copied()
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.producers = FlatMap::new();
+        self.ongoing_txns = FlatMap::new();
+        self.aborted_txns.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::domain::record::Record;
+    use crate::core::domain::record_batch::TRANSACTIONAL_FLAG_MASK;
+
+    fn batch(base_sequence: i32, records: usize, base_offset: i64) -> RecordBatch {
+        let mut batch = RecordBatch::new(
+            0,
+            (0..records)
+                .map(|i| Record::new(i as i32, None, Some(b"v".to_vec())))
+                .collect(),
+        );
+        batch.producer_id = 7;
+        batch.producer_epoch = 0;
+        batch.base_sequence = base_sequence;
+        batch.base_offset = base_offset;
+        batch
+    }
+
+    #[test]
+    fn test_detects_duplicate_and_out_of_order_sequences() {
+        let mut state = ProducerStateManager::new();
+        for i in 0..6 {
+            let next = batch(i * 2, 2, i as i64 * 2);
+            assert_eq!(state.check_sequence(&next), Ok(SequenceCheck::Append));
+            state.update(&next);
+        }
+
+        // A retry of a recent batch returns the offsets it was first written at
+        assert_eq!(
+            state.check_sequence(&batch(8, 2, 0)),
+            Ok(SequenceCheck::Duplicate(LogAppendInfo {
+                base_offset: 8,
+                last_offset: 9,
+                size_in_bytes: 0,
+            }))
+        );
+        // Evicted from the cache
+        assert_eq!(
+            state.check_sequence(&batch(0, 2, 0)),
+            Err(ErrorCode::DuplicateSequenceNumber)
+        );
+        // Sequence 12 was skipped
+        assert_eq!(
+            state.check_sequence(&batch(14, 2, 0)),
+            Err(ErrorCode::OutOfOrderSequenceNumber)
+        );
+        assert_eq!(
+            state.check_sequence(&batch(12, 2, 0)),
+            Ok(SequenceCheck::Append)
+        );
+    }
+
+    #[test]
+    fn test_tracks_open_and_aborted_transactions() {
+        let mut state = ProducerStateManager::new();
+        let mut txn_batch = batch(0, 2, 10);
+        txn_batch.attributes = TRANSACTIONAL_FLAG_MASK;
+        state.update(&txn_batch);
+        assert_eq!(state.first_unstable_offset(), Some(10));
+
+        let marker = EndTransactionMarker {
+            control_type: ControlRecordType::Abort,
+            coordinator_epoch: 0,
+        };
+        // Written with a bumped epoch, as when a newer producer instance fences this one
+        let mut marker_batch = marker.to_batch(7, 1, 0);
+        marker_batch.base_offset = 12;
+        state.update(&marker_batch);
+        assert_eq!(
+            state.check_sequence(&batch(2, 1, 0)),
+            Err(ErrorCode::InvalidProducerEpoch)
+        );
+
+        assert_eq!(state.first_unstable_offset(), None);
+        assert_eq!(
+            state.aborted_txns_in_range(0, 11),
+            vec![AbortedTxn {
+                producer_id: 7,
+                first_offset: 10,
+                last_offset: 12,
+            }]
+        );
+        assert!(state.aborted_txns_in_range(13, 20).is_empty());
+    }
+
+    #[test]
+    fn test_idle_producers_expire_unless_in_a_transaction() {
+        let mut state = ProducerStateManager::new();
+        let mut idle = batch(0, 1, 0);
+        idle.max_timestamp = 1_000;
+        state.update(&idle);
+        let mut txn_batch = batch(0, 1, 1);
+        txn_batch.producer_id = 8;
+        txn_batch.max_timestamp = 1_000;
+        txn_batch.attributes = TRANSACTIONAL_FLAG_MASK;
+        state.update(&txn_batch);
+
+        assert_eq!(state.remove_expired_producers(5_999, 5_000), 0);
+        assert_eq!(state.remove_expired_producers(6_000, 5_000), 1);
+        // Forgotten, so a retry of its batch is no longer recognised
+        assert_eq!(state.check_sequence(&idle), Ok(SequenceCheck::Append));
+        txn_batch.base_sequence = 1;
+        assert_eq!(state.check_sequence(&txn_batch), Ok(SequenceCheck::Append));
+        txn_batch.base_sequence = 5;
+        assert_eq!(
+            state.check_sequence(&txn_batch),
+            Err(ErrorCode::OutOfOrderSequenceNumber)
+        );
+    }
+
+    #[test]
+    fn test_every_batch_in_the_window_answers_with_its_original_offsets() {
+        let mut state = ProducerStateManager::new();
+        for i in 0..NUM_BATCHES_TO_RETAIN as i32 + 1 {
+            state.update(&batch(i * 3, 3, 100 + i as i64 * 3));
+        }
+
+        // The oldest batch fell out when the sixth came in
+        assert_eq!(
+            state.check_sequence(&batch(0, 3, 0)),
+            Err(ErrorCode::DuplicateSequenceNumber)
+        );
+        for i in 1..NUM_BATCHES_TO_RETAIN as i32 + 1 {
+            let base_offset = 100 + i as i64 * 3;
+            assert_eq!(
+                state.check_sequence(&batch(i * 3, 3, 0)),
+                Ok(SequenceCheck::Duplicate(LogAppendInfo {
+                    base_offset,
+                    last_offset: base_offset + 2,
+                    size_in_bytes: 0,
+                }))
+            );
+        }
+        // Same first sequence as a cached batch but a different length is not a retry of it
+        assert_eq!(
+            state.check_sequence(&batch(3, 2, 0)),
+            Err(ErrorCode::OutOfOrderSequenceNumber)
+        );
+    }
+}
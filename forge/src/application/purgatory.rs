@@ -0,0 +1,295 @@
+use std::time::{Duration, Instant};
+
+use crate::shared::collections::FlatMap;
+
+/// An operation parked until a condition holds or its timeout passes. The purgatory retries
+/// `try_complete` whenever one of the operation's watch keys is checked.
+pub trait DelayedOperation {
+    type Context: ?Sized;
+
+    /// Completes the operation if its condition holds, returning whether it did.
+    fn try_complete(&mut self, ctx: &mut Self::Context) -> bool;
+
+    /// Completes the operation with whatever result is available once the timeout passes.
+    fn on_expiration(&mut self, ctx: &mut Self::Context);
+}
+
+struct WatchedOperation<K, O> {
+    operation: O,
+    keys: Vec<K>,
+    deadline: Instant,
+}
+
+pub struct DelayedOperationPurgatory<K: Ord, O> {
+    pub name: &'static str,
+    next_id: u64,
+    operations: FlatMap<u64, WatchedOperation<K, O>>,
+    watchers: FlatMap<K, Vec<u64>>,
+}
+
+impl<K: Ord + Clone, O: DelayedOperation> DelayedOperationPurgatory<K, O> {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            next_id: 0,
+            operations: FlatMap::new(),
+            watchers: FlatMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    pub fn is_watching(&self, key: &K) -> bool {
+        self.watchers.contains_key(key)
+    }
+
+    /// Completes the operation right away if possible, otherwise parks it under `keys`.
+    /// Returns whether the operation completed.
+    pub fn try_complete_else_watch(
+        &mut self,
+        mut operation: O,
+        keys: Vec<K>,
+        timeout: Duration,
+        ctx: &mut O::Context,
+    ) -> bool {
+        if operation.try_complete(ctx) {
+            return true;
+        }
+        self.watch(operation, keys, timeout);
+        false
+    }
+
+    pub fn watch(&mut self, operation: O, keys: Vec<K>, timeout: Duration) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        for key in &keys {
+            match self.watchers.get_mut(key) {
+                Some(ids) => ids.push(id),
+                None => {
+                    self.watchers.insert(key.clone(), vec![id]);
+                }
+            }
+        }
+
+        self.operations.insert(
+            id,
+            WatchedOperation {
+                operation,
+                keys,
+                deadline: Instant::now() + timeout,
+            },
+        );
+    }
+
+    /// Retries every operation watching `key` after a related event, e.g. an append to a
+    /// partition. Returns how many operations completed.
+    pub fn check_and_complete(&mut self, key: &K, ctx: &mut O::Context) -> usize {
+        let Some(ids) = self.watchers.get(key).cloned() else {
+            return 0;
+        };
+
+        let mut completed = 0;
+        for id in ids {
+            if let Some(watched) = self.operations.get_mut(&id)
+                && watched.operation.try_complete(ctx)
+            {
+                self.remove(id);
+                completed += 1;
+            }
+        }
+        completed
+    }
+
+    /// Expires every operation whose deadline has passed. Returns how many expired.
+    pub fn expire_timed_out(&mut self, ctx: &mut O::Context) -> usize {
+        let now = Instant::now();
+        let expired: Vec<u64> = self
+            .operations
+            .iter()
+            .filter(|(_, watched)| watched.deadline <= now)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in &expired {
+            if let Some(mut watched) = self.remove(*id) {
+                watched.operation.on_expiration(ctx);
+            }
+        }
+
+        if !expired.is_empty() {
+            tracing::debug!(
+                "Expired {} delayed operations in {} purgatory",
+                expired.len(),
+                self.name
+            );
+        }
+        expired.len()
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.operations.values().map(|w| w.deadline).min()
+    }
+
+    fn remove(&mut self, id: u64) -> Option<WatchedOperation<K, O>> {
+        let watched = self.operations.remove(&id)?;
+        for key in &watched.keys {
+            if let Some(ids) = self.watchers.get_mut(key) {
+                ids.retain(|watched_id| *watched_id != id);
+                if ids.is_empty() {
+                    self.watchers.remove(key);
+                }
+            }
+        }
+        Some(watched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Completes once the context reaches `target`, recording how it finished there.
+    struct Threshold {
+        name: &'static str,
+        target: u32,
+    }
+
+    struct Context {
+        level: u32,
+        completed: Vec<&'static str>,
+        expired: Vec<&'static str>,
+    }
+
+    impl DelayedOperation for Threshold {
+        type Context = Context;
+
+        fn try_complete(&mut self, ctx: &mut Context) -> bool {
+            if ctx.level < self.target {
+                return false;
+            }
+            ctx.completed.push(self.name);
+            true
+        }
+
+        fn on_expiration(&mut self, ctx: &mut Context) {
+            ctx.expired.push(self.name);
+        }
+    }
+
+    fn context() -> Context {
+        Context {
+            level: 0,
+            completed: vec![],
+            expired: vec![],
+        }
+    }
+
+    #[test]
+    fn test_operations_complete_when_their_key_is_checked() {
+        let mut purgatory = DelayedOperationPurgatory::new("test");
+        let mut ctx = context();
+        let timeout = Duration::from_secs(60);
+
+        ctx.level = 1;
+        let first = Threshold {
+            name: "now",
+            target: 1,
+        };
+        assert!(purgatory.try_complete_else_watch(first, vec!["a"], timeout, &mut ctx));
+        assert!(purgatory.is_empty());
+
+        let low = Threshold {
+            name: "low",
+            target: 2,
+        };
+        let high = Threshold {
+            name: "high",
+            target: 3,
+        };
+        assert!(!purgatory.try_complete_else_watch(low, vec!["a"], timeout, &mut ctx));
+        assert!(!purgatory.try_complete_else_watch(high, vec!["b"], timeout, &mut ctx));
+        assert_eq!(purgatory.len(), 2);
+
+        ctx.level = 3;
+        // Only operations watching the checked key are retried
+        assert_eq!(purgatory.check_and_complete(&"a", &mut ctx), 1);
+        assert_eq!(ctx.completed, ["now", "low"]);
+        assert!(!purgatory.is_watching(&"a"));
+        assert_eq!(purgatory.check_and_complete(&"c", &mut ctx), 0);
+        assert_eq!(purgatory.check_and_complete(&"b", &mut ctx), 1);
+        assert!(purgatory.is_empty());
+        assert!(ctx.expired.is_empty());
+    }
+
+    #[test]
+    fn test_timed_out_operations_expire() {
+        let mut purgatory = DelayedOperationPurgatory::new("test");
+        let mut ctx = context();
+        purgatory.watch(
+            Threshold {
+                name: "expired",
+                target: 1,
+            },
+            vec!["a"],
+            Duration::ZERO,
+        );
+        purgatory.watch(
+            Threshold {
+                name: "waiting",
+                target: 1,
+            },
+            vec!["a"],
+            Duration::from_secs(60),
+        );
+        assert!(purgatory.next_deadline().unwrap() <= Instant::now());
+
+        assert_eq!(purgatory.expire_timed_out(&mut ctx), 1);
+        assert_eq!(ctx.expired, ["expired"]);
+        assert!(ctx.completed.is_empty());
+        assert_eq!(purgatory.len(), 1);
+        assert!(purgatory.is_watching(&"a"));
+    }
+
+    #[test]
+    fn test_completed_operation_stops_watching_all_its_keys() {
+        let mut purgatory = DelayedOperationPurgatory::new("test");
+        let mut ctx = context();
+        let timeout = Duration::from_secs(60);
+        purgatory.watch(
+            Threshold {
+                name: "both",
+                target: 1,
+            },
+            vec!["a", "b"],
+            timeout,
+        );
+        purgatory.watch(
+            Threshold {
+                name: "b only",
+                target: 2,
+            },
+            vec!["b"],
+            timeout,
+        );
+
+        ctx.level = 1;
+        assert_eq!(purgatory.check_and_complete(&"a", &mut ctx), 1);
+        assert!(!purgatory.is_watching(&"a"));
+        // "b" keeps the operation still waiting on it, and only that one
+        assert!(purgatory.is_watching(&"b"));
+        assert_eq!(purgatory.check_and_complete(&"b", &mut ctx), 0);
+        assert_eq!(ctx.completed, ["both"]);
+
+        ctx.level = 2;
+        assert_eq!(purgatory.check_and_complete(&"b", &mut ctx), 1);
+        assert!(!purgatory.is_watching(&"b"));
+        assert!(purgatory.is_empty());
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::VecDeque;
+
+use crate::shared::collections::FlatMap;
+use crate::shared::constants::{
+    CONSUMER_BYTE_RATE_CONFIG, PRODUCER_BYTE_RATE_CONFIG, REQUEST_PERCENTAGE_CONFIG,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QuotaType {
+    /// Bytes per second produced.
+    Produce,
+    /// Bytes per second fetched by consumers.
+    Fetch,
+    /// Percentage of one handler thread spent on the client's requests.
+    Request,
+}
+
+impl QuotaType {
+    pub fn from_config_name(name: &str) -> Option<Self> {
+        match name {
+            PRODUCER_BYTE_RATE_CONFIG => Some(Self::Produce),
+            CONSUMER_BYTE_RATE_CONFIG => Some(Self::Fetch),
+            REQUEST_PERCENTAGE_CONFIG => Some(Self::Request),
+            _ => None,
+        }
+    }
+}
+
+/// Who a quota applies to. `None` matches any user or client id; the most specific match wins,
+/// with user taking precedence over client id.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct QuotaEntity {
+    pub user: Option<String>,
+    pub client_id: Option<String>,
+}
+
+/// A rate over the last `num_samples` windows of `sample_window_ms`.
+#[derive(Debug, Default)]
+struct Rate {
+    /// `(window_start_ms, total)` per sample, oldest first.
+    samples: VecDeque<(i64, f64)>,
+}
+
+impl Rate {
+    fn record(&mut self, value: f64, now_ms: i64, sample_window_ms: i64, num_samples: usize) {
+        match self.samples.back_mut() {
+            Some((start_ms, total)) if now_ms < *start_ms + sample_window_ms => *total += value,
+            _ => self.samples.push_back((now_ms, value)),
+        }
+        while self.samples.len() > num_samples {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Returns the rate per second and the window length (ms) it was measured over. The window
+    /// is never shorter than all but one sample, so a first burst is not read as a huge rate.
+    fn measure(&mut self, now_ms: i64, sample_window_ms: i64, num_samples: usize) -> (f64, i64) {
+        let max_age_ms = sample_window_ms * num_samples as i64;
+        while let Some((start_ms, _)) = self.samples.front()
+            && now_ms - start_ms >= max_age_ms
+        {
+            self.samples.pop_front();
+        }
+
+        let total: f64 = self.samples.iter().map(|(_, value)| value).sum();
+        let oldest_ms = self
+            .samples
+            .front()
+            .map_or(now_ms, |(start_ms, _)| *start_ms);
+        let window_ms = (now_ms - oldest_ms)
+            .max(sample_window_ms * (num_samples as i64 - 1))
+            .max(1);
+        (total * 1000.0 / window_ms as f64, window_ms)
+    }
+}
+
+/// Tracks produce, fetch and request-time rates per user and client id, and tells the request
+/// path how long to throttle a client that went over its quota.
+pub struct QuotaManager {
+    sample_window_ms: i64,
+    num_samples: usize,
+    quotas: FlatMap<(QuotaType, QuotaEntity), f64>,
+    sensors: FlatMap<(QuotaType, QuotaEntity), Rate>,
+}
+
+impl QuotaManager {
+    pub fn new(sample_window_ms: i64, num_samples: usize) -> Self {
+        Self {
+            sample_window_ms,
+            num_samples: num_samples.max(2),
+            quotas: FlatMap::new(),
+            sensors: FlatMap::new(),
+        }
+    }
+
+    /// Sets a quota, or removes it when `limit` is `None`.
+    pub fn set_quota(&mut self, quota_type: QuotaType, entity: QuotaEntity, limit: Option<f64>) {
+        let key = (quota_type, entity);
+        match limit {
+            Some(limit) => {
+                self.quotas.insert(key, limit);
+            }
+            None => {
+                self.quotas.remove(&key);
+            }
+        }
+    }
+
+    /// The quota that applies and the entity its usage is tracked under. A default quota
+    /// (no user, no client id) is tracked per user and client id pair.
+    fn quota_for(
+        &self,
+        quota_type: QuotaType,
+        user: &str,
+        client_id: &str,
+    ) -> Option<(QuotaEntity, f64)> {
+        let candidates = [
+            (Some(user), Some(client_id)),
+            (Some(user), None),
+            (None, Some(client_id)),
+            (None, None),
+        ];
+        candidates
+            .into_iter()
+            .find_map(|(user_match, client_match)| {
+                let entity = QuotaEntity {
+                    user: user_match.map(str::to_string),
+                    client_id: client_match.map(str::to_string),
+                };
+                let limit = *self.quotas.get(&(quota_type, entity.clone()))?;
+                let tracked = if user_match.is_none() && client_match.is_none() {
+                    QuotaEntity {
+                        user: Some(user.to_string()),
+                        client_id: Some(client_id.to_string()),
+                    }
+                } else {
+                    entity
+                };
+                Some((tracked, limit))
+            })
+    }
+
+    /// Records `value` (bytes, or milliseconds of handler time for request quotas) and returns
+    /// how long the client should be throttled, or 0 while it is within its quota.
+    pub fn record(
+        &mut self,
+        quota_type: QuotaType,
+        user: &str,
+        client_id: &str,
+        value: f64,
+        now_ms: i64,
+    ) -> i32 {
+        let Some((entity, limit)) = self.quota_for(quota_type, user, client_id) else {
+            return 0;
+        };
+
+        // Milliseconds per second over 10 is the percentage of one thread
+        let value = if quota_type == QuotaType::Request {
+            value / 10.0
+        } else {
+            value
+        };
+
+        let key = (quota_type, entity);
+        if !self.sensors.contains_key(&key) {
+            self.sensors.insert(key.clone(), Rate::default());
+        }
+        let Some(rate) = self.sensors.get_mut(&key) else {
+            return 0;
+        };
+        rate.record(value, now_ms, self.sample_window_ms, self.num_samples);
+        let (observed, window_ms) = rate.measure(now_ms, self.sample_window_ms, self.num_samples);
+        if observed <= limit {
+            return 0;
+        }
+
+        // Long enough that the rate over the window falls back to the quota
+        let throttle_ms = if limit > 0.0 {
+            (observed - limit) / limit * window_ms as f64
+        } else {
+            f64::MAX
+        };
+        let max_throttle_ms = self.sample_window_ms * self.num_samples as i64;
+        throttle_ms.min(max_throttle_ms as f64) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttles_client_over_its_byte_rate() {
+        let mut quota_manager = QuotaManager::new(1000, 11);
+        quota_manager.set_quota(
+            QuotaType::Produce,
+            QuotaEntity {
+                user: None,
+                client_id: Some("loader".into()),
+            },
+            Some(1000.0),
+        );
+
+        let now = 1_000_000;
+        assert_eq!(
+            quota_manager.record(QuotaType::Produce, "alice", "loader", 5000.0, now),
+            0
+        );
+        let throttle_ms =
+            quota_manager.record(QuotaType::Produce, "alice", "loader", 10_000.0, now + 100);
+        assert!(throttle_ms > 0);
+
+        // Another client id has no quota
+        assert_eq!(
+            quota_manager.record(QuotaType::Produce, "alice", "other", 1e9, now),
+            0
+        );
+        // Within quota again once the samples age out
+        assert_eq!(
+            quota_manager.record(QuotaType::Produce, "alice", "loader", 10.0, now + 20_000),
+            0
+        );
+    }
+}
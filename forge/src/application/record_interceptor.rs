@@ -0,0 +1,31 @@
+use crate::core::domain::record_batch::RecordBatch;
+
+/// Looks at, rewrites or refuses the records clients produce and consume, such as a WASM
+/// plugin from `adapters::driven::wasm_interceptor`. Control batches and the internal topics
+/// never reach it, and followers replicate what was appended untouched.
+pub trait RecordInterceptor: Send + Sync {
+    /// Called with each batch a producer sends, before it is appended. The records may be
+    /// rewritten but not added or removed; an error refuses the batch with its message.
+    fn on_produce(&self, topic: &str, batch: &mut RecordBatch) -> Result<(), String>;
+
+    /// Called with each batch a consumer fetches. The records may be rewritten or removed,
+    /// which the consumer sees as a gap in the offsets; an error fails the partition's fetch.
+    fn on_fetch(&self, topic: &str, batch: &mut RecordBatch) -> Result<(), String>;
+}
+
+/// Runs interceptors one after the other, each seeing what the previous one left.
+pub struct InterceptorChain(pub Vec<Box<dyn RecordInterceptor>>);
+
+impl RecordInterceptor for InterceptorChain {
+    fn on_produce(&self, topic: &str, batch: &mut RecordBatch) -> Result<(), String> {
+        self.0
+            .iter()
+            .try_for_each(|interceptor| interceptor.on_produce(topic, batch))
+    }
+
+    fn on_fetch(&self, topic: &str, batch: &mut RecordBatch) -> Result<(), String> {
+        self.0
+            .iter()
+            .try_for_each(|interceptor| interceptor.on_fetch(topic, batch))
+    }
+}
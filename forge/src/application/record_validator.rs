@@ -0,0 +1,219 @@
+use async_trait::async_trait;
+use serde::de::IgnoredAny;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::application::metadata_listener::BrokerMetadataListener;
+use crate::core::domain::metadata_records::CONFIG_RESOURCE_TOPIC;
+use crate::core::domain::record::Record;
+use crate::core::domain::record_batch::RecordBatch;
+use crate::core::error::ErrorCode;
+use crate::shared::collections::FlatMap;
+use crate::shared::constants::{RECORD_VALIDATORS_CONFIG, REQUIRED_HEADERS_CONFIG};
+
+pub const JSON_VALIDATOR: &str = "json";
+pub const REQUIRED_HEADERS_VALIDATOR: &str = "required.headers";
+
+/// The validator names a record.validators value lists.
+pub fn validator_names(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// Checks the records producers send to a topic before they are appended, after any
+/// interceptor ran. Topics opt in by listing validator names in their record.validators
+/// config; checks against an external service, such as a schema registry, register with
+/// `RecordValidators::register` next to the built-in ones.
+#[async_trait]
+pub trait RecordValidator: Send + Sync {
+    /// `configs` are the topic's config overrides, for settings of the validator. An error
+    /// refuses the record's batch with INVALID_RECORD and says what is wrong.
+    async fn validate(
+        &self,
+        topic: &str,
+        configs: &FlatMap<String, String>,
+        record: &Record,
+    ) -> Result<(), String>;
+}
+
+/// Requires the value to be a JSON document. Tombstones pass.
+pub struct JsonValidator;
+
+#[async_trait]
+impl RecordValidator for JsonValidator {
+    async fn validate(
+        &self,
+        _topic: &str,
+        _configs: &FlatMap<String, String>,
+        record: &Record,
+    ) -> Result<(), String> {
+        match &record.value {
+            Some(value) => serde_json::from_slice::<IgnoredAny>(value)
+                .map(|_| ())
+                .map_err(|e| format!("value is not JSON: {}", e)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Requires the headers named in the topic's record.validator.required.headers.
+pub struct RequiredHeadersValidator;
+
+#[async_trait]
+impl RecordValidator for RequiredHeadersValidator {
+    async fn validate(
+        &self,
+        _topic: &str,
+        configs: &FlatMap<String, String>,
+        record: &Record,
+    ) -> Result<(), String> {
+        let Some(required) = configs.get(&REQUIRED_HEADERS_CONFIG.to_string()) else {
+            return Ok(());
+        };
+        match required
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .find(|name| !record.headers.iter().any(|header| &*header.key == *name))
+        {
+            Some(missing) => Err(format!("header {} is missing", missing)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The validators by name, each run for the topics whose record.validators lists it.
+pub struct RecordValidators {
+    validators: FlatMap<String, Arc<dyn RecordValidator>>,
+    metadata: Arc<Mutex<BrokerMetadataListener>>,
+}
+
+impl RecordValidators {
+    /// Starts with the built-in `json` and `required.headers` validators.
+    pub fn new(metadata: Arc<Mutex<BrokerMetadataListener>>) -> Self {
+        let mut validators = Self {
+            validators: FlatMap::new(),
+            metadata,
+        };
+        validators.register(JSON_VALIDATOR, Arc::new(JsonValidator));
+        validators.register(
+            REQUIRED_HEADERS_VALIDATOR,
+            Arc::new(RequiredHeadersValidator),
+        );
+        validators
+    }
+
+    pub fn register(&mut self, name: &str, validator: Arc<dyn RecordValidator>) {
+        self.validators.insert(name.to_string(), validator);
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.validators.keys().cloned().collect()
+    }
+
+    /// Runs the validators `topic` enables over every record; returns the index of the first
+    /// batch refused, the error and why. Naming a validator this broker does not have
+    /// refuses every batch with INVALID_CONFIG; the controller rejects such names, so
+    /// only a topic configured before a validator was removed can hit this.
+    pub async fn validate(
+        &self,
+        topic: &str,
+        batches: &[RecordBatch],
+    ) -> Option<(usize, ErrorCode, String)> {
+        // Most topics enable no validator: look up the one key, and copy the topic's configs
+        // only for those that do.
+        let (enabled, configs) = {
+            let listener = self.metadata.lock().await;
+            let configs = listener
+                .metadata
+                .configs
+                .get(&(CONFIG_RESOURCE_TOPIC, topic.to_string()))?;
+            let enabled = configs.get(&RECORD_VALIDATORS_CONFIG.to_string())?.clone();
+            (enabled, configs.clone())
+        };
+
+        let mut validators = Vec::new();
+        for name in validator_names(&enabled) {
+            match self.validators.get(&name.to_string()) {
+                Some(validator) => validators.push((name, validator)),
+                None => {
+                    return Some((
+                        0,
+                        ErrorCode::InvalidConfig,
+                        format!("Topic {} names unknown record validator {}", topic, name),
+                    ));
+                }
+            }
+        }
+
+        for (index, batch) in batches.iter().enumerate() {
+            if batch.is_control_batch() {
+                continue;
+            }
+            for (record_index, record) in batch.records.iter().enumerate() {
+                for (name, validator) in &validators {
+                    if let Err(reason) = validator.validate(topic, &configs, record).await {
+                        return Some((
+                            index,
+                            ErrorCode::InvalidRecord,
+                            format!("Record {} refused by {}: {}", record_index, name, reason),
+                        ));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::domain::record::Header;
+
+    #[tokio::test]
+    async fn test_builtin_validators() {
+        let mut configs = FlatMap::new();
+        configs.insert(
+            REQUIRED_HEADERS_CONFIG.to_string(),
+            "trace-id, source".to_string(),
+        );
+        let mut record = Record::new(0, None, Some(br#"{"id": 1}"#.to_vec()));
+
+        assert!(
+            JsonValidator
+                .validate("events", &configs, &record)
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            RequiredHeadersValidator
+                .validate("events", &configs, &record)
+                .await,
+            Err("header trace-id is missing".to_string())
+        );
+
+        for key in ["trace-id", "source"] {
+            record.headers.push(Header {
+                key: key.into(),
+                value: None,
+            });
+        }
+        record.value = Some(b"{\"id\":".to_vec());
+        assert!(
+            RequiredHeadersValidator
+                .validate("events", &configs, &record)
+                .await
+                .is_ok()
+        );
+        assert!(
+            JsonValidator
+                .validate("events", &configs, &record)
+                .await
+                .unwrap_err()
+                .starts_with("value is not JSON")
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use futures::future::join_all;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::application::replica_manager::ReplicaManager;
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::FetchClient;
+use crate::protocol::fetch::{
+    FetchPartition, FetchRequest, FetchResponse, FetchTopic, ISOLATION_READ_UNCOMMITTED,
+};
+use crate::shared::collections::FlatMap;
+use crate::shared::constants::{
+    DEFAULT_REPLICA_FETCH_BACKOFF_MS, DEFAULT_REPLICA_FETCH_MAX_BYTES,
+    DEFAULT_REPLICA_FETCH_MIN_BYTES, DEFAULT_REPLICA_FETCH_WAIT_MAX_MS,
+};
+use crate::shared::scheduler::spawn_named;
+
+/// Pulls records for every partition this broker follows on one leader and appends them to
+/// the local log. The fetch offset doubles as the follower's log end offset report.
+pub struct ReplicaFetcher {
+    pub broker_id: i32,
+    pub leader_id: i32,
+    client: Box<dyn FetchClient>,
+    replica_manager: Arc<Mutex<ReplicaManager>>,
+}
+
+impl ReplicaFetcher {
+    pub fn new(
+        broker_id: i32,
+        leader_id: i32,
+        client: Box<dyn FetchClient>,
+        replica_manager: Arc<Mutex<ReplicaManager>>,
+    ) -> Self {
+        Self {
+            broker_id,
+            leader_id,
+            client,
+            replica_manager,
+        }
+    }
+
+    pub async fn run(mut self, cancel_token: CancellationToken) {
+        tracing::info!(
+            "Starting replica fetcher on broker {} for leader {}",
+            self.broker_id,
+            self.leader_id
+        );
+        let backoff = Duration::from_millis(DEFAULT_REPLICA_FETCH_BACKOFF_MS);
+
+        loop {
+            let fetched = tokio::select! {
+                fetched = self.fetch_once() => fetched,
+                _ = cancel_token.cancelled() => break,
+            };
+
+            let idle = match fetched {
+                Ok(fetched) => !fetched,
+                Err(e) => {
+                    tracing::warn!("Replica fetch from leader {} failed: {}", self.leader_id, e);
+                    true
+                }
+            };
+            if idle {
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = cancel_token.cancelled() => break,
+                }
+            }
+        }
+
+        tracing::info!(
+            "Stopped replica fetcher on broker {} for leader {}",
+            self.broker_id,
+            self.leader_id
+        );
+    }
+
+    /// Runs one fetch round trip. Returns `false` when no partition follows this leader.
+    async fn fetch_once(&mut self) -> Result<bool, String> {
+        let fetch_offsets = self
+            .replica_manager
+            .lock()
+            .await
+            .follower_fetch_offsets(self.leader_id);
+        if fetch_offsets.is_empty() {
+            return Ok(false);
+        }
+
+        let request = self.build_request(&fetch_offsets);
+        let response = self.client.fetch(&request).await?;
+        self.process_response(response).await;
+        Ok(true)
+    }
+
+    fn build_request(&self, fetch_offsets: &[(TopicPartition, i64, i32)]) -> FetchRequest {
+        let mut topics: Vec<FetchTopic> = Vec::new();
+        for (topic_partition, fetch_offset, leader_epoch) in fetch_offsets {
+            let partition = FetchPartition {
+                partition: topic_partition.partition,
+                current_leader_epoch: *leader_epoch,
+                fetch_offset: *fetch_offset,
+                log_start_offset: -1,
+                partition_max_bytes: DEFAULT_REPLICA_FETCH_MAX_BYTES,
+            };
+            match topics.iter_mut().find(|t| t.topic == topic_partition.topic) {
+                Some(topic) => topic.partitions.push(partition),
+                None => topics.push(FetchTopic {
+                    topic: topic_partition.topic.clone(),
+                    partitions: vec![partition],
+                }),
+            }
+        }
+
+        FetchRequest {
+            replica_id: self.broker_id,
+            max_wait_ms: DEFAULT_REPLICA_FETCH_WAIT_MAX_MS,
+            min_bytes: DEFAULT_REPLICA_FETCH_MIN_BYTES,
+            max_bytes: DEFAULT_REPLICA_FETCH_MAX_BYTES,
+            isolation_level: ISOLATION_READ_UNCOMMITTED,
+            session_id: 0,
+            session_epoch: -1,
+            topics,
+            forgotten_topics: vec![],
+            rack_id: String::new(),
+        }
+    }
+
+    async fn process_response(&mut self, response: FetchResponse) {
+        let mut appends = Vec::new();
+
+        for topic in response.responses {
+            for partition in topic.partitions {
+                let topic_partition =
+                    TopicPartition::new(topic.topic.clone(), partition.partition_index);
+
+                if partition.error_code == ErrorCode::FencedLeaderEpoch.code()
+                    || partition.error_code == ErrorCode::UnknownLeaderEpoch.code()
+                {
+                    // Either side is behind on metadata; retry once the controller catches us up
+                    tracing::info!(
+                        "Leader {} rejected our epoch for {} with error {}",
+                        self.leader_id,
+                        topic_partition,
+                        partition.error_code
+                    );
+                    continue;
+                }
+
+                if partition.error_code != ErrorCode::None.code() {
+                    tracing::warn!(
+                        "Leader {} returned error {} for {}",
+                        self.leader_id,
+                        partition.error_code,
+                        topic_partition
+                    );
+                    continue;
+                }
+
+                let batches = match partition.records.batches() {
+                    Ok(batches) => batches,
+                    Err(e) => {
+                        tracing::warn!(
+                            "Leader {} returned corrupt records for {}: {}",
+                            self.leader_id,
+                            topic_partition,
+                            e
+                        );
+                        continue;
+                    }
+                };
+                appends.push((topic_partition, batches, partition.high_watermark));
+            }
+        }
+
+        // Each partition's log writes on its own, so a slow one holds up only itself
+        let replica_manager = &self.replica_manager;
+        join_all(appends.into_iter().map(
+            |(topic_partition, batches, high_watermark)| async move {
+                if let Err(e) = ReplicaManager::append_records_to_follower(
+                    replica_manager,
+                    &topic_partition,
+                    batches,
+                    high_watermark,
+                )
+                .await
+                {
+                    tracing::warn!(
+                        "Failed to append replicated records to {}: {}",
+                        topic_partition,
+                        e
+                    );
+                }
+            },
+        ))
+        .await;
+    }
+}
+
+/// Runs one `ReplicaFetcher` per leader broker this broker follows.
+pub struct ReplicaFetcherManager {
+    pub broker_id: i32,
+    replica_manager: Arc<Mutex<ReplicaManager>>,
+    fetchers: FlatMap<i32, (CancellationToken, JoinHandle<()>)>,
+}
+
+impl ReplicaFetcherManager {
+    pub fn new(broker_id: i32, replica_manager: Arc<Mutex<ReplicaManager>>) -> Self {
+        Self {
+            broker_id,
+            replica_manager,
+            fetchers: FlatMap::new(),
+        }
+    }
+
+    pub fn has_fetcher(&self, leader_id: i32) -> bool {
+        self.fetchers.contains_key(&leader_id)
+    }
+
+    pub fn leader_ids(&self) -> Vec<i32> {
+        self.fetchers.keys().copied().collect()
+    }
+
+    /// Starts fetching from `leader_id` unless a fetcher for it is already running.
+    pub fn add_fetcher(&mut self, leader_id: i32, client: Box<dyn FetchClient>) {
+        if self.has_fetcher(leader_id) {
+            return;
+        }
+
+        let fetcher = ReplicaFetcher::new(
+            self.broker_id,
+            leader_id,
+            client,
+            self.replica_manager.clone(),
+        );
+        let cancel_token = CancellationToken::new();
+        let handle = spawn_named(
+            &format!("replica-fetcher-{}", leader_id),
+            fetcher.run(cancel_token.clone()),
+        );
+        self.fetchers.insert(leader_id, (cancel_token, handle));
+    }
+
+    pub async fn remove_fetcher(&mut self, leader_id: i32) {
+        if let Some((cancel_token, handle)) = self.fetchers.remove(&leader_id) {
+            cancel_token.cancel();
+            let _ = handle.await;
+        }
+    }
+
+    pub async fn shutdown(&mut self) {
+        let leader_ids: Vec<i32> = self.fetchers.keys().copied().collect();
+        for leader_id in leader_ids {
+            self.remove_fetcher(leader_id).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::fetch_handler::FetchHandler;
+    use crate::application::replica_manager::ACKS_LEADER;
+    use crate::application::request_context::RequestContext;
+    use crate::config::SecurityProtocol;
+    use crate::core::domain::principal::KafkaPrincipal;
+    use crate::core::domain::record::Record;
+    use crate::core::domain::record_batch::RecordBatch;
+    use async_trait::async_trait;
+
+    struct InProcessFetchClient(FetchHandler);
+
+    #[async_trait]
+    impl FetchClient for InProcessFetchClient {
+        async fn fetch(&mut self, request: &FetchRequest) -> Result<FetchResponse, String> {
+            let context = RequestContext {
+                principal: KafkaPrincipal::anonymous(),
+                client_host: "127.0.0.1".to_string(),
+                client_id: String::new(),
+                listener: SecurityProtocol::Plaintext,
+            };
+            Ok(self.0.handle(&context, request.clone()).await)
+        }
+    }
+
+    async fn replica(
+        broker_id: i32,
+        dir: &std::path::Path,
+        topic_partition: &TopicPartition,
+    ) -> ReplicaManager {
+        let mut replica_manager =
+            ReplicaManager::new(broker_id, dir.join(broker_id.to_string()), 1);
+        replica_manager
+            .create_partition(topic_partition.clone(), vec![1, 2])
+            .await
+            .unwrap();
+        replica_manager
+    }
+
+    #[tokio::test]
+    async fn test_follower_replicates_and_advances_leader_high_watermark() {
+        let dir = std::env::temp_dir().join(format!("forge-replica-{}", uuid::Uuid::new_v4()));
+        let topic_partition = TopicPartition::new("events", 0);
+
+        let mut leader = replica(1, &dir, &topic_partition).await;
+        leader
+            .become_leader(&topic_partition, 0, vec![1, 2])
+            .unwrap();
+        let mut follower = replica(2, &dir, &topic_partition).await;
+        follower
+            .become_follower(&topic_partition, 0, 1)
+            .await
+            .unwrap();
+        let leader = Arc::new(Mutex::new(leader));
+        let follower = Arc::new(Mutex::new(follower));
+
+        let batch = RecordBatch::new(0, vec![Record::new(0, None, Some(b"hello".to_vec()))]);
+        leader
+            .lock()
+            .await
+            .append_records(&topic_partition, ACKS_LEADER, batch)
+            .await
+            .unwrap();
+        assert_eq!(
+            leader
+                .lock()
+                .await
+                .get_partition(&topic_partition)
+                .unwrap()
+                .high_watermark(),
+            0
+        );
+
+        let mut manager = ReplicaFetcherManager::new(2, follower.clone());
+        manager.add_fetcher(
+            1,
+            Box::new(InProcessFetchClient(FetchHandler::new(
+                leader.clone(),
+                None,
+            ))),
+        );
+
+        let replicated = async {
+            loop {
+                let leader_high_watermark = leader
+                    .lock()
+                    .await
+                    .get_partition(&topic_partition)
+                    .unwrap()
+                    .high_watermark();
+                let follower_log_end_offset = follower
+                    .lock()
+                    .await
+                    .get_partition(&topic_partition)
+                    .unwrap()
+                    .log_end_offset();
+                if leader_high_watermark == 1 && follower_log_end_offset == 1 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), replicated)
+            .await
+            .unwrap();
+
+        manager.shutdown().await;
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -0,0 +1,1191 @@
+use futures::future::join_all;
+use futures::stream::{self, StreamExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, oneshot};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use crate::adapters::driven::storage::file_system::{FileSystem, TokioFileSystem};
+use crate::adapters::driven::storage::log::PartitionLog;
+use crate::application::delayed_fetch::{DelayedFetch, NewBytes};
+use crate::application::delayed_produce::DelayedProduce;
+use crate::application::log_actor::{LogHandle, LogOffsets};
+use crate::application::log_metrics::PartitionLogStats;
+use crate::application::partition::{
+    AppendStart, LogAppendInfo, LogRead, Partition, ReplicaRole, WrittenAppend,
+};
+use crate::application::producer_state::AbortedTxn;
+use crate::application::purgatory::DelayedOperationPurgatory;
+use crate::application::replica_selector::{
+    ClientMetadata, PartitionView, ReplicaSelector, ReplicaView,
+};
+use crate::config::LogConfig;
+use crate::core::domain::record_batch::{RecordBatch, Records};
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::ControllerChannel;
+use crate::protocol::fetch::ISOLATION_READ_COMMITTED;
+use crate::protocol::list_offsets::{EARLIEST_TIMESTAMP, LATEST_TIMESTAMP};
+use crate::shared::collections::{FlatMap, FlatSet};
+use crate::shared::constants::{CLEAN_SHUTDOWN_FILE, CLUSTER_METADATA_DIR};
+use crate::shared::scheduler::{spawn_named, spawn_periodic};
+use crate::shared::time::current_time_ms;
+
+pub const ACKS_NONE: i16 = 0;
+pub const ACKS_LEADER: i16 = 1;
+pub const ACKS_ALL: i16 = -1;
+
+#[derive(Debug, Clone)]
+pub struct FetchPartitionData {
+    pub high_watermark: i64,
+    pub last_stable_offset: i64,
+    pub log_start_offset: i64,
+    /// Only filled for read_committed fetches.
+    pub aborted_transactions: Vec<AbortedTxn>,
+    pub records: Records,
+}
+
+impl FetchPartitionData {
+    /// The records decoded, for the broker's own readers of internal topics.
+    pub fn batches(&self) -> Result<Vec<RecordBatch>, ErrorCode> {
+        self.records.batches().map_err(|e| {
+            tracing::error!("Failed to decode fetched batches: {}", e);
+            ErrorCode::KafkaStorageError
+        })
+    }
+}
+
+/// A fetch checked against the partition under the replica manager lock, read without it.
+pub struct PreparedFetch {
+    log_read: LogRead,
+    high_watermark: i64,
+    last_stable_offset: i64,
+    log_start_offset: i64,
+    /// Aborted transactions up to the read's max offset, for read_committed fetches.
+    aborted_transactions: Vec<AbortedTxn>,
+}
+
+impl PreparedFetch {
+    pub async fn read(self, max_bytes: usize) -> Result<FetchPartitionData, ErrorCode> {
+        let records = self.log_read.read(max_bytes).await?;
+        // Consumers drop records of these transactions; they learn of the abort from the marker
+        let last_offset = records
+            .raw()
+            .last()
+            .and_then(|last| RecordBatch::peek_offsets(last).ok());
+        let aborted_transactions = match last_offset {
+            Some((_, last_offset)) => self
+                .aborted_transactions
+                .into_iter()
+                .filter(|txn| txn.first_offset <= last_offset)
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Ok(FetchPartitionData {
+            high_watermark: self.high_watermark,
+            last_stable_offset: self.last_stable_offset,
+            log_start_offset: self.log_start_offset,
+            aborted_transactions,
+            records,
+        })
+    }
+}
+
+/// The offsets of every hosted partition, for readers that should not wait on the replica
+/// manager lock.
+#[derive(Clone, Default)]
+pub struct SharedOffsets(Arc<std::sync::RwLock<FlatMap<TopicPartition, Arc<LogOffsets>>>>);
+
+impl SharedOffsets {
+    pub fn get(&self, topic_partition: &TopicPartition) -> Option<Arc<LogOffsets>> {
+        self.0
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(topic_partition)
+            .cloned()
+    }
+
+    pub fn log_stats(&self) -> Vec<PartitionLogStats> {
+        self.0
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(topic_partition, offsets)| PartitionLogStats {
+                topic_partition: topic_partition.clone(),
+                size_bytes: offsets.size_bytes(),
+                segments: offsets.segments(),
+                log_start_offset: offsets.log_start_offset(),
+                log_end_offset: offsets.log_end_offset(),
+                high_watermark: offsets.high_watermark(),
+            })
+            .collect()
+    }
+
+    fn insert(&self, topic_partition: TopicPartition, offsets: Arc<LogOffsets>) {
+        self.0
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(topic_partition, offsets);
+    }
+
+    fn remove(&self, topic_partition: &TopicPartition) {
+        self.0
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(topic_partition);
+    }
+}
+
+/// A ListOffsets partition checked against its leader.
+pub struct OffsetLookup {
+    topic_partition: TopicPartition,
+    log: LogHandle,
+    /// Caps read_committed answers instead of the high watermark.
+    last_stable_offset: Option<i64>,
+    pub leader_epoch: i32,
+}
+
+impl OffsetLookup {
+    /// `(timestamp, offset)` of the first record at or after `timestamp`, or `(-1, -1)` if
+    /// there is none below the high watermark. The earliest and latest sentinels answer the
+    /// log start offset and the high watermark (the last stable offset under read_committed)
+    /// with a timestamp of -1.
+    pub async fn resolve(&self, timestamp: i64) -> Result<(i64, i64), ErrorCode> {
+        let offsets = self.log.offsets();
+        let max_offset = self
+            .last_stable_offset
+            .unwrap_or_else(|| offsets.high_watermark());
+        match timestamp {
+            EARLIEST_TIMESTAMP => Ok((-1, offsets.log_start_offset())),
+            LATEST_TIMESTAMP => Ok((-1, max_offset)),
+            timestamp => {
+                let found = self
+                    .log
+                    .offset_for_timestamp(timestamp)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!(
+                            "Failed to look up {} by timestamp: {}",
+                            self.topic_partition,
+                            e
+                        );
+                        ErrorCode::KafkaStorageError
+                    })?;
+                match found {
+                    Some((offset, timestamp)) if offset < max_offset => Ok((timestamp, offset)),
+                    _ => Ok((-1, -1)),
+                }
+            }
+        }
+    }
+}
+
+pub struct ReplicaManager {
+    pub broker_id: i32,
+    pub log_dir: PathBuf,
+    pub min_insync_replicas: usize,
+    /// Applied to partitions created from now on; see `set_log_config` to change it.
+    pub log_config: LogConfig,
+    /// Where partition logs keep their files; see `set_file_system`.
+    fs: Arc<dyn FileSystem>,
+    partitions: FlatMap<TopicPartition, Partition>,
+    /// Logs found and recovered at startup, until the metadata says to host them.
+    recovered: FlatMap<TopicPartition, Partition>,
+    offsets: SharedOffsets,
+    fetch_purgatory: DelayedOperationPurgatory<TopicPartition, DelayedFetch>,
+    produce_purgatory: DelayedOperationPurgatory<TopicPartition, DelayedProduce>,
+    /// `broker.rack` of every broker that has one, this broker included.
+    broker_racks: FlatMap<i32, String>,
+    /// Unset means consumers always fetch from the leader.
+    replica_selector: Option<Box<dyn ReplicaSelector>>,
+    /// Led partitions whose ISR changed since it was last reported to the controller.
+    isr_changes: FlatSet<TopicPartition>,
+}
+
+impl ReplicaManager {
+    pub fn new(broker_id: i32, log_dir: impl AsRef<Path>, min_insync_replicas: usize) -> Self {
+        Self {
+            broker_id,
+            log_dir: PathBuf::from(log_dir.as_ref()),
+            min_insync_replicas,
+            log_config: LogConfig::default(),
+            fs: Arc::new(TokioFileSystem),
+            partitions: FlatMap::new(),
+            recovered: FlatMap::new(),
+            offsets: SharedOffsets::default(),
+            fetch_purgatory: DelayedOperationPurgatory::new("Fetch"),
+            produce_purgatory: DelayedOperationPurgatory::new("Produce"),
+            broker_racks: FlatMap::new(),
+            replica_selector: None,
+            isr_changes: FlatSet::new(),
+        }
+    }
+
+    pub fn set_broker_rack(&mut self, broker_id: i32, rack: Option<String>) {
+        match rack {
+            Some(rack) => self.broker_racks.insert(broker_id, rack),
+            None => self.broker_racks.remove(&broker_id),
+        };
+    }
+
+    pub fn set_replica_selector(&mut self, replica_selector: Box<dyn ReplicaSelector>) {
+        self.replica_selector = Some(replica_selector);
+    }
+
+    /// Applies to logs recovered or created from now on.
+    pub fn set_file_system(&mut self, fs: Arc<dyn FileSystem>) {
+        self.fs = fs;
+    }
+
+    pub fn shared_offsets(&self) -> SharedOffsets {
+        self.offsets.clone()
+    }
+
+    /// Switches existing partition logs to `log_config` and keeps it for new ones.
+    pub fn set_log_config(&mut self, log_config: LogConfig) {
+        for partition in self.partitions.values() {
+            partition.log.set_config(log_config.clone());
+        }
+        self.log_config = log_config;
+    }
+
+    pub async fn create_partition(
+        &mut self,
+        topic_partition: TopicPartition,
+        replicas: Vec<i32>,
+    ) -> Result<(), String> {
+        if self.partitions.contains_key(&topic_partition) {
+            return Ok(());
+        }
+
+        let partition = match self.recovered.remove(&topic_partition) {
+            Some(mut partition) => {
+                partition.isr = replicas.clone();
+                partition.update_replicas(replicas);
+                partition
+            }
+            None => {
+                let dir = self.log_dir.join(topic_partition.to_string());
+                let log = PartitionLog::create(
+                    self.fs.clone(),
+                    dir,
+                    self.log_config.segment_bytes,
+                    self.log_config.retention_bytes,
+                    self.log_config.retention_ms,
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+                let log = LogHandle::spawn(log, self.log_config.clone());
+
+                let mut partition =
+                    Partition::new(topic_partition.clone(), self.broker_id, log, replicas);
+                partition
+                    .rebuild_producer_state()
+                    .await
+                    .map_err(|e| e.to_string())?;
+                partition
+            }
+        };
+        self.offsets
+            .insert(topic_partition.clone(), partition.log.offsets().clone());
+        self.partitions.insert(topic_partition, partition);
+        Ok(())
+    }
+
+    /// Opens every partition log in the log dir, cutting what a crash left torn, and replays
+    /// its producer state, `threads` partitions at a time. The partitions wait for
+    /// `create_partition`, as only the metadata says which of them this broker still hosts.
+    /// After a clean shutdown the logs are trusted without a scan.
+    pub async fn recover_logs(&mut self, threads: usize) -> Result<(), String> {
+        // Gone from here on, so a crash before the next clean shutdown scans again
+        let marker = self.log_dir.join(CLEAN_SHUTDOWN_FILE);
+        let clean_shutdown = match self.fs.remove_file(&marker).await {
+            Ok(()) => {
+                self.fs
+                    .sync_dir(&self.log_dir)
+                    .await
+                    .map_err(|e| format!("Failed to sync {}: {}", self.log_dir.display(), e))?;
+                true
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+            Err(e) => return Err(format!("Failed to remove {}: {}", marker.display(), e)),
+        };
+
+        let mut entries = tokio::fs::read_dir(&self.log_dir)
+            .await
+            .map_err(|e| format!("Failed to list {}: {}", self.log_dir.display(), e))?;
+        let mut dirs = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(|e| e.to_string())? {
+            let path = entry.path();
+            if path.is_dir()
+                && !path.ends_with(CLUSTER_METADATA_DIR)
+                && let Some(topic_partition) = TopicPartition::from_dir(&path)
+            {
+                dirs.push((topic_partition, path));
+            }
+        }
+
+        let started = Instant::now();
+        let count = dirs.len();
+        let (broker_id, log_config, fs) = (self.broker_id, &self.log_config, &self.fs);
+        let recoveries = stream::iter(dirs)
+            .map(|(topic_partition, dir)| {
+                spawn_named(
+                    "log-recovery",
+                    recover_partition(
+                        fs.clone(),
+                        topic_partition,
+                        dir,
+                        broker_id,
+                        log_config.clone(),
+                        clean_shutdown,
+                    ),
+                )
+            })
+            .buffer_unordered(threads.max(1))
+            .collect::<Vec<_>>()
+            .await;
+        for recovered in recoveries {
+            let partition = recovered.map_err(|e| format!("Log recovery failed: {}", e))??;
+            self.recovered
+                .insert(partition.topic_partition.clone(), partition);
+        }
+        tracing::info!(
+            "Recovered {} partition logs in {} ms{}",
+            count,
+            started.elapsed().as_millis(),
+            if clean_shutdown {
+                " after a clean shutdown"
+            } else {
+                ""
+            }
+        );
+        Ok(())
+    }
+
+    /// Drops a replica this broker no longer hosts, deleting its log.
+    pub async fn delete_partition(
+        &mut self,
+        topic_partition: &TopicPartition,
+    ) -> Result<(), String> {
+        let Some(partition) = self.partitions.remove(topic_partition) else {
+            return Ok(());
+        };
+        self.isr_changes.remove(topic_partition);
+        self.offsets.remove(topic_partition);
+        partition.log.close().await?;
+        tokio::fs::remove_dir_all(&partition.log.dir)
+            .await
+            .map_err(|e| format!("Failed to delete {}: {}", partition.log.dir.display(), e))?;
+        tracing::info!(
+            "Broker {} deleted its replica of {}",
+            self.broker_id,
+            topic_partition
+        );
+        Ok(())
+    }
+
+    /// Sets the replicas of a hosted partition, e.g. as a reassignment moves it.
+    pub fn update_replicas(&mut self, topic_partition: &TopicPartition, replicas: Vec<i32>) {
+        if let Some(partition) = self.partitions.get_mut(topic_partition)
+            && partition.replicas != replicas
+        {
+            partition.update_replicas(replicas);
+        }
+    }
+
+    /// Fsyncs every partition log, e.g. before the broker exits. A failure is logged and
+    /// the remaining logs are still flushed.
+    pub async fn flush_logs(&mut self) {
+        for partition in self.partitions.values() {
+            if let Err(e) = partition.log.flush().await {
+                tracing::error!("{}", e);
+            }
+        }
+    }
+
+    /// Stops every partition log once it is flushed, then leaves the clean-shutdown marker in
+    /// the log dir if they all stopped cleanly, so the next start skips recovery. Nothing may
+    /// append afterwards.
+    pub async fn close_logs(&mut self) {
+        let mut clean = true;
+        for partition in self.partitions.values().chain(self.recovered.values()) {
+            if let Err(e) = partition.log.close().await {
+                tracing::error!("{}", e);
+                clean = false;
+            }
+        }
+        if !clean {
+            return;
+        }
+
+        let marker = self.log_dir.join(CLEAN_SHUTDOWN_FILE);
+        let written = match self.fs.open_append(&marker).await {
+            Ok(_) => self.fs.sync_dir(&self.log_dir).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            tracing::error!("Failed to write {}: {}", marker.display(), e);
+        }
+    }
+
+    pub fn has_topic(&self, topic: &str) -> bool {
+        self.partitions
+            .keys()
+            .any(|topic_partition| topic_partition.topic == topic)
+    }
+
+    /// The partitions of `topic` this broker leads, in order.
+    pub fn led_partitions(&self, topic: &str) -> Vec<i32> {
+        self.partitions
+            .iter()
+            .filter(|(topic_partition, partition)| {
+                topic_partition.topic == topic && partition.is_leader()
+            })
+            .map(|(topic_partition, _)| topic_partition.partition)
+            .collect()
+    }
+
+    pub fn get_partition(&self, topic_partition: &TopicPartition) -> Option<&Partition> {
+        self.partitions.get(topic_partition)
+    }
+
+    pub fn become_leader(
+        &mut self,
+        topic_partition: &TopicPartition,
+        leader_epoch: i32,
+        isr: Vec<i32>,
+    ) -> Result<bool, ErrorCode> {
+        let partition = self
+            .partitions
+            .get_mut(topic_partition)
+            .ok_or(ErrorCode::UnknownTopicOrPartition)?;
+        Ok(partition.become_leader(leader_epoch, isr))
+    }
+
+    /// Switches to following `leader_id`, dropping records above the high watermark that the
+    /// new leader may not have.
+    pub async fn become_follower(
+        &mut self,
+        topic_partition: &TopicPartition,
+        leader_epoch: i32,
+        leader_id: i32,
+    ) -> Result<bool, ErrorCode> {
+        let partition = self
+            .partitions
+            .get_mut(topic_partition)
+            .ok_or(ErrorCode::UnknownTopicOrPartition)?;
+        if !partition.become_follower(leader_epoch, leader_id) {
+            return Ok(false);
+        }
+
+        let high_watermark = partition.high_watermark();
+        partition.truncate_to(high_watermark).await?;
+        Ok(true)
+    }
+
+    /// Returns the partition if this broker leads it, rejecting callers on another epoch first
+    /// so a stale client learns to refresh metadata rather than retry.
+    fn leader_partition_mut(
+        &mut self,
+        topic_partition: &TopicPartition,
+        current_leader_epoch: Option<i32>,
+    ) -> Result<&mut Partition, ErrorCode> {
+        let partition = self
+            .partitions
+            .get_mut(topic_partition)
+            .ok_or(ErrorCode::UnknownTopicOrPartition)?;
+
+        partition.validate_leader_epoch(current_leader_epoch)?;
+
+        if !partition.is_leader() {
+            return Err(ErrorCode::NotLeaderOrFollower);
+        }
+
+        Ok(partition)
+    }
+
+    /// Like `leader_partition_mut`, but a follower may also serve consumers (KIP-392).
+    fn readable_partition_mut(
+        &mut self,
+        topic_partition: &TopicPartition,
+        current_leader_epoch: Option<i32>,
+    ) -> Result<&mut Partition, ErrorCode> {
+        let partition = self
+            .partitions
+            .get_mut(topic_partition)
+            .ok_or(ErrorCode::UnknownTopicOrPartition)?;
+
+        partition.validate_leader_epoch(current_leader_epoch)?;
+
+        if partition.role == ReplicaRole::Offline {
+            return Err(ErrorCode::NotLeaderOrFollower);
+        }
+
+        Ok(partition)
+    }
+
+    /// Asks the replica selector where a consumer of a partition led here should fetch from.
+    /// Returns `None` when it should stay on this broker.
+    pub fn preferred_read_replica(
+        &self,
+        topic_partition: &TopicPartition,
+        client: &ClientMetadata,
+    ) -> Option<i32> {
+        let replica_selector = self.replica_selector.as_ref()?;
+        let partition = self
+            .partitions
+            .get(topic_partition)
+            .filter(|p| p.is_leader())?;
+
+        let now = current_time_ms();
+        let leader = ReplicaView {
+            broker_id: self.broker_id,
+            rack: self.broker_racks.get(&self.broker_id).cloned(),
+            log_end_offset: partition.log_end_offset(),
+            time_since_last_caught_up_ms: 0,
+        };
+        let mut replicas = vec![leader.clone()];
+        for replica_id in partition.isr.iter().filter(|id| **id != self.broker_id) {
+            if let Some(state) = partition.follower_states.get(replica_id) {
+                replicas.push(ReplicaView {
+                    broker_id: *replica_id,
+                    rack: self.broker_racks.get(replica_id).cloned(),
+                    log_end_offset: state.log_end_offset,
+                    time_since_last_caught_up_ms: now - state.last_caught_up_time_ms,
+                });
+            }
+        }
+
+        replica_selector
+            .select(topic_partition, client, &PartitionView { leader, replicas })
+            .filter(|broker_id| *broker_id != self.broker_id)
+    }
+
+    pub async fn append_records(
+        &mut self,
+        topic_partition: &TopicPartition,
+        acks: i16,
+        batch: RecordBatch,
+    ) -> Result<LogAppendInfo, ErrorCode> {
+        let mut batch = batch;
+        let pending = loop {
+            match self.start_append(topic_partition, acks, batch)? {
+                AppendStart::Duplicate(info) => return Ok(info),
+                AppendStart::Queued(pending) => break pending,
+                AppendStart::QueueFull(full, log) => {
+                    log.wait_for_room().await.map_err(log_storage_error)?;
+                    batch = full;
+                }
+            }
+        };
+        let written = pending.written().await;
+        self.complete_append(topic_partition, written).await
+    }
+
+    /// Like `append_records`, but holds the lock only to queue the batch and to account for
+    /// it, so appends to other partitions go on while this one is written.
+    pub async fn append_records_concurrently(
+        replica_manager: &Mutex<Self>,
+        topic_partition: &TopicPartition,
+        acks: i16,
+        batch: RecordBatch,
+    ) -> Result<LogAppendInfo, ErrorCode> {
+        let mut batch = batch;
+        let pending = loop {
+            let started =
+                replica_manager
+                    .lock()
+                    .await
+                    .start_append(topic_partition, acks, batch)?;
+            match started {
+                AppendStart::Duplicate(info) => return Ok(info),
+                AppendStart::Queued(pending) => break pending,
+                AppendStart::QueueFull(full, log) => {
+                    log.wait_for_room().await.map_err(log_storage_error)?;
+                    batch = full;
+                }
+            }
+        };
+        let written = pending.written().await;
+        replica_manager
+            .lock()
+            .await
+            .complete_append(topic_partition, written)
+            .await
+    }
+
+    fn start_append(
+        &mut self,
+        topic_partition: &TopicPartition,
+        acks: i16,
+        batch: RecordBatch,
+    ) -> Result<AppendStart, ErrorCode> {
+        if !matches!(acks, ACKS_NONE | ACKS_LEADER | ACKS_ALL) {
+            return Err(ErrorCode::InvalidRequiredAcks);
+        }
+
+        let min_insync_replicas = self.min_insync_replicas;
+        let partition = self.leader_partition_mut(topic_partition, None)?;
+
+        if acks == ACKS_ALL && partition.isr.len() < min_insync_replicas {
+            tracing::warn!(
+                "Rejecting produce to {}: ISR size {} is below min.insync.replicas {}",
+                topic_partition,
+                partition.isr.len(),
+                min_insync_replicas
+            );
+            return Err(ErrorCode::NotEnoughReplicas);
+        }
+
+        partition.start_append_to_leader(batch)
+    }
+
+    /// Accounts for a written append and wakes the operations it lets complete.
+    async fn complete_append(
+        &mut self,
+        topic_partition: &TopicPartition,
+        written: WrittenAppend,
+    ) -> Result<LogAppendInfo, ErrorCode> {
+        let partition = self
+            .partitions
+            .get_mut(topic_partition)
+            .ok_or(ErrorCode::NotLeaderOrFollower)?;
+        let high_watermark = partition.high_watermark();
+        let (info, exposed_bytes) = partition
+            .complete_append(written)
+            .instrument(tracing::debug_span!(
+                "append",
+                topic = %topic_partition.topic,
+                partition = topic_partition.partition
+            ))
+            .await?;
+        let high_watermark_advanced = partition.high_watermark() > high_watermark;
+
+        self.complete_delayed_requests(
+            topic_partition,
+            NewBytes {
+                appended: info.size_in_bytes,
+                committed: exposed_bytes,
+            },
+            high_watermark_advanced,
+        );
+        Ok(info)
+    }
+
+    /// Wakes operations waiting on `topic_partition` after its log end or high watermark moved.
+    fn complete_delayed_requests(
+        &mut self,
+        topic_partition: &TopicPartition,
+        new_bytes: NewBytes,
+        high_watermark_advanced: bool,
+    ) {
+        let mut new_bytes = new_bytes;
+        self.fetch_purgatory
+            .check_and_complete(topic_partition, &mut new_bytes);
+        if high_watermark_advanced {
+            self.produce_purgatory
+                .check_and_complete(topic_partition, &mut self.partitions);
+        }
+    }
+
+    /// Parks an acks=all produce until each partition's high watermark reaches its required
+    /// offset or `timeout` passes. The receiver fires when the produce should be re-checked.
+    pub fn watch_produce(
+        &mut self,
+        required_offsets: Vec<(TopicPartition, i64)>,
+        timeout: Duration,
+    ) -> oneshot::Receiver<()> {
+        self.produce_purgatory
+            .expire_timed_out(&mut self.partitions);
+
+        let keys = required_offsets.iter().map(|(tp, _)| tp.clone()).collect();
+        let (delayed_produce, rx) = DelayedProduce::new(required_offsets);
+        self.produce_purgatory.try_complete_else_watch(
+            delayed_produce,
+            keys,
+            timeout,
+            &mut self.partitions,
+        );
+        rx
+    }
+
+    /// Parks a fetch until `min_bytes` of new data is readable on `partitions` or `max_wait`
+    /// passes. The receiver fires when the fetch should be retried.
+    pub fn watch_fetch(
+        &mut self,
+        from_follower: bool,
+        partitions: Vec<TopicPartition>,
+        min_bytes: usize,
+        accumulated_bytes: usize,
+        max_wait: Duration,
+    ) -> oneshot::Receiver<()> {
+        // Drop fetches whose handlers already gave up so idle partitions don't keep them
+        self.fetch_purgatory
+            .expire_timed_out(&mut NewBytes::default());
+
+        let (delayed_fetch, rx) = DelayedFetch::new(from_follower, min_bytes, accumulated_bytes);
+        self.fetch_purgatory
+            .watch(delayed_fetch, partitions, max_wait);
+        rx
+    }
+
+    /// Serves a consumer fetch up to the high watermark, on the leader or a follower.
+    pub async fn fetch_records(
+        &mut self,
+        topic_partition: &TopicPartition,
+        current_leader_epoch: Option<i32>,
+        offset: i64,
+        max_bytes: usize,
+        isolation_level: i8,
+    ) -> Result<FetchPartitionData, ErrorCode> {
+        self.prepare_fetch(
+            topic_partition,
+            current_leader_epoch,
+            offset,
+            isolation_level,
+        )?
+        .read(max_bytes)
+        .await
+    }
+
+    /// Checks a consumer fetch and takes what it answers besides the records.
+    pub fn prepare_fetch(
+        &mut self,
+        topic_partition: &TopicPartition,
+        current_leader_epoch: Option<i32>,
+        offset: i64,
+        isolation_level: i8,
+    ) -> Result<PreparedFetch, ErrorCode> {
+        let partition = self.readable_partition_mut(topic_partition, current_leader_epoch)?;
+        let high_watermark = partition.high_watermark();
+        let last_stable_offset = partition.last_stable_offset();
+        let read_committed = isolation_level == ISOLATION_READ_COMMITTED;
+        let max_offset = if read_committed {
+            last_stable_offset
+        } else {
+            high_watermark
+        };
+        let log_read = partition.start_read(offset, max_offset)?;
+        let aborted_transactions = if read_committed {
+            partition.aborted_txns_in_range(offset, max_offset)
+        } else {
+            Vec::new()
+        };
+
+        Ok(PreparedFetch {
+            log_read,
+            high_watermark,
+            last_stable_offset,
+            log_start_offset: partition.log_start_offset(),
+            aborted_transactions,
+        })
+    }
+
+    /// Checks a ListOffsets partition against its leader; the lookup itself needs no lock.
+    pub fn prepare_list_offset(
+        &mut self,
+        topic_partition: &TopicPartition,
+        current_leader_epoch: Option<i32>,
+        isolation_level: i8,
+    ) -> Result<OffsetLookup, ErrorCode> {
+        let partition = self.leader_partition_mut(topic_partition, current_leader_epoch)?;
+        Ok(OffsetLookup {
+            topic_partition: topic_partition.clone(),
+            log: partition.log.clone(),
+            last_stable_offset: (isolation_level == ISOLATION_READ_COMMITTED)
+                .then(|| partition.last_stable_offset()),
+            leader_epoch: partition.leader_epoch,
+        })
+    }
+
+    /// Checks a fetch from follower `replica_id`, which reads up to the log end offset, and
+    /// records the fetch offset as the follower's log end offset, which may advance the high
+    /// watermark.
+    pub fn prepare_follower_fetch(
+        &mut self,
+        topic_partition: &TopicPartition,
+        replica_id: i32,
+        current_leader_epoch: Option<i32>,
+        offset: i64,
+    ) -> Result<PreparedFetch, ErrorCode> {
+        let partition = self.leader_partition_mut(topic_partition, current_leader_epoch)?;
+        let high_watermark = partition.high_watermark();
+        let isr_size = partition.isr.len();
+        let exposed_bytes = partition.update_follower_fetch_state(replica_id, offset)?;
+        let high_watermark_advanced = partition.high_watermark() > high_watermark;
+        let isr_expanded = partition.isr.len() > isr_size;
+
+        let log_read = partition.start_read(offset, partition.log_end_offset())?;
+        let prepared = PreparedFetch {
+            log_read,
+            high_watermark: partition.high_watermark(),
+            last_stable_offset: partition.last_stable_offset(),
+            log_start_offset: partition.log_start_offset(),
+            aborted_transactions: Vec::new(),
+        };
+
+        if isr_expanded {
+            self.isr_changes.insert(topic_partition.clone());
+        }
+        if high_watermark_advanced {
+            self.complete_delayed_requests(
+                topic_partition,
+                NewBytes {
+                    appended: 0,
+                    committed: exposed_bytes,
+                },
+                true,
+            );
+        }
+        Ok(prepared)
+    }
+
+    /// `(high_watermark, log_end_offset)` of each partition hosted here, to tell whether a
+    /// fetch read before they moved.
+    pub fn fetch_positions(&self, partitions: &[TopicPartition]) -> Vec<Option<(i64, i64)>> {
+        partitions
+            .iter()
+            .map(|topic_partition| {
+                let partition = self.partitions.get(topic_partition)?;
+                Some((partition.high_watermark(), partition.log_end_offset()))
+            })
+            .collect()
+    }
+
+    /// Partitions this broker follows for `leader_id`, with the offset to fetch next and the
+    /// leader epoch the follower knows about.
+    pub fn follower_fetch_offsets(&self, leader_id: i32) -> Vec<(TopicPartition, i64, i32)> {
+        self.partitions
+            .values()
+            .filter(|p| p.role == ReplicaRole::Follower { leader_id })
+            .map(|p| {
+                (
+                    p.topic_partition.clone(),
+                    p.log_end_offset(),
+                    p.leader_epoch,
+                )
+            })
+            .collect()
+    }
+
+    /// Appends batches fetched from the leader and adopts its high watermark. Holds the lock
+    /// only to queue each batch and to account for them once written.
+    pub async fn append_records_to_follower(
+        replica_manager: &Mutex<Self>,
+        topic_partition: &TopicPartition,
+        batches: Vec<RecordBatch>,
+        leader_high_watermark: i64,
+    ) -> Result<(), ErrorCode> {
+        let mut pending = Vec::new();
+        let mut batches = batches.into_iter();
+        let mut next = batches.next();
+        while let Some(batch) = next.take() {
+            let started = replica_manager
+                .lock()
+                .await
+                .start_append_to_follower(topic_partition, batch)?;
+            match started {
+                Some(AppendStart::QueueFull(batch, log)) => {
+                    log.wait_for_room().await.map_err(log_storage_error)?;
+                    next = Some(batch);
+                    continue;
+                }
+                Some(AppendStart::Queued(queued)) => pending.push(queued),
+                Some(AppendStart::Duplicate(_)) | None => {}
+            }
+            next = batches.next();
+        }
+        let written = join_all(pending.into_iter().map(|p| p.written())).await;
+
+        let mut replica_manager = replica_manager.lock().await;
+        let partition = replica_manager
+            .partitions
+            .get_mut(topic_partition)
+            .ok_or(ErrorCode::UnknownTopicOrPartition)?;
+        for written in written {
+            partition.complete_append(written).await?;
+        }
+
+        // Consumers may be parked on this follower waiting for committed data
+        let exposed_bytes = partition.update_follower_high_watermark(leader_high_watermark);
+        if exposed_bytes > 0 {
+            replica_manager.complete_delayed_requests(
+                topic_partition,
+                NewBytes {
+                    appended: 0,
+                    committed: exposed_bytes,
+                },
+                false,
+            );
+        }
+        Ok(())
+    }
+
+    /// Queues a batch fetched from the leader, or returns `None` if this replica has it.
+    fn start_append_to_follower(
+        &mut self,
+        topic_partition: &TopicPartition,
+        batch: RecordBatch,
+    ) -> Result<Option<AppendStart>, ErrorCode> {
+        let partition = self
+            .partitions
+            .get_mut(topic_partition)
+            .ok_or(ErrorCode::UnknownTopicOrPartition)?;
+        if !matches!(partition.role, ReplicaRole::Follower { .. }) {
+            return Err(ErrorCode::NotLeaderOrFollower);
+        }
+
+        // The leader returns whole batches, so the first may overlap what we already have
+        if batch.last_offset() < partition.next_offset() {
+            return Ok(None);
+        }
+        partition.start_append_to_follower(batch).map(Some)
+    }
+
+    /// Shrinks the ISR of every led partition whose followers fell behind by more than
+    /// `max_lag_ms`.
+    pub fn shrink_isrs(&mut self, max_lag_ms: i64) {
+        let mut advanced = Vec::new();
+        for partition in self.partitions.values_mut().filter(|p| p.is_leader()) {
+            let high_watermark = partition.high_watermark();
+            let isr_size = partition.isr.len();
+            let exposed_bytes = partition.maybe_shrink_isr(max_lag_ms);
+            if partition.isr.len() < isr_size {
+                self.isr_changes.insert(partition.topic_partition.clone());
+            }
+            if partition.high_watermark() > high_watermark {
+                advanced.push((partition.topic_partition.clone(), exposed_bytes));
+            }
+        }
+
+        for (topic_partition, exposed_bytes) in advanced {
+            self.complete_delayed_requests(
+                &topic_partition,
+                NewBytes {
+                    appended: 0,
+                    committed: exposed_bytes,
+                },
+                true,
+            );
+        }
+    }
+
+    /// Takes the ISR changes not yet reported to the controller, with the leader epoch each
+    /// was made at.
+    pub fn take_isr_changes(&mut self) -> Vec<(TopicPartition, i32, Vec<i32>)> {
+        let changed = std::mem::take(&mut self.isr_changes);
+        changed
+            .iter()
+            .filter_map(|topic_partition| {
+                let partition = self.partitions.get(topic_partition)?;
+                partition.is_leader().then(|| {
+                    (
+                        topic_partition.clone(),
+                        partition.leader_epoch,
+                        partition.isr.clone(),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Forgets the producers of every partition that wrote nothing for `expiration_ms`.
+    pub fn remove_expired_producers(&mut self, expiration_ms: i64) {
+        let now = current_time_ms();
+        for partition in self.partitions.values_mut() {
+            let expired = partition.remove_expired_producers(now, expiration_ms);
+            if expired > 0 {
+                tracing::debug!(
+                    "Expired {} idle producers of {}",
+                    expired,
+                    partition.topic_partition
+                );
+            }
+        }
+    }
+
+    pub fn start_producer_id_expiration(
+        replica_manager: Arc<Mutex<ReplicaManager>>,
+        expiration_ms: i64,
+        check_interval: Duration,
+        cancel_token: CancellationToken,
+    ) -> JoinHandle<()> {
+        spawn_periodic(
+            "producer-id-expiration",
+            check_interval,
+            cancel_token,
+            move || {
+                let replica_manager = replica_manager.clone();
+                async move {
+                    replica_manager
+                        .lock()
+                        .await
+                        .remove_expired_producers(expiration_ms);
+                }
+            },
+        )
+    }
+
+    pub fn start_isr_expiration(
+        replica_manager: Arc<Mutex<ReplicaManager>>,
+        max_lag_ms: i64,
+        cancel_token: CancellationToken,
+    ) -> JoinHandle<()> {
+        // Checking at half the lag bound keeps detection within 1.5x of it
+        let check_interval = Duration::from_millis((max_lag_ms / 2).max(1) as u64);
+        spawn_periodic("isr-expiration", check_interval, cancel_token, move || {
+            let replica_manager = replica_manager.clone();
+            async move {
+                replica_manager.lock().await.shrink_isrs(max_lag_ms);
+            }
+        })
+    }
+
+    /// Reports ISR changes of led partitions to the controller every `interval`. A rejected
+    /// change is dropped: the controller's answer arrives as metadata and resets the ISR.
+    pub fn start_isr_change_propagation(
+        replica_manager: Arc<Mutex<ReplicaManager>>,
+        channel: Box<dyn ControllerChannel>,
+        interval: Duration,
+        cancel_token: CancellationToken,
+    ) -> JoinHandle<()> {
+        let channel = Arc::new(Mutex::new(channel));
+        spawn_periodic(
+            "isr-change-propagation",
+            interval,
+            cancel_token,
+            move || {
+                let replica_manager = replica_manager.clone();
+                let channel = channel.clone();
+                async move {
+                    // Not held while the controller publishes back to this broker
+                    let (broker_id, changes) = {
+                        let mut replica_manager = replica_manager.lock().await;
+                        (
+                            replica_manager.broker_id,
+                            replica_manager.take_isr_changes(),
+                        )
+                    };
+                    let mut channel = channel.lock().await;
+                    for (topic_partition, leader_epoch, isr) in changes {
+                        let result = channel
+                            .alter_partition(
+                                broker_id,
+                                topic_partition.topic.clone(),
+                                topic_partition.partition,
+                                leader_epoch,
+                                isr,
+                            )
+                            .await;
+                        match result {
+                            Ok(ErrorCode::None) => {}
+                            Ok(error_code) => tracing::warn!(
+                                "Controller rejected the ISR change of {}: {}",
+                                topic_partition,
+                                error_code
+                            ),
+                            Err(e) => tracing::warn!(
+                                "Failed to report the ISR change of {}: {}",
+                                topic_partition,
+                                e
+                            ),
+                        }
+                    }
+                }
+            },
+        )
+    }
+}
+
+async fn recover_partition(
+    fs: Arc<dyn FileSystem>,
+    topic_partition: TopicPartition,
+    dir: PathBuf,
+    broker_id: i32,
+    log_config: LogConfig,
+    clean_shutdown: bool,
+) -> Result<Partition, String> {
+    let log = PartitionLog::open(
+        fs,
+        &dir,
+        log_config.segment_bytes,
+        log_config.retention_bytes,
+        log_config.retention_ms,
+        clean_shutdown,
+    )
+    .await
+    .map_err(|e| format!("Failed to recover {}: {}", dir.display(), e))?;
+    let log = LogHandle::spawn(log, log_config);
+
+    // The replicas come with the metadata
+    let mut partition = Partition::new(topic_partition, broker_id, log, Vec::new());
+    partition.rebuild_producer_state().await.map_err(|e| {
+        format!(
+            "Failed to rebuild the producer state of {}: {}",
+            dir.display(),
+            e
+        )
+    })?;
+    Ok(partition)
+}
+
+fn log_storage_error(e: String) -> ErrorCode {
+    tracing::error!("{}", e);
+    ErrorCode::KafkaStorageError
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::domain::record::Record;
+
+    #[tokio::test]
+    async fn test_recovered_logs_are_picked_up_by_create_partition() {
+        let dir = std::env::temp_dir().join(format!("forge-recovery-{}", uuid::Uuid::new_v4()));
+        let partitions: Vec<TopicPartition> =
+            (0..3).map(|p| TopicPartition::new("events", p)).collect();
+
+        let mut replica_manager = ReplicaManager::new(1, &dir, 1);
+        for (count, topic_partition) in partitions.iter().enumerate() {
+            replica_manager
+                .create_partition(topic_partition.clone(), vec![1])
+                .await
+                .unwrap();
+            replica_manager
+                .become_leader(topic_partition, 0, vec![1])
+                .unwrap();
+            for _ in 0..count {
+                let batch = RecordBatch::new(0, vec![Record::new(0, None, Some(b"a".to_vec()))]);
+                replica_manager
+                    .append_records(topic_partition, ACKS_LEADER, batch)
+                    .await
+                    .unwrap();
+            }
+        }
+        replica_manager.close_logs().await;
+        drop(replica_manager);
+        assert!(dir.join(CLEAN_SHUTDOWN_FILE).exists());
+
+        let mut replica_manager = ReplicaManager::new(1, &dir, 1);
+        replica_manager.recover_logs(2).await.unwrap();
+        assert!(!dir.join(CLEAN_SHUTDOWN_FILE).exists());
+        assert_eq!(replica_manager.recovered.len(), 3);
+        for (count, topic_partition) in partitions.iter().enumerate() {
+            replica_manager
+                .create_partition(topic_partition.clone(), vec![1, 2])
+                .await
+                .unwrap();
+            let partition = replica_manager.get_partition(topic_partition).unwrap();
+            assert_eq!(partition.log_end_offset(), count as i64);
+            assert_eq!(partition.replicas, [1, 2]);
+        }
+        assert!(replica_manager.recovered.is_empty());
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
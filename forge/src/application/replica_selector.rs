@@ -0,0 +1,115 @@
+use crate::core::domain::topic_partition::TopicPartition;
+
+/// What a fetching consumer told us about where it runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientMetadata {
+    pub rack_id: String,
+}
+
+/// The leader's view of one replica that could serve a consumer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplicaView {
+    pub broker_id: i32,
+    pub rack: Option<String>,
+    pub log_end_offset: i64,
+    pub time_since_last_caught_up_ms: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionView {
+    pub leader: ReplicaView,
+    /// In-sync replicas, leader included.
+    pub replicas: Vec<ReplicaView>,
+}
+
+/// Decides which replica a consumer should fetch from (KIP-392). Consulted by the leader only.
+pub trait ReplicaSelector: Send + Sync {
+    /// Returns the broker to fetch from, or `None` to keep fetching from the leader.
+    fn select(
+        &self,
+        topic_partition: &TopicPartition,
+        client: &ClientMetadata,
+        partition: &PartitionView,
+    ) -> Option<i32>;
+}
+
+/// Sends consumers to a replica in their own rack, preferring the leader and otherwise the
+/// most caught-up follower.
+pub struct RackAwareReplicaSelector;
+
+impl ReplicaSelector for RackAwareReplicaSelector {
+    fn select(
+        &self,
+        _topic_partition: &TopicPartition,
+        client: &ClientMetadata,
+        partition: &PartitionView,
+    ) -> Option<i32> {
+        if client.rack_id.is_empty() {
+            return None;
+        }
+        if partition.leader.rack.as_deref() == Some(client.rack_id.as_str()) {
+            return Some(partition.leader.broker_id);
+        }
+
+        partition
+            .replicas
+            .iter()
+            .filter(|replica| replica.rack.as_deref() == Some(client.rack_id.as_str()))
+            .max_by_key(|replica| {
+                (
+                    replica.log_end_offset,
+                    -replica.time_since_last_caught_up_ms,
+                )
+            })
+            .map(|replica| replica.broker_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replica(broker_id: i32, rack: &str, log_end_offset: i64) -> ReplicaView {
+        ReplicaView {
+            broker_id,
+            rack: Some(rack.to_string()),
+            log_end_offset,
+            time_since_last_caught_up_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_rack_aware_selector_prefers_most_caught_up_local_replica() {
+        let leader = replica(1, "us-east-1a", 10);
+        let partition = PartitionView {
+            leader: leader.clone(),
+            replicas: vec![
+                leader,
+                replica(2, "us-east-1b", 8),
+                replica(3, "us-east-1b", 10),
+            ],
+        };
+        let topic_partition = TopicPartition::new("events", 0);
+        let client = |rack_id: &str| ClientMetadata {
+            rack_id: rack_id.to_string(),
+        };
+
+        let selector = RackAwareReplicaSelector;
+        assert_eq!(
+            selector.select(&topic_partition, &client("us-east-1b"), &partition),
+            Some(3)
+        );
+        assert_eq!(
+            selector.select(&topic_partition, &client("us-east-1a"), &partition),
+            Some(1)
+        );
+        assert_eq!(
+            selector.select(&topic_partition, &client("us-east-1c"), &partition),
+            None
+        );
+        assert_eq!(
+            selector.select(&topic_partition, &client(""), &partition),
+            None
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use crate::application::audit;
+use crate::config::SecurityProtocol;
+use crate::core::domain::acl::{AclOperation, Resource};
+use crate::core::domain::principal::KafkaPrincipal;
+use crate::core::ports::driven::Authorizer;
+
+/// Who sent a request and from where, as seen by the handlers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestContext {
+    pub principal: KafkaPrincipal,
+    pub client_host: String,
+    pub client_id: String,
+    pub listener: SecurityProtocol,
+}
+
+impl RequestContext {
+    /// Allows everything when the broker runs without an authorizer.
+    pub async fn authorize(
+        &self,
+        authorizer: Option<&Arc<dyn Authorizer>>,
+        operation: AclOperation,
+        resource: &Resource,
+    ) -> bool {
+        match authorizer {
+            Some(authorizer) => {
+                let allowed = authorizer
+                    .authorize(&self.principal, &self.client_host, operation, resource)
+                    .await;
+                if !allowed {
+                    audit::authorization_denied(self, operation, resource);
+                }
+                allowed
+            }
+            None => true,
+        }
+    }
+
+    /// Like `authorize`, but a denial is not audited: for filtering what a response lists,
+    /// where leaving something out is not a failed request.
+    pub async fn is_authorized(
+        &self,
+        authorizer: Option<&Arc<dyn Authorizer>>,
+        operation: AclOperation,
+        resource: &Resource,
+    ) -> bool {
+        match authorizer {
+            Some(authorizer) => {
+                authorizer
+                    .authorize(&self.principal, &self.client_host, operation, resource)
+                    .await
+            }
+            None => true,
+        }
+    }
+}
@@ -0,0 +1,68 @@
+pub mod plain;
+pub mod scram;
+
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::application::sasl::plain::{PlainSaslClient, PlainSaslServer};
+use crate::application::sasl::scram::{ScramMechanism, ScramSaslClient, ScramSaslServer};
+use crate::core::ports::driven::CredentialStore;
+
+pub const PLAIN_MECHANISM: &str = "PLAIN";
+pub const SCRAM_SHA_256_MECHANISM: &str = "SCRAM-SHA-256";
+pub const SCRAM_SHA_512_MECHANISM: &str = "SCRAM-SHA-512";
+/// Mechanisms a listener may enable through `sasl.enabled.mechanisms`.
+pub const SUPPORTED_MECHANISMS: &[&str] = &[
+    PLAIN_MECHANISM,
+    SCRAM_SHA_256_MECHANISM,
+    SCRAM_SHA_512_MECHANISM,
+];
+
+/// The server side of one SASL exchange.
+#[async_trait]
+pub trait SaslServer: Send {
+    /// Takes the client's next message and returns the reply to send back. An error fails
+    /// the exchange, and its message is sent to the client.
+    async fn evaluate_response(&mut self, response: &[u8]) -> Result<Vec<u8>, String>;
+
+    fn is_complete(&self) -> bool;
+
+    /// The authenticated user, once the exchange is complete.
+    fn authorization_id(&self) -> Option<&str>;
+}
+
+/// The client side of one SASL exchange, used between brokers.
+pub trait SaslClient: Send {
+    /// Takes the server's last reply, empty at the start, and returns the next message to
+    /// send, or `None` when the reply only had to be checked.
+    fn evaluate_challenge(&mut self, challenge: &[u8]) -> Result<Option<Vec<u8>>, String>;
+
+    fn is_complete(&self) -> bool;
+}
+
+/// A server for `mechanism`, or `None` if it is not supported.
+pub fn create_server(
+    mechanism: &str,
+    credentials: Arc<dyn CredentialStore>,
+) -> Option<Box<dyn SaslServer>> {
+    if mechanism == PLAIN_MECHANISM {
+        return Some(Box::new(PlainSaslServer::new(credentials)));
+    }
+    ScramMechanism::from_name(mechanism).map(|mechanism| {
+        Box::new(ScramSaslServer::new(mechanism, credentials)) as Box<dyn SaslServer>
+    })
+}
+
+/// A client for `mechanism`, or `None` if it is not supported.
+pub fn create_client(
+    mechanism: &str,
+    username: String,
+    password: String,
+) -> Option<Box<dyn SaslClient>> {
+    if mechanism == PLAIN_MECHANISM {
+        return Some(Box::new(PlainSaslClient::new(username, password)));
+    }
+    ScramMechanism::from_name(mechanism).map(|mechanism| {
+        Box::new(ScramSaslClient::new(mechanism, username, password)) as Box<dyn SaslClient>
+    })
+}
@@ -0,0 +1,143 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::application::sasl::{SaslClient, SaslServer};
+use crate::core::ports::driven::CredentialStore;
+
+/// The client's only PLAIN message (RFC 4616): `[authzid] NUL username NUL password`.
+pub fn client_message(username: &str, password: &str) -> Vec<u8> {
+    format!("\0{}\0{}", username, password).into_bytes()
+}
+
+pub struct PlainSaslClient {
+    username: String,
+    password: String,
+    sent: bool,
+}
+
+impl PlainSaslClient {
+    pub fn new(username: String, password: String) -> Self {
+        Self {
+            username,
+            password,
+            sent: false,
+        }
+    }
+}
+
+impl SaslClient for PlainSaslClient {
+    fn evaluate_challenge(&mut self, _challenge: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        if self.sent {
+            return Err("PLAIN authentication already completed".to_string());
+        }
+        self.sent = true;
+        Ok(Some(client_message(&self.username, &self.password)))
+    }
+
+    fn is_complete(&self) -> bool {
+        self.sent
+    }
+}
+
+/// Checks the username and password of a PLAIN message against the credential store. The
+/// password crosses the network in the clear, so PLAIN belongs on trusted networks only.
+pub struct PlainSaslServer {
+    credentials: Arc<dyn CredentialStore>,
+    authorization_id: Option<String>,
+}
+
+impl PlainSaslServer {
+    pub fn new(credentials: Arc<dyn CredentialStore>) -> Self {
+        Self {
+            credentials,
+            authorization_id: None,
+        }
+    }
+}
+
+#[async_trait]
+impl SaslServer for PlainSaslServer {
+    async fn evaluate_response(&mut self, response: &[u8]) -> Result<Vec<u8>, String> {
+        if self.is_complete() {
+            return Err("PLAIN authentication already completed".to_string());
+        }
+        let message = std::str::from_utf8(response)
+            .map_err(|_| "Authentication failed: invalid UTF-8 in PLAIN message".to_string())?;
+        let fields: Vec<&str> = message.split('\0').collect();
+        let [authorization_id, username, password] = fields[..] else {
+            return Err(
+                "Authentication failed: expected [authzid] NUL username NUL password".to_string(),
+            );
+        };
+        if username.is_empty() {
+            return Err("Authentication failed: username not specified".to_string());
+        }
+        if password.is_empty() {
+            return Err("Authentication failed: password not specified".to_string());
+        }
+        if !authorization_id.is_empty() && authorization_id != username {
+            return Err(
+                "Authentication failed: authorization id must be empty or equal the username"
+                    .to_string(),
+            );
+        }
+        if !self.credentials.verify_plain(username, password).await {
+            return Err("Authentication failed: invalid username or password".to_string());
+        }
+
+        self.authorization_id = Some(username.to_string());
+        Ok(Vec::new())
+    }
+
+    fn is_complete(&self) -> bool {
+        self.authorization_id.is_some()
+    }
+
+    fn authorization_id(&self) -> Option<&str> {
+        self.authorization_id.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::domain::scram_credential::ScramCredential;
+
+    struct SingleUser;
+
+    #[async_trait]
+    impl CredentialStore for SingleUser {
+        async fn verify_plain(&self, username: &str, password: &str) -> bool {
+            username == "alice" && password == "alice-secret"
+        }
+
+        async fn scram_credential(&self, _: &str, _: &str) -> Option<ScramCredential> {
+            None
+        }
+    }
+
+    #[tokio::test]
+    async fn test_accepts_only_matching_credentials() {
+        let mut server = PlainSaslServer::new(Arc::new(SingleUser));
+        assert!(
+            server
+                .evaluate_response(&client_message("alice", "wrong"))
+                .await
+                .is_err()
+        );
+        assert!(
+            server
+                .evaluate_response(b"bob\0alice\0alice-secret")
+                .await
+                .is_err()
+        );
+        assert!(server.evaluate_response(b"alice\0alice").await.is_err());
+        assert!(!server.is_complete());
+
+        server
+            .evaluate_response(&client_message("alice", "alice-secret"))
+            .await
+            .unwrap();
+        assert_eq!(server.authorization_id(), Some("alice"));
+    }
+}
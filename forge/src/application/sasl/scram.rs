@@ -0,0 +1,480 @@
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use hmac::{Hmac, Mac};
+use rand::RngExt;
+use sha2::{Digest, Sha256, Sha512};
+use std::sync::Arc;
+
+use crate::application::sasl::{
+    SCRAM_SHA_256_MECHANISM, SCRAM_SHA_512_MECHANISM, SaslClient, SaslServer,
+};
+use crate::core::domain::scram_credential::{MIN_SCRAM_ITERATIONS, ScramCredential};
+use crate::core::ports::driven::CredentialStore;
+use crate::shared::byte::constant_time_eq;
+
+const NONCE_BYTES: usize = 24;
+/// The GS2 header of clients that neither support nor use channel binding.
+const GS2_HEADER: &str = "n,,";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScramMechanism {
+    Sha256,
+    Sha512,
+}
+
+impl ScramMechanism {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            SCRAM_SHA_256_MECHANISM => Some(Self::Sha256),
+            SCRAM_SHA_512_MECHANISM => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sha256 => SCRAM_SHA_256_MECHANISM,
+            Self::Sha512 => SCRAM_SHA_512_MECHANISM,
+        }
+    }
+
+    fn hash(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha256 => Sha256::digest(data).to_vec(),
+            Self::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+
+    fn hmac(self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha256 => {
+                let mut mac =
+                    Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+            Self::Sha512 => {
+                let mut mac =
+                    Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+
+    fn salted_password(self, password: &str, salt: &[u8], iterations: u32) -> Vec<u8> {
+        match self {
+            Self::Sha256 => {
+                pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(password.as_bytes(), salt, iterations)
+                    .to_vec()
+            }
+            Self::Sha512 => {
+                pbkdf2::pbkdf2_hmac_array::<Sha512, 64>(password.as_bytes(), salt, iterations)
+                    .to_vec()
+            }
+        }
+    }
+
+    fn client_key(self, salted_password: &[u8]) -> Vec<u8> {
+        self.hmac(salted_password, b"Client Key")
+    }
+
+    fn server_key(self, salted_password: &[u8]) -> Vec<u8> {
+        self.hmac(salted_password, b"Server Key")
+    }
+
+    /// Derives what the broker stores for `password`, with a fresh random salt.
+    pub fn credential(self, password: &str, iterations: u32) -> ScramCredential {
+        let salt = random_bytes().to_vec();
+        let salted_password = self.salted_password(password, &salt, iterations);
+        ScramCredential {
+            stored_key: self.hash(&self.client_key(&salted_password)),
+            server_key: self.server_key(&salted_password),
+            salt,
+            iterations,
+        }
+    }
+
+    /// Whether `password` is the one `credential` was derived from, for callers given the
+    /// password itself rather than a SCRAM exchange.
+    pub fn verify_password(self, credential: &ScramCredential, password: &str) -> bool {
+        let salted_password =
+            self.salted_password(password, &credential.salt, credential.iterations);
+        constant_time_eq(
+            &self.hash(&self.client_key(&salted_password)),
+            &credential.stored_key,
+        )
+    }
+}
+
+fn random_bytes() -> [u8; NONCE_BYTES] {
+    rand::rng().random()
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(x, y)| x ^ y).collect()
+}
+
+/// Usernames escape `,` and `=` as `=2C` and `=3D` (RFC 5802 section 5.1).
+fn encode_username(username: &str) -> String {
+    username.replace('=', "=3D").replace(',', "=2C")
+}
+
+fn decode_username(username: &str) -> Result<String, String> {
+    let decoded = username.replace("=2C", ",").replace("=3D", "=");
+    if decoded.len() + 2 * username.matches('=').count() != username.len() {
+        return Err(format!("Invalid escape in SCRAM username {}", username));
+    }
+    Ok(decoded)
+}
+
+/// Splits `n=user,r=nonce,...` into `(name, value)` attributes in message order.
+fn attributes(message: &str) -> Result<Vec<(&str, &str)>, String> {
+    message
+        .split(',')
+        .map(|attribute| match attribute.split_once('=') {
+            Some((name, value)) if name.len() == 1 => Ok((name, value)),
+            _ => Err(format!("Invalid SCRAM attribute {}", attribute)),
+        })
+        .collect()
+}
+
+fn attribute<'a>(attributes: &[(&str, &'a str)], name: &str) -> Result<&'a str, String> {
+    attributes
+        .iter()
+        .find(|(attribute, _)| *attribute == name)
+        .map(|(_, value)| *value)
+        .ok_or_else(|| format!("SCRAM message is missing attribute {}", name))
+}
+
+fn decode_base64(name: &str, value: &str) -> Result<Vec<u8>, String> {
+    STANDARD
+        .decode(value)
+        .map_err(|e| format!("Invalid base64 in SCRAM attribute {}: {}", name, e))
+}
+
+enum ServerState {
+    ReceiveClientFirst,
+    ReceiveClientFinal {
+        username: String,
+        gs2_header: String,
+        client_first_bare: String,
+        server_first: String,
+        nonce: String,
+        credential: ScramCredential,
+    },
+    Complete(String),
+    Failed,
+}
+
+/// Verifies SCRAM-SHA-256 or SCRAM-SHA-512 (RFC 5802, 7677) against salted credentials, so
+/// the password never crosses the network. Channel binding is not supported.
+pub struct ScramSaslServer {
+    mechanism: ScramMechanism,
+    credentials: Arc<dyn CredentialStore>,
+    state: ServerState,
+}
+
+impl ScramSaslServer {
+    pub fn new(mechanism: ScramMechanism, credentials: Arc<dyn CredentialStore>) -> Self {
+        Self {
+            mechanism,
+            credentials,
+            state: ServerState::ReceiveClientFirst,
+        }
+    }
+
+    /// Answers `gs2-header client-first-bare` with the salt, iterations and combined nonce.
+    async fn receive_client_first(&mut self, message: &str) -> Result<Vec<u8>, String> {
+        let mut parts = message.splitn(3, ',');
+        let (Some(cbind_flag), Some(authzid), Some(client_first_bare)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err("Invalid SCRAM client-first message".to_string());
+        };
+        if cbind_flag.starts_with("p=") {
+            return Err("SCRAM channel binding is not supported".to_string());
+        }
+        if cbind_flag != "n" && cbind_flag != "y" {
+            return Err(format!("Invalid SCRAM channel binding flag {}", cbind_flag));
+        }
+
+        let attributes = attributes(client_first_bare)?;
+        if attributes.first().map(|(name, _)| *name) == Some("m") {
+            return Err("SCRAM mandatory extensions are not supported".to_string());
+        }
+        let username = decode_username(attribute(&attributes, "n")?)?;
+        let client_nonce = attribute(&attributes, "r")?;
+        if !authzid.is_empty() && authzid != format!("a={}", encode_username(&username)) {
+            return Err(
+                "Authentication failed: authorization id must be empty or equal the username"
+                    .to_string(),
+            );
+        }
+
+        let Some(credential) = self
+            .credentials
+            .scram_credential(self.mechanism.name(), &username)
+            .await
+        else {
+            return Err("Authentication failed: invalid user credentials".to_string());
+        };
+
+        let nonce = format!("{}{}", client_nonce, STANDARD.encode(random_bytes()));
+        let server_first = format!(
+            "r={},s={},i={}",
+            nonce,
+            STANDARD.encode(&credential.salt),
+            credential.iterations
+        );
+        self.state = ServerState::ReceiveClientFinal {
+            username,
+            gs2_header: format!("{},{},", cbind_flag, authzid),
+            client_first_bare: client_first_bare.to_string(),
+            server_first: server_first.clone(),
+            nonce,
+            credential,
+        };
+        Ok(server_first.into_bytes())
+    }
+}
+
+#[async_trait]
+impl SaslServer for ScramSaslServer {
+    async fn evaluate_response(&mut self, response: &[u8]) -> Result<Vec<u8>, String> {
+        let message = std::str::from_utf8(response)
+            .map_err(|_| "Authentication failed: invalid UTF-8 in SCRAM message".to_string())?;
+        // Any error ends the exchange
+        match std::mem::replace(&mut self.state, ServerState::Failed) {
+            ServerState::ReceiveClientFirst => self.receive_client_first(message).await,
+            ServerState::ReceiveClientFinal {
+                username,
+                gs2_header,
+                client_first_bare,
+                server_first,
+                nonce,
+                credential,
+            } => {
+                let (without_proof, proof) = message
+                    .rsplit_once(",p=")
+                    .ok_or_else(|| "SCRAM client-final message is missing the proof".to_string())?;
+                let attributes = attributes(without_proof)?;
+                let channel_binding = decode_base64("c", attribute(&attributes, "c")?)?;
+                if channel_binding != gs2_header.as_bytes() {
+                    return Err("SCRAM channel binding does not match the GS2 header".to_string());
+                }
+                if attribute(&attributes, "r")? != nonce {
+                    return Err("Authentication failed: invalid SCRAM nonce".to_string());
+                }
+
+                let proof = decode_base64("p", proof)?;
+                let auth_message =
+                    format!("{},{},{}", client_first_bare, server_first, without_proof);
+                let client_signature = self
+                    .mechanism
+                    .hmac(&credential.stored_key, auth_message.as_bytes());
+                let client_key = xor(&proof, &client_signature);
+                if proof.len() != client_signature.len()
+                    || !constant_time_eq(&self.mechanism.hash(&client_key), &credential.stored_key)
+                {
+                    return Err("Authentication failed: invalid username or password".to_string());
+                }
+
+                let server_signature = self
+                    .mechanism
+                    .hmac(&credential.server_key, auth_message.as_bytes());
+                self.state = ServerState::Complete(username);
+                Ok(format!("v={}", STANDARD.encode(server_signature)).into_bytes())
+            }
+            ServerState::Complete(_) | ServerState::Failed => {
+                Err("SCRAM authentication already finished".to_string())
+            }
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        matches!(self.state, ServerState::Complete(_))
+    }
+
+    fn authorization_id(&self) -> Option<&str> {
+        match &self.state {
+            ServerState::Complete(username) => Some(username),
+            _ => None,
+        }
+    }
+}
+
+enum ClientState {
+    SendClientFirst,
+    ReceiveServerFirst { client_first_bare: String },
+    ReceiveServerFinal { server_signature: Vec<u8> },
+    Complete,
+    Failed,
+}
+
+/// Logs in with SCRAM, checking that the server also knows the credential.
+pub struct ScramSaslClient {
+    mechanism: ScramMechanism,
+    username: String,
+    password: String,
+    nonce: String,
+    state: ClientState,
+}
+
+impl ScramSaslClient {
+    pub fn new(mechanism: ScramMechanism, username: String, password: String) -> Self {
+        Self {
+            mechanism,
+            username,
+            password,
+            nonce: STANDARD.encode(random_bytes()),
+            state: ClientState::SendClientFirst,
+        }
+    }
+}
+
+impl SaslClient for ScramSaslClient {
+    fn evaluate_challenge(&mut self, challenge: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let challenge = std::str::from_utf8(challenge)
+            .map_err(|_| "Invalid UTF-8 in SCRAM server message".to_string())?;
+        match std::mem::replace(&mut self.state, ClientState::Failed) {
+            ClientState::SendClientFirst => {
+                let client_first_bare =
+                    format!("n={},r={}", encode_username(&self.username), self.nonce);
+                let message = format!("{}{}", GS2_HEADER, client_first_bare);
+                self.state = ClientState::ReceiveServerFirst { client_first_bare };
+                Ok(Some(message.into_bytes()))
+            }
+            ClientState::ReceiveServerFirst { client_first_bare } => {
+                let attributes = attributes(challenge)?;
+                let nonce = attribute(&attributes, "r")?;
+                if !nonce.starts_with(&self.nonce) {
+                    return Err("SCRAM server nonce does not extend the client nonce".to_string());
+                }
+                let salt = decode_base64("s", attribute(&attributes, "s")?)?;
+                let iterations: u32 = attribute(&attributes, "i")?
+                    .parse()
+                    .map_err(|_| "Invalid SCRAM iteration count".to_string())?;
+                if iterations < MIN_SCRAM_ITERATIONS {
+                    return Err(format!(
+                        "SCRAM iterations {} is below the minimum of {}",
+                        iterations, MIN_SCRAM_ITERATIONS
+                    ));
+                }
+
+                let without_proof = format!("c={},r={}", STANDARD.encode(GS2_HEADER), nonce);
+                let auth_message = format!("{},{},{}", client_first_bare, challenge, without_proof);
+                let salted_password =
+                    self.mechanism
+                        .salted_password(&self.password, &salt, iterations);
+                let client_key = self.mechanism.client_key(&salted_password);
+                let stored_key = self.mechanism.hash(&client_key);
+                let client_signature = self.mechanism.hmac(&stored_key, auth_message.as_bytes());
+                let proof = xor(&client_key, &client_signature);
+                let server_signature = self.mechanism.hmac(
+                    &self.mechanism.server_key(&salted_password),
+                    auth_message.as_bytes(),
+                );
+
+                self.state = ClientState::ReceiveServerFinal { server_signature };
+                Ok(Some(
+                    format!("{},p={}", without_proof, STANDARD.encode(proof)).into_bytes(),
+                ))
+            }
+            ClientState::ReceiveServerFinal { server_signature } => {
+                let attributes = attributes(challenge)?;
+                if let Ok(error) = attribute(&attributes, "e") {
+                    return Err(format!("SCRAM authentication failed: {}", error));
+                }
+                let signature = decode_base64("v", attribute(&attributes, "v")?)?;
+                if !constant_time_eq(&signature, &server_signature) {
+                    return Err("SCRAM server signature is invalid".to_string());
+                }
+                self.state = ClientState::Complete;
+                Ok(None)
+            }
+            ClientState::Complete | ClientState::Failed => {
+                Err("SCRAM authentication already finished".to_string())
+            }
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        matches!(self.state, ClientState::Complete)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct SingleCredential(ScramCredential);
+
+    #[async_trait]
+    impl CredentialStore for SingleCredential {
+        async fn verify_plain(&self, _: &str, _: &str) -> bool {
+            false
+        }
+
+        async fn scram_credential(&self, _: &str, username: &str) -> Option<ScramCredential> {
+            (username == "alice").then(|| self.0.clone())
+        }
+    }
+
+    #[test]
+    fn test_client_matches_rfc_7677_example() {
+        let mut client = ScramSaslClient::new(
+            ScramMechanism::Sha256,
+            "user".to_string(),
+            "pencil".to_string(),
+        );
+        client.nonce = "rOprNGfwEbeRWgbNEkqO".to_string();
+
+        let client_first = client.evaluate_challenge(b"").unwrap().unwrap();
+        assert_eq!(client_first, b"n,,n=user,r=rOprNGfwEbeRWgbNEkqO");
+        let client_final = client
+            .evaluate_challenge(
+                b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,\
+                  s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096",
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(client_final).unwrap(),
+            "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,\
+             p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+        );
+        assert_eq!(
+            client
+                .evaluate_challenge(b"v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=")
+                .unwrap(),
+            None
+        );
+        assert!(client.is_complete());
+    }
+
+    #[tokio::test]
+    async fn test_server_accepts_only_the_stored_password() {
+        let mechanism = ScramMechanism::Sha512;
+        let credentials = Arc::new(SingleCredential(mechanism.credential("secret", 4096)));
+
+        for (password, accepted) in [("secret", true), ("guess", false)] {
+            let mut client =
+                ScramSaslClient::new(mechanism, "alice".to_string(), password.to_string());
+            let mut server = ScramSaslServer::new(mechanism, credentials.clone());
+
+            let client_first = client.evaluate_challenge(b"").unwrap().unwrap();
+            let server_first = server.evaluate_response(&client_first).await.unwrap();
+            let client_final = client.evaluate_challenge(&server_first).unwrap().unwrap();
+            match server.evaluate_response(&client_final).await {
+                Ok(server_final) => {
+                    assert!(accepted);
+                    assert_eq!(client.evaluate_challenge(&server_final).unwrap(), None);
+                    assert_eq!(server.authorization_id(), Some("alice"));
+                }
+                Err(_) => assert!(!accepted && !server.is_complete()),
+            }
+        }
+    }
+}
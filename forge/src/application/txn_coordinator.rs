@@ -0,0 +1,882 @@
+use bytes::BytesMut;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::application::group_coordinator::GroupCoordinator;
+use crate::application::producer_id_manager::ProducerIdManager;
+use crate::application::replica_manager::{ACKS_ALL, ReplicaManager};
+use crate::core::domain::control_record::{ControlRecordType, EndTransactionMarker};
+use crate::core::domain::record::Record;
+use crate::core::domain::record_batch::RecordBatch;
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::domain::transaction_records::{
+    TransactionLogKey, TransactionLogValue, TransactionState,
+};
+use crate::core::error::ErrorCode;
+use crate::protocol::fetch::ISOLATION_READ_UNCOMMITTED;
+use crate::protocol::types::Type;
+use crate::shared::collections::{FlatMap, FlatSet};
+use crate::shared::constants::{CONSUMER_OFFSETS_TOPIC, TRANSACTION_STATE_TOPIC};
+use crate::shared::hash::internal_topic_partition_for;
+use crate::shared::scheduler::spawn_periodic;
+use crate::shared::time::current_time_ms;
+
+const LOAD_FETCH_MAX_BYTES: usize = 5 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct TransactionMetadata {
+    pub transactional_id: String,
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+    pub transaction_timeout_ms: i32,
+    pub state: TransactionState,
+    /// Partitions written in the ongoing transaction that still need a marker.
+    pub partitions: FlatSet<TopicPartition>,
+    pub start_timestamp: i64,
+    pub last_update_timestamp: i64,
+}
+
+impl TransactionMetadata {
+    fn transition_to(&mut self, target: TransactionState) {
+        debug_assert!(
+            target.valid_previous_states().contains(&self.state),
+            "Transaction {} cannot transition from {:?} to {:?}",
+            self.transactional_id,
+            self.state,
+            target
+        );
+        self.state = target;
+        self.last_update_timestamp = current_time_ms();
+    }
+
+    fn to_log_value(&self) -> TransactionLogValue {
+        TransactionLogValue {
+            producer_id: self.producer_id,
+            producer_epoch: self.producer_epoch,
+            transaction_timeout_ms: self.transaction_timeout_ms,
+            state: self.state,
+            partitions: self.partitions.iter().cloned().collect(),
+            last_update_timestamp: self.last_update_timestamp,
+            start_timestamp: self.start_timestamp,
+        }
+    }
+
+    fn from_log_value(transactional_id: String, value: TransactionLogValue) -> Self {
+        let mut partitions = FlatSet::new();
+        for tp in value.partitions {
+            partitions.insert(tp);
+        }
+        Self {
+            transactional_id,
+            producer_id: value.producer_id,
+            producer_epoch: value.producer_epoch,
+            transaction_timeout_ms: value.transaction_timeout_ms,
+            state: value.state,
+            partitions,
+            start_timestamp: value.start_timestamp,
+            last_update_timestamp: value.last_update_timestamp,
+        }
+    }
+
+    fn has_timed_out(&self, now: i64) -> bool {
+        self.state == TransactionState::Ongoing
+            && self.start_timestamp + self.transaction_timeout_ms as i64 <= now
+    }
+}
+
+/// Markers are written through the local replica manager only, so every partition of a
+/// transaction must be led by its coordinator: a single-broker limit, enforced when partitions
+/// are added.
+pub struct TransactionCoordinator {
+    pub transaction_state_partitions: i32,
+    pub max_transaction_timeout_ms: i32,
+    producer_id_manager: ProducerIdManager,
+    transactions: FlatMap<String, TransactionMetadata>,
+    /// The leader epoch each `__transaction_state` partition was last loaded at, which is also
+    /// the coordinator epoch of the markers for its transactions.
+    loaded_partitions: FlatMap<i32, i32>,
+    /// Producer ids whose marker reached `__consumer_offsets`, and whether they committed,
+    /// until the group coordinator applies their offsets.
+    offset_markers: Vec<(i64, bool)>,
+}
+
+impl TransactionCoordinator {
+    pub fn new(
+        transaction_state_partitions: i32,
+        max_transaction_timeout_ms: i32,
+        producer_id_manager: ProducerIdManager,
+    ) -> Self {
+        Self {
+            transaction_state_partitions,
+            max_transaction_timeout_ms,
+            producer_id_manager,
+            transactions: FlatMap::new(),
+            loaded_partitions: FlatMap::new(),
+            offset_markers: Vec::new(),
+        }
+    }
+
+    /// Takes the transactions whose offsets the group coordinator should now apply or drop.
+    pub fn take_offset_markers(&mut self) -> Vec<(i64, bool)> {
+        std::mem::take(&mut self.offset_markers)
+    }
+
+    pub fn transaction(&self, transactional_id: &str) -> Option<&TransactionMetadata> {
+        self.transactions.get(&transactional_id.to_string())
+    }
+
+    pub fn partition_for(&self, transactional_id: &str) -> i32 {
+        internal_topic_partition_for(transactional_id, self.transaction_state_partitions)
+    }
+
+    fn coordinator_epoch(&self, transactional_id: &str) -> i32 {
+        self.loaded_partitions
+            .get(&self.partition_for(transactional_id))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Loads the `__transaction_state` partition owning `transactional_id` the first time this
+    /// broker serves it as leader, and again after every leadership change.
+    pub async fn ensure_loaded(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        transactional_id: &str,
+    ) -> Result<(), ErrorCode> {
+        let partition = self.partition_for(transactional_id);
+        let topic_partition = TopicPartition::new(TRANSACTION_STATE_TOPIC, partition);
+        let leader_epoch = match replica_manager.get_partition(&topic_partition) {
+            Some(partition) if partition.is_leader() => partition.leader_epoch,
+            _ => return Err(ErrorCode::NotCoordinator),
+        };
+        if self.loaded_partitions.get(&partition) == Some(&leader_epoch) {
+            return Ok(());
+        }
+
+        self.load_partition(replica_manager, partition).await?;
+        self.loaded_partitions.insert(partition, leader_epoch);
+        Ok(())
+    }
+
+    pub async fn init_producer_id(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        transactional_id: Option<&str>,
+        transaction_timeout_ms: i32,
+    ) -> Result<(i64, i16), ErrorCode> {
+        let Some(transactional_id) = transactional_id else {
+            return Ok((self.producer_id_manager.generate_producer_id().await?, 0));
+        };
+
+        if transaction_timeout_ms <= 0 || transaction_timeout_ms > self.max_transaction_timeout_ms {
+            return Err(ErrorCode::InvalidTransactionTimeout);
+        }
+
+        let current = self
+            .transactions
+            .get(&transactional_id.to_string())
+            .cloned();
+        let mut updated = match current {
+            None => {
+                let now = current_time_ms();
+                TransactionMetadata {
+                    transactional_id: transactional_id.to_string(),
+                    producer_id: self.producer_id_manager.generate_producer_id().await?,
+                    producer_epoch: 0,
+                    transaction_timeout_ms,
+                    state: TransactionState::Empty,
+                    partitions: FlatSet::new(),
+                    start_timestamp: -1,
+                    last_update_timestamp: now,
+                }
+            }
+            Some(current) => match current.state {
+                TransactionState::PrepareCommit
+                | TransactionState::PrepareAbort
+                | TransactionState::PrepareEpochFence => {
+                    return Err(ErrorCode::ConcurrentTransactions);
+                }
+                TransactionState::Ongoing => {
+                    // Fence the previous producer instance and abort its transaction; the
+                    // client retries and gets a fresh epoch once the abort completes.
+                    self.fence_and_abort(replica_manager, transactional_id)
+                        .await?;
+                    return Err(ErrorCode::ConcurrentTransactions);
+                }
+                _ => {
+                    let mut updated = current;
+                    if updated.producer_epoch >= i16::MAX - 1 {
+                        updated.producer_id =
+                            self.producer_id_manager.generate_producer_id().await?;
+                        updated.producer_epoch = 0;
+                    } else {
+                        updated.producer_epoch += 1;
+                    }
+                    updated.transaction_timeout_ms = transaction_timeout_ms;
+                    updated.transition_to(TransactionState::Empty);
+                    updated
+                }
+            },
+        };
+
+        updated.partitions.clear();
+        self.persist(replica_manager, &updated).await?;
+
+        let result = (updated.producer_id, updated.producer_epoch);
+        tracing::info!(
+            "Initialized transactional id {} with producer id {} and epoch {}",
+            transactional_id,
+            result.0,
+            result.1
+        );
+        self.transactions
+            .insert(transactional_id.to_string(), updated);
+        Ok(result)
+    }
+
+    fn validate_producer(
+        &self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+    ) -> Result<TransactionMetadata, ErrorCode> {
+        let metadata = self
+            .transactions
+            .get(&transactional_id.to_string())
+            .ok_or(ErrorCode::InvalidProducerIdMapping)?;
+
+        if metadata.producer_id != producer_id {
+            return Err(ErrorCode::InvalidProducerIdMapping);
+        }
+        if metadata.producer_epoch != producer_epoch {
+            return Err(ErrorCode::ProducerFenced);
+        }
+        Ok(metadata.clone())
+    }
+
+    /// Fails for partitions this broker does not lead, as it could not write their markers.
+    pub async fn add_partitions_to_txn(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        partitions: Vec<TopicPartition>,
+    ) -> Result<(), ErrorCode> {
+        let mut updated = self.validate_producer(transactional_id, producer_id, producer_epoch)?;
+
+        match updated.state {
+            TransactionState::PrepareCommit
+            | TransactionState::PrepareAbort
+            | TransactionState::PrepareEpochFence => return Err(ErrorCode::ConcurrentTransactions),
+            TransactionState::Dead => return Err(ErrorCode::InvalidProducerIdMapping),
+            TransactionState::Ongoing
+                if partitions.iter().all(|tp| updated.partitions.contains(tp)) =>
+            {
+                return Ok(());
+            }
+            TransactionState::Ongoing => {}
+            _ => updated.start_timestamp = current_time_ms(),
+        }
+        for tp in &partitions {
+            match replica_manager.get_partition(tp) {
+                Some(partition) if partition.is_leader() => {}
+                Some(_) => return Err(ErrorCode::NotLeaderOrFollower),
+                None => return Err(ErrorCode::UnknownTopicOrPartition),
+            }
+        }
+
+        for tp in partitions {
+            updated.partitions.insert(tp);
+        }
+        updated.transition_to(TransactionState::Ongoing);
+
+        self.persist(replica_manager, &updated).await?;
+        self.transactions
+            .insert(transactional_id.to_string(), updated);
+        Ok(())
+    }
+
+    pub async fn end_txn(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        commit: bool,
+    ) -> Result<(), ErrorCode> {
+        let mut updated = self.validate_producer(transactional_id, producer_id, producer_epoch)?;
+
+        match (updated.state, commit) {
+            (TransactionState::Ongoing, _) => {}
+            (TransactionState::CompleteCommit, true) | (TransactionState::CompleteAbort, false) => {
+                return Ok(());
+            }
+            (TransactionState::PrepareCommit, true) | (TransactionState::PrepareAbort, false) => {
+                return Err(ErrorCode::ConcurrentTransactions);
+            }
+            _ => return Err(ErrorCode::InvalidTxnState),
+        }
+
+        updated.transition_to(if commit {
+            TransactionState::PrepareCommit
+        } else {
+            TransactionState::PrepareAbort
+        });
+        self.persist(replica_manager, &updated).await?;
+        self.transactions
+            .insert(transactional_id.to_string(), updated);
+
+        // The decision is durable now; markers that fail here are retried by the periodic task
+        self.complete_transaction(replica_manager, transactional_id)
+            .await;
+        Ok(())
+    }
+
+    async fn fence_and_abort(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        transactional_id: &str,
+    ) -> Result<(), ErrorCode> {
+        let Some(mut updated) = self
+            .transactions
+            .get(&transactional_id.to_string())
+            .cloned()
+        else {
+            return Err(ErrorCode::InvalidProducerIdMapping);
+        };
+
+        updated.producer_epoch = updated.producer_epoch.saturating_add(1);
+        updated.transition_to(TransactionState::PrepareAbort);
+        self.persist(replica_manager, &updated).await?;
+        self.transactions
+            .insert(transactional_id.to_string(), updated);
+
+        self.complete_transaction(replica_manager, transactional_id)
+            .await;
+        Ok(())
+    }
+
+    /// Writes markers for a prepared transaction and moves it to its complete state once
+    /// every partition has one. Partitions that fail keep the transaction prepared.
+    async fn complete_transaction(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        transactional_id: &str,
+    ) {
+        let Some(mut updated) = self
+            .transactions
+            .get(&transactional_id.to_string())
+            .cloned()
+        else {
+            return;
+        };
+
+        let (control_type, complete_state) = match updated.state {
+            TransactionState::PrepareCommit => {
+                (ControlRecordType::Commit, TransactionState::CompleteCommit)
+            }
+            TransactionState::PrepareAbort => {
+                (ControlRecordType::Abort, TransactionState::CompleteAbort)
+            }
+            _ => return,
+        };
+
+        let marker = EndTransactionMarker {
+            control_type,
+            coordinator_epoch: self.coordinator_epoch(transactional_id),
+        };
+        let pending: Vec<TopicPartition> = updated.partitions.iter().cloned().collect();
+        for tp in pending {
+            let batch = marker.to_batch(
+                updated.producer_id,
+                updated.producer_epoch,
+                current_time_ms(),
+            );
+            match replica_manager.append_records(&tp, ACKS_ALL, batch).await {
+                Ok(_) => {
+                    if tp.topic == CONSUMER_OFFSETS_TOPIC {
+                        self.offset_markers.push((
+                            updated.producer_id,
+                            control_type == ControlRecordType::Commit,
+                        ));
+                    }
+                    updated.partitions.remove(&tp);
+                }
+                Err(e) => tracing::warn!(
+                    "Failed to write {:?} marker for transaction {} to {}: {}",
+                    control_type,
+                    transactional_id,
+                    tp,
+                    e
+                ),
+            }
+        }
+
+        if updated.partitions.is_empty() {
+            updated.transition_to(complete_state);
+            if let Err(e) = self.persist(replica_manager, &updated).await {
+                tracing::warn!(
+                    "Failed to persist completion of transaction {}: {}",
+                    transactional_id,
+                    e
+                );
+            }
+            tracing::info!(
+                "Completed transaction {} as {:?}",
+                transactional_id,
+                complete_state
+            );
+        }
+
+        self.transactions
+            .insert(transactional_id.to_string(), updated);
+    }
+
+    /// Aborts transactions open longer than their timeout, fencing the producer, and retries
+    /// marker writes for transactions stuck in a prepared state.
+    pub async fn abort_timed_out_transactions(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+    ) -> usize {
+        let now = current_time_ms();
+        let mut aborted = 0;
+
+        let timed_out: Vec<String> = self
+            .transactions
+            .values()
+            .filter(|t| t.has_timed_out(now))
+            .map(|t| t.transactional_id.clone())
+            .collect();
+        for transactional_id in timed_out {
+            tracing::info!(
+                "Aborting transaction {} because it exceeded its timeout",
+                transactional_id
+            );
+            match self
+                .fence_and_abort(replica_manager, &transactional_id)
+                .await
+            {
+                Ok(()) => aborted += 1,
+                Err(e) => tracing::warn!(
+                    "Failed to abort timed out transaction {}: {}",
+                    transactional_id,
+                    e
+                ),
+            }
+        }
+
+        let prepared: Vec<String> = self
+            .transactions
+            .values()
+            .filter(|t| {
+                matches!(
+                    t.state,
+                    TransactionState::PrepareCommit | TransactionState::PrepareAbort
+                )
+            })
+            .map(|t| t.transactional_id.clone())
+            .collect();
+        for transactional_id in prepared {
+            self.complete_transaction(replica_manager, &transactional_id)
+                .await;
+        }
+
+        aborted
+    }
+
+    /// The group coordinator is locked only once the others are released.
+    pub fn start_transaction_timeouts(
+        coordinator: Arc<Mutex<TransactionCoordinator>>,
+        replica_manager: Arc<Mutex<ReplicaManager>>,
+        group_coordinator: Arc<Mutex<GroupCoordinator>>,
+        check_interval: Duration,
+        cancel_token: CancellationToken,
+    ) -> JoinHandle<()> {
+        spawn_periodic(
+            "transaction-timeouts",
+            check_interval,
+            cancel_token,
+            move || {
+                let coordinator = coordinator.clone();
+                let replica_manager = replica_manager.clone();
+                let group_coordinator = group_coordinator.clone();
+                async move {
+                    let offset_markers = {
+                        let mut coordinator = coordinator.lock().await;
+                        let mut replica_manager = replica_manager.lock().await;
+                        coordinator
+                            .abort_timed_out_transactions(&mut replica_manager)
+                            .await;
+                        coordinator.take_offset_markers()
+                    };
+                    if !offset_markers.is_empty() {
+                        let mut group_coordinator = group_coordinator.lock().await;
+                        for (producer_id, commit) in offset_markers {
+                            group_coordinator.complete_transactional_offsets(producer_id, commit);
+                        }
+                    }
+                }
+            },
+        )
+    }
+
+    async fn persist(
+        &self,
+        replica_manager: &mut ReplicaManager,
+        metadata: &TransactionMetadata,
+    ) -> Result<(), ErrorCode> {
+        let mut key = BytesMut::new();
+        TransactionLogKey {
+            transactional_id: metadata.transactional_id.clone(),
+        }
+        .encode(&mut key);
+
+        let mut value = BytesMut::new();
+        metadata.to_log_value().encode(&mut value);
+
+        let batch = RecordBatch::new(
+            current_time_ms(),
+            vec![Record::new(0, Some(key.to_vec()), Some(value.to_vec()))],
+        );
+        let topic_partition = TopicPartition::new(
+            TRANSACTION_STATE_TOPIC,
+            self.partition_for(&metadata.transactional_id),
+        );
+
+        match replica_manager
+            .append_records(&topic_partition, ACKS_ALL, batch)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(ErrorCode::UnknownTopicOrPartition | ErrorCode::NotLeaderOrFollower) => {
+                Err(ErrorCode::NotCoordinator)
+            }
+            Err(error) => {
+                tracing::error!(
+                    "Failed to write transaction {} to {}: {}",
+                    metadata.transactional_id,
+                    topic_partition,
+                    error
+                );
+                Err(ErrorCode::CoordinatorNotAvailable)
+            }
+        }
+    }
+
+    /// Rebuilds transaction state for one `__transaction_state` partition after this broker
+    /// becomes its leader. Prepared transactions are completed by the next periodic run.
+    pub async fn load_partition(
+        &mut self,
+        replica_manager: &mut ReplicaManager,
+        partition: i32,
+    ) -> Result<(), ErrorCode> {
+        let topic_partition = TopicPartition::new(TRANSACTION_STATE_TOPIC, partition);
+        let mut offset = replica_manager
+            .get_partition(&topic_partition)
+            .ok_or(ErrorCode::NotCoordinator)?
+            .log_start_offset();
+
+        loop {
+            let data = replica_manager
+                .fetch_records(
+                    &topic_partition,
+                    None,
+                    offset,
+                    LOAD_FETCH_MAX_BYTES,
+                    ISOLATION_READ_UNCOMMITTED,
+                )
+                .await?;
+            let batches = data.batches()?;
+            if batches.is_empty() {
+                break;
+            }
+
+            for batch in &batches {
+                for record in &batch.records {
+                    if let Err(e) = self.apply_record(record) {
+                        tracing::warn!(
+                            "Skipping malformed record in {} at batch offset {}: {}",
+                            topic_partition,
+                            batch.base_offset,
+                            e
+                        );
+                    }
+                }
+                offset = batch.last_offset() + 1;
+            }
+
+            if offset >= data.high_watermark {
+                break;
+            }
+        }
+
+        tracing::info!(
+            "Loaded transaction state from {} up to offset {}",
+            topic_partition,
+            offset
+        );
+        Ok(())
+    }
+
+    fn apply_record(&mut self, record: &Record) -> Result<(), String> {
+        let Some(key_bytes) = &record.key else {
+            return Err("Missing record key".to_string());
+        };
+        let key = TransactionLogKey::decode(&mut key_bytes.as_slice())?;
+
+        match &record.value {
+            Some(value) => {
+                let value = TransactionLogValue::decode(&mut value.as_slice())?;
+                self.transactions.insert(
+                    key.transactional_id.clone(),
+                    TransactionMetadata::from_log_value(key.transactional_id, value),
+                );
+            }
+            None => {
+                self.transactions.remove(&key.transactional_id);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::driven::producer_id::LocalProducerIdBlockSource;
+
+    const TRANSACTIONAL_ID: &str = "txn-1";
+
+    /// A broker leading `__transaction_state` at leader epoch 3 and one data partition.
+    async fn setup() -> (std::path::PathBuf, ReplicaManager, TopicPartition) {
+        let dir = std::env::temp_dir().join(format!("forge-txn-{}", uuid::Uuid::new_v4()));
+        let mut replica_manager = ReplicaManager::new(1, &dir, 1);
+        let state_partition = TopicPartition::new(TRANSACTION_STATE_TOPIC, 0);
+        let data_partition = TopicPartition::new("events", 0);
+        for (tp, leader_epoch) in [(&state_partition, 3), (&data_partition, 0)] {
+            replica_manager
+                .create_partition(tp.clone(), vec![1])
+                .await
+                .unwrap();
+            replica_manager
+                .become_leader(tp, leader_epoch, vec![1])
+                .unwrap();
+        }
+        (dir, replica_manager, data_partition)
+    }
+
+    fn coordinator(dir: &std::path::Path) -> TransactionCoordinator {
+        TransactionCoordinator::new(
+            1,
+            60_000,
+            ProducerIdManager::new(1, 10, Box::new(LocalProducerIdBlockSource::new(dir))),
+        )
+    }
+
+    async fn markers(
+        replica_manager: &mut ReplicaManager,
+        tp: &TopicPartition,
+    ) -> Vec<EndTransactionMarker> {
+        replica_manager
+            .fetch_records(tp, None, 0, 1024 * 1024, ISOLATION_READ_UNCOMMITTED)
+            .await
+            .unwrap()
+            .batches()
+            .unwrap()
+            .iter()
+            .filter_map(EndTransactionMarker::from_batch)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_reinit_bumps_the_epoch() {
+        let (dir, mut replica_manager, _) = setup().await;
+        let mut coordinator = coordinator(&dir);
+        coordinator
+            .ensure_loaded(&mut replica_manager, TRANSACTIONAL_ID)
+            .await
+            .unwrap();
+
+        let (producer_id, epoch) = coordinator
+            .init_producer_id(&mut replica_manager, Some(TRANSACTIONAL_ID), 1000)
+            .await
+            .unwrap();
+        assert_eq!(epoch, 0);
+        assert_eq!(
+            coordinator
+                .init_producer_id(&mut replica_manager, Some(TRANSACTIONAL_ID), 1000)
+                .await,
+            Ok((producer_id, 1))
+        );
+        // The old instance is fenced
+        assert_eq!(
+            coordinator
+                .end_txn(&mut replica_manager, TRANSACTIONAL_ID, producer_id, 0, true)
+                .await,
+            Err(ErrorCode::ProducerFenced)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prepared_transaction_refuses_concurrent_requests() {
+        let (dir, mut replica_manager, data_partition) = setup().await;
+        let mut coordinator = coordinator(&dir);
+        coordinator
+            .ensure_loaded(&mut replica_manager, TRANSACTIONAL_ID)
+            .await
+            .unwrap();
+        let (producer_id, epoch) = coordinator
+            .init_producer_id(&mut replica_manager, Some(TRANSACTIONAL_ID), 1000)
+            .await
+            .unwrap();
+
+        for state in [
+            TransactionState::PrepareCommit,
+            TransactionState::PrepareAbort,
+            TransactionState::PrepareEpochFence,
+        ] {
+            // As if its markers were still being written
+            coordinator
+                .transactions
+                .get_mut(&TRANSACTIONAL_ID.to_string())
+                .unwrap()
+                .state = state;
+            assert_eq!(
+                coordinator
+                    .init_producer_id(&mut replica_manager, Some(TRANSACTIONAL_ID), 1000)
+                    .await,
+                Err(ErrorCode::ConcurrentTransactions)
+            );
+            assert_eq!(
+                coordinator
+                    .add_partitions_to_txn(
+                        &mut replica_manager,
+                        TRANSACTIONAL_ID,
+                        producer_id,
+                        epoch,
+                        vec![data_partition.clone()],
+                    )
+                    .await,
+                Err(ErrorCode::ConcurrentTransactions)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_transaction_is_aborted_and_its_producer_fenced() {
+        let (dir, mut replica_manager, data_partition) = setup().await;
+        let mut coordinator = coordinator(&dir);
+        coordinator
+            .ensure_loaded(&mut replica_manager, TRANSACTIONAL_ID)
+            .await
+            .unwrap();
+        let (producer_id, epoch) = coordinator
+            .init_producer_id(&mut replica_manager, Some(TRANSACTIONAL_ID), 1000)
+            .await
+            .unwrap();
+        coordinator
+            .add_partitions_to_txn(
+                &mut replica_manager,
+                TRANSACTIONAL_ID,
+                producer_id,
+                epoch,
+                vec![data_partition.clone()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            coordinator
+                .abort_timed_out_transactions(&mut replica_manager)
+                .await,
+            0
+        );
+
+        coordinator
+            .transactions
+            .get_mut(&TRANSACTIONAL_ID.to_string())
+            .unwrap()
+            .start_timestamp -= 1000;
+        assert_eq!(
+            coordinator
+                .abort_timed_out_transactions(&mut replica_manager)
+                .await,
+            1
+        );
+        let transaction = coordinator.transaction(TRANSACTIONAL_ID).unwrap();
+        assert_eq!(transaction.state, TransactionState::CompleteAbort);
+        assert_eq!(transaction.producer_epoch, epoch + 1);
+        // The marker carries the leader epoch of the `__transaction_state` partition
+        assert_eq!(
+            markers(&mut replica_manager, &data_partition).await,
+            vec![EndTransactionMarker {
+                control_type: ControlRecordType::Abort,
+                coordinator_epoch: 3,
+            }]
+        );
+        assert_eq!(
+            coordinator
+                .end_txn(
+                    &mut replica_manager,
+                    TRANSACTIONAL_ID,
+                    producer_id,
+                    epoch,
+                    true
+                )
+                .await,
+            Err(ErrorCode::ProducerFenced)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_partition_rebuilds_transactions() {
+        let (dir, mut replica_manager, data_partition) = setup().await;
+        let mut coordinator = coordinator(&dir);
+        coordinator
+            .ensure_loaded(&mut replica_manager, TRANSACTIONAL_ID)
+            .await
+            .unwrap();
+        let (producer_id, epoch) = coordinator
+            .init_producer_id(&mut replica_manager, Some(TRANSACTIONAL_ID), 1000)
+            .await
+            .unwrap();
+        coordinator
+            .add_partitions_to_txn(
+                &mut replica_manager,
+                TRANSACTIONAL_ID,
+                producer_id,
+                epoch,
+                vec![data_partition.clone()],
+            )
+            .await
+            .unwrap();
+
+        let mut reloaded = self::coordinator(&dir);
+        reloaded
+            .ensure_loaded(&mut replica_manager, TRANSACTIONAL_ID)
+            .await
+            .unwrap();
+        let transaction = reloaded.transaction(TRANSACTIONAL_ID).unwrap();
+        assert_eq!(transaction.producer_id, producer_id);
+        assert_eq!(transaction.producer_epoch, epoch);
+        assert_eq!(transaction.state, TransactionState::Ongoing);
+        assert!(transaction.partitions.contains(&data_partition));
+
+        // A partition this broker does not lead cannot get a marker
+        assert_eq!(
+            reloaded
+                .add_partitions_to_txn(
+                    &mut replica_manager,
+                    TRANSACTIONAL_ID,
+                    producer_id,
+                    epoch,
+                    vec![TopicPartition::new("events", 1)],
+                )
+                .await,
+            Err(ErrorCode::UnknownTopicOrPartition)
+        );
+    }
+}
@@ -0,0 +1,602 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::application::group_coordinator::GroupCoordinator;
+use crate::application::replica_manager::ReplicaManager;
+use crate::application::request_context::RequestContext;
+use crate::application::txn_coordinator::TransactionCoordinator;
+use crate::core::domain::acl::{AclOperation, Resource, ResourceType};
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::Authorizer;
+use crate::protocol::add_offsets_to_txn::{AddOffsetsToTxnRequest, AddOffsetsToTxnResponse};
+use crate::protocol::add_partitions_to_txn::{
+    AddPartitionsToTxnPartitionResult, AddPartitionsToTxnRequest, AddPartitionsToTxnResponse,
+    AddPartitionsToTxnTopicResult,
+};
+use crate::protocol::end_txn::{EndTxnRequest, EndTxnResponse};
+use crate::protocol::init_producer_id::{InitProducerIdRequest, InitProducerIdResponse};
+use crate::shared::constants::CONSUMER_OFFSETS_TOPIC;
+
+/// Serves the producer id and transaction APIs. Lock order: coordinator, then replica manager;
+/// the group coordinator is only locked once both are released.
+pub struct TxnHandler {
+    coordinator: Arc<Mutex<TransactionCoordinator>>,
+    /// Applies the offsets of transactions once their markers are written.
+    group_coordinator: Arc<Mutex<GroupCoordinator>>,
+    replica_manager: Arc<Mutex<ReplicaManager>>,
+    authorizer: Option<Arc<dyn Authorizer>>,
+}
+
+impl TxnHandler {
+    pub fn new(
+        coordinator: Arc<Mutex<TransactionCoordinator>>,
+        group_coordinator: Arc<Mutex<GroupCoordinator>>,
+        replica_manager: Arc<Mutex<ReplicaManager>>,
+        authorizer: Option<Arc<dyn Authorizer>>,
+    ) -> Self {
+        Self {
+            coordinator,
+            group_coordinator,
+            replica_manager,
+            authorizer,
+        }
+    }
+
+    async fn authorize(
+        &self,
+        context: &RequestContext,
+        operation: AclOperation,
+        resource_type: ResourceType,
+        name: &str,
+    ) -> bool {
+        context
+            .authorize(
+                self.authorizer.as_ref(),
+                operation,
+                &Resource::new(resource_type, name),
+            )
+            .await
+    }
+
+    /// Any broker hands out ids to idempotent producers, which need IdempotentWrite on the
+    /// cluster. A transactional id is served by its coordinator and needs Write on it.
+    pub async fn init_producer_id(
+        &self,
+        context: &RequestContext,
+        request: InitProducerIdRequest,
+    ) -> InitProducerIdResponse {
+        let error_response = |error: ErrorCode| InitProducerIdResponse {
+            throttle_time_ms: 0,
+            error_code: error.code(),
+            producer_id: -1,
+            producer_epoch: -1,
+        };
+
+        let authorizer = self.authorizer.as_ref();
+        let authorized = match &request.transactional_id {
+            Some(transactional_id) => {
+                let resource = Resource::new(ResourceType::TransactionalId, transactional_id);
+                context
+                    .authorize(authorizer, AclOperation::Write, &resource)
+                    .await
+            }
+            None => {
+                context
+                    .authorize(
+                        authorizer,
+                        AclOperation::IdempotentWrite,
+                        &Resource::cluster(),
+                    )
+                    .await
+            }
+        };
+        if !authorized {
+            return error_response(match request.transactional_id {
+                Some(_) => ErrorCode::TransactionalIdAuthorizationFailed,
+                None => ErrorCode::ClusterAuthorizationFailed,
+            });
+        }
+
+        let (result, offset_markers) = {
+            let mut coordinator = self.coordinator.lock().await;
+            let mut replica_manager = self.replica_manager.lock().await;
+            if let Some(transactional_id) = &request.transactional_id
+                && let Err(error) = coordinator
+                    .ensure_loaded(&mut replica_manager, transactional_id)
+                    .await
+            {
+                return error_response(error);
+            }
+            let result = coordinator
+                .init_producer_id(
+                    &mut replica_manager,
+                    request.transactional_id.as_deref(),
+                    request.transaction_timeout_ms,
+                )
+                .await;
+            // Fencing a previous instance aborts its transaction
+            (result, coordinator.take_offset_markers())
+        };
+        self.complete_offsets(offset_markers).await;
+
+        match result {
+            Ok((producer_id, producer_epoch)) => InitProducerIdResponse {
+                throttle_time_ms: 0,
+                error_code: ErrorCode::None.code(),
+                producer_id,
+                producer_epoch,
+            },
+            Err(error) => error_response(error),
+        }
+    }
+
+    /// Needs Write on the transactional id and on every topic; when a topic is denied, no
+    /// partition is added.
+    pub async fn add_partitions_to_txn(
+        &self,
+        context: &RequestContext,
+        request: AddPartitionsToTxnRequest,
+    ) -> AddPartitionsToTxnResponse {
+        let mut errors = Vec::with_capacity(request.topics.len());
+        if !self
+            .authorize(
+                context,
+                AclOperation::Write,
+                ResourceType::TransactionalId,
+                &request.transactional_id,
+            )
+            .await
+        {
+            errors.resize(
+                request.topics.len(),
+                ErrorCode::TransactionalIdAuthorizationFailed,
+            );
+        } else {
+            for topic in &request.topics {
+                let authorized = self
+                    .authorize(
+                        context,
+                        AclOperation::Write,
+                        ResourceType::Topic,
+                        &topic.name,
+                    )
+                    .await;
+                errors.push(if authorized {
+                    ErrorCode::None
+                } else {
+                    ErrorCode::TopicAuthorizationFailed
+                });
+            }
+        }
+
+        let error = if errors.iter().any(|error| *error != ErrorCode::None) {
+            ErrorCode::OperationNotAttempted
+        } else {
+            let partitions = request
+                .topics
+                .iter()
+                .flat_map(|topic| {
+                    topic
+                        .partitions
+                        .iter()
+                        .map(|partition| TopicPartition::new(topic.name.clone(), *partition))
+                })
+                .collect();
+            let mut coordinator = self.coordinator.lock().await;
+            let mut replica_manager = self.replica_manager.lock().await;
+            let result = match coordinator
+                .ensure_loaded(&mut replica_manager, &request.transactional_id)
+                .await
+            {
+                Ok(()) => {
+                    coordinator
+                        .add_partitions_to_txn(
+                            &mut replica_manager,
+                            &request.transactional_id,
+                            request.producer_id,
+                            request.producer_epoch,
+                            partitions,
+                        )
+                        .await
+                }
+                Err(error) => Err(error),
+            };
+            result.err().unwrap_or(ErrorCode::None)
+        };
+
+        let results = request
+            .topics
+            .into_iter()
+            .zip(errors)
+            .map(|(topic, topic_error)| AddPartitionsToTxnTopicResult {
+                name: topic.name,
+                results: topic
+                    .partitions
+                    .into_iter()
+                    .map(|partition_index| AddPartitionsToTxnPartitionResult {
+                        partition_index,
+                        error_code: if topic_error == ErrorCode::None {
+                            error.code()
+                        } else {
+                            topic_error.code()
+                        },
+                    })
+                    .collect(),
+            })
+            .collect();
+        AddPartitionsToTxnResponse {
+            throttle_time_ms: 0,
+            results,
+        }
+    }
+
+    /// Adds the `__consumer_offsets` partition of the group to the transaction, so it gets a
+    /// marker too. Needs Write on the transactional id and Read on the group.
+    pub async fn add_offsets_to_txn(
+        &self,
+        context: &RequestContext,
+        request: AddOffsetsToTxnRequest,
+    ) -> AddOffsetsToTxnResponse {
+        let response = |error: ErrorCode| AddOffsetsToTxnResponse {
+            throttle_time_ms: 0,
+            error_code: error.code(),
+        };
+
+        if !self
+            .authorize(
+                context,
+                AclOperation::Write,
+                ResourceType::TransactionalId,
+                &request.transactional_id,
+            )
+            .await
+        {
+            return response(ErrorCode::TransactionalIdAuthorizationFailed);
+        }
+        if !self
+            .authorize(
+                context,
+                AclOperation::Read,
+                ResourceType::Group,
+                &request.group_id,
+            )
+            .await
+        {
+            return response(ErrorCode::GroupAuthorizationFailed);
+        }
+
+        let offsets_partition = self
+            .group_coordinator
+            .lock()
+            .await
+            .metadata_manager
+            .partition_for(&request.group_id);
+        let mut coordinator = self.coordinator.lock().await;
+        let mut replica_manager = self.replica_manager.lock().await;
+        if let Err(error) = coordinator
+            .ensure_loaded(&mut replica_manager, &request.transactional_id)
+            .await
+        {
+            return response(error);
+        }
+        match coordinator
+            .add_partitions_to_txn(
+                &mut replica_manager,
+                &request.transactional_id,
+                request.producer_id,
+                request.producer_epoch,
+                vec![TopicPartition::new(
+                    CONSUMER_OFFSETS_TOPIC,
+                    offsets_partition,
+                )],
+            )
+            .await
+        {
+            Ok(()) => response(ErrorCode::None),
+            Err(error) => response(error),
+        }
+    }
+
+    /// Commits or aborts the transaction. Needs Write on the transactional id.
+    pub async fn end_txn(
+        &self,
+        context: &RequestContext,
+        request: EndTxnRequest,
+    ) -> EndTxnResponse {
+        let response = |error: ErrorCode| EndTxnResponse {
+            throttle_time_ms: 0,
+            error_code: error.code(),
+        };
+
+        if !self
+            .authorize(
+                context,
+                AclOperation::Write,
+                ResourceType::TransactionalId,
+                &request.transactional_id,
+            )
+            .await
+        {
+            return response(ErrorCode::TransactionalIdAuthorizationFailed);
+        }
+
+        let (result, offset_markers) = {
+            let mut coordinator = self.coordinator.lock().await;
+            let mut replica_manager = self.replica_manager.lock().await;
+            let result = match coordinator
+                .ensure_loaded(&mut replica_manager, &request.transactional_id)
+                .await
+            {
+                Ok(()) => {
+                    coordinator
+                        .end_txn(
+                            &mut replica_manager,
+                            &request.transactional_id,
+                            request.producer_id,
+                            request.producer_epoch,
+                            request.committed,
+                        )
+                        .await
+                }
+                Err(error) => Err(error),
+            };
+            (result, coordinator.take_offset_markers())
+        };
+
+        self.complete_offsets(offset_markers).await;
+        response(result.err().unwrap_or(ErrorCode::None))
+    }
+
+    async fn complete_offsets(&self, offset_markers: Vec<(i64, bool)>) {
+        if offset_markers.is_empty() {
+            return;
+        }
+        let mut group_coordinator = self.group_coordinator.lock().await;
+        for (producer_id, commit) in offset_markers {
+            group_coordinator.complete_transactional_offsets(producer_id, commit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::driven::producer_id::LocalProducerIdBlockSource;
+    use crate::application::group_handler::GroupHandler;
+    use crate::application::metadata_listener::BrokerMetadataListener;
+    use crate::application::producer_id_manager::ProducerIdManager;
+    use crate::config::SecurityProtocol;
+    use crate::core::domain::control_record::{ControlRecordType, EndTransactionMarker};
+    use crate::core::domain::metadata_records::RegisterBrokerRecord;
+    use crate::core::domain::principal::KafkaPrincipal;
+    use crate::core::ports::driven::FetchClient;
+    use crate::protocol::add_partitions_to_txn::AddPartitionsToTxnTopic;
+    use crate::protocol::fetch::ISOLATION_READ_UNCOMMITTED;
+    use crate::protocol::txn_offset_commit::{
+        TxnOffsetCommitPartition, TxnOffsetCommitRequest, TxnOffsetCommitTopic,
+    };
+    use crate::shared::constants::TRANSACTION_STATE_TOPIC;
+
+    const TRANSACTIONAL_ID: &str = "txn-1";
+    const GROUP_ID: &str = "group-1";
+
+    struct Broker {
+        txn_handler: TxnHandler,
+        group_handler: GroupHandler,
+        group_coordinator: Arc<Mutex<GroupCoordinator>>,
+        replica_manager: Arc<Mutex<ReplicaManager>>,
+        context: RequestContext,
+    }
+
+    /// A single broker leading one partition of each internal topic and of `events`.
+    async fn broker() -> Broker {
+        let dir = std::env::temp_dir().join(format!("forge-txn-handler-{}", uuid::Uuid::new_v4()));
+        let mut replica_manager = ReplicaManager::new(1, &dir, 1);
+        for topic in [TRANSACTION_STATE_TOPIC, CONSUMER_OFFSETS_TOPIC, "events"] {
+            let tp = TopicPartition::new(topic, 0);
+            replica_manager
+                .create_partition(tp.clone(), vec![1])
+                .await
+                .unwrap();
+            replica_manager.become_leader(&tp, 0, vec![1]).unwrap();
+        }
+        let replica_manager = Arc::new(Mutex::new(replica_manager));
+        let group_coordinator = Arc::new(Mutex::new(GroupCoordinator::new(1)));
+        let coordinator = TransactionCoordinator::new(
+            1,
+            60_000,
+            ProducerIdManager::new(1, 10, Box::new(LocalProducerIdBlockSource::new(&dir))),
+        );
+        let metadata = Arc::new(Mutex::new(BrokerMetadataListener::new(
+            1,
+            replica_manager.clone(),
+            Box::new(|_: &RegisterBrokerRecord| -> Box<dyn FetchClient> {
+                unreachable!("a single broker fetches from no other")
+            }),
+        )));
+        Broker {
+            txn_handler: TxnHandler::new(
+                Arc::new(Mutex::new(coordinator)),
+                group_coordinator.clone(),
+                replica_manager.clone(),
+                None,
+            ),
+            group_handler: GroupHandler::new(
+                group_coordinator.clone(),
+                replica_manager.clone(),
+                metadata,
+                None,
+            ),
+            group_coordinator,
+            replica_manager,
+            context: RequestContext {
+                principal: KafkaPrincipal::anonymous(),
+                client_host: "127.0.0.1".to_string(),
+                client_id: "producer".to_string(),
+                listener: SecurityProtocol::Plaintext,
+            },
+        }
+    }
+
+    /// Runs a transaction writing to `events` and committing an offset of 42 for the group,
+    /// then ends it.
+    async fn run_transaction(broker: &Broker, committed: bool) {
+        let response = broker
+            .txn_handler
+            .init_producer_id(
+                &broker.context,
+                InitProducerIdRequest {
+                    transactional_id: Some(TRANSACTIONAL_ID.to_string()),
+                    transaction_timeout_ms: 10_000,
+                },
+            )
+            .await;
+        assert_eq!(response.error_code, ErrorCode::None.code());
+        let (producer_id, producer_epoch) = (response.producer_id, response.producer_epoch);
+
+        let response = broker
+            .txn_handler
+            .add_partitions_to_txn(
+                &broker.context,
+                AddPartitionsToTxnRequest {
+                    transactional_id: TRANSACTIONAL_ID.to_string(),
+                    producer_id,
+                    producer_epoch,
+                    topics: vec![AddPartitionsToTxnTopic {
+                        name: "events".to_string(),
+                        partitions: vec![0],
+                    }],
+                },
+            )
+            .await;
+        assert_eq!(
+            response.results[0].results[0].error_code,
+            ErrorCode::None.code()
+        );
+
+        let response = broker
+            .txn_handler
+            .add_offsets_to_txn(
+                &broker.context,
+                AddOffsetsToTxnRequest {
+                    transactional_id: TRANSACTIONAL_ID.to_string(),
+                    producer_id,
+                    producer_epoch,
+                    group_id: GROUP_ID.to_string(),
+                },
+            )
+            .await;
+        assert_eq!(response.error_code, ErrorCode::None.code());
+
+        let response = broker
+            .group_handler
+            .txn_offset_commit(
+                &broker.context,
+                TxnOffsetCommitRequest {
+                    transactional_id: TRANSACTIONAL_ID.to_string(),
+                    group_id: GROUP_ID.to_string(),
+                    producer_id,
+                    producer_epoch,
+                    topics: vec![TxnOffsetCommitTopic {
+                        name: "events".to_string(),
+                        partitions: vec![TxnOffsetCommitPartition {
+                            partition_index: 0,
+                            committed_offset: 42,
+                            committed_leader_epoch: -1,
+                            committed_metadata: None,
+                        }],
+                    }],
+                },
+            )
+            .await;
+        assert_eq!(
+            response.topics[0].partitions[0].error_code,
+            ErrorCode::None.code()
+        );
+        // Not visible until the transaction commits
+        assert!(committed_offsets(broker).await.is_empty());
+
+        let response = broker
+            .txn_handler
+            .end_txn(
+                &broker.context,
+                EndTxnRequest {
+                    transactional_id: TRANSACTIONAL_ID.to_string(),
+                    producer_id,
+                    producer_epoch,
+                    committed,
+                },
+            )
+            .await;
+        assert_eq!(response.error_code, ErrorCode::None.code());
+    }
+
+    async fn markers(broker: &Broker, topic: &str) -> Vec<ControlRecordType> {
+        broker
+            .replica_manager
+            .lock()
+            .await
+            .fetch_records(
+                &TopicPartition::new(topic, 0),
+                None,
+                0,
+                1024 * 1024,
+                ISOLATION_READ_UNCOMMITTED,
+            )
+            .await
+            .unwrap()
+            .batches()
+            .unwrap()
+            .iter()
+            .filter_map(EndTransactionMarker::from_batch)
+            .map(|marker| marker.control_type)
+            .collect()
+    }
+
+    async fn committed_offsets(broker: &Broker) -> Vec<(TopicPartition, i64)> {
+        broker
+            .group_coordinator
+            .lock()
+            .await
+            .fetch_offsets(GROUP_ID, None)
+            .into_iter()
+            .map(|(tp, offset)| (tp, offset.offset))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_committed_transaction_marks_partitions_and_applies_offsets() {
+        let broker = broker().await;
+        run_transaction(&broker, true).await;
+
+        assert_eq!(
+            markers(&broker, "events").await,
+            vec![ControlRecordType::Commit]
+        );
+        assert_eq!(
+            markers(&broker, CONSUMER_OFFSETS_TOPIC).await,
+            vec![ControlRecordType::Commit]
+        );
+        assert_eq!(
+            committed_offsets(&broker).await,
+            vec![(TopicPartition::new("events", 0), 42)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_aborted_transaction_marks_partitions_and_drops_offsets() {
+        let broker = broker().await;
+        run_transaction(&broker, false).await;
+
+        assert_eq!(
+            markers(&broker, "events").await,
+            vec![ControlRecordType::Abort]
+        );
+        assert_eq!(
+            markers(&broker, CONSUMER_OFFSETS_TOPIC).await,
+            vec![ControlRecordType::Abort]
+        );
+        assert!(committed_offsets(&broker).await.is_empty());
+    }
+}
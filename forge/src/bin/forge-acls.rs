@@ -0,0 +1,471 @@
+use clap::{Args, Parser, Subcommand};
+
+use forge::adapters::driven::broker_client::BrokerClient;
+use forge::core::domain::acl::{
+    ACL_FILTER_ANY, AclOperation, AclPermissionType, CLUSTER_RESOURCE_NAME,
+    PATTERN_TYPE_FILTER_MATCH, PatternType, ResourceType, WILDCARD,
+};
+use forge::core::error::ErrorCode;
+use forge::protocol::create_acls::{
+    AclCreation, CREATE_ACLS_API_KEY, CREATE_ACLS_MAX_VERSION, CreateAclsRequest,
+    CreateAclsResponse,
+};
+use forge::protocol::delete_acls::{
+    DELETE_ACLS_API_KEY, DELETE_ACLS_MAX_VERSION, DeleteAclsFilter, DeleteAclsRequest,
+    DeleteAclsResponse,
+};
+use forge::protocol::describe_acls::{
+    DESCRIBE_ACLS_API_KEY, DESCRIBE_ACLS_MAX_VERSION, DescribeAclsRequest, DescribeAclsResponse,
+};
+use forge::tools::ClientArgs;
+
+const CLIENT_ID: &str = "forge-acls";
+
+/// Adds, lists and removes the ACLs of the broker's authorizer. Operations and types are
+/// named as in the ACL file: Read, DescribeConfigs, Literal, Prefixed.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    #[command(flatten)]
+    client: ClientArgs,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Grants or denies operations on resources to principals.
+    Add(AddArgs),
+    /// Prints the ACLs of the given resources, or of every resource.
+    List(ListArgs),
+    /// Removes the ACLs matching the given resources, principals, hosts and operations.
+    Remove(RemoveArgs),
+}
+
+/// The resources an ACL command acts on; repeat a flag for several.
+#[derive(Debug, Args)]
+struct ResourceArgs {
+    #[arg(long)]
+    topic: Vec<String>,
+
+    #[arg(long)]
+    group: Vec<String>,
+
+    #[arg(long)]
+    transactional_id: Vec<String>,
+
+    #[arg(long)]
+    cluster: bool,
+}
+
+#[derive(Debug, Args)]
+struct PrincipalArgs {
+    /// A principal to allow, as type:name, e.g. User:alice or User:* for everyone.
+    #[arg(long)]
+    allow_principal: Vec<String>,
+
+    /// A principal to deny, as type:name.
+    #[arg(long)]
+    deny_principal: Vec<String>,
+
+    /// A host the allow principals connect from; * when not given.
+    #[arg(long)]
+    allow_host: Vec<String>,
+
+    /// A host the deny principals connect from; * when not given.
+    #[arg(long)]
+    deny_host: Vec<String>,
+
+    #[arg(long, value_parser = parse_operation)]
+    operation: Vec<AclOperation>,
+}
+
+#[derive(Debug, Args)]
+struct AddArgs {
+    #[command(flatten)]
+    resources: ResourceArgs,
+
+    #[command(flatten)]
+    principals: PrincipalArgs,
+
+    /// Literal or Prefixed.
+    #[arg(long, default_value = "Literal", value_parser = parse_pattern_type)]
+    resource_pattern_type: PatternType,
+}
+
+#[derive(Debug, Args)]
+struct ListArgs {
+    #[command(flatten)]
+    resources: ResourceArgs,
+
+    /// Only lists the ACLs of this principal.
+    #[arg(long)]
+    principal: Option<String>,
+
+    /// Literal, Prefixed, Any or Match; Match also lists the wildcard and prefixed ACLs that
+    /// apply to the named resources.
+    #[arg(long, default_value = "Literal")]
+    resource_pattern_type: String,
+}
+
+#[derive(Debug, Args)]
+struct RemoveArgs {
+    #[command(flatten)]
+    resources: ResourceArgs,
+
+    /// Without principals or operations, every ACL of the resources is removed.
+    #[command(flatten)]
+    principals: PrincipalArgs,
+
+    /// Literal, Prefixed, Any or Match.
+    #[arg(long, default_value = "Literal")]
+    resource_pattern_type: String,
+}
+
+fn parse_operation(value: &str) -> Result<AclOperation, String> {
+    value.parse()
+}
+
+fn parse_pattern_type(value: &str) -> Result<PatternType, String> {
+    value.parse()
+}
+
+/// The wire code of a pattern type filter, which also takes Any and Match.
+fn pattern_type_filter(value: &str) -> Result<i8, String> {
+    match value {
+        "Any" => Ok(ACL_FILTER_ANY),
+        "Match" => Ok(PATTERN_TYPE_FILTER_MATCH),
+        _ => value.parse::<PatternType>().map(PatternType::code),
+    }
+}
+
+fn code_name<T: ToString>(code: i8, from_code: fn(i8) -> Option<T>) -> String {
+    from_code(code).map_or_else(|| format!("Unknown({})", code), |value| value.to_string())
+}
+
+fn pattern_display(resource_type: i8, name: &str, pattern_type: i8) -> String {
+    format!(
+        "ResourcePattern(resourceType={}, name={}, patternType={})",
+        code_name(resource_type, ResourceType::from_code),
+        name,
+        code_name(pattern_type, PatternType::from_code)
+    )
+}
+
+fn acl_display(principal: &str, host: &str, operation: i8, permission_type: i8) -> String {
+    format!(
+        "(principal={}, host={}, operation={}, permissionType={})",
+        principal,
+        host,
+        code_name(operation, AclOperation::from_code),
+        code_name(permission_type, AclPermissionType::from_code)
+    )
+}
+
+fn error_display(error_code: i16, error_message: Option<&str>) -> String {
+    format!(
+        "error code {}{}",
+        error_code,
+        error_message
+            .map(|message| format!(": {}", message))
+            .unwrap_or_default()
+    )
+}
+
+impl ResourceArgs {
+    fn resources(&self) -> Vec<(ResourceType, String)> {
+        let mut resources = Vec::new();
+        for (resource_type, names) in [
+            (ResourceType::Topic, &self.topic),
+            (ResourceType::Group, &self.group),
+            (ResourceType::TransactionalId, &self.transactional_id),
+        ] {
+            resources.extend(names.iter().map(|name| (resource_type, name.clone())));
+        }
+        if self.cluster {
+            resources.push((ResourceType::Cluster, CLUSTER_RESOURCE_NAME.to_string()));
+        }
+        resources
+    }
+}
+
+impl PrincipalArgs {
+    /// Each principal with its permission and hosts; `None` when no principal is given.
+    fn entries(&self) -> Option<Vec<(String, AclPermissionType, String)>> {
+        let hosts = |hosts: &Vec<String>| {
+            if hosts.is_empty() {
+                vec![WILDCARD.to_string()]
+            } else {
+                hosts.clone()
+            }
+        };
+        let mut entries = Vec::new();
+        for (principals, permission_type, hosts) in [
+            (
+                &self.allow_principal,
+                AclPermissionType::Allow,
+                hosts(&self.allow_host),
+            ),
+            (
+                &self.deny_principal,
+                AclPermissionType::Deny,
+                hosts(&self.deny_host),
+            ),
+        ] {
+            for principal in principals {
+                for host in &hosts {
+                    entries.push((principal.clone(), permission_type, host.clone()));
+                }
+            }
+        }
+        (!entries.is_empty()).then_some(entries)
+    }
+}
+
+struct Acls {
+    client: BrokerClient,
+}
+
+impl Acls {
+    async fn run(&mut self, command: &Command) -> Result<(), String> {
+        match command {
+            Command::Add(args) => self.add(args).await,
+            Command::List(args) => self.list(args).await,
+            Command::Remove(args) => self.remove(args).await,
+        }
+    }
+
+    async fn add(&mut self, args: &AddArgs) -> Result<(), String> {
+        let resources = args.resources.resources();
+        if resources.is_empty() {
+            return Err("Name at least one resource to add ACLs to".to_string());
+        }
+        let entries = args
+            .principals
+            .entries()
+            .ok_or("Name at least one --allow-principal or --deny-principal")?;
+        let operations = if args.principals.operation.is_empty() {
+            vec![AclOperation::All]
+        } else {
+            args.principals.operation.clone()
+        };
+
+        let mut creations = Vec::new();
+        for (resource_type, name) in &resources {
+            for (principal, permission_type, host) in &entries {
+                for operation in &operations {
+                    creations.push(AclCreation {
+                        resource_type: resource_type.code(),
+                        resource_name: name.clone(),
+                        resource_pattern_type: args.resource_pattern_type.code(),
+                        principal: principal.clone(),
+                        host: host.clone(),
+                        operation: operation.code(),
+                        permission_type: permission_type.code(),
+                    });
+                }
+            }
+        }
+        let request = CreateAclsRequest { creations };
+        let mut response = self
+            .client
+            .send_request(CREATE_ACLS_API_KEY, CREATE_ACLS_MAX_VERSION, |buf| {
+                request.encode(buf, CREATE_ACLS_MAX_VERSION)
+            })
+            .await?;
+        let response = CreateAclsResponse::decode(&mut response, CREATE_ACLS_MAX_VERSION)?;
+
+        let mut failed = false;
+        let mut current = None;
+        for (creation, result) in request.creations.iter().zip(&response.results) {
+            let pattern = pattern_display(
+                creation.resource_type,
+                &creation.resource_name,
+                creation.resource_pattern_type,
+            );
+            if current.as_ref() != Some(&pattern) {
+                println!("Adding ACLs for resource `{}`:", pattern);
+                current = Some(pattern);
+            }
+            let acl = acl_display(
+                &creation.principal,
+                &creation.host,
+                creation.operation,
+                creation.permission_type,
+            );
+            if result.error_code == ErrorCode::None.code() {
+                println!(" \t{}", acl);
+            } else {
+                failed = true;
+                println!(
+                    " \t{} failed: {}",
+                    acl,
+                    error_display(result.error_code, result.error_message.as_deref())
+                );
+            }
+        }
+        if failed {
+            return Err("Some ACLs could not be added".to_string());
+        }
+        Ok(())
+    }
+
+    async fn list(&mut self, args: &ListArgs) -> Result<(), String> {
+        let pattern_type = pattern_type_filter(&args.resource_pattern_type)?;
+        let resources = args.resources.resources();
+        let requests: Vec<DescribeAclsRequest> = if resources.is_empty() {
+            vec![DescribeAclsRequest {
+                resource_type_filter: ACL_FILTER_ANY,
+                resource_name_filter: None,
+                pattern_type_filter: ACL_FILTER_ANY,
+                principal_filter: args.principal.clone(),
+                host_filter: None,
+                operation: ACL_FILTER_ANY,
+                permission_type: ACL_FILTER_ANY,
+            }]
+        } else {
+            resources
+                .into_iter()
+                .map(|(resource_type, name)| DescribeAclsRequest {
+                    resource_type_filter: resource_type.code(),
+                    resource_name_filter: Some(name),
+                    pattern_type_filter: pattern_type,
+                    principal_filter: args.principal.clone(),
+                    host_filter: None,
+                    operation: ACL_FILTER_ANY,
+                    permission_type: ACL_FILTER_ANY,
+                })
+                .collect()
+        };
+
+        for request in &requests {
+            let mut response = self
+                .client
+                .send_request(DESCRIBE_ACLS_API_KEY, DESCRIBE_ACLS_MAX_VERSION, |buf| {
+                    request.encode(buf, DESCRIBE_ACLS_MAX_VERSION)
+                })
+                .await?;
+            let response = DescribeAclsResponse::decode(&mut response, DESCRIBE_ACLS_MAX_VERSION)?;
+            if response.error_code != ErrorCode::None.code() {
+                return Err(format!(
+                    "Failed to list ACLs: {}",
+                    error_display(response.error_code, response.error_message.as_deref())
+                ));
+            }
+            for resource in &response.resources {
+                println!(
+                    "Current ACLs for resource `{}`:",
+                    pattern_display(
+                        resource.resource_type,
+                        &resource.resource_name,
+                        resource.pattern_type
+                    )
+                );
+                for acl in &resource.acls {
+                    println!(
+                        " \t{}",
+                        acl_display(
+                            &acl.principal,
+                            &acl.host,
+                            acl.operation,
+                            acl.permission_type
+                        )
+                    );
+                }
+                println!();
+            }
+        }
+        Ok(())
+    }
+
+    async fn remove(&mut self, args: &RemoveArgs) -> Result<(), String> {
+        let pattern_type = pattern_type_filter(&args.resource_pattern_type)?;
+        let resources = args.resources.resources();
+        if resources.is_empty() {
+            return Err("Name at least one resource to remove ACLs from".to_string());
+        }
+        let entries: Vec<(Option<String>, i8, Option<String>)> = match args.principals.entries() {
+            Some(entries) => entries
+                .into_iter()
+                .map(|(principal, permission_type, host)| {
+                    (Some(principal), permission_type.code(), Some(host))
+                })
+                .collect(),
+            None => vec![(None, ACL_FILTER_ANY, None)],
+        };
+        let operations: Vec<i8> = if args.principals.operation.is_empty() {
+            vec![ACL_FILTER_ANY]
+        } else {
+            args.principals
+                .operation
+                .iter()
+                .map(|operation| operation.code())
+                .collect()
+        };
+
+        let mut filters = Vec::new();
+        for (resource_type, name) in &resources {
+            for (principal, permission_type, host) in &entries {
+                for operation in &operations {
+                    filters.push(DeleteAclsFilter {
+                        resource_type_filter: resource_type.code(),
+                        resource_name_filter: Some(name.clone()),
+                        pattern_type_filter: pattern_type,
+                        principal_filter: principal.clone(),
+                        host_filter: host.clone(),
+                        operation: *operation,
+                        permission_type: *permission_type,
+                    });
+                }
+            }
+        }
+        let request = DeleteAclsRequest { filters };
+        let mut response = self
+            .client
+            .send_request(DELETE_ACLS_API_KEY, DELETE_ACLS_MAX_VERSION, |buf| {
+                request.encode(buf, DELETE_ACLS_MAX_VERSION)
+            })
+            .await?;
+        let response = DeleteAclsResponse::decode(&mut response, DELETE_ACLS_MAX_VERSION)?;
+
+        let mut removed = 0;
+        for result in &response.filter_results {
+            if result.error_code != ErrorCode::None.code() {
+                return Err(format!(
+                    "Failed to remove ACLs: {}",
+                    error_display(result.error_code, result.error_message.as_deref())
+                ));
+            }
+            for acl in &result.matching_acls {
+                removed += 1;
+                println!(
+                    "Removed {} from `{}`",
+                    acl_display(
+                        &acl.principal,
+                        &acl.host,
+                        acl.operation,
+                        acl.permission_type
+                    ),
+                    pattern_display(acl.resource_type, &acl.resource_name, acl.pattern_type)
+                );
+            }
+        }
+        if removed == 0 {
+            println!("No ACLs matched.");
+        }
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let mut tool = Acls {
+        client: cli.client.client(CLIENT_ID),
+    };
+    if let Err(e) = tool.run(&cli.command).await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
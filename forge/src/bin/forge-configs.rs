@@ -0,0 +1,361 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use forge::adapters::driven::broker_client::BrokerClient;
+use forge::core::domain::metadata_records::{
+    CONFIG_RESOURCE_BROKER, CONFIG_RESOURCE_BROKER_LOGGER, CONFIG_RESOURCE_TOPIC,
+};
+use forge::core::error::ErrorCode;
+use forge::protocol::alter_configs::{
+    ALTER_CONFIGS_API_KEY, ALTER_CONFIGS_MAX_VERSION, AlterConfigsRequest, AlterConfigsResource,
+    AlterConfigsResponse, AlterableConfig,
+};
+use forge::protocol::describe_configs::{
+    CONFIG_SOURCE_DEFAULT, CONFIG_SOURCE_DYNAMIC_BROKER, CONFIG_SOURCE_DYNAMIC_BROKER_LOGGER,
+    CONFIG_SOURCE_DYNAMIC_DEFAULT_BROKER, CONFIG_SOURCE_DYNAMIC_TOPIC, CONFIG_SOURCE_STATIC_BROKER,
+    DESCRIBE_CONFIGS_API_KEY, DESCRIBE_CONFIGS_MAX_VERSION, DescribeConfigsRequest,
+    DescribeConfigsResource, DescribeConfigsResourceResult, DescribeConfigsResponse,
+};
+use forge::shared::collections::FlatMap;
+use forge::tools::ClientArgs;
+
+const CLIENT_ID: &str = "forge-configs";
+
+/// Describes and alters topic, broker and broker logger configs. Broker and logger configs
+/// are served by the broker they belong to, so --bootstrap-server must be that broker.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    #[command(flatten)]
+    client: ClientArgs,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Prints the dynamic configs of an entity, with the source of each value.
+    Describe(DescribeArgs),
+    /// Sets and removes dynamic configs of an entity, leaving its other overrides alone.
+    Alter(AlterArgs),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum EntityType {
+    Topics,
+    Brokers,
+    BrokerLoggers,
+}
+
+#[derive(Debug, Args)]
+#[command(group(clap::ArgGroup::new("entity").required(true).args(
+    ["entity_name", "entity_default"]
+)))]
+struct EntityArgs {
+    #[arg(long, value_enum)]
+    entity_type: EntityType,
+
+    /// A topic name or a broker id.
+    #[arg(long)]
+    entity_name: Option<String>,
+
+    /// The cluster-wide default of every broker's configs.
+    #[arg(long)]
+    entity_default: bool,
+}
+
+#[derive(Debug, Args)]
+struct DescribeArgs {
+    #[command(flatten)]
+    entity: EntityArgs,
+
+    /// Prints every config, including static and default ones, not just the overrides.
+    #[arg(long)]
+    all: bool,
+}
+
+#[derive(Debug, Args)]
+#[command(group(clap::ArgGroup::new("changes").required(true).multiple(true).args(
+    ["add_config", "delete_config"]
+)))]
+struct AlterArgs {
+    #[command(flatten)]
+    entity: EntityArgs,
+
+    /// name=value pairs separated by commas; wrap a list value in brackets:
+    /// `cleanup.policy=[compact,delete]`.
+    #[arg(long, value_parser = parse_configs)]
+    add_config: Option<AddedConfigs>,
+
+    /// Config names separated by commas.
+    #[arg(long, value_delimiter = ',')]
+    delete_config: Vec<String>,
+}
+
+/// The name=value pairs of one --add-config.
+#[derive(Debug, Clone)]
+struct AddedConfigs(Vec<(String, String)>);
+
+/// Splits `a=1,b=[x,y]` at the commas outside brackets.
+fn parse_configs(value: &str) -> Result<AddedConfigs, String> {
+    let mut pairs = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ',' if depth == 0 => {
+                pairs.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    pairs.push(&value[start..]);
+
+    pairs
+        .into_iter()
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (name, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected name=value, got {}", pair))?;
+            let value = value.trim();
+            let value = value
+                .strip_prefix('[')
+                .and_then(|value| value.strip_suffix(']'))
+                .unwrap_or(value);
+            Ok((name.trim().to_string(), value.to_string()))
+        })
+        .collect::<Result<_, String>>()
+        .map(AddedConfigs)
+}
+
+fn source_name(source: i8) -> &'static str {
+    match source {
+        CONFIG_SOURCE_DYNAMIC_TOPIC => "DYNAMIC_TOPIC_CONFIG",
+        CONFIG_SOURCE_DYNAMIC_BROKER => "DYNAMIC_BROKER_CONFIG",
+        CONFIG_SOURCE_DYNAMIC_DEFAULT_BROKER => "DYNAMIC_DEFAULT_BROKER_CONFIG",
+        CONFIG_SOURCE_STATIC_BROKER => "STATIC_BROKER_CONFIG",
+        CONFIG_SOURCE_DEFAULT => "DEFAULT_CONFIG",
+        CONFIG_SOURCE_DYNAMIC_BROKER_LOGGER => "DYNAMIC_BROKER_LOGGER_CONFIG",
+        _ => "UNKNOWN",
+    }
+}
+
+impl EntityArgs {
+    /// The resource type and name the config APIs know the entity by.
+    fn resource(&self) -> Result<(i8, String), String> {
+        let name = self.entity_name.clone().unwrap_or_default();
+        match self.entity_type {
+            EntityType::Topics if self.entity_default => {
+                Err("Topics have no --entity-default".to_string())
+            }
+            EntityType::Topics => Ok((CONFIG_RESOURCE_TOPIC, name)),
+            EntityType::Brokers => Ok((CONFIG_RESOURCE_BROKER, name)),
+            EntityType::BrokerLoggers if self.entity_default => {
+                Err("Broker loggers have no --entity-default".to_string())
+            }
+            EntityType::BrokerLoggers => Ok((CONFIG_RESOURCE_BROKER_LOGGER, name)),
+        }
+    }
+
+    /// The source of the overrides set on this entity itself.
+    fn dynamic_source(&self) -> i8 {
+        match self.entity_type {
+            EntityType::Topics => CONFIG_SOURCE_DYNAMIC_TOPIC,
+            EntityType::Brokers if self.entity_default => CONFIG_SOURCE_DYNAMIC_DEFAULT_BROKER,
+            EntityType::Brokers => CONFIG_SOURCE_DYNAMIC_BROKER,
+            EntityType::BrokerLoggers => CONFIG_SOURCE_DYNAMIC_BROKER_LOGGER,
+        }
+    }
+
+    fn display(&self) -> String {
+        let name = self.entity_name.as_deref().unwrap_or_default();
+        match self.entity_type {
+            EntityType::Topics => format!("topic {}", name),
+            EntityType::Brokers if self.entity_default => "the default broker".to_string(),
+            EntityType::Brokers => format!("broker {}", name),
+            EntityType::BrokerLoggers => format!("broker {} loggers", name),
+        }
+    }
+}
+
+struct Configs {
+    client: BrokerClient,
+}
+
+impl Configs {
+    async fn run(&mut self, command: &Command) -> Result<(), String> {
+        match command {
+            Command::Describe(args) => self.describe(args).await,
+            Command::Alter(args) => self.alter(args).await,
+        }
+    }
+
+    async fn describe(&mut self, args: &DescribeArgs) -> Result<(), String> {
+        let configs = self.describe_configs(&args.entity).await?;
+        let dynamic_source = args.entity.dynamic_source();
+        println!(
+            "{} configs for {} are:",
+            if args.all { "All" } else { "Dynamic" },
+            args.entity.display()
+        );
+        for config in configs
+            .iter()
+            .filter(|config| args.all || config.config_source == dynamic_source)
+        {
+            let synonyms: Vec<String> = config
+                .synonyms
+                .iter()
+                .map(|synonym| {
+                    format!(
+                        "{}:{}={}",
+                        source_name(synonym.source),
+                        synonym.name,
+                        synonym.value.as_deref().unwrap_or("null")
+                    )
+                })
+                .collect();
+            println!(
+                "  {}={} sensitive={} synonyms={{{}}}",
+                config.name,
+                config.value.as_deref().unwrap_or("null"),
+                config.is_sensitive,
+                synonyms.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// AlterConfigs replaces all overrides of a resource, so the current ones are read back
+    /// and sent along with the change.
+    async fn alter(&mut self, args: &AlterArgs) -> Result<(), String> {
+        let dynamic_source = args.entity.dynamic_source();
+        let mut overrides = FlatMap::new();
+        for config in self.describe_configs(&args.entity).await? {
+            if config.config_source == dynamic_source
+                && let Some(value) = config.value
+            {
+                overrides.insert(config.name, value);
+            }
+        }
+
+        let missing: Vec<&str> = args
+            .delete_config
+            .iter()
+            .filter(|name| !overrides.contains_key(name))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "Invalid config(s): {} are not set on {}",
+                missing.join(","),
+                args.entity.display()
+            ));
+        }
+        for name in &args.delete_config {
+            overrides.remove(name);
+        }
+        for (name, value) in args.add_config.iter().flat_map(|added| &added.0) {
+            overrides.insert(name.clone(), value.clone());
+        }
+
+        let (resource_type, resource_name) = args.entity.resource()?;
+        let request = AlterConfigsRequest {
+            resources: vec![AlterConfigsResource {
+                resource_type,
+                resource_name,
+                configs: overrides
+                    .iter()
+                    .map(|(name, value)| AlterableConfig {
+                        name: name.clone(),
+                        value: Some(value.clone()),
+                    })
+                    .collect(),
+            }],
+            validate_only: false,
+        };
+        let mut response = self
+            .client
+            .send_request(ALTER_CONFIGS_API_KEY, ALTER_CONFIGS_MAX_VERSION, |buf| {
+                request.encode(buf, ALTER_CONFIGS_MAX_VERSION)
+            })
+            .await?;
+        let response = AlterConfigsResponse::decode(&mut response, ALTER_CONFIGS_MAX_VERSION)?;
+        let result = response
+            .responses
+            .first()
+            .ok_or("The response does not cover the entity")?;
+        if result.error_code != ErrorCode::None.code() {
+            return Err(format!(
+                "Failed to alter configs of {}: error code {}{}",
+                args.entity.display(),
+                result.error_code,
+                result
+                    .error_message
+                    .as_ref()
+                    .map(|message| format!(": {}", message))
+                    .unwrap_or_default()
+            ));
+        }
+        println!("Completed updating config for {}.", args.entity.display());
+        Ok(())
+    }
+
+    async fn describe_configs(
+        &mut self,
+        entity: &EntityArgs,
+    ) -> Result<Vec<DescribeConfigsResourceResult>, String> {
+        let (resource_type, resource_name) = entity.resource()?;
+        let request = DescribeConfigsRequest {
+            resources: vec![DescribeConfigsResource {
+                resource_type,
+                resource_name,
+                configuration_keys: None,
+            }],
+            include_synonyms: true,
+            include_documentation: false,
+        };
+        let mut response = self
+            .client
+            .send_request(
+                DESCRIBE_CONFIGS_API_KEY,
+                DESCRIBE_CONFIGS_MAX_VERSION,
+                |buf| request.encode(buf, DESCRIBE_CONFIGS_MAX_VERSION),
+            )
+            .await?;
+        let response =
+            DescribeConfigsResponse::decode(&mut response, DESCRIBE_CONFIGS_MAX_VERSION)?;
+        let result = response
+            .results
+            .into_iter()
+            .next()
+            .ok_or("The response does not cover the entity")?;
+        if result.error_code != ErrorCode::None.code() {
+            return Err(format!(
+                "Failed to describe configs of {}: error code {}{}",
+                entity.display(),
+                result.error_code,
+                result
+                    .error_message
+                    .map(|message| format!(": {}", message))
+                    .unwrap_or_default()
+            ));
+        }
+        Ok(result.configs)
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let mut tool = Configs {
+        client: cli.client.client(CLIENT_ID),
+    };
+    if let Err(e) = tool.run(&cli.command).await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
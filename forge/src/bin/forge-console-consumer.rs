@@ -0,0 +1,391 @@
+use clap::Parser;
+use std::io::Write;
+use tokio::time::{Duration, Instant};
+
+use forge::adapters::driven::broker_client::BrokerClient;
+use forge::client::consumer::AbortedFilter;
+use forge::core::domain::record::Header;
+use forge::core::domain::record_batch::RecordBatch;
+use forge::core::domain::topic_partition::TopicPartition;
+use forge::core::ports::driven::FetchClient;
+use forge::protocol::fetch::{
+    FetchPartition, FetchRequest, FetchTopic, ISOLATION_READ_COMMITTED, ISOLATION_READ_UNCOMMITTED,
+    PartitionData,
+};
+use forge::protocol::list_offsets::{EARLIEST_TIMESTAMP, LATEST_TIMESTAMP};
+use forge::tools::{ClientArgs, check, commit_offsets, fetch_committed_offsets, list_offsets};
+
+const CLIENT_ID: &str = "forge-console-consumer";
+const FETCH_MAX_BYTES: i32 = 50 * 1024 * 1024;
+const PARTITION_MAX_BYTES: i32 = 1024 * 1024;
+
+/// Prints the records of one partition to stdout, one line per record, until Ctrl+C,
+/// --max-messages or --timeout-ms. With --group the position is committed for the group
+/// periodically and on exit, and a restarted consumer resumes from it.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    #[command(flatten)]
+    client: ClientArgs,
+
+    #[arg(long)]
+    topic: String,
+
+    /// The bootstrap server must lead this partition.
+    #[arg(long, default_value_t = 0)]
+    partition: i32,
+
+    /// Starts at the earliest offset when there is no --offset and no committed offset.
+    #[arg(long)]
+    from_beginning: bool,
+
+    /// earliest, latest or an offset; overrides the group's committed offset.
+    #[arg(long, conflicts_with = "from_beginning", value_parser = parse_offset)]
+    offset: Option<StartOffset>,
+
+    /// Resumes from, and commits to, this group's offsets. The consumer does not join the
+    /// group, so its members must not be consuming at the same time.
+    #[arg(long)]
+    group: Option<String>,
+
+    #[arg(long, default_value_t = 5000)]
+    auto_commit_interval_ms: u64,
+
+    /// Exits after printing this many records.
+    #[arg(long)]
+    max_messages: Option<u64>,
+
+    /// Exits when no record has arrived for this long.
+    #[arg(long)]
+    timeout_ms: Option<u64>,
+
+    /// read_uncommitted or read_committed.
+    #[arg(long, default_value = "read_uncommitted", value_parser = parse_isolation_level)]
+    isolation_level: i8,
+
+    /// How long the broker holds a fetch open while the partition has nothing new.
+    #[arg(long, default_value_t = 500)]
+    fetch_max_wait_ms: i32,
+
+    #[arg(long)]
+    print_timestamp: bool,
+
+    #[arg(long)]
+    print_offset: bool,
+
+    #[arg(long)]
+    print_headers: bool,
+
+    #[arg(long)]
+    print_key: bool,
+
+    /// Separates the printed fields of a record.
+    #[arg(long, default_value = "\t")]
+    key_separator: String,
+
+    /// Separates one printed header from the next.
+    #[arg(long, default_value = ",")]
+    headers_separator: String,
+
+    /// Printed in place of a null key, value or header value.
+    #[arg(long, default_value = "null")]
+    null_literal: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum StartOffset {
+    Earliest,
+    Latest,
+    Offset(i64),
+}
+
+fn parse_offset(value: &str) -> Result<StartOffset, String> {
+    match value {
+        "earliest" => Ok(StartOffset::Earliest),
+        "latest" => Ok(StartOffset::Latest),
+        _ => match value.parse::<i64>() {
+            Ok(offset) if offset >= 0 => Ok(StartOffset::Offset(offset)),
+            _ => Err(format!(
+                "expected earliest, latest or an offset, got {}",
+                value
+            )),
+        },
+    }
+}
+
+fn parse_isolation_level(value: &str) -> Result<i8, String> {
+    match value {
+        "read_uncommitted" => Ok(ISOLATION_READ_UNCOMMITTED),
+        "read_committed" => Ok(ISOLATION_READ_COMMITTED),
+        _ => Err(format!(
+            "expected read_uncommitted or read_committed, got {}",
+            value
+        )),
+    }
+}
+
+impl Cli {
+    fn text(&self, bytes: Option<&[u8]>) -> String {
+        bytes.map_or_else(
+            || self.null_literal.clone(),
+            |bytes| String::from_utf8_lossy(bytes).into_owned(),
+        )
+    }
+
+    fn format_headers(&self, headers: &[Header]) -> String {
+        if headers.is_empty() {
+            return "NO_HEADERS".to_string();
+        }
+        headers
+            .iter()
+            .map(|header| format!("{}:{}", header.key, self.text(header.value.as_deref())))
+            .collect::<Vec<_>>()
+            .join(&self.headers_separator)
+    }
+}
+
+struct ConsoleConsumer {
+    cli: Cli,
+    client: BrokerClient,
+    /// Set with --group once the coordinator is found.
+    coordinator: Option<BrokerClient>,
+    /// The next offset to print.
+    position: i64,
+    committed: i64,
+    consumed: u64,
+}
+
+impl ConsoleConsumer {
+    async fn run(&mut self) -> Result<(), String> {
+        if let Some(group) = &self.cli.group {
+            self.coordinator = Some(
+                self.cli
+                    .client
+                    .coordinator(&mut self.client, group, CLIENT_ID)
+                    .await?,
+            );
+        }
+        self.position = self.start_offset().await?;
+        self.committed = self.position;
+
+        let auto_commit_interval = Duration::from_millis(self.cli.auto_commit_interval_ms);
+        let timeout = self.cli.timeout_ms.map(Duration::from_millis);
+        let mut next_commit = Instant::now() + auto_commit_interval;
+        let mut last_record = Instant::now();
+        let result = loop {
+            if self
+                .cli
+                .max_messages
+                .is_some_and(|max| self.consumed >= max)
+            {
+                break Ok(());
+            }
+            let idle_deadline = timeout.map(|timeout| last_record + timeout);
+            let fetched = tokio::select! {
+                fetched = self.fetch() => fetched,
+                _ = tokio::time::sleep_until(idle_deadline.unwrap_or_else(Instant::now)),
+                    if idle_deadline.is_some() => break Ok(()),
+                _ = tokio::signal::ctrl_c() => break Ok(()),
+            };
+            match fetched {
+                Ok(0) => {}
+                Ok(_) => last_record = Instant::now(),
+                Err(e) => break Err(e),
+            }
+            if Instant::now() >= next_commit {
+                if let Err(e) = self.commit().await {
+                    eprintln!("{}", e);
+                }
+                next_commit = Instant::now() + auto_commit_interval;
+            }
+        };
+
+        let commit = self.commit().await;
+        eprintln!("Processed a total of {} messages", self.consumed);
+        result.and(commit)
+    }
+
+    fn topic_partition(&self) -> TopicPartition {
+        TopicPartition::new(self.cli.topic.clone(), self.cli.partition)
+    }
+
+    /// --offset wins, then the group's committed offset, then --from-beginning.
+    async fn start_offset(&mut self) -> Result<i64, String> {
+        let start = match self.cli.offset {
+            Some(StartOffset::Offset(offset)) => return Ok(offset),
+            Some(start) => start,
+            None => {
+                if let Some(committed) = self.fetch_committed().await? {
+                    return Ok(committed);
+                }
+                if self.cli.from_beginning {
+                    StartOffset::Earliest
+                } else {
+                    StartOffset::Latest
+                }
+            }
+        };
+        let timestamp = match start {
+            StartOffset::Earliest => EARLIEST_TIMESTAMP,
+            _ => LATEST_TIMESTAMP,
+        };
+        let topic_partition = self.topic_partition();
+        let offsets = list_offsets(
+            &mut self.client,
+            self.cli.isolation_level,
+            &[topic_partition],
+            timestamp,
+        )
+        .await?;
+        let (_, offset) = offsets
+            .into_iter()
+            .next()
+            .ok_or("The response does not cover the partition")?;
+        offset.map_err(|error_code| format!("Failed to list offsets: error code {}", error_code))
+    }
+
+    async fn fetch_committed(&mut self) -> Result<Option<i64>, String> {
+        let topic_partition = self.topic_partition();
+        let (Some(group), Some(coordinator)) = (&self.cli.group, &mut self.coordinator) else {
+            return Ok(None);
+        };
+        let committed =
+            fetch_committed_offsets(coordinator, group, Some(&[topic_partition])).await?;
+        Ok(committed.first().map(|(_, offset)| *offset))
+    }
+
+    /// Commits the position if it moved since the last commit. The consumer is not a group
+    /// member, so it commits as a standalone consumer.
+    async fn commit(&mut self) -> Result<(), String> {
+        let topic_partition = self.topic_partition();
+        let (Some(group), Some(coordinator)) = (&self.cli.group, &mut self.coordinator) else {
+            return Ok(());
+        };
+        if self.position == self.committed {
+            return Ok(());
+        }
+        commit_offsets(coordinator, group, &[(topic_partition, self.position)]).await?;
+        self.committed = self.position;
+        Ok(())
+    }
+
+    /// Fetches from the position and prints what arrives; returns how many records were
+    /// printed.
+    async fn fetch(&mut self) -> Result<u64, String> {
+        let request = FetchRequest {
+            replica_id: -1,
+            max_wait_ms: self.cli.fetch_max_wait_ms,
+            min_bytes: 1,
+            max_bytes: FETCH_MAX_BYTES,
+            isolation_level: self.cli.isolation_level,
+            session_id: 0,
+            session_epoch: -1,
+            topics: vec![FetchTopic {
+                topic: self.cli.topic.clone(),
+                partitions: vec![FetchPartition {
+                    partition: self.cli.partition,
+                    current_leader_epoch: -1,
+                    fetch_offset: self.position,
+                    log_start_offset: -1,
+                    partition_max_bytes: PARTITION_MAX_BYTES,
+                }],
+            }],
+            forgotten_topics: vec![],
+            rack_id: String::new(),
+        };
+        let response = self.client.fetch(&request).await?;
+        check(response.error_code, "fetch")?;
+        let Some(partition) = response
+            .responses
+            .into_iter()
+            .flat_map(|topic| topic.partitions)
+            .find(|partition| partition.partition_index == self.cli.partition)
+        else {
+            return Err("The response does not cover the partition".to_string());
+        };
+        check(partition.error_code, "fetch")?;
+        let batches = partition.records.batches()?;
+        self.print(&partition, &batches)
+            .map_err(|e| format!("Failed to write to stdout: {}", e))
+    }
+
+    fn print(
+        &mut self,
+        partition: &PartitionData,
+        batches: &[RecordBatch],
+    ) -> std::io::Result<u64> {
+        let mut aborted = AbortedFilter::new(partition);
+        let mut stdout = std::io::stdout().lock();
+        let printed_before = self.consumed;
+        for batch in batches {
+            if batch.last_offset() < self.position {
+                continue;
+            }
+            if aborted.skip(batch) {
+                self.position = batch.last_offset() + 1;
+                continue;
+            }
+
+            for record in &batch.records {
+                let offset = batch.base_offset + record.offset_delta.0 as i64;
+                if offset < self.position {
+                    continue;
+                }
+                if self
+                    .cli
+                    .max_messages
+                    .is_some_and(|max| self.consumed >= max)
+                {
+                    stdout.flush()?;
+                    return Ok(self.consumed - printed_before);
+                }
+
+                let mut fields = Vec::new();
+                if self.cli.print_timestamp {
+                    fields.push(if batch.is_log_append_time() {
+                        format!("LogAppendTime:{}", batch.max_timestamp)
+                    } else {
+                        format!(
+                            "CreateTime:{}",
+                            batch.base_timestamp + record.timestamp_delta.0
+                        )
+                    });
+                }
+                if self.cli.print_offset {
+                    fields.push(format!("Offset:{}", offset));
+                }
+                if self.cli.print_headers {
+                    fields.push(self.cli.format_headers(&record.headers));
+                }
+                if self.cli.print_key {
+                    fields.push(self.cli.text(record.key.as_deref()));
+                }
+                fields.push(self.cli.text(record.value.as_deref()));
+                writeln!(stdout, "{}", fields.join(&self.cli.key_separator))?;
+
+                self.position = offset + 1;
+                self.consumed += 1;
+            }
+            self.position = self.position.max(batch.last_offset() + 1);
+        }
+        stdout.flush()?;
+        Ok(self.consumed - printed_before)
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let mut consumer = ConsoleConsumer {
+        client: cli.client.client(CLIENT_ID),
+        cli,
+        coordinator: None,
+        position: 0,
+        committed: 0,
+        consumed: 0,
+    };
+    if let Err(e) = consumer.run().await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,309 @@
+use clap::Parser;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::time::{Duration, Instant};
+
+use forge::adapters::driven::broker_client::BrokerClient;
+use forge::application::replica_manager::ACKS_NONE;
+use forge::core::domain::compression::CompressionType;
+use forge::core::domain::record::{Header, Record};
+use forge::core::domain::record_batch::RecordBatch;
+use forge::core::error::ErrorCode;
+use forge::protocol::produce::{
+    PRODUCE_API_KEY, PRODUCE_MAX_VERSION, PartitionProduceData, ProduceRequest, ProduceResponse,
+    TopicProduceData,
+};
+use forge::protocol::types::Varlong;
+use forge::shared::time::current_time_ms;
+use forge::tools::ClientArgs;
+
+/// Produces the lines read from stdin to a topic, one record per line, until stdin closes
+/// or Ctrl+C. With --parse-headers and --parse-key a line reads
+/// `name:value,name:value<TAB>key<TAB>value`.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    #[command(flatten)]
+    client: ClientArgs,
+
+    #[arg(long)]
+    topic: String,
+
+    /// The bootstrap server must lead this partition.
+    #[arg(long, default_value_t = 0)]
+    partition: i32,
+
+    /// 0, 1 or all.
+    #[arg(long, default_value = "all", value_parser = parse_acks)]
+    acks: i16,
+
+    /// How long the broker may wait for replicas before failing an acks=all request.
+    #[arg(long, default_value_t = 30_000)]
+    request_timeout_ms: i32,
+
+    /// none, gzip, snappy, lz4 or zstd.
+    #[arg(long, default_value = "none", value_parser = parse_compression)]
+    compression_type: CompressionType,
+
+    /// How long a batch waits for more lines before it is sent.
+    #[arg(long, default_value_t = 1000)]
+    linger_ms: u64,
+
+    /// A batch is sent as soon as its keys, values and headers reach this many bytes.
+    #[arg(long, default_value_t = 16 * 1024)]
+    batch_size: usize,
+
+    /// Splits each line into key and value at the first --key-separator.
+    #[arg(long)]
+    parse_key: bool,
+
+    #[arg(long, default_value = "\t")]
+    key_separator: String,
+
+    /// Reads headers from the start of each line, up to the first --headers-delimiter.
+    #[arg(long)]
+    parse_headers: bool,
+
+    #[arg(long, default_value = "\t")]
+    headers_delimiter: String,
+
+    /// Separates one header from the next.
+    #[arg(long, default_value = ",")]
+    headers_separator: String,
+
+    /// Separates a header's name from its value; a header without one has a null value.
+    #[arg(long, default_value = ":")]
+    headers_key_separator: String,
+
+    /// A key, value or header value equal to this is sent as null.
+    #[arg(long)]
+    null_marker: Option<String>,
+}
+
+fn parse_acks(value: &str) -> Result<i16, String> {
+    match value {
+        "all" | "-1" => Ok(-1),
+        "0" => Ok(0),
+        "1" => Ok(1),
+        _ => Err(format!("expected 0, 1 or all, got {}", value)),
+    }
+}
+
+fn parse_compression(value: &str) -> Result<CompressionType, String> {
+    CompressionType::from_name(value)
+        .ok_or_else(|| format!("expected none, gzip, snappy, lz4 or zstd, got {}", value))
+}
+
+/// One line's worth of record.
+struct ParsedLine {
+    key: Option<Vec<u8>>,
+    value: Option<Vec<u8>>,
+    headers: Vec<Header>,
+}
+
+impl Cli {
+    fn parse_line(&self, line: &str) -> Result<ParsedLine, String> {
+        let mut rest = line;
+        let mut headers = Vec::new();
+        if self.parse_headers {
+            let Some((header_text, tail)) = rest.split_once(self.headers_delimiter.as_str()) else {
+                return Err(format!("no headers delimiter {:?}", self.headers_delimiter));
+            };
+            rest = tail;
+            for header in header_text
+                .split(self.headers_separator.as_str())
+                .filter(|header| !header.is_empty())
+            {
+                let (key, value) = match header.split_once(self.headers_key_separator.as_str()) {
+                    Some((key, value)) => (key, self.bytes(value)),
+                    None => (header, None),
+                };
+                headers.push(Header {
+                    key: key.into(),
+                    value,
+                });
+            }
+        }
+
+        let (key, value) = if self.parse_key {
+            let Some((key, value)) = rest.split_once(self.key_separator.as_str()) else {
+                return Err(format!("no key separator {:?}", self.key_separator));
+            };
+            (self.bytes(key), self.bytes(value))
+        } else {
+            (None, self.bytes(rest))
+        };
+        Ok(ParsedLine {
+            key,
+            value,
+            headers,
+        })
+    }
+
+    fn bytes(&self, text: &str) -> Option<Vec<u8>> {
+        (self.null_marker.as_deref() != Some(text)).then(|| text.as_bytes().to_vec())
+    }
+}
+
+/// The records waiting to be sent as one batch.
+#[derive(Default)]
+struct PendingBatch {
+    base_timestamp: i64,
+    records: Vec<Record>,
+    bytes: usize,
+    /// When the batch is sent even if it is not full.
+    deadline: Option<Instant>,
+}
+
+impl PendingBatch {
+    fn push(&mut self, line: ParsedLine, linger: Duration) {
+        let timestamp = current_time_ms();
+        if self.records.is_empty() {
+            self.base_timestamp = timestamp;
+            self.deadline = Some(Instant::now() + linger);
+        }
+        self.bytes += line.key.as_ref().map_or(0, Vec::len)
+            + line.value.as_ref().map_or(0, Vec::len)
+            + line
+                .headers
+                .iter()
+                .map(|header| header.key.len() + header.value.as_ref().map_or(0, Vec::len))
+                .sum::<usize>();
+
+        let mut record = Record::new(self.records.len() as i32, line.key, line.value);
+        record.timestamp_delta = Varlong(timestamp - self.base_timestamp);
+        record.headers = line.headers;
+        self.records.push(record);
+    }
+
+    /// Empties the pending batch into a batch ready to send.
+    fn take(&mut self, compression: CompressionType) -> Option<RecordBatch> {
+        let pending = std::mem::take(self);
+        let max_timestamp = pending
+            .records
+            .last()
+            .map(|record| pending.base_timestamp + record.timestamp_delta.0)?;
+        let mut batch = RecordBatch::new(pending.base_timestamp, pending.records);
+        batch.max_timestamp = max_timestamp;
+        batch.attributes = compression.id();
+        Some(batch)
+    }
+}
+
+struct ConsoleProducer {
+    cli: Cli,
+    client: BrokerClient,
+    failed_records: usize,
+}
+
+impl ConsoleProducer {
+    async fn run(&mut self) -> Result<(), String> {
+        let linger = Duration::from_millis(self.cli.linger_ms);
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        let mut pending = PendingBatch::default();
+        let mut line_number = 0;
+        loop {
+            let deadline = pending.deadline;
+            tokio::select! {
+                line = lines.next_line() => {
+                    let Some(line) = line.map_err(|e| format!("Failed to read stdin: {}", e))?
+                    else {
+                        break;
+                    };
+                    line_number += 1;
+                    match self.cli.parse_line(&line) {
+                        Ok(parsed) => pending.push(parsed, linger),
+                        Err(e) => {
+                            eprintln!("Skipping line {}: {}", line_number, e);
+                            continue;
+                        }
+                    }
+                    if pending.bytes >= self.cli.batch_size {
+                        self.send(&mut pending).await;
+                    }
+                }
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)),
+                    if deadline.is_some() => self.send(&mut pending).await,
+                _ = tokio::signal::ctrl_c() => break,
+            }
+        }
+        self.send(&mut pending).await;
+
+        if self.failed_records > 0 {
+            return Err(format!("Failed to produce {} records", self.failed_records));
+        }
+        Ok(())
+    }
+
+    /// Sends what is pending; failures are reported and counted rather than retried.
+    async fn send(&mut self, pending: &mut PendingBatch) {
+        let Some(batch) = pending.take(self.cli.compression_type) else {
+            return;
+        };
+        let record_count = batch.records.len();
+        if let Err(e) = self.produce(batch).await {
+            eprintln!("Failed to produce {} records: {}", record_count, e);
+            self.failed_records += record_count;
+        }
+    }
+
+    async fn produce(&mut self, batch: RecordBatch) -> Result<(), String> {
+        let request = ProduceRequest {
+            transactional_id: None,
+            acks: self.cli.acks,
+            timeout_ms: self.cli.request_timeout_ms,
+            topics: vec![TopicProduceData {
+                name: self.cli.topic.clone(),
+                partitions: vec![PartitionProduceData {
+                    index: self.cli.partition,
+                    records: vec![batch],
+                    corrupt: None,
+                }],
+            }],
+        };
+        let encode = |buf: &mut _| request.encode(buf, PRODUCE_MAX_VERSION);
+        if self.cli.acks == ACKS_NONE {
+            return self
+                .client
+                .send_without_response(PRODUCE_API_KEY, PRODUCE_MAX_VERSION, encode)
+                .await;
+        }
+
+        let mut response = self
+            .client
+            .send_request(PRODUCE_API_KEY, PRODUCE_MAX_VERSION, encode)
+            .await?;
+        let response = ProduceResponse::decode(&mut response, PRODUCE_MAX_VERSION)?;
+        let partition = response
+            .responses
+            .iter()
+            .flat_map(|topic| &topic.partitions)
+            .find(|partition| partition.index == self.cli.partition)
+            .ok_or("The response does not cover the partition")?;
+        if partition.error_code != ErrorCode::None.code() {
+            return Err(format!(
+                "error code {}{}",
+                partition.error_code,
+                partition
+                    .error_message
+                    .as_ref()
+                    .map(|message| format!(": {}", message))
+                    .unwrap_or_default()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let mut producer = ConsoleProducer {
+        client: cli.client.client("forge-console-producer"),
+        cli,
+        failed_records: 0,
+    };
+    if let Err(e) = producer.run().await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
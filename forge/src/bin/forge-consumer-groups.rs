@@ -0,0 +1,468 @@
+use clap::{Args, Parser, Subcommand};
+
+use forge::adapters::driven::broker_client::BrokerClient;
+use forge::core::domain::consumer_protocol::Assignment;
+use forge::core::domain::topic_partition::TopicPartition;
+use forge::protocol::describe_groups::{
+    DESCRIBE_GROUPS_API_KEY, DESCRIBE_GROUPS_MAX_VERSION, DescribeGroupsRequest,
+    DescribeGroupsResponse, DescribedGroup,
+};
+use forge::protocol::fetch::ISOLATION_READ_UNCOMMITTED;
+use forge::protocol::list_groups::{
+    LIST_GROUPS_API_KEY, LIST_GROUPS_MAX_VERSION, ListGroupsRequest, ListGroupsResponse,
+};
+use forge::protocol::list_offsets::{EARLIEST_TIMESTAMP, LATEST_TIMESTAMP};
+use forge::protocol::types::Type;
+use forge::tools::{
+    ClientArgs, check, commit_offsets, fetch_committed_offsets, list_offsets, print_table,
+};
+
+const CLIENT_ID: &str = "forge-consumer-groups";
+
+/// Lists and describes consumer groups, and resets their committed offsets.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    #[command(flatten)]
+    client: ClientArgs,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Lists the groups the bootstrap server coordinates.
+    List,
+    /// Shows each partition's committed offset, log end offset and lag, and who consumes it.
+    Describe(DescribeArgs),
+    /// Moves a group's committed offsets; the group must have no active members.
+    ResetOffsets(ResetArgs),
+}
+
+#[derive(Debug, Args)]
+struct DescribeArgs {
+    #[arg(long, required = true)]
+    group: Vec<String>,
+
+    /// Lists the members and their assignments instead of the partitions.
+    #[arg(long, conflicts_with = "state")]
+    members: bool,
+
+    /// Shows the group's state and assignment strategy instead of the partitions.
+    #[arg(long)]
+    state: bool,
+}
+
+#[derive(Debug, Args)]
+#[command(group(clap::ArgGroup::new("target").required(true).args(
+    ["to_earliest", "to_latest", "to_timestamp", "to_offset"]
+)))]
+#[command(group(clap::ArgGroup::new("scope").required(true).args(["topic", "all_topics"])))]
+struct ResetArgs {
+    #[arg(long)]
+    group: String,
+
+    /// topic or topic:0,1,2. Without partitions, the ones the group has committed.
+    #[arg(long, value_parser = parse_topic)]
+    topic: Vec<(String, Vec<i32>)>,
+
+    /// Every partition the group has committed.
+    #[arg(long)]
+    all_topics: bool,
+
+    #[arg(long)]
+    to_earliest: bool,
+
+    #[arg(long)]
+    to_latest: bool,
+
+    /// The first offset whose record is at or after this time, in ms since the epoch; the
+    /// log end where there is none.
+    #[arg(long)]
+    to_timestamp: Option<i64>,
+
+    /// Clamped to the partition's earliest and latest offsets.
+    #[arg(long)]
+    to_offset: Option<i64>,
+
+    /// Prints the new offsets without committing them.
+    #[arg(long)]
+    dry_run: bool,
+}
+
+fn parse_topic(value: &str) -> Result<(String, Vec<i32>), String> {
+    let Some((topic, partitions)) = value.split_once(':') else {
+        return Ok((value.to_string(), vec![]));
+    };
+    let partitions = partitions
+        .split(',')
+        .map(|partition| {
+            partition
+                .trim()
+                .parse::<i32>()
+                .map_err(|_| format!("invalid partition {:?} in {}", partition, value))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((topic.to_string(), partitions))
+}
+
+struct ConsumerGroups {
+    client_args: ClientArgs,
+    client: BrokerClient,
+}
+
+impl ConsumerGroups {
+    async fn run(&mut self, command: &Command) -> Result<(), String> {
+        match command {
+            Command::List => self.list().await,
+            Command::Describe(args) => {
+                let mut failed = false;
+                for group in &args.group {
+                    if let Err(e) = self.describe(group, args).await {
+                        eprintln!("{}", e);
+                        failed = true;
+                    }
+                }
+                if failed {
+                    return Err("Failed to describe some groups".to_string());
+                }
+                Ok(())
+            }
+            Command::ResetOffsets(args) => self.reset_offsets(args).await,
+        }
+    }
+
+    async fn list(&mut self) -> Result<(), String> {
+        let mut response = self
+            .client
+            .send_request(LIST_GROUPS_API_KEY, LIST_GROUPS_MAX_VERSION, |buf| {
+                ListGroupsRequest.encode(buf, LIST_GROUPS_MAX_VERSION)
+            })
+            .await?;
+        let response = ListGroupsResponse::decode(&mut response, LIST_GROUPS_MAX_VERSION)?;
+        check(response.error_code, "list groups")?;
+        let mut group_ids: Vec<String> = response
+            .groups
+            .into_iter()
+            .map(|group| group.group_id)
+            .collect();
+        group_ids.sort();
+        for group_id in group_ids {
+            println!("{}", group_id);
+        }
+        Ok(())
+    }
+
+    async fn coordinator(&mut self, group: &str) -> Result<BrokerClient, String> {
+        self.client_args
+            .coordinator(&mut self.client, group, CLIENT_ID)
+            .await
+    }
+
+    async fn describe_group(
+        coordinator: &mut BrokerClient,
+        group: &str,
+    ) -> Result<DescribedGroup, String> {
+        let request = DescribeGroupsRequest {
+            groups: vec![group.to_string()],
+            include_authorized_operations: false,
+        };
+        let mut response = coordinator
+            .send_request(
+                DESCRIBE_GROUPS_API_KEY,
+                DESCRIBE_GROUPS_MAX_VERSION,
+                |buf| request.encode(buf, DESCRIBE_GROUPS_MAX_VERSION),
+            )
+            .await?;
+        let response = DescribeGroupsResponse::decode(&mut response, DESCRIBE_GROUPS_MAX_VERSION)?;
+        let described = response
+            .groups
+            .into_iter()
+            .next()
+            .ok_or("The response does not describe the group")?;
+        check(described.error_code, &format!("describe group {}", group))?;
+        Ok(described)
+    }
+
+    async fn describe(&mut self, group: &str, args: &DescribeArgs) -> Result<(), String> {
+        let mut coordinator = self.coordinator(group).await?;
+        let described = Self::describe_group(&mut coordinator, group).await?;
+        if described.group_state == "Dead" {
+            return Err(format!("Consumer group {} does not exist", group));
+        }
+
+        // Members whose assignment does not parse, e.g. of other protocol types, own nothing
+        let assignments: Vec<Vec<TopicPartition>> = described
+            .members
+            .iter()
+            .map(|member| {
+                Assignment::decode(&mut member.member_assignment.as_slice())
+                    .map(|assignment| assignment.partitions)
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        if args.state {
+            print_table(
+                &[
+                    "GROUP",
+                    "COORDINATOR",
+                    "ASSIGNMENT-STRATEGY",
+                    "STATE",
+                    "#MEMBERS",
+                ],
+                &[vec![
+                    group.to_string(),
+                    coordinator.address.clone(),
+                    described.protocol_data.clone(),
+                    described.group_state.clone(),
+                    described.members.len().to_string(),
+                ]],
+            );
+            return Ok(());
+        }
+        if args.members {
+            let rows: Vec<Vec<String>> = described
+                .members
+                .iter()
+                .zip(&assignments)
+                .map(|(member, partitions)| {
+                    vec![
+                        group.to_string(),
+                        member.member_id.clone(),
+                        member.client_host.clone(),
+                        member.client_id.clone(),
+                        partitions.len().to_string(),
+                        partitions
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(","),
+                    ]
+                })
+                .collect();
+            print_table(
+                &[
+                    "GROUP",
+                    "CONSUMER-ID",
+                    "HOST",
+                    "CLIENT-ID",
+                    "#PARTITIONS",
+                    "ASSIGNMENT",
+                ],
+                &rows,
+            );
+            return Ok(());
+        }
+
+        let committed = fetch_committed_offsets(&mut coordinator, group, None).await?;
+        let mut partitions: Vec<TopicPartition> =
+            committed.iter().map(|(tp, _)| tp.clone()).collect();
+        partitions.extend(assignments.iter().flatten().cloned());
+        partitions.sort();
+        partitions.dedup();
+        let log_end_offsets = if partitions.is_empty() {
+            vec![]
+        } else {
+            list_offsets(
+                &mut self.client,
+                ISOLATION_READ_UNCOMMITTED,
+                &partitions,
+                LATEST_TIMESTAMP,
+            )
+            .await?
+        };
+
+        let rows: Vec<Vec<String>> = partitions
+            .iter()
+            .map(|topic_partition| {
+                let current = committed
+                    .iter()
+                    .find(|(tp, _)| tp == topic_partition)
+                    .map(|(_, offset)| *offset);
+                let log_end = log_end_offsets
+                    .iter()
+                    .find(|(tp, _)| tp == topic_partition)
+                    .and_then(|(_, offset)| offset.ok());
+                let owner = assignments
+                    .iter()
+                    .position(|partitions| partitions.contains(topic_partition))
+                    .map(|index| &described.members[index]);
+                let show = |value: Option<i64>| value.map_or("-".to_string(), |v| v.to_string());
+                vec![
+                    group.to_string(),
+                    topic_partition.topic.clone(),
+                    topic_partition.partition.to_string(),
+                    show(current),
+                    show(log_end),
+                    show(
+                        current
+                            .zip(log_end)
+                            .map(|(current, end)| (end - current).max(0)),
+                    ),
+                    owner.map_or("-".to_string(), |member| member.member_id.clone()),
+                    owner.map_or("-".to_string(), |member| member.client_host.clone()),
+                    owner.map_or("-".to_string(), |member| member.client_id.clone()),
+                ]
+            })
+            .collect();
+        if described.members.is_empty() {
+            eprintln!("Consumer group {} has no active members.", group);
+        }
+        print_table(
+            &[
+                "GROUP",
+                "TOPIC",
+                "PARTITION",
+                "CURRENT-OFFSET",
+                "LOG-END-OFFSET",
+                "LAG",
+                "CONSUMER-ID",
+                "HOST",
+                "CLIENT-ID",
+            ],
+            &rows,
+        );
+        Ok(())
+    }
+
+    async fn reset_offsets(&mut self, args: &ResetArgs) -> Result<(), String> {
+        let group = args.group.as_str();
+        let mut coordinator = self.coordinator(group).await?;
+        let described = Self::describe_group(&mut coordinator, group).await?;
+        if described.group_state != "Empty" && described.group_state != "Dead" {
+            return Err(format!(
+                "Offsets can only be reset while the group is inactive, but {} is {}",
+                group, described.group_state
+            ));
+        }
+
+        let committed = fetch_committed_offsets(&mut coordinator, group, None).await?;
+        let mut partitions = Vec::new();
+        if args.all_topics {
+            partitions.extend(committed.iter().map(|(tp, _)| tp.clone()));
+        }
+        for (topic, indexes) in &args.topic {
+            if !indexes.is_empty() {
+                partitions.extend(
+                    indexes
+                        .iter()
+                        .map(|index| TopicPartition::new(topic.clone(), *index)),
+                );
+                continue;
+            }
+            let before = partitions.len();
+            partitions.extend(
+                committed
+                    .iter()
+                    .filter(|(tp, _)| tp.topic == *topic)
+                    .map(|(tp, _)| tp.clone()),
+            );
+            if partitions.len() == before {
+                return Err(format!(
+                    "Group {} has no offsets for {}; name its partitions as {}:0,1,...",
+                    group, topic, topic
+                ));
+            }
+        }
+        partitions.sort();
+        partitions.dedup();
+        if partitions.is_empty() {
+            return Err(format!("Group {} has no committed offsets", group));
+        }
+
+        let new_offsets = self.target_offsets(args, &partitions).await?;
+        let rows: Vec<Vec<String>> = new_offsets
+            .iter()
+            .map(|(tp, offset)| {
+                vec![
+                    group.to_string(),
+                    tp.topic.clone(),
+                    tp.partition.to_string(),
+                    offset.to_string(),
+                ]
+            })
+            .collect();
+        print_table(&["GROUP", "TOPIC", "PARTITION", "NEW-OFFSET"], &rows);
+
+        if args.dry_run {
+            eprintln!("Dry run: the offsets were not committed.");
+            return Ok(());
+        }
+        commit_offsets(&mut coordinator, group, &new_offsets).await
+    }
+
+    /// Where each partition's committed offset moves to.
+    async fn target_offsets(
+        &mut self,
+        args: &ResetArgs,
+        partitions: &[TopicPartition],
+    ) -> Result<Vec<(TopicPartition, i64)>, String> {
+        let earliest = self.offsets(partitions, EARLIEST_TIMESTAMP).await?;
+        let latest = self.offsets(partitions, LATEST_TIMESTAMP).await?;
+        let by_timestamp = match args.to_timestamp {
+            Some(timestamp) => Some(self.offsets(partitions, timestamp).await?),
+            None => None,
+        };
+
+        Ok(partitions
+            .iter()
+            .enumerate()
+            .map(|(index, tp)| {
+                let offset = if args.to_earliest {
+                    earliest[index]
+                } else if let Some(by_timestamp) = &by_timestamp {
+                    match by_timestamp[index] {
+                        offset if offset >= 0 => offset,
+                        _ => latest[index],
+                    }
+                } else if let Some(offset) = args.to_offset {
+                    offset.clamp(earliest[index], latest[index])
+                } else {
+                    latest[index]
+                };
+                (tp.clone(), offset)
+            })
+            .collect())
+    }
+
+    /// One ListOffsets answer per partition, in the order of `partitions`.
+    async fn offsets(
+        &mut self,
+        partitions: &[TopicPartition],
+        timestamp: i64,
+    ) -> Result<Vec<i64>, String> {
+        let listed = list_offsets(
+            &mut self.client,
+            ISOLATION_READ_UNCOMMITTED,
+            partitions,
+            timestamp,
+        )
+        .await?;
+        partitions
+            .iter()
+            .map(|tp| match listed.iter().find(|(listed, _)| listed == tp) {
+                Some((_, Ok(offset))) => Ok(*offset),
+                Some((_, Err(error_code))) => Err(format!(
+                    "Failed to list offsets of {}: error code {}",
+                    tp, error_code
+                )),
+                None => Err(format!("The response does not cover {}", tp)),
+            })
+            .collect()
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let mut tool = ConsumerGroups {
+        client: cli.client.client(CLIENT_ID),
+        client_args: cli.client,
+    };
+    if let Err(e) = tool.run(&cli.command).await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,255 @@
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use forge::adapters::driven::broker_client::BrokerClient;
+use forge::core::error::ErrorCode;
+use forge::protocol::elect_leaders::{
+    ELECT_LEADERS_API_KEY, ELECT_LEADERS_MAX_VERSION, ELECTION_TYPE_PREFERRED,
+    ELECTION_TYPE_UNCLEAN, ElectLeadersRequest, ElectLeadersResponse, TopicPartitions,
+};
+use forge::protocol::metadata::{
+    METADATA_API_KEY, METADATA_MAX_VERSION, MetadataRequest, MetadataResponse,
+};
+use forge::tools::{ClientArgs, check};
+
+const CLIENT_ID: &str = "forge-leader-election";
+const REQUEST_TIMEOUT_MS: i32 = 30_000;
+
+/// Triggers leader elections: preferred ones move leadership back to the first replica of
+/// each partition, unclean ones bring back partitions left without a leader, at the cost of
+/// the records their out-of-sync replicas lack.
+#[derive(Debug, Parser)]
+#[command(version)]
+#[command(group(clap::ArgGroup::new("scope").required(true).args(
+    ["all_topic_partitions", "topic", "path_to_json_file"]
+)))]
+struct Cli {
+    #[command(flatten)]
+    client: ClientArgs,
+
+    #[arg(long, value_enum)]
+    election_type: ElectionType,
+
+    /// Elects leaders for every partition of every topic.
+    #[arg(long)]
+    all_topic_partitions: bool,
+
+    #[arg(long)]
+    topic: Option<String>,
+
+    /// A partition of --topic; repeat for several. Every partition of the topic when not given.
+    #[arg(long, requires = "topic")]
+    partition: Vec<i32>,
+
+    /// Partitions listed as `{"partitions":[{"topic":"events","partition":0}]}`.
+    #[arg(long)]
+    path_to_json_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ElectionType {
+    Preferred,
+    Unclean,
+}
+
+impl ElectionType {
+    fn code(self) -> i8 {
+        match self {
+            ElectionType::Preferred => ELECTION_TYPE_PREFERRED,
+            ElectionType::Unclean => ELECTION_TYPE_UNCLEAN,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PartitionList {
+    partitions: Vec<ListedPartition>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListedPartition {
+    topic: String,
+    partition: i32,
+}
+
+struct LeaderElection {
+    client: BrokerClient,
+}
+
+impl LeaderElection {
+    async fn run(&mut self, cli: &Cli) -> Result<(), String> {
+        let topic_partitions = if cli.all_topic_partitions {
+            None
+        } else if let Some(topic) = &cli.topic {
+            let partition_ids = if cli.partition.is_empty() {
+                self.partitions_of(topic).await?
+            } else {
+                cli.partition.clone()
+            };
+            Some(vec![TopicPartitions {
+                topic: topic.clone(),
+                partition_ids,
+            }])
+        } else if let Some(path) = &cli.path_to_json_file {
+            Some(read_partition_list(path).await?)
+        } else {
+            unreachable!("clap requires a scope")
+        };
+
+        let request = ElectLeadersRequest {
+            election_type: cli.election_type.code(),
+            topic_partitions,
+            timeout_ms: REQUEST_TIMEOUT_MS,
+        };
+        let mut response = self
+            .client
+            .send_request(ELECT_LEADERS_API_KEY, ELECT_LEADERS_MAX_VERSION, |buf| {
+                request.encode(buf, ELECT_LEADERS_MAX_VERSION)
+            })
+            .await?;
+        let response = ElectLeadersResponse::decode(&mut response, ELECT_LEADERS_MAX_VERSION)?;
+        check(response.error_code, "elect leaders")?;
+
+        let election = format!("{:?}", cli.election_type).to_uppercase();
+        let mut elected = Vec::new();
+        let mut not_needed = Vec::new();
+        let mut failed = Vec::new();
+        for topic in &response.replica_election_results {
+            for result in &topic.partition_result {
+                let partition = format!("{}-{}", topic.topic, result.partition_id);
+                if result.error_code == ErrorCode::None.code() {
+                    elected.push(partition);
+                } else if result.error_code == ErrorCode::ElectionNotNeeded.code() {
+                    not_needed.push(partition);
+                } else {
+                    let reason = result
+                        .error_message
+                        .clone()
+                        .unwrap_or_else(|| describe_error(result.error_code));
+                    failed.push((partition, reason));
+                }
+            }
+        }
+
+        if !elected.is_empty() {
+            println!(
+                "Successfully completed leader election ({}) for partitions {}",
+                election,
+                elected.join(", ")
+            );
+        }
+        if !not_needed.is_empty() {
+            println!(
+                "Valid replica already elected for partitions {}",
+                not_needed.join(", ")
+            );
+        }
+        if elected.is_empty() && not_needed.is_empty() && failed.is_empty() {
+            println!("No partition needed a leader election ({})", election);
+        }
+        for (partition, reason) in &failed {
+            println!(
+                "Error completing leader election ({}) for partition {}: {}",
+                election, partition, reason
+            );
+        }
+        if !failed.is_empty() {
+            return Err(format!(
+                "{} partitions failed leader election",
+                failed.len()
+            ));
+        }
+        Ok(())
+    }
+
+    async fn partitions_of(&mut self, topic: &str) -> Result<Vec<i32>, String> {
+        let request = MetadataRequest {
+            topics: Some(vec![topic.to_string()]),
+            allow_auto_topic_creation: false,
+            include_cluster_authorized_operations: false,
+            include_topic_authorized_operations: false,
+        };
+        let mut response = self
+            .client
+            .send_request(METADATA_API_KEY, METADATA_MAX_VERSION, |buf| {
+                request.encode(buf, METADATA_MAX_VERSION)
+            })
+            .await?;
+        let response = MetadataResponse::decode(&mut response, METADATA_MAX_VERSION)?;
+        let topic_metadata = response
+            .topics
+            .into_iter()
+            .find(|metadata| metadata.name == topic)
+            .ok_or_else(|| format!("No metadata returned for topic {}", topic))?;
+        check(
+            topic_metadata.error_code,
+            &format!("describe topic {}", topic),
+        )?;
+        let mut partition_ids: Vec<i32> = topic_metadata
+            .partitions
+            .iter()
+            .map(|partition| partition.partition_index)
+            .collect();
+        partition_ids.sort_unstable();
+        Ok(partition_ids)
+    }
+}
+
+async fn read_partition_list(path: &PathBuf) -> Result<Vec<TopicPartitions>, String> {
+    let json = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let list: PartitionList = serde_json::from_str(&json)
+        .map_err(|e| format!("Invalid partition list {}: {}", path.display(), e))?;
+    if list.partitions.is_empty() {
+        return Err(format!("{} lists no partitions", path.display()));
+    }
+
+    let mut topics: Vec<TopicPartitions> = Vec::new();
+    for listed in list.partitions {
+        match topics.iter_mut().find(|topic| topic.topic == listed.topic) {
+            Some(topic) if topic.partition_ids.contains(&listed.partition) => {
+                return Err(format!(
+                    "{} lists {}-{} more than once",
+                    path.display(),
+                    listed.topic,
+                    listed.partition
+                ));
+            }
+            Some(topic) => topic.partition_ids.push(listed.partition),
+            None => topics.push(TopicPartitions {
+                topic: listed.topic,
+                partition_ids: vec![listed.partition],
+            }),
+        }
+    }
+    Ok(topics)
+}
+
+fn describe_error(error_code: i16) -> String {
+    let reason = if error_code == ErrorCode::PreferredLeaderNotAvailable.code() {
+        "the preferred replica is not alive or not in sync"
+    } else if error_code == ErrorCode::EligibleLeadersNotAvailable.code() {
+        "no replica is alive"
+    } else if error_code == ErrorCode::UnknownTopicOrPartition.code() {
+        "the partition does not exist"
+    } else if error_code == ErrorCode::ClusterAuthorizationFailed.code() {
+        "not authorized to alter the cluster"
+    } else {
+        return format!("error code {}", error_code);
+    };
+    format!("{} (error code {})", reason, error_code)
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let mut tool = LeaderElection {
+        client: cli.client.client(CLIENT_ID),
+    };
+    if let Err(e) = tool.run(&cli).await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,294 @@
+use clap::Parser;
+use serde::Serialize;
+use tokio::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use forge::client::cluster::ClientConfig;
+use forge::client::consumer::{Consumer, ConsumerConfig, ConsumerRecord, OffsetReset};
+use forge::client::producer::{Delivery, Producer, ProducerConfig};
+use forge::core::domain::topic_partition::TopicPartition;
+use forge::shared::collections::FlatMap;
+
+const CLIENT_ID: &str = "forge-mirror";
+const POLL_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Copies topics from a source cluster to a target cluster until Ctrl+C, each record to the
+/// same partition of the target topic with its key, value, headers and timestamp. The
+/// target topics must exist, or be auto-created, with at least as many partitions.
+///
+/// The mirror consumes as a member of --group on the source, so several mirrors with the
+/// same group share the partitions. Every --checkpoint-interval-ms, once the target has
+/// acknowledged what was sent, the source positions are committed for the group; a
+/// restarted mirror resumes from them and may copy the records since the last checkpoint
+/// again. With --checkpoint-topic each checkpoint is also written to the target as one
+/// record per partition, pairing the source offset with the target offset it was copied
+/// to, so consumers moving to the target can translate their committed offsets.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    /// Comma-separated host:port of source brokers.
+    #[arg(long)]
+    source_bootstrap_servers: String,
+
+    /// Comma-separated host:port of target brokers.
+    #[arg(long)]
+    target_bootstrap_servers: String,
+
+    /// Source topics to copy; repeatable or comma-separated.
+    #[arg(long = "topic", required = true, value_delimiter = ',')]
+    topics: Vec<String>,
+
+    /// Copies the source topic OLD into the target topic NEW; repeatable.
+    #[arg(long = "rename", value_name = "OLD=NEW", value_parser = parse_rename)]
+    renames: Vec<(String, String)>,
+
+    /// Put before the target name of every topic without a --rename, e.g. "primary.".
+    #[arg(long, default_value = "")]
+    target_prefix: String,
+
+    #[arg(long, default_value = CLIENT_ID)]
+    group: String,
+
+    /// earliest or latest; where partitions without a checkpoint start.
+    #[arg(long, default_value = "earliest", value_parser = parse_offset_reset)]
+    auto_offset_reset: OffsetReset,
+
+    #[arg(long, default_value_t = 5000)]
+    checkpoint_interval_ms: u64,
+
+    /// Target topic the checkpoints are also written to.
+    #[arg(long)]
+    checkpoint_topic: Option<String>,
+}
+
+fn parse_rename(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((old, new)) if !old.is_empty() && !new.is_empty() => {
+            Ok((old.to_string(), new.to_string()))
+        }
+        _ => Err(format!("expected OLD=NEW, got {}", value)),
+    }
+}
+
+fn parse_offset_reset(value: &str) -> Result<OffsetReset, String> {
+    match value {
+        "earliest" => Ok(OffsetReset::Earliest),
+        "latest" => Ok(OffsetReset::Latest),
+        _ => Err(format!("expected earliest or latest, got {}", value)),
+    }
+}
+
+impl Cli {
+    fn target_topic(&self, source_topic: &str) -> String {
+        self.renames
+            .iter()
+            .find(|(old, _)| old == source_topic)
+            .map_or_else(
+                || format!("{}{}", self.target_prefix, source_topic),
+                |(_, new)| new.clone(),
+            )
+    }
+}
+
+/// One partition's progress, as written to --checkpoint-topic under the key
+/// `source_topic:partition`.
+#[derive(Debug, Serialize)]
+struct Checkpoint<'a> {
+    group: &'a str,
+    source_topic: &'a str,
+    target_topic: String,
+    partition: i32,
+    /// The next source offset to copy, as committed for the group.
+    source_offset: i64,
+    /// Where the record before `source_offset` was written.
+    target_offset: i64,
+}
+
+/// A record sent to the target and not yet part of a checkpoint.
+struct InFlight {
+    source: TopicPartition,
+    offset: i64,
+    delivery: Delivery,
+}
+
+struct Mirror {
+    cli: Cli,
+    consumer: Consumer,
+    producer: Producer,
+    in_flight: Vec<InFlight>,
+    /// By source partition: the next source offset and the target offset of the record
+    /// before it, for what the target acknowledged since the last checkpoint.
+    acknowledged: FlatMap<TopicPartition, (i64, i64)>,
+    mirrored: u64,
+}
+
+impl Mirror {
+    async fn run(&mut self) -> Result<(), String> {
+        let topics: Vec<&str> = self.cli.topics.iter().map(String::as_str).collect();
+        self.consumer.subscribe(&topics, None);
+
+        let checkpoint_interval = Duration::from_millis(self.cli.checkpoint_interval_ms);
+        let mut next_checkpoint = Instant::now() + checkpoint_interval;
+        // Checked between polls: a poll dropped halfway leaves its response on the connection
+        let interrupted = CancellationToken::new();
+        tokio::spawn({
+            let interrupted = interrupted.clone();
+            async move {
+                let _ = tokio::signal::ctrl_c().await;
+                interrupted.cancel();
+            }
+        });
+        let result = loop {
+            if interrupted.is_cancelled() {
+                break Ok(());
+            }
+            let copied = match self.consumer.poll(POLL_TIMEOUT).await {
+                Ok(records) => self.copy(records).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = copied {
+                break Err(e);
+            }
+            if Instant::now() >= next_checkpoint {
+                if let Err(e) = self.checkpoint().await {
+                    break Err(e);
+                }
+                next_checkpoint = Instant::now() + checkpoint_interval;
+            }
+        };
+
+        let checkpoint = self.checkpoint().await;
+        eprintln!("Mirrored a total of {} messages", self.mirrored);
+        result.and(checkpoint)
+    }
+
+    async fn copy(&mut self, records: Vec<ConsumerRecord>) -> Result<(), String> {
+        for record in records {
+            let target_topic = self.cli.target_topic(&record.topic);
+            let delivery = self
+                .producer
+                .enqueue_to(
+                    &target_topic,
+                    Some(record.partition),
+                    Some(record.timestamp),
+                    record.key,
+                    record.value,
+                    record.headers,
+                )
+                .await?;
+            self.in_flight.push(InFlight {
+                source: TopicPartition::new(record.topic, record.partition),
+                offset: record.offset,
+                delivery,
+            });
+        }
+        Ok(())
+    }
+
+    /// Waits for the target to acknowledge every record sent, then commits the source
+    /// positions of the partitions still assigned. A record the target refused fails the
+    /// mirror without committing.
+    async fn checkpoint(&mut self) -> Result<(), String> {
+        for in_flight in std::mem::take(&mut self.in_flight) {
+            let metadata = in_flight.delivery.await.map_err(|e| {
+                format!(
+                    "Failed to copy offset {} of {}: {}",
+                    in_flight.offset, in_flight.source, e
+                )
+            })?;
+            self.acknowledged
+                .insert(in_flight.source, (in_flight.offset + 1, metadata.offset));
+            self.mirrored += 1;
+        }
+
+        let acknowledged = std::mem::take(&mut self.acknowledged);
+        // Partitions revoked since are copied again by their new owner from its checkpoint
+        let offsets: Vec<(TopicPartition, i64)> = acknowledged
+            .iter()
+            .filter(|(source, _)| self.consumer.assignment().contains(source))
+            .map(|(source, (next_offset, _))| (source.clone(), *next_offset))
+            .collect();
+        if offsets.is_empty() {
+            return Ok(());
+        }
+        if let Err(e) = self.consumer.commit_sync(Some(&offsets)).await {
+            // The next poll rejoins the group, and what was copied is copied again
+            eprintln!("{}", e);
+            return Ok(());
+        }
+
+        let Some(checkpoint_topic) = &self.cli.checkpoint_topic else {
+            return Ok(());
+        };
+        let mut deliveries = Vec::new();
+        for (source, next_offset) in &offsets {
+            let Some((_, target_offset)) = acknowledged.get(source) else {
+                continue;
+            };
+            let checkpoint = Checkpoint {
+                group: &self.cli.group,
+                source_topic: &source.topic,
+                target_topic: self.cli.target_topic(&source.topic),
+                partition: source.partition,
+                source_offset: *next_offset,
+                target_offset: *target_offset,
+            };
+            let value = serde_json::to_vec(&checkpoint).map_err(|e| e.to_string())?;
+            let key = format!("{}:{}", source.topic, source.partition);
+            deliveries.push(
+                self.producer
+                    .enqueue(
+                        checkpoint_topic,
+                        Some(key.into_bytes()),
+                        Some(value),
+                        vec![],
+                    )
+                    .await?,
+            );
+        }
+        for delivery in deliveries {
+            delivery
+                .await
+                .map_err(|e| format!("Failed to write a checkpoint: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    let mut consumer_config = ConsumerConfig::new(
+        ClientConfig::new(&cli.source_bootstrap_servers, CLIENT_ID),
+        &cli.group,
+    );
+    consumer_config.auto_offset_reset = cli.auto_offset_reset;
+    consumer_config.enable_auto_commit = false;
+    // Copies within a partition stay in order even when a batch is retried
+    let mut producer_config =
+        ProducerConfig::new(ClientConfig::new(&cli.target_bootstrap_servers, CLIENT_ID));
+    producer_config.enable_idempotence = true;
+    let producer = match Producer::new(producer_config) {
+        Ok(producer) => producer,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut mirror = Mirror {
+        consumer: Consumer::new(consumer_config),
+        producer,
+        cli,
+        in_flight: Vec::new(),
+        acknowledged: FlatMap::new(),
+        mirrored: 0,
+    };
+    let result = mirror.run().await;
+    let closed = mirror.consumer.close().await;
+    if let Err(e) = result.and(closed) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
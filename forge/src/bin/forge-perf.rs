@@ -0,0 +1,451 @@
+use clap::{Args, Parser, Subcommand};
+use rand::RngExt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::time::{Duration, Instant};
+
+use forge::adapters::driven::broker_client::BrokerClient;
+use forge::application::replica_manager::ACKS_NONE;
+use forge::core::domain::compression::CompressionType;
+use forge::core::domain::record::Record;
+use forge::core::domain::record_batch::RecordBatch;
+use forge::core::domain::topic_partition::TopicPartition;
+use forge::core::ports::driven::FetchClient;
+use forge::protocol::fetch::{
+    FetchPartition, FetchRequest, FetchTopic, ISOLATION_READ_UNCOMMITTED,
+};
+use forge::protocol::list_offsets::EARLIEST_TIMESTAMP;
+use forge::protocol::produce::{
+    PRODUCE_API_KEY, PRODUCE_MAX_VERSION, PartitionProduceData, ProduceRequest, ProduceResponse,
+    TopicProduceData,
+};
+use forge::shared::time::current_time_ms;
+use forge::tools::{ClientArgs, check, list_offsets};
+
+const CLIENT_ID: &str = "forge-perf";
+const FETCH_MAX_BYTES: i32 = 50 * 1024 * 1024;
+
+/// Measures produce or fetch throughput and request latency against one broker, with one
+/// connection per partition. Run the same command against two builds to compare them.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    #[command(flatten)]
+    client: ClientArgs,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Sends --num-records records of --record-size bytes, spread evenly over the partitions.
+    Produce(ProduceArgs),
+    /// Reads from the earliest offset of each partition until --num-records records arrive.
+    Consume(ConsumeArgs),
+}
+
+#[derive(Debug, Args)]
+struct CommonArgs {
+    #[arg(long)]
+    topic: String,
+
+    /// Uses partitions 0 to N-1, which the bootstrap server must lead.
+    #[arg(long, default_value_t = 1)]
+    partitions: i32,
+
+    #[arg(long)]
+    num_records: u64,
+
+    /// How often progress is printed; 0 prints only the summary.
+    #[arg(long, default_value_t = 5000)]
+    reporting_interval_ms: u64,
+}
+
+#[derive(Debug, Args)]
+struct ProduceArgs {
+    #[command(flatten)]
+    common: CommonArgs,
+
+    /// Value size in bytes; records have no key.
+    #[arg(long, default_value_t = 100)]
+    record_size: usize,
+
+    /// Bytes of values per batch; each produce request carries one batch.
+    #[arg(long, default_value_t = 16 * 1024)]
+    batch_size: usize,
+
+    /// 0, 1 or all.
+    #[arg(long, default_value = "1", value_parser = parse_acks)]
+    acks: i16,
+
+    /// none, gzip, snappy, lz4 or zstd.
+    #[arg(long, default_value = "none", value_parser = parse_compression)]
+    compression_type: CompressionType,
+
+    /// Caps the send rate in records per second across all partitions.
+    #[arg(long)]
+    throughput: Option<u64>,
+
+    #[arg(long, default_value_t = 30_000)]
+    request_timeout_ms: i32,
+}
+
+#[derive(Debug, Args)]
+struct ConsumeArgs {
+    #[command(flatten)]
+    common: CommonArgs,
+
+    /// Bytes a fetch may return per partition.
+    #[arg(long, default_value_t = 1024 * 1024)]
+    fetch_size: i32,
+
+    #[arg(long, default_value_t = 500)]
+    fetch_max_wait_ms: i32,
+
+    /// Gives up when no record has arrived for this long.
+    #[arg(long, default_value_t = 10_000)]
+    timeout_ms: u64,
+}
+
+fn parse_acks(value: &str) -> Result<i16, String> {
+    match value {
+        "all" | "-1" => Ok(-1),
+        "0" => Ok(0),
+        "1" => Ok(1),
+        _ => Err(format!("expected 0, 1 or all, got {}", value)),
+    }
+}
+
+fn parse_compression(value: &str) -> Result<CompressionType, String> {
+    CompressionType::from_name(value)
+        .ok_or_else(|| format!("expected none, gzip, snappy, lz4 or zstd, got {}", value))
+}
+
+/// Records and value bytes moved so far, shared by the partition tasks and the reporter.
+#[derive(Default)]
+struct Progress {
+    records: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl Progress {
+    fn add(&self, records: u64, bytes: u64) {
+        self.records.fetch_add(records, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Prints the rate of every interval until aborted.
+    async fn report(self: Arc<Self>, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        let (mut records, mut bytes) = (0, 0);
+        loop {
+            ticker.tick().await;
+            let now_records = self.records.load(Ordering::Relaxed);
+            let now_bytes = self.bytes.load(Ordering::Relaxed);
+            println!(
+                "{} records, {}",
+                now_records,
+                rate(now_records - records, now_bytes - bytes, interval)
+            );
+            (records, bytes) = (now_records, now_bytes);
+        }
+    }
+}
+
+fn rate(records: u64, bytes: u64, elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+    format!(
+        "{:.1} records/sec ({:.2} MB/sec)",
+        records as f64 / seconds,
+        bytes as f64 / seconds / (1024.0 * 1024.0)
+    )
+}
+
+/// Average, max and percentiles of request latencies, in ms.
+fn latency_summary(mut latencies: Vec<Duration>) -> String {
+    if latencies.is_empty() {
+        return "no latencies measured".to_string();
+    }
+    latencies.sort_unstable();
+    let ms = |latency: Duration| latency.as_secs_f64() * 1000.0;
+    let percentile = |p: f64| {
+        let rank = (latencies.len() as f64 * p).ceil() as usize;
+        ms(latencies[rank.clamp(1, latencies.len()) - 1])
+    };
+    let total: Duration = latencies.iter().sum();
+    format!(
+        "{:.2} ms avg, {:.2} ms max, {:.2} ms p50, {:.2} ms p95, {:.2} ms p99, {:.2} ms p99.9 \
+         over {} requests",
+        ms(total) / latencies.len() as f64,
+        ms(*latencies.last().unwrap()),
+        percentile(0.5),
+        percentile(0.95),
+        percentile(0.99),
+        percentile(0.999),
+        latencies.len()
+    )
+}
+
+/// Runs one task per partition, each given its share of `num_records`, and prints the
+/// summary once all of them finish.
+async fn run_partitions<F, Fut>(
+    cli: &Cli,
+    common: &CommonArgs,
+    run_partition: F,
+) -> Result<(), String>
+where
+    F: Fn(BrokerClient, i32, u64, Arc<Progress>) -> Fut,
+    Fut: Future<Output = Result<Vec<Duration>, String>> + Send + 'static,
+{
+    if common.partitions < 1 {
+        return Err("--partitions must be at least 1".to_string());
+    }
+    let progress = Arc::new(Progress::default());
+    let reporter = (common.reporting_interval_ms > 0).then(|| {
+        tokio::spawn(
+            progress
+                .clone()
+                .report(Duration::from_millis(common.reporting_interval_ms)),
+        )
+    });
+
+    let partitions = common.partitions as u64;
+    let start = Instant::now();
+    let tasks: Vec<_> = (0..common.partitions)
+        .map(|partition| {
+            let index = partition as u64;
+            let share = common.num_records / partitions
+                + u64::from(index < common.num_records % partitions);
+            tokio::spawn(run_partition(
+                cli.client.client(CLIENT_ID),
+                partition,
+                share,
+                progress.clone(),
+            ))
+        })
+        .collect();
+
+    let mut latencies = Vec::new();
+    let mut result = Ok(());
+    for (partition, task) in tasks.into_iter().enumerate() {
+        match task.await.map_err(|e| e.to_string()).and_then(|r| r) {
+            Ok(task_latencies) => latencies.extend(task_latencies),
+            Err(e) => result = Err(format!("Partition {}: {}", partition, e)),
+        }
+    }
+    let elapsed = start.elapsed();
+    if let Some(reporter) = reporter {
+        reporter.abort();
+    }
+
+    println!(
+        "{} records in {:.2} s, {}; latency {}",
+        progress.records.load(Ordering::Relaxed),
+        elapsed.as_secs_f64(),
+        rate(
+            progress.records.load(Ordering::Relaxed),
+            progress.bytes.load(Ordering::Relaxed),
+            elapsed
+        ),
+        latency_summary(latencies)
+    );
+    result
+}
+
+async fn produce(cli: &Cli, args: &ProduceArgs) -> Result<(), String> {
+    // One random value reused for every record, so generating data costs nothing per record.
+    let mut rng = rand::rng();
+    let value: Arc<[u8]> = (0..args.record_size)
+        .map(|_| rng.random_range(b'A'..=b'Z'))
+        .collect();
+    let records_per_batch = (args.batch_size / args.record_size.max(1)).max(1) as u64;
+    let partition_throughput = args
+        .throughput
+        .map(|throughput| throughput as f64 / args.common.partitions.max(1) as f64);
+    let topic = args.common.topic.clone();
+    let (acks, timeout_ms, compression) =
+        (args.acks, args.request_timeout_ms, args.compression_type);
+
+    run_partitions(
+        cli,
+        &args.common,
+        |mut client, partition, share, progress| {
+            let (topic, value) = (topic.clone(), value.clone());
+            async move {
+                let mut latencies = Vec::new();
+                let start = Instant::now();
+                let mut sent = 0;
+                while sent < share {
+                    if let Some(throughput) = partition_throughput {
+                        let due = start + Duration::from_secs_f64(sent as f64 / throughput);
+                        tokio::time::sleep_until(due).await;
+                    }
+                    let count = records_per_batch.min(share - sent);
+                    let records = (0..count)
+                        .map(|delta| Record::new(delta as i32, None, Some(value.to_vec())))
+                        .collect();
+                    let mut batch = RecordBatch::new(current_time_ms(), records);
+                    batch.attributes = compression.id();
+                    let request = ProduceRequest {
+                        transactional_id: None,
+                        acks,
+                        timeout_ms,
+                        topics: vec![TopicProduceData {
+                            name: topic.clone(),
+                            partitions: vec![PartitionProduceData {
+                                index: partition,
+                                records: vec![batch],
+                                corrupt: None,
+                            }],
+                        }],
+                    };
+
+                    let sent_at = Instant::now();
+                    send_produce(&mut client, &request, acks, partition).await?;
+                    if acks != ACKS_NONE {
+                        latencies.push(sent_at.elapsed());
+                    }
+                    sent += count;
+                    progress.add(count, count * value.len() as u64);
+                }
+                Ok(latencies)
+            }
+        },
+    )
+    .await
+}
+
+async fn send_produce(
+    client: &mut BrokerClient,
+    request: &ProduceRequest,
+    acks: i16,
+    partition: i32,
+) -> Result<(), String> {
+    let encode = |buf: &mut _| request.encode(buf, PRODUCE_MAX_VERSION);
+    if acks == ACKS_NONE {
+        return client
+            .send_without_response(PRODUCE_API_KEY, PRODUCE_MAX_VERSION, encode)
+            .await;
+    }
+    let mut response = client
+        .send_request(PRODUCE_API_KEY, PRODUCE_MAX_VERSION, encode)
+        .await?;
+    let response = ProduceResponse::decode(&mut response, PRODUCE_MAX_VERSION)?;
+    let partition = response
+        .responses
+        .iter()
+        .flat_map(|topic| &topic.partitions)
+        .find(|response| response.index == partition)
+        .ok_or("The response does not cover the partition")?;
+    check(partition.error_code, "produce")
+}
+
+async fn consume(cli: &Cli, args: &ConsumeArgs) -> Result<(), String> {
+    let topic = args.common.topic.clone();
+    let total = args.common.num_records;
+    let (fetch_size, max_wait_ms) = (args.fetch_size, args.fetch_max_wait_ms);
+    let timeout = Duration::from_millis(args.timeout_ms);
+
+    // Partitions hold different amounts, so every task reads until the records of all of
+    // them add up to --num-records rather than stopping at its own share.
+    run_partitions(cli, &args.common, |mut client, partition, _, progress| {
+        let topic = topic.clone();
+        async move {
+            let topic_partition = TopicPartition::new(topic.clone(), partition);
+            let (_, offset) = list_offsets(
+                &mut client,
+                ISOLATION_READ_UNCOMMITTED,
+                &[topic_partition],
+                EARLIEST_TIMESTAMP,
+            )
+            .await?
+            .into_iter()
+            .next()
+            .ok_or("The response does not cover the partition")?;
+            let mut position = offset.map_err(|error_code| {
+                format!("Failed to list offsets: error code {}", error_code)
+            })?;
+
+            let mut latencies = Vec::new();
+            let mut last_record = Instant::now();
+            while progress.records.load(Ordering::Relaxed) < total {
+                if last_record.elapsed() >= timeout {
+                    return Err(format!(
+                        "No records for {} ms at offset {}",
+                        timeout.as_millis(),
+                        position
+                    ));
+                }
+                let request = FetchRequest {
+                    replica_id: -1,
+                    max_wait_ms,
+                    min_bytes: 1,
+                    max_bytes: FETCH_MAX_BYTES,
+                    isolation_level: ISOLATION_READ_UNCOMMITTED,
+                    session_id: 0,
+                    session_epoch: -1,
+                    topics: vec![FetchTopic {
+                        topic: topic.clone(),
+                        partitions: vec![FetchPartition {
+                            partition,
+                            current_leader_epoch: -1,
+                            fetch_offset: position,
+                            log_start_offset: -1,
+                            partition_max_bytes: fetch_size,
+                        }],
+                    }],
+                    forgotten_topics: vec![],
+                    rack_id: String::new(),
+                };
+
+                let sent_at = Instant::now();
+                let response = client.fetch(&request).await?;
+                latencies.push(sent_at.elapsed());
+                check(response.error_code, "fetch")?;
+                let data = response
+                    .responses
+                    .into_iter()
+                    .flat_map(|topic| topic.partitions)
+                    .find(|data| data.partition_index == partition)
+                    .ok_or("The response does not cover the partition")?;
+                check(data.error_code, "fetch")?;
+
+                let (mut records, mut bytes) = (0, 0);
+                for batch in &data.records.batches()? {
+                    if batch.last_offset() < position {
+                        continue;
+                    }
+                    for record in &batch.records {
+                        if batch.base_offset + record.offset_delta.0 as i64 >= position {
+                            records += 1;
+                            bytes += record.key.as_ref().map_or(0, Vec::len)
+                                + record.value.as_ref().map_or(0, Vec::len);
+                        }
+                    }
+                    position = batch.last_offset() + 1;
+                }
+                if records > 0 {
+                    last_record = Instant::now();
+                    progress.add(records, bytes as u64);
+                }
+            }
+            Ok(latencies)
+        }
+    })
+    .await
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let result = match &cli.command {
+        Command::Produce(args) => produce(&cli, args).await,
+        Command::Consume(args) => consume(&cli, args).await,
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,511 @@
+use clap::{Args, Parser, Subcommand};
+use rand::RngExt;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use forge::adapters::driven::broker_client::BrokerClient;
+use forge::core::error::ErrorCode;
+use forge::protocol::alter_partition_reassignments::{
+    ALTER_PARTITION_REASSIGNMENTS_API_KEY, ALTER_PARTITION_REASSIGNMENTS_MAX_VERSION,
+    AlterPartitionReassignmentsRequest, AlterPartitionReassignmentsResponse, ReassignablePartition,
+    ReassignableTopic,
+};
+use forge::protocol::list_partition_reassignments::{
+    LIST_PARTITION_REASSIGNMENTS_API_KEY, LIST_PARTITION_REASSIGNMENTS_MAX_VERSION,
+    ListPartitionReassignmentsRequest, ListPartitionReassignmentsResponse,
+    ListPartitionReassignmentsTopic,
+};
+use forge::protocol::metadata::{
+    METADATA_API_KEY, METADATA_MAX_VERSION, MetadataRequest, MetadataResponse, MetadataTopic,
+};
+use forge::tools::{ClientArgs, check};
+
+const CLIENT_ID: &str = "forge-reassign-partitions";
+const PLAN_VERSION: i32 = 1;
+const REQUEST_TIMEOUT_MS: i32 = 30_000;
+
+/// Moves partition replicas between brokers: generates a plan spreading topics over a list
+/// of brokers, executes it and follows it until every partition has moved.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    #[command(flatten)]
+    client: ClientArgs,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Prints the current assignment of the topics and a plan spreading their replicas
+    /// round-robin over the brokers; nothing is moved.
+    Generate(GenerateArgs),
+    /// Starts the reassignments of a plan and waits until they complete.
+    Execute(ExecuteArgs),
+    /// Reports whether each partition of a plan has reached its target replicas.
+    Verify(PlanArgs),
+    /// Cancels the reassignments of a plan still in progress, keeping the original replicas.
+    Cancel(PlanArgs),
+}
+
+#[derive(Debug, Args)]
+struct GenerateArgs {
+    /// Topics to move, separated by commas.
+    #[arg(long, value_delimiter = ',', required = true)]
+    topics: Vec<String>,
+
+    /// Ids of the brokers to place replicas on, separated by commas.
+    #[arg(long, value_delimiter = ',', required = true)]
+    broker_list: Vec<i32>,
+}
+
+#[derive(Debug, Args)]
+struct PlanArgs {
+    /// A plan as printed by generate, or one written by hand in the same format.
+    #[arg(long)]
+    reassignment_json_file: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct ExecuteArgs {
+    #[command(flatten)]
+    plan: PlanArgs,
+
+    /// Returns once the reassignments have started; verify then tells when they are done.
+    #[arg(long)]
+    no_wait: bool,
+
+    /// How often to check progress while waiting.
+    #[arg(long, default_value_t = 1000)]
+    interval_ms: u64,
+}
+
+/// Kafka's reassignment JSON: `{"version":1,"partitions":[{"topic":..,"partition":..,
+/// "replicas":[..]}]}`. Fields this tool does not use, such as `log_dirs`, are ignored.
+#[derive(Debug, Serialize, Deserialize)]
+struct Plan {
+    version: i32,
+    partitions: Vec<PlannedPartition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PlannedPartition {
+    topic: String,
+    partition: i32,
+    replicas: Vec<i32>,
+}
+
+impl PlannedPartition {
+    fn name(&self) -> String {
+        format!("{}-{}", self.topic, self.partition)
+    }
+}
+
+impl Plan {
+    fn new(mut partitions: Vec<PlannedPartition>) -> Self {
+        partitions.sort_by(|a, b| (&a.topic, a.partition).cmp(&(&b.topic, b.partition)));
+        Self {
+            version: PLAN_VERSION,
+            partitions,
+        }
+    }
+
+    async fn read(path: &PathBuf) -> Result<Self, String> {
+        let json = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let plan: Plan = serde_json::from_str(&json)
+            .map_err(|e| format!("Invalid reassignment plan {}: {}", path.display(), e))?;
+        if plan.version != PLAN_VERSION {
+            return Err(format!("Unsupported plan version {}", plan.version));
+        }
+        if plan.partitions.is_empty() {
+            return Err(format!("{} lists no partitions", path.display()));
+        }
+        Ok(plan)
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a plan always serializes")
+    }
+
+    /// The plan's partitions grouped by topic, in the order topics first appear.
+    fn by_topic(&self) -> Vec<(String, Vec<&PlannedPartition>)> {
+        let mut topics: Vec<(String, Vec<&PlannedPartition>)> = Vec::new();
+        for partition in &self.partitions {
+            match topics
+                .iter_mut()
+                .find(|(topic, _)| *topic == partition.topic)
+            {
+                Some((_, partitions)) => partitions.push(partition),
+                None => topics.push((partition.topic.clone(), vec![partition])),
+            }
+        }
+        topics
+    }
+}
+
+/// Where a planned partition stands.
+enum Status {
+    InProgress {
+        adding_replicas: Vec<i32>,
+        removing_replicas: Vec<i32>,
+    },
+    Completed,
+    /// No reassignment is running, yet the replicas differ from the plan's, e.g. after a
+    /// cancel.
+    Differs(Vec<i32>),
+}
+
+struct ReassignPartitions {
+    client: BrokerClient,
+}
+
+impl ReassignPartitions {
+    async fn run(&mut self, command: &Command) -> Result<(), String> {
+        match command {
+            Command::Generate(args) => self.generate(args).await,
+            Command::Execute(args) => self.execute(args).await,
+            Command::Verify(args) => {
+                let plan = Plan::read(&args.reassignment_json_file).await?;
+                let in_progress = self.print_progress(&plan).await?;
+                if in_progress > 0 {
+                    println!("{} reassignments are still in progress", in_progress);
+                }
+                Ok(())
+            }
+            Command::Cancel(args) => self.cancel(args).await,
+        }
+    }
+
+    async fn generate(&mut self, args: &GenerateArgs) -> Result<(), String> {
+        let mut brokers = args.broker_list.clone();
+        brokers.sort_unstable();
+        brokers.dedup();
+        if brokers.len() != args.broker_list.len() {
+            return Err("--broker-list names a broker more than once".to_string());
+        }
+
+        let topics = self.describe_topics(&args.topics).await?;
+        let current = Plan::new(current_assignment(&topics));
+        let mut proposed = Vec::new();
+        for topic in &topics {
+            let replication_factor = topic
+                .partitions
+                .iter()
+                .map(|partition| partition.replica_nodes.len())
+                .max()
+                .unwrap_or(0);
+            if replication_factor > brokers.len() {
+                return Err(format!(
+                    "Topic {} has replication factor {}, more than the {} brokers listed",
+                    topic.name,
+                    replication_factor,
+                    brokers.len()
+                ));
+            }
+            // Starting each topic at a random broker spreads leadership across topics
+            let start_index = rand::rng().random_range(0..brokers.len());
+            for partition in &topic.partitions {
+                let replicas = (0..replication_factor)
+                    .map(|i| {
+                        brokers
+                            [(start_index + partition.partition_index as usize + i) % brokers.len()]
+                    })
+                    .collect();
+                proposed.push(PlannedPartition {
+                    topic: topic.name.clone(),
+                    partition: partition.partition_index,
+                    replicas,
+                });
+            }
+        }
+
+        println!("Current partition replica assignment");
+        println!("{}", current.to_json());
+        println!();
+        println!("Proposed partition reassignment configuration");
+        println!("{}", Plan::new(proposed).to_json());
+        Ok(())
+    }
+
+    async fn execute(&mut self, args: &ExecuteArgs) -> Result<(), String> {
+        let plan = Plan::read(&args.plan.reassignment_json_file).await?;
+        let topics: Vec<String> = plan
+            .by_topic()
+            .into_iter()
+            .map(|(topic, _)| topic)
+            .collect();
+        let current = Plan::new(current_assignment(&self.describe_topics(&topics).await?));
+        println!("Current partition replica assignment");
+        println!("{}", current.to_json());
+        println!();
+        println!("Save this to use as the --reassignment-json-file option during rollback");
+
+        let request = AlterPartitionReassignmentsRequest {
+            timeout_ms: REQUEST_TIMEOUT_MS,
+            topics: plan
+                .by_topic()
+                .into_iter()
+                .map(|(name, partitions)| ReassignableTopic {
+                    name,
+                    partitions: partitions
+                        .into_iter()
+                        .map(|partition| ReassignablePartition {
+                            partition_index: partition.partition,
+                            replicas: Some(partition.replicas.clone()),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        };
+        let failures = self.alter_reassignments(&request).await?;
+        if !failures.is_empty() {
+            return Err(format!(
+                "Failed to start the reassignment of {}",
+                failures.join(", ")
+            ));
+        }
+        println!(
+            "Successfully started partition reassignments for {}",
+            plan.partitions
+                .iter()
+                .map(PlannedPartition::name)
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        if args.no_wait {
+            return Ok(());
+        }
+
+        loop {
+            println!();
+            if self.print_progress(&plan).await? == 0 {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(args.interval_ms)).await;
+        }
+    }
+
+    async fn cancel(&mut self, args: &PlanArgs) -> Result<(), String> {
+        let plan = Plan::read(&args.reassignment_json_file).await?;
+        let request = AlterPartitionReassignmentsRequest {
+            timeout_ms: REQUEST_TIMEOUT_MS,
+            topics: plan
+                .by_topic()
+                .into_iter()
+                .map(|(name, partitions)| ReassignableTopic {
+                    name,
+                    partitions: partitions
+                        .into_iter()
+                        .map(|partition| ReassignablePartition {
+                            partition_index: partition.partition,
+                            replicas: None,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        };
+        let failures = self.alter_reassignments(&request).await?;
+        if !failures.is_empty() {
+            return Err(format!("Failed to cancel {}", failures.join(", ")));
+        }
+        println!(
+            "Successfully cancelled partition reassignments for {}",
+            plan.partitions
+                .iter()
+                .map(PlannedPartition::name)
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        Ok(())
+    }
+
+    /// Sends the request and returns a description of each partition it failed for.
+    async fn alter_reassignments(
+        &mut self,
+        request: &AlterPartitionReassignmentsRequest,
+    ) -> Result<Vec<String>, String> {
+        let mut response = self
+            .client
+            .send_flexible_request(
+                ALTER_PARTITION_REASSIGNMENTS_API_KEY,
+                ALTER_PARTITION_REASSIGNMENTS_MAX_VERSION,
+                |buf| request.encode(buf, ALTER_PARTITION_REASSIGNMENTS_MAX_VERSION),
+            )
+            .await?;
+        let response = AlterPartitionReassignmentsResponse::decode(
+            &mut response,
+            ALTER_PARTITION_REASSIGNMENTS_MAX_VERSION,
+        )?;
+        check(response.error_code, "alter partition reassignments")?;
+
+        let mut failures = Vec::new();
+        for topic in response.responses {
+            for partition in topic.partitions {
+                if partition.error_code != ErrorCode::None.code() {
+                    failures.push(format!(
+                        "{}-{} (error code {}{})",
+                        topic.name,
+                        partition.partition_index,
+                        partition.error_code,
+                        partition
+                            .error_message
+                            .map(|message| format!(": {}", message))
+                            .unwrap_or_default()
+                    ));
+                }
+            }
+        }
+        Ok(failures)
+    }
+
+    /// Prints where each partition of the plan stands and returns how many are still moving.
+    async fn print_progress(&mut self, plan: &Plan) -> Result<usize, String> {
+        let statuses = self.statuses(plan).await?;
+        println!("Status of partition reassignment:");
+        let mut in_progress = 0;
+        for (partition, status) in &statuses {
+            match status {
+                Status::InProgress {
+                    adding_replicas,
+                    removing_replicas,
+                } => {
+                    in_progress += 1;
+                    println!(
+                        "Reassignment of partition {} is still in progress: adding {:?}, removing {:?}.",
+                        partition.name(),
+                        adding_replicas,
+                        removing_replicas
+                    );
+                }
+                Status::Completed => {
+                    println!(
+                        "Reassignment of partition {} is completed.",
+                        partition.name()
+                    )
+                }
+                Status::Differs(replicas) => println!(
+                    "There is no active reassignment of partition {}, but its replicas are {:?} rather than {:?}.",
+                    partition.name(),
+                    replicas,
+                    partition.replicas
+                ),
+            }
+        }
+        Ok(in_progress)
+    }
+
+    async fn statuses(&mut self, plan: &Plan) -> Result<Vec<(PlannedPartition, Status)>, String> {
+        let request = ListPartitionReassignmentsRequest {
+            timeout_ms: REQUEST_TIMEOUT_MS,
+            topics: Some(
+                plan.by_topic()
+                    .into_iter()
+                    .map(|(name, partitions)| ListPartitionReassignmentsTopic {
+                        name,
+                        partition_indexes: partitions
+                            .into_iter()
+                            .map(|partition| partition.partition)
+                            .collect(),
+                    })
+                    .collect(),
+            ),
+        };
+        let mut response = self
+            .client
+            .send_flexible_request(
+                LIST_PARTITION_REASSIGNMENTS_API_KEY,
+                LIST_PARTITION_REASSIGNMENTS_MAX_VERSION,
+                |buf| request.encode(buf, LIST_PARTITION_REASSIGNMENTS_MAX_VERSION),
+            )
+            .await?;
+        let ongoing = ListPartitionReassignmentsResponse::decode(
+            &mut response,
+            LIST_PARTITION_REASSIGNMENTS_MAX_VERSION,
+        )?;
+        check(ongoing.error_code, "list partition reassignments")?;
+
+        let topics: Vec<String> = plan
+            .by_topic()
+            .into_iter()
+            .map(|(topic, _)| topic)
+            .collect();
+        let current = current_assignment(&self.describe_topics(&topics).await?);
+
+        let mut statuses = Vec::with_capacity(plan.partitions.len());
+        for planned in &plan.partitions {
+            let reassignment = ongoing
+                .topics
+                .iter()
+                .filter(|topic| topic.name == planned.topic)
+                .flat_map(|topic| &topic.partitions)
+                .find(|partition| partition.partition_index == planned.partition);
+            let replicas = current
+                .iter()
+                .find(|partition| {
+                    partition.topic == planned.topic && partition.partition == planned.partition
+                })
+                .map(|partition| partition.replicas.clone())
+                .ok_or_else(|| format!("Partition {} does not exist", planned.name()))?;
+            let state = match reassignment {
+                Some(reassignment) => Status::InProgress {
+                    adding_replicas: reassignment.adding_replicas.clone(),
+                    removing_replicas: reassignment.removing_replicas.clone(),
+                },
+                None if replicas == planned.replicas => Status::Completed,
+                None => Status::Differs(replicas),
+            };
+            statuses.push((planned.clone(), state));
+        }
+        Ok(statuses)
+    }
+
+    /// Fetches the partitions of every topic, failing if one cannot be described.
+    async fn describe_topics(&mut self, topics: &[String]) -> Result<Vec<MetadataTopic>, String> {
+        let request = MetadataRequest {
+            topics: Some(topics.to_vec()),
+            allow_auto_topic_creation: false,
+            include_cluster_authorized_operations: false,
+            include_topic_authorized_operations: false,
+        };
+        let mut response = self
+            .client
+            .send_request(METADATA_API_KEY, METADATA_MAX_VERSION, |buf| {
+                request.encode(buf, METADATA_MAX_VERSION)
+            })
+            .await?;
+        let response = MetadataResponse::decode(&mut response, METADATA_MAX_VERSION)?;
+        for topic in &response.topics {
+            check(topic.error_code, &format!("describe topic {}", topic.name))?;
+        }
+        Ok(response.topics)
+    }
+}
+
+fn current_assignment(topics: &[MetadataTopic]) -> Vec<PlannedPartition> {
+    topics
+        .iter()
+        .flat_map(|topic| {
+            topic.partitions.iter().map(|partition| PlannedPartition {
+                topic: topic.name.clone(),
+                partition: partition.partition_index,
+                replicas: partition.replica_nodes.clone(),
+            })
+        })
+        .collect()
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let mut tool = ReassignPartitions {
+        client: cli.client.client(CLIENT_ID),
+    };
+    if let Err(e) = tool.run(&cli.command).await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
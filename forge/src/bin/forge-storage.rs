@@ -0,0 +1,228 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+
+use forge::adapters::driven::meta_properties::MetaProperties;
+use forge::adapters::driven::storage::verifier::verify_partition;
+use forge::config::BrokerConfig;
+use forge::core::domain::topic_partition::TopicPartition;
+use forge::tools::print_table;
+
+/// Prepares and inspects a broker's log directory offline.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Prints a new random cluster id.
+    RandomUuid,
+    /// Writes meta.properties, tying the log directory to a cluster and a node.
+    Format(FormatArgs),
+    /// Prints meta.properties and the partitions stored in the log directory.
+    Info(ConfigArgs),
+}
+
+/// Resolves the log directory the way the broker does.
+#[derive(Debug, Args)]
+struct ConfigArgs {
+    /// Kafka-style server.properties file.
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Sets node.id.
+    #[arg(long)]
+    node_id: Option<i32>,
+
+    /// Sets log.dirs.
+    #[arg(long)]
+    log_dirs: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct FormatArgs {
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    /// The cluster id, as printed by random-uuid.
+    #[arg(long)]
+    cluster_id: String,
+
+    /// Leaves an already formatted directory alone instead of failing.
+    #[arg(long)]
+    ignore_formatted: bool,
+}
+
+impl ConfigArgs {
+    async fn load(&self) -> Result<BrokerConfig, String> {
+        let flags = [
+            ("node.id", self.node_id.map(|id| id.to_string())),
+            ("log.dirs", self.log_dirs.clone()),
+        ];
+        let overrides: Vec<(String, String)> = flags
+            .into_iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value?)))
+            .collect();
+        BrokerConfig::load_layered(self.config.as_deref(), std::env::vars(), &overrides).await
+    }
+}
+
+async fn format(args: &FormatArgs) -> Result<(), String> {
+    MetaProperties::validate_cluster_id(&args.cluster_id)?;
+    let config = args.config.load().await?;
+    let log_dir = &config.log_dir;
+
+    if let Some(existing) = MetaProperties::read(log_dir).await? {
+        if !args.ignore_formatted {
+            return Err(format!(
+                "Log directory {} is already formatted for cluster {}. Use --ignore-formatted to leave it alone.",
+                log_dir.display(),
+                existing.cluster_id
+            ));
+        }
+        println!(
+            "Log directory {} is already formatted, skipping it.",
+            log_dir.display()
+        );
+        return Ok(());
+    }
+
+    MetaProperties::new(args.cluster_id.clone(), config.node_id)
+        .write(log_dir)
+        .await?;
+    println!(
+        "Formatted {} for cluster {} and node {}.",
+        log_dir.display(),
+        args.cluster_id,
+        config.node_id
+    );
+    Ok(())
+}
+
+async fn info(args: &ConfigArgs) -> Result<(), String> {
+    let config = args.load().await?;
+    let log_dir = &config.log_dir;
+    println!("Found log directory:\n  {}\n", log_dir.display());
+
+    match MetaProperties::read(log_dir).await? {
+        Some(meta) => {
+            println!(
+                "Found metadata: {{cluster.id={}, node.id={}, version={}}}\n",
+                meta.cluster_id, meta.node_id, meta.version
+            );
+            if meta.node_id != config.node_id {
+                println!(
+                    "Warning: the directory belongs to node {}, but node.id is {}.\n",
+                    meta.node_id, config.node_id
+                );
+            }
+        }
+        None => println!("The directory is not formatted: it has no meta.properties.\n"),
+    }
+
+    let mut entries = tokio::fs::read_dir(log_dir)
+        .await
+        .map_err(|e| format!("Failed to list {}: {}", log_dir.display(), e))?;
+    let mut partitions = Vec::new();
+    while let Some(entry) = entries.next_entry().await.map_err(|e| e.to_string())? {
+        let path = entry.path();
+        if path.is_dir()
+            && let Some(partition) = TopicPartition::from_dir(&path)
+        {
+            partitions.push((partition, path));
+        }
+    }
+    if partitions.is_empty() {
+        println!("No partitions found.");
+        return Ok(());
+    }
+    partitions.sort();
+
+    let mut rows = Vec::new();
+    let mut total_bytes = 0;
+    for (partition, path) in &partitions {
+        // The verifier reads every batch, so the offsets come from the data, not the names
+        let report = verify_partition(path, false, |_| {}).await?;
+        let bytes: u64 = report
+            .segments
+            .iter()
+            .map(|segment| segment.log_bytes)
+            .sum();
+        let records: usize = report.segments.iter().map(|segment| segment.records).sum();
+        let first = report
+            .segments
+            .iter()
+            .find_map(|segment| segment.first_offset);
+        let last = report
+            .segments
+            .iter()
+            .rev()
+            .find_map(|segment| segment.last_offset);
+        let offsets = match (first, last) {
+            (Some(first), Some(last)) => format!("{}-{}", first, last),
+            _ => "-".to_string(),
+        };
+        total_bytes += bytes;
+        rows.push(vec![
+            partition.topic.clone(),
+            partition.partition.to_string(),
+            report.segments.len().to_string(),
+            records.to_string(),
+            bytes.to_string(),
+            offsets,
+            if report.has_errors() {
+                "CORRUPT".to_string()
+            } else {
+                "OK".to_string()
+            },
+        ]);
+    }
+
+    let topics = {
+        let mut topics: Vec<&str> = partitions
+            .iter()
+            .map(|(partition, _)| partition.topic.as_str())
+            .collect();
+        topics.dedup();
+        topics.len()
+    };
+    println!(
+        "Found {} topic(s) with {} partition(s), {} bytes of log in total:\n",
+        topics,
+        partitions.len(),
+        total_bytes
+    );
+    print_table(
+        &[
+            "TOPIC",
+            "PARTITION",
+            "SEGMENTS",
+            "RECORDS",
+            "SIZE",
+            "OFFSETS",
+            "STATUS",
+        ],
+        &rows,
+    );
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let result = match &cli.command {
+        Command::RandomUuid => {
+            println!("{}", MetaProperties::random_cluster_id());
+            Ok(())
+        }
+        Command::Format(args) => format(args).await,
+        Command::Info(args) => info(args).await,
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
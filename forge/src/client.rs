@@ -0,0 +1,6 @@
+pub mod accumulator;
+pub mod cluster;
+pub mod consumer;
+pub mod partitioner;
+pub mod producer;
+pub mod transaction_manager;
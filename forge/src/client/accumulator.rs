@@ -0,0 +1,423 @@
+use std::collections::VecDeque;
+use tokio::sync::oneshot;
+use tokio::time::{Duration, Instant};
+
+use crate::client::producer::RecordMetadata;
+use crate::core::domain::compression::CompressionType;
+use crate::core::domain::record::{Header, Record};
+use crate::core::domain::record_batch::RecordBatch;
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::protocol::types::Varlong;
+use crate::shared::collections::FlatMap;
+
+/// Bytes a record takes in a batch besides its key, value and headers: length, attributes,
+/// timestamp and offset deltas and the counts, at their usual varint sizes.
+const RECORD_OVERHEAD: usize = 16;
+
+/// Records bound for one partition, sent together in one RecordBatch.
+pub struct ProducerBatch {
+    pub topic_partition: TopicPartition,
+    records: Vec<Record>,
+    /// Create time of each record, and where to report its outcome.
+    pending: Vec<(i64, oneshot::Sender<Result<RecordMetadata, String>>)>,
+    size: usize,
+    created: Instant,
+    /// How often the batch has been sent again after a retriable error.
+    pub attempts: u32,
+    /// Set while a retried batch waits out its backoff.
+    retry_at: Option<Instant>,
+    /// -1 until an idempotent producer stamps the batch; kept across retries so the broker
+    /// can tell a retry from new records.
+    producer_id: i64,
+    producer_epoch: i16,
+    base_sequence: i32,
+}
+
+impl ProducerBatch {
+    fn new(topic_partition: TopicPartition) -> Self {
+        Self {
+            topic_partition,
+            records: Vec::new(),
+            pending: Vec::new(),
+            size: 0,
+            created: Instant::now(),
+            attempts: 0,
+            retry_at: None,
+            producer_id: -1,
+            producer_epoch: -1,
+            base_sequence: -1,
+        }
+    }
+
+    pub fn record_count(&self) -> usize {
+        self.records.len()
+    }
+
+    /// The records as a magic v2 batch, with offset and timestamp deltas from the first.
+    pub fn build(&self, compression: CompressionType) -> RecordBatch {
+        let base_timestamp = self.pending.first().map_or(0, |(timestamp, _)| *timestamp);
+        let records = self
+            .records
+            .iter()
+            .zip(&self.pending)
+            .map(|(record, (timestamp, _))| Record {
+                timestamp_delta: Varlong(timestamp - base_timestamp),
+                ..record.clone()
+            })
+            .collect();
+        let mut batch = RecordBatch::new(base_timestamp, records);
+        batch.max_timestamp = self
+            .pending
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .max()
+            .unwrap_or(base_timestamp);
+        batch.attributes = compression.id();
+        batch.producer_id = self.producer_id;
+        batch.producer_epoch = self.producer_epoch;
+        batch.base_sequence = self.base_sequence;
+        batch
+    }
+
+    /// Reports each record at `base_offset` plus its position in the batch; `base_offset` is
+    /// -1 when the broker did not say.
+    pub fn complete(self, base_offset: i64, log_append_time_ms: i64) {
+        for (index, (timestamp, result)) in self.pending.into_iter().enumerate() {
+            let _ = result.send(Ok(RecordMetadata {
+                topic: self.topic_partition.topic.clone(),
+                partition: self.topic_partition.partition,
+                offset: if base_offset >= 0 {
+                    base_offset + index as i64
+                } else {
+                    -1
+                },
+                timestamp: if log_append_time_ms >= 0 {
+                    log_append_time_ms
+                } else {
+                    timestamp
+                },
+            }));
+        }
+    }
+
+    pub fn fail(self, error: &str) {
+        for (_, result) in self.pending {
+            let _ = result.send(Err(error.to_string()));
+        }
+    }
+}
+
+/// The sequence after `count` records starting at `sequence`; sequences wrap around to zero.
+fn next_sequence(sequence: i32, count: usize) -> i32 {
+    ((sequence as i64 + count as i64) % (i32::MAX as i64 + 1)) as i32
+}
+
+/// Collects records into a batch per partition until the batch reaches `batch_size` bytes or
+/// has waited `linger`. Each partition has at most `max_in_flight` batches sent and not yet
+/// completed; with one, its records are written in the order they were sent, even when
+/// retried.
+pub struct RecordAccumulator {
+    batch_size: usize,
+    linger: Duration,
+    max_in_flight: usize,
+    batches: FlatMap<TopicPartition, VecDeque<ProducerBatch>>,
+    /// Batches drained and not yet completed or requeued, by partition.
+    in_flight: FlatMap<TopicPartition, usize>,
+    /// Id and epoch stamped on drained batches when the producer is idempotent.
+    producer: Option<(i64, i16)>,
+    /// The sequence of the next record stamped, by partition.
+    next_sequences: FlatMap<TopicPartition, i32>,
+    /// Batches appended to and not yet completed, in flight or not.
+    incomplete: usize,
+    /// While positive every batch is ready, however young.
+    flushes: usize,
+}
+
+impl RecordAccumulator {
+    pub fn new(batch_size: usize, linger: Duration, max_in_flight: usize) -> Self {
+        Self {
+            batch_size,
+            linger,
+            max_in_flight,
+            batches: FlatMap::new(),
+            in_flight: FlatMap::new(),
+            producer: None,
+            next_sequences: FlatMap::new(),
+            incomplete: 0,
+            flushes: 0,
+        }
+    }
+
+    /// Adds a record to the partition's last batch, starting a new one when it would grow
+    /// past `batch_size`. A record larger than that gets a batch of its own.
+    pub fn append(
+        &mut self,
+        topic_partition: &TopicPartition,
+        timestamp: i64,
+        key: Option<Vec<u8>>,
+        value: Option<Vec<u8>>,
+        headers: Vec<Header>,
+    ) -> oneshot::Receiver<Result<RecordMetadata, String>> {
+        let size = RECORD_OVERHEAD
+            + key.as_ref().map_or(0, Vec::len)
+            + value.as_ref().map_or(0, Vec::len)
+            + headers
+                .iter()
+                .map(|header| header.key.len() + header.value.as_ref().map_or(0, Vec::len))
+                .sum::<usize>();
+
+        if self.batches.get(topic_partition).is_none() {
+            self.batches
+                .insert(topic_partition.clone(), VecDeque::new());
+        }
+        let queue = self
+            .batches
+            .get_mut(topic_partition)
+            .expect("Inserted above");
+        let has_room = queue
+            .back()
+            .is_some_and(|batch| batch.size + size <= self.batch_size);
+        if !has_room {
+            queue.push_back(ProducerBatch::new(topic_partition.clone()));
+            self.incomplete += 1;
+        }
+        let batch = queue.back_mut().expect("Pushed above");
+
+        let (tx, rx) = oneshot::channel();
+        let mut record = Record::new(batch.records.len() as i32, key, value);
+        record.headers = headers;
+        batch.records.push(record);
+        batch.pending.push((timestamp, tx));
+        batch.size += size;
+        rx
+    }
+
+    pub fn producer(&self) -> Option<(i64, i16)> {
+        self.producer
+    }
+
+    /// Stamps batches drained from now on with `producer`, numbering every partition's
+    /// records from zero again.
+    pub fn set_producer(&mut self, producer: Option<(i64, i16)>) {
+        self.producer = producer;
+        self.next_sequences = FlatMap::new();
+    }
+
+    pub fn begin_flush(&mut self) {
+        self.flushes += 1;
+    }
+
+    pub fn end_flush(&mut self) {
+        self.flushes -= 1;
+    }
+
+    /// Whether every batch appended so far has completed.
+    pub fn is_idle(&self) -> bool {
+        self.incomplete == 0
+    }
+
+    fn in_flight(&self, topic_partition: &TopicPartition) -> usize {
+        self.in_flight.get(topic_partition).copied().unwrap_or(0)
+    }
+
+    /// When the batch may be sent: once its backoff ends if it is being retried, otherwise
+    /// once it stops lingering.
+    fn ready_time(&self, batch: &ProducerBatch) -> Instant {
+        batch.retry_at.unwrap_or(batch.created + self.linger)
+    }
+
+    fn is_ready(&self, batch: &ProducerBatch, queued: usize, now: Instant) -> bool {
+        match batch.retry_at {
+            Some(retry_at) => now >= retry_at,
+            None => {
+                self.flushes > 0
+                    || queued > 1
+                    || batch.size >= self.batch_size
+                    || now >= self.ready_time(batch)
+            }
+        }
+    }
+
+    /// Takes the first batches of every partition below `max_in_flight`, as long as they are
+    /// full, have lingered long enough, have waited out their backoff or a flush is in
+    /// progress. They count as in flight until `complete` or `requeue`.
+    pub fn drain_ready(&mut self, now: Instant) -> Vec<ProducerBatch> {
+        let topic_partitions: Vec<TopicPartition> = self.batches.keys().cloned().collect();
+        let mut drained = Vec::new();
+        for topic_partition in topic_partitions {
+            let mut in_flight = self.in_flight(&topic_partition);
+            let mut queue = self.batches.remove(&topic_partition).expect("Listed above");
+            while in_flight < self.max_in_flight
+                && queue
+                    .front()
+                    .is_some_and(|batch| self.is_ready(batch, queue.len(), now))
+            {
+                let mut batch = queue.pop_front().expect("Checked above");
+                self.stamp(&mut batch);
+                drained.push(batch);
+                in_flight += 1;
+            }
+            self.in_flight.insert(topic_partition.clone(), in_flight);
+            if !queue.is_empty() {
+                self.batches.insert(topic_partition, queue);
+            }
+        }
+        drained
+    }
+
+    /// Gives the batch the producer's id and its partition's next sequences, unless it already
+    /// has them from an earlier attempt.
+    fn stamp(&mut self, batch: &mut ProducerBatch) {
+        let Some((producer_id, producer_epoch)) = self.producer else {
+            return;
+        };
+        if (batch.producer_id, batch.producer_epoch) == (producer_id, producer_epoch) {
+            return;
+        }
+        let sequence = self
+            .next_sequences
+            .get(&batch.topic_partition)
+            .copied()
+            .unwrap_or(0);
+        self.next_sequences.insert(
+            batch.topic_partition.clone(),
+            next_sequence(sequence, batch.record_count()),
+        );
+        batch.producer_id = producer_id;
+        batch.producer_epoch = producer_epoch;
+        batch.base_sequence = sequence;
+    }
+
+    /// When the first batch not yet ready can be sent, among partitions below `max_in_flight`.
+    pub fn next_ready_time(&self) -> Option<Instant> {
+        self.batches
+            .iter()
+            .filter(|(topic_partition, _)| self.in_flight(topic_partition) < self.max_in_flight)
+            .filter_map(|(_, queue)| queue.front())
+            .map(|batch| self.ready_time(batch))
+            .min()
+    }
+
+    /// Frees the batch's slot in its partition's window.
+    pub fn complete(&mut self, topic_partition: &TopicPartition) {
+        let in_flight = self.in_flight(topic_partition);
+        self.in_flight
+            .insert(topic_partition.clone(), in_flight.saturating_sub(1));
+        self.incomplete -= 1;
+    }
+
+    /// Puts a batch that failed with a retriable error back at the front of its partition,
+    /// to be sent again at `retry_at`.
+    pub fn requeue(&mut self, mut batch: ProducerBatch, retry_at: Instant) {
+        let topic_partition = batch.topic_partition.clone();
+        let in_flight = self.in_flight(&topic_partition);
+        self.in_flight
+            .insert(topic_partition.clone(), in_flight.saturating_sub(1));
+        batch.attempts += 1;
+        batch.retry_at = Some(retry_at);
+        match self.batches.get_mut(&topic_partition) {
+            Some(queue) => queue.push_front(batch),
+            None => {
+                self.batches
+                    .insert(topic_partition, VecDeque::from([batch]));
+            }
+        }
+    }
+
+    /// Fails every batch not in flight, e.g. when the producer cannot get an id.
+    pub fn abort(&mut self, error: &str) {
+        let topic_partitions: Vec<TopicPartition> = self.batches.keys().cloned().collect();
+        for topic_partition in topic_partitions {
+            if let Some(queue) = self.batches.remove(&topic_partition) {
+                for batch in queue {
+                    self.incomplete -= 1;
+                    batch.fail(error);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_batches_fill_then_drain_one_per_partition() {
+        let mut accumulator = RecordAccumulator::new(80, Duration::from_secs(60), 1);
+        let topic_partition = TopicPartition::new("orders", 0);
+        let mut results = Vec::new();
+        for (i, timestamp) in [1_000, 1_005, 1_002].into_iter().enumerate() {
+            results.push(accumulator.append(
+                &topic_partition,
+                timestamp,
+                None,
+                Some(vec![i as u8; 20]),
+                vec![],
+            ));
+        }
+
+        // Two records fit in 80 bytes, the third starts a second batch
+        let now = Instant::now();
+        let first = accumulator.drain_ready(now);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].record_count(), 2);
+        assert!(accumulator.drain_ready(now).is_empty());
+
+        let batch = first
+            .into_iter()
+            .next()
+            .unwrap()
+            .build(CompressionType::None);
+        assert_eq!(batch.base_timestamp, 1_000);
+        assert_eq!(batch.max_timestamp, 1_005);
+        assert_eq!(batch.last_offset_delta, 1);
+        assert_eq!(batch.records[1].offset_delta.0, 1);
+        assert_eq!(batch.records[1].timestamp_delta.0, 5);
+
+        // The second batch is young and alone, so it waits for its linger or a flush
+        accumulator.complete(&topic_partition);
+        assert!(accumulator.drain_ready(now).is_empty());
+        accumulator.begin_flush();
+        let second = accumulator.drain_ready(now);
+        assert_eq!(second.len(), 1);
+        second.into_iter().next().unwrap().complete(42, -1);
+        accumulator.complete(&topic_partition);
+        assert!(accumulator.is_idle());
+
+        let metadata = results.pop().unwrap().await.unwrap().unwrap();
+        assert_eq!((metadata.offset, metadata.timestamp), (42, 1_002));
+    }
+
+    #[tokio::test]
+    async fn test_retried_batch_keeps_its_sequence_and_goes_first() {
+        let mut accumulator = RecordAccumulator::new(1024, Duration::ZERO, 2);
+        accumulator.set_producer(Some((7, 0)));
+        let topic_partition = TopicPartition::new("orders", 0);
+        for i in 0..2 {
+            accumulator.append(&topic_partition, 1_000, None, Some(vec![i; 10]), vec![]);
+        }
+
+        let now = Instant::now();
+        let first = accumulator.drain_ready(now).pop().unwrap();
+        assert_eq!(first.build(CompressionType::None).base_sequence, 0);
+        accumulator.append(&topic_partition, 1_001, None, Some(vec![2; 10]), vec![]);
+        accumulator.requeue(first, now + Duration::from_millis(100));
+
+        // The retry waits out its backoff and holds back the batch queued behind it
+        assert!(accumulator.drain_ready(now).is_empty());
+        let drained = accumulator.drain_ready(now + Duration::from_millis(100));
+        let batches: Vec<RecordBatch> = drained
+            .iter()
+            .map(|batch| batch.build(CompressionType::None))
+            .collect();
+        assert_eq!(drained[0].attempts, 1);
+        assert_eq!(
+            batches
+                .iter()
+                .map(|batch| (batch.producer_id, batch.base_sequence))
+                .collect::<Vec<_>>(),
+            vec![(7, 0), (7, 2)]
+        );
+    }
+}
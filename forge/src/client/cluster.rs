@@ -0,0 +1,363 @@
+use bytes::{Bytes, BytesMut};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::adapters::driven::broker_client::BrokerClient;
+use crate::core::error::ErrorCode;
+use crate::protocol::find_coordinator::{
+    COORDINATOR_TYPE_GROUP, COORDINATOR_TYPE_TRANSACTION, FIND_COORDINATOR_API_KEY,
+    FIND_COORDINATOR_MAX_VERSION, FindCoordinatorRequest, FindCoordinatorResponse,
+};
+use crate::protocol::metadata::{
+    METADATA_API_KEY, METADATA_MAX_VERSION, MetadataRequest, MetadataResponse,
+};
+use crate::shared::collections::FlatMap;
+
+/// How a client logs in on each connection it opens.
+#[derive(Clone, PartialEq)]
+pub struct SaslCredentials {
+    /// PLAIN, SCRAM-SHA-256 or SCRAM-SHA-512.
+    pub mechanism: String,
+    pub username: String,
+    pub password: String,
+}
+
+// Client configs end up in application logs, so the password is left out
+impl std::fmt::Debug for SaslCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SaslCredentials")
+            .field("mechanism", &self.mechanism)
+            .field("username", &self.username)
+            .field("password", &"[hidden]")
+            .finish()
+    }
+}
+
+/// Settings every client shares: where it learns about the cluster and how it identifies
+/// itself to the brokers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientConfig {
+    /// `host:port` of the brokers cluster metadata is first asked from, tried in turn until
+    /// one answers. Once the cluster has described itself every broker it names is tried too.
+    pub bootstrap_servers: Vec<String>,
+    pub client_id: String,
+    pub sasl: Option<SaslCredentials>,
+    /// How long the partitions of a topic are trusted before they are asked again, even
+    /// without an error suggesting they changed.
+    pub metadata_max_age_ms: u64,
+    /// How long to wait before reaching out again to a broker that could not be reached.
+    pub reconnect_backoff_ms: u64,
+}
+
+impl ClientConfig {
+    /// `bootstrap_servers` is a comma-separated list of `host:port`, like the Java client's
+    /// `bootstrap.servers`.
+    pub fn new(bootstrap_servers: impl Into<String>, client_id: impl Into<String>) -> Self {
+        Self {
+            bootstrap_servers: bootstrap_servers
+                .into()
+                .split(',')
+                .map(str::trim)
+                .filter(|address| !address.is_empty())
+                .map(str::to_string)
+                .collect(),
+            client_id: client_id.into(),
+            sasl: None,
+            metadata_max_age_ms: 300_000,
+            reconnect_backoff_ms: 50,
+        }
+    }
+
+    fn connect(&self, address: &str) -> BrokerClient {
+        let client = BrokerClient::new(address, self.client_id.clone());
+        match &self.sasl {
+            Some(sasl) => client.with_sasl(
+                sasl.mechanism.clone(),
+                sasl.username.clone(),
+                sasl.password.clone(),
+            ),
+            None => client,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionInfo {
+    pub partition: i32,
+    /// `NO_LEADER` while the partition is offline.
+    pub leader: i32,
+    pub leader_epoch: i32,
+    pub replicas: Vec<i32>,
+    pub isr: Vec<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopicInfo {
+    pub name: String,
+    /// Ordered by partition index, so `partitions[p]` describes partition `p`.
+    pub partitions: Vec<PartitionInfo>,
+}
+
+/// A client's view of the cluster: the brokers and the topics it has asked about, and one
+/// connection per broker, opened on first use.
+pub struct Cluster {
+    config: ClientConfig,
+    /// The connection for requests any broker can answer, and the address it goes to.
+    any_broker: Mutex<(String, BrokerClient)>,
+    /// `host:port` by broker id.
+    brokers: Mutex<FlatMap<i32, String>>,
+    /// The partitions of each topic and when they were fetched.
+    topics: Mutex<FlatMap<String, (Arc<TopicInfo>, Instant)>>,
+    connections: Mutex<FlatMap<i32, Arc<Mutex<BrokerClient>>>>,
+}
+
+impl Cluster {
+    pub fn new(config: ClientConfig) -> Self {
+        let address = config
+            .bootstrap_servers
+            .first()
+            .cloned()
+            .unwrap_or_default();
+        Self {
+            any_broker: Mutex::new((address.clone(), config.connect(&address))),
+            config,
+            brokers: Mutex::new(FlatMap::new()),
+            topics: Mutex::new(FlatMap::new()),
+            connections: Mutex::new(FlatMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// Sends a request any broker can answer. When the broker last used cannot be reached,
+    /// the others the cluster named and then the bootstrap servers are tried in turn, so the
+    /// client keeps going as long as one of them is up.
+    pub async fn send_any(
+        &self,
+        api_key: i16,
+        api_version: i16,
+        encode_body: impl Fn(&mut BytesMut),
+    ) -> Result<Bytes, String> {
+        let mut any_broker = self.any_broker.lock().await;
+        let mut error = match any_broker
+            .1
+            .send_request(api_key, api_version, &encode_body)
+            .await
+        {
+            Ok(response) => return Ok(response),
+            Err(e) => e,
+        };
+
+        let mut addresses: Vec<String> = self.brokers.lock().await.values().cloned().collect();
+        for address in &self.config.bootstrap_servers {
+            if !addresses.contains(address) {
+                addresses.push(address.clone());
+            }
+        }
+        for address in addresses {
+            if address == any_broker.0 {
+                continue;
+            }
+            tracing::warn!(
+                "Broker {} could not be reached ({}), trying {}",
+                any_broker.0,
+                error,
+                address
+            );
+            *any_broker = (address.clone(), self.config.connect(&address));
+            error = match any_broker
+                .1
+                .send_request(api_key, api_version, &encode_body)
+                .await
+            {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+        }
+        Err(format!("No broker could be reached: {}", error))
+    }
+
+    /// The partitions of `topic`, asking the cluster the first time and again once they are
+    /// older than `metadata_max_age_ms`. A topic that does not exist yet is created when the
+    /// brokers allow it.
+    pub async fn topic(&self, topic: &str) -> Result<Arc<TopicInfo>, String> {
+        let max_age = Duration::from_millis(self.config.metadata_max_age_ms);
+        let cached = self.topics.lock().await.get(&topic.to_string()).cloned();
+        match cached {
+            Some((info, fetched_at)) if fetched_at.elapsed() < max_age => return Ok(info),
+            Some((info, _)) => {
+                // What was known is still better than nothing until the cluster answers
+                if let Err(e) = self.refresh(&[topic.to_string()]).await {
+                    tracing::warn!("Failed to refresh metadata for {}: {}", topic, e);
+                    return Ok(info);
+                }
+            }
+            None => self.refresh(&[topic.to_string()]).await?,
+        }
+        self.topics
+            .lock()
+            .await
+            .get(&topic.to_string())
+            .map(|(info, _)| info.clone())
+            .ok_or_else(|| format!("No metadata for topic {}", topic))
+    }
+
+    /// Fetches the current brokers and the partitions of `topics`, replacing what was known.
+    /// Fails if a topic cannot be described.
+    pub async fn refresh(&self, topics: &[String]) -> Result<(), String> {
+        let request = MetadataRequest {
+            topics: Some(topics.to_vec()),
+            allow_auto_topic_creation: true,
+            include_cluster_authorized_operations: false,
+            include_topic_authorized_operations: false,
+        };
+        let mut response = self
+            .send_any(METADATA_API_KEY, METADATA_MAX_VERSION, |buf| {
+                request.encode(buf, METADATA_MAX_VERSION)
+            })
+            .await?;
+        let response = MetadataResponse::decode(&mut response, METADATA_MAX_VERSION)?;
+
+        for broker in &response.brokers {
+            self.add_broker(broker.node_id, format!("{}:{}", broker.host, broker.port))
+                .await;
+        }
+
+        let mut known = self.topics.lock().await;
+        for topic in response.topics {
+            if topic.error_code != ErrorCode::None.code() {
+                return Err(format!(
+                    "Failed to describe topic {}: error code {}",
+                    topic.name, topic.error_code
+                ));
+            }
+            let mut partitions: Vec<PartitionInfo> = topic
+                .partitions
+                .into_iter()
+                .map(|partition| PartitionInfo {
+                    partition: partition.partition_index,
+                    leader: partition.leader_id,
+                    leader_epoch: partition.leader_epoch,
+                    replicas: partition.replica_nodes,
+                    isr: partition.isr_nodes,
+                })
+                .collect();
+            partitions.sort_by_key(|partition| partition.partition);
+            // A broker that has not caught up yet may describe a leader that already moved on
+            if let Some((previous, _)) = known.get(&topic.name) {
+                for partition in partitions.iter_mut() {
+                    if let Some(newer) = previous
+                        .partitions
+                        .get(partition.partition as usize)
+                        .filter(|previous| previous.leader_epoch > partition.leader_epoch)
+                    {
+                        *partition = newer.clone();
+                    }
+                }
+            }
+            known.insert(
+                topic.name.clone(),
+                (
+                    Arc::new(TopicInfo {
+                        name: topic.name,
+                        partitions,
+                    }),
+                    Instant::now(),
+                ),
+            );
+        }
+        Ok(())
+    }
+
+    /// Records where `broker_id` listens; a connection to an address it has left is dropped
+    /// so the next request reaches the new one.
+    async fn add_broker(&self, broker_id: i32, address: String) {
+        let previous = self.brokers.lock().await.insert(broker_id, address.clone());
+        if previous.is_some_and(|previous| previous != address) {
+            self.connections.lock().await.remove(&broker_id);
+        }
+    }
+
+    /// The id of the broker coordinating `group_id`, whose connection `connection` then
+    /// opens; the error code instead when the cluster cannot name one yet.
+    pub async fn group_coordinator(&self, group_id: &str) -> Result<Result<i32, i16>, String> {
+        self.coordinator(group_id, COORDINATOR_TYPE_GROUP).await
+    }
+
+    /// Like `group_coordinator`, for the broker coordinating `transactional_id`.
+    pub async fn transaction_coordinator(
+        &self,
+        transactional_id: &str,
+    ) -> Result<Result<i32, i16>, String> {
+        self.coordinator(transactional_id, COORDINATOR_TYPE_TRANSACTION)
+            .await
+    }
+
+    async fn coordinator(&self, key: &str, key_type: i8) -> Result<Result<i32, i16>, String> {
+        let request = FindCoordinatorRequest {
+            key: key.to_string(),
+            key_type,
+        };
+        let mut response = self
+            .send_any(
+                FIND_COORDINATOR_API_KEY,
+                FIND_COORDINATOR_MAX_VERSION,
+                |buf| request.encode(buf, FIND_COORDINATOR_MAX_VERSION),
+            )
+            .await?;
+        let response =
+            FindCoordinatorResponse::decode(&mut response, FIND_COORDINATOR_MAX_VERSION)?;
+        if response.error_code != ErrorCode::None.code() {
+            return Ok(Err(response.error_code));
+        }
+        self.add_broker(
+            response.node_id,
+            format!("{}:{}", response.host, response.port),
+        )
+        .await;
+        Ok(Ok(response.node_id))
+    }
+
+    /// The connection to `broker_id`, shared by every request sent to it.
+    pub async fn connection(&self, broker_id: i32) -> Result<Arc<Mutex<BrokerClient>>, String> {
+        let mut connections = self.connections.lock().await;
+        if let Some(connection) = connections.get(&broker_id) {
+            return Ok(connection.clone());
+        }
+        let address = self
+            .brokers
+            .lock()
+            .await
+            .get(&broker_id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown broker {}", broker_id))?;
+        let connection = Arc::new(Mutex::new(self.config.connect(&address)));
+        connections.insert(broker_id, connection.clone());
+        Ok(connection)
+    }
+}
+
+/// Errors meaning the coordinator has moved or is not ready yet; finding it again and
+/// retrying may succeed.
+pub fn is_coordinator_unavailable(error_code: i16) -> bool {
+    error_code == ErrorCode::CoordinatorLoadInProgress.code()
+        || error_code == ErrorCode::CoordinatorNotAvailable.code()
+        || error_code == ErrorCode::NotCoordinator.code()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bootstrap_servers_are_comma_separated() {
+        let config = ClientConfig::new(" broker-1:9092, broker-2:9092,,", "app");
+        assert_eq!(
+            config.bootstrap_servers,
+            vec!["broker-1:9092".to_string(), "broker-2:9092".to_string()]
+        );
+    }
+}
@@ -0,0 +1,1221 @@
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use futures::future::join_all;
+use futures::stream::{self, Stream};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI16, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::adapters::driven::broker_client::BrokerClient;
+use crate::application::assignor::partition_assignor::{
+    RebalanceProtocol, assignor_for, perform_assignment,
+};
+use crate::client::cluster::{ClientConfig, Cluster, is_coordinator_unavailable};
+use crate::core::domain::consumer_protocol::{Assignment, CONSUMER_PROTOCOL_TYPE, Subscription};
+use crate::core::domain::metadata_records::NO_LEADER;
+use crate::core::domain::record::Header;
+use crate::core::domain::record_batch::RecordBatch;
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::core::ports::driven::FetchClient;
+use crate::protocol::fetch::{
+    CONSUMER_REPLICA_ID, FetchPartition, FetchRequest, FetchResponse, FetchTopic,
+    ISOLATION_READ_UNCOMMITTED, PartitionData,
+};
+use crate::protocol::heartbeat::{
+    HEARTBEAT_API_KEY, HEARTBEAT_MAX_VERSION, HeartbeatRequest, HeartbeatResponse,
+};
+use crate::protocol::join_group::{
+    JOIN_GROUP_API_KEY, JOIN_GROUP_MAX_VERSION, JoinGroupRequest, JoinGroupRequestProtocol,
+    JoinGroupResponse, UNKNOWN_MEMBER_ID,
+};
+use crate::protocol::leave_group::{
+    LEAVE_GROUP_API_KEY, LEAVE_GROUP_MAX_VERSION, LeaveGroupRequest, LeaveGroupResponse,
+};
+use crate::protocol::list_offsets::{EARLIEST_TIMESTAMP, LATEST_TIMESTAMP};
+use crate::protocol::offset_commit::{
+    OFFSET_COMMIT_API_KEY, OFFSET_COMMIT_MAX_VERSION, OffsetCommitRequest, OffsetCommitResponse,
+};
+use crate::protocol::sync_group::{
+    SYNC_GROUP_API_KEY, SYNC_GROUP_MAX_VERSION, SyncGroupRequest, SyncGroupRequestAssignment,
+    SyncGroupResponse,
+};
+use crate::protocol::types::Type;
+use crate::shared::collections::{FlatMap, FlatSet};
+use crate::tools::{check, fetch_committed_offsets, list_offsets, offset_commit_request};
+
+const DEFAULT_SESSION_TIMEOUT_MS: i32 = 10_000;
+const DEFAULT_REBALANCE_TIMEOUT_MS: i32 = 300_000;
+const DEFAULT_HEARTBEAT_INTERVAL_MS: u64 = 3_000;
+const DEFAULT_ASSIGNOR: &str = "range";
+const DEFAULT_FETCH_MAX_WAIT_MS: i32 = 500;
+const DEFAULT_MAX_PARTITION_FETCH_BYTES: i32 = 1024 * 1024;
+const DEFAULT_AUTO_COMMIT_INTERVAL_MS: u64 = 5_000;
+const FETCH_MAX_BYTES: i32 = 50 * 1024 * 1024;
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Where a consumer starts on a partition its group has no committed offset for, or one
+/// `seek_to_beginning` or `seek_to_end` moved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OffsetReset {
+    Earliest,
+    Latest,
+}
+
+impl OffsetReset {
+    fn timestamp(self) -> i64 {
+        match self {
+            OffsetReset::Earliest => EARLIEST_TIMESTAMP,
+            OffsetReset::Latest => LATEST_TIMESTAMP,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsumerConfig {
+    pub client: ClientConfig,
+    pub group_id: String,
+    /// The member is removed from the group when no heartbeat arrives for this long.
+    pub session_timeout_ms: i32,
+    /// How long the coordinator waits for every member to rejoin once a rebalance starts.
+    pub rebalance_timeout_ms: i32,
+    pub heartbeat_interval_ms: u64,
+    /// Names of the assignors offered to the group, most preferred first, e.g. range,
+    /// roundrobin or sticky. The group uses one every member offers.
+    pub assignors: Vec<String>,
+    pub auto_offset_reset: OffsetReset,
+    pub isolation_level: i8,
+    /// How long the leader holds a fetch open while the partitions have nothing new.
+    pub fetch_max_wait_ms: i32,
+    pub max_partition_fetch_bytes: i32,
+    /// Commits the positions every `auto_commit_interval_ms` from `poll`, and before the
+    /// partitions are revoked or the consumer closes.
+    pub enable_auto_commit: bool,
+    pub auto_commit_interval_ms: u64,
+}
+
+impl ConsumerConfig {
+    pub fn new(client: ClientConfig, group_id: impl Into<String>) -> Self {
+        Self {
+            client,
+            group_id: group_id.into(),
+            session_timeout_ms: DEFAULT_SESSION_TIMEOUT_MS,
+            rebalance_timeout_ms: DEFAULT_REBALANCE_TIMEOUT_MS,
+            heartbeat_interval_ms: DEFAULT_HEARTBEAT_INTERVAL_MS,
+            assignors: vec![DEFAULT_ASSIGNOR.to_string()],
+            auto_offset_reset: OffsetReset::Latest,
+            isolation_level: ISOLATION_READ_UNCOMMITTED,
+            fetch_max_wait_ms: DEFAULT_FETCH_MAX_WAIT_MS,
+            max_partition_fetch_bytes: DEFAULT_MAX_PARTITION_FETCH_BYTES,
+            enable_auto_commit: true,
+            auto_commit_interval_ms: DEFAULT_AUTO_COMMIT_INTERVAL_MS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsumerRecord {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+    /// The broker's append time when the topic uses LogAppendTime, otherwise the create time.
+    pub timestamp: i64,
+    pub key: Option<Vec<u8>>,
+    pub value: Option<Vec<u8>>,
+    pub headers: Vec<Header>,
+}
+
+/// Told when partitions move to or away from the consumer. Revocation runs while no other
+/// member can own the partitions yet: with an eager assignor every partition is revoked
+/// before the consumer rejoins the group, with cooperative-sticky only those taken away,
+/// once the rebalance is over.
+#[async_trait]
+pub trait RebalanceListener: Send + Sync {
+    async fn on_partitions_revoked(&self, _partitions: &[TopicPartition]) {}
+
+    async fn on_partitions_assigned(&self, _partitions: &[TopicPartition]) {}
+}
+
+/// Resolves once the coordinator has answered a `commit_async`.
+pub struct PendingCommit(JoinHandle<Result<(), String>>);
+
+impl Future for PendingCommit {
+    type Output = Result<(), String>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0)
+            .poll(cx)
+            .map(|result| result.map_err(|e| e.to_string()).and_then(|result| result))
+    }
+}
+
+/// Tracks the producers whose open transaction was aborted, so read_committed consumers
+/// can skip their batches up to the abort marker.
+pub struct AbortedFilter {
+    /// Latest first offset first; entries move to `active` once the batches reach them.
+    pending: Vec<(i64, i64)>,
+    active: Vec<i64>,
+}
+
+impl AbortedFilter {
+    pub fn new(partition: &PartitionData) -> Self {
+        let mut pending: Vec<(i64, i64)> = partition
+            .aborted_transactions
+            .iter()
+            .flatten()
+            .map(|txn| (txn.first_offset, txn.producer_id))
+            .collect();
+        pending.sort_unstable_by_key(|(first_offset, _)| std::cmp::Reverse(*first_offset));
+        Self {
+            pending,
+            active: Vec::new(),
+        }
+    }
+
+    /// Whether the batch is skipped; control batches always are.
+    pub fn skip(&mut self, batch: &RecordBatch) -> bool {
+        while let Some(&(first_offset, producer_id)) = self.pending.last() {
+            if first_offset > batch.last_offset() {
+                break;
+            }
+            self.pending.pop();
+            self.active.push(producer_id);
+        }
+
+        if batch.is_control_batch() {
+            self.active
+                .retain(|producer_id| *producer_id != batch.producer_id);
+            return true;
+        }
+        batch.is_transactional() && self.active.contains(&batch.producer_id)
+    }
+}
+
+/// Reads the topics it subscribes to as a member of a consumer group: the group's leader
+/// spreads the partitions over the members, and each member fetches its own share from the
+/// partition leaders. Membership is kept up by a background heartbeat, and a rebalance
+/// announced there is joined on the next `poll`.
+pub struct Consumer {
+    config: ConsumerConfig,
+    cluster: Cluster,
+    subscription: Vec<String>,
+    listener: Option<Box<dyn RebalanceListener>>,
+    /// The broker coordinating the group, once found.
+    coordinator: Option<i32>,
+    member_id: String,
+    generation_id: i32,
+    /// That of the assignor the group picked when the consumer last joined.
+    rebalance_protocol: RebalanceProtocol,
+    rejoin_needed: bool,
+    /// Set by the heartbeat task to the error that ended it.
+    heartbeat_error: Arc<AtomicI16>,
+    /// Stops the heartbeat task between heartbeats, never in the middle of a request on the
+    /// shared coordinator connection.
+    heartbeat: Option<CancellationToken>,
+    assignment: Vec<TopicPartition>,
+    /// The next offset to return, by assigned partition; a partition without one starts
+    /// from the committed offset or `auto_offset_reset`.
+    positions: FlatMap<TopicPartition, i64>,
+    /// Partitions without a position that start at one end rather than the committed offset.
+    pending_resets: FlatMap<TopicPartition, OffsetReset>,
+    /// Assigned partitions left out of fetches until resumed.
+    paused: FlatSet<TopicPartition>,
+    /// Set when a leader turned out to be wrong or missing.
+    metadata_stale: bool,
+    next_auto_commit: Instant,
+}
+
+impl Consumer {
+    pub fn new(config: ConsumerConfig) -> Self {
+        Self {
+            cluster: Cluster::new(config.client.clone()),
+            config,
+            subscription: Vec::new(),
+            listener: None,
+            coordinator: None,
+            member_id: UNKNOWN_MEMBER_ID.to_string(),
+            generation_id: -1,
+            rebalance_protocol: RebalanceProtocol::Eager,
+            rejoin_needed: true,
+            heartbeat_error: Arc::new(AtomicI16::new(ErrorCode::None.code())),
+            heartbeat: None,
+            assignment: Vec::new(),
+            positions: FlatMap::new(),
+            pending_resets: FlatMap::new(),
+            paused: FlatSet::new(),
+            metadata_stale: false,
+            next_auto_commit: Instant::now(),
+        }
+    }
+
+    /// Replaces the subscription; the group is joined, or rejoined, on the next `poll`.
+    pub fn subscribe(&mut self, topics: &[&str], listener: Option<Box<dyn RebalanceListener>>) {
+        self.subscription = topics.iter().map(|topic| topic.to_string()).collect();
+        self.listener = listener;
+        self.rejoin_needed = true;
+    }
+
+    /// The partitions the group currently assigns to this consumer.
+    pub fn assignment(&self) -> &[TopicPartition] {
+        &self.assignment
+    }
+
+    /// The offset of the next record `poll` returns from an assigned partition.
+    pub fn position(&self, topic_partition: &TopicPartition) -> Option<i64> {
+        self.positions.get(topic_partition).copied()
+    }
+
+    /// Returns the records that arrive within `timeout`, joining the group first when
+    /// needed. Returns as soon as some arrive; an empty result means none did.
+    pub async fn poll(&mut self, timeout: Duration) -> Result<Vec<ConsumerRecord>, String> {
+        if self.subscription.is_empty() {
+            return Err("The consumer is not subscribed to any topic".to_string());
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            self.check_heartbeat();
+            if self.rejoin_needed {
+                self.join_group().await?;
+            }
+            if self.metadata_stale {
+                self.cluster.refresh(&self.subscription).await?;
+                self.metadata_stale = false;
+            }
+            self.reset_positions().await?;
+            self.maybe_auto_commit().await;
+
+            let records = self
+                .fetch(deadline.saturating_duration_since(Instant::now()))
+                .await?;
+            if !records.is_empty() || Instant::now() >= deadline {
+                return Ok(records);
+            }
+        }
+    }
+
+    /// The records of every assigned partition as they arrive, polling for more whenever
+    /// the last ones have been taken. An error does not end the stream; the next item polls
+    /// again.
+    pub fn stream(&mut self) -> impl Stream<Item = Result<ConsumerRecord, String>> + '_ {
+        let max_wait = Duration::from_millis(self.config.fetch_max_wait_ms.max(0) as u64);
+        stream::unfold(
+            (self, VecDeque::new()),
+            move |(consumer, mut buffered)| async move {
+                loop {
+                    if let Some(record) = buffered.pop_front() {
+                        return Some((Ok(record), (consumer, buffered)));
+                    }
+                    match consumer.poll(max_wait).await {
+                        Ok(records) => buffered.extend(records),
+                        Err(e) => return Some((Err(e), (consumer, buffered))),
+                    }
+                }
+            },
+        )
+    }
+
+    /// Stops fetching from `partitions` without giving them up, until they are resumed or
+    /// the group takes them away.
+    pub fn pause(&mut self, partitions: &[TopicPartition]) -> Result<(), String> {
+        self.check_assigned(partitions)?;
+        for topic_partition in partitions {
+            self.paused.insert(topic_partition.clone());
+        }
+        Ok(())
+    }
+
+    pub fn resume(&mut self, partitions: &[TopicPartition]) -> Result<(), String> {
+        self.check_assigned(partitions)?;
+        for topic_partition in partitions {
+            self.paused.remove(topic_partition);
+        }
+        Ok(())
+    }
+
+    /// The assigned partitions that are paused.
+    pub fn paused(&self) -> Vec<TopicPartition> {
+        self.paused.iter().cloned().collect()
+    }
+
+    /// Makes the next `poll` read `topic_partition` from `offset` on. Nothing is committed.
+    pub fn seek(&mut self, topic_partition: &TopicPartition, offset: i64) -> Result<(), String> {
+        if offset < 0 {
+            return Err(format!(
+                "Cannot seek {} to offset {}",
+                topic_partition, offset
+            ));
+        }
+        self.check_assigned(std::slice::from_ref(topic_partition))?;
+        self.pending_resets.remove(topic_partition);
+        self.positions.insert(topic_partition.clone(), offset);
+        Ok(())
+    }
+
+    /// Makes the next `poll` read `partitions` from their first offset still in the log.
+    pub fn seek_to_beginning(&mut self, partitions: &[TopicPartition]) -> Result<(), String> {
+        self.seek_to(partitions, OffsetReset::Earliest)
+    }
+
+    /// Makes the next `poll` read only the records written to `partitions` from then on.
+    pub fn seek_to_end(&mut self, partitions: &[TopicPartition]) -> Result<(), String> {
+        self.seek_to(partitions, OffsetReset::Latest)
+    }
+
+    fn seek_to(&mut self, partitions: &[TopicPartition], reset: OffsetReset) -> Result<(), String> {
+        self.check_assigned(partitions)?;
+        for topic_partition in partitions {
+            self.positions.remove(topic_partition);
+            self.pending_resets.insert(topic_partition.clone(), reset);
+        }
+        Ok(())
+    }
+
+    fn check_assigned(&self, partitions: &[TopicPartition]) -> Result<(), String> {
+        match partitions
+            .iter()
+            .find(|topic_partition| !self.assignment.contains(topic_partition))
+        {
+            Some(topic_partition) => Err(format!("{} is not assigned", topic_partition)),
+            None => Ok(()),
+        }
+    }
+
+    /// Leaves the group, so the partitions move to the other members right away instead of
+    /// after the session timeout.
+    pub async fn close(mut self) -> Result<(), String> {
+        self.stop_heartbeat();
+        self.revoke_assignment().await;
+        let Some(coordinator) = self.coordinator else {
+            return Ok(());
+        };
+        if self.member_id == UNKNOWN_MEMBER_ID {
+            return Ok(());
+        }
+
+        let request = LeaveGroupRequest {
+            group_id: self.config.group_id.clone(),
+            member_id: self.member_id.clone(),
+        };
+        let mut response = self
+            .send_to_coordinator(
+                coordinator,
+                LEAVE_GROUP_API_KEY,
+                LEAVE_GROUP_MAX_VERSION,
+                |buf| request.encode(buf, LEAVE_GROUP_MAX_VERSION),
+            )
+            .await?;
+        let response = LeaveGroupResponse::decode(&mut response, LEAVE_GROUP_MAX_VERSION)?;
+        check(response.error_code, "leave the group")
+    }
+
+    /// Commits `offsets`, or the position of every assigned partition when None, and waits
+    /// until the coordinator has stored them. An offset is the next one to read, as
+    /// `position` reports it. Fails if the group has rebalanced since the partitions were
+    /// assigned; the next `poll` then rejoins.
+    pub async fn commit_sync(
+        &mut self,
+        offsets: Option<&[(TopicPartition, i64)]>,
+    ) -> Result<(), String> {
+        let request = self.commit_request(offsets);
+        if request.topics.is_empty() {
+            return Ok(());
+        }
+        loop {
+            let coordinator = self.coordinator().await?;
+            let mut response = self
+                .send_to_coordinator(
+                    coordinator,
+                    OFFSET_COMMIT_API_KEY,
+                    OFFSET_COMMIT_MAX_VERSION,
+                    |buf| request.encode(buf, OFFSET_COMMIT_MAX_VERSION),
+                )
+                .await?;
+            let response = OffsetCommitResponse::decode(&mut response, OFFSET_COMMIT_MAX_VERSION)?;
+            let error_code = commit_error(&response);
+            if error_code == ErrorCode::None.code() {
+                return Ok(());
+            }
+            if is_coordinator_unavailable(error_code) {
+                self.coordinator = None;
+                tokio::time::sleep(RETRY_BACKOFF).await;
+                continue;
+            }
+            if self.handle_group_error(error_code) {
+                self.rejoin_needed = true;
+            }
+            return Err(format!(
+                "Failed to commit offsets of group {}: error code {}",
+                self.config.group_id, error_code
+            ));
+        }
+    }
+
+    /// Like `commit_sync`, but returns once the commit is queued on the coordinator's
+    /// connection, before any later commit. Await the result for the outcome, or drop it.
+    pub async fn commit_async(
+        &mut self,
+        offsets: Option<&[(TopicPartition, i64)]>,
+    ) -> Result<PendingCommit, String> {
+        let request = self.commit_request(offsets);
+        let coordinator = self.coordinator().await?;
+        let connection = self.cluster.connection(coordinator).await?;
+        let mut connection = connection.lock_owned().await;
+        Ok(PendingCommit(tokio::spawn(async move {
+            if request.topics.is_empty() {
+                return Ok(());
+            }
+            let mut response = connection
+                .send_request(OFFSET_COMMIT_API_KEY, OFFSET_COMMIT_MAX_VERSION, |buf| {
+                    request.encode(buf, OFFSET_COMMIT_MAX_VERSION)
+                })
+                .await?;
+            let response = OffsetCommitResponse::decode(&mut response, OFFSET_COMMIT_MAX_VERSION)?;
+            check(commit_error(&response), "commit offsets")
+        })))
+    }
+
+    /// Commits for the member's current generation.
+    fn commit_request(&self, offsets: Option<&[(TopicPartition, i64)]>) -> OffsetCommitRequest {
+        let positions: Vec<(TopicPartition, i64)>;
+        let offsets = match offsets {
+            Some(offsets) => offsets,
+            None => {
+                positions = self
+                    .positions
+                    .iter()
+                    .map(|(topic_partition, offset)| (topic_partition.clone(), *offset))
+                    .collect();
+                &positions
+            }
+        };
+        offset_commit_request(
+            &self.config.group_id,
+            self.generation_id,
+            &self.member_id,
+            offsets,
+        )
+    }
+
+    /// Commits the positions once `auto_commit_interval_ms` has passed since the last time.
+    async fn maybe_auto_commit(&mut self) {
+        if !self.config.enable_auto_commit || Instant::now() < self.next_auto_commit {
+            return;
+        }
+        self.next_auto_commit =
+            Instant::now() + Duration::from_millis(self.config.auto_commit_interval_ms);
+        self.auto_commit().await;
+    }
+
+    async fn auto_commit(&mut self) {
+        if !self.config.enable_auto_commit || self.positions.is_empty() {
+            return;
+        }
+        if let Err(e) = self.commit_sync(None).await {
+            tracing::warn!("Auto-commit failed: {}", e);
+        }
+    }
+
+    /// Picks up the error that stopped the heartbeat task, if any, and prepares to rejoin.
+    fn check_heartbeat(&mut self) {
+        let error_code = self
+            .heartbeat_error
+            .swap(ErrorCode::None.code(), Ordering::Relaxed);
+        if error_code == ErrorCode::None.code() {
+            return;
+        }
+        self.handle_group_error(error_code);
+        self.rejoin_needed = true;
+    }
+
+    /// Forgets what a coordinator error says is no longer valid; returns whether the
+    /// request that failed may simply be retried.
+    fn handle_group_error(&mut self, error_code: i16) -> bool {
+        if error_code == ErrorCode::UnknownMemberId.code()
+            || error_code == ErrorCode::IllegalGeneration.code()
+        {
+            self.member_id = UNKNOWN_MEMBER_ID.to_string();
+            self.generation_id = -1;
+            true
+        } else if is_coordinator_unavailable(error_code) {
+            self.coordinator = None;
+            true
+        } else {
+            error_code == ErrorCode::RebalanceInProgress.code()
+        }
+    }
+
+    async fn coordinator(&mut self) -> Result<i32, String> {
+        loop {
+            if let Some(coordinator) = self.coordinator {
+                return Ok(coordinator);
+            }
+            match self
+                .cluster
+                .group_coordinator(&self.config.group_id)
+                .await?
+            {
+                Ok(coordinator) => self.coordinator = Some(coordinator),
+                Err(error_code) if error_code == ErrorCode::CoordinatorNotAvailable.code() => {
+                    tokio::time::sleep(RETRY_BACKOFF).await
+                }
+                Err(error_code) => {
+                    return Err(format!(
+                        "Failed to find the coordinator of group {}: error code {}",
+                        self.config.group_id, error_code
+                    ));
+                }
+            }
+        }
+    }
+
+    async fn send_to_coordinator(
+        &mut self,
+        coordinator: i32,
+        api_key: i16,
+        api_version: i16,
+        encode: impl FnOnce(&mut BytesMut),
+    ) -> Result<Bytes, String> {
+        let connection = self.cluster.connection(coordinator).await?;
+        let result = connection
+            .lock()
+            .await
+            .send_request(api_key, api_version, encode)
+            .await;
+        if result.is_err() {
+            self.coordinator = None;
+        }
+        result
+    }
+
+    async fn revoke_assignment(&mut self) {
+        let revoked = self.assignment.clone();
+        self.revoke(revoked).await;
+    }
+
+    /// Commits the positions when auto-committing, then gives up `revoked` and forgets
+    /// their positions.
+    async fn revoke(&mut self, revoked: Vec<TopicPartition>) {
+        if revoked.is_empty() {
+            return;
+        }
+        self.auto_commit().await;
+        self.assignment
+            .retain(|topic_partition| !revoked.contains(topic_partition));
+        for topic_partition in &revoked {
+            self.positions.remove(topic_partition);
+            self.pending_resets.remove(topic_partition);
+            self.paused.remove(topic_partition);
+        }
+        if let Some(listener) = &self.listener {
+            listener.on_partitions_revoked(&revoked).await;
+        }
+    }
+
+    /// Joins the group and syncs until the group has a generation this member belongs to.
+    /// An eager member gives up its assignment first. A cooperative one keeps it and tells
+    /// the leader what it owns, then revokes the partitions the new assignment takes away
+    /// and rejoins, so that they can go to their new owners.
+    async fn join_group(&mut self) -> Result<(), String> {
+        self.stop_heartbeat();
+        if revokes_before_join(self.rebalance_protocol, self.generation_id) {
+            self.revoke_assignment().await;
+        }
+
+        let assignment = loop {
+            let coordinator = self.coordinator().await?;
+            let joined = self.send_join_group(coordinator).await?;
+            if joined.error_code != ErrorCode::None.code() {
+                if self.handle_group_error(joined.error_code) {
+                    tokio::time::sleep(RETRY_BACKOFF).await;
+                    continue;
+                }
+                return Err(format!(
+                    "Failed to join group {}: error code {}",
+                    self.config.group_id, joined.error_code
+                ));
+            }
+            self.member_id = joined.member_id.clone();
+            self.generation_id = joined.generation_id;
+            self.rebalance_protocol = assignor_for(&joined.protocol_name)
+                .map_or(RebalanceProtocol::Eager, |assignor| {
+                    assignor.rebalance_protocol()
+                });
+
+            let assignments = if joined.leader == joined.member_id {
+                self.assign(&joined).await?
+            } else {
+                vec![]
+            };
+            let synced = self.send_sync_group(coordinator, assignments).await?;
+            if synced.error_code != ErrorCode::None.code() {
+                if self.handle_group_error(synced.error_code) {
+                    continue;
+                }
+                return Err(format!(
+                    "Failed to sync group {}: error code {}",
+                    self.config.group_id, synced.error_code
+                ));
+            }
+            break Assignment::decode(&mut synced.assignment.as_slice())?;
+        };
+
+        let mut partitions = assignment.partitions;
+        partitions.sort();
+        tracing::info!(
+            "Joined group {} in generation {} as {} with partitions {:?}",
+            self.config.group_id,
+            self.generation_id,
+            self.member_id,
+            partitions
+        );
+        let (revoked, assigned) = assignment_changes(&self.assignment, &partitions);
+        self.rejoin_needed = !revoked.is_empty();
+        self.revoke(revoked).await;
+        self.assignment = partitions;
+        if let Some(listener) = &self.listener {
+            listener.on_partitions_assigned(&assigned).await;
+        }
+        self.start_heartbeat().await
+    }
+
+    async fn send_join_group(&mut self, coordinator: i32) -> Result<JoinGroupResponse, String> {
+        let mut metadata = BytesMut::new();
+        Subscription {
+            topics: self.subscription.clone(),
+            user_data: None,
+            owned_partitions: self.assignment.clone(),
+            generation_id: self.generation_id,
+        }
+        .encode(&mut metadata);
+        let request = JoinGroupRequest {
+            group_id: self.config.group_id.clone(),
+            session_timeout_ms: self.config.session_timeout_ms,
+            rebalance_timeout_ms: self.config.rebalance_timeout_ms,
+            member_id: self.member_id.clone(),
+            protocol_type: CONSUMER_PROTOCOL_TYPE.to_string(),
+            protocols: self
+                .config
+                .assignors
+                .iter()
+                .map(|name| JoinGroupRequestProtocol {
+                    name: name.clone(),
+                    metadata: metadata.to_vec(),
+                })
+                .collect(),
+        };
+        let mut response = self
+            .send_to_coordinator(
+                coordinator,
+                JOIN_GROUP_API_KEY,
+                JOIN_GROUP_MAX_VERSION,
+                |buf| request.encode(buf, JOIN_GROUP_MAX_VERSION),
+            )
+            .await?;
+        JoinGroupResponse::decode(&mut response, JOIN_GROUP_MAX_VERSION)
+    }
+
+    async fn send_sync_group(
+        &mut self,
+        coordinator: i32,
+        assignments: Vec<SyncGroupRequestAssignment>,
+    ) -> Result<SyncGroupResponse, String> {
+        let request = SyncGroupRequest {
+            group_id: self.config.group_id.clone(),
+            generation_id: self.generation_id,
+            member_id: self.member_id.clone(),
+            assignments,
+        };
+        let mut response = self
+            .send_to_coordinator(
+                coordinator,
+                SYNC_GROUP_API_KEY,
+                SYNC_GROUP_MAX_VERSION,
+                |buf| request.encode(buf, SYNC_GROUP_MAX_VERSION),
+            )
+            .await?;
+        SyncGroupResponse::decode(&mut response, SYNC_GROUP_MAX_VERSION)
+    }
+
+    /// As the group's leader, spreads the partitions of every topic some member subscribed
+    /// to over the members, with the assignor the coordinator picked.
+    async fn assign(
+        &self,
+        joined: &JoinGroupResponse,
+    ) -> Result<Vec<SyncGroupRequestAssignment>, String> {
+        let members: Vec<(String, Vec<u8>)> = joined
+            .members
+            .iter()
+            .map(|member| (member.member_id.clone(), member.metadata.clone()))
+            .collect();
+        let mut topics: Vec<String> = Vec::new();
+        for (member_id, metadata) in &members {
+            let subscription = Subscription::decode(&mut metadata.as_slice())
+                .map_err(|e| format!("Invalid subscription from {}: {}", member_id, e))?;
+            for topic in subscription.topics {
+                if !topics.contains(&topic) {
+                    topics.push(topic);
+                }
+            }
+        }
+
+        self.cluster.refresh(&topics).await?;
+        let mut partitions_per_topic = FlatMap::new();
+        for topic in &topics {
+            let info = self.cluster.topic(topic).await?;
+            partitions_per_topic.insert(topic.clone(), info.partitions.len() as i32);
+        }
+        let assignments =
+            perform_assignment(&joined.protocol_name, &members, &partitions_per_topic)?;
+        Ok(assignments
+            .into_iter()
+            .map(|(member_id, assignment)| SyncGroupRequestAssignment {
+                member_id,
+                assignment,
+            })
+            .collect())
+    }
+
+    async fn start_heartbeat(&mut self) -> Result<(), String> {
+        let coordinator = self.coordinator().await?;
+        let connection = self.cluster.connection(coordinator).await?;
+        let request = HeartbeatRequest {
+            group_id: self.config.group_id.clone(),
+            generation_id: self.generation_id,
+            member_id: self.member_id.clone(),
+        };
+        let interval = Duration::from_millis(self.config.heartbeat_interval_ms);
+        let heartbeat_error = self.heartbeat_error.clone();
+        let cancel_token = CancellationToken::new();
+        self.heartbeat = Some(cancel_token.clone());
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => return,
+                    _ = tokio::time::sleep(interval) => {}
+                }
+                let response = connection
+                    .lock()
+                    .await
+                    .send_request(HEARTBEAT_API_KEY, HEARTBEAT_MAX_VERSION, |buf| {
+                        request.encode(buf, HEARTBEAT_MAX_VERSION)
+                    })
+                    .await
+                    .and_then(|mut response| {
+                        HeartbeatResponse::decode(&mut response, HEARTBEAT_MAX_VERSION)
+                    });
+                let error_code = match response {
+                    Ok(response) => response.error_code,
+                    Err(e) => {
+                        tracing::warn!("Heartbeat to the group coordinator failed: {}", e);
+                        ErrorCode::CoordinatorNotAvailable.code()
+                    }
+                };
+                // An answer for a generation the consumer already left says nothing
+                if error_code != ErrorCode::None.code() && !cancel_token.is_cancelled() {
+                    heartbeat_error.store(error_code, Ordering::Relaxed);
+                    return;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn stop_heartbeat(&mut self) {
+        if let Some(heartbeat) = self.heartbeat.take() {
+            heartbeat.cancel();
+        }
+    }
+
+    /// Starts assigned partitions without a position where they were sought to, else at the
+    /// group's committed offset, or where `auto_offset_reset` says when there is none.
+    async fn reset_positions(&mut self) -> Result<(), String> {
+        let missing: Vec<TopicPartition> = self
+            .assignment
+            .iter()
+            .filter(|topic_partition| !self.positions.contains_key(topic_partition))
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let unsought: Vec<TopicPartition> = missing
+            .iter()
+            .filter(|topic_partition| !self.pending_resets.contains_key(topic_partition))
+            .cloned()
+            .collect();
+        if !unsought.is_empty() {
+            let coordinator = self.coordinator().await?;
+            let connection = self.cluster.connection(coordinator).await?;
+            let committed = fetch_committed_offsets(
+                &mut *connection.lock().await,
+                &self.config.group_id,
+                Some(&unsought),
+            )
+            .await?;
+            for (topic_partition, offset) in committed {
+                self.positions.insert(topic_partition, offset);
+            }
+        }
+
+        for reset in [OffsetReset::Earliest, OffsetReset::Latest] {
+            let partitions: Vec<TopicPartition> = missing
+                .iter()
+                .filter(|topic_partition| {
+                    !self.positions.contains_key(topic_partition)
+                        && self
+                            .pending_resets
+                            .get(topic_partition)
+                            .copied()
+                            .unwrap_or(self.config.auto_offset_reset)
+                            == reset
+                })
+                .cloned()
+                .collect();
+            self.list_positions(&partitions, reset).await?;
+        }
+        Ok(())
+    }
+
+    /// Asks the leaders of `partitions` where `reset` puts them and starts them there.
+    async fn list_positions(
+        &mut self,
+        partitions: &[TopicPartition],
+        reset: OffsetReset,
+    ) -> Result<(), String> {
+        for (leader, partitions) in self.by_leader(partitions).await? {
+            let connection = self.cluster.connection(leader).await?;
+            let offsets = match list_offsets(
+                &mut *connection.lock().await,
+                self.config.isolation_level,
+                &partitions,
+                reset.timestamp(),
+            )
+            .await
+            {
+                Ok(offsets) => offsets,
+                Err(e) => {
+                    self.leader_unreachable(leader, &e).await;
+                    continue;
+                }
+            };
+            for (topic_partition, offset) in offsets {
+                match offset {
+                    Ok(offset) => {
+                        self.pending_resets.remove(&topic_partition);
+                        self.positions.insert(topic_partition, offset);
+                    }
+                    Err(error_code) if is_stale_metadata(error_code) => self.metadata_stale = true,
+                    Err(error_code) => {
+                        return Err(format!(
+                            "Failed to list offsets of {}: error code {}",
+                            topic_partition, error_code
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Groups `partitions` by the broker leading them; partitions without a known leader are
+    /// left out until the metadata is refreshed.
+    async fn by_leader(
+        &mut self,
+        partitions: &[TopicPartition],
+    ) -> Result<Vec<(i32, Vec<TopicPartition>)>, String> {
+        let mut by_leader: Vec<(i32, Vec<TopicPartition>)> = Vec::new();
+        for topic_partition in partitions {
+            let info = self.cluster.topic(&topic_partition.topic).await?;
+            let leader = info
+                .partitions
+                .get(topic_partition.partition as usize)
+                .map_or(NO_LEADER, |partition| partition.leader);
+            if leader == NO_LEADER {
+                self.metadata_stale = true;
+                continue;
+            }
+            match by_leader.iter_mut().find(|(broker, _)| *broker == leader) {
+                Some((_, partitions)) => partitions.push(topic_partition.clone()),
+                None => by_leader.push((leader, vec![topic_partition.clone()])),
+            }
+        }
+        Ok(by_leader)
+    }
+
+    /// Fetches from every leader of an assigned partition at once, waiting at most
+    /// `max_wait` for records to arrive.
+    async fn fetch(&mut self, max_wait: Duration) -> Result<Vec<ConsumerRecord>, String> {
+        let max_wait_ms = (max_wait.as_millis() as i32).min(self.config.fetch_max_wait_ms);
+        let fetchable: Vec<TopicPartition> = self
+            .assignment
+            .iter()
+            .filter(|topic_partition| {
+                self.positions.contains_key(topic_partition)
+                    && !self.paused.contains(topic_partition)
+            })
+            .cloned()
+            .collect();
+        let by_leader = self.by_leader(&fetchable).await?;
+        if by_leader.is_empty() {
+            tokio::time::sleep(Duration::from_millis(max_wait_ms.max(0) as u64)).await;
+            return Ok(vec![]);
+        }
+
+        let mut requests = Vec::with_capacity(by_leader.len());
+        for (leader, partitions) in by_leader {
+            let mut topics: Vec<FetchTopic> = Vec::new();
+            for topic_partition in partitions {
+                let partition = FetchPartition {
+                    partition: topic_partition.partition,
+                    current_leader_epoch: -1,
+                    fetch_offset: self.positions.get(&topic_partition).copied().unwrap_or(0),
+                    log_start_offset: -1,
+                    partition_max_bytes: self.config.max_partition_fetch_bytes,
+                };
+                match topics
+                    .iter_mut()
+                    .find(|topic| topic.topic == topic_partition.topic)
+                {
+                    Some(topic) => topic.partitions.push(partition),
+                    None => topics.push(FetchTopic {
+                        topic: topic_partition.topic,
+                        partitions: vec![partition],
+                    }),
+                }
+            }
+            let request = FetchRequest {
+                replica_id: CONSUMER_REPLICA_ID,
+                max_wait_ms,
+                min_bytes: 1,
+                max_bytes: FETCH_MAX_BYTES,
+                isolation_level: self.config.isolation_level,
+                session_id: 0,
+                session_epoch: -1,
+                topics,
+                forgotten_topics: vec![],
+                rack_id: String::new(),
+            };
+            requests.push((leader, (self.cluster.connection(leader).await?, request)));
+        }
+        let responses = join_all(
+            requests
+                .iter()
+                .map(|(_, (connection, request))| fetch_from(connection, request)),
+        )
+        .await;
+
+        let mut records = Vec::new();
+        for ((leader, _), response) in requests.into_iter().zip(responses) {
+            let response = match response {
+                Ok(response) => response,
+                Err(e) => {
+                    self.leader_unreachable(leader, &e).await;
+                    continue;
+                }
+            };
+            check(response.error_code, "fetch")?;
+            for topic in response.responses {
+                for partition in topic.partitions {
+                    let topic_partition =
+                        TopicPartition::new(topic.topic.clone(), partition.partition_index);
+                    if !self.positions.contains_key(&topic_partition) {
+                        continue;
+                    }
+                    if partition.error_code == ErrorCode::None.code() {
+                        self.collect(topic_partition, partition, &mut records);
+                    } else if partition.error_code == ErrorCode::OffsetOutOfRange.code() {
+                        tracing::info!(
+                            "Fetch position of {} is out of range, resetting it",
+                            topic_partition
+                        );
+                        self.positions.remove(&topic_partition);
+                    } else if is_stale_metadata(partition.error_code) {
+                        self.metadata_stale = true;
+                    } else {
+                        return Err(format!(
+                            "Failed to fetch {}: error code {}",
+                            topic_partition, partition.error_code
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    /// The broker may have gone down, in which case its partitions get new leaders the next
+    /// metadata names. The backoff keeps the consumer from hammering it meanwhile.
+    async fn leader_unreachable(&mut self, leader: i32, error: &str) {
+        tracing::warn!("Broker {} could not be reached: {}", leader, error);
+        self.metadata_stale = true;
+        tokio::time::sleep(Duration::from_millis(
+            self.cluster.config().reconnect_backoff_ms,
+        ))
+        .await;
+    }
+
+    /// Turns the partition's fetched batches into records from the position on, and moves
+    /// the position past them.
+    fn collect(
+        &mut self,
+        topic_partition: TopicPartition,
+        partition: PartitionData,
+        records: &mut Vec<ConsumerRecord>,
+    ) {
+        let Some(mut position) = self.positions.get(&topic_partition).copied() else {
+            return;
+        };
+        let batches = match partition.records.batches() {
+            Ok(batches) => batches,
+            Err(e) => {
+                tracing::warn!(
+                    "Dropping corrupt records fetched from {}: {}",
+                    topic_partition,
+                    e
+                );
+                return;
+            }
+        };
+        let mut aborted = AbortedFilter::new(&partition);
+        for batch in batches {
+            if batch.last_offset() < position {
+                continue;
+            }
+            if aborted.skip(&batch) {
+                position = batch.last_offset() + 1;
+                continue;
+            }
+
+            let next_position = position.max(batch.last_offset() + 1);
+            let log_append_time = batch.is_log_append_time().then_some(batch.max_timestamp);
+            for record in batch.records {
+                let offset = batch.base_offset + record.offset_delta.0 as i64;
+                if offset < position {
+                    continue;
+                }
+                records.push(ConsumerRecord {
+                    topic: topic_partition.topic.clone(),
+                    partition: topic_partition.partition,
+                    offset,
+                    timestamp: log_append_time
+                        .unwrap_or(batch.base_timestamp + record.timestamp_delta.0),
+                    key: record.key,
+                    value: record.value,
+                    headers: record.headers,
+                });
+            }
+            position = next_position;
+        }
+        self.positions.insert(topic_partition, position);
+    }
+}
+
+/// Whether a member gives up its whole assignment before it joins: always with an eager
+/// assignor, and with a cooperative one when the group forgot the member, which has lost
+/// its partitions.
+fn revokes_before_join(protocol: RebalanceProtocol, generation_id: i32) -> bool {
+    protocol == RebalanceProtocol::Eager || generation_id < 0
+}
+
+/// The partitions `assigned` takes away from `owned`, and those it adds.
+fn assignment_changes(
+    owned: &[TopicPartition],
+    assigned: &[TopicPartition],
+) -> (Vec<TopicPartition>, Vec<TopicPartition>) {
+    let revoked = owned
+        .iter()
+        .filter(|topic_partition| !assigned.contains(topic_partition))
+        .cloned()
+        .collect();
+    let added = assigned
+        .iter()
+        .filter(|topic_partition| !owned.contains(topic_partition))
+        .cloned()
+        .collect();
+    (revoked, added)
+}
+
+impl Drop for Consumer {
+    fn drop(&mut self) {
+        self.stop_heartbeat();
+    }
+}
+
+async fn fetch_from(
+    connection: &Mutex<BrokerClient>,
+    request: &FetchRequest,
+) -> Result<FetchResponse, String> {
+    connection.lock().await.fetch(request).await
+}
+
+/// The first partition error in an OffsetCommit response, if any.
+fn commit_error(response: &OffsetCommitResponse) -> i16 {
+    response
+        .topics
+        .iter()
+        .flat_map(|topic| &topic.partitions)
+        .map(|partition| partition.error_code)
+        .find(|error_code| *error_code != ErrorCode::None.code())
+        .unwrap_or(ErrorCode::None.code())
+}
+
+/// Errors meaning the partition's leader has moved.
+pub fn is_stale_metadata(error_code: i16) -> bool {
+    error_code == ErrorCode::NotLeaderOrFollower.code()
+        || error_code == ErrorCode::LeaderNotAvailable.code()
+        || error_code == ErrorCode::UnknownTopicOrPartition.code()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partitions(topic: &str, partitions: &[i32]) -> Vec<TopicPartition> {
+        partitions
+            .iter()
+            .map(|partition| TopicPartition::new(topic, *partition))
+            .collect()
+    }
+
+    #[test]
+    fn test_eager_member_revokes_everything() {
+        assert!(revokes_before_join(RebalanceProtocol::Eager, 3));
+
+        // Nothing is left to revoke after the join; the whole assignment is new
+        let assigned = partitions("t", &[0, 1]);
+        let (revoked, added) = assignment_changes(&[], &assigned);
+        assert!(revoked.is_empty());
+        assert_eq!(added, assigned);
+    }
+
+    #[test]
+    fn test_cooperative_member_revokes_only_what_moved() {
+        assert!(!revokes_before_join(RebalanceProtocol::Cooperative, 3));
+
+        let owned = partitions("t", &[0, 1, 2, 3]);
+        let assigned = partitions("t", &[0, 1, 4]);
+        let (revoked, added) = assignment_changes(&owned, &assigned);
+        assert_eq!(revoked, partitions("t", &[2, 3]));
+        assert_eq!(added, partitions("t", &[4]));
+
+        let (revoked, added) = assignment_changes(&owned, &owned);
+        assert!(revoked.is_empty());
+        assert!(added.is_empty());
+    }
+
+    #[test]
+    fn test_member_with_reset_generation_gives_up_everything() {
+        assert!(revokes_before_join(RebalanceProtocol::Cooperative, -1));
+        assert!(revokes_before_join(RebalanceProtocol::Eager, -1));
+    }
+}
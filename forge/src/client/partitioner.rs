@@ -0,0 +1,221 @@
+use rand::RngExt;
+use std::sync::Mutex;
+
+use crate::client::cluster::PartitionInfo;
+use crate::core::domain::metadata_records::NO_LEADER;
+use crate::shared::collections::FlatMap;
+use crate::shared::hash::murmur2;
+
+/// Picks the partition of each record a producer sends.
+pub trait Partitioner: Send + Sync {
+    /// `partitions` describes every partition of `topic`, `partitions[p]` partition `p`; the
+    /// result must be one of them.
+    fn partition(
+        &self,
+        topic: &str,
+        key: Option<&[u8]>,
+        value: Option<&[u8]>,
+        partitions: &[PartitionInfo],
+    ) -> i32;
+}
+
+/// The partition the Java and librdkafka clients give `key`, whether or not it has a leader.
+pub fn partition_for_key(key: &[u8], partition_count: usize) -> i32 {
+    ((murmur2(key) & 0x7fff_ffff) as usize % partition_count) as i32
+}
+
+/// Partitions that have a leader, or all of them when none has.
+fn available(partitions: &[PartitionInfo]) -> Vec<i32> {
+    let available: Vec<i32> = partitions
+        .iter()
+        .filter(|partition| partition.leader != NO_LEADER)
+        .map(|partition| partition.partition)
+        .collect();
+    if available.is_empty() {
+        partitions
+            .iter()
+            .map(|partition| partition.partition)
+            .collect()
+    } else {
+        available
+    }
+}
+
+/// What the Java producer does by default: records with a key go to the partition its
+/// murmur2 hash picks, the others stick to one partition at a time like
+/// `StickyPartitioner`.
+pub struct DefaultPartitioner {
+    sticky: StickyPartitioner,
+}
+
+impl DefaultPartitioner {
+    pub fn new(batch_size: usize) -> Self {
+        Self {
+            sticky: StickyPartitioner::new(batch_size),
+        }
+    }
+}
+
+impl Partitioner for DefaultPartitioner {
+    fn partition(
+        &self,
+        topic: &str,
+        key: Option<&[u8]>,
+        value: Option<&[u8]>,
+        partitions: &[PartitionInfo],
+    ) -> i32 {
+        match key {
+            Some(key) => partition_for_key(key, partitions.len()),
+            None => self.sticky.partition(topic, key, value, partitions),
+        }
+    }
+}
+
+/// Sends every record of a topic to one partition until `batch_size` bytes have gone
+/// there, then moves to another picked at random, so records fill whole batches instead of
+/// spreading thinly over all partitions. Keys are ignored.
+pub struct StickyPartitioner {
+    batch_size: usize,
+    /// The current partition of each topic and the bytes sent to it so far.
+    current: Mutex<FlatMap<String, (i32, usize)>>,
+}
+
+impl StickyPartitioner {
+    pub fn new(batch_size: usize) -> Self {
+        Self {
+            batch_size,
+            current: Mutex::new(FlatMap::new()),
+        }
+    }
+}
+
+impl Partitioner for StickyPartitioner {
+    fn partition(
+        &self,
+        topic: &str,
+        key: Option<&[u8]>,
+        value: Option<&[u8]>,
+        partitions: &[PartitionInfo],
+    ) -> i32 {
+        let size = key.map_or(0, <[u8]>::len) + value.map_or(0, <[u8]>::len);
+        let mut current = self
+            .current
+            .lock()
+            .expect("Sticky partitioner lock poisoned");
+        let previous = match current.get_mut(&topic.to_string()) {
+            Some((partition, sent)) if (*partition as usize) < partitions.len() => {
+                if *sent < self.batch_size {
+                    *sent += size;
+                    return *partition;
+                }
+                Some(*partition)
+            }
+            _ => None,
+        };
+
+        let mut candidates = available(partitions);
+        if candidates.len() > 1 {
+            candidates.retain(|partition| Some(*partition) != previous);
+        }
+        let partition = candidates[rand::rng().random_range(0..candidates.len())];
+        current.insert(topic.to_string(), (partition, size));
+        partition
+    }
+}
+
+/// Spreads records evenly over the partitions with a leader, one after the other,
+/// whatever their key.
+#[derive(Default)]
+pub struct RoundRobinPartitioner {
+    /// The number of records sent to each topic so far.
+    counters: Mutex<FlatMap<String, usize>>,
+}
+
+impl RoundRobinPartitioner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Partitioner for RoundRobinPartitioner {
+    fn partition(
+        &self,
+        topic: &str,
+        _key: Option<&[u8]>,
+        _value: Option<&[u8]>,
+        partitions: &[PartitionInfo],
+    ) -> i32 {
+        let mut counters = self
+            .counters
+            .lock()
+            .expect("Round-robin partitioner lock poisoned");
+        let counter = counters.get(&topic.to_string()).copied().unwrap_or(0);
+        counters.insert(topic.to_string(), counter.wrapping_add(1));
+
+        let candidates = available(partitions);
+        candidates[counter % candidates.len()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partitions(leaders: &[i32]) -> Vec<PartitionInfo> {
+        leaders
+            .iter()
+            .enumerate()
+            .map(|(partition, leader)| PartitionInfo {
+                partition: partition as i32,
+                leader: *leader,
+                leader_epoch: 0,
+                replicas: vec![],
+                isr: vec![],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_keys_hash_like_the_java_client() {
+        // Expected values from Kafka's UtilsTest
+        assert_eq!(murmur2(b"21"), -973932308);
+        assert_eq!(murmur2(b"foobar"), -790332482);
+        assert_eq!(murmur2(b"a-little-bit-long-string"), -985981536);
+        assert_eq!(murmur2(b"a-little-bit-longer-string"), -1486304829);
+        assert_eq!(
+            murmur2(b"lkjh234lh9fiuh90y23oiuhsafujhadof229phr9h19h89h8"),
+            -58897971
+        );
+
+        // A key keeps its partition even while that partition has no leader
+        let partitioner = DefaultPartitioner::new(16);
+        let offline = partitions(&[NO_LEADER, 1, 1]);
+        assert_eq!(
+            partitioner.partition("orders", Some(b"foobar"), None, &offline),
+            0
+        );
+    }
+
+    #[test]
+    fn test_sticky_and_round_robin_skip_partitions_without_leader() {
+        let online = partitions(&[1, NO_LEADER, 1, 1]);
+
+        let sticky = StickyPartitioner::new(10);
+        let first = sticky.partition("orders", None, Some(&[0; 6]), &online);
+        assert_ne!(first, 1);
+        assert_eq!(
+            sticky.partition("orders", None, Some(&[0; 6]), &online),
+            first
+        );
+        // 12 bytes have gone to the partition, more than a batch, so the next record moves on
+        let next = sticky.partition("orders", None, Some(&[0; 6]), &online);
+        assert_ne!(next, first);
+        assert_ne!(next, 1);
+
+        let round_robin = RoundRobinPartitioner::new();
+        let picked: Vec<i32> = (0..4)
+            .map(|_| round_robin.partition("orders", Some(b"key"), None, &online))
+            .collect();
+        assert_eq!(picked, vec![0, 2, 3, 0]);
+    }
+}
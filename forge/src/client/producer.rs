@@ -0,0 +1,741 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::{Mutex, Notify, oneshot};
+use tokio::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::application::producer_state::NUM_BATCHES_TO_RETAIN;
+use crate::application::replica_manager::{ACKS_ALL, ACKS_NONE};
+use crate::client::accumulator::{ProducerBatch, RecordAccumulator};
+use crate::client::cluster::{ClientConfig, Cluster, is_coordinator_unavailable};
+use crate::client::consumer::is_stale_metadata;
+use crate::client::partitioner::{DefaultPartitioner, Partitioner};
+use crate::client::transaction_manager::TransactionManager;
+use crate::core::domain::compression::CompressionType;
+use crate::core::domain::metadata_records::NO_LEADER;
+use crate::core::domain::record::Header;
+use crate::core::domain::record_batch::TRANSACTIONAL_FLAG_MASK;
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::protocol::init_producer_id::{
+    INIT_PRODUCER_ID_API_KEY, INIT_PRODUCER_ID_MAX_VERSION, InitProducerIdRequest,
+    InitProducerIdResponse,
+};
+use crate::protocol::produce::{
+    PRODUCE_API_KEY, PRODUCE_MAX_VERSION, PartitionProduceData, ProduceRequest, ProduceResponse,
+    TopicProduceData,
+};
+use crate::shared::time::current_time_ms;
+
+const DEFAULT_REQUEST_TIMEOUT_MS: i32 = 30_000;
+const DEFAULT_LINGER_MS: u64 = 5;
+const DEFAULT_BATCH_SIZE: usize = 16 * 1024;
+const DEFAULT_RETRIES: u32 = 10;
+const DEFAULT_RETRY_BACKOFF_MS: u64 = 100;
+const DEFAULT_RETRY_BACKOFF_MAX_MS: u64 = 1000;
+const DEFAULT_TRANSACTION_TIMEOUT_MS: i32 = 60_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProducerConfig {
+    pub client: ClientConfig,
+    /// 0, 1 or -1 (all in-sync replicas), as Produce's `acks`.
+    pub acks: i16,
+    pub request_timeout_ms: i32,
+    pub compression: CompressionType,
+    /// How long a partition's batch waits for more records before it is sent.
+    pub linger_ms: u64,
+    /// Bytes of records after which a batch is sent without waiting for `linger_ms`.
+    pub batch_size: usize,
+    /// How often a batch is sent again after a retriable error before its records fail.
+    pub retries: u32,
+    /// The wait before the first retry of a batch, doubling with each further one.
+    pub retry_backoff_ms: u64,
+    pub retry_backoff_max_ms: u64,
+    /// Batches a partition may have sent and not yet answered. With more than one, a retried
+    /// batch can land after the batches behind it unless the producer is idempotent.
+    pub max_in_flight_requests: usize,
+    /// Numbers every batch with a producer id and per-partition sequence, so the leader
+    /// writes a retried batch only once and in order. Requires acks=-1 and at most
+    /// `NUM_BATCHES_TO_RETAIN` in flight.
+    pub enable_idempotence: bool,
+    /// Makes the producer transactional: records are only sent inside a transaction, which
+    /// commits or aborts them all at once. Implies idempotence.
+    pub transactional_id: Option<String>,
+    /// How long the coordinator lets a transaction run before aborting it.
+    pub transaction_timeout_ms: i32,
+}
+
+impl ProducerConfig {
+    pub fn new(client: ClientConfig) -> Self {
+        Self {
+            client,
+            acks: ACKS_ALL,
+            request_timeout_ms: DEFAULT_REQUEST_TIMEOUT_MS,
+            compression: CompressionType::None,
+            linger_ms: DEFAULT_LINGER_MS,
+            batch_size: DEFAULT_BATCH_SIZE,
+            retries: DEFAULT_RETRIES,
+            retry_backoff_ms: DEFAULT_RETRY_BACKOFF_MS,
+            retry_backoff_max_ms: DEFAULT_RETRY_BACKOFF_MAX_MS,
+            max_in_flight_requests: NUM_BATCHES_TO_RETAIN,
+            enable_idempotence: false,
+            transactional_id: None,
+            transaction_timeout_ms: DEFAULT_TRANSACTION_TIMEOUT_MS,
+        }
+    }
+}
+
+/// Where a record was written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordMetadata {
+    pub topic: String,
+    pub partition: i32,
+    /// -1 with acks=0, when the broker does not say.
+    pub offset: i64,
+    /// The broker's append time when the topic uses LogAppendTime, otherwise the create time.
+    pub timestamp: i64,
+}
+
+/// Resolves once the batch holding the record has been acknowledged, or has failed.
+pub struct Delivery(oneshot::Receiver<Result<RecordMetadata, String>>);
+
+impl Future for Delivery {
+    type Output = Result<RecordMetadata, String>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx).map(|result| {
+            result
+                .unwrap_or_else(|_| Err("The producer shut down before sending the record".into()))
+        })
+    }
+}
+
+/// Writes records to the leaders of their partitions. Records are collected into a batch per
+/// partition, and a background task sends the batches that are full or have lingered, one
+/// request per leader. Cloning is cheap and clones share the batches and connections, so
+/// one producer can serve a whole application.
+#[derive(Clone)]
+pub struct Producer {
+    inner: Arc<ProducerInner>,
+}
+
+struct ProducerInner {
+    sender: Arc<Sender>,
+    partitioner: Box<dyn Partitioner>,
+    /// Cancelled when the last clone is dropped; the sender then sends what is queued and
+    /// stops.
+    shutdown: CancellationToken,
+}
+
+impl Drop for ProducerInner {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+/// State shared between the producer handles and the task sending their batches.
+struct Sender {
+    config: ProducerConfig,
+    cluster: Cluster,
+    accumulator: Mutex<RecordAccumulator>,
+    /// Tells the sending task to look for ready batches again.
+    wakeup: Notify,
+    /// Tells flushes a batch has completed.
+    completed: Notify,
+    /// Set when the producer is transactional.
+    transaction: Option<Mutex<TransactionManager>>,
+}
+
+impl Producer {
+    /// Starts the sending task, so it must be called within a tokio runtime. Records are
+    /// partitioned as the Java client does by default. Fails if the config asks for
+    /// idempotence without what it needs. A transactional producer must call
+    /// `init_transactions` before anything else.
+    pub fn new(config: ProducerConfig) -> Result<Self, String> {
+        let partitioner = DefaultPartitioner::new(config.batch_size);
+        Self::with_partitioner(config, Box::new(partitioner))
+    }
+
+    /// Like `new`, with `partitioner` picking the partition of each record.
+    pub fn with_partitioner(
+        mut config: ProducerConfig,
+        partitioner: Box<dyn Partitioner>,
+    ) -> Result<Self, String> {
+        if config.transactional_id.is_some() {
+            config.enable_idempotence = true;
+        }
+        if config.max_in_flight_requests == 0 {
+            return Err("max_in_flight_requests must be at least 1".to_string());
+        }
+        if config.enable_idempotence && config.acks != ACKS_ALL {
+            return Err("An idempotent producer needs acks=-1".to_string());
+        }
+        if config.enable_idempotence && config.max_in_flight_requests > NUM_BATCHES_TO_RETAIN {
+            return Err(format!(
+                "An idempotent producer may have at most {} requests in flight",
+                NUM_BATCHES_TO_RETAIN
+            ));
+        }
+
+        let transaction = config.transactional_id.as_ref().map(|transactional_id| {
+            Mutex::new(TransactionManager::new(
+                transactional_id.clone(),
+                config.transaction_timeout_ms,
+                config.retries,
+                Duration::from_millis(config.retry_backoff_ms),
+            ))
+        });
+        let sender = Arc::new(Sender {
+            cluster: Cluster::new(config.client.clone()),
+            accumulator: Mutex::new(RecordAccumulator::new(
+                config.batch_size,
+                Duration::from_millis(config.linger_ms),
+                config.max_in_flight_requests,
+            )),
+            config,
+            wakeup: Notify::new(),
+            completed: Notify::new(),
+            transaction,
+        });
+        let shutdown = CancellationToken::new();
+        tokio::spawn(sender.clone().run(shutdown.clone()));
+        Ok(Self {
+            inner: Arc::new(ProducerInner {
+                sender,
+                partitioner,
+                shutdown,
+            }),
+        })
+    }
+
+    /// Writes one record and resolves once the leader has acknowledged it as `acks` asks.
+    /// The partitioner picks the partition from the key and value.
+    pub async fn send(
+        &self,
+        topic: &str,
+        key: Option<Vec<u8>>,
+        value: Option<Vec<u8>>,
+        headers: Vec<Header>,
+    ) -> Result<RecordMetadata, String> {
+        self.enqueue(topic, key, value, headers).await?.await
+    }
+
+    /// Adds one record to its partition's batch and returns without waiting for it to be
+    /// sent, so a single task can keep many records in flight.
+    pub async fn enqueue(
+        &self,
+        topic: &str,
+        key: Option<Vec<u8>>,
+        value: Option<Vec<u8>>,
+        headers: Vec<Header>,
+    ) -> Result<Delivery, String> {
+        self.enqueue_to(topic, None, None, key, value, headers)
+            .await
+    }
+
+    /// Like `enqueue`, writing to `partition` instead of the one the partitioner picks, and
+    /// with `timestamp` as the create time instead of now.
+    pub async fn enqueue_to(
+        &self,
+        topic: &str,
+        partition: Option<i32>,
+        timestamp: Option<i64>,
+        key: Option<Vec<u8>>,
+        value: Option<Vec<u8>>,
+        headers: Vec<Header>,
+    ) -> Result<Delivery, String> {
+        let info = self.inner.sender.cluster.topic(topic).await?;
+        if info.partitions.is_empty() {
+            return Err(format!("Topic {} has no partitions", topic));
+        }
+        let partition = partition.unwrap_or_else(|| {
+            self.inner.partitioner.partition(
+                topic,
+                key.as_deref(),
+                value.as_deref(),
+                &info.partitions,
+            )
+        });
+        if partition < 0 || partition as usize >= info.partitions.len() {
+            return Err(format!(
+                "Partition {} of topic {} does not exist, it has {}",
+                partition,
+                topic,
+                info.partitions.len()
+            ));
+        }
+        let topic_partition = TopicPartition::new(topic, partition);
+
+        let sender = &self.inner.sender;
+        // Held until the record is queued, so a commit cannot slip in before it
+        let transaction = match &sender.transaction {
+            Some(transaction) => {
+                let mut transaction = transaction.lock().await;
+                transaction
+                    .add_partition(&sender.cluster, &topic_partition)
+                    .await?;
+                Some(transaction)
+            }
+            None => None,
+        };
+        let delivery = sender.accumulator.lock().await.append(
+            &topic_partition,
+            timestamp.unwrap_or_else(current_time_ms),
+            key,
+            value,
+            headers,
+        );
+        drop(transaction);
+        sender.wakeup.notify_one();
+        Ok(Delivery(delivery))
+    }
+
+    /// Sends every queued record right away, without waiting for `linger_ms`, and waits
+    /// until they have all completed.
+    pub async fn flush(&self) {
+        let sender = &self.inner.sender;
+        sender.accumulator.lock().await.begin_flush();
+        sender.wakeup.notify_one();
+        loop {
+            let completed = sender.completed.notified();
+            tokio::pin!(completed);
+            completed.as_mut().enable();
+            if sender.accumulator.lock().await.is_idle() {
+                break;
+            }
+            completed.await;
+        }
+        sender.accumulator.lock().await.end_flush();
+    }
+
+    /// Gets the producer id of the transactional id, fencing any older producer using it
+    /// and aborting the transaction it left open. Must be called once before the first
+    /// transaction.
+    pub async fn init_transactions(&self) -> Result<(), String> {
+        let sender = &self.inner.sender;
+        let mut transaction = sender.transaction()?.lock().await;
+        let producer = transaction.init(&sender.cluster).await?;
+        sender.accumulator.lock().await.set_producer(Some(producer));
+        Ok(())
+    }
+
+    pub async fn begin_transaction(&self) -> Result<(), String> {
+        self.inner.sender.transaction()?.lock().await.begin()
+    }
+
+    /// Commits `offsets`, the positions of a consumer of `group_id`, with the transaction,
+    /// so records consumed and the records produced from them are committed together.
+    pub async fn send_offsets_to_transaction(
+        &self,
+        offsets: &[(TopicPartition, i64)],
+        group_id: &str,
+    ) -> Result<(), String> {
+        let sender = &self.inner.sender;
+        let mut transaction = sender.transaction()?.lock().await;
+        transaction
+            .send_offsets(&sender.cluster, offsets, group_id)
+            .await
+    }
+
+    /// Sends the transaction's queued records and commits them. Fails without committing
+    /// when any of them failed; the transaction must then be aborted.
+    pub async fn commit_transaction(&self) -> Result<(), String> {
+        let sender = &self.inner.sender;
+        let transaction = sender.transaction()?;
+        transaction.lock().await.check_in_transaction()?;
+        self.flush().await;
+        transaction.lock().await.commit(&sender.cluster).await
+    }
+
+    /// Sends the transaction's queued records and aborts them, so read_committed consumers
+    /// never see them.
+    pub async fn abort_transaction(&self) -> Result<(), String> {
+        let sender = &self.inner.sender;
+        let transaction = sender.transaction()?;
+        self.flush().await;
+        let mut transaction = transaction.lock().await;
+        let producer = transaction.producer();
+        transaction.abort(&sender.cluster).await?;
+        if transaction.producer() != producer {
+            let mut accumulator = sender.accumulator.lock().await;
+            accumulator.set_producer(Some(transaction.producer()));
+        }
+        Ok(())
+    }
+}
+
+impl Sender {
+    /// Sends ready batches until the producer is dropped, then sends the rest and stops.
+    async fn run(self: Arc<Self>, shutdown: CancellationToken) {
+        let mut shutting_down = false;
+        loop {
+            let needs_producer_id = {
+                let mut accumulator = self.accumulator.lock().await;
+                if !shutting_down && shutdown.is_cancelled() {
+                    shutting_down = true;
+                    accumulator.begin_flush();
+                }
+                if shutting_down && accumulator.is_idle() {
+                    return;
+                }
+                // A transactional producer gets its id from init_transactions
+                self.config.enable_idempotence
+                    && self.transaction.is_none()
+                    && accumulator.producer().is_none()
+                    && !accumulator.is_idle()
+            };
+            if needs_producer_id {
+                self.obtain_producer_id().await;
+                continue;
+            }
+
+            let (ready, next_ready_time) = {
+                let mut accumulator = self.accumulator.lock().await;
+                (
+                    accumulator.drain_ready(Instant::now()),
+                    accumulator.next_ready_time(),
+                )
+            };
+            self.send_ready(ready).await;
+
+            let linger = async {
+                match next_ready_time {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                _ = self.wakeup.notified() => {}
+                _ = linger => {}
+                _ = shutdown.cancelled(), if !shutting_down => {}
+            }
+        }
+    }
+
+    fn transaction(&self) -> Result<&Mutex<TransactionManager>, String> {
+        self.transaction
+            .as_ref()
+            .ok_or_else(|| "The producer has no transactional_id".to_string())
+    }
+
+    /// Asks any broker for an idempotent producer id. Queued records fail if the cluster
+    /// refuses one; otherwise it is asked again after `retry_backoff_ms`.
+    async fn obtain_producer_id(&self) {
+        let request = InitProducerIdRequest {
+            transactional_id: None,
+            transaction_timeout_ms: -1,
+        };
+        let response = self
+            .cluster
+            .send_any(
+                INIT_PRODUCER_ID_API_KEY,
+                INIT_PRODUCER_ID_MAX_VERSION,
+                |buf| request.encode(buf, INIT_PRODUCER_ID_MAX_VERSION),
+            )
+            .await
+            .and_then(|mut response| {
+                InitProducerIdResponse::decode(&mut response, INIT_PRODUCER_ID_MAX_VERSION)
+            });
+
+        match response {
+            Ok(response) if response.error_code == ErrorCode::None.code() => {
+                tracing::debug!(
+                    "Producing with producer id {} and epoch {}",
+                    response.producer_id,
+                    response.producer_epoch
+                );
+                self.accumulator
+                    .lock()
+                    .await
+                    .set_producer(Some((response.producer_id, response.producer_epoch)));
+            }
+            Ok(response) if !is_coordinator_unavailable(response.error_code) => {
+                let error = format!(
+                    "Failed to get a producer id: error code {}",
+                    response.error_code
+                );
+                self.accumulator.lock().await.abort(&error);
+                self.completed.notify_waiters();
+            }
+            result => {
+                tracing::warn!(
+                    "Failed to get a producer id, retrying: {}",
+                    result.map_or_else(
+                        |e| e,
+                        |response| format!("error code {}", response.error_code)
+                    )
+                );
+                tokio::time::sleep(Duration::from_millis(self.config.retry_backoff_ms)).await;
+            }
+        }
+    }
+
+    /// Sends one Produce request per leader, each from its own task. A partition with several
+    /// batches ready gets one in each of several requests.
+    async fn send_ready(self: &Arc<Self>, ready: Vec<ProducerBatch>) {
+        let mut requests: Vec<(i32, Vec<ProducerBatch>)> = Vec::new();
+        for batch in ready {
+            let topic_partition = &batch.topic_partition;
+            let leader = match self.cluster.topic(&topic_partition.topic).await {
+                Ok(info) => info
+                    .partitions
+                    .get(topic_partition.partition as usize)
+                    .map_or(NO_LEADER, |partition| partition.leader),
+                Err(e) => {
+                    self.finish(batch, Err(ProduceError::Request(e))).await;
+                    continue;
+                }
+            };
+            if leader == NO_LEADER {
+                let error = ProduceError::Broker(ErrorCode::LeaderNotAvailable.code(), None);
+                self.finish(batch, Err(error)).await;
+                continue;
+            }
+            let request = requests.iter_mut().find(|(broker, batches)| {
+                *broker == leader
+                    && batches
+                        .iter()
+                        .all(|queued| queued.topic_partition != batch.topic_partition)
+            });
+            match request {
+                Some((_, batches)) => batches.push(batch),
+                None => requests.push((leader, vec![batch])),
+            }
+        }
+
+        for (leader, batches) in requests {
+            tokio::spawn(self.clone().produce(leader, batches));
+        }
+    }
+
+    async fn produce(self: Arc<Self>, leader: i32, batches: Vec<ProducerBatch>) {
+        match self.send_produce(leader, &batches).await {
+            Ok(results) => {
+                for batch in batches {
+                    let result = results
+                        .iter()
+                        .find(|(topic_partition, _)| *topic_partition == batch.topic_partition)
+                        .map(|(_, result)| result.clone())
+                        .unwrap_or_else(|| {
+                            Err(ProduceError::Request(format!(
+                                "The response does not cover {}",
+                                batch.topic_partition
+                            )))
+                        });
+                    self.finish(batch, result).await;
+                }
+            }
+            Err(e) => {
+                for batch in batches {
+                    self.finish(batch, Err(ProduceError::Request(e.clone())))
+                        .await;
+                }
+            }
+        }
+    }
+
+    /// Writes the batches to their leader; each partition gets its base offset and log append
+    /// time, or why it failed.
+    async fn send_produce(
+        &self,
+        leader: i32,
+        batches: &[ProducerBatch],
+    ) -> Result<Vec<(TopicPartition, Result<(i64, i64), ProduceError>)>, String> {
+        let config = &self.config;
+        let mut topics: Vec<TopicProduceData> = Vec::new();
+        for batch in batches {
+            let mut records = batch.build(config.compression);
+            if config.transactional_id.is_some() {
+                records.attributes |= TRANSACTIONAL_FLAG_MASK;
+            }
+            let partition = PartitionProduceData {
+                index: batch.topic_partition.partition,
+                records: vec![records],
+                corrupt: None,
+            };
+            match topics
+                .iter_mut()
+                .find(|topic| topic.name == batch.topic_partition.topic)
+            {
+                Some(topic) => topic.partitions.push(partition),
+                None => topics.push(TopicProduceData {
+                    name: batch.topic_partition.topic.clone(),
+                    partitions: vec![partition],
+                }),
+            }
+        }
+        let request = ProduceRequest {
+            transactional_id: config.transactional_id.clone(),
+            acks: config.acks,
+            timeout_ms: config.request_timeout_ms,
+            topics,
+        };
+        let encode = |buf: &mut _| request.encode(buf, PRODUCE_MAX_VERSION);
+
+        let connection = self.cluster.connection(leader).await?;
+        let mut connection = connection.lock().await;
+        if config.acks == ACKS_NONE {
+            connection
+                .send_without_response(PRODUCE_API_KEY, PRODUCE_MAX_VERSION, encode)
+                .await?;
+            return Ok(batches
+                .iter()
+                .map(|batch| (batch.topic_partition.clone(), Ok((-1, -1))))
+                .collect());
+        }
+
+        let mut response = connection
+            .send_request(PRODUCE_API_KEY, PRODUCE_MAX_VERSION, encode)
+            .await?;
+        let response = ProduceResponse::decode(&mut response, PRODUCE_MAX_VERSION)?;
+        let mut results = Vec::new();
+        for topic in response.responses {
+            for partition in topic.partitions {
+                let topic_partition = TopicPartition::new(topic.name.clone(), partition.index);
+                let result = if partition.error_code == ErrorCode::None.code() {
+                    Ok((partition.base_offset, partition.log_append_time_ms))
+                } else {
+                    Err(ProduceError::Broker(
+                        partition.error_code,
+                        partition.error_message,
+                    ))
+                };
+                results.push((topic_partition, result));
+            }
+        }
+        Ok(results)
+    }
+
+    /// Reports the batch's outcome to its records and frees its slot in the partition's
+    /// window, unless the error is worth another attempt; then the batch goes back to the
+    /// front of its partition after a backoff, doubling with each attempt.
+    async fn finish(&self, batch: ProducerBatch, result: Result<(i64, i64), ProduceError>) {
+        let config = &self.config;
+        let topic_partition = batch.topic_partition.clone();
+        let error = match result {
+            Ok((base_offset, log_append_time_ms)) => {
+                batch.complete(base_offset, log_append_time_ms);
+                self.completed(&topic_partition, false).await;
+                return;
+            }
+            // An earlier attempt was written, but is too old for the broker to say where
+            Err(ProduceError::Broker(error_code, _))
+                if config.enable_idempotence
+                    && error_code == ErrorCode::DuplicateSequenceNumber.code() =>
+            {
+                batch.complete(-1, -1);
+                self.completed(&topic_partition, false).await;
+                return;
+            }
+            Err(error) => error,
+        };
+
+        if batch.attempts < config.retries && error.is_retriable(config.enable_idempotence) {
+            tracing::debug!(
+                "Retrying a batch for {} after attempt {}: {}",
+                topic_partition,
+                batch.attempts + 1,
+                error.describe(&topic_partition)
+            );
+            if error.is_stale_metadata()
+                && let Err(e) = self
+                    .cluster
+                    .refresh(std::slice::from_ref(&topic_partition.topic))
+                    .await
+            {
+                tracing::debug!("Failed to refresh metadata for {}: {}", topic_partition, e);
+            }
+            let backoff = config
+                .retry_backoff_ms
+                .saturating_mul(1 << batch.attempts.min(16))
+                .min(config.retry_backoff_max_ms.max(config.retry_backoff_ms));
+            self.accumulator
+                .lock()
+                .await
+                .requeue(batch, Instant::now() + Duration::from_millis(backoff));
+            self.wakeup.notify_one();
+            return;
+        }
+
+        let error_code = match error {
+            ProduceError::Broker(error_code, _) => Some(error_code),
+            ProduceError::Request(_) => None,
+        };
+        let error = error.describe(&topic_partition);
+        batch.fail(&error);
+        // The records' sequences are lost, so the broker would reject those after them. A
+        // transaction gets a new producer when it is aborted instead.
+        let reset_producer = match &self.transaction {
+            Some(transaction) => {
+                transaction.lock().await.fail(&error, error_code);
+                false
+            }
+            None => config.enable_idempotence,
+        };
+        self.completed(&topic_partition, reset_producer).await;
+    }
+
+    async fn completed(&self, topic_partition: &TopicPartition, reset_producer: bool) {
+        {
+            let mut accumulator = self.accumulator.lock().await;
+            accumulator.complete(topic_partition);
+            if reset_producer {
+                accumulator.set_producer(None);
+            }
+        }
+        self.wakeup.notify_one();
+        self.completed.notify_waiters();
+    }
+}
+
+/// Why a batch was not written.
+#[derive(Debug, Clone)]
+enum ProduceError {
+    /// The partition's error code in the response, and its message if any.
+    Broker(i16, Option<String>),
+    /// No answer covers the partition, e.g. because the connection failed.
+    Request(String),
+}
+
+impl ProduceError {
+    /// Whether sending the batch again may succeed. A sequence out of order usually means an
+    /// earlier batch of the window failed and is being retried.
+    fn is_retriable(&self, idempotent: bool) -> bool {
+        match self {
+            ProduceError::Broker(error_code, _) => {
+                is_stale_metadata(*error_code)
+                    || *error_code == ErrorCode::RequestTimedOut.code()
+                    || *error_code == ErrorCode::NotEnoughReplicas.code()
+                    || *error_code == ErrorCode::NotEnoughReplicasAfterAppend.code()
+                    || *error_code == ErrorCode::CorruptMessage.code()
+                    || *error_code == ErrorCode::KafkaStorageError.code()
+                    || (idempotent && *error_code == ErrorCode::OutOfOrderSequenceNumber.code())
+            }
+            ProduceError::Request(_) => true,
+        }
+    }
+
+    fn is_stale_metadata(&self) -> bool {
+        match self {
+            ProduceError::Broker(error_code, _) => is_stale_metadata(*error_code),
+            ProduceError::Request(_) => true,
+        }
+    }
+
+    fn describe(&self, topic_partition: &TopicPartition) -> String {
+        match self {
+            ProduceError::Broker(error_code, message) => format!(
+                "Failed to produce to {}: error code {}{}",
+                topic_partition,
+                error_code,
+                message
+                    .as_ref()
+                    .map(|message| format!(": {}", message))
+                    .unwrap_or_default()
+            ),
+            ProduceError::Request(e) => format!("Failed to produce to {}: {}", topic_partition, e),
+        }
+    }
+}
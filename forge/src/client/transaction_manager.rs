@@ -0,0 +1,470 @@
+use bytes::{Bytes, BytesMut};
+use std::time::Duration;
+
+use crate::client::cluster::{Cluster, is_coordinator_unavailable};
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::protocol::add_offsets_to_txn::{
+    ADD_OFFSETS_TO_TXN_API_KEY, ADD_OFFSETS_TO_TXN_MAX_VERSION, AddOffsetsToTxnRequest,
+    AddOffsetsToTxnResponse,
+};
+use crate::protocol::add_partitions_to_txn::{
+    ADD_PARTITIONS_TO_TXN_API_KEY, ADD_PARTITIONS_TO_TXN_MAX_VERSION, AddPartitionsToTxnRequest,
+    AddPartitionsToTxnResponse, AddPartitionsToTxnTopic,
+};
+use crate::protocol::end_txn::{
+    END_TXN_API_KEY, END_TXN_MAX_VERSION, EndTxnRequest, EndTxnResponse,
+};
+use crate::protocol::init_producer_id::{
+    INIT_PRODUCER_ID_API_KEY, INIT_PRODUCER_ID_MAX_VERSION, InitProducerIdRequest,
+    InitProducerIdResponse,
+};
+use crate::protocol::offset_commit::{OffsetCommitPartitionResponse, OffsetCommitTopicResponse};
+use crate::protocol::txn_offset_commit::{
+    TXN_OFFSET_COMMIT_API_KEY, TXN_OFFSET_COMMIT_MAX_VERSION, TxnOffsetCommitPartition,
+    TxnOffsetCommitRequest, TxnOffsetCommitResponse, TxnOffsetCommitTopic,
+};
+use crate::shared::collections::FlatSet;
+use crate::tools::offset_commit_request;
+
+/// Where a transactional producer is between transactions.
+#[derive(Debug, Clone, PartialEq)]
+enum State {
+    Uninitialized,
+    Ready,
+    InTransaction,
+    /// Something in the transaction failed, so it can only be aborted.
+    Abortable(String),
+    /// A newer instance with the same transactional id fenced this one, or it lost the right
+    /// to use it; the producer can only be dropped.
+    Fatal(String),
+}
+
+/// Drives a transactional producer's side of the transaction APIs against the coordinator of
+/// its transactional id.
+pub struct TransactionManager {
+    transactional_id: String,
+    transaction_timeout_ms: i32,
+    retries: u32,
+    retry_backoff: Duration,
+    state: State,
+    /// The broker coordinating the transactional id, once found.
+    coordinator: Option<i32>,
+    /// The producer id and epoch the coordinator gave this instance.
+    producer: (i64, i16),
+    /// Partitions the ongoing transaction writes to, including the group's offsets partition
+    /// once offsets are sent.
+    partitions: FlatSet<TopicPartition>,
+}
+
+impl TransactionManager {
+    pub fn new(
+        transactional_id: String,
+        transaction_timeout_ms: i32,
+        retries: u32,
+        retry_backoff: Duration,
+    ) -> Self {
+        Self {
+            transactional_id,
+            transaction_timeout_ms,
+            retries,
+            retry_backoff,
+            state: State::Uninitialized,
+            coordinator: None,
+            producer: (-1, -1),
+            partitions: FlatSet::new(),
+        }
+    }
+
+    pub fn transactional_id(&self) -> &str {
+        &self.transactional_id
+    }
+
+    pub fn producer(&self) -> (i64, i16) {
+        self.producer
+    }
+
+    /// Fails unless a transaction is in progress and nothing in it has failed yet.
+    pub fn check_in_transaction(&self) -> Result<(), String> {
+        match &self.state {
+            State::InTransaction => Ok(()),
+            State::Uninitialized => Err("init_transactions has not completed".to_string()),
+            State::Ready => Err("No transaction is in progress".to_string()),
+            State::Abortable(e) => Err(format!("The transaction can only be aborted: {}", e)),
+            State::Fatal(e) => Err(format!("The producer can no longer be used: {}", e)),
+        }
+    }
+
+    /// Makes the transaction abortable only, e.g. because one of its records failed, or the
+    /// producer unusable when `error_code` says a newer instance fenced it.
+    pub fn fail(&mut self, error: &str, error_code: Option<i16>) {
+        if error_code.is_some_and(is_fatal) {
+            self.state = State::Fatal(error.to_string());
+        } else if self.state == State::InTransaction {
+            self.state = State::Abortable(error.to_string());
+        }
+    }
+
+    /// Gets a producer id and epoch for the transactional id, fencing older instances and
+    /// aborting a transaction they left open.
+    pub async fn init(&mut self, cluster: &Cluster) -> Result<(i64, i16), String> {
+        match &self.state {
+            State::InTransaction => {
+                return Err("A transaction is in progress".to_string());
+            }
+            State::Fatal(e) => return Err(format!("The producer can no longer be used: {}", e)),
+            _ => {}
+        }
+
+        let request = InitProducerIdRequest {
+            transactional_id: Some(self.transactional_id.clone()),
+            transaction_timeout_ms: self.transaction_timeout_ms,
+        };
+        let mut attempts = 0;
+        loop {
+            let response = self
+                .send(
+                    cluster,
+                    INIT_PRODUCER_ID_API_KEY,
+                    INIT_PRODUCER_ID_MAX_VERSION,
+                    |buf| request.encode(buf, INIT_PRODUCER_ID_MAX_VERSION),
+                )
+                .await
+                .and_then(|mut response| {
+                    InitProducerIdResponse::decode(&mut response, INIT_PRODUCER_ID_MAX_VERSION)
+                });
+            match response {
+                Ok(response) if response.error_code == ErrorCode::None.code() => {
+                    self.producer = (response.producer_id, response.producer_epoch);
+                    self.state = State::Ready;
+                    self.partitions.clear();
+                    return Ok(self.producer);
+                }
+                response => {
+                    let error = response.map(|response| response.error_code);
+                    self.retry("initialize transactions", error, &mut attempts)
+                        .await?
+                }
+            }
+        }
+    }
+
+    pub fn begin(&mut self) -> Result<(), String> {
+        match &self.state {
+            State::Ready => {
+                self.state = State::InTransaction;
+                Ok(())
+            }
+            State::InTransaction => Err("A transaction is already in progress".to_string()),
+            _ => self.check_in_transaction(),
+        }
+    }
+
+    /// Adds the partition to the transaction the first time it is written to, so the
+    /// coordinator writes a marker there when the transaction ends.
+    pub async fn add_partition(
+        &mut self,
+        cluster: &Cluster,
+        topic_partition: &TopicPartition,
+    ) -> Result<(), String> {
+        self.check_in_transaction()?;
+        if self.partitions.contains(topic_partition) {
+            return Ok(());
+        }
+
+        let request = AddPartitionsToTxnRequest {
+            transactional_id: self.transactional_id.clone(),
+            producer_id: self.producer.0,
+            producer_epoch: self.producer.1,
+            topics: vec![AddPartitionsToTxnTopic {
+                name: topic_partition.topic.clone(),
+                partitions: vec![topic_partition.partition],
+            }],
+        };
+        let mut attempts = 0;
+        loop {
+            let response = self
+                .send(
+                    cluster,
+                    ADD_PARTITIONS_TO_TXN_API_KEY,
+                    ADD_PARTITIONS_TO_TXN_MAX_VERSION,
+                    |buf| request.encode(buf, ADD_PARTITIONS_TO_TXN_MAX_VERSION),
+                )
+                .await
+                .and_then(|mut response| {
+                    AddPartitionsToTxnResponse::decode(
+                        &mut response,
+                        ADD_PARTITIONS_TO_TXN_MAX_VERSION,
+                    )
+                });
+            let error = response.map(|response| {
+                response
+                    .results
+                    .iter()
+                    .flat_map(|topic| &topic.results)
+                    .map(|partition| partition.error_code)
+                    .find(|error_code| *error_code != ErrorCode::None.code())
+                    .unwrap_or(ErrorCode::None.code())
+            });
+            if error == Ok(ErrorCode::None.code()) {
+                self.partitions.insert(topic_partition.clone());
+                return Ok(());
+            }
+            let operation = format!("add {} to the transaction", topic_partition);
+            self.retry(&operation, error, &mut attempts).await?;
+        }
+    }
+
+    /// Makes committing the transaction commit `offsets` for `group_id` too.
+    pub async fn send_offsets(
+        &mut self,
+        cluster: &Cluster,
+        offsets: &[(TopicPartition, i64)],
+        group_id: &str,
+    ) -> Result<(), String> {
+        self.check_in_transaction()?;
+
+        let request = AddOffsetsToTxnRequest {
+            transactional_id: self.transactional_id.clone(),
+            producer_id: self.producer.0,
+            producer_epoch: self.producer.1,
+            group_id: group_id.to_string(),
+        };
+        let mut attempts = 0;
+        loop {
+            let response = self
+                .send(
+                    cluster,
+                    ADD_OFFSETS_TO_TXN_API_KEY,
+                    ADD_OFFSETS_TO_TXN_MAX_VERSION,
+                    |buf| request.encode(buf, ADD_OFFSETS_TO_TXN_MAX_VERSION),
+                )
+                .await
+                .and_then(|mut response| {
+                    AddOffsetsToTxnResponse::decode(&mut response, ADD_OFFSETS_TO_TXN_MAX_VERSION)
+                });
+            match response {
+                Ok(response) if response.error_code == ErrorCode::None.code() => break,
+                response => {
+                    let error = response.map(|response| response.error_code);
+                    let operation =
+                        format!("add the offsets of group {} to the transaction", group_id);
+                    self.retry(&operation, error, &mut attempts).await?;
+                }
+            }
+        }
+
+        let topics = offset_commit_request(group_id, -1, "", offsets)
+            .topics
+            .into_iter()
+            .map(|topic| TxnOffsetCommitTopic {
+                name: topic.name,
+                partitions: topic
+                    .partitions
+                    .into_iter()
+                    .map(|partition| TxnOffsetCommitPartition {
+                        partition_index: partition.partition_index,
+                        committed_offset: partition.committed_offset,
+                        committed_leader_epoch: partition.committed_leader_epoch,
+                        committed_metadata: partition.committed_metadata,
+                    })
+                    .collect(),
+            })
+            .collect();
+        let request = TxnOffsetCommitRequest {
+            transactional_id: self.transactional_id.clone(),
+            group_id: group_id.to_string(),
+            producer_id: self.producer.0,
+            producer_epoch: self.producer.1,
+            topics,
+        };
+        let mut attempts = 0;
+        loop {
+            let response = self.send_txn_offset_commit(cluster, &request).await;
+            let error = response.map(|response| {
+                response
+                    .topics
+                    .iter()
+                    .flat_map(|topic| &topic.partitions)
+                    .map(|partition| partition.error_code)
+                    .find(|error_code| *error_code != ErrorCode::None.code())
+                    .unwrap_or(ErrorCode::None.code())
+            });
+            if error == Ok(ErrorCode::None.code()) {
+                return Ok(());
+            }
+            let operation = format!("commit offsets of group {} in the transaction", group_id);
+            self.retry(&operation, error, &mut attempts).await?;
+        }
+    }
+
+    /// Offsets are committed by the group's coordinator, not the transaction's.
+    async fn send_txn_offset_commit(
+        &self,
+        cluster: &Cluster,
+        request: &TxnOffsetCommitRequest,
+    ) -> Result<TxnOffsetCommitResponse, String> {
+        let coordinator = match cluster.group_coordinator(&request.group_id).await? {
+            Ok(coordinator) => coordinator,
+            Err(error_code) => return Ok(Self::txn_offset_commit_error(error_code)),
+        };
+        let connection = cluster.connection(coordinator).await?;
+        let mut response = connection
+            .lock()
+            .await
+            .send_request(
+                TXN_OFFSET_COMMIT_API_KEY,
+                TXN_OFFSET_COMMIT_MAX_VERSION,
+                |buf| request.encode(buf, TXN_OFFSET_COMMIT_MAX_VERSION),
+            )
+            .await?;
+        TxnOffsetCommitResponse::decode(&mut response, TXN_OFFSET_COMMIT_MAX_VERSION)
+    }
+
+    fn txn_offset_commit_error(error_code: i16) -> TxnOffsetCommitResponse {
+        TxnOffsetCommitResponse {
+            throttle_time_ms: 0,
+            topics: vec![OffsetCommitTopicResponse {
+                name: String::new(),
+                partitions: vec![OffsetCommitPartitionResponse {
+                    partition_index: -1,
+                    error_code,
+                }],
+            }],
+        }
+    }
+
+    pub async fn commit(&mut self, cluster: &Cluster) -> Result<(), String> {
+        self.check_in_transaction()?;
+        self.end(cluster, true).await
+    }
+
+    /// Aborts the transaction. When one of its records failed, the producer is initialized
+    /// again instead, which aborts it as well and restarts the sequence numbers the failure
+    /// left a gap in.
+    pub async fn abort(&mut self, cluster: &Cluster) -> Result<(), String> {
+        match &self.state {
+            State::InTransaction => self.end(cluster, false).await,
+            State::Abortable(_) => self.init(cluster).await.map(|_| ()),
+            _ => self.check_in_transaction(),
+        }
+    }
+
+    async fn end(&mut self, cluster: &Cluster, commit: bool) -> Result<(), String> {
+        // The coordinator knows nothing of a transaction that wrote nowhere
+        if !self.partitions.is_empty() {
+            let request = EndTxnRequest {
+                transactional_id: self.transactional_id.clone(),
+                producer_id: self.producer.0,
+                producer_epoch: self.producer.1,
+                committed: commit,
+            };
+            let mut attempts = 0;
+            loop {
+                let response = self
+                    .send(cluster, END_TXN_API_KEY, END_TXN_MAX_VERSION, |buf| {
+                        request.encode(buf, END_TXN_MAX_VERSION)
+                    })
+                    .await
+                    .and_then(|mut response| {
+                        EndTxnResponse::decode(&mut response, END_TXN_MAX_VERSION)
+                    });
+                match response {
+                    Ok(response) if response.error_code == ErrorCode::None.code() => break,
+                    response => {
+                        let error = response.map(|response| response.error_code);
+                        let operation = if commit {
+                            "commit the transaction"
+                        } else {
+                            "abort the transaction"
+                        };
+                        self.retry(operation, error, &mut attempts).await?;
+                    }
+                }
+            }
+        }
+        self.state = State::Ready;
+        self.partitions.clear();
+        Ok(())
+    }
+
+    /// Sends a request to the transaction coordinator, finding it first when needed.
+    async fn send(
+        &mut self,
+        cluster: &Cluster,
+        api_key: i16,
+        api_version: i16,
+        encode_body: impl FnOnce(&mut BytesMut),
+    ) -> Result<Bytes, String> {
+        let coordinator = match self.coordinator {
+            Some(coordinator) => coordinator,
+            None => match cluster
+                .transaction_coordinator(&self.transactional_id)
+                .await?
+            {
+                Ok(coordinator) => {
+                    self.coordinator = Some(coordinator);
+                    coordinator
+                }
+                Err(error_code) => {
+                    return Err(format!(
+                        "No coordinator for transactional id {}: error code {}",
+                        self.transactional_id, error_code
+                    ));
+                }
+            },
+        };
+        let connection = cluster.connection(coordinator).await?;
+        let mut connection = connection.lock().await;
+        connection
+            .send_request(api_key, api_version, encode_body)
+            .await
+    }
+
+    /// Waits out the backoff when the error is worth another attempt, else returns it. Errors
+    /// reaching the coordinator, or saying it moved, look it up again; those fencing the
+    /// producer leave it unusable.
+    async fn retry(
+        &mut self,
+        operation: &str,
+        error: Result<i16, String>,
+        attempts: &mut u32,
+    ) -> Result<(), String> {
+        let (retriable, message) = match error {
+            Ok(error_code) => {
+                if is_coordinator_unavailable(error_code) {
+                    self.coordinator = None;
+                }
+                let retriable = is_coordinator_unavailable(error_code)
+                    || error_code == ErrorCode::ConcurrentTransactions.code();
+                if is_fatal(error_code) {
+                    self.state = State::Fatal(format!("error code {}", error_code));
+                }
+                let message = format!("Failed to {}: error code {}", operation, error_code);
+                (retriable, message)
+            }
+            Err(e) => {
+                self.coordinator = None;
+                (true, format!("Failed to {}: {}", operation, e))
+            }
+        };
+
+        if retriable && *attempts < self.retries {
+            *attempts += 1;
+            tracing::debug!("{}, retrying", message);
+            tokio::time::sleep(self.retry_backoff).await;
+            return Ok(());
+        }
+        if let State::InTransaction = self.state {
+            self.state = State::Abortable(message.clone());
+        }
+        Err(message)
+    }
+}
+
+/// Errors after which the transactional id is no longer this producer's to use.
+fn is_fatal(error_code: i16) -> bool {
+    error_code == ErrorCode::ProducerFenced.code()
+        || error_code == ErrorCode::InvalidProducerEpoch.code()
+        || error_code == ErrorCode::TransactionalIdAuthorizationFailed.code()
+}
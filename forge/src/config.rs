@@ -0,0 +1,1605 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::application::sasl::{PLAIN_MECHANISM, SUPPORTED_MECHANISMS};
+use crate::core::domain::principal::KafkaPrincipal;
+use crate::shared::collections::FlatMap;
+use crate::shared::constants::{
+    DEFAULT_AUTO_CREATE_TOPICS_ENABLE, DEFAULT_BROKER_HEARTBEAT_INTERVAL_MS,
+    DEFAULT_BROKER_SESSION_TIMEOUT_MS, DEFAULT_CONNECTIONS_MAX_IDLE_MS,
+    DEFAULT_DEAD_LETTER_TOPIC_SUFFIX, DEFAULT_FAILED_AUTHENTICATION_DELAY_MS, DEFAULT_LISTENER,
+    DEFAULT_LOG_DIR, DEFAULT_MAX_CONNECTION_CREATION_RATE, DEFAULT_MAX_CONNECTIONS,
+    DEFAULT_MAX_QUEUED_REQUESTS_PER_CONNECTION, DEFAULT_MESSAGE_MAX_BYTES,
+    DEFAULT_MIN_INSYNC_REPLICAS, DEFAULT_NUM_IO_THREADS, DEFAULT_NUM_PARTITIONS,
+    DEFAULT_NUM_RECOVERY_THREADS_PER_DATA_DIR, DEFAULT_OFFSETS_RETENTION_MINUTES,
+    DEFAULT_OFFSETS_TOPIC_REPLICATION_FACTOR, DEFAULT_PRODUCER_ID_EXPIRATION_MS,
+    DEFAULT_REPLICA_LAG_TIME_MAX_MS, DEFAULT_REPLICATION_FACTOR,
+    DEFAULT_REST_CONSUMER_INSTANCE_TIMEOUT_MS, DEFAULT_REST_MAX_CONNECTIONS,
+    DEFAULT_RETENTION_BYTES, DEFAULT_RETENTION_MS, DEFAULT_SEGMENT_BYTES,
+    DEFAULT_SERVER_LOG_MAX_BYTES, DEFAULT_SERVER_LOG_MAX_FILES, DEFAULT_SERVER_LOG_ROLL_MS,
+    DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_MS, DEFAULT_SOCKET_BUFFER_BYTES,
+    DEFAULT_SOCKET_REQUEST_MAX_BYTES, DEFAULT_SOCKET_REQUEST_READ_TIMEOUT_MS,
+    DEFAULT_TRANSACTION_STATE_REPLICATION_FACTOR, DEFAULT_WASM_INTERCEPTOR_FUEL,
+    DEFAULT_WASM_INTERCEPTOR_MEMORY_MAX_BYTES,
+};
+use crate::shared::logging::parse_directives;
+
+/// Environment variables with this prefix override config file settings: `FORGE_LOG_DIRS`
+/// sets `log.dirs`.
+pub const ENV_PREFIX: &str = "FORGE_";
+/// Names the config file rather than a setting.
+pub const CONFIG_FILE_ENV: &str = "FORGE_CONFIG";
+
+/// Settings that can be changed while the broker runs, through broker config overrides set
+/// with AlterConfigs. Thread counts are absent: tokio runtimes cannot be resized.
+pub const DYNAMIC_BROKER_CONFIGS: &[&str] = &[
+    "log.segment.bytes",
+    "log.retention.bytes",
+    "log.retention.ms",
+    "log.retention.hours",
+    "log.flush.interval.messages",
+    "log.flush.interval.ms",
+    "quota.producer.default",
+    "quota.consumer.default",
+    "log.level",
+];
+
+/// How partition logs are laid out on disk and when they are trimmed and fsynced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogConfig {
+    pub segment_bytes: u32,
+    /// 0 keeps every byte.
+    pub retention_bytes: u64,
+    /// 0 keeps records forever.
+    pub retention_ms: u64,
+    /// Fsync after this many appended messages; `None` leaves flushing to the OS.
+    pub flush_interval_messages: Option<u64>,
+    /// Fsync on the first append this long after the previous flush.
+    pub flush_interval_ms: Option<u64>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            segment_bytes: DEFAULT_SEGMENT_BYTES,
+            retention_bytes: DEFAULT_RETENTION_BYTES,
+            retention_ms: DEFAULT_RETENTION_MS,
+            flush_interval_messages: None,
+            flush_interval_ms: None,
+        }
+    }
+}
+
+/// How the broker accepts and serves client connections.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SocketConfig {
+    pub request_max_bytes: u32,
+    /// Stricter limits by API key. A connection sending a larger request is closed.
+    pub request_max_bytes_per_api: FlatMap<i16, u32>,
+    /// Connections that send part of a request and then nothing more for this long are closed.
+    pub request_read_timeout_ms: u64,
+    /// SO_SNDBUF of accepted sockets; `None` keeps the OS default.
+    pub send_buffer_bytes: Option<u64>,
+    /// SO_RCVBUF of accepted sockets; `None` keeps the OS default.
+    pub receive_buffer_bytes: Option<u64>,
+    /// Disables Nagle's algorithm, so small responses are not held back.
+    pub tcp_nodelay: bool,
+    /// Enables TCP keepalive probes, so dead peers are noticed even without traffic.
+    pub keepalive: bool,
+    /// Expects every connection to start with a PROXY protocol v2 header, as sent by an L4
+    /// load balancer, and uses the client address it carries.
+    pub proxy_protocol: bool,
+    pub max_connections: usize,
+    pub max_connections_per_ip: usize,
+    /// New connections accepted per second; the listener stops accepting while over it.
+    pub max_connection_creation_rate: u32,
+    /// New connections accepted per second from one IP; more are closed right away.
+    pub max_connection_creation_rate_per_ip: u32,
+    /// Connections with no request or response for this long are closed.
+    pub connections_max_idle_ms: u64,
+    /// Requests read from one connection but not yet handled; reading pauses at the limit.
+    pub max_queued_requests_per_connection: usize,
+    /// How long a failed SASL authentication is held before its response is sent and the
+    /// connection closed, slowing down password guessing.
+    pub failed_authentication_delay_ms: u64,
+    /// On shutdown, how long requests already read may take to be answered before their
+    /// connections are dropped.
+    pub shutdown_drain_timeout_ms: u64,
+}
+
+impl Default for SocketConfig {
+    fn default() -> Self {
+        Self {
+            request_max_bytes: DEFAULT_SOCKET_REQUEST_MAX_BYTES,
+            request_max_bytes_per_api: FlatMap::new(),
+            request_read_timeout_ms: DEFAULT_SOCKET_REQUEST_READ_TIMEOUT_MS,
+            send_buffer_bytes: Some(DEFAULT_SOCKET_BUFFER_BYTES),
+            receive_buffer_bytes: Some(DEFAULT_SOCKET_BUFFER_BYTES),
+            tcp_nodelay: true,
+            keepalive: true,
+            proxy_protocol: false,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            max_connections_per_ip: DEFAULT_MAX_CONNECTIONS,
+            max_connection_creation_rate: DEFAULT_MAX_CONNECTION_CREATION_RATE,
+            max_connection_creation_rate_per_ip: DEFAULT_MAX_CONNECTION_CREATION_RATE,
+            connections_max_idle_ms: DEFAULT_CONNECTIONS_MAX_IDLE_MS,
+            max_queued_requests_per_connection: DEFAULT_MAX_QUEUED_REQUESTS_PER_CONNECTION,
+            failed_authentication_delay_ms: DEFAULT_FAILED_AUTHENTICATION_DELAY_MS,
+            shutdown_drain_timeout_ms: DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_MS,
+        }
+    }
+}
+
+/// How clients on the listener authenticate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityProtocol {
+    Plaintext,
+    /// SASL authentication before any other request, without TLS.
+    SaslPlaintext,
+}
+
+impl FromStr for SecurityProtocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "PLAINTEXT" => Ok(Self::Plaintext),
+            "SASL_PLAINTEXT" => Ok(Self::SaslPlaintext),
+            _ => Err(format!("Unsupported security protocol {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for SecurityProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Plaintext => write!(f, "PLAINTEXT"),
+            Self::SaslPlaintext => write!(f, "SASL_PLAINTEXT"),
+        }
+    }
+}
+
+/// How segment files are read and written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IoExecutor {
+    /// tokio::fs.
+    #[default]
+    Tokio,
+    /// std::fs on the blocking pool, each read or write in one call.
+    Blocking,
+    /// Like `Blocking`, with O_DIRECT so segments skip the page cache. Linux only.
+    Direct,
+}
+
+impl FromStr for IoExecutor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tokio" => Ok(Self::Tokio),
+            "blocking" => Ok(Self::Blocking),
+            "direct" => Ok(Self::Direct),
+            _ => Err(format!("Unsupported I/O executor {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for IoExecutor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tokio => write!(f, "tokio"),
+            Self::Blocking => write!(f, "blocking"),
+            Self::Direct => write!(f, "direct"),
+        }
+    }
+}
+
+/// Sends MQTT messages published to topics matching `filter`, which may hold `+` and `#`
+/// wildcards, to the Forge topic `topic`. Written `filter:topic`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MqttTopicMapping {
+    pub filter: String,
+    pub topic: String,
+}
+
+impl FromStr for MqttTopicMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Topic names cannot hold a colon, so the last one separates them
+        let (filter, topic) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("{} is not filter:topic", s))?;
+        let levels: Vec<&str> = filter.split('/').collect();
+        for (i, level) in levels.iter().enumerate() {
+            let wildcard_ok = match *level {
+                "#" => i == levels.len() - 1,
+                "+" => true,
+                level => !level.contains(['#', '+']),
+            };
+            if !wildcard_ok {
+                return Err(format!(
+                    "{} has a wildcard that is not a whole level, or # before the last",
+                    filter
+                ));
+            }
+        }
+        let valid_topic = !topic.is_empty()
+            && topic.len() <= 249
+            && topic
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+        if filter.is_empty() || !valid_topic {
+            return Err(format!("{} is not filter:topic", s));
+        }
+        Ok(Self {
+            filter: filter.to_string(),
+            topic: topic.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for MqttTopicMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.filter, self.topic)
+    }
+}
+
+/// Where the broker writes its own log besides stdout, and when that file is rolled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerLogConfig {
+    /// `None` logs to stdout only.
+    pub file: Option<PathBuf>,
+    /// Gets a copy of the audit events alone, rolled like `file`.
+    pub audit_file: Option<PathBuf>,
+    /// Roll once the file would grow past this size; 0 never rolls on size.
+    pub max_bytes: u64,
+    /// Roll once the file has been written to for this long; 0 never rolls on age.
+    pub roll_ms: u64,
+    /// Rolled files kept next to the active one; older ones are deleted.
+    pub max_files: u32,
+}
+
+impl Default for ServerLogConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            audit_file: None,
+            max_bytes: DEFAULT_SERVER_LOG_MAX_BYTES,
+            roll_ms: DEFAULT_SERVER_LOG_ROLL_MS,
+            max_files: DEFAULT_SERVER_LOG_MAX_FILES,
+        }
+    }
+}
+
+/// SASL settings, used when the listener is `SASL_PLAINTEXT`.
+#[derive(Clone, PartialEq)]
+pub struct SaslConfig {
+    pub enabled_mechanisms: Vec<String>,
+    /// The mechanism this broker uses to authenticate to other brokers.
+    pub inter_broker_mechanism: String,
+    pub inter_broker_username: Option<String>,
+    pub inter_broker_password: Option<String>,
+}
+
+// The broker logs its config at startup, so the password is left out
+impl std::fmt::Debug for SaslConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SaslConfig")
+            .field("enabled_mechanisms", &self.enabled_mechanisms)
+            .field("inter_broker_mechanism", &self.inter_broker_mechanism)
+            .field("inter_broker_username", &self.inter_broker_username)
+            .field(
+                "inter_broker_password",
+                &self.inter_broker_password.as_ref().map(|_| "[hidden]"),
+            )
+            .finish()
+    }
+}
+
+impl Default for SaslConfig {
+    fn default() -> Self {
+        Self {
+            enabled_mechanisms: vec![PLAIN_MECHANISM.to_string()],
+            inter_broker_mechanism: PLAIN_MECHANISM.to_string(),
+            inter_broker_username: None,
+            inter_broker_password: None,
+        }
+    }
+}
+
+/// Faults injected into request handling, so client retries and timeouts can be exercised
+/// against a misbehaving broker. Each applies to one API key and hits the given percentage of
+/// its requests. Only honored by brokers built with the `chaos` feature.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChaosConfig {
+    /// Requests held back before they are handled: percent and milliseconds.
+    pub latency: FlatMap<i16, (f64, u64)>,
+    /// Requests answered with an error code instead of being handled: percent and code.
+    pub errors: FlatMap<i16, (f64, i16)>,
+    /// Requests handled but never answered, by percent.
+    pub drops: FlatMap<i16, f64>,
+}
+
+impl ChaosConfig {
+    pub fn is_empty(&self) -> bool {
+        self.latency.is_empty() && self.errors.is_empty() && self.drops.is_empty()
+    }
+}
+
+/// Static broker settings, read from a Kafka-style `.properties` file at startup. Names follow
+/// Kafka's (`log.dirs`, `log.segment.bytes`, ...) so existing files mostly carry over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokerConfig {
+    pub node_id: i32,
+    /// `host:port` the broker binds to.
+    pub listener: String,
+    pub listener_security_protocol: SecurityProtocol,
+    /// `host:port` other brokers and clients connect to; defaults to `listener`.
+    pub advertised_listener: Option<String>,
+    /// `host:port` of the HTTP endpoint serving metrics and the connection list; `None`
+    /// disables it.
+    pub admin_listener: Option<String>,
+    /// `host:port` of the HTTP REST proxy for producing and consuming with JSON; `None`
+    /// disables it. Unless rest.basic.auth.enable is set, every request acts as the
+    /// ANONYMOUS principal, so anyone reaching it has what ACLs grant ANONYMOUS, or the whole
+    /// cluster without an authorizer.
+    pub rest_listener: Option<String>,
+    /// Requires HTTP Basic credentials on the REST proxy, checked against the credentials
+    /// file as a PLAIN password or a SCRAM user; requests act as that user. Basic credentials
+    /// cross the network in the clear.
+    pub rest_basic_auth_enable: bool,
+    /// A REST consumer instance unused for this long is deleted.
+    pub rest_consumer_instance_timeout_ms: u64,
+    /// REST connections served at once; the proxy closes new ones beyond this.
+    pub rest_max_connections: usize,
+    /// `host:port` of the MQTT listener whose publishes are appended to Forge topics; `None`
+    /// disables it.
+    pub mqtt_listener: Option<String>,
+    /// Tried in order; the first that matches an MQTT topic picks the Forge topic.
+    pub mqtt_topic_mappings: Vec<MqttTopicMapping>,
+    pub rack: Option<String>,
+    pub log_dir: PathBuf,
+    pub log: LogConfig,
+    pub socket: SocketConfig,
+    pub sasl: SaslConfig,
+    /// Worker threads of the runtime that handles requests, apart from the one serving sockets.
+    pub num_io_threads: usize,
+    /// Partition logs recovered at once at startup.
+    pub num_recovery_threads_per_data_dir: usize,
+    pub log_io_executor: IoExecutor,
+    pub min_insync_replicas: usize,
+    pub replica_lag_time_max_ms: i64,
+    /// A partition forgets a producer that wrote nothing to it for this long, and with it
+    /// the sequences its retries are checked against.
+    pub producer_id_expiration_ms: i64,
+    /// Committed offsets of a group are dropped once it has been empty for this long, and
+    /// those committed without a group this long after their commit.
+    pub offsets_retention_minutes: i64,
+    pub num_partitions: i32,
+    pub default_replication_factor: i16,
+    pub auto_create_topics_enable: bool,
+    pub offsets_topic_replication_factor: i16,
+    pub transaction_state_replication_factor: i16,
+    pub broker_heartbeat_interval_ms: u64,
+    pub broker_session_timeout_ms: i64,
+    pub authorizer_enable: bool,
+    pub super_users: Vec<KafkaPrincipal>,
+    pub allow_everyone_if_no_acl_found: bool,
+    /// Bytes per second any producer may send unless a more specific quota applies.
+    pub quota_producer_default: Option<f64>,
+    /// Bytes per second any consumer may fetch unless a more specific quota applies.
+    pub quota_consumer_default: Option<f64>,
+    /// `tracing` filter directives; `None` keeps `RUST_LOG` or the built-in default.
+    pub log_level: Option<String>,
+    /// OTLP/gRPC collector, e.g. `http://localhost:4317`, that receives request traces. Needs
+    /// the `otel` build feature.
+    pub otlp_endpoint: Option<String>,
+    /// WASM modules run, in order, over the records clients produce and consume. Needs the
+    /// `wasm` build feature.
+    pub wasm_interceptors: Vec<PathBuf>,
+    /// Fuel, roughly instructions, a WASM interceptor may burn on one record.
+    pub wasm_interceptor_fuel: u64,
+    pub wasm_interceptor_memory_max_bytes: usize,
+    /// Produced batches larger than this are refused with MESSAGE_TOO_LARGE.
+    pub message_max_bytes: usize,
+    /// Appends the batches a producer had refused, for being corrupt, too large or rejected
+    /// by an interceptor, to `<topic><dead_letter_topic_suffix>` before answering. Those over
+    /// message.max.bytes keep only their dead letter headers.
+    pub dead_letter_enable: bool,
+    pub dead_letter_topic_suffix: String,
+    pub server_log: ServerLogConfig,
+    pub chaos: ChaosConfig,
+}
+
+impl Default for BrokerConfig {
+    fn default() -> Self {
+        Self {
+            node_id: 1,
+            listener: DEFAULT_LISTENER.to_string(),
+            listener_security_protocol: SecurityProtocol::Plaintext,
+            advertised_listener: None,
+            admin_listener: None,
+            rest_listener: None,
+            rest_basic_auth_enable: false,
+            rest_consumer_instance_timeout_ms: DEFAULT_REST_CONSUMER_INSTANCE_TIMEOUT_MS,
+            rest_max_connections: DEFAULT_REST_MAX_CONNECTIONS,
+            mqtt_listener: None,
+            mqtt_topic_mappings: Vec::new(),
+            rack: None,
+            log_dir: PathBuf::from(DEFAULT_LOG_DIR),
+            log: LogConfig::default(),
+            socket: SocketConfig::default(),
+            sasl: SaslConfig::default(),
+            num_io_threads: DEFAULT_NUM_IO_THREADS,
+            num_recovery_threads_per_data_dir: DEFAULT_NUM_RECOVERY_THREADS_PER_DATA_DIR,
+            log_io_executor: IoExecutor::default(),
+            min_insync_replicas: DEFAULT_MIN_INSYNC_REPLICAS,
+            replica_lag_time_max_ms: DEFAULT_REPLICA_LAG_TIME_MAX_MS,
+            producer_id_expiration_ms: DEFAULT_PRODUCER_ID_EXPIRATION_MS,
+            offsets_retention_minutes: DEFAULT_OFFSETS_RETENTION_MINUTES,
+            num_partitions: DEFAULT_NUM_PARTITIONS,
+            default_replication_factor: DEFAULT_REPLICATION_FACTOR,
+            auto_create_topics_enable: DEFAULT_AUTO_CREATE_TOPICS_ENABLE,
+            offsets_topic_replication_factor: DEFAULT_OFFSETS_TOPIC_REPLICATION_FACTOR,
+            transaction_state_replication_factor: DEFAULT_TRANSACTION_STATE_REPLICATION_FACTOR,
+            broker_heartbeat_interval_ms: DEFAULT_BROKER_HEARTBEAT_INTERVAL_MS,
+            broker_session_timeout_ms: DEFAULT_BROKER_SESSION_TIMEOUT_MS,
+            authorizer_enable: false,
+            super_users: Vec::new(),
+            allow_everyone_if_no_acl_found: false,
+            quota_producer_default: None,
+            quota_consumer_default: None,
+            log_level: None,
+            otlp_endpoint: None,
+            wasm_interceptors: Vec::new(),
+            wasm_interceptor_fuel: DEFAULT_WASM_INTERCEPTOR_FUEL,
+            wasm_interceptor_memory_max_bytes: DEFAULT_WASM_INTERCEPTOR_MEMORY_MAX_BYTES,
+            message_max_bytes: DEFAULT_MESSAGE_MAX_BYTES,
+            dead_letter_enable: false,
+            dead_letter_topic_suffix: DEFAULT_DEAD_LETTER_TOPIC_SUFFIX.to_string(),
+            server_log: ServerLogConfig::default(),
+            chaos: ChaosConfig::default(),
+        }
+    }
+}
+
+fn parse<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| {
+        format!(
+            "Invalid value {} for {}: expected {}",
+            value,
+            name,
+            std::any::type_name::<T>()
+        )
+    })
+}
+
+/// Kafka writes optional sizes as -1.
+/// Splits a comma separated list whose entries have the colon separated fields of `format`.
+fn parse_entries<'a>(
+    name: &str,
+    value: &'a str,
+    format: &str,
+) -> Result<Vec<Vec<&'a str>>, String> {
+    let fields = format.split(':').count();
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let parts: Vec<&str> = entry.split(':').map(str::trim).collect();
+            if parts.len() == fields {
+                Ok(parts)
+            } else {
+                Err(format!(
+                    "Invalid entry {} in {}: expected {}",
+                    entry, name, format
+                ))
+            }
+        })
+        .collect()
+}
+
+fn parse_optional_u64(name: &str, value: &str) -> Result<Option<u64>, String> {
+    let value: i64 = parse(name, value)?;
+    Ok((value >= 0).then_some(value as u64))
+}
+
+/// Accepts `host:port`, which is plaintext, as well as Kafka's `PLAINTEXT://host:port`.
+fn parse_listener(name: &str, value: &str) -> Result<(SecurityProtocol, String), String> {
+    let (protocol, address) = match value.split_once("://") {
+        Some((protocol, address)) => (parse(name, protocol)?, address),
+        None => (SecurityProtocol::Plaintext, value),
+    };
+    if address.contains(',') {
+        return Err(format!("Only one listener is supported in {}", name));
+    }
+    match address.rsplit_once(':') {
+        Some((_, port)) if port.parse::<u16>().is_ok() => Ok((protocol, address.to_string())),
+        _ => Err(format!(
+            "Invalid value {} for {}: expected host:port",
+            value, name
+        )),
+    }
+}
+
+/// Splits `.properties` contents into `(name, value)` pairs in file order. Lines starting with
+/// `#` or `!` are comments; the first `=` or `:` separates name and value.
+pub fn parse_properties(contents: &str) -> Result<Vec<(String, String)>, String> {
+    let mut properties = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+        let Some(separator) = line.find(['=', ':']) else {
+            return Err(format!("Line {}: expected name=value", index + 1));
+        };
+        properties.push((
+            line[..separator].trim().to_string(),
+            line[separator + 1..].trim().to_string(),
+        ));
+    }
+    Ok(properties)
+}
+
+/// Picks the `FORGE_*` variables out of `vars` as `(name, value)` settings, turning
+/// `FORGE_LOG_RETENTION_MS` into `log.retention.ms`.
+pub fn env_properties(vars: impl IntoIterator<Item = (String, String)>) -> Vec<(String, String)> {
+    vars.into_iter()
+        .filter(|(key, _)| key != CONFIG_FILE_ENV)
+        .filter_map(|(key, value)| {
+            let name = key.strip_prefix(ENV_PREFIX)?;
+            Some((name.to_lowercase().replace('_', "."), value))
+        })
+        .collect()
+}
+
+impl BrokerConfig {
+    /// Builds the config from, lowest precedence first: the defaults, the properties file at
+    /// `path`, the `FORGE_*` variables of `env` and the command-line `overrides`, then
+    /// validates it. The error lists every problem found, not just the first.
+    pub async fn load_layered(
+        path: Option<&Path>,
+        env: impl IntoIterator<Item = (String, String)>,
+        overrides: &[(String, String)],
+    ) -> Result<Self, String> {
+        let env = env_properties(env);
+        let mut config = Self::default();
+        let mut problems = Vec::new();
+        if let Some(path) = path {
+            let contents = tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| format!("IO error when reading {}: {}", path.display(), e))?;
+            match parse_properties(&contents) {
+                Ok(properties) => {
+                    config.set_all(&properties, &path.display().to_string(), &mut problems)
+                }
+                Err(e) => problems.push(format!("{} (in {})", e, path.display())),
+            }
+        }
+        config.set_all(&env, "environment", &mut problems);
+        config.set_all(overrides, "command line", &mut problems);
+        problems.extend(config.validate().await);
+
+        if problems.is_empty() {
+            return Ok(config);
+        }
+        Err(format!(
+            "Found {} config problem(s):\n  - {}",
+            problems.len(),
+            problems.join("\n  - ")
+        ))
+    }
+
+    fn set_all(
+        &mut self,
+        properties: &[(String, String)],
+        source: &str,
+        problems: &mut Vec<String>,
+    ) {
+        for (name, value) in properties {
+            if let Err(e) = self.set(name, value) {
+                problems.push(format!("{} (from {})", e, source));
+            }
+        }
+    }
+
+    /// Checks that don't touch the disk: value ranges and settings that contradict each other.
+    /// Each entry names the setting, the problem and how to fix it.
+    pub fn check(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut require = |ok: bool, name: &str, problem: String, fix: &str| {
+            if !ok {
+                problems.push(format!("{}: {}; {}", name, problem, fix));
+            }
+        };
+
+        require(
+            self.node_id >= 0,
+            "node.id",
+            format!("{} is negative", self.node_id),
+            "use a unique id of 0 or more",
+        );
+        if let Some(advertised) = &self.advertised_listener {
+            let (host, port) = self.advertised_host_port();
+            require(
+                !matches!(host.as_str(), "" | "0.0.0.0" | "::" | "[::]") && port > 0,
+                "advertised.listeners",
+                format!("{} is not an address clients can connect to", advertised),
+                "set it to this broker's host name and a non-zero port",
+            );
+        }
+        require(
+            self.log.segment_bytes > 0,
+            "log.segment.bytes",
+            "must be positive".to_string(),
+            "use e.g. 1073741824 (1 GiB)",
+        );
+        require(
+            self.log.retention_bytes == 0
+                || self.log.segment_bytes as u64 <= self.log.retention_bytes,
+            "log.segment.bytes",
+            format!(
+                "{} exceeds log.retention.bytes {}, and the active segment is never deleted",
+                self.log.segment_bytes, self.log.retention_bytes
+            ),
+            "lower log.segment.bytes or raise log.retention.bytes",
+        );
+        require(
+            self.socket.request_max_bytes > 0,
+            "socket.request.max.bytes",
+            "must be positive".to_string(),
+            "use e.g. 104857600 (100 MiB)",
+        );
+        require(
+            self.socket.request_read_timeout_ms > 0,
+            "socket.request.read.timeout.ms",
+            "must be positive".to_string(),
+            "use e.g. 30000 (30 seconds)",
+        );
+        for (api_key, max_bytes) in self.socket.request_max_bytes_per_api.iter() {
+            require(
+                *max_bytes >= 8,
+                "socket.request.max.bytes.per.api",
+                format!(
+                    "{} for API key {} is smaller than a request header",
+                    max_bytes, api_key
+                ),
+                "use at least 8",
+            );
+        }
+        for (name, size) in [
+            ("socket.send.buffer.bytes", self.socket.send_buffer_bytes),
+            (
+                "socket.receive.buffer.bytes",
+                self.socket.receive_buffer_bytes,
+            ),
+        ] {
+            require(
+                size != Some(0),
+                name,
+                "must be positive".to_string(),
+                "use e.g. 102400, or -1 for the OS default",
+            );
+        }
+        require(
+            self.socket.connections_max_idle_ms > 0,
+            "connections.max.idle.ms",
+            "must be positive".to_string(),
+            "use e.g. 600000 (10 minutes)",
+        );
+        if self.listener_security_protocol == SecurityProtocol::SaslPlaintext {
+            require(
+                !self.sasl.enabled_mechanisms.is_empty(),
+                "sasl.enabled.mechanisms",
+                "is empty on a SASL_PLAINTEXT listener".to_string(),
+                "enable e.g. PLAIN",
+            );
+            for mechanism in &self.sasl.enabled_mechanisms {
+                require(
+                    SUPPORTED_MECHANISMS.contains(&mechanism.as_str()),
+                    "sasl.enabled.mechanisms",
+                    format!("{} is not supported", mechanism),
+                    &format!("use one of {}", SUPPORTED_MECHANISMS.join(", ")),
+                );
+            }
+            require(
+                self.sasl
+                    .enabled_mechanisms
+                    .contains(&self.sasl.inter_broker_mechanism),
+                "sasl.mechanism.inter.broker.protocol",
+                format!(
+                    "{} is not in sasl.enabled.mechanisms",
+                    self.sasl.inter_broker_mechanism
+                ),
+                "enable it, or pick an enabled mechanism",
+            );
+            require(
+                self.sasl.inter_broker_username.is_some()
+                    && self.sasl.inter_broker_password.is_some(),
+                "sasl.inter.broker.username",
+                "brokers on a SASL_PLAINTEXT listener must authenticate to each other".to_string(),
+                "set sasl.inter.broker.username and sasl.inter.broker.password",
+            );
+        }
+        require(
+            self.socket.max_queued_requests_per_connection >= 1,
+            "max.queued.requests.per.connection",
+            "must be at least 1".to_string(),
+            "use e.g. 32",
+        );
+        require(
+            self.socket.max_connections_per_ip >= 1,
+            "max.connections.per.ip",
+            "must be at least 1".to_string(),
+            "raise it, or leave it unset for no limit",
+        );
+        require(
+            self.socket.max_connections_per_ip <= self.socket.max_connections,
+            "max.connections.per.ip",
+            format!(
+                "{} exceeds max.connections {}",
+                self.socket.max_connections_per_ip, self.socket.max_connections
+            ),
+            "lower max.connections.per.ip or raise max.connections",
+        );
+        for (name, rate) in [
+            (
+                "max.connection.creation.rate",
+                self.socket.max_connection_creation_rate,
+            ),
+            (
+                "max.connection.creation.rate.per.ip",
+                self.socket.max_connection_creation_rate_per_ip,
+            ),
+        ] {
+            require(
+                rate >= 1,
+                name,
+                "must be at least 1".to_string(),
+                "raise it, or leave it unset for no limit",
+            );
+        }
+        require(
+            self.num_io_threads >= 1,
+            "num.io.threads",
+            "must be at least 1".to_string(),
+            "use e.g. 8",
+        );
+        require(
+            self.num_recovery_threads_per_data_dir >= 1,
+            "num.recovery.threads.per.data.dir",
+            "must be at least 1".to_string(),
+            "use e.g. 4",
+        );
+        require(
+            self.min_insync_replicas >= 1,
+            "min.insync.replicas",
+            "must be at least 1".to_string(),
+            "set it to 1, or to 2 with a replication factor of 3",
+        );
+        require(
+            self.min_insync_replicas <= self.default_replication_factor.max(0) as usize,
+            "min.insync.replicas",
+            format!(
+                "{} exceeds default.replication.factor {}, so acks=all produce to new topics always fails",
+                self.min_insync_replicas, self.default_replication_factor
+            ),
+            "lower min.insync.replicas or raise default.replication.factor",
+        );
+        require(
+            self.mqtt_listener.is_none() || !self.mqtt_topic_mappings.is_empty(),
+            "mqtt.topic.mappings",
+            "is empty, so the MQTT listener would drop every message".to_string(),
+            "map MQTT topic filters to topics, e.g. sensors/#:sensors",
+        );
+        require(
+            self.rest_consumer_instance_timeout_ms > 0,
+            "rest.consumer.instance.timeout.ms",
+            "must be positive".to_string(),
+            "use e.g. 300000",
+        );
+        require(
+            self.rest_max_connections > 0,
+            "rest.max.connections",
+            "must be positive".to_string(),
+            "use e.g. 1000",
+        );
+        require(
+            self.replica_lag_time_max_ms > 0,
+            "replica.lag.time.max.ms",
+            "must be positive".to_string(),
+            "use e.g. 30000",
+        );
+        require(
+            self.producer_id_expiration_ms > 0,
+            "producer.id.expiration.ms",
+            "must be positive".to_string(),
+            "use e.g. 86400000",
+        );
+        require(
+            self.offsets_retention_minutes > 0,
+            "offsets.retention.minutes",
+            "must be positive".to_string(),
+            "use e.g. 10080",
+        );
+        require(
+            self.num_partitions >= 1,
+            "num.partitions",
+            format!("{} is below 1", self.num_partitions),
+            "use 1 or more",
+        );
+        for (name, replication_factor) in [
+            (
+                "default.replication.factor",
+                self.default_replication_factor,
+            ),
+            (
+                "offsets.topic.replication.factor",
+                self.offsets_topic_replication_factor,
+            ),
+            (
+                "transaction.state.log.replication.factor",
+                self.transaction_state_replication_factor,
+            ),
+        ] {
+            require(
+                replication_factor >= 1,
+                name,
+                format!("{} is below 1", replication_factor),
+                "use 1 for a single broker, 3 in production",
+            );
+        }
+        require(
+            (self.broker_heartbeat_interval_ms as i64) < self.broker_session_timeout_ms,
+            "broker.heartbeat.interval.ms",
+            format!(
+                "{} is not below broker.session.timeout.ms {}, so the broker would be fenced between heartbeats",
+                self.broker_heartbeat_interval_ms, self.broker_session_timeout_ms
+            ),
+            "keep the interval well under the timeout, e.g. 2000 and 9000",
+        );
+        for (name, quota) in [
+            ("quota.producer.default", self.quota_producer_default),
+            ("quota.consumer.default", self.quota_consumer_default),
+        ] {
+            require(
+                quota.is_none_or(|quota| quota >= 0.0),
+                name,
+                "is negative".to_string(),
+                "use a rate in bytes per second, or remove it for no quota",
+            );
+        }
+        if let Some(endpoint) = &self.otlp_endpoint {
+            require(
+                endpoint.starts_with("http://") || endpoint.starts_with("https://"),
+                "otlp.endpoint",
+                format!("{} is not an http(s) URL", endpoint),
+                "use the collector's gRPC address, e.g. http://localhost:4317",
+            );
+            require(
+                cfg!(feature = "otel"),
+                "otlp.endpoint",
+                "this broker was built without OpenTelemetry support".to_string(),
+                "rebuild with --features otel, or remove the setting",
+            );
+        }
+        if !self.wasm_interceptors.is_empty() {
+            require(
+                cfg!(feature = "wasm"),
+                "wasm.interceptors",
+                "this broker was built without WASM support".to_string(),
+                "rebuild with --features wasm, or remove the setting",
+            );
+            require(
+                self.wasm_interceptor_fuel > 0,
+                "wasm.interceptor.fuel",
+                "must be positive".to_string(),
+                "use e.g. 10000000",
+            );
+            require(
+                self.wasm_interceptor_memory_max_bytes > 0,
+                "wasm.interceptor.memory.max.bytes",
+                "must be positive".to_string(),
+                "use e.g. 16777216",
+            );
+        }
+        require(
+            self.message_max_bytes > 0,
+            "message.max.bytes",
+            "must be positive".to_string(),
+            "use e.g. 1048588",
+        );
+        require(
+            !self.dead_letter_enable || !self.dead_letter_topic_suffix.is_empty(),
+            "dead.letter.topic.suffix",
+            "must not be empty".to_string(),
+            "use e.g. .dlq",
+        );
+        let percents = self
+            .chaos
+            .latency
+            .iter()
+            .map(|(api_key, (percent, _))| ("chaos.latency.ms", api_key, percent))
+            .chain(
+                self.chaos
+                    .errors
+                    .iter()
+                    .map(|(api_key, (percent, _))| ("chaos.error.codes", api_key, percent)),
+            )
+            .chain(
+                self.chaos
+                    .drops
+                    .iter()
+                    .map(|(api_key, percent)| ("chaos.dropped.responses", api_key, percent)),
+            );
+        for (name, api_key, percent) in percents {
+            require(
+                (0.0..=100.0).contains(percent),
+                name,
+                format!("{} for API key {} is not a percentage", percent, api_key),
+                "use a value from 0 to 100",
+            );
+        }
+        require(
+            self.chaos.is_empty() || cfg!(feature = "chaos"),
+            "chaos.*",
+            "this broker was built without fault injection".to_string(),
+            "rebuild with --features chaos, or remove the settings",
+        );
+        problems
+    }
+
+    /// `check` plus whether the log directory can be created and written to.
+    pub async fn validate(&self) -> Vec<String> {
+        let mut problems = self.check();
+        if let Err(e) = Self::check_writable(&self.log_dir).await {
+            problems.push(format!(
+                "log.dirs: {} is not writable ({}); create it and give the broker's user write access, or point log.dirs elsewhere",
+                self.log_dir.display(),
+                e
+            ));
+        }
+        problems
+    }
+
+    async fn check_writable(dir: &Path) -> std::io::Result<()> {
+        tokio::fs::create_dir_all(dir).await?;
+        let probe = dir.join(".forge-write-probe");
+        tokio::fs::write(&probe, b"").await?;
+        tokio::fs::remove_file(&probe).await
+    }
+
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| format!("IO error when reading {}: {}", path.display(), e))?;
+        Self::from_properties(&contents)
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))
+    }
+
+    pub fn from_properties(contents: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        for (name, value) in parse_properties(contents)? {
+            config.set(&name, &value)?;
+        }
+        Ok(config)
+    }
+
+    /// Applies one setting by its Kafka name. Unknown names are logged and ignored, as a file
+    /// shared with Kafka brokers may carry settings this broker does not have.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "node.id" | "broker.id" => self.node_id = parse(name, value)?,
+            "listeners" => {
+                (self.listener_security_protocol, self.listener) = parse_listener(name, value)?
+            }
+            "advertised.listeners" => {
+                self.advertised_listener = Some(parse_listener(name, value)?.1)
+            }
+            "admin.listener" => {
+                self.admin_listener = (!value.is_empty()).then(|| value.to_string())
+            }
+            "rest.listener" => self.rest_listener = (!value.is_empty()).then(|| value.to_string()),
+            "rest.basic.auth.enable" => self.rest_basic_auth_enable = parse(name, value)?,
+            "rest.consumer.instance.timeout.ms" => {
+                self.rest_consumer_instance_timeout_ms = parse(name, value)?
+            }
+            "rest.max.connections" => self.rest_max_connections = parse(name, value)?,
+            "mqtt.listener" => self.mqtt_listener = (!value.is_empty()).then(|| value.to_string()),
+            "mqtt.topic.mappings" => {
+                self.mqtt_topic_mappings = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|mapping| !mapping.is_empty())
+                    .map(|mapping| {
+                        mapping
+                            .parse()
+                            .map_err(|e| format!("Invalid value for {}: {}", name, e))
+                    })
+                    .collect::<Result<_, String>>()?
+            }
+            "broker.rack" => self.rack = (!value.is_empty()).then(|| value.to_string()),
+            "log.dirs" | "log.dir" => {
+                if value.contains(',') {
+                    return Err(format!("Only one log directory is supported in {}", name));
+                }
+                self.log_dir = PathBuf::from(value);
+            }
+            "log.segment.bytes" => self.log.segment_bytes = parse(name, value)?,
+            "log.retention.bytes" => {
+                self.log.retention_bytes = parse_optional_u64(name, value)?.unwrap_or(0)
+            }
+            "log.retention.ms" => {
+                self.log.retention_ms = parse_optional_u64(name, value)?.unwrap_or(0)
+            }
+            "log.retention.hours" => {
+                let hours: u64 = parse(name, value)?;
+                self.log.retention_ms = hours * 60 * 60 * 1000;
+            }
+            "log.flush.interval.messages" => {
+                self.log.flush_interval_messages = parse_optional_u64(name, value)?
+            }
+            "log.flush.interval.ms" => {
+                self.log.flush_interval_ms = parse_optional_u64(name, value)?
+            }
+            "socket.request.max.bytes" => self.socket.request_max_bytes = parse(name, value)?,
+            "socket.request.read.timeout.ms" => {
+                self.socket.request_read_timeout_ms = parse(name, value)?
+            }
+            "socket.request.max.bytes.per.api" => {
+                let mut limits = FlatMap::new();
+                for limit in value.split(',').map(str::trim).filter(|l| !l.is_empty()) {
+                    let Some((api_key, max_bytes)) = limit.split_once(':') else {
+                        return Err(format!(
+                            "Invalid limit {} in {}: expected <api key>:<bytes>",
+                            limit, name
+                        ));
+                    };
+                    limits.insert(parse(name, api_key.trim())?, parse(name, max_bytes.trim())?);
+                }
+                self.socket.request_max_bytes_per_api = limits;
+            }
+            "socket.send.buffer.bytes" => {
+                self.socket.send_buffer_bytes = parse_optional_u64(name, value)?
+            }
+            "socket.receive.buffer.bytes" => {
+                self.socket.receive_buffer_bytes = parse_optional_u64(name, value)?
+            }
+            "socket.tcp.nodelay" => self.socket.tcp_nodelay = parse(name, value)?,
+            "socket.keepalive.enable" => self.socket.keepalive = parse(name, value)?,
+            "proxy.protocol.enable" => self.socket.proxy_protocol = parse(name, value)?,
+            "max.connections" => self.socket.max_connections = parse(name, value)?,
+            "max.connections.per.ip" => self.socket.max_connections_per_ip = parse(name, value)?,
+            "max.connection.creation.rate" => {
+                self.socket.max_connection_creation_rate = parse(name, value)?
+            }
+            "max.connection.creation.rate.per.ip" => {
+                self.socket.max_connection_creation_rate_per_ip = parse(name, value)?
+            }
+            "connections.max.idle.ms" => self.socket.connections_max_idle_ms = parse(name, value)?,
+            "max.queued.requests.per.connection" => {
+                self.socket.max_queued_requests_per_connection = parse(name, value)?
+            }
+            "connection.failed.authentication.delay.ms" => {
+                self.socket.failed_authentication_delay_ms = parse(name, value)?
+            }
+            "shutdown.drain.timeout.ms" => {
+                self.socket.shutdown_drain_timeout_ms = parse(name, value)?
+            }
+            "sasl.enabled.mechanisms" => {
+                self.sasl.enabled_mechanisms = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|mechanism| !mechanism.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }
+            "sasl.mechanism.inter.broker.protocol" => {
+                self.sasl.inter_broker_mechanism = value.to_string()
+            }
+            "sasl.inter.broker.username" => {
+                self.sasl.inter_broker_username = (!value.is_empty()).then(|| value.to_string())
+            }
+            "sasl.inter.broker.password" => {
+                self.sasl.inter_broker_password = (!value.is_empty()).then(|| value.to_string())
+            }
+            "num.io.threads" => self.num_io_threads = parse(name, value)?,
+            "num.recovery.threads.per.data.dir" => {
+                self.num_recovery_threads_per_data_dir = parse(name, value)?
+            }
+            "log.io.executor" => self.log_io_executor = parse(name, value)?,
+            "min.insync.replicas" => self.min_insync_replicas = parse(name, value)?,
+            "replica.lag.time.max.ms" => self.replica_lag_time_max_ms = parse(name, value)?,
+            "producer.id.expiration.ms" => self.producer_id_expiration_ms = parse(name, value)?,
+            "offsets.retention.minutes" => self.offsets_retention_minutes = parse(name, value)?,
+            "num.partitions" => self.num_partitions = parse(name, value)?,
+            "default.replication.factor" => self.default_replication_factor = parse(name, value)?,
+            "auto.create.topics.enable" => self.auto_create_topics_enable = parse(name, value)?,
+            "offsets.topic.replication.factor" => {
+                self.offsets_topic_replication_factor = parse(name, value)?
+            }
+            "transaction.state.log.replication.factor" => {
+                self.transaction_state_replication_factor = parse(name, value)?
+            }
+            "broker.heartbeat.interval.ms" => {
+                self.broker_heartbeat_interval_ms = parse(name, value)?
+            }
+            "broker.session.timeout.ms" => self.broker_session_timeout_ms = parse(name, value)?,
+            "authorizer.enable" => self.authorizer_enable = parse(name, value)?,
+            "super.users" => {
+                self.super_users = value
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|user| !user.is_empty())
+                    .map(|user| match user.split_once(':') {
+                        Some((principal_type, name)) => Ok(KafkaPrincipal {
+                            principal_type: principal_type.to_string(),
+                            name: name.to_string(),
+                        }),
+                        None => Err(format!("Invalid principal {} in super.users", user)),
+                    })
+                    .collect::<Result<_, String>>()?
+            }
+            "allow.everyone.if.no.acl.found" => {
+                self.allow_everyone_if_no_acl_found = parse(name, value)?
+            }
+            "quota.producer.default" => self.quota_producer_default = Some(parse(name, value)?),
+            "quota.consumer.default" => self.quota_consumer_default = Some(parse(name, value)?),
+            "log.level" => {
+                parse_directives(value)?;
+                self.log_level = Some(value.to_string());
+            }
+            "otlp.endpoint" => self.otlp_endpoint = (!value.is_empty()).then(|| value.to_string()),
+            "wasm.interceptors" => {
+                self.wasm_interceptors = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|path| !path.is_empty())
+                    .map(PathBuf::from)
+                    .collect()
+            }
+            "wasm.interceptor.fuel" => self.wasm_interceptor_fuel = parse(name, value)?,
+            "wasm.interceptor.memory.max.bytes" => {
+                self.wasm_interceptor_memory_max_bytes = parse(name, value)?
+            }
+            "message.max.bytes" => self.message_max_bytes = parse(name, value)?,
+            "dead.letter.enable" => self.dead_letter_enable = parse(name, value)?,
+            "dead.letter.topic.suffix" => self.dead_letter_topic_suffix = value.to_string(),
+            "server.log.file" => {
+                self.server_log.file = (!value.is_empty()).then(|| PathBuf::from(value))
+            }
+            "audit.log.file" => {
+                self.server_log.audit_file = (!value.is_empty()).then(|| PathBuf::from(value))
+            }
+            "server.log.max.bytes" => self.server_log.max_bytes = parse(name, value)?,
+            "server.log.roll.ms" => self.server_log.roll_ms = parse(name, value)?,
+            "server.log.max.files" => self.server_log.max_files = parse(name, value)?,
+            "chaos.latency.ms" => {
+                let mut latency = FlatMap::new();
+                for entry in parse_entries(name, value, "<api key>:<percent>:<ms>")? {
+                    let fault = (parse(name, entry[1])?, parse(name, entry[2])?);
+                    latency.insert(parse(name, entry[0])?, fault);
+                }
+                self.chaos.latency = latency;
+            }
+            "chaos.error.codes" => {
+                let mut errors = FlatMap::new();
+                for entry in parse_entries(name, value, "<api key>:<percent>:<error code>")? {
+                    let fault = (parse(name, entry[1])?, parse(name, entry[2])?);
+                    errors.insert(parse(name, entry[0])?, fault);
+                }
+                self.chaos.errors = errors;
+            }
+            "chaos.dropped.responses" => {
+                let mut drops = FlatMap::new();
+                for entry in parse_entries(name, value, "<api key>:<percent>")? {
+                    drops.insert(parse(name, entry[0])?, parse(name, entry[1])?);
+                }
+                self.chaos.drops = drops;
+            }
+            _ => tracing::warn!("Ignoring unknown config {}", name),
+        }
+        Ok(())
+    }
+
+    /// Every setting under its Kafka name, rendered so `set` reads it back. Used to diff
+    /// configs on reload.
+    pub fn to_properties(&self) -> Vec<(&'static str, String)> {
+        let optional = |value: Option<u64>| value.map_or("-1".to_string(), |v| v.to_string());
+        let unlimited_if_zero = |value: u64| {
+            if value == 0 {
+                "-1".to_string()
+            } else {
+                value.to_string()
+            }
+        };
+        let unset = |value: Option<String>| value.unwrap_or_default();
+        vec![
+            ("node.id", self.node_id.to_string()),
+            (
+                "listeners",
+                format!("{}://{}", self.listener_security_protocol, self.listener),
+            ),
+            (
+                "advertised.listeners",
+                unset(self.advertised_listener.clone()),
+            ),
+            ("admin.listener", unset(self.admin_listener.clone())),
+            ("rest.listener", unset(self.rest_listener.clone())),
+            (
+                "rest.basic.auth.enable",
+                self.rest_basic_auth_enable.to_string(),
+            ),
+            (
+                "rest.consumer.instance.timeout.ms",
+                self.rest_consumer_instance_timeout_ms.to_string(),
+            ),
+            (
+                "rest.max.connections",
+                self.rest_max_connections.to_string(),
+            ),
+            ("mqtt.listener", unset(self.mqtt_listener.clone())),
+            (
+                "mqtt.topic.mappings",
+                self.mqtt_topic_mappings
+                    .iter()
+                    .map(|mapping| mapping.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            ("broker.rack", unset(self.rack.clone())),
+            ("log.dirs", self.log_dir.display().to_string()),
+            ("log.segment.bytes", self.log.segment_bytes.to_string()),
+            (
+                "log.retention.bytes",
+                unlimited_if_zero(self.log.retention_bytes),
+            ),
+            ("log.retention.ms", unlimited_if_zero(self.log.retention_ms)),
+            (
+                "log.flush.interval.messages",
+                optional(self.log.flush_interval_messages),
+            ),
+            (
+                "log.flush.interval.ms",
+                optional(self.log.flush_interval_ms),
+            ),
+            (
+                "socket.request.max.bytes",
+                self.socket.request_max_bytes.to_string(),
+            ),
+            (
+                "socket.request.read.timeout.ms",
+                self.socket.request_read_timeout_ms.to_string(),
+            ),
+            (
+                "socket.request.max.bytes.per.api",
+                self.socket
+                    .request_max_bytes_per_api
+                    .iter()
+                    .map(|(api_key, max_bytes)| format!("{}:{}", api_key, max_bytes))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            (
+                "socket.send.buffer.bytes",
+                optional(self.socket.send_buffer_bytes),
+            ),
+            (
+                "socket.receive.buffer.bytes",
+                optional(self.socket.receive_buffer_bytes),
+            ),
+            ("socket.tcp.nodelay", self.socket.tcp_nodelay.to_string()),
+            ("socket.keepalive.enable", self.socket.keepalive.to_string()),
+            (
+                "proxy.protocol.enable",
+                self.socket.proxy_protocol.to_string(),
+            ),
+            ("max.connections", self.socket.max_connections.to_string()),
+            (
+                "max.connections.per.ip",
+                self.socket.max_connections_per_ip.to_string(),
+            ),
+            (
+                "max.connection.creation.rate",
+                self.socket.max_connection_creation_rate.to_string(),
+            ),
+            (
+                "max.connection.creation.rate.per.ip",
+                self.socket.max_connection_creation_rate_per_ip.to_string(),
+            ),
+            (
+                "connections.max.idle.ms",
+                self.socket.connections_max_idle_ms.to_string(),
+            ),
+            (
+                "max.queued.requests.per.connection",
+                self.socket.max_queued_requests_per_connection.to_string(),
+            ),
+            (
+                "connection.failed.authentication.delay.ms",
+                self.socket.failed_authentication_delay_ms.to_string(),
+            ),
+            (
+                "shutdown.drain.timeout.ms",
+                self.socket.shutdown_drain_timeout_ms.to_string(),
+            ),
+            (
+                "sasl.enabled.mechanisms",
+                self.sasl.enabled_mechanisms.join(","),
+            ),
+            (
+                "sasl.mechanism.inter.broker.protocol",
+                self.sasl.inter_broker_mechanism.clone(),
+            ),
+            (
+                "sasl.inter.broker.username",
+                unset(self.sasl.inter_broker_username.clone()),
+            ),
+            // Compared on reload and logged, so never rendered
+            (
+                "sasl.inter.broker.password",
+                self.sasl
+                    .inter_broker_password
+                    .as_ref()
+                    .map_or(String::new(), |_| "[hidden]".to_string()),
+            ),
+            ("num.io.threads", self.num_io_threads.to_string()),
+            (
+                "num.recovery.threads.per.data.dir",
+                self.num_recovery_threads_per_data_dir.to_string(),
+            ),
+            ("log.io.executor", self.log_io_executor.to_string()),
+            ("min.insync.replicas", self.min_insync_replicas.to_string()),
+            (
+                "replica.lag.time.max.ms",
+                self.replica_lag_time_max_ms.to_string(),
+            ),
+            (
+                "producer.id.expiration.ms",
+                self.producer_id_expiration_ms.to_string(),
+            ),
+            (
+                "offsets.retention.minutes",
+                self.offsets_retention_minutes.to_string(),
+            ),
+            ("num.partitions", self.num_partitions.to_string()),
+            (
+                "default.replication.factor",
+                self.default_replication_factor.to_string(),
+            ),
+            (
+                "auto.create.topics.enable",
+                self.auto_create_topics_enable.to_string(),
+            ),
+            (
+                "offsets.topic.replication.factor",
+                self.offsets_topic_replication_factor.to_string(),
+            ),
+            (
+                "transaction.state.log.replication.factor",
+                self.transaction_state_replication_factor.to_string(),
+            ),
+            (
+                "broker.heartbeat.interval.ms",
+                self.broker_heartbeat_interval_ms.to_string(),
+            ),
+            (
+                "broker.session.timeout.ms",
+                self.broker_session_timeout_ms.to_string(),
+            ),
+            ("authorizer.enable", self.authorizer_enable.to_string()),
+            (
+                "super.users",
+                self.super_users
+                    .iter()
+                    .map(|user| user.to_string())
+                    .collect::<Vec<_>>()
+                    .join(";"),
+            ),
+            (
+                "allow.everyone.if.no.acl.found",
+                self.allow_everyone_if_no_acl_found.to_string(),
+            ),
+            (
+                "quota.producer.default",
+                unset(self.quota_producer_default.map(|q| q.to_string())),
+            ),
+            (
+                "quota.consumer.default",
+                unset(self.quota_consumer_default.map(|q| q.to_string())),
+            ),
+            ("log.level", unset(self.log_level.clone())),
+            ("otlp.endpoint", unset(self.otlp_endpoint.clone())),
+            (
+                "wasm.interceptors",
+                self.wasm_interceptors
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            (
+                "wasm.interceptor.fuel",
+                self.wasm_interceptor_fuel.to_string(),
+            ),
+            (
+                "wasm.interceptor.memory.max.bytes",
+                self.wasm_interceptor_memory_max_bytes.to_string(),
+            ),
+            ("message.max.bytes", self.message_max_bytes.to_string()),
+            ("dead.letter.enable", self.dead_letter_enable.to_string()),
+            (
+                "dead.letter.topic.suffix",
+                self.dead_letter_topic_suffix.clone(),
+            ),
+            (
+                "server.log.file",
+                unset(
+                    self.server_log
+                        .file
+                        .as_ref()
+                        .map(|file| file.display().to_string()),
+                ),
+            ),
+            (
+                "audit.log.file",
+                unset(
+                    self.server_log
+                        .audit_file
+                        .as_ref()
+                        .map(|file| file.display().to_string()),
+                ),
+            ),
+            (
+                "server.log.max.bytes",
+                self.server_log.max_bytes.to_string(),
+            ),
+            ("server.log.roll.ms", self.server_log.roll_ms.to_string()),
+            (
+                "server.log.max.files",
+                self.server_log.max_files.to_string(),
+            ),
+            (
+                "chaos.latency.ms",
+                self.chaos
+                    .latency
+                    .iter()
+                    .map(|(api_key, (percent, ms))| format!("{}:{}:{}", api_key, percent, ms))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            (
+                "chaos.error.codes",
+                self.chaos
+                    .errors
+                    .iter()
+                    .map(|(api_key, (percent, code))| format!("{}:{}:{}", api_key, percent, code))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            (
+                "chaos.dropped.responses",
+                self.chaos
+                    .drops
+                    .iter()
+                    .map(|(api_key, percent)| format!("{}:{}", api_key, percent))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+        ]
+    }
+
+    /// Takes every dynamic setting (see `DYNAMIC_BROKER_CONFIGS`) from `other`.
+    pub fn copy_dynamic_from(&mut self, other: &BrokerConfig) {
+        self.log = other.log.clone();
+        self.quota_producer_default = other.quota_producer_default;
+        self.quota_consumer_default = other.quota_consumer_default;
+        self.log_level = other.log_level.clone();
+    }
+
+    pub fn is_dynamic(name: &str) -> bool {
+        DYNAMIC_BROKER_CONFIGS.contains(&name)
+    }
+
+    /// This config with dynamic `overrides` applied on top. Names that are not dynamic are
+    /// refused, so a restart never silently changes what an override meant.
+    pub fn with_overrides(&self, overrides: &FlatMap<String, String>) -> Result<Self, String> {
+        let mut config = self.clone();
+        for (name, value) in overrides.iter() {
+            if !Self::is_dynamic(name) {
+                return Err(format!("{} cannot be updated dynamically", name));
+            }
+            config.set(name, value)?;
+        }
+        Ok(config)
+    }
+
+    /// The address this broker registers with the controller.
+    pub fn advertised_host_port(&self) -> (String, i32) {
+        let address = self.advertised_listener.as_ref().unwrap_or(&self.listener);
+        match address.rsplit_once(':') {
+            Some((host, port)) => (host.to_string(), port.parse().unwrap_or(-1)),
+            None => (address.clone(), -1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_properties_override_defaults() {
+        let config = BrokerConfig::from_properties(
+            "# broker 2\n\
+             node.id=2\n\
+             listeners=PLAINTEXT://0.0.0.0:9093\n\
+             advertised.listeners = PLAINTEXT://broker2:9093\n\
+             log.dirs=/var/lib/forge\n\
+             log.retention.hours=24\n\
+             log.retention.bytes=-1\n\
+             log.flush.interval.messages=1000\n\
+             super.users=User:admin;User:ops\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.node_id, 2);
+        assert_eq!(config.listener, "0.0.0.0:9093");
+        assert_eq!(config.advertised_host_port(), ("broker2".to_string(), 9093));
+        assert_eq!(config.log_dir, PathBuf::from("/var/lib/forge"));
+        assert_eq!(config.log.retention_ms, 24 * 60 * 60 * 1000);
+        assert_eq!(config.log.retention_bytes, 0);
+        assert_eq!(config.log.flush_interval_messages, Some(1000));
+        assert_eq!(config.log.segment_bytes, DEFAULT_SEGMENT_BYTES);
+        assert_eq!(config.super_users.len(), 2);
+
+        assert!(BrokerConfig::from_properties("num.partitions=many").is_err());
+        assert!(BrokerConfig::from_properties("listeners").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_command_line_overrides_environment_overrides_file() {
+        let dir = std::env::temp_dir().join(format!("forge-config-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("server.properties");
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let contents = format!("node.id=2\nlog.dirs={}\nnum.partitions=3\n", dir.display());
+        tokio::fs::write(&path, contents).await.unwrap();
+
+        let env = [
+            ("FORGE_NODE_ID", "3"),
+            ("FORGE_LOG_RETENTION_MS", "1000"),
+            ("FORGE_CONFIG", "/etc/forge/server.properties"),
+            ("HOME", "/root"),
+        ]
+        .map(|(key, value)| (key.to_string(), value.to_string()));
+        let overrides = vec![("node.id".to_string(), "4".to_string())];
+        let config = BrokerConfig::load_layered(Some(&path), env, &overrides)
+            .await
+            .unwrap();
+
+        assert_eq!(config.node_id, 4);
+        assert_eq!(config.log.retention_ms, 1000);
+        assert_eq!(config.num_partitions, 3);
+        assert_eq!(config.log_dir, dir);
+
+        // Every problem is reported, not just the first
+        let env = [("FORGE_NUM_PARTITIONS".to_string(), "many".to_string())];
+        let overrides = vec![
+            ("log.segment.bytes".to_string(), "2048".to_string()),
+            ("log.retention.bytes".to_string(), "1024".to_string()),
+            ("min.insync.replicas".to_string(), "2".to_string()),
+        ];
+        let error = BrokerConfig::load_layered(Some(&path), env, &overrides)
+            .await
+            .unwrap_err();
+        assert!(error.starts_with("Found 3 config problem(s)"), "{}", error);
+        assert!(error.contains("num.partitions"));
+        assert!(error.contains("lower log.segment.bytes or raise log.retention.bytes"));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
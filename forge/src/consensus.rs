@@ -1,4 +1,4 @@
 pub mod messages;
+pub mod metadata_cache;
 pub mod node;
 pub mod state;
-pub mod metadata_cache;
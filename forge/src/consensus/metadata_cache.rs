@@ -1,14 +1,19 @@
 use crate::core::domain::metadata_records::{
-    MetadataRecord, PartitionRecord, RegisterBrokerRecord,
+    CONFIG_RESOURCE_BROKER, CONFIG_RESOURCE_TOPIC, ConfigRecord, FenceBrokerRecord, MetadataRecord,
+    PartitionRecord, RegisterBrokerRecord,
 };
-use crate::shared::collections::FlatMap;
+use crate::shared::collections::{FlatMap, FlatSet};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ClusterMetadataCache {
     /// Maps broker_id to its registration details
     pub brokers: FlatMap<i32, RegisterBrokerRecord>,
+    /// Registered brokers that are currently unavailable
+    pub fenced_brokers: FlatSet<i32>,
     /// Maps topic_name to its metadata and partitions
     pub topics: FlatMap<String, TopicMetadata>,
+    /// Config overrides keyed by (resource_type, resource_name), then config name
+    pub configs: FlatMap<(i8, String), FlatMap<String, String>>,
     /// The offset of the highest metadata record applied to this cache
     pub last_applied_offset: i64,
 }
@@ -24,7 +29,9 @@ impl ClusterMetadataCache {
     pub fn new() -> Self {
         Self {
             brokers: FlatMap::new(),
+            fenced_brokers: FlatSet::new(),
             topics: FlatMap::new(),
+            configs: FlatMap::new(),
             last_applied_offset: 0,
         }
     }
@@ -33,6 +40,12 @@ impl ClusterMetadataCache {
         match record {
             MetadataRecord::RegisterBroker(broker) => {
                 self.brokers.insert(broker.broker_id, broker.clone());
+                self.fenced_brokers.remove(&broker.broker_id);
+            }
+            MetadataRecord::FenceBroker(fence) => {
+                if self.brokers.contains_key(&fence.broker_id) {
+                    self.fenced_brokers.insert(fence.broker_id);
+                }
             }
             MetadataRecord::Topic(topic) => {
                 let mut partitions_map = FlatMap::new();
@@ -65,10 +78,88 @@ impl ClusterMetadataCache {
                     );
                 }
             }
+            MetadataRecord::Config(config) => {
+                let key = (config.resource_type, config.resource_name.clone());
+                match &config.value {
+                    Some(value) => {
+                        if let Some(configs) = self.configs.get_mut(&key) {
+                            configs.insert(config.name.clone(), value.clone());
+                        } else {
+                            let mut configs = FlatMap::new();
+                            configs.insert(config.name.clone(), value.clone());
+                            self.configs.insert(key, configs);
+                        }
+                    }
+                    None => {
+                        if let Some(configs) = self.configs.get_mut(&key) {
+                            configs.remove(&config.name);
+                        }
+                    }
+                }
+            }
+            MetadataRecord::PartitionChange(change) => {
+                if let Some(partition) = self
+                    .topics
+                    .get_mut(&change.topic_name)
+                    .and_then(|t| t.partitions.get_mut(&change.partition_index))
+                {
+                    partition.isr = change.isr.clone();
+                    partition.leader = change.leader;
+                    partition.leader_epoch = change.leader_epoch;
+                    if let Some(replicas) = &change.replicas {
+                        partition.replicas = replicas.clone();
+                    }
+                    if let Some(adding_replicas) = &change.adding_replicas {
+                        partition.adding_replicas = adding_replicas.clone();
+                    }
+                    if let Some(removing_replicas) = &change.removing_replicas {
+                        partition.removing_replicas = removing_replicas.clone();
+                    }
+                }
+            }
         }
         self.last_applied_offset = offset;
     }
 
+    pub fn is_broker_alive(&self, broker_id: i32) -> bool {
+        self.brokers.contains_key(&broker_id) && !self.fenced_brokers.contains(&broker_id)
+    }
+
+    /// Registered, unfenced broker ids in ascending order.
+    pub fn live_brokers(&self) -> Vec<i32> {
+        self.brokers
+            .keys()
+            .copied()
+            .filter(|id| !self.fenced_brokers.contains(id))
+            .collect()
+    }
+
+    pub fn topic_config(&self, topic_name: &str, name: &str) -> Option<&String> {
+        self.configs
+            .get(&(CONFIG_RESOURCE_TOPIC, topic_name.to_string()))
+            .and_then(|configs| configs.get(&name.to_string()))
+    }
+
+    /// Dynamic config overrides of `broker_id`: cluster-wide defaults (empty resource name)
+    /// overlaid with those set on the broker itself.
+    pub fn broker_configs(&self, broker_id: i32) -> FlatMap<String, String> {
+        let mut configs = FlatMap::new();
+        for resource_name in [String::new(), broker_id.to_string()] {
+            if let Some(overrides) = self.configs.get(&(CONFIG_RESOURCE_BROKER, resource_name)) {
+                for (name, value) in overrides.iter() {
+                    configs.insert(name.clone(), value.clone());
+                }
+            }
+        }
+        configs
+    }
+
+    pub fn partition(&self, topic_name: &str, partition_index: i32) -> Option<&PartitionRecord> {
+        self.topics
+            .get(&topic_name.to_string())
+            .and_then(|t| t.partitions.get(&partition_index))
+    }
+
     pub fn replay_records(&mut self, offset: i64, records: &[MetadataRecord]) {
         for record in records {
             self.apply_record(offset, record);
@@ -82,6 +173,12 @@ impl ClusterMetadataCache {
             snapshot.push(MetadataRecord::RegisterBroker(broker.clone()));
         }
 
+        for broker_id in self.fenced_brokers.iter() {
+            snapshot.push(MetadataRecord::FenceBroker(FenceBrokerRecord {
+                broker_id: *broker_id,
+            }));
+        }
+
         for topic_meta in self.topics.values() {
             let mut partitions_vec = Vec::new();
             for partition in topic_meta.partitions.values() {
@@ -95,6 +192,17 @@ impl ClusterMetadataCache {
             snapshot.push(MetadataRecord::Topic(topic_record));
         }
 
+        for ((resource_type, resource_name), configs) in self.configs.iter() {
+            for (name, value) in configs.iter() {
+                snapshot.push(MetadataRecord::Config(ConfigRecord {
+                    resource_type: *resource_type,
+                    resource_name: resource_name.clone(),
+                    name: name.clone(),
+                    value: Some(value.clone()),
+                }));
+            }
+        }
+
         snapshot
     }
 }
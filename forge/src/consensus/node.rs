@@ -5,9 +5,8 @@ use crate::{
     adapters::driven::storage::log::PartitionLog,
     consensus::{
         messages::{
-            AppendEntries, AppendEntriesResponse,
+            AppendEntries, AppendEntriesResponse, InstallSnapshot, InstallSnapshotResponse,
             RequestVote, RequestVoteResponse,
-            InstallSnapshot, InstallSnapshotResponse,
         },
         state::{PersistentState, Role},
     },
@@ -62,6 +61,11 @@ impl Node {
         }
     }
 
+    /// Votes, or matching logs, needed out of the peers and this node.
+    fn majority(&self) -> usize {
+        ((self.peers.len() + 1) >> 1) + 1
+    }
+
     fn start_election(&mut self) {
         let mut votes = FlatSet::new();
         votes.insert(self.id);
@@ -79,6 +83,11 @@ impl Node {
             self.id,
             self.persistent_state.current_term
         );
+
+        // A single voter elects itself without waiting for any response
+        if self.peers.is_empty() {
+            self.become_leader();
+        }
     }
 
     pub fn handle_request_vote(&mut self, request: RequestVote) -> RequestVoteResponse {
@@ -272,8 +281,7 @@ impl Node {
             } = self.role
             {
                 votes_received.insert(from_id);
-                let majority = (self.peers.len() + 1) >> 1 + 1;
-                if votes_received.len() >= majority {
+                if votes_received.len() >= self.majority() {
                     won_election = true;
                 }
             }
@@ -325,12 +333,10 @@ impl Node {
             if response.success {
                 match_index.insert(peer_id, response.match_index);
                 next_index.insert(peer_id, response.match_index + 1);
-            } else {
-                if let Some(n_idx) = next_index.get_mut(&peer_id) {
-                    if *n_idx > 0 {
-                        *n_idx -= 1;
-                    }
-                }
+            } else if let Some(n_idx) = next_index.get_mut(&peer_id)
+                && *n_idx > 0
+            {
+                *n_idx -= 1;
             }
         }
 
@@ -348,23 +354,21 @@ impl Node {
             match_indexes.push(self.log_store.get_last_log_index());
             match_indexes.sort_unstable();
 
-            let majority = (self.peers.len() + 1) >> 1 + 1;
-            match_indexes[match_indexes.len() - majority]
+            match_indexes[match_indexes.len() - self.majority()]
         } else {
             return;
         };
 
-        if new_commit_index > self.commit_index {
-            if let Ok(Some(term)) = self.log_store.get_term_at_index(new_commit_index).await {
-                if term == self.persistent_state.current_term {
-                    self.commit_index = new_commit_index;
-                    tracing::info!(
-                        "Leader {} advanced commit_index to {}",
-                        self.id,
-                        self.commit_index
-                    );
-                }
-            }
+        if new_commit_index > self.commit_index
+            && let Ok(Some(term)) = self.log_store.get_term_at_index(new_commit_index).await
+            && term == self.persistent_state.current_term
+        {
+            self.commit_index = new_commit_index;
+            tracing::info!(
+                "Leader {} advanced commit_index to {}",
+                self.id,
+                self.commit_index
+            );
         }
     }
 
@@ -473,3 +477,67 @@ impl Node {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn node(peers: Vec<u32>) -> (Node, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("forge-node-{}", uuid::Uuid::new_v4()));
+        let log = PartitionLog::new(&dir, 1024 * 1024, 0, 0).await.unwrap();
+        (Node::new(1, peers, log), dir)
+    }
+
+    fn granted(node: &Node) -> RequestVoteResponse {
+        RequestVoteResponse {
+            term: node.persistent_state.current_term,
+            vote_granted: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_majority() {
+        for (peers, majority) in [
+            (vec![], 1),
+            (vec![2], 2),
+            (vec![2, 3], 2),
+            (vec![2, 3, 4], 3),
+        ] {
+            let (node, dir) = node(peers).await;
+            assert_eq!(node.majority(), majority);
+            tokio::fs::remove_dir_all(&dir).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_two_node_election_needs_both_votes() {
+        let (mut node, dir) = node(vec![2]).await;
+        node.start_election();
+        assert!(matches!(node.role, Role::Candidate { .. }));
+
+        let response = granted(&node);
+        node.handle_request_vote_response(response, 2);
+        assert!(matches!(node.role, Role::Leader { .. }));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_three_node_election_needs_one_peer_vote() {
+        let (mut node, dir) = node(vec![2, 3]).await;
+        node.start_election();
+
+        let refused = RequestVoteResponse {
+            term: node.persistent_state.current_term,
+            vote_granted: false,
+        };
+        node.handle_request_vote_response(refused, 2);
+        assert!(matches!(node.role, Role::Candidate { .. }));
+
+        let response = granted(&node);
+        node.handle_request_vote_response(response, 3);
+        assert!(matches!(node.role, Role::Leader { .. }));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -17,7 +17,7 @@ pub enum Role {
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct PersistentState {
     pub current_term: u64,
     pub voted_for: Option<u32>,
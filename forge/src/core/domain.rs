@@ -1,3 +1,13 @@
+pub mod acl;
+pub mod compression;
+pub mod consumer_protocol;
+pub mod control_record;
+pub mod group_records;
 pub mod metadata_records;
+pub mod principal;
+pub mod producer_id_block;
 pub mod record;
 pub mod record_batch;
+pub mod scram_credential;
+pub mod topic_partition;
+pub mod transaction_records;
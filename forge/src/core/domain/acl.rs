@@ -0,0 +1,312 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::core::domain::principal::KafkaPrincipal;
+
+/// Matches every resource name, principal or host.
+pub const WILDCARD: &str = "*";
+pub const WILDCARD_PRINCIPAL: &str = "User:*";
+pub const CLUSTER_RESOURCE_NAME: &str = "kafka-cluster";
+
+/// Kafka's wire code for "any" in ACL filters, shared by every enum below.
+pub const ACL_FILTER_ANY: i8 = 1;
+/// Pattern type filter selecting every pattern that matches the filter's resource name.
+pub const PATTERN_TYPE_FILTER_MATCH: i8 = 2;
+
+/// Declares a `Display`/`FromStr` pair over the variant names, for the ACL file format, and
+/// the Kafka wire code of each variant, for the ACL APIs.
+macro_rules! named_enum {
+    ($name:ident { $($variant:ident = $code:literal),+ $(,)? }) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+        pub enum $name {
+            $($variant),+
+        }
+
+        impl $name {
+            pub fn code(self) -> i8 {
+                match self {
+                    $(Self::$variant => $code),+
+                }
+            }
+
+            pub fn from_code(code: i8) -> Option<Self> {
+                match code {
+                    $($code => Some(Self::$variant),)+
+                    _ => None,
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    $(Self::$variant => write!(f, stringify!($variant))),+
+                }
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $(stringify!($variant) => Ok(Self::$variant),)+
+                    _ => Err(format!("Unknown {} {}", stringify!($name), s)),
+                }
+            }
+        }
+    };
+}
+
+named_enum!(ResourceType {
+    Topic = 2,
+    Group = 3,
+    Cluster = 4,
+    TransactionalId = 5,
+});
+
+named_enum!(PatternType {
+    Literal = 3,
+    Prefixed = 4,
+});
+
+named_enum!(AclOperation {
+    All = 2,
+    Read = 3,
+    Write = 4,
+    Create = 5,
+    Delete = 6,
+    Alter = 7,
+    Describe = 8,
+    ClusterAction = 9,
+    DescribeConfigs = 10,
+    AlterConfigs = 11,
+    IdempotentWrite = 12,
+});
+
+named_enum!(AclPermissionType {
+    Allow = 3,
+    Deny = 2,
+});
+
+impl AclOperation {
+    /// Whether an ACL granting `self` also grants `requested`; e.g. Read implies Describe.
+    pub fn implies(self, requested: AclOperation) -> bool {
+        match (self, requested) {
+            (Self::All, _) => true,
+            (granted, requested) if granted == requested => true,
+            (Self::Read | Self::Write | Self::Delete | Self::Alter, Self::Describe) => true,
+            (Self::AlterConfigs, Self::DescribeConfigs) => true,
+            _ => false,
+        }
+    }
+}
+
+/// The concrete resource a request acts on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resource {
+    pub resource_type: ResourceType,
+    pub name: String,
+}
+
+impl Resource {
+    pub fn new(resource_type: ResourceType, name: impl Into<String>) -> Self {
+        Self {
+            resource_type,
+            name: name.into(),
+        }
+    }
+
+    pub fn cluster() -> Self {
+        Self::new(ResourceType::Cluster, CLUSTER_RESOURCE_NAME)
+    }
+}
+
+/// The resources an ACL covers: one name, a name prefix, or every name via `*`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ResourcePattern {
+    pub resource_type: ResourceType,
+    pub name: String,
+    pub pattern_type: PatternType,
+}
+
+impl ResourcePattern {
+    pub fn matches(&self, resource: &Resource) -> bool {
+        if self.resource_type != resource.resource_type {
+            return false;
+        }
+        match self.pattern_type {
+            PatternType::Literal => self.name == WILDCARD || self.name == resource.name,
+            PatternType::Prefixed => resource.name.starts_with(&self.name),
+        }
+    }
+}
+
+/// One access control entry bound to a resource pattern.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AclBinding {
+    pub pattern: ResourcePattern,
+    /// `type:name`, or `User:*` for everyone.
+    pub principal: String,
+    /// Client host, or `*` for any.
+    pub host: String,
+    pub operation: AclOperation,
+    pub permission_type: AclPermissionType,
+}
+
+impl AclBinding {
+    /// Whether this entry speaks about `principal` connecting from `host`.
+    pub fn applies_to(&self, principal: &KafkaPrincipal, host: &str) -> bool {
+        (self.principal == WILDCARD_PRINCIPAL || self.principal == principal.to_string())
+            && (self.host == WILDCARD || self.host == host)
+    }
+}
+
+/// How a filter selects resource patterns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternTypeFilter {
+    Any,
+    /// Patterns that would apply to a resource with the filter's name: the literal name, the
+    /// wildcard and every matching prefix.
+    Match,
+    Exact(PatternType),
+}
+
+/// Selects ACL bindings, as DescribeAcls and DeleteAcls do; each `None` matches anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AclBindingFilter {
+    pub resource_type: Option<ResourceType>,
+    pub name: Option<String>,
+    pub pattern_type: PatternTypeFilter,
+    pub principal: Option<String>,
+    pub host: Option<String>,
+    pub operation: Option<AclOperation>,
+    pub permission_type: Option<AclPermissionType>,
+}
+
+impl AclBindingFilter {
+    pub fn matches(&self, binding: &AclBinding) -> bool {
+        let pattern = &binding.pattern;
+        let pattern_matches = match (self.pattern_type, &self.name) {
+            (PatternTypeFilter::Match, Some(name)) => {
+                pattern.matches(&Resource::new(pattern.resource_type, name.as_str()))
+            }
+            (PatternTypeFilter::Exact(pattern_type), _) if pattern_type != pattern.pattern_type => {
+                false
+            }
+            (_, name) => name.as_ref().is_none_or(|name| *name == pattern.name),
+        };
+        pattern_matches
+            && self
+                .resource_type
+                .is_none_or(|resource_type| resource_type == pattern.resource_type)
+            && self
+                .principal
+                .as_ref()
+                .is_none_or(|principal| *principal == binding.principal)
+            && self.host.as_ref().is_none_or(|host| *host == binding.host)
+            && self
+                .operation
+                .is_none_or(|operation| operation == binding.operation)
+            && self
+                .permission_type
+                .is_none_or(|permission_type| permission_type == binding.permission_type)
+    }
+}
+
+/// Renders as the comma-separated line used by the ACL file:
+/// `principal,host,operation,permission,resource_type,pattern_type,name`.
+impl fmt::Display for AclBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{},{},{},{},{}",
+            self.principal,
+            self.host,
+            self.operation,
+            self.permission_type,
+            self.pattern.resource_type,
+            self.pattern.pattern_type,
+            self.pattern.name
+        )
+    }
+}
+
+impl FromStr for AclBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // The resource name is last so it may itself contain commas
+        let fields: Vec<&str> = s.splitn(7, ',').collect();
+        let [
+            principal,
+            host,
+            operation,
+            permission_type,
+            resource_type,
+            pattern_type,
+            name,
+        ] = fields[..]
+        else {
+            return Err(format!("Malformed ACL entry: {}", s));
+        };
+
+        Ok(Self {
+            pattern: ResourcePattern {
+                resource_type: resource_type.parse()?,
+                name: name.to_string(),
+                pattern_type: pattern_type.parse()?,
+            },
+            principal: principal.to_string(),
+            host: host.to_string(),
+            operation: operation.parse()?,
+            permission_type: permission_type.parse()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_match_selects_patterns_applying_to_name() {
+        let binding = |name: &str, pattern_type| AclBinding {
+            pattern: ResourcePattern {
+                resource_type: ResourceType::Topic,
+                name: name.to_string(),
+                pattern_type,
+            },
+            principal: "User:alice".to_string(),
+            host: WILDCARD.to_string(),
+            operation: AclOperation::Read,
+            permission_type: AclPermissionType::Allow,
+        };
+        let literal = binding("orders", PatternType::Literal);
+        let wildcard = binding(WILDCARD, PatternType::Literal);
+        let prefixed = binding("ord", PatternType::Prefixed);
+        let other = binding("payments", PatternType::Literal);
+
+        let mut filter = AclBindingFilter {
+            resource_type: Some(ResourceType::Topic),
+            name: Some("orders".to_string()),
+            pattern_type: PatternTypeFilter::Match,
+            principal: None,
+            host: None,
+            operation: None,
+            permission_type: None,
+        };
+        let selected = |filter: &AclBindingFilter| {
+            [&literal, &wildcard, &prefixed, &other].map(|binding| filter.matches(binding))
+        };
+        assert_eq!(selected(&filter), [true, true, true, false]);
+
+        filter.pattern_type = PatternTypeFilter::Exact(PatternType::Literal);
+        assert_eq!(selected(&filter), [true, false, false, false]);
+
+        filter.name = None;
+        filter.pattern_type = PatternTypeFilter::Any;
+        filter.operation = Some(AclOperation::Write);
+        assert_eq!(selected(&filter), [false; 4]);
+    }
+}
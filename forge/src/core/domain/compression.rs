@@ -0,0 +1,206 @@
+use std::fmt;
+use std::io::{Read, Write};
+
+/// The codec bits of a record batch's attributes.
+pub const COMPRESSION_CODEC_MASK: i16 = 0x07;
+
+/// Java clients frame snappy the way xerial's SnappyOutputStream does: this header, then
+/// length-prefixed raw snappy blocks.
+const XERIAL_SNAPPY_MAGIC: [u8; 8] = *b"\x82SNAPPY\0";
+const XERIAL_SNAPPY_HEADER_LENGTH: usize = 16;
+const XERIAL_SNAPPY_BLOCK_BYTES: usize = 32 * 1024;
+/// Far above any batch a producer could send, yet low enough that a few bytes of crafted
+/// input cannot inflate into an allocation that takes the broker down.
+pub const MAX_DECOMPRESSED_BYTES: usize = 256 * 1024 * 1024;
+
+/// How the records of a batch are compressed; one codec covers every record in the batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompressionType {
+    #[default]
+    None,
+    Gzip,
+    Snappy,
+    Lz4,
+    Zstd,
+}
+
+impl CompressionType {
+    pub fn from_attributes(attributes: i16) -> Result<Self, String> {
+        match attributes & COMPRESSION_CODEC_MASK {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Gzip),
+            2 => Ok(Self::Snappy),
+            3 => Ok(Self::Lz4),
+            4 => Ok(Self::Zstd),
+            codec => Err(format!("Unknown compression codec {}", codec)),
+        }
+    }
+
+    pub fn id(self) -> i16 {
+        match self {
+            Self::None => 0,
+            Self::Gzip => 1,
+            Self::Snappy => 2,
+            Self::Lz4 => 3,
+            Self::Zstd => 4,
+        }
+    }
+
+    /// Parses a `compression.type` value.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::None),
+            "gzip" => Some(Self::Gzip),
+            "snappy" => Some(Self::Snappy),
+            "lz4" => Some(Self::Lz4),
+            "zstd" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Gzip => "gzip",
+            Self::Snappy => "snappy",
+            Self::Lz4 => "lz4",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let compressed = match self {
+            Self::None => return Ok(data.to_vec()),
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data).and_then(|_| encoder.finish())
+            }
+            Self::Snappy => return Self::compress_xerial_snappy(data),
+            Self::Lz4 => {
+                let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+                encoder
+                    .write_all(data)
+                    .and_then(|_| encoder.finish().map_err(std::io::Error::other))
+            }
+            Self::Zstd => zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL),
+        };
+        compressed.map_err(|e| format!("Failed to compress with {}: {}", self, e))
+    }
+
+    pub fn decompress(self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let mut decompressed = Vec::new();
+        let limit = MAX_DECOMPRESSED_BYTES as u64 + 1;
+        let result = match self {
+            Self::None => return Ok(data.to_vec()),
+            Self::Gzip => flate2::read::GzDecoder::new(data)
+                .take(limit)
+                .read_to_end(&mut decompressed),
+            Self::Snappy => return Self::decompress_snappy(data),
+            Self::Lz4 => lz4_flex::frame::FrameDecoder::new(data)
+                .take(limit)
+                .read_to_end(&mut decompressed),
+            Self::Zstd => {
+                zstd::Decoder::new(data).and_then(|d| d.take(limit).read_to_end(&mut decompressed))
+            }
+        };
+        match result {
+            Ok(_) if decompressed.len() > MAX_DECOMPRESSED_BYTES => Err(format!(
+                "Decompressed {} exceeds {} bytes",
+                self, MAX_DECOMPRESSED_BYTES
+            )),
+            Ok(_) => Ok(decompressed),
+            Err(e) => Err(format!("Failed to decompress {}: {}", self, e)),
+        }
+    }
+
+    fn compress_xerial_snappy(data: &[u8]) -> Result<Vec<u8>, String> {
+        let mut output = XERIAL_SNAPPY_MAGIC.to_vec();
+        output.extend_from_slice(&1i32.to_be_bytes());
+        output.extend_from_slice(&1i32.to_be_bytes());
+        let mut encoder = snap::raw::Encoder::new();
+        for block in data.chunks(XERIAL_SNAPPY_BLOCK_BYTES) {
+            let compressed = encoder
+                .compress_vec(block)
+                .map_err(|e| format!("Failed to compress with snappy: {}", e))?;
+            output.extend_from_slice(&(compressed.len() as i32).to_be_bytes());
+            output.extend_from_slice(&compressed);
+        }
+        Ok(output)
+    }
+
+    /// Accepts both xerial framing and a bare snappy block, which librdkafka sends.
+    fn decompress_snappy(data: &[u8]) -> Result<Vec<u8>, String> {
+        let mut decoder = snap::raw::Decoder::new();
+        let mut total = 0;
+        let mut decode = |decoder: &mut snap::raw::Decoder, block: &[u8]| {
+            let length = snap::raw::decompress_len(block)
+                .map_err(|e| format!("Failed to decompress snappy: {}", e))?;
+            total += length;
+            if total > MAX_DECOMPRESSED_BYTES {
+                return Err(format!(
+                    "Decompressed snappy exceeds {} bytes",
+                    MAX_DECOMPRESSED_BYTES
+                ));
+            }
+            decoder
+                .decompress_vec(block)
+                .map_err(|e| format!("Failed to decompress snappy: {}", e))
+        };
+        if !data.starts_with(&XERIAL_SNAPPY_MAGIC) {
+            return decode(&mut decoder, data);
+        }
+
+        let mut output = Vec::new();
+        let mut rest = data.get(XERIAL_SNAPPY_HEADER_LENGTH..).unwrap_or_default();
+        while !rest.is_empty() {
+            let Some((length, tail)) = rest.split_first_chunk::<4>() else {
+                return Err("Truncated snappy block length".to_string());
+            };
+            let length = i32::from_be_bytes(*length).max(0) as usize;
+            let Some(block) = tail.get(..length) else {
+                return Err("Truncated snappy block".to_string());
+            };
+            output.extend_from_slice(&decode(&mut decoder, block)?);
+            rest = &tail[length..];
+        }
+        Ok(output)
+    }
+}
+
+impl fmt::Display for CompressionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_codec_round_trips() {
+        let data: Vec<u8> = (0..100_000u32)
+            .flat_map(|i| (i % 251).to_be_bytes())
+            .collect();
+        for codec in [
+            CompressionType::None,
+            CompressionType::Gzip,
+            CompressionType::Snappy,
+            CompressionType::Lz4,
+            CompressionType::Zstd,
+        ] {
+            let compressed = codec.compress(&data).unwrap();
+            assert_eq!(codec.decompress(&compressed).unwrap(), data, "{}", codec);
+            assert_eq!(CompressionType::from_attributes(codec.id()), Ok(codec));
+        }
+
+        let bare = snap::raw::Encoder::new()
+            .compress_vec(b"librdkafka")
+            .unwrap();
+        assert_eq!(
+            CompressionType::Snappy.decompress(&bare).unwrap(),
+            b"librdkafka"
+        );
+    }
+}
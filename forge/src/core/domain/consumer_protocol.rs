@@ -0,0 +1,146 @@
+use bytes::{Buf, BufMut};
+
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::protocol::types::{Type, capacity_for};
+
+pub const CONSUMER_PROTOCOL_TYPE: &str = "consumer";
+
+const SUBSCRIPTION_VERSION: i16 = 2;
+const ASSIGNMENT_VERSION: i16 = 1;
+
+/// Member metadata a consumer sends in JoinGroup for each protocol it supports.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Subscription {
+    pub topics: Vec<String>,
+    pub user_data: Option<Vec<u8>>,
+    pub owned_partitions: Vec<TopicPartition>,
+    /// Generation in which `owned_partitions` were assigned, or -1 if unknown.
+    pub generation_id: i32,
+}
+
+/// Assignment the group leader hands back to each member through SyncGroup.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Assignment {
+    pub partitions: Vec<TopicPartition>,
+    pub user_data: Option<Vec<u8>>,
+}
+
+fn encode_nullable_bytes<B: BufMut>(buf: &mut B, bytes: &Option<Vec<u8>>) {
+    match bytes {
+        Some(bytes) => {
+            (bytes.len() as i32).encode(buf);
+            buf.put_slice(bytes);
+        }
+        None => (-1i32).encode(buf),
+    }
+}
+
+fn decode_nullable_bytes<B: Buf>(buf: &mut B) -> Result<Option<Vec<u8>>, String> {
+    let len = i32::decode(buf)?;
+    if len < 0 {
+        return Ok(None);
+    }
+    if buf.remaining() < len as usize {
+        return Err("Not enough data for user data".to_string());
+    }
+    let mut bytes = vec![0u8; len as usize];
+    buf.copy_to_slice(&mut bytes);
+    Ok(Some(bytes))
+}
+
+/// Encodes partitions grouped by topic, in the order topics first appear.
+fn encode_topic_partitions<B: BufMut>(buf: &mut B, partitions: &[TopicPartition]) {
+    let mut topics: Vec<(&str, Vec<i32>)> = Vec::new();
+    for tp in partitions {
+        match topics.iter_mut().find(|(topic, _)| *topic == tp.topic) {
+            Some((_, indexes)) => indexes.push(tp.partition),
+            None => topics.push((&tp.topic, vec![tp.partition])),
+        }
+    }
+
+    (topics.len() as i32).encode(buf);
+    for (topic, indexes) in topics {
+        topic.to_string().encode(buf);
+        (indexes.len() as i32).encode(buf);
+        for index in indexes {
+            index.encode(buf);
+        }
+    }
+}
+
+fn decode_topic_partitions<B: Buf>(buf: &mut B) -> Result<Vec<TopicPartition>, String> {
+    let mut partitions = Vec::new();
+    let topics_len = i32::decode(buf)?;
+    for _ in 0..topics_len {
+        let topic = String::decode(buf)?;
+        let partitions_len = i32::decode(buf)?;
+        for _ in 0..partitions_len {
+            partitions.push(TopicPartition::new(topic.clone(), i32::decode(buf)?));
+        }
+    }
+    Ok(partitions)
+}
+
+impl Type for Subscription {
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        SUBSCRIPTION_VERSION.encode(buf);
+        (self.topics.len() as i32).encode(buf);
+        for topic in &self.topics {
+            topic.encode(buf);
+        }
+        encode_nullable_bytes(buf, &self.user_data);
+        encode_topic_partitions(buf, &self.owned_partitions);
+        self.generation_id.encode(buf);
+    }
+
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let version = i16::decode(buf)?;
+        if version < 0 {
+            return Err(format!("Invalid subscription version: {}", version));
+        }
+
+        let topics_len = i32::decode(buf)?;
+        let mut topics =
+            Vec::with_capacity(capacity_for::<String, B>(topics_len.max(0) as usize, buf));
+        for _ in 0..topics_len {
+            topics.push(String::decode(buf)?);
+        }
+        let user_data = decode_nullable_bytes(buf)?;
+
+        let owned_partitions = if version >= 1 {
+            decode_topic_partitions(buf)?
+        } else {
+            vec![]
+        };
+        let generation_id = if version >= 2 { i32::decode(buf)? } else { -1 };
+
+        Ok(Self {
+            topics,
+            user_data,
+            owned_partitions,
+            generation_id,
+        })
+    }
+}
+
+impl Type for Assignment {
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        ASSIGNMENT_VERSION.encode(buf);
+        encode_topic_partitions(buf, &self.partitions);
+        encode_nullable_bytes(buf, &self.user_data);
+    }
+
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let version = i16::decode(buf)?;
+        if version < 0 {
+            return Err(format!("Invalid assignment version: {}", version));
+        }
+        if !buf.has_remaining() {
+            return Ok(Self::default());
+        }
+        Ok(Self {
+            partitions: decode_topic_partitions(buf)?,
+            user_data: decode_nullable_bytes(buf)?,
+        })
+    }
+}
@@ -0,0 +1,66 @@
+use bytes::BytesMut;
+
+use crate::core::domain::record::Record;
+use crate::core::domain::record_batch::{CONTROL_FLAG_MASK, RecordBatch, TRANSACTIONAL_FLAG_MASK};
+use crate::protocol::types::Type;
+
+const CONTROL_RECORD_KEY_VERSION: i16 = 0;
+const END_TXN_MARKER_VALUE_VERSION: i16 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlRecordType {
+    Abort = 0,
+    Commit = 1,
+}
+
+/// COMMIT/ABORT marker closing a producer's transaction on one partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndTransactionMarker {
+    pub control_type: ControlRecordType,
+    pub coordinator_epoch: i32,
+}
+
+impl EndTransactionMarker {
+    pub fn to_batch(&self, producer_id: i64, producer_epoch: i16, timestamp: i64) -> RecordBatch {
+        let mut key = BytesMut::new();
+        CONTROL_RECORD_KEY_VERSION.encode(&mut key);
+        (self.control_type as i16).encode(&mut key);
+
+        let mut value = BytesMut::new();
+        END_TXN_MARKER_VALUE_VERSION.encode(&mut value);
+        self.coordinator_epoch.encode(&mut value);
+
+        let mut batch = RecordBatch::new(
+            timestamp,
+            vec![Record::new(0, Some(key.to_vec()), Some(value.to_vec()))],
+        );
+        batch.attributes = TRANSACTIONAL_FLAG_MASK | CONTROL_FLAG_MASK;
+        batch.producer_id = producer_id;
+        batch.producer_epoch = producer_epoch;
+        batch
+    }
+
+    /// Decodes the marker carried by a control batch, if it is an end-transaction marker.
+    pub fn from_batch(batch: &RecordBatch) -> Option<Self> {
+        if !batch.is_control_batch() {
+            return None;
+        }
+        let record = batch.records.first()?;
+        let mut key = record.key.as_deref()?;
+        let mut value = record.value.as_deref()?;
+
+        let _version = i16::decode(&mut key).ok()?;
+        let control_type = match i16::decode(&mut key).ok()? {
+            0 => ControlRecordType::Abort,
+            1 => ControlRecordType::Commit,
+            _ => return None,
+        };
+        let _version = i16::decode(&mut value).ok()?;
+        let coordinator_epoch = i32::decode(&mut value).ok()?;
+
+        Some(Self {
+            control_type,
+            coordinator_epoch,
+        })
+    }
+}
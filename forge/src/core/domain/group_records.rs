@@ -0,0 +1,210 @@
+use bytes::{Buf, BufMut};
+
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::protocol::types::{Type, capacity_for};
+
+const OFFSET_COMMIT_KEY_VERSION: i16 = 1;
+const GROUP_METADATA_KEY_VERSION: i16 = 2;
+const OFFSET_COMMIT_VALUE_VERSION: i16 = 3;
+const GROUP_METADATA_VALUE_VERSION: i16 = 3;
+
+/// Key of a record in `__consumer_offsets`. A record with a null value is a tombstone for the key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GroupRecordKey {
+    OffsetCommit {
+        group_id: String,
+        topic_partition: TopicPartition,
+    },
+    GroupMetadata {
+        group_id: String,
+    },
+}
+
+impl GroupRecordKey {
+    pub fn group_id(&self) -> &str {
+        match self {
+            Self::OffsetCommit { group_id, .. } => group_id,
+            Self::GroupMetadata { group_id } => group_id,
+        }
+    }
+}
+
+impl Type for GroupRecordKey {
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        match self {
+            Self::OffsetCommit {
+                group_id,
+                topic_partition,
+            } => {
+                OFFSET_COMMIT_KEY_VERSION.encode(buf);
+                group_id.encode(buf);
+                topic_partition.topic.encode(buf);
+                topic_partition.partition.encode(buf);
+            }
+            Self::GroupMetadata { group_id } => {
+                GROUP_METADATA_KEY_VERSION.encode(buf);
+                group_id.encode(buf);
+            }
+        }
+    }
+
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let version = i16::decode(buf)?;
+        match version {
+            0 | OFFSET_COMMIT_KEY_VERSION => {
+                let group_id = String::decode(buf)?;
+                let topic = String::decode(buf)?;
+                let partition = i32::decode(buf)?;
+                Ok(Self::OffsetCommit {
+                    group_id,
+                    topic_partition: TopicPartition::new(topic, partition),
+                })
+            }
+            GROUP_METADATA_KEY_VERSION => Ok(Self::GroupMetadata {
+                group_id: String::decode(buf)?,
+            }),
+            _ => Err(format!("Unknown group record key version: {}", version)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffsetAndMetadata {
+    pub offset: i64,
+    pub leader_epoch: i32,
+    pub metadata: String,
+    pub commit_timestamp: i64,
+}
+
+impl Type for OffsetAndMetadata {
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        OFFSET_COMMIT_VALUE_VERSION.encode(buf);
+        self.offset.encode(buf);
+        self.leader_epoch.encode(buf);
+        self.metadata.encode(buf);
+        self.commit_timestamp.encode(buf);
+    }
+
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let version = i16::decode(buf)?;
+        if version != OFFSET_COMMIT_VALUE_VERSION {
+            return Err(format!("Unknown offset commit value version: {}", version));
+        }
+        Ok(Self {
+            offset: i64::decode(buf)?,
+            leader_epoch: i32::decode(buf)?,
+            metadata: String::decode(buf)?,
+            commit_timestamp: i64::decode(buf)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberMetadataValue {
+    pub member_id: String,
+    pub client_id: String,
+    pub client_host: String,
+    pub rebalance_timeout_ms: i32,
+    pub session_timeout_ms: i32,
+    pub subscription: Vec<u8>,
+    pub assignment: Vec<u8>,
+}
+
+fn encode_bytes<B: BufMut>(buf: &mut B, bytes: &[u8]) {
+    (bytes.len() as i32).encode(buf);
+    buf.put_slice(bytes);
+}
+
+fn decode_bytes<B: Buf>(buf: &mut B) -> Result<Vec<u8>, String> {
+    let len = i32::decode(buf)?;
+    if len < 0 {
+        return Ok(vec![]);
+    }
+    if buf.remaining() < len as usize {
+        return Err("Not enough data for bytes".to_string());
+    }
+    let mut bytes = vec![0u8; len as usize];
+    buf.copy_to_slice(&mut bytes);
+    Ok(bytes)
+}
+
+impl Type for MemberMetadataValue {
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.member_id.encode(buf);
+        self.client_id.encode(buf);
+        self.client_host.encode(buf);
+        self.rebalance_timeout_ms.encode(buf);
+        self.session_timeout_ms.encode(buf);
+        encode_bytes(buf, &self.subscription);
+        encode_bytes(buf, &self.assignment);
+    }
+
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            member_id: String::decode(buf)?,
+            client_id: String::decode(buf)?,
+            client_host: String::decode(buf)?,
+            rebalance_timeout_ms: i32::decode(buf)?,
+            session_timeout_ms: i32::decode(buf)?,
+            subscription: decode_bytes(buf)?,
+            assignment: decode_bytes(buf)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupMetadataValue {
+    pub protocol_type: String,
+    pub generation_id: i32,
+    /// Empty when the group has no members.
+    pub protocol_name: String,
+    pub leader_id: String,
+    pub current_state_timestamp: i64,
+    pub members: Vec<MemberMetadataValue>,
+}
+
+impl Type for GroupMetadataValue {
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        GROUP_METADATA_VALUE_VERSION.encode(buf);
+        self.protocol_type.encode(buf);
+        self.generation_id.encode(buf);
+        self.protocol_name.encode(buf);
+        self.leader_id.encode(buf);
+        self.current_state_timestamp.encode(buf);
+        (self.members.len() as i32).encode(buf);
+        for member in &self.members {
+            member.encode(buf);
+        }
+    }
+
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let version = i16::decode(buf)?;
+        if version != GROUP_METADATA_VALUE_VERSION {
+            return Err(format!("Unknown group metadata value version: {}", version));
+        }
+
+        let protocol_type = String::decode(buf)?;
+        let generation_id = i32::decode(buf)?;
+        let protocol_name = String::decode(buf)?;
+        let leader_id = String::decode(buf)?;
+        let current_state_timestamp = i64::decode(buf)?;
+
+        let members_len = i32::decode(buf)?;
+        let mut members = Vec::with_capacity(capacity_for::<MemberMetadataValue, B>(
+            members_len.max(0) as usize,
+            buf,
+        ));
+        for _ in 0..members_len {
+            members.push(MemberMetadataValue::decode(buf)?);
+        }
+
+        Ok(Self {
+            protocol_type,
+            generation_id,
+            protocol_name,
+            leader_id,
+            current_state_timestamp,
+            members,
+        })
+    }
+}
@@ -1,12 +1,23 @@
 use bytes::{Buf, BufMut};
+use uuid::Uuid;
 
-use crate::protocol::types::Type;
+use crate::protocol::types::{Type, capacity_for};
+
+pub const NO_LEADER: i32 = -1;
+
+pub const CONFIG_RESOURCE_TOPIC: i8 = 2;
+pub const CONFIG_RESOURCE_BROKER: i8 = 4;
+/// Logger levels of one broker: applied by that broker alone and never recorded here.
+pub const CONFIG_RESOURCE_BROKER_LOGGER: i8 = 8;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum MetadataRecord {
     RegisterBroker(RegisterBrokerRecord),
     Topic(TopicRecord),
     Partition(PartitionRecord),
+    PartitionChange(PartitionChangeRecord),
+    FenceBroker(FenceBrokerRecord),
+    Config(ConfigRecord),
 }
 
 impl MetadataRecord {
@@ -15,6 +26,9 @@ impl MetadataRecord {
             Self::RegisterBroker(_) => 27,
             Self::Topic(_) => 2,
             Self::Partition(_) => 3,
+            Self::Config(_) => 4,
+            Self::PartitionChange(_) => 5,
+            Self::FenceBroker(_) => 7,
         }
     }
 }
@@ -26,6 +40,9 @@ impl Type for MetadataRecord {
             Self::RegisterBroker(r) => r.encode(buf),
             Self::Topic(r) => r.encode(buf),
             Self::Partition(r) => r.encode(buf),
+            Self::PartitionChange(r) => r.encode(buf),
+            Self::FenceBroker(r) => r.encode(buf),
+            Self::Config(r) => r.encode(buf),
         }
     }
 
@@ -35,6 +52,9 @@ impl Type for MetadataRecord {
             27 => Ok(Self::RegisterBroker(RegisterBrokerRecord::decode(buf)?)),
             2 => Ok(Self::Topic(TopicRecord::decode(buf)?)),
             3 => Ok(Self::Partition(PartitionRecord::decode(buf)?)),
+            4 => Ok(Self::Config(ConfigRecord::decode(buf)?)),
+            5 => Ok(Self::PartitionChange(PartitionChangeRecord::decode(buf)?)),
+            7 => Ok(Self::FenceBroker(FenceBrokerRecord::decode(buf)?)),
             _ => Err(format!("Unknown metadata record type: {}", record_type)),
         }
     }
@@ -43,22 +63,30 @@ impl Type for MetadataRecord {
 #[derive(Debug, Clone, PartialEq)]
 pub struct RegisterBrokerRecord {
     pub broker_id: i32,
+    /// Generated each time the broker process starts, so a restart is told apart from a
+    /// second process claiming the same broker id.
+    pub incarnation_id: Uuid,
     pub host: String,
     pub port: i32,
+    pub rack: Option<String>,
 }
 
 impl Type for RegisterBrokerRecord {
     fn encode<B: BufMut>(&self, buf: &mut B) {
         self.broker_id.encode(buf);
+        self.incarnation_id.encode(buf);
         self.host.encode(buf);
         self.port.encode(buf);
+        self.rack.encode(buf);
     }
 
     fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
         Ok(Self {
             broker_id: i32::decode(buf)?,
+            incarnation_id: Uuid::decode(buf)?,
             host: String::decode(buf)?,
             port: i32::decode(buf)?,
+            rack: Option::<String>::decode(buf)?,
         })
     }
 }
@@ -81,7 +109,10 @@ impl Type for TopicRecord {
     fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
         let topic_name = String::decode(buf)?;
         let partitions_len = i32::decode(buf)?;
-        let mut partitions = Vec::with_capacity(partitions_len as usize);
+        let mut partitions = Vec::with_capacity(capacity_for::<PartitionRecord, B>(
+            partitions_len.max(0) as usize,
+            buf,
+        ));
         for _ in 0..partitions_len {
             partitions.push(PartitionRecord::decode(buf)?);
         }
@@ -96,37 +127,162 @@ impl Type for TopicRecord {
 pub struct PartitionRecord {
     pub topic_name: String,
     pub partition_index: i32,
-    pub leader: String,
-    pub replicas: Vec<String>,
+    /// Assigned replicas in preference order; the first live in-sync one is the preferred leader.
+    pub replicas: Vec<i32>,
+    pub isr: Vec<i32>,
+    /// `NO_LEADER` while the partition is offline.
+    pub leader: i32,
+    pub leader_epoch: i32,
+    /// Replicas a reassignment is moving onto the partition; they are part of `replicas`
+    /// until it completes.
+    pub adding_replicas: Vec<i32>,
+    /// Replicas a reassignment drops once every adding replica has joined the ISR.
+    pub removing_replicas: Vec<i32>,
+}
+
+impl PartitionRecord {
+    pub fn is_reassigning(&self) -> bool {
+        !self.adding_replicas.is_empty() || !self.removing_replicas.is_empty()
+    }
+
+    /// The replicas the partition ends up with once its reassignment completes.
+    pub fn target_replicas(&self) -> Vec<i32> {
+        self.replicas
+            .iter()
+            .copied()
+            .filter(|id| !self.removing_replicas.contains(id))
+            .collect()
+    }
 }
 
 impl Type for PartitionRecord {
     fn encode<B: BufMut>(&self, buf: &mut B) {
         self.topic_name.encode(buf);
         self.partition_index.encode(buf);
+        self.replicas.encode(buf);
+        self.isr.encode(buf);
         self.leader.encode(buf);
-        (self.replicas.len() as i32).encode(buf);
-        for replica in &self.replicas {
-            replica.encode(buf);
-        }
+        self.leader_epoch.encode(buf);
+        self.adding_replicas.encode(buf);
+        self.removing_replicas.encode(buf);
     }
 
     fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
-        let topic_name = String::decode(buf)?;
-        let partition_index = i32::decode(buf)?;
-        let leader = String::decode(buf)?;
+        Ok(Self {
+            topic_name: String::decode(buf)?,
+            partition_index: i32::decode(buf)?,
+            replicas: Vec::<i32>::decode(buf)?,
+            isr: Vec::<i32>::decode(buf)?,
+            leader: i32::decode(buf)?,
+            leader_epoch: i32::decode(buf)?,
+            adding_replicas: Vec::<i32>::decode(buf)?,
+            removing_replicas: Vec::<i32>::decode(buf)?,
+        })
+    }
+}
 
-        let replicas_len = i32::decode(buf)?;
-        let mut replicas = Vec::with_capacity(replicas_len as usize);
-        for _ in 0..replicas_len {
-            replicas.push(String::decode(buf)?);
+/// A leader, ISR or replica change to an existing partition. The replica lists are `None`
+/// when unchanged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionChangeRecord {
+    pub topic_name: String,
+    pub partition_index: i32,
+    pub isr: Vec<i32>,
+    pub leader: i32,
+    pub leader_epoch: i32,
+    pub replicas: Option<Vec<i32>>,
+    pub adding_replicas: Option<Vec<i32>>,
+    pub removing_replicas: Option<Vec<i32>>,
+}
+
+impl PartitionChangeRecord {
+    /// Moves the leader and ISR of `partition`, leaving its replicas as they are.
+    pub fn leader_change(
+        partition: &PartitionRecord,
+        isr: Vec<i32>,
+        leader: i32,
+        leader_epoch: i32,
+    ) -> Self {
+        Self {
+            topic_name: partition.topic_name.clone(),
+            partition_index: partition.partition_index,
+            isr,
+            leader,
+            leader_epoch,
+            replicas: None,
+            adding_replicas: None,
+            removing_replicas: None,
         }
+    }
+}
+
+impl Type for PartitionChangeRecord {
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.topic_name.encode(buf);
+        self.partition_index.encode(buf);
+        self.isr.encode(buf);
+        self.leader.encode(buf);
+        self.leader_epoch.encode(buf);
+        self.replicas.encode(buf);
+        self.adding_replicas.encode(buf);
+        self.removing_replicas.encode(buf);
+    }
 
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
         Ok(Self {
-            topic_name,
-            partition_index,
-            leader,
-            replicas,
+            topic_name: String::decode(buf)?,
+            partition_index: i32::decode(buf)?,
+            isr: Vec::<i32>::decode(buf)?,
+            leader: i32::decode(buf)?,
+            leader_epoch: i32::decode(buf)?,
+            replicas: Option::<Vec<i32>>::decode(buf)?,
+            adding_replicas: Option::<Vec<i32>>::decode(buf)?,
+            removing_replicas: Option::<Vec<i32>>::decode(buf)?,
+        })
+    }
+}
+
+/// Marks a broker unavailable; it stays fenced until it registers again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FenceBrokerRecord {
+    pub broker_id: i32,
+}
+
+impl Type for FenceBrokerRecord {
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.broker_id.encode(buf);
+    }
+
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            broker_id: i32::decode(buf)?,
+        })
+    }
+}
+
+/// Sets (or with `value: None`, removes) one config override on a topic or broker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigRecord {
+    pub resource_type: i8,
+    pub resource_name: String,
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl Type for ConfigRecord {
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.resource_type.encode(buf);
+        self.resource_name.encode(buf);
+        self.name.encode(buf);
+        self.value.encode(buf);
+    }
+
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            resource_type: i8::decode(buf)?,
+            resource_name: String::decode(buf)?,
+            name: String::decode(buf)?,
+            value: Option::<String>::decode(buf)?,
         })
     }
 }
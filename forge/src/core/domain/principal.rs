@@ -0,0 +1,30 @@
+use std::fmt;
+
+pub const USER_PRINCIPAL_TYPE: &str = "User";
+
+/// The authenticated identity behind a connection, rendered as `type:name`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KafkaPrincipal {
+    pub principal_type: String,
+    pub name: String,
+}
+
+impl KafkaPrincipal {
+    pub fn user(name: impl Into<String>) -> Self {
+        Self {
+            principal_type: USER_PRINCIPAL_TYPE.to_string(),
+            name: name.into(),
+        }
+    }
+
+    /// The principal of connections that did not authenticate.
+    pub fn anonymous() -> Self {
+        Self::user("ANONYMOUS")
+    }
+}
+
+impl fmt::Display for KafkaPrincipal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.principal_type, self.name)
+    }
+}
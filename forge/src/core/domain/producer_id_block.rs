@@ -0,0 +1,44 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+const PRODUCER_ID_BLOCK_VERSION: i16 = 0;
+
+/// A contiguous range of producer ids reserved for one broker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProducerIdBlock {
+    pub assigned_broker_id: i32,
+    pub first_producer_id: i64,
+    pub size: i64,
+}
+
+impl ProducerIdBlock {
+    pub fn last_producer_id(&self) -> i64 {
+        self.first_producer_id + self.size - 1
+    }
+
+    pub fn next_block_first_id(&self) -> i64 {
+        self.first_producer_id + self.size
+    }
+}
+
+impl Type for ProducerIdBlock {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let version = i16::decode(buf)?;
+        if version != PRODUCER_ID_BLOCK_VERSION {
+            return Err(format!("Unknown producer id block version: {}", version));
+        }
+        Ok(Self {
+            assigned_broker_id: i32::decode(buf)?,
+            first_producer_id: i64::decode(buf)?,
+            size: i64::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        PRODUCER_ID_BLOCK_VERSION.encode(buf);
+        self.assigned_broker_id.encode(buf);
+        self.first_producer_id.encode(buf);
+        self.size.encode(buf);
+    }
+}
@@ -1,15 +1,16 @@
-use crate::protocol::types::{Type, Varint, Varlong};
-use crate::shared::byte::{decode_nullable_bytes, encode_nullable_bytes};
+use crate::protocol::types::{ByteStr, Type, Varint, Varlong, capacity_for};
+use crate::shared::byte::{decode_nullable_bytes, encode_nullable_bytes, nullable_bytes_size};
 use bytes::{Buf, BufMut};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Header {
-    pub key: String,
+    pub key: ByteStr,
     pub value: Option<Vec<u8>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Record {
+    /// As decoded; encoding writes the record's actual size instead.
     pub length: Varint,
     pub attributes: i8,
     pub timestamp_delta: Varlong,
@@ -19,6 +20,36 @@ pub struct Record {
     pub headers: Vec<Header>,
 }
 
+impl Record {
+    pub fn new(offset_delta: i32, key: Option<Vec<u8>>, value: Option<Vec<u8>>) -> Self {
+        Self {
+            length: Varint(0),
+            attributes: 0,
+            timestamp_delta: Varlong(0),
+            offset_delta: Varint(offset_delta),
+            key,
+            value,
+            headers: vec![],
+        }
+    }
+
+    /// The encoded size of the record after its length, which is what the length holds.
+    pub fn size(&self) -> usize {
+        let mut size = size_of::<i8>()
+            + self.timestamp_delta.size()
+            + self.offset_delta.size()
+            + nullable_bytes_size(&self.key)
+            + nullable_bytes_size(&self.value)
+            + Varint(self.headers.len() as i32).size();
+        for header in &self.headers {
+            size += Varint(header.key.len() as i32).size()
+                + header.key.len()
+                + nullable_bytes_size(&header.value);
+        }
+        size
+    }
+}
+
 impl Type for Record {
     fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
         let length = Varint::decode(buf)?;
@@ -35,20 +66,12 @@ impl Type for Record {
         let value = decode_nullable_bytes(buf)?;
 
         let headers_count = Varint::decode(buf)?;
-        let mut headers = Vec::new();
+        let mut headers = Vec::with_capacity(capacity_for::<Header, B>(
+            headers_count.0.max(0) as usize,
+            buf,
+        ));
         for _ in 0..headers_count.0 {
-            let h_key_len = Varint::decode(buf)?;
-            if h_key_len.0 < 0 {
-                return Err("Not enough data for Header key length".to_string());
-            }
-
-            if buf.remaining() < h_key_len.0 as usize {
-                return Err("Not enough data for Header key".to_string());
-            }
-            let mut hk_bytes = vec![0; h_key_len.0 as usize];
-            buf.copy_to_slice(&mut hk_bytes);
-            let h_key = String::from_utf8(hk_bytes).map_err(|_| "Invalid UTF-8 for Header key")?;
-
+            let h_key = ByteStr::decode_varint(buf, "Header key")?;
             let h_value = decode_nullable_bytes(buf)?;
 
             headers.push(Header {
@@ -69,7 +92,7 @@ impl Type for Record {
     }
 
     fn encode<B: BufMut>(&self, buf: &mut B) {
-        self.length.encode(buf);
+        Varint(self.size() as i32).encode(buf);
         self.attributes.encode(buf);
         self.timestamp_delta.encode(buf);
         self.offset_delta.encode(buf);
@@ -79,8 +102,7 @@ impl Type for Record {
 
         Varint(self.headers.len() as i32).encode(buf);
         for header in &self.headers {
-            Varint(header.key.len() as i32).encode(buf);
-            buf.put_slice(header.key.as_bytes());
+            header.key.encode_varint(buf);
             encode_nullable_bytes(buf, &header.value);
         }
     }
@@ -1,7 +1,8 @@
+use crate::core::domain::compression::CompressionType;
 use crate::core::domain::record::Record;
-use crate::protocol::types::Type;
-use bytes::{Buf, BufMut};
-use crc32fast::Hasher;
+use crate::protocol::types::{Type, capacity_for};
+use crate::shared::buffer_pool;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct RecordBatch {
@@ -25,10 +26,262 @@ const PARTITION_LEADER_EPOCH_SIZE: usize = 4;
 const MAGIC_SIZE: usize = 1;
 const CRC_SIZE: usize = 4;
 const HEADER_SIZE: usize = PARTITION_LEADER_EPOCH_SIZE + MAGIC_SIZE + CRC_SIZE;
+/// Attributes through records count: the part of the payload that is never compressed.
+const RECORDS_HEADER_SIZE: usize = 2 + 4 + 8 + 8 + 8 + 2 + 4 + 4;
 
 pub const BATCH_HEADER_SIZE: usize = 8 + 4;
 pub const BATCH_LENGTH_OFFSET: usize = 8;
 
+/// Set when the broker, not the producer, stamped the records.
+pub const LOG_APPEND_TIME_FLAG_MASK: i16 = 0x08;
+pub const TRANSACTIONAL_FLAG_MASK: i16 = 0x10;
+pub const CONTROL_FLAG_MASK: i16 = 0x20;
+/// The base sequence of a batch that is not idempotent, or that a coordinator wrote on a
+/// producer's behalf.
+pub const NO_SEQUENCE: i32 = -1;
+
+impl RecordBatch {
+    /// Builds a non-transactional magic v2 batch; offsets and epoch are assigned on append.
+    pub fn new(base_timestamp: i64, records: Vec<Record>) -> Self {
+        let records_count = records.len() as i32;
+        Self {
+            base_offset: 0,
+            batch_length: 0,
+            partition_leader_epoch: 0,
+            magic: 2,
+            crc: 0,
+            attributes: 0,
+            last_offset_delta: (records_count - 1).max(0),
+            base_timestamp,
+            max_timestamp: base_timestamp,
+            producer_id: -1,
+            producer_epoch: -1,
+            base_sequence: NO_SEQUENCE,
+            records_count,
+            records,
+        }
+    }
+
+    pub fn is_log_append_time(&self) -> bool {
+        self.attributes & LOG_APPEND_TIME_FLAG_MASK != 0
+    }
+
+    pub fn is_transactional(&self) -> bool {
+        self.attributes & TRANSACTIONAL_FLAG_MASK != 0
+    }
+
+    pub fn is_control_batch(&self) -> bool {
+        self.attributes & CONTROL_FLAG_MASK != 0
+    }
+
+    pub fn last_offset(&self) -> i64 {
+        self.base_offset + self.last_offset_delta as i64
+    }
+
+    /// Encodes straight into `buf`: the length and CRC are written as zeros and filled in
+    /// once the payload is there, so unlike a plain `BufMut` it needs no buffer in between.
+    pub fn encode_to(&self, buf: &mut BytesMut) {
+        let start = buf.len();
+        self.base_offset.encode(buf);
+        0i32.encode(buf);
+        self.partition_leader_epoch.encode(buf);
+        self.magic.encode(buf);
+        0u32.encode(buf);
+
+        let payload_start = buf.len();
+        self.attributes.encode(buf);
+        self.last_offset_delta.encode(buf);
+        self.base_timestamp.encode(buf);
+        self.max_timestamp.encode(buf);
+        self.producer_id.encode(buf);
+        self.producer_epoch.encode(buf);
+        self.base_sequence.encode(buf);
+        self.records_count.encode(buf);
+
+        match CompressionType::from_attributes(self.attributes) {
+            Ok(CompressionType::None) | Err(_) => {
+                for record in &self.records {
+                    record.encode(buf);
+                }
+            }
+            Ok(compression) => {
+                let mut records = buffer_pool::take();
+                for record in &self.records {
+                    record.encode(&mut records);
+                }
+                let compressed = compression
+                    .compress(&records)
+                    .expect("Compressing into memory does not fail");
+                buffer_pool::recycle(records);
+                buf.put_slice(&compressed);
+            }
+        }
+
+        let batch_length = (buf.len() - start - BATCH_HEADER_SIZE) as i32;
+        buf[start + BATCH_LENGTH_OFFSET..start + BATCH_HEADER_SIZE]
+            .copy_from_slice(&batch_length.to_be_bytes());
+        let crc = crc32c::crc32c(&buf[payload_start..]);
+        buf[payload_start - CRC_SIZE..payload_start].copy_from_slice(&crc.to_be_bytes());
+    }
+
+    /// Checks the CRC-32C of an encoded batch, from its base offset on, without decoding
+    /// its records. The CRC covers the attributes through the end of the batch.
+    pub fn verify_checksum(batch: &[u8]) -> Result<(), String> {
+        let mut header = batch
+            .get(..BATCH_HEADER_SIZE + HEADER_SIZE)
+            .ok_or("Not enough data for a record batch header")?;
+        header.advance(BATCH_LENGTH_OFFSET);
+        let batch_length = header.get_i32();
+        header.advance(PARTITION_LEADER_EPOCH_SIZE + MAGIC_SIZE);
+        let payload_len = payload_len(batch_length)?;
+        let payload = batch
+            .get(BATCH_HEADER_SIZE + HEADER_SIZE..)
+            .and_then(|payload| payload.get(..payload_len))
+            .ok_or("Not enough data for record batch payload")?;
+        check_crc(payload, header.get_u32())
+    }
+
+    /// Replaces the CRC-32 (IEEE) that Forge wrote before it checksummed batches with
+    /// CRC-32C, as Kafka does, by the CRC-32C of the same bytes. Leaves the batch alone and
+    /// returns false when it does not carry a valid legacy checksum.
+    pub fn upgrade_legacy_checksum(batch: &mut [u8]) -> bool {
+        let Some(mut header) = batch.get(..BATCH_HEADER_SIZE + HEADER_SIZE) else {
+            return false;
+        };
+        header.advance(BATCH_LENGTH_OFFSET);
+        let batch_length = header.get_i32();
+        header.advance(PARTITION_LEADER_EPOCH_SIZE + MAGIC_SIZE);
+        let crc = header.get_u32();
+        let Some(payload) = payload_len(batch_length).ok().and_then(|payload_len| {
+            batch
+                .get(BATCH_HEADER_SIZE + HEADER_SIZE..)
+                .and_then(|payload| payload.get(..payload_len))
+        }) else {
+            return false;
+        };
+        if crc32fast::hash(payload) != crc {
+            return false;
+        }
+        let crc = crc32c::crc32c(payload);
+        let crc_offset = BATCH_HEADER_SIZE + HEADER_SIZE - CRC_SIZE;
+        batch[crc_offset..crc_offset + CRC_SIZE].copy_from_slice(&crc.to_be_bytes());
+        true
+    }
+
+    /// The base and last offset of an encoded batch, read from its header without decoding
+    /// the rest.
+    pub fn peek_offsets(batch: &[u8]) -> Result<(i64, i64), String> {
+        let mut header = batch
+            .get(..BATCH_HEADER_SIZE + HEADER_SIZE + 2 + 4)
+            .ok_or("Not enough data for a record batch header")?;
+        let base_offset = header.get_i64();
+        // Batch length, partition leader epoch, magic, CRC and attributes
+        header.advance(4 + HEADER_SIZE + 2);
+        Ok((base_offset, base_offset + header.get_i32() as i64))
+    }
+
+    /// Leaves out the records before `offset`, as a read from the middle of the batch wants.
+    /// The base offset stays, so the records keep theirs.
+    pub fn drop_records_before(&mut self, offset: i64) {
+        let base_offset = self.base_offset;
+        self.records
+            .retain(|record| base_offset + record.offset_delta.0 as i64 >= offset);
+        self.records_count = self.records.len() as i32;
+    }
+
+    /// The partition leader epoch of an encoded batch, read off its header.
+    pub fn peek_partition_leader_epoch(batch: &[u8]) -> Result<i32, String> {
+        let mut header = batch
+            .get(12..16)
+            .ok_or("Not enough data for a record batch header")?;
+        Ok(header.get_i32())
+    }
+}
+
+/// Encoded batches, each exactly as it sits in the log and goes on the wire. Fetches pass
+/// them from the log to the socket as they are, without decoding and encoding them again.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Records(Vec<Bytes>);
+
+impl Records {
+    /// `batches` must each hold one whole encoded batch.
+    pub fn from_raw(batches: Vec<Bytes>) -> Self {
+        Self(batches)
+    }
+
+    pub fn encode(batches: &[RecordBatch]) -> Self {
+        let mut buf = BytesMut::new();
+        Self(
+            batches
+                .iter()
+                .map(|batch| {
+                    batch.encode_to(&mut buf);
+                    buf.split().freeze()
+                })
+                .collect(),
+        )
+    }
+
+    /// Splits a `records` field into its batches without copying them. A trailing partial
+    /// batch, which brokers may return when a fetch hits its byte limit, is dropped.
+    pub fn split(mut records: Bytes) -> Self {
+        let mut batches = Vec::new();
+        while records.len() >= BATCH_HEADER_SIZE {
+            let batch_length = i32::from_be_bytes(
+                records[BATCH_LENGTH_OFFSET..BATCH_HEADER_SIZE]
+                    .try_into()
+                    .unwrap(),
+            );
+            if batch_length < 0 || records.len() < BATCH_HEADER_SIZE + batch_length as usize {
+                break;
+            }
+            batches.push(records.split_to(BATCH_HEADER_SIZE + batch_length as usize));
+        }
+        Self(batches)
+    }
+
+    pub fn raw(&self) -> &[Bytes] {
+        &self.0
+    }
+
+    /// The number of batches.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn size_in_bytes(&self) -> usize {
+        self.0.iter().map(Bytes::len).sum()
+    }
+
+    /// Checks each batch's CRC and decodes it.
+    pub fn batches(&self) -> Result<Vec<RecordBatch>, String> {
+        self.0
+            .iter()
+            .map(|batch| RecordBatch::decode(&mut batch.clone()))
+            .collect()
+    }
+}
+
+fn payload_len(batch_length: i32) -> Result<usize, String> {
+    usize::try_from(batch_length)
+        .ok()
+        .and_then(|length| length.checked_sub(HEADER_SIZE))
+        .filter(|length| *length >= RECORDS_HEADER_SIZE)
+        .ok_or_else(|| format!("Invalid record batch length {}", batch_length))
+}
+
+fn check_crc(payload: &[u8], crc: u32) -> Result<(), String> {
+    // crc32c uses the CPU's CRC instructions where there are any
+    if crc32c::crc32c(payload) != crc {
+        return Err("CRC check failed".to_string());
+    }
+    Ok(())
+}
+
 impl Type for RecordBatch {
     fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
         let base_offset = i64::decode(buf)?;
@@ -37,18 +290,12 @@ impl Type for RecordBatch {
         let magic = i8::decode(buf)?;
         let crc = u32::decode(buf)?;
 
+        let expected_payload_len = payload_len(batch_length)?;
         let buf_bytes = buf.chunk();
-        let expected_payload_len = batch_length as usize - HEADER_SIZE;
         if buf_bytes.len() < expected_payload_len {
             return Err("Not enough data for record batch payload".to_string());
         }
-
-        let mut hasher = Hasher::new();
-        hasher.update(&buf_bytes[..expected_payload_len]);
-        let calculated_crc = hasher.finalize();
-        if calculated_crc != crc {
-            return Err("CRC check failed".to_string());
-        }
+        check_crc(&buf_bytes[..expected_payload_len], crc)?;
 
         let attributes = i16::decode(buf)?;
         let last_offset_delta = i32::decode(buf)?;
@@ -58,10 +305,28 @@ impl Type for RecordBatch {
         let producer_epoch = i16::decode(buf)?;
         let base_sequence = i32::decode(buf)?;
         let records_count = i32::decode(buf)?;
+        if records_count < 0 {
+            return Err(format!("Invalid record count {}", records_count));
+        }
 
-        let mut records = Vec::with_capacity(records_count as usize);
+        // Records may not run past the batch, whatever their counts and lengths claim
+        let records_len = expected_payload_len - RECORDS_HEADER_SIZE;
+        // Records decode from `Bytes` so their header keys can share it
+        let compression = CompressionType::from_attributes(attributes)?;
+        let mut records_buf = match compression {
+            CompressionType::None => buf.copy_to_bytes(records_len),
+            compression => {
+                let decompressed = compression.decompress(&buf.chunk()[..records_len])?;
+                buf.advance(records_len);
+                Bytes::from(decompressed)
+            }
+        };
+        let mut records = Vec::with_capacity(capacity_for::<Record, _>(
+            records_count as usize,
+            &records_buf,
+        ));
         for _ in 0..records_count {
-            records.push(Record::decode(buf)?);
+            records.push(Record::decode(&mut records_buf)?);
         }
 
         Ok(RecordBatch {
@@ -83,33 +348,10 @@ impl Type for RecordBatch {
     }
 
     fn encode<B: BufMut>(&self, buf: &mut B) {
-        let mut temp_buf = Vec::new();
-
-        self.attributes.encode(&mut temp_buf);
-        self.last_offset_delta.encode(&mut temp_buf);
-        self.base_timestamp.encode(&mut temp_buf);
-        self.max_timestamp.encode(&mut temp_buf);
-        self.producer_id.encode(&mut temp_buf);
-        self.producer_epoch.encode(&mut temp_buf);
-        self.base_sequence.encode(&mut temp_buf);
-        self.records_count.encode(&mut temp_buf);
-
-        for record in &self.records {
-            record.encode(&mut temp_buf);
-        }
-
-        let batch_length = (HEADER_SIZE + temp_buf.len()) as i32;
-        let mut hasher = Hasher::new();
-        hasher.update(&temp_buf);
-        let crc = hasher.finalize();
-
-        self.base_offset.encode(buf);
-        batch_length.encode(buf);
-        self.partition_leader_epoch.encode(buf);
-        self.magic.encode(buf);
-        crc.encode(buf);
-
-        buf.put_slice(&temp_buf);
+        let mut encoded = buffer_pool::take();
+        self.encode_to(&mut encoded);
+        buf.put_slice(&encoded);
+        buffer_pool::recycle(encoded);
     }
 }
 
@@ -118,7 +360,6 @@ mod tests {
     use super::*;
     use crate::core::domain::record::Header;
     use crate::protocol::types::{Varint, Varlong};
-    use bytes::BytesMut;
 
     #[test]
     fn test_record_batch_roundtrip() {
@@ -131,7 +372,7 @@ mod tests {
             key: Some(b"hello_key".to_vec()),
             value: Some(b"world_value".to_vec()),
             headers: vec![Header {
-                key: "header1".to_string(),
+                key: "header1".into(),
                 value: Some(b"header_val".to_vec()),
             }],
         };
@@ -156,7 +397,7 @@ mod tests {
             key: None,
             value: Some(vec![]),
             headers: vec![Header {
-                key: "empty_header".to_string(),
+                key: "empty_header".into(),
                 value: None,
             }],
         };
@@ -217,6 +458,11 @@ mod tests {
             decoded_batch.crc != 0,
             "CRC32C checksum should be computed and strictly != 0"
         );
+        // attributes, timestamp delta, offset delta, key, value and header count
+        assert_eq!(
+            decoded_batch.records[1].length,
+            Varint(1 + 2 + 1 + 14 + 1 + 1)
+        );
 
         // Verify Record 1 (Full payload)
         let decoded_record1 = &decoded_batch.records[0];
@@ -248,4 +494,34 @@ mod tests {
             decoded_record3.headers[0].value
         ); // Should be None
     }
+
+    #[test]
+    fn test_verify_checksum() {
+        let batch = RecordBatch::new(0, vec![Record::new(0, None, Some(b"value".to_vec()))]);
+        let mut buffer = BytesMut::new();
+        batch.encode(&mut buffer);
+        assert!(RecordBatch::verify_checksum(&buffer).is_ok());
+
+        let last = buffer.len() - 1;
+        buffer[last] ^= 1;
+        assert!(RecordBatch::verify_checksum(&buffer).is_err());
+        assert!(RecordBatch::verify_checksum(&buffer[..last]).is_err());
+    }
+
+    #[test]
+    fn test_header_keys_share_the_decoded_buffer() {
+        let mut record = Record::new(0, None, Some(b"value".to_vec()));
+        record.headers = vec![Header {
+            key: "trace-id".into(),
+            value: Some(b"abc".to_vec()),
+        }];
+        let mut buffer = BytesMut::new();
+        RecordBatch::new(0, vec![record]).encode(&mut buffer);
+        let encoded = buffer.freeze();
+
+        let decoded = RecordBatch::decode(&mut encoded.clone()).unwrap();
+        let key = &decoded.records[0].headers[0].key;
+        assert_eq!(key, "trace-id");
+        assert!(encoded.as_ptr_range().contains(&key.as_ptr()));
+    }
 }
@@ -0,0 +1,76 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::fmt;
+use std::str::FromStr;
+
+/// Below this the salted password is cheap enough to brute-force, as in Kafka.
+pub const MIN_SCRAM_ITERATIONS: u32 = 4096;
+
+/// What the broker keeps of a SCRAM password (RFC 5802): enough to verify a client's proof and
+/// prove itself back, but not enough to log in as the user. Rendered as
+/// `salt=<base64>,stored_key=<base64>,server_key=<base64>,iterations=<n>`, the format Kafka's
+/// tools print.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScramCredential {
+    pub salt: Vec<u8>,
+    pub stored_key: Vec<u8>,
+    pub server_key: Vec<u8>,
+    pub iterations: u32,
+}
+
+impl fmt::Display for ScramCredential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "salt={},stored_key={},server_key={},iterations={}",
+            STANDARD.encode(&self.salt),
+            STANDARD.encode(&self.stored_key),
+            STANDARD.encode(&self.server_key),
+            self.iterations
+        )
+    }
+}
+
+impl FromStr for ScramCredential {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut salt, mut stored_key, mut server_key, mut iterations) = (None, None, None, None);
+        for field in s.split(',') {
+            let (name, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid SCRAM credential field {}", field))?;
+            let decode = |value: &str| {
+                STANDARD
+                    .decode(value)
+                    .map_err(|e| format!("Invalid base64 in SCRAM credential {}: {}", name, e))
+            };
+            match name {
+                "salt" => salt = Some(decode(value)?),
+                "stored_key" => stored_key = Some(decode(value)?),
+                "server_key" => server_key = Some(decode(value)?),
+                "iterations" => {
+                    iterations = Some(value.parse().map_err(|_| {
+                        format!("Invalid SCRAM iterations {}: expected a number", value)
+                    })?)
+                }
+                _ => return Err(format!("Unknown SCRAM credential field {}", name)),
+            }
+        }
+
+        let missing = |name: &str| format!("SCRAM credential is missing {}", name);
+        let credential = Self {
+            salt: salt.ok_or_else(|| missing("salt"))?,
+            stored_key: stored_key.ok_or_else(|| missing("stored_key"))?,
+            server_key: server_key.ok_or_else(|| missing("server_key"))?,
+            iterations: iterations.ok_or_else(|| missing("iterations"))?,
+        };
+        if credential.iterations < MIN_SCRAM_ITERATIONS {
+            return Err(format!(
+                "SCRAM iterations {} is below the minimum of {}",
+                credential.iterations, MIN_SCRAM_ITERATIONS
+            ));
+        }
+        Ok(credential)
+    }
+}
@@ -0,0 +1,139 @@
+use bytes::{Buf, BufMut};
+
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::protocol::types::{Type, capacity_for};
+
+const TRANSACTION_LOG_KEY_VERSION: i16 = 0;
+const TRANSACTION_LOG_VALUE_VERSION: i16 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionState {
+    /// Producer id assigned, no partitions added yet.
+    Empty = 0,
+    Ongoing = 1,
+    /// Commit decided and persisted; markers are still being written.
+    PrepareCommit = 2,
+    /// Abort decided and persisted; markers are still being written.
+    PrepareAbort = 3,
+    CompleteCommit = 4,
+    CompleteAbort = 5,
+    Dead = 6,
+    PrepareEpochFence = 7,
+}
+
+impl TransactionState {
+    pub fn from_id(id: i8) -> Result<Self, String> {
+        match id {
+            0 => Ok(Self::Empty),
+            1 => Ok(Self::Ongoing),
+            2 => Ok(Self::PrepareCommit),
+            3 => Ok(Self::PrepareAbort),
+            4 => Ok(Self::CompleteCommit),
+            5 => Ok(Self::CompleteAbort),
+            6 => Ok(Self::Dead),
+            7 => Ok(Self::PrepareEpochFence),
+            _ => Err(format!("Unknown transaction state: {}", id)),
+        }
+    }
+
+    pub fn valid_previous_states(self) -> &'static [TransactionState] {
+        use TransactionState::*;
+        match self {
+            Empty => &[Empty, CompleteCommit, CompleteAbort],
+            Ongoing => &[Ongoing, Empty, CompleteCommit, CompleteAbort],
+            PrepareCommit => &[Ongoing],
+            PrepareAbort => &[Ongoing, PrepareEpochFence],
+            CompleteCommit => &[PrepareCommit],
+            CompleteAbort => &[PrepareAbort],
+            Dead => &[Empty, CompleteCommit, CompleteAbort],
+            PrepareEpochFence => &[Ongoing],
+        }
+    }
+}
+
+/// Key of a record in `__transaction_state`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionLogKey {
+    pub transactional_id: String,
+}
+
+impl Type for TransactionLogKey {
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        TRANSACTION_LOG_KEY_VERSION.encode(buf);
+        self.transactional_id.encode(buf);
+    }
+
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let version = i16::decode(buf)?;
+        if version != TRANSACTION_LOG_KEY_VERSION {
+            return Err(format!("Unknown transaction log key version: {}", version));
+        }
+        Ok(Self {
+            transactional_id: String::decode(buf)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionLogValue {
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+    pub transaction_timeout_ms: i32,
+    pub state: TransactionState,
+    pub partitions: Vec<TopicPartition>,
+    pub last_update_timestamp: i64,
+    pub start_timestamp: i64,
+}
+
+impl Type for TransactionLogValue {
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        TRANSACTION_LOG_VALUE_VERSION.encode(buf);
+        self.producer_id.encode(buf);
+        self.producer_epoch.encode(buf);
+        self.transaction_timeout_ms.encode(buf);
+        (self.state as i8).encode(buf);
+        (self.partitions.len() as i32).encode(buf);
+        for tp in &self.partitions {
+            tp.topic.encode(buf);
+            tp.partition.encode(buf);
+        }
+        self.last_update_timestamp.encode(buf);
+        self.start_timestamp.encode(buf);
+    }
+
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let version = i16::decode(buf)?;
+        if version != TRANSACTION_LOG_VALUE_VERSION {
+            return Err(format!(
+                "Unknown transaction log value version: {}",
+                version
+            ));
+        }
+
+        let producer_id = i64::decode(buf)?;
+        let producer_epoch = i16::decode(buf)?;
+        let transaction_timeout_ms = i32::decode(buf)?;
+        let state = TransactionState::from_id(i8::decode(buf)?)?;
+
+        let partitions_len = i32::decode(buf)?;
+        let mut partitions = Vec::with_capacity(capacity_for::<TopicPartition, B>(
+            partitions_len.max(0) as usize,
+            buf,
+        ));
+        for _ in 0..partitions_len {
+            let topic = String::decode(buf)?;
+            let partition = i32::decode(buf)?;
+            partitions.push(TopicPartition::new(topic, partition));
+        }
+
+        Ok(Self {
+            producer_id,
+            producer_epoch,
+            transaction_timeout_ms,
+            state,
+            partitions,
+            last_update_timestamp: i64::decode(buf)?,
+            start_timestamp: i64::decode(buf)?,
+        })
+    }
+}
@@ -1 +1,121 @@
+use async_trait::async_trait;
+use uuid::Uuid;
 
+use crate::core::domain::acl::{AclBinding, AclOperation, Resource};
+use crate::core::domain::metadata_records::{MetadataRecord, RegisterBrokerRecord};
+use crate::core::domain::principal::KafkaPrincipal;
+use crate::core::domain::producer_id_block::ProducerIdBlock;
+use crate::core::domain::scram_credential::ScramCredential;
+use crate::core::error::ErrorCode;
+use crate::protocol::fetch::{FetchRequest, FetchResponse};
+
+/// Hands out producer id blocks that are never reused, even across restarts.
+#[async_trait]
+pub trait ProducerIdBlockSource: Send + Sync {
+    async fn allocate_block(
+        &mut self,
+        broker_id: i32,
+        block_size: i64,
+    ) -> Result<ProducerIdBlock, String>;
+}
+
+/// Sends Fetch requests to another broker on behalf of the follower replica fetcher.
+#[async_trait]
+pub trait FetchClient: Send {
+    async fn fetch(&mut self, request: &FetchRequest) -> Result<FetchResponse, String>;
+}
+
+/// Delivers metadata records written by the active controller to one broker, in log order.
+#[async_trait]
+pub trait MetadataPublisher: Send {
+    async fn publish(&mut self, offset: i64, records: &[MetadataRecord]) -> Result<(), String>;
+}
+
+/// A broker's channel to the active controller. `Ok` carries the controller's answer, `Err` a
+/// transport failure.
+#[async_trait]
+pub trait ControllerChannel: Send {
+    async fn register_broker(
+        &mut self,
+        registration: RegisterBrokerRecord,
+    ) -> Result<ErrorCode, String>;
+
+    async fn broker_heartbeat(
+        &mut self,
+        broker_id: i32,
+        incarnation_id: Uuid,
+    ) -> Result<ErrorCode, String>;
+
+    async fn create_topic(
+        &mut self,
+        topic_name: String,
+        num_partitions: i32,
+        replication_factor: i16,
+    ) -> Result<ErrorCode, String>;
+
+    /// Replaces the config overrides of a topic or broker, as AlterConfigs does.
+    async fn alter_configs(
+        &mut self,
+        resource_type: i8,
+        resource_name: String,
+        configs: Vec<(String, Option<String>)>,
+        validate_only: bool,
+    ) -> Result<ErrorCode, String>;
+
+    /// Moves a partition onto `target` replicas, or with `None` cancels its reassignment.
+    async fn alter_partition_reassignment(
+        &mut self,
+        topic_name: String,
+        partition_index: i32,
+        target: Option<Vec<i32>>,
+    ) -> Result<ErrorCode, String>;
+
+    /// Reports the ISR the partition leader `broker_id` arrived at during `leader_epoch`.
+    async fn alter_partition(
+        &mut self,
+        broker_id: i32,
+        topic_name: String,
+        partition_index: i32,
+        leader_epoch: i32,
+        isr: Vec<i32>,
+    ) -> Result<ErrorCode, String>;
+
+    /// Runs a preferred leader election for a partition, or with `unclean` elects a leader
+    /// for one that has none.
+    async fn elect_partition_leader(
+        &mut self,
+        topic_name: String,
+        partition_index: i32,
+        unclean: bool,
+    ) -> Result<ErrorCode, String>;
+}
+
+/// Decides whether a principal may perform an operation on a resource, and owns the ACLs
+/// behind that decision.
+#[async_trait]
+pub trait Authorizer: Send + Sync {
+    async fn authorize(
+        &self,
+        principal: &KafkaPrincipal,
+        host: &str,
+        operation: AclOperation,
+        resource: &Resource,
+    ) -> bool;
+
+    async fn acls(&self) -> Vec<AclBinding>;
+
+    async fn create_acls(&self, bindings: Vec<AclBinding>) -> Result<(), String>;
+
+    /// Removes every binding equal to one of `bindings` and returns how many were removed.
+    async fn delete_acls(&self, bindings: &[AclBinding]) -> Result<usize, String>;
+}
+
+/// Holds the user credentials SASL mechanisms check clients against.
+#[async_trait]
+pub trait CredentialStore: Send + Sync {
+    /// Whether `password` is the PLAIN password of `username`.
+    async fn verify_plain(&self, username: &str, password: &str) -> bool;
+
+    /// The salted credential of `username` for a SCRAM `mechanism`, e.g. `SCRAM-SHA-256`.
+    async fn scram_credential(&self, mechanism: &str, username: &str) -> Option<ScramCredential>;
+}
@@ -1,7 +1,10 @@
 pub mod adapters;
 pub mod application;
+pub mod client;
+pub mod config;
 pub mod consensus;
 pub mod core;
 pub mod protocol;
 pub mod shared;
+pub mod tools;
 pub use shared::logging;
@@ -0,0 +1,693 @@
+use clap::Parser;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use forge::adapters::driven::acl_authorizer::FileAclAuthorizer;
+use forge::adapters::driven::broker_client::BrokerClient;
+use forge::adapters::driven::credential_store::FileCredentialStore;
+use forge::adapters::driven::meta_properties::MetaProperties;
+use forge::adapters::driven::producer_id::LocalProducerIdBlockSource;
+use forge::adapters::driven::storage::file_system;
+use forge::adapters::driven::storage::log::PartitionLog;
+#[cfg(feature = "wasm")]
+use forge::adapters::driven::wasm_interceptor::WasmInterceptor;
+use forge::adapters::driving::admin_server::AdminServer;
+use forge::adapters::driving::connection_quotas::ConnectionQuotas;
+use forge::adapters::driving::connection_registry::ConnectionRegistry;
+use forge::adapters::driving::mqtt_bridge::MqttBridge;
+use forge::adapters::driving::request_dispatcher::RequestDispatcher;
+use forge::adapters::driving::rest_proxy::RestProxy;
+use forge::adapters::driving::sasl_authenticator::SaslListenerConfig;
+use forge::adapters::driving::tcp_server::TcpServer;
+use forge::application::admin_handler::AdminHandler;
+use forge::application::alter_configs_handler::AlterConfigsHandler;
+use forge::application::auto_topic_creation::AutoTopicCreationManager;
+use forge::application::broker_lifecycle::BrokerLifecycleManager;
+use forge::application::controller::QuorumController;
+use forge::application::dead_letter::DeadLetterQueue;
+use forge::application::describe_configs_handler::DescribeConfigsHandler;
+use forge::application::dynamic_config::DynamicBrokerConfig;
+use forge::application::fetch_handler::FetchHandler;
+use forge::application::group_coordinator::GroupCoordinator;
+use forge::application::group_handler::GroupHandler;
+use forge::application::list_offsets_handler::ListOffsetsHandler;
+use forge::application::log_metrics::LogMetrics;
+use forge::application::metadata_handler::MetadataHandler;
+use forge::application::metadata_listener::BrokerMetadataListener;
+use forge::application::produce_handler::ProduceHandler;
+use forge::application::producer_id_manager::ProducerIdManager;
+use forge::application::quota_manager::QuotaManager;
+#[cfg(feature = "wasm")]
+use forge::application::record_interceptor::InterceptorChain;
+use forge::application::record_interceptor::RecordInterceptor;
+use forge::application::record_validator::RecordValidators;
+use forge::application::replica_manager::ReplicaManager;
+use forge::application::sasl::scram::ScramMechanism;
+use forge::application::txn_coordinator::TransactionCoordinator;
+use forge::application::txn_handler::TxnHandler;
+use forge::config::{BrokerConfig, CONFIG_FILE_ENV, SecurityProtocol};
+use forge::consensus::node::Node;
+use forge::core::domain::metadata_records::RegisterBrokerRecord;
+use forge::core::ports::driven::{Authorizer, FetchClient};
+use forge::logging::LogLevelHandle;
+use forge::shared::constants::{
+    ACL_FILE, CLUSTER_METADATA_DIR, CREDENTIALS_FILE, DEFAULT_OFFSETS_RETENTION_CHECK_INTERVAL_MS,
+    DEFAULT_OFFSETS_TOPIC_PARTITIONS, DEFAULT_PRODUCER_ID_BLOCK_SIZE,
+    DEFAULT_PRODUCER_ID_EXPIRATION_CHECK_INTERVAL_MS, DEFAULT_QUOTA_WINDOW_NUM,
+    DEFAULT_QUOTA_WINDOW_SIZE_MS, DEFAULT_SCRAM_ITERATIONS,
+    DEFAULT_TRANSACTION_ABORT_CHECK_INTERVAL_MS, DEFAULT_TRANSACTION_MAX_TIMEOUT_MS,
+    DEFAULT_TRANSACTION_STATE_PARTITIONS, LOG_METRICS_REFRESH_INTERVAL_MS,
+};
+use forge::shared::metrics::MetricsSource;
+use forge::shared::rolling_file::RollingFile;
+use forge::shared::scheduler::spawn_named;
+
+const CONTROLLER_TICK_INTERVAL: Duration = Duration::from_millis(50);
+const ISR_CHANGE_PROPAGATION_INTERVAL: Duration = Duration::from_millis(2500);
+const GROUP_SESSION_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Runs a Forge broker. Settings come from, lowest precedence first: the properties file,
+/// `FORGE_*` environment variables (`FORGE_LOG_DIRS` sets `log.dirs`) and these flags.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    /// Kafka-style server.properties file.
+    #[arg(env = CONFIG_FILE_ENV)]
+    config: Option<PathBuf>,
+
+    /// Sets node.id.
+    #[arg(long)]
+    node_id: Option<i32>,
+
+    /// Sets listeners, e.g. PLAINTEXT://0.0.0.0:9092.
+    #[arg(long)]
+    listeners: Option<String>,
+
+    /// Sets advertised.listeners.
+    #[arg(long)]
+    advertised_listeners: Option<String>,
+
+    /// Sets log.dirs.
+    #[arg(long)]
+    log_dirs: Option<String>,
+
+    /// Sets any other setting; repeatable.
+    #[arg(long = "override", value_name = "NAME=VALUE", value_parser = parse_override)]
+    overrides: Vec<(String, String)>,
+
+    /// Reads a password from stdin, prints the credentials file line storing it salted for
+    /// the user, and exits.
+    #[arg(long, num_args = 2, value_names = ["MECHANISM", "USER"])]
+    scram_credential: Option<Vec<String>>,
+}
+
+impl Cli {
+    /// Every setting given on the command line, the dedicated flags last.
+    fn overrides(&self) -> Vec<(String, String)> {
+        let flags = [
+            ("node.id", self.node_id.map(|id| id.to_string())),
+            ("listeners", self.listeners.clone()),
+            ("advertised.listeners", self.advertised_listeners.clone()),
+            ("log.dirs", self.log_dirs.clone()),
+        ];
+        let mut overrides = self.overrides.clone();
+        overrides.extend(
+            flags
+                .into_iter()
+                .filter_map(|(name, value)| Some((name.to_string(), value?))),
+        );
+        overrides
+    }
+}
+
+fn parse_override(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got {}", value))
+}
+
+/// Prints `<mechanism> <user> <credential>` for the password on the first line of stdin.
+fn print_scram_credential(mechanism: &str, user: &str) -> Result<(), String> {
+    let scram_mechanism = ScramMechanism::from_name(mechanism)
+        .ok_or_else(|| format!("{} is not a SCRAM mechanism", mechanism))?;
+    let mut password = String::new();
+    std::io::stdin()
+        .read_line(&mut password)
+        .map_err(|e| format!("Failed to read the password from stdin: {}", e))?;
+    let password = password.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        return Err("Empty password".to_string());
+    }
+    let credential = scram_mechanism.credential(password, DEFAULT_SCRAM_ITERATIONS);
+    println!("{} {} {}", mechanism, user, credential);
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let cli = Cli::parse();
+    if let Some([mechanism, user]) = cli.scram_credential.as_deref() {
+        if let Err(e) = print_scram_credential(mechanism, user) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let (log_level, log_outputs) = forge::logging::init();
+
+    let config =
+        BrokerConfig::load_layered(cli.config.as_deref(), std::env::vars(), &cli.overrides()).await;
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            // Printed as is: the problem list spans several lines
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    tracing::info!("Starting broker {} with {:?}", config.node_id, config);
+
+    if let Some(directives) = &config.log_level {
+        log_level.set(Some(directives))?;
+    }
+    let server_log = &config.server_log;
+    let open_log_file = |path| {
+        let roll_interval =
+            (server_log.roll_ms > 0).then(|| Duration::from_millis(server_log.roll_ms));
+        RollingFile::open(
+            path,
+            server_log.max_bytes,
+            roll_interval,
+            server_log.max_files,
+        )
+    };
+    if let Some(path) = &server_log.file {
+        log_outputs.add(forge::logging::file_output(open_log_file(path)?))?;
+        tracing::info!("Logging to {}", path.display());
+    }
+    if let Some(path) = &server_log.audit_file {
+        log_outputs.add(forge::logging::audit_output(open_log_file(path)?))?;
+        tracing::info!("Writing audit events to {}", path.display());
+    }
+    #[cfg(feature = "otel")]
+    let tracer_provider = match &config.otlp_endpoint {
+        Some(endpoint) => {
+            let (output, provider) = forge::shared::otlp::output(endpoint, config.node_id)?;
+            log_outputs.add(output)?;
+            tracing::info!("Exporting request traces to {}", endpoint);
+            Some(provider)
+        }
+        None => None,
+    };
+
+    let result = run(cli, config, log_level).await;
+    #[cfg(feature = "otel")]
+    if let Some(provider) = tracer_provider
+        && let Err(e) = provider.shutdown()
+    {
+        tracing::warn!("Failed to flush request traces: {}", e);
+    }
+    result
+}
+
+/// Loads the modules of wasm.interceptors into one chain, or `None` when there are none.
+#[cfg(feature = "wasm")]
+fn record_interceptor(config: &BrokerConfig) -> Result<Option<Arc<dyn RecordInterceptor>>, String> {
+    if config.wasm_interceptors.is_empty() {
+        return Ok(None);
+    }
+    let interceptors = config
+        .wasm_interceptors
+        .iter()
+        .map(|path| {
+            let interceptor = WasmInterceptor::load(
+                path,
+                config.wasm_interceptor_fuel,
+                config.wasm_interceptor_memory_max_bytes,
+            )?;
+            tracing::info!("Loaded WASM interceptor {}", path.display());
+            Ok(Box::new(interceptor) as Box<dyn RecordInterceptor>)
+        })
+        .collect::<Result<_, String>>()?;
+    Ok(Some(Arc::new(InterceptorChain(interceptors))))
+}
+
+/// Without the `wasm` feature the config check already refused wasm.interceptors.
+#[cfg(not(feature = "wasm"))]
+fn record_interceptor(
+    _config: &BrokerConfig,
+) -> Result<Option<Arc<dyn RecordInterceptor>>, String> {
+    Ok(None)
+}
+
+/// Re-reads the config on every SIGHUP and applies the reloadable settings. An invalid file
+/// is logged and ignored, keeping the running config.
+fn spawn_config_reload(
+    cli: Cli,
+    listener: Arc<Mutex<BrokerMetadataListener>>,
+    cancel_token: CancellationToken,
+) -> std::io::Result<JoinHandle<()>> {
+    let mut hangups = signal(SignalKind::hangup())?;
+    Ok(spawn_named("config-reload", async move {
+        loop {
+            tokio::select! {
+                _ = hangups.recv() => {}
+                _ = cancel_token.cancelled() => break,
+            }
+
+            tracing::info!("Received SIGHUP, reloading config");
+            // Collected up front: `Vars` is not Send
+            let env: Vec<_> = std::env::vars().collect();
+            let config =
+                BrokerConfig::load_layered(cli.config.as_deref(), env, &cli.overrides()).await;
+            let result = match config {
+                Ok(config) => listener.lock().await.reload_config(config).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                tracing::error!("Keeping the running config: {}", e);
+            }
+        }
+    }))
+}
+
+/// Runs a single-node cluster: this process is both the only controller voter and the only
+/// broker, as the controller quorum has no network transport yet.
+async fn run(
+    cli: Cli,
+    config: BrokerConfig,
+    log_level: LogLevelHandle,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let cancel_token = CancellationToken::new();
+    let broker_id = config.node_id;
+
+    // An unformatted directory is accepted; one formatted for another node is not
+    let meta = MetaProperties::read(&config.log_dir).await?;
+    match &meta {
+        Some(meta) if meta.node_id != broker_id => {
+            return Err(format!(
+                "{} was formatted for node {}, but node.id is {}",
+                config.log_dir.display(),
+                meta.node_id,
+                broker_id
+            )
+            .into());
+        }
+        Some(meta) => tracing::info!("Joining cluster {}", meta.cluster_id),
+        None => tracing::warn!(
+            "{} has no meta.properties; run forge-storage format to tie it to a cluster",
+            config.log_dir.display()
+        ),
+    }
+
+    let mut replica_manager =
+        ReplicaManager::new(broker_id, &config.log_dir, config.min_insync_replicas);
+    replica_manager.log_config = config.log.clone();
+    let fs = file_system::for_executor(config.log_io_executor);
+    replica_manager.set_file_system(fs.clone());
+    replica_manager
+        .recover_logs(config.num_recovery_threads_per_data_dir)
+        .await?;
+    let replica_manager = Arc::new(Mutex::new(replica_manager));
+    let mut quota_manager =
+        QuotaManager::new(DEFAULT_QUOTA_WINDOW_SIZE_MS, DEFAULT_QUOTA_WINDOW_NUM);
+    DynamicBrokerConfig::apply_default_quotas(&mut quota_manager, &config);
+    let quota_manager = Arc::new(Mutex::new(quota_manager));
+
+    let metadata_log = PartitionLog::create(
+        fs,
+        config.log_dir.join(CLUSTER_METADATA_DIR),
+        config.log.segment_bytes,
+        0,
+        0,
+    )
+    .await?;
+    let node = Node::new(broker_id as u32, vec![], metadata_log);
+    let controller = Arc::new(Mutex::new(QuorumController::new(
+        node,
+        config.broker_session_timeout_ms,
+    )));
+    while !controller.lock().await.is_active() {
+        controller.lock().await.raft_node.tick();
+        tokio::time::sleep(CONTROLLER_TICK_INTERVAL).await;
+    }
+
+    let client_id = format!("broker-{}-fetcher", broker_id);
+    let inter_broker_sasl = match (
+        config.listener_security_protocol,
+        &config.sasl.inter_broker_username,
+        &config.sasl.inter_broker_password,
+    ) {
+        (SecurityProtocol::SaslPlaintext, Some(username), Some(password)) => Some((
+            config.sasl.inter_broker_mechanism.clone(),
+            username.clone(),
+            password.clone(),
+        )),
+        _ => None,
+    };
+    let listener = Arc::new(Mutex::new(BrokerMetadataListener::new(
+        broker_id,
+        replica_manager.clone(),
+        Box::new(
+            move |broker: &RegisterBrokerRecord| -> Box<dyn FetchClient> {
+                let client = BrokerClient::new(
+                    format!("{}:{}", broker.host, broker.port),
+                    client_id.clone(),
+                );
+                Box::new(match &inter_broker_sasl {
+                    Some((mechanism, username, password)) => {
+                        client.with_sasl(mechanism.clone(), username.clone(), password.clone())
+                    }
+                    None => client,
+                })
+            },
+        ),
+    )));
+    listener
+        .lock()
+        .await
+        .set_dynamic_config(DynamicBrokerConfig::new(
+            config.clone(),
+            replica_manager.clone(),
+            quota_manager.clone(),
+            Some(log_level.clone()),
+        ));
+    controller
+        .lock()
+        .await
+        .add_publisher(broker_id, Box::new(listener.clone()))
+        .await?;
+
+    let (host, port) = config.advertised_host_port();
+    let mut lifecycle = BrokerLifecycleManager::new(
+        broker_id,
+        host,
+        port,
+        config.rack.clone(),
+        config.broker_heartbeat_interval_ms,
+        Box::new(controller.clone()),
+    );
+    lifecycle.tick().await;
+    let lifecycle_task = spawn_named("broker-lifecycle", lifecycle.run(cancel_token.clone()));
+
+    controller
+        .lock()
+        .await
+        .bootstrap_internal_topics(
+            config.offsets_topic_replication_factor,
+            config.transaction_state_replication_factor,
+        )
+        .await
+        .map_err(|e| format!("Failed to create internal topics: {}", e))?;
+
+    let isr_expiration = ReplicaManager::start_isr_expiration(
+        replica_manager.clone(),
+        config.replica_lag_time_max_ms,
+        cancel_token.clone(),
+    );
+    let producer_id_expiration = ReplicaManager::start_producer_id_expiration(
+        replica_manager.clone(),
+        config.producer_id_expiration_ms,
+        Duration::from_millis(DEFAULT_PRODUCER_ID_EXPIRATION_CHECK_INTERVAL_MS),
+        cancel_token.clone(),
+    );
+    let isr_change_propagation = ReplicaManager::start_isr_change_propagation(
+        replica_manager.clone(),
+        Box::new(controller.clone()),
+        ISR_CHANGE_PROPAGATION_INTERVAL,
+        cancel_token.clone(),
+    );
+    let session_expiration = QuorumController::start_broker_session_expiration(
+        controller.clone(),
+        config.broker_session_timeout_ms,
+        cancel_token.clone(),
+    );
+    let group_coordinator = Arc::new(Mutex::new(GroupCoordinator::new(
+        DEFAULT_OFFSETS_TOPIC_PARTITIONS,
+    )));
+    let offsets_expiration = GroupCoordinator::start_offsets_expiration(
+        group_coordinator.clone(),
+        replica_manager.clone(),
+        config.offsets_retention_minutes.saturating_mul(60 * 1000),
+        Duration::from_millis(DEFAULT_OFFSETS_RETENTION_CHECK_INTERVAL_MS),
+        cancel_token.clone(),
+    );
+    let group_session_expiration = GroupCoordinator::start_session_expiration(
+        group_coordinator.clone(),
+        GROUP_SESSION_CHECK_INTERVAL,
+        cancel_token.clone(),
+    );
+    let txn_coordinator = Arc::new(Mutex::new(TransactionCoordinator::new(
+        DEFAULT_TRANSACTION_STATE_PARTITIONS,
+        DEFAULT_TRANSACTION_MAX_TIMEOUT_MS,
+        ProducerIdManager::new(
+            broker_id,
+            DEFAULT_PRODUCER_ID_BLOCK_SIZE,
+            Box::new(LocalProducerIdBlockSource::new(&config.log_dir)),
+        ),
+    )));
+    let transaction_timeouts = TransactionCoordinator::start_transaction_timeouts(
+        txn_coordinator.clone(),
+        replica_manager.clone(),
+        group_coordinator.clone(),
+        Duration::from_millis(DEFAULT_TRANSACTION_ABORT_CHECK_INTERVAL_MS),
+        cancel_token.clone(),
+    );
+    let log_metrics = Arc::new(LogMetrics::new());
+    let log_metrics_refresh = log_metrics.clone().start_refresh(
+        replica_manager.lock().await.shared_offsets(),
+        Duration::from_millis(LOG_METRICS_REFRESH_INTERVAL_MS),
+        cancel_token.clone(),
+    );
+
+    let authorizer: Option<Arc<dyn Authorizer>> = if config.authorizer_enable {
+        let authorizer = FileAclAuthorizer::load(
+            config.log_dir.join(ACL_FILE),
+            config.super_users.clone(),
+            config.allow_everyone_if_no_acl_found,
+        )
+        .await?;
+        Some(Arc::new(authorizer))
+    } else {
+        None
+    };
+    let auto_topic_creation = config.auto_create_topics_enable.then(|| {
+        Arc::new(AutoTopicCreationManager::new(
+            Box::new(controller.clone()),
+            config.num_partitions,
+            config.default_replication_factor,
+        ))
+    });
+
+    let config_reload = spawn_config_reload(cli, listener.clone(), cancel_token.clone())?;
+
+    // Every handler serving clients intercepts; the in-process follower fetches do not
+    let interceptor = record_interceptor(&config)?;
+    let validators = Arc::new(RecordValidators::new(listener.clone()));
+    controller
+        .lock()
+        .await
+        .set_record_validators(validators.names());
+    let dead_letter_queue = config.dead_letter_enable.then(|| {
+        Arc::new(DeadLetterQueue::new(
+            replica_manager.clone(),
+            auto_topic_creation.clone(),
+            config.dead_letter_topic_suffix.clone(),
+            config.message_max_bytes,
+        ))
+    });
+    let produce_handler = || {
+        let mut handler = ProduceHandler::new(
+            replica_manager.clone(),
+            authorizer.clone(),
+            auto_topic_creation.clone(),
+        )
+        .with_validators(validators.clone())
+        .with_message_max_bytes(config.message_max_bytes);
+        if let Some(interceptor) = &interceptor {
+            handler = handler.with_interceptor(interceptor.clone());
+        }
+        if let Some(dead_letter_queue) = &dead_letter_queue {
+            handler = handler.with_dead_letter_queue(dead_letter_queue.clone());
+        }
+        handler
+    };
+    let fetch_handler = || {
+        let handler = FetchHandler::new(replica_manager.clone(), authorizer.clone());
+        match &interceptor {
+            Some(interceptor) => handler.with_interceptor(interceptor.clone()),
+            None => handler,
+        }
+    };
+
+    let rest_proxy = match &config.rest_listener {
+        Some(address) => {
+            let proxy = RestProxy::new(
+                produce_handler(),
+                fetch_handler(),
+                ListOffsetsHandler::new(replica_manager.clone(), authorizer.clone()),
+                GroupHandler::new(
+                    group_coordinator.clone(),
+                    replica_manager.clone(),
+                    listener.clone(),
+                    authorizer.clone(),
+                ),
+                listener.clone(),
+                Duration::from_millis(config.rest_consumer_instance_timeout_ms),
+            )
+            .with_max_connections(config.rest_max_connections);
+            let proxy = if config.rest_basic_auth_enable {
+                proxy.with_basic_auth(
+                    Arc::new(
+                        FileCredentialStore::load(config.log_dir.join(CREDENTIALS_FILE)).await?,
+                    ),
+                    Duration::from_millis(config.socket.failed_authentication_delay_ms),
+                )
+            } else {
+                proxy
+            };
+            Some(proxy.start(address, cancel_token.clone()).await?)
+        }
+        None => None,
+    };
+
+    let mqtt_bridge = match &config.mqtt_listener {
+        Some(address) => {
+            let bridge = MqttBridge::new(
+                produce_handler(),
+                listener.clone(),
+                config.mqtt_topic_mappings.clone(),
+            );
+            Some(bridge.start(address, cancel_token.clone()).await?)
+        }
+        None => None,
+    };
+
+    let dispatcher = Arc::new(
+        RequestDispatcher::new(
+            produce_handler(),
+            fetch_handler(),
+            MetadataHandler::new(
+                broker_id,
+                meta.map(|meta| meta.cluster_id),
+                listener.clone(),
+                auto_topic_creation,
+                authorizer.clone(),
+            ),
+            AdminHandler::new(
+                AlterConfigsHandler::new(
+                    broker_id,
+                    Box::new(controller.clone()),
+                    authorizer.clone(),
+                    Some(log_level.clone()),
+                ),
+                DescribeConfigsHandler::new(
+                    broker_id,
+                    listener.clone(),
+                    authorizer.clone(),
+                    Some(log_level.clone()),
+                ),
+                Box::new(controller.clone()),
+                listener.clone(),
+                authorizer.clone(),
+            ),
+            ListOffsetsHandler::new(replica_manager.clone(), authorizer.clone()),
+            GroupHandler::new(
+                group_coordinator.clone(),
+                replica_manager.clone(),
+                listener.clone(),
+                authorizer.clone(),
+            ),
+            TxnHandler::new(
+                txn_coordinator,
+                group_coordinator,
+                replica_manager.clone(),
+                authorizer,
+            ),
+            quota_manager,
+        )
+        .with_chaos(config.chaos.clone()),
+    );
+    let connection_quotas = Arc::new(ConnectionQuotas::new(&config.socket));
+    let connections = Arc::new(ConnectionRegistry::new());
+    let admin_server = match &config.admin_listener {
+        Some(address) => {
+            let metrics: Vec<Arc<dyn MetricsSource>> = vec![
+                connections.clone(),
+                connection_quotas.clone(),
+                dispatcher.request_metrics(),
+                log_metrics,
+            ];
+            let server = AdminServer::new(connections.clone(), metrics, log_level.clone());
+            Some(server.start(address, cancel_token.clone()).await?)
+        }
+        None => None,
+    };
+    let sasl = match config.listener_security_protocol {
+        SecurityProtocol::SaslPlaintext => Some(SaslListenerConfig {
+            enabled_mechanisms: config.sasl.enabled_mechanisms.clone(),
+            credentials: Arc::new(
+                FileCredentialStore::load(config.log_dir.join(CREDENTIALS_FILE)).await?,
+            ),
+            failed_authentication_delay: Duration::from_millis(
+                config.socket.failed_authentication_delay_ms,
+            ),
+        }),
+        SecurityProtocol::Plaintext => None,
+    };
+    let handler_runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(config.num_io_threads)
+        .thread_name("forge-io")
+        .enable_all()
+        .build()?;
+    let result = TcpServer::listen(
+        &config.listener,
+        config.socket.clone(),
+        connection_quotas,
+        connections,
+        sasl,
+        dispatcher,
+        handler_runtime.handle().clone(),
+    )
+    .await;
+    // Requests left after the drain timeout are cut short: blocking here is not allowed
+    handler_runtime.shutdown_background();
+
+    cancel_token.cancel();
+    let _ = tokio::join!(
+        lifecycle_task,
+        isr_expiration,
+        producer_id_expiration,
+        isr_change_propagation,
+        session_expiration,
+        offsets_expiration,
+        group_session_expiration,
+        transaction_timeouts,
+        log_metrics_refresh,
+        config_reload
+    );
+    if let Some(admin_server) = admin_server {
+        let _ = admin_server.await;
+    }
+    if let Some(rest_proxy) = rest_proxy {
+        let _ = rest_proxy.await;
+    }
+    if let Some(mqtt_bridge) = mqtt_bridge {
+        let _ = mqtt_bridge.await;
+    }
+    listener.lock().await.shutdown().await;
+
+    // Nothing appends any more: the handlers, fetchers and controller tasks have stopped
+    replica_manager.lock().await.close_logs().await;
+    if let Err(e) = controller.lock().await.raft_node.log_store.flush().await {
+        tracing::error!("{}", e);
+    }
+    tracing::info!("Flushed logs, broker {} stopped", broker_id);
+    result
+}
@@ -1,3 +1,34 @@
+pub mod add_offsets_to_txn;
+pub mod add_partitions_to_txn;
+pub mod alter_configs;
+pub mod alter_partition_reassignments;
+pub mod api_versions;
+pub mod create_acls;
+pub mod delete_acls;
+pub mod describe_acls;
+pub mod describe_configs;
+pub mod describe_groups;
+pub mod elect_leaders;
+pub mod end_txn;
+pub mod fetch;
+pub mod find_coordinator;
+pub mod fuzzing;
+pub mod heartbeat;
+pub mod init_producer_id;
+pub mod join_group;
+pub mod leave_group;
+pub mod list_groups;
+pub mod list_offsets;
+pub mod list_partition_reassignments;
+pub mod metadata;
+pub mod offset_commit;
+pub mod offset_fetch;
+pub mod produce;
 pub mod request;
 pub mod response;
+pub mod round_trip;
+pub mod sasl_authenticate;
+pub mod sasl_handshake;
+pub mod sync_group;
+pub mod txn_offset_commit;
 pub mod types;
@@ -0,0 +1,55 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const ADD_OFFSETS_TO_TXN_API_KEY: i16 = 25;
+pub const ADD_OFFSETS_TO_TXN_MIN_VERSION: i16 = 0;
+/// v3 is the first flexible version, which is not supported yet.
+pub const ADD_OFFSETS_TO_TXN_MAX_VERSION: i16 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddOffsetsToTxnRequest {
+    pub transactional_id: String,
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+    /// The group whose offsets the transaction will commit.
+    pub group_id: String,
+}
+
+impl AddOffsetsToTxnRequest {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            transactional_id: String::decode(buf)?,
+            producer_id: i64::decode(buf)?,
+            producer_epoch: i16::decode(buf)?,
+            group_id: String::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.transactional_id.encode(buf);
+        self.producer_id.encode(buf);
+        self.producer_epoch.encode(buf);
+        self.group_id.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddOffsetsToTxnResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+}
+
+impl AddOffsetsToTxnResponse {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            throttle_time_ms: i32::decode(buf)?,
+            error_code: i16::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.throttle_time_ms.encode(buf);
+        self.error_code.encode(buf);
+    }
+}
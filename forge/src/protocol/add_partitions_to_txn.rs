@@ -0,0 +1,114 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const ADD_PARTITIONS_TO_TXN_API_KEY: i16 = 24;
+pub const ADD_PARTITIONS_TO_TXN_MIN_VERSION: i16 = 0;
+/// v3 is the first flexible version, which is not supported yet.
+pub const ADD_PARTITIONS_TO_TXN_MAX_VERSION: i16 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddPartitionsToTxnRequest {
+    pub transactional_id: String,
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+    pub topics: Vec<AddPartitionsToTxnTopic>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddPartitionsToTxnTopic {
+    pub name: String,
+    pub partitions: Vec<i32>,
+}
+
+impl Type for AddPartitionsToTxnTopic {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            name: String::decode(buf)?,
+            partitions: Vec::<i32>::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.name.encode(buf);
+        self.partitions.encode(buf);
+    }
+}
+
+impl AddPartitionsToTxnRequest {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            transactional_id: String::decode(buf)?,
+            producer_id: i64::decode(buf)?,
+            producer_epoch: i16::decode(buf)?,
+            topics: Vec::<AddPartitionsToTxnTopic>::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.transactional_id.encode(buf);
+        self.producer_id.encode(buf);
+        self.producer_epoch.encode(buf);
+        self.topics.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddPartitionsToTxnResponse {
+    pub throttle_time_ms: i32,
+    pub results: Vec<AddPartitionsToTxnTopicResult>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddPartitionsToTxnTopicResult {
+    pub name: String,
+    pub results: Vec<AddPartitionsToTxnPartitionResult>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddPartitionsToTxnPartitionResult {
+    pub partition_index: i32,
+    pub error_code: i16,
+}
+
+impl Type for AddPartitionsToTxnPartitionResult {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            partition_index: i32::decode(buf)?,
+            error_code: i16::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.partition_index.encode(buf);
+        self.error_code.encode(buf);
+    }
+}
+
+impl Type for AddPartitionsToTxnTopicResult {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            name: String::decode(buf)?,
+            results: Vec::<AddPartitionsToTxnPartitionResult>::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.name.encode(buf);
+        self.results.encode(buf);
+    }
+}
+
+impl AddPartitionsToTxnResponse {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            throttle_time_ms: i32::decode(buf)?,
+            results: Vec::<AddPartitionsToTxnTopicResult>::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.throttle_time_ms.encode(buf);
+        self.results.encode(buf);
+    }
+}
@@ -0,0 +1,117 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const ALTER_CONFIGS_API_KEY: i16 = 33;
+pub const ALTER_CONFIGS_MIN_VERSION: i16 = 0;
+/// v2 switches to the flexible encoding, which is not supported yet.
+pub const ALTER_CONFIGS_MAX_VERSION: i16 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterConfigsRequest {
+    pub resources: Vec<AlterConfigsResource>,
+    pub validate_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterConfigsResource {
+    pub resource_type: i8,
+    pub resource_name: String,
+    pub configs: Vec<AlterableConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterableConfig {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl Type for AlterableConfig {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            name: String::decode(buf)?,
+            value: Option::<String>::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.name.encode(buf);
+        self.value.encode(buf);
+    }
+}
+
+impl Type for AlterConfigsResource {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            resource_type: i8::decode(buf)?,
+            resource_name: String::decode(buf)?,
+            configs: Vec::<AlterableConfig>::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.resource_type.encode(buf);
+        self.resource_name.encode(buf);
+        self.configs.encode(buf);
+    }
+}
+
+impl AlterConfigsRequest {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            resources: Vec::<AlterConfigsResource>::decode(buf)?,
+            validate_only: bool::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.resources.encode(buf);
+        self.validate_only.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterConfigsResponse {
+    pub throttle_time_ms: i32,
+    pub responses: Vec<AlterConfigsResourceResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterConfigsResourceResponse {
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    pub resource_type: i8,
+    pub resource_name: String,
+}
+
+impl Type for AlterConfigsResourceResponse {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            error_code: i16::decode(buf)?,
+            error_message: Option::<String>::decode(buf)?,
+            resource_type: i8::decode(buf)?,
+            resource_name: String::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.error_code.encode(buf);
+        self.error_message.encode(buf);
+        self.resource_type.encode(buf);
+        self.resource_name.encode(buf);
+    }
+}
+
+impl AlterConfigsResponse {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            throttle_time_ms: i32::decode(buf)?,
+            responses: Vec::<AlterConfigsResourceResponse>::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.throttle_time_ms.encode(buf);
+        self.responses.encode(buf);
+    }
+}
@@ -0,0 +1,157 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::{CompactArray, CompactString, TaggedFields, Type};
+
+pub const ALTER_PARTITION_REASSIGNMENTS_API_KEY: i16 = 45;
+/// Only flexible versions exist: requests use header v2 and responses header v1.
+pub const ALTER_PARTITION_REASSIGNMENTS_MIN_VERSION: i16 = 0;
+pub const ALTER_PARTITION_REASSIGNMENTS_MAX_VERSION: i16 = 0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterPartitionReassignmentsRequest {
+    pub timeout_ms: i32,
+    pub topics: Vec<ReassignableTopic>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReassignableTopic {
+    pub name: String,
+    pub partitions: Vec<ReassignablePartition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReassignablePartition {
+    pub partition_index: i32,
+    /// The replicas to move to, or `None` to cancel the reassignment in progress.
+    pub replicas: Option<Vec<i32>>,
+}
+
+impl Type for ReassignablePartition {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let partition_index = i32::decode(buf)?;
+        let replicas = Option::<CompactArray<i32>>::decode(buf)?;
+        TaggedFields::decode(buf)?;
+        Ok(Self {
+            partition_index,
+            replicas: replicas.map(|replicas| replicas.0),
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.partition_index.encode(buf);
+        self.replicas.clone().map(CompactArray).encode(buf);
+        TaggedFields.encode(buf);
+    }
+}
+
+impl Type for ReassignableTopic {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let name = CompactString::decode(buf)?.0;
+        let partitions = CompactArray::<ReassignablePartition>::decode(buf)?.0;
+        TaggedFields::decode(buf)?;
+        Ok(Self { name, partitions })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        CompactString(self.name.clone()).encode(buf);
+        CompactArray(self.partitions.clone()).encode(buf);
+        TaggedFields.encode(buf);
+    }
+}
+
+impl AlterPartitionReassignmentsRequest {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        let timeout_ms = i32::decode(buf)?;
+        let topics = CompactArray::<ReassignableTopic>::decode(buf)?.0;
+        TaggedFields::decode(buf)?;
+        Ok(Self { timeout_ms, topics })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.timeout_ms.encode(buf);
+        CompactArray(self.topics.clone()).encode(buf);
+        TaggedFields.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterPartitionReassignmentsResponse {
+    pub throttle_time_ms: i32,
+    /// Set when the whole request failed, e.g. on authorization.
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    pub responses: Vec<ReassignableTopicResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReassignableTopicResponse {
+    pub name: String,
+    pub partitions: Vec<ReassignablePartitionResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReassignablePartitionResponse {
+    pub partition_index: i32,
+    pub error_code: i16,
+    pub error_message: Option<String>,
+}
+
+impl Type for ReassignablePartitionResponse {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let partition_index = i32::decode(buf)?;
+        let error_code = i16::decode(buf)?;
+        let error_message = Option::<CompactString>::decode(buf)?;
+        TaggedFields::decode(buf)?;
+        Ok(Self {
+            partition_index,
+            error_code,
+            error_message: error_message.map(|message| message.0),
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.partition_index.encode(buf);
+        self.error_code.encode(buf);
+        self.error_message.clone().map(CompactString).encode(buf);
+        TaggedFields.encode(buf);
+    }
+}
+
+impl Type for ReassignableTopicResponse {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let name = CompactString::decode(buf)?.0;
+        let partitions = CompactArray::<ReassignablePartitionResponse>::decode(buf)?.0;
+        TaggedFields::decode(buf)?;
+        Ok(Self { name, partitions })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        CompactString(self.name.clone()).encode(buf);
+        CompactArray(self.partitions.clone()).encode(buf);
+        TaggedFields.encode(buf);
+    }
+}
+
+impl AlterPartitionReassignmentsResponse {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        let throttle_time_ms = i32::decode(buf)?;
+        let error_code = i16::decode(buf)?;
+        let error_message = Option::<CompactString>::decode(buf)?;
+        let responses = CompactArray::<ReassignableTopicResponse>::decode(buf)?.0;
+        TaggedFields::decode(buf)?;
+        Ok(Self {
+            throttle_time_ms,
+            error_code,
+            error_message: error_message.map(|message| message.0),
+            responses,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.throttle_time_ms.encode(buf);
+        self.error_code.encode(buf);
+        self.error_message.clone().map(CompactString).encode(buf);
+        CompactArray(self.responses.clone()).encode(buf);
+        TaggedFields.encode(buf);
+    }
+}
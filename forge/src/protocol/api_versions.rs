@@ -0,0 +1,56 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const API_VERSIONS_API_KEY: i16 = 18;
+pub const API_VERSIONS_MIN_VERSION: i16 = 0;
+/// v3 switches to the flexible encoding, which is not supported yet.
+pub const API_VERSIONS_MAX_VERSION: i16 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApiVersion {
+    pub api_key: i16,
+    pub min_version: i16,
+    pub max_version: i16,
+}
+
+impl Type for ApiVersion {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            api_key: i16::decode(buf)?,
+            min_version: i16::decode(buf)?,
+            max_version: i16::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.api_key.encode(buf);
+        self.min_version.encode(buf);
+        self.max_version.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiVersionsResponse {
+    pub error_code: i16,
+    pub api_keys: Vec<ApiVersion>,
+    pub throttle_time_ms: i32,
+}
+
+impl ApiVersionsResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        Ok(Self {
+            error_code: i16::decode(buf)?,
+            api_keys: Vec::<ApiVersion>::decode(buf)?,
+            throttle_time_ms: if version >= 1 { i32::decode(buf)? } else { 0 },
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.error_code.encode(buf);
+        self.api_keys.encode(buf);
+        if version >= 1 {
+            self.throttle_time_ms.encode(buf);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use bytes::{Buf, BufMut};
+
+use crate::core::domain::acl::PatternType;
+use crate::protocol::types::Type;
+
+pub const CREATE_ACLS_API_KEY: i16 = 30;
+pub const CREATE_ACLS_MIN_VERSION: i16 = 0;
+/// v2 switches to the flexible encoding, which is not supported yet.
+pub const CREATE_ACLS_MAX_VERSION: i16 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateAclsRequest {
+    pub creations: Vec<AclCreation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AclCreation {
+    pub resource_type: i8,
+    pub resource_name: String,
+    /// v1+; v0 only knows literal patterns.
+    pub resource_pattern_type: i8,
+    pub principal: String,
+    pub host: String,
+    pub operation: i8,
+    pub permission_type: i8,
+}
+
+impl AclCreation {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        Ok(Self {
+            resource_type: i8::decode(buf)?,
+            resource_name: String::decode(buf)?,
+            resource_pattern_type: if version >= 1 {
+                i8::decode(buf)?
+            } else {
+                PatternType::Literal.code()
+            },
+            principal: String::decode(buf)?,
+            host: String::decode(buf)?,
+            operation: i8::decode(buf)?,
+            permission_type: i8::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.resource_type.encode(buf);
+        self.resource_name.encode(buf);
+        if version >= 1 {
+            self.resource_pattern_type.encode(buf);
+        }
+        self.principal.encode(buf);
+        self.host.encode(buf);
+        self.operation.encode(buf);
+        self.permission_type.encode(buf);
+    }
+}
+
+impl CreateAclsRequest {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let creation_count = i32::decode(buf)?;
+        let mut creations = Vec::new();
+        for _ in 0..creation_count.max(0) {
+            creations.push(AclCreation::decode(buf, version)?);
+        }
+        Ok(Self { creations })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        (self.creations.len() as i32).encode(buf);
+        for creation in &self.creations {
+            creation.encode(buf, version);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateAclsResponse {
+    pub throttle_time_ms: i32,
+    /// One per creation, in request order.
+    pub results: Vec<AclCreationResult>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AclCreationResult {
+    pub error_code: i16,
+    pub error_message: Option<String>,
+}
+
+impl Type for AclCreationResult {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            error_code: i16::decode(buf)?,
+            error_message: Option::<String>::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.error_code.encode(buf);
+        self.error_message.encode(buf);
+    }
+}
+
+impl CreateAclsResponse {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            throttle_time_ms: i32::decode(buf)?,
+            results: Vec::<AclCreationResult>::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.throttle_time_ms.encode(buf);
+        self.results.encode(buf);
+    }
+}
@@ -0,0 +1,187 @@
+use bytes::{Buf, BufMut};
+
+use crate::core::domain::acl::PatternType;
+use crate::protocol::types::Type;
+
+pub const DELETE_ACLS_API_KEY: i16 = 31;
+pub const DELETE_ACLS_MIN_VERSION: i16 = 0;
+/// v2 switches to the flexible encoding, which is not supported yet.
+pub const DELETE_ACLS_MAX_VERSION: i16 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeleteAclsRequest {
+    pub filters: Vec<DeleteAclsFilter>,
+}
+
+/// Selects bindings the way a DescribeAcls request does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeleteAclsFilter {
+    pub resource_type_filter: i8,
+    pub resource_name_filter: Option<String>,
+    /// v1+; v0 only knows literal patterns.
+    pub pattern_type_filter: i8,
+    pub principal_filter: Option<String>,
+    pub host_filter: Option<String>,
+    pub operation: i8,
+    pub permission_type: i8,
+}
+
+impl DeleteAclsFilter {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        Ok(Self {
+            resource_type_filter: i8::decode(buf)?,
+            resource_name_filter: Option::<String>::decode(buf)?,
+            pattern_type_filter: if version >= 1 {
+                i8::decode(buf)?
+            } else {
+                PatternType::Literal.code()
+            },
+            principal_filter: Option::<String>::decode(buf)?,
+            host_filter: Option::<String>::decode(buf)?,
+            operation: i8::decode(buf)?,
+            permission_type: i8::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.resource_type_filter.encode(buf);
+        self.resource_name_filter.encode(buf);
+        if version >= 1 {
+            self.pattern_type_filter.encode(buf);
+        }
+        self.principal_filter.encode(buf);
+        self.host_filter.encode(buf);
+        self.operation.encode(buf);
+        self.permission_type.encode(buf);
+    }
+}
+
+impl DeleteAclsRequest {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let filter_count = i32::decode(buf)?;
+        let mut filters = Vec::new();
+        for _ in 0..filter_count.max(0) {
+            filters.push(DeleteAclsFilter::decode(buf, version)?);
+        }
+        Ok(Self { filters })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        (self.filters.len() as i32).encode(buf);
+        for filter in &self.filters {
+            filter.encode(buf, version);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeleteAclsResponse {
+    pub throttle_time_ms: i32,
+    /// One per filter, in request order.
+    pub filter_results: Vec<DeleteAclsFilterResult>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeleteAclsFilterResult {
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    pub matching_acls: Vec<DeleteAclsMatchingAcl>,
+}
+
+/// A binding the filter removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeleteAclsMatchingAcl {
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    pub resource_type: i8,
+    pub resource_name: String,
+    /// v1+.
+    pub pattern_type: i8,
+    pub principal: String,
+    pub host: String,
+    pub operation: i8,
+    pub permission_type: i8,
+}
+
+impl DeleteAclsMatchingAcl {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        Ok(Self {
+            error_code: i16::decode(buf)?,
+            error_message: Option::<String>::decode(buf)?,
+            resource_type: i8::decode(buf)?,
+            resource_name: String::decode(buf)?,
+            pattern_type: if version >= 1 {
+                i8::decode(buf)?
+            } else {
+                PatternType::Literal.code()
+            },
+            principal: String::decode(buf)?,
+            host: String::decode(buf)?,
+            operation: i8::decode(buf)?,
+            permission_type: i8::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.error_code.encode(buf);
+        self.error_message.encode(buf);
+        self.resource_type.encode(buf);
+        self.resource_name.encode(buf);
+        if version >= 1 {
+            self.pattern_type.encode(buf);
+        }
+        self.principal.encode(buf);
+        self.host.encode(buf);
+        self.operation.encode(buf);
+        self.permission_type.encode(buf);
+    }
+}
+
+impl DeleteAclsFilterResult {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let error_code = i16::decode(buf)?;
+        let error_message = Option::<String>::decode(buf)?;
+        let acl_count = i32::decode(buf)?;
+        let mut matching_acls = Vec::new();
+        for _ in 0..acl_count.max(0) {
+            matching_acls.push(DeleteAclsMatchingAcl::decode(buf, version)?);
+        }
+        Ok(Self {
+            error_code,
+            error_message,
+            matching_acls,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.error_code.encode(buf);
+        self.error_message.encode(buf);
+        (self.matching_acls.len() as i32).encode(buf);
+        for acl in &self.matching_acls {
+            acl.encode(buf, version);
+        }
+    }
+}
+
+impl DeleteAclsResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let throttle_time_ms = i32::decode(buf)?;
+        let result_count = i32::decode(buf)?;
+        let mut filter_results = Vec::new();
+        for _ in 0..result_count.max(0) {
+            filter_results.push(DeleteAclsFilterResult::decode(buf, version)?);
+        }
+        Ok(Self {
+            throttle_time_ms,
+            filter_results,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.throttle_time_ms.encode(buf);
+        (self.filter_results.len() as i32).encode(buf);
+        for result in &self.filter_results {
+            result.encode(buf, version);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use bytes::{Buf, BufMut};
+
+use crate::core::domain::acl::PatternType;
+use crate::protocol::types::Type;
+
+pub const DESCRIBE_ACLS_API_KEY: i16 = 29;
+pub const DESCRIBE_ACLS_MIN_VERSION: i16 = 0;
+/// v2 switches to the flexible encoding, which is not supported yet.
+pub const DESCRIBE_ACLS_MAX_VERSION: i16 = 1;
+
+/// Each field selects the bindings equal to it; the `i8` codes take `ACL_FILTER_ANY`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeAclsRequest {
+    pub resource_type_filter: i8,
+    pub resource_name_filter: Option<String>,
+    /// v1+; v0 only knows literal patterns.
+    pub pattern_type_filter: i8,
+    pub principal_filter: Option<String>,
+    pub host_filter: Option<String>,
+    pub operation: i8,
+    pub permission_type: i8,
+}
+
+impl DescribeAclsRequest {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        Ok(Self {
+            resource_type_filter: i8::decode(buf)?,
+            resource_name_filter: Option::<String>::decode(buf)?,
+            pattern_type_filter: if version >= 1 {
+                i8::decode(buf)?
+            } else {
+                PatternType::Literal.code()
+            },
+            principal_filter: Option::<String>::decode(buf)?,
+            host_filter: Option::<String>::decode(buf)?,
+            operation: i8::decode(buf)?,
+            permission_type: i8::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.resource_type_filter.encode(buf);
+        self.resource_name_filter.encode(buf);
+        if version >= 1 {
+            self.pattern_type_filter.encode(buf);
+        }
+        self.principal_filter.encode(buf);
+        self.host_filter.encode(buf);
+        self.operation.encode(buf);
+        self.permission_type.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeAclsResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    pub resources: Vec<DescribeAclsResource>,
+}
+
+/// The bindings sharing one resource pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeAclsResource {
+    pub resource_type: i8,
+    pub resource_name: String,
+    /// v1+.
+    pub pattern_type: i8,
+    pub acls: Vec<AclDescription>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AclDescription {
+    pub principal: String,
+    pub host: String,
+    pub operation: i8,
+    pub permission_type: i8,
+}
+
+impl Type for AclDescription {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            principal: String::decode(buf)?,
+            host: String::decode(buf)?,
+            operation: i8::decode(buf)?,
+            permission_type: i8::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.principal.encode(buf);
+        self.host.encode(buf);
+        self.operation.encode(buf);
+        self.permission_type.encode(buf);
+    }
+}
+
+impl DescribeAclsResource {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        Ok(Self {
+            resource_type: i8::decode(buf)?,
+            resource_name: String::decode(buf)?,
+            pattern_type: if version >= 1 {
+                i8::decode(buf)?
+            } else {
+                PatternType::Literal.code()
+            },
+            acls: Vec::<AclDescription>::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.resource_type.encode(buf);
+        self.resource_name.encode(buf);
+        if version >= 1 {
+            self.pattern_type.encode(buf);
+        }
+        self.acls.encode(buf);
+    }
+}
+
+impl DescribeAclsResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let throttle_time_ms = i32::decode(buf)?;
+        let error_code = i16::decode(buf)?;
+        let error_message = Option::<String>::decode(buf)?;
+        let resource_count = i32::decode(buf)?;
+        let mut resources = Vec::new();
+        for _ in 0..resource_count.max(0) {
+            resources.push(DescribeAclsResource::decode(buf, version)?);
+        }
+        Ok(Self {
+            throttle_time_ms,
+            error_code,
+            error_message,
+            resources,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.throttle_time_ms.encode(buf);
+        self.error_code.encode(buf);
+        self.error_message.encode(buf);
+        (self.resources.len() as i32).encode(buf);
+        for resource in &self.resources {
+            resource.encode(buf, version);
+        }
+    }
+}
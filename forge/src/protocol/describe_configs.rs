@@ -0,0 +1,238 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const DESCRIBE_CONFIGS_API_KEY: i16 = 32;
+pub const DESCRIBE_CONFIGS_MIN_VERSION: i16 = 0;
+/// v4 switches to the flexible encoding, which is not supported yet.
+pub const DESCRIBE_CONFIGS_MAX_VERSION: i16 = 3;
+
+/// Where a config value comes from, as Kafka numbers them.
+pub const CONFIG_SOURCE_UNKNOWN: i8 = 0;
+pub const CONFIG_SOURCE_DYNAMIC_TOPIC: i8 = 1;
+pub const CONFIG_SOURCE_DYNAMIC_BROKER: i8 = 2;
+pub const CONFIG_SOURCE_DYNAMIC_DEFAULT_BROKER: i8 = 3;
+pub const CONFIG_SOURCE_STATIC_BROKER: i8 = 4;
+pub const CONFIG_SOURCE_DEFAULT: i8 = 5;
+pub const CONFIG_SOURCE_DYNAMIC_BROKER_LOGGER: i8 = 6;
+
+/// Sent as `config_type`; this broker does not describe the types of its configs.
+pub const CONFIG_TYPE_UNKNOWN: i8 = 0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeConfigsRequest {
+    pub resources: Vec<DescribeConfigsResource>,
+    /// v1+.
+    pub include_synonyms: bool,
+    /// v3+.
+    pub include_documentation: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeConfigsResource {
+    pub resource_type: i8,
+    pub resource_name: String,
+    /// `None` describes every config of the resource.
+    pub configuration_keys: Option<Vec<String>>,
+}
+
+impl Type for DescribeConfigsResource {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            resource_type: i8::decode(buf)?,
+            resource_name: String::decode(buf)?,
+            configuration_keys: Option::<Vec<String>>::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.resource_type.encode(buf);
+        self.resource_name.encode(buf);
+        self.configuration_keys.encode(buf);
+    }
+}
+
+impl DescribeConfigsRequest {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        Ok(Self {
+            resources: Vec::<DescribeConfigsResource>::decode(buf)?,
+            include_synonyms: version >= 1 && bool::decode(buf)?,
+            include_documentation: version >= 3 && bool::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.resources.encode(buf);
+        if version >= 1 {
+            self.include_synonyms.encode(buf);
+        }
+        if version >= 3 {
+            self.include_documentation.encode(buf);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeConfigsResponse {
+    pub throttle_time_ms: i32,
+    pub results: Vec<DescribeConfigsResult>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeConfigsResult {
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    pub resource_type: i8,
+    pub resource_name: String,
+    pub configs: Vec<DescribeConfigsResourceResult>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeConfigsResourceResult {
+    pub name: String,
+    /// `None` for sensitive configs.
+    pub value: Option<String>,
+    pub read_only: bool,
+    /// v1+ sends `config_source` instead; v0 clients get whether it is `CONFIG_SOURCE_DEFAULT`.
+    pub config_source: i8,
+    pub is_sensitive: bool,
+    /// v1+: the values this config could take, the effective one first.
+    pub synonyms: Vec<DescribeConfigsSynonym>,
+    /// v3+.
+    pub config_type: i8,
+    /// v3+.
+    pub documentation: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeConfigsSynonym {
+    pub name: String,
+    pub value: Option<String>,
+    pub source: i8,
+}
+
+impl Type for DescribeConfigsSynonym {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            name: String::decode(buf)?,
+            value: Option::<String>::decode(buf)?,
+            source: i8::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.name.encode(buf);
+        self.value.encode(buf);
+        self.source.encode(buf);
+    }
+}
+
+impl DescribeConfigsResourceResult {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let name = String::decode(buf)?;
+        let value = Option::<String>::decode(buf)?;
+        let read_only = bool::decode(buf)?;
+        let config_source = if version >= 1 {
+            i8::decode(buf)?
+        } else if bool::decode(buf)? {
+            CONFIG_SOURCE_DEFAULT
+        } else {
+            CONFIG_SOURCE_UNKNOWN
+        };
+        let is_sensitive = bool::decode(buf)?;
+        let synonyms = if version >= 1 {
+            Vec::<DescribeConfigsSynonym>::decode(buf)?
+        } else {
+            Vec::new()
+        };
+        let (config_type, documentation) = if version >= 3 {
+            (i8::decode(buf)?, Option::<String>::decode(buf)?)
+        } else {
+            (CONFIG_TYPE_UNKNOWN, None)
+        };
+        Ok(Self {
+            name,
+            value,
+            read_only,
+            config_source,
+            is_sensitive,
+            synonyms,
+            config_type,
+            documentation,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.name.encode(buf);
+        self.value.encode(buf);
+        self.read_only.encode(buf);
+        if version >= 1 {
+            self.config_source.encode(buf);
+        } else {
+            (self.config_source == CONFIG_SOURCE_DEFAULT).encode(buf);
+        }
+        self.is_sensitive.encode(buf);
+        if version >= 1 {
+            self.synonyms.encode(buf);
+        }
+        if version >= 3 {
+            self.config_type.encode(buf);
+            self.documentation.encode(buf);
+        }
+    }
+}
+
+impl DescribeConfigsResult {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let error_code = i16::decode(buf)?;
+        let error_message = Option::<String>::decode(buf)?;
+        let resource_type = i8::decode(buf)?;
+        let resource_name = String::decode(buf)?;
+        let config_count = i32::decode(buf)?;
+        let mut configs = Vec::new();
+        for _ in 0..config_count.max(0) {
+            configs.push(DescribeConfigsResourceResult::decode(buf, version)?);
+        }
+        Ok(Self {
+            error_code,
+            error_message,
+            resource_type,
+            resource_name,
+            configs,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.error_code.encode(buf);
+        self.error_message.encode(buf);
+        self.resource_type.encode(buf);
+        self.resource_name.encode(buf);
+        (self.configs.len() as i32).encode(buf);
+        for config in &self.configs {
+            config.encode(buf, version);
+        }
+    }
+}
+
+impl DescribeConfigsResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let throttle_time_ms = i32::decode(buf)?;
+        let result_count = i32::decode(buf)?;
+        let mut results = Vec::new();
+        for _ in 0..result_count.max(0) {
+            results.push(DescribeConfigsResult::decode(buf, version)?);
+        }
+        Ok(Self {
+            throttle_time_ms,
+            results,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.throttle_time_ms.encode(buf);
+        (self.results.len() as i32).encode(buf);
+        for result in &self.results {
+            result.encode(buf, version);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const DESCRIBE_GROUPS_API_KEY: i16 = 15;
+pub const DESCRIBE_GROUPS_MIN_VERSION: i16 = 0;
+/// v5 switches to the flexible encoding, which is not supported yet.
+pub const DESCRIBE_GROUPS_MAX_VERSION: i16 = 4;
+
+/// Sent as `authorized_operations` when the client did not ask for them.
+pub const AUTHORIZED_OPERATIONS_OMITTED: i32 = i32::MIN;
+
+fn decode_bytes<B: Buf>(buf: &mut B) -> Result<Vec<u8>, String> {
+    let len = i32::decode(buf)?;
+    if len < 0 {
+        return Ok(Vec::new());
+    }
+    let len = len as usize;
+    if buf.remaining() < len {
+        return Err("Not enough data for member bytes".to_string());
+    }
+    let mut bytes = vec![0u8; len];
+    buf.copy_to_slice(&mut bytes);
+    Ok(bytes)
+}
+
+fn encode_bytes<B: BufMut>(bytes: &[u8], buf: &mut B) {
+    buf.put_i32(bytes.len() as i32);
+    buf.put_slice(bytes);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeGroupsRequest {
+    pub groups: Vec<String>,
+    /// v3+.
+    pub include_authorized_operations: bool,
+}
+
+impl DescribeGroupsRequest {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        Ok(Self {
+            groups: Vec::<String>::decode(buf)?,
+            include_authorized_operations: version >= 3 && bool::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.groups.encode(buf);
+        if version >= 3 {
+            self.include_authorized_operations.encode(buf);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeGroupsResponse {
+    pub throttle_time_ms: i32,
+    pub groups: Vec<DescribedGroup>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribedGroup {
+    pub error_code: i16,
+    pub group_id: String,
+    /// Empty, PreparingRebalance, CompletingRebalance, Stable or Dead.
+    pub group_state: String,
+    pub protocol_type: String,
+    /// The selected protocol, e.g. the partition assignor's name; empty outside Stable.
+    pub protocol_data: String,
+    pub members: Vec<DescribedGroupMember>,
+    pub authorized_operations: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribedGroupMember {
+    pub member_id: String,
+    /// v4+.
+    pub group_instance_id: Option<String>,
+    pub client_id: String,
+    pub client_host: String,
+    /// The member's metadata for the selected protocol.
+    pub member_metadata: Vec<u8>,
+    pub member_assignment: Vec<u8>,
+}
+
+impl DescribedGroupMember {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        Ok(Self {
+            member_id: String::decode(buf)?,
+            group_instance_id: if version >= 4 {
+                Option::<String>::decode(buf)?
+            } else {
+                None
+            },
+            client_id: String::decode(buf)?,
+            client_host: String::decode(buf)?,
+            member_metadata: decode_bytes(buf)?,
+            member_assignment: decode_bytes(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.member_id.encode(buf);
+        if version >= 4 {
+            self.group_instance_id.encode(buf);
+        }
+        self.client_id.encode(buf);
+        self.client_host.encode(buf);
+        encode_bytes(&self.member_metadata, buf);
+        encode_bytes(&self.member_assignment, buf);
+    }
+}
+
+impl DescribedGroup {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let error_code = i16::decode(buf)?;
+        let group_id = String::decode(buf)?;
+        let group_state = String::decode(buf)?;
+        let protocol_type = String::decode(buf)?;
+        let protocol_data = String::decode(buf)?;
+
+        let member_count = i32::decode(buf)?;
+        let mut members = Vec::new();
+        for _ in 0..member_count.max(0) {
+            members.push(DescribedGroupMember::decode(buf, version)?);
+        }
+
+        let authorized_operations = if version >= 3 {
+            i32::decode(buf)?
+        } else {
+            AUTHORIZED_OPERATIONS_OMITTED
+        };
+        Ok(Self {
+            error_code,
+            group_id,
+            group_state,
+            protocol_type,
+            protocol_data,
+            members,
+            authorized_operations,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.error_code.encode(buf);
+        self.group_id.encode(buf);
+        self.group_state.encode(buf);
+        self.protocol_type.encode(buf);
+        self.protocol_data.encode(buf);
+        (self.members.len() as i32).encode(buf);
+        for member in &self.members {
+            member.encode(buf, version);
+        }
+        if version >= 3 {
+            self.authorized_operations.encode(buf);
+        }
+    }
+}
+
+impl DescribeGroupsResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let throttle_time_ms = if version >= 1 { i32::decode(buf)? } else { 0 };
+        let group_count = i32::decode(buf)?;
+        let mut groups = Vec::new();
+        for _ in 0..group_count.max(0) {
+            groups.push(DescribedGroup::decode(buf, version)?);
+        }
+        Ok(Self {
+            throttle_time_ms,
+            groups,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        if version >= 1 {
+            self.throttle_time_ms.encode(buf);
+        }
+        (self.groups.len() as i32).encode(buf);
+        for group in &self.groups {
+            group.encode(buf, version);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const ELECT_LEADERS_API_KEY: i16 = 43;
+pub const ELECT_LEADERS_MIN_VERSION: i16 = 0;
+/// v2 switches to the flexible encoding, which is not supported yet.
+pub const ELECT_LEADERS_MAX_VERSION: i16 = 1;
+
+/// Moves leadership back to the first replica of each partition, when it is in sync.
+pub const ELECTION_TYPE_PREFERRED: i8 = 0;
+/// Elects a leader for partitions that have none, out of sync replicas included.
+pub const ELECTION_TYPE_UNCLEAN: i8 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElectLeadersRequest {
+    /// v1+; v0 only runs preferred elections.
+    pub election_type: i8,
+    /// `None` elects leaders for every partition.
+    pub topic_partitions: Option<Vec<TopicPartitions>>,
+    pub timeout_ms: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopicPartitions {
+    pub topic: String,
+    pub partition_ids: Vec<i32>,
+}
+
+impl Type for TopicPartitions {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            topic: String::decode(buf)?,
+            partition_ids: Vec::<i32>::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.topic.encode(buf);
+        self.partition_ids.encode(buf);
+    }
+}
+
+impl ElectLeadersRequest {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let election_type = if version >= 1 {
+            i8::decode(buf)?
+        } else {
+            ELECTION_TYPE_PREFERRED
+        };
+        Ok(Self {
+            election_type,
+            topic_partitions: Option::<Vec<TopicPartitions>>::decode(buf)?,
+            timeout_ms: i32::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        if version >= 1 {
+            self.election_type.encode(buf);
+        }
+        self.topic_partitions.encode(buf);
+        self.timeout_ms.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElectLeadersResponse {
+    pub throttle_time_ms: i32,
+    /// v1+; set when the whole request failed, e.g. on authorization.
+    pub error_code: i16,
+    pub replica_election_results: Vec<ReplicaElectionResult>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplicaElectionResult {
+    pub topic: String,
+    pub partition_result: Vec<PartitionResult>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionResult {
+    pub partition_id: i32,
+    pub error_code: i16,
+    pub error_message: Option<String>,
+}
+
+impl Type for PartitionResult {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            partition_id: i32::decode(buf)?,
+            error_code: i16::decode(buf)?,
+            error_message: Option::<String>::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.partition_id.encode(buf);
+        self.error_code.encode(buf);
+        self.error_message.encode(buf);
+    }
+}
+
+impl Type for ReplicaElectionResult {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            topic: String::decode(buf)?,
+            partition_result: Vec::<PartitionResult>::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.topic.encode(buf);
+        self.partition_result.encode(buf);
+    }
+}
+
+impl ElectLeadersResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let throttle_time_ms = i32::decode(buf)?;
+        let error_code = if version >= 1 { i16::decode(buf)? } else { 0 };
+        Ok(Self {
+            throttle_time_ms,
+            error_code,
+            replica_election_results: Vec::<ReplicaElectionResult>::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.throttle_time_ms.encode(buf);
+        if version >= 1 {
+            self.error_code.encode(buf);
+        }
+        self.replica_election_results.encode(buf);
+    }
+}
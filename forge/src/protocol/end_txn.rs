@@ -0,0 +1,55 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const END_TXN_API_KEY: i16 = 26;
+pub const END_TXN_MIN_VERSION: i16 = 0;
+/// v3 is the first flexible version, which is not supported yet.
+pub const END_TXN_MAX_VERSION: i16 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndTxnRequest {
+    pub transactional_id: String,
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+    /// True to commit the transaction, false to abort it.
+    pub committed: bool,
+}
+
+impl EndTxnRequest {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            transactional_id: String::decode(buf)?,
+            producer_id: i64::decode(buf)?,
+            producer_epoch: i16::decode(buf)?,
+            committed: bool::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.transactional_id.encode(buf);
+        self.producer_id.encode(buf);
+        self.producer_epoch.encode(buf);
+        self.committed.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndTxnResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+}
+
+impl EndTxnResponse {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            throttle_time_ms: i32::decode(buf)?,
+            error_code: i16::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.throttle_time_ms.encode(buf);
+        self.error_code.encode(buf);
+    }
+}
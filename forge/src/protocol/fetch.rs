@@ -0,0 +1,336 @@
+use bytes::{Buf, BufMut, BytesMut};
+
+use crate::core::domain::record_batch::{RecordBatch, Records};
+use crate::protocol::response::ResponseBody;
+use crate::protocol::types::Type;
+use crate::shared::buffer_pool;
+
+pub const FETCH_API_KEY: i16 = 1;
+pub const FETCH_MIN_VERSION: i16 = 4;
+pub const FETCH_MAX_VERSION: i16 = 11;
+
+pub const ISOLATION_READ_UNCOMMITTED: i8 = 0;
+pub const ISOLATION_READ_COMMITTED: i8 = 1;
+
+/// Replica id sent by consumers; brokers fetching as followers send their own id.
+pub const CONSUMER_REPLICA_ID: i32 = -1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchRequest {
+    pub replica_id: i32,
+    pub max_wait_ms: i32,
+    pub min_bytes: i32,
+    pub max_bytes: i32,
+    pub isolation_level: i8,
+    pub session_id: i32,
+    pub session_epoch: i32,
+    pub topics: Vec<FetchTopic>,
+    pub forgotten_topics: Vec<ForgottenTopic>,
+    pub rack_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchTopic {
+    pub topic: String,
+    pub partitions: Vec<FetchPartition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchPartition {
+    pub partition: i32,
+    pub current_leader_epoch: i32,
+    pub fetch_offset: i64,
+    pub log_start_offset: i64,
+    pub partition_max_bytes: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForgottenTopic {
+    pub topic: String,
+    pub partitions: Vec<i32>,
+}
+
+impl FetchRequest {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let replica_id = i32::decode(buf)?;
+        let max_wait_ms = i32::decode(buf)?;
+        let min_bytes = i32::decode(buf)?;
+        let max_bytes = i32::decode(buf)?;
+        let isolation_level = i8::decode(buf)?;
+        let (session_id, session_epoch) = if version >= 7 {
+            (i32::decode(buf)?, i32::decode(buf)?)
+        } else {
+            (0, -1)
+        };
+
+        let topic_count = i32::decode(buf)?;
+        let mut topics = Vec::new();
+        for _ in 0..topic_count.max(0) {
+            let topic = String::decode(buf)?;
+            let partition_count = i32::decode(buf)?;
+            let mut partitions = Vec::new();
+            for _ in 0..partition_count.max(0) {
+                let partition = i32::decode(buf)?;
+                let current_leader_epoch = if version >= 9 { i32::decode(buf)? } else { -1 };
+                let fetch_offset = i64::decode(buf)?;
+                let log_start_offset = if version >= 5 { i64::decode(buf)? } else { -1 };
+                let partition_max_bytes = i32::decode(buf)?;
+                partitions.push(FetchPartition {
+                    partition,
+                    current_leader_epoch,
+                    fetch_offset,
+                    log_start_offset,
+                    partition_max_bytes,
+                });
+            }
+            topics.push(FetchTopic { topic, partitions });
+        }
+
+        let mut forgotten_topics = Vec::new();
+        if version >= 7 {
+            let forgotten_count = i32::decode(buf)?;
+            for _ in 0..forgotten_count.max(0) {
+                forgotten_topics.push(ForgottenTopic {
+                    topic: String::decode(buf)?,
+                    partitions: Vec::<i32>::decode(buf)?,
+                });
+            }
+        }
+
+        let rack_id = if version >= 11 {
+            String::decode(buf)?
+        } else {
+            String::new()
+        };
+
+        Ok(Self {
+            replica_id,
+            max_wait_ms,
+            min_bytes,
+            max_bytes,
+            isolation_level,
+            session_id,
+            session_epoch,
+            topics,
+            forgotten_topics,
+            rack_id,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.replica_id.encode(buf);
+        self.max_wait_ms.encode(buf);
+        self.min_bytes.encode(buf);
+        self.max_bytes.encode(buf);
+        self.isolation_level.encode(buf);
+        if version >= 7 {
+            self.session_id.encode(buf);
+            self.session_epoch.encode(buf);
+        }
+
+        (self.topics.len() as i32).encode(buf);
+        for topic in &self.topics {
+            topic.topic.encode(buf);
+            (topic.partitions.len() as i32).encode(buf);
+            for partition in &topic.partitions {
+                partition.partition.encode(buf);
+                if version >= 9 {
+                    partition.current_leader_epoch.encode(buf);
+                }
+                partition.fetch_offset.encode(buf);
+                if version >= 5 {
+                    partition.log_start_offset.encode(buf);
+                }
+                partition.partition_max_bytes.encode(buf);
+            }
+        }
+
+        if version >= 7 {
+            (self.forgotten_topics.len() as i32).encode(buf);
+            for forgotten in &self.forgotten_topics {
+                forgotten.topic.encode(buf);
+                forgotten.partitions.encode(buf);
+            }
+        }
+
+        if version >= 11 {
+            self.rack_id.encode(buf);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub session_id: i32,
+    pub responses: Vec<FetchableTopicResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchableTopicResponse {
+    pub topic: String,
+    pub partitions: Vec<PartitionData>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionData {
+    pub partition_index: i32,
+    pub error_code: i16,
+    pub high_watermark: i64,
+    pub last_stable_offset: i64,
+    pub log_start_offset: i64,
+    pub aborted_transactions: Option<Vec<AbortedTransaction>>,
+    pub preferred_read_replica: i32,
+    pub records: Records,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AbortedTransaction {
+    pub producer_id: i64,
+    pub first_offset: i64,
+}
+
+impl Type for AbortedTransaction {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            producer_id: i64::decode(buf)?,
+            first_offset: i64::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.producer_id.encode(buf);
+        self.first_offset.encode(buf);
+    }
+}
+
+impl FetchResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let throttle_time_ms = i32::decode(buf)?;
+        let (error_code, session_id) = if version >= 7 {
+            (i16::decode(buf)?, i32::decode(buf)?)
+        } else {
+            (0, 0)
+        };
+
+        let topic_count = i32::decode(buf)?;
+        let mut responses = Vec::new();
+        for _ in 0..topic_count.max(0) {
+            let topic = String::decode(buf)?;
+            let partition_count = i32::decode(buf)?;
+            let mut partitions = Vec::new();
+            for _ in 0..partition_count.max(0) {
+                let partition_index = i32::decode(buf)?;
+                let error_code = i16::decode(buf)?;
+                let high_watermark = i64::decode(buf)?;
+                let last_stable_offset = i64::decode(buf)?;
+                let log_start_offset = if version >= 5 { i64::decode(buf)? } else { -1 };
+                let aborted_transactions = Option::<Vec<AbortedTransaction>>::decode(buf)?;
+                let preferred_read_replica = if version >= 11 { i32::decode(buf)? } else { -1 };
+                let records = decode_raw_records(buf)?;
+                partitions.push(PartitionData {
+                    partition_index,
+                    error_code,
+                    high_watermark,
+                    last_stable_offset,
+                    log_start_offset,
+                    aborted_transactions,
+                    preferred_read_replica,
+                    records,
+                });
+            }
+            responses.push(FetchableTopicResponse { topic, partitions });
+        }
+
+        Ok(Self {
+            throttle_time_ms,
+            error_code,
+            session_id,
+            responses,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.encode_with(buf, version, |buf, records| {
+            (records.size_in_bytes() as i32).encode(buf);
+            for batch in records.raw() {
+                buf.put_slice(batch);
+            }
+        });
+    }
+
+    /// Encodes into `body` with the record batches chained in rather than copied.
+    pub fn encode_chained(&self, body: &mut ResponseBody, version: i16) {
+        let ResponseBody { buf, chunks } = body;
+        self.encode_with(buf, version, |buf: &mut BytesMut, records| {
+            (records.size_in_bytes() as i32).encode(buf);
+            for batch in records.raw() {
+                chunks.push((buf.len(), batch.clone()));
+            }
+        });
+    }
+
+    fn encode_with<B: BufMut>(
+        &self,
+        buf: &mut B,
+        version: i16,
+        mut put_records: impl FnMut(&mut B, &Records),
+    ) {
+        self.throttle_time_ms.encode(buf);
+        if version >= 7 {
+            self.error_code.encode(buf);
+            self.session_id.encode(buf);
+        }
+
+        (self.responses.len() as i32).encode(buf);
+        for topic in &self.responses {
+            topic.topic.encode(buf);
+            (topic.partitions.len() as i32).encode(buf);
+            for partition in &topic.partitions {
+                partition.partition_index.encode(buf);
+                partition.error_code.encode(buf);
+                partition.high_watermark.encode(buf);
+                partition.last_stable_offset.encode(buf);
+                if version >= 5 {
+                    partition.log_start_offset.encode(buf);
+                }
+                partition.aborted_transactions.encode(buf);
+                if version >= 11 {
+                    partition.preferred_read_replica.encode(buf);
+                }
+                put_records(buf, &partition.records);
+            }
+        }
+    }
+}
+
+/// Encodes batches as the nullable `records` bytes field shared by Produce and Fetch.
+pub fn encode_records<B: BufMut>(buf: &mut B, batches: &[RecordBatch]) {
+    let mut records = buffer_pool::take();
+    for batch in batches {
+        batch.encode_to(&mut records);
+    }
+    (records.len() as i32).encode(buf);
+    buf.put_slice(&records);
+    buffer_pool::recycle(records);
+}
+
+/// Decodes the nullable `records` bytes field. A trailing partial batch, which brokers may
+/// return when a fetch hits its byte limit, is dropped.
+pub fn decode_records<B: Buf>(buf: &mut B) -> Result<Vec<RecordBatch>, String> {
+    decode_raw_records(buf)?.batches()
+}
+
+/// Like `decode_records`, but leaves the batches encoded.
+pub fn decode_raw_records<B: Buf>(buf: &mut B) -> Result<Records, String> {
+    let len = i32::decode(buf)?;
+    if len < 0 {
+        return Ok(Records::default());
+    }
+    let len = len as usize;
+    if buf.remaining() < len {
+        return Err("Not enough data for records".to_string());
+    }
+    Ok(Records::split(buf.copy_to_bytes(len)))
+}
@@ -0,0 +1,81 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const FIND_COORDINATOR_API_KEY: i16 = 10;
+pub const FIND_COORDINATOR_MIN_VERSION: i16 = 0;
+/// v3 switches to the flexible encoding, which is not supported yet.
+pub const FIND_COORDINATOR_MAX_VERSION: i16 = 2;
+
+pub const COORDINATOR_TYPE_GROUP: i8 = 0;
+pub const COORDINATOR_TYPE_TRANSACTION: i8 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FindCoordinatorRequest {
+    /// A group id or a transactional id, depending on `key_type`.
+    pub key: String,
+    pub key_type: i8,
+}
+
+impl FindCoordinatorRequest {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        Ok(Self {
+            key: String::decode(buf)?,
+            key_type: if version >= 1 {
+                i8::decode(buf)?
+            } else {
+                COORDINATOR_TYPE_GROUP
+            },
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.key.encode(buf);
+        if version >= 1 {
+            self.key_type.encode(buf);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FindCoordinatorResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    pub node_id: i32,
+    pub host: String,
+    pub port: i32,
+}
+
+impl FindCoordinatorResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let throttle_time_ms = if version >= 1 { i32::decode(buf)? } else { 0 };
+        let error_code = i16::decode(buf)?;
+        let error_message = if version >= 1 {
+            Option::<String>::decode(buf)?
+        } else {
+            None
+        };
+        Ok(Self {
+            throttle_time_ms,
+            error_code,
+            error_message,
+            node_id: i32::decode(buf)?,
+            host: String::decode(buf)?,
+            port: i32::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        if version >= 1 {
+            self.throttle_time_ms.encode(buf);
+        }
+        self.error_code.encode(buf);
+        if version >= 1 {
+            self.error_message.encode(buf);
+        }
+        self.node_id.encode(buf);
+        self.host.encode(buf);
+        self.port.encode(buf);
+    }
+}
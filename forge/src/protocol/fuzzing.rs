@@ -0,0 +1,489 @@
+//! Entry points for the decoder fuzz targets and property tests. Each takes bytes straight
+//! from the network or the log and must return, with a value or an error, without panicking,
+//! looping or allocating far beyond its input.
+
+use crate::core::domain::consumer_protocol::{Assignment, Subscription};
+use crate::core::domain::group_records::{GroupMetadataValue, GroupRecordKey, OffsetAndMetadata};
+use crate::core::domain::metadata_records::MetadataRecord;
+use crate::core::domain::producer_id_block::ProducerIdBlock;
+use crate::core::domain::record_batch::RecordBatch;
+use crate::core::domain::transaction_records::{TransactionLogKey, TransactionLogValue};
+use crate::protocol::add_offsets_to_txn::{
+    ADD_OFFSETS_TO_TXN_API_KEY, ADD_OFFSETS_TO_TXN_MAX_VERSION, ADD_OFFSETS_TO_TXN_MIN_VERSION,
+    AddOffsetsToTxnRequest, AddOffsetsToTxnResponse,
+};
+use crate::protocol::add_partitions_to_txn::{
+    ADD_PARTITIONS_TO_TXN_API_KEY, ADD_PARTITIONS_TO_TXN_MAX_VERSION,
+    ADD_PARTITIONS_TO_TXN_MIN_VERSION, AddPartitionsToTxnRequest, AddPartitionsToTxnResponse,
+};
+use crate::protocol::alter_configs::{
+    ALTER_CONFIGS_API_KEY, ALTER_CONFIGS_MAX_VERSION, ALTER_CONFIGS_MIN_VERSION,
+    AlterConfigsRequest, AlterConfigsResponse,
+};
+use crate::protocol::alter_partition_reassignments::{
+    ALTER_PARTITION_REASSIGNMENTS_API_KEY, ALTER_PARTITION_REASSIGNMENTS_MAX_VERSION,
+    ALTER_PARTITION_REASSIGNMENTS_MIN_VERSION, AlterPartitionReassignmentsRequest,
+    AlterPartitionReassignmentsResponse,
+};
+use crate::protocol::api_versions::{
+    API_VERSIONS_API_KEY, API_VERSIONS_MAX_VERSION, API_VERSIONS_MIN_VERSION, ApiVersionsResponse,
+};
+use crate::protocol::create_acls::{
+    CREATE_ACLS_API_KEY, CREATE_ACLS_MAX_VERSION, CREATE_ACLS_MIN_VERSION, CreateAclsRequest,
+    CreateAclsResponse,
+};
+use crate::protocol::delete_acls::{
+    DELETE_ACLS_API_KEY, DELETE_ACLS_MAX_VERSION, DELETE_ACLS_MIN_VERSION, DeleteAclsRequest,
+    DeleteAclsResponse,
+};
+use crate::protocol::describe_acls::{
+    DESCRIBE_ACLS_API_KEY, DESCRIBE_ACLS_MAX_VERSION, DESCRIBE_ACLS_MIN_VERSION,
+    DescribeAclsRequest, DescribeAclsResponse,
+};
+use crate::protocol::describe_configs::{
+    DESCRIBE_CONFIGS_API_KEY, DESCRIBE_CONFIGS_MAX_VERSION, DESCRIBE_CONFIGS_MIN_VERSION,
+    DescribeConfigsRequest, DescribeConfigsResponse,
+};
+use crate::protocol::describe_groups::{
+    DESCRIBE_GROUPS_API_KEY, DESCRIBE_GROUPS_MAX_VERSION, DESCRIBE_GROUPS_MIN_VERSION,
+    DescribeGroupsRequest, DescribeGroupsResponse,
+};
+use crate::protocol::elect_leaders::{
+    ELECT_LEADERS_API_KEY, ELECT_LEADERS_MAX_VERSION, ELECT_LEADERS_MIN_VERSION,
+    ElectLeadersRequest, ElectLeadersResponse,
+};
+use crate::protocol::end_txn::{
+    END_TXN_API_KEY, END_TXN_MAX_VERSION, END_TXN_MIN_VERSION, EndTxnRequest, EndTxnResponse,
+};
+use crate::protocol::fetch::decode_records;
+use crate::protocol::fetch::{
+    FETCH_API_KEY, FETCH_MAX_VERSION, FETCH_MIN_VERSION, FetchRequest, FetchResponse,
+};
+use crate::protocol::find_coordinator::{
+    FIND_COORDINATOR_API_KEY, FIND_COORDINATOR_MAX_VERSION, FIND_COORDINATOR_MIN_VERSION,
+    FindCoordinatorRequest, FindCoordinatorResponse,
+};
+use crate::protocol::heartbeat::{
+    HEARTBEAT_API_KEY, HEARTBEAT_MAX_VERSION, HEARTBEAT_MIN_VERSION, HeartbeatRequest,
+    HeartbeatResponse,
+};
+use crate::protocol::init_producer_id::{
+    INIT_PRODUCER_ID_API_KEY, INIT_PRODUCER_ID_MAX_VERSION, INIT_PRODUCER_ID_MIN_VERSION,
+    InitProducerIdRequest, InitProducerIdResponse,
+};
+use crate::protocol::join_group::{
+    JOIN_GROUP_API_KEY, JOIN_GROUP_MAX_VERSION, JOIN_GROUP_MIN_VERSION, JoinGroupRequest,
+    JoinGroupResponse,
+};
+use crate::protocol::leave_group::{
+    LEAVE_GROUP_API_KEY, LEAVE_GROUP_MAX_VERSION, LEAVE_GROUP_MIN_VERSION, LeaveGroupRequest,
+    LeaveGroupResponse,
+};
+use crate::protocol::list_groups::{
+    LIST_GROUPS_API_KEY, LIST_GROUPS_MAX_VERSION, LIST_GROUPS_MIN_VERSION, ListGroupsRequest,
+    ListGroupsResponse,
+};
+use crate::protocol::list_offsets::{
+    LIST_OFFSETS_API_KEY, LIST_OFFSETS_MAX_VERSION, LIST_OFFSETS_MIN_VERSION, ListOffsetsRequest,
+    ListOffsetsResponse,
+};
+use crate::protocol::list_partition_reassignments::{
+    LIST_PARTITION_REASSIGNMENTS_API_KEY, LIST_PARTITION_REASSIGNMENTS_MAX_VERSION,
+    LIST_PARTITION_REASSIGNMENTS_MIN_VERSION, ListPartitionReassignmentsRequest,
+    ListPartitionReassignmentsResponse,
+};
+use crate::protocol::metadata::{
+    METADATA_API_KEY, METADATA_MAX_VERSION, METADATA_MIN_VERSION, MetadataRequest, MetadataResponse,
+};
+use crate::protocol::offset_commit::{
+    OFFSET_COMMIT_API_KEY, OFFSET_COMMIT_MAX_VERSION, OFFSET_COMMIT_MIN_VERSION,
+    OffsetCommitRequest, OffsetCommitResponse,
+};
+use crate::protocol::offset_fetch::{
+    OFFSET_FETCH_API_KEY, OFFSET_FETCH_MAX_VERSION, OFFSET_FETCH_MIN_VERSION, OffsetFetchRequest,
+    OffsetFetchResponse,
+};
+use crate::protocol::produce::{
+    PRODUCE_API_KEY, PRODUCE_MAX_VERSION, PRODUCE_MIN_VERSION, ProduceRequest, ProduceResponse,
+};
+use crate::protocol::request::RequestHeader;
+use crate::protocol::response::ResponseHeader;
+use crate::protocol::sasl_authenticate::{
+    SASL_AUTHENTICATE_API_KEY, SASL_AUTHENTICATE_MAX_VERSION, SASL_AUTHENTICATE_MIN_VERSION,
+    SaslAuthenticateRequest, SaslAuthenticateResponse,
+};
+use crate::protocol::sasl_handshake::{
+    SASL_HANDSHAKE_API_KEY, SASL_HANDSHAKE_MAX_VERSION, SASL_HANDSHAKE_MIN_VERSION,
+    SaslHandshakeRequest, SaslHandshakeResponse,
+};
+use crate::protocol::sync_group::{
+    SYNC_GROUP_API_KEY, SYNC_GROUP_MAX_VERSION, SYNC_GROUP_MIN_VERSION, SyncGroupRequest,
+    SyncGroupResponse,
+};
+use crate::protocol::txn_offset_commit::{
+    TXN_OFFSET_COMMIT_API_KEY, TXN_OFFSET_COMMIT_MAX_VERSION, TXN_OFFSET_COMMIT_MIN_VERSION,
+    TxnOffsetCommitRequest, TxnOffsetCommitResponse,
+};
+use crate::protocol::types::{TaggedFields, Type};
+
+/// Every API with decoders, as (api key, min version, max version).
+pub const APIS: &[(i16, i16, i16)] = &[
+    (
+        API_VERSIONS_API_KEY,
+        API_VERSIONS_MIN_VERSION,
+        API_VERSIONS_MAX_VERSION,
+    ),
+    (
+        ADD_OFFSETS_TO_TXN_API_KEY,
+        ADD_OFFSETS_TO_TXN_MIN_VERSION,
+        ADD_OFFSETS_TO_TXN_MAX_VERSION,
+    ),
+    (
+        ADD_PARTITIONS_TO_TXN_API_KEY,
+        ADD_PARTITIONS_TO_TXN_MIN_VERSION,
+        ADD_PARTITIONS_TO_TXN_MAX_VERSION,
+    ),
+    (
+        ALTER_CONFIGS_API_KEY,
+        ALTER_CONFIGS_MIN_VERSION,
+        ALTER_CONFIGS_MAX_VERSION,
+    ),
+    (
+        ALTER_PARTITION_REASSIGNMENTS_API_KEY,
+        ALTER_PARTITION_REASSIGNMENTS_MIN_VERSION,
+        ALTER_PARTITION_REASSIGNMENTS_MAX_VERSION,
+    ),
+    (
+        CREATE_ACLS_API_KEY,
+        CREATE_ACLS_MIN_VERSION,
+        CREATE_ACLS_MAX_VERSION,
+    ),
+    (
+        DELETE_ACLS_API_KEY,
+        DELETE_ACLS_MIN_VERSION,
+        DELETE_ACLS_MAX_VERSION,
+    ),
+    (
+        DESCRIBE_ACLS_API_KEY,
+        DESCRIBE_ACLS_MIN_VERSION,
+        DESCRIBE_ACLS_MAX_VERSION,
+    ),
+    (
+        DESCRIBE_CONFIGS_API_KEY,
+        DESCRIBE_CONFIGS_MIN_VERSION,
+        DESCRIBE_CONFIGS_MAX_VERSION,
+    ),
+    (
+        DESCRIBE_GROUPS_API_KEY,
+        DESCRIBE_GROUPS_MIN_VERSION,
+        DESCRIBE_GROUPS_MAX_VERSION,
+    ),
+    (
+        ELECT_LEADERS_API_KEY,
+        ELECT_LEADERS_MIN_VERSION,
+        ELECT_LEADERS_MAX_VERSION,
+    ),
+    (END_TXN_API_KEY, END_TXN_MIN_VERSION, END_TXN_MAX_VERSION),
+    (FETCH_API_KEY, FETCH_MIN_VERSION, FETCH_MAX_VERSION),
+    (
+        FIND_COORDINATOR_API_KEY,
+        FIND_COORDINATOR_MIN_VERSION,
+        FIND_COORDINATOR_MAX_VERSION,
+    ),
+    (
+        HEARTBEAT_API_KEY,
+        HEARTBEAT_MIN_VERSION,
+        HEARTBEAT_MAX_VERSION,
+    ),
+    (
+        INIT_PRODUCER_ID_API_KEY,
+        INIT_PRODUCER_ID_MIN_VERSION,
+        INIT_PRODUCER_ID_MAX_VERSION,
+    ),
+    (
+        JOIN_GROUP_API_KEY,
+        JOIN_GROUP_MIN_VERSION,
+        JOIN_GROUP_MAX_VERSION,
+    ),
+    (
+        LEAVE_GROUP_API_KEY,
+        LEAVE_GROUP_MIN_VERSION,
+        LEAVE_GROUP_MAX_VERSION,
+    ),
+    (
+        LIST_GROUPS_API_KEY,
+        LIST_GROUPS_MIN_VERSION,
+        LIST_GROUPS_MAX_VERSION,
+    ),
+    (
+        LIST_OFFSETS_API_KEY,
+        LIST_OFFSETS_MIN_VERSION,
+        LIST_OFFSETS_MAX_VERSION,
+    ),
+    (
+        LIST_PARTITION_REASSIGNMENTS_API_KEY,
+        LIST_PARTITION_REASSIGNMENTS_MIN_VERSION,
+        LIST_PARTITION_REASSIGNMENTS_MAX_VERSION,
+    ),
+    (METADATA_API_KEY, METADATA_MIN_VERSION, METADATA_MAX_VERSION),
+    (
+        OFFSET_COMMIT_API_KEY,
+        OFFSET_COMMIT_MIN_VERSION,
+        OFFSET_COMMIT_MAX_VERSION,
+    ),
+    (
+        OFFSET_FETCH_API_KEY,
+        OFFSET_FETCH_MIN_VERSION,
+        OFFSET_FETCH_MAX_VERSION,
+    ),
+    (PRODUCE_API_KEY, PRODUCE_MIN_VERSION, PRODUCE_MAX_VERSION),
+    (
+        SASL_AUTHENTICATE_API_KEY,
+        SASL_AUTHENTICATE_MIN_VERSION,
+        SASL_AUTHENTICATE_MAX_VERSION,
+    ),
+    (
+        SASL_HANDSHAKE_API_KEY,
+        SASL_HANDSHAKE_MIN_VERSION,
+        SASL_HANDSHAKE_MAX_VERSION,
+    ),
+    (
+        SYNC_GROUP_API_KEY,
+        SYNC_GROUP_MIN_VERSION,
+        SYNC_GROUP_MAX_VERSION,
+    ),
+    (
+        TXN_OFFSET_COMMIT_API_KEY,
+        TXN_OFFSET_COMMIT_MIN_VERSION,
+        TXN_OFFSET_COMMIT_MAX_VERSION,
+    ),
+];
+
+/// Decodes a request frame the way the broker does: the header, then the body its api key
+/// and version select.
+pub fn decode_request(mut data: &[u8]) -> Result<(), String> {
+    let header = RequestHeader::decode(&mut data)?;
+    decode_request_body(header.api_key, header.api_version, data)
+}
+
+pub fn decode_request_body(api_key: i16, version: i16, mut data: &[u8]) -> Result<(), String> {
+    let buf = &mut data;
+    match api_key {
+        // The broker answers ApiVersions without reading the body
+        API_VERSIONS_API_KEY => {}
+        ALTER_PARTITION_REASSIGNMENTS_API_KEY => {
+            TaggedFields::decode(buf)?;
+            AlterPartitionReassignmentsRequest::decode(buf, version)?;
+        }
+        LIST_PARTITION_REASSIGNMENTS_API_KEY => {
+            TaggedFields::decode(buf)?;
+            ListPartitionReassignmentsRequest::decode(buf, version)?;
+        }
+        ADD_OFFSETS_TO_TXN_API_KEY => {
+            AddOffsetsToTxnRequest::decode(buf, version)?;
+        }
+        ADD_PARTITIONS_TO_TXN_API_KEY => {
+            AddPartitionsToTxnRequest::decode(buf, version)?;
+        }
+        ALTER_CONFIGS_API_KEY => {
+            AlterConfigsRequest::decode(buf, version)?;
+        }
+        CREATE_ACLS_API_KEY => {
+            CreateAclsRequest::decode(buf, version)?;
+        }
+        DELETE_ACLS_API_KEY => {
+            DeleteAclsRequest::decode(buf, version)?;
+        }
+        DESCRIBE_ACLS_API_KEY => {
+            DescribeAclsRequest::decode(buf, version)?;
+        }
+        DESCRIBE_CONFIGS_API_KEY => {
+            DescribeConfigsRequest::decode(buf, version)?;
+        }
+        DESCRIBE_GROUPS_API_KEY => {
+            DescribeGroupsRequest::decode(buf, version)?;
+        }
+        ELECT_LEADERS_API_KEY => {
+            ElectLeadersRequest::decode(buf, version)?;
+        }
+        END_TXN_API_KEY => {
+            EndTxnRequest::decode(buf, version)?;
+        }
+        FETCH_API_KEY => {
+            FetchRequest::decode(buf, version)?;
+        }
+        FIND_COORDINATOR_API_KEY => {
+            FindCoordinatorRequest::decode(buf, version)?;
+        }
+        HEARTBEAT_API_KEY => {
+            HeartbeatRequest::decode(buf, version)?;
+        }
+        INIT_PRODUCER_ID_API_KEY => {
+            InitProducerIdRequest::decode(buf, version)?;
+        }
+        JOIN_GROUP_API_KEY => {
+            JoinGroupRequest::decode(buf, version)?;
+        }
+        LEAVE_GROUP_API_KEY => {
+            LeaveGroupRequest::decode(buf, version)?;
+        }
+        LIST_GROUPS_API_KEY => {
+            ListGroupsRequest::decode(buf, version)?;
+        }
+        LIST_OFFSETS_API_KEY => {
+            ListOffsetsRequest::decode(buf, version)?;
+        }
+        METADATA_API_KEY => {
+            MetadataRequest::decode(buf, version)?;
+        }
+        OFFSET_COMMIT_API_KEY => {
+            OffsetCommitRequest::decode(buf, version)?;
+        }
+        OFFSET_FETCH_API_KEY => {
+            OffsetFetchRequest::decode(buf, version)?;
+        }
+        PRODUCE_API_KEY => {
+            ProduceRequest::decode(buf, version)?;
+        }
+        SASL_AUTHENTICATE_API_KEY => {
+            SaslAuthenticateRequest::decode(buf, version)?;
+        }
+        SASL_HANDSHAKE_API_KEY => {
+            SaslHandshakeRequest::decode(buf, version)?;
+        }
+        SYNC_GROUP_API_KEY => {
+            SyncGroupRequest::decode(buf, version)?;
+        }
+        TXN_OFFSET_COMMIT_API_KEY => {
+            TxnOffsetCommitRequest::decode(buf, version)?;
+        }
+        _ => return Err(format!("Unknown api key {}", api_key)),
+    }
+    Ok(())
+}
+
+/// Decodes a response frame the way the client does, after its header.
+pub fn decode_response(api_key: i16, version: i16, mut data: &[u8]) -> Result<(), String> {
+    let buf = &mut data;
+    ResponseHeader::decode(buf)?;
+    match api_key {
+        API_VERSIONS_API_KEY => {
+            ApiVersionsResponse::decode(buf, version)?;
+        }
+        ADD_OFFSETS_TO_TXN_API_KEY => {
+            AddOffsetsToTxnResponse::decode(buf, version)?;
+        }
+        ADD_PARTITIONS_TO_TXN_API_KEY => {
+            AddPartitionsToTxnResponse::decode(buf, version)?;
+        }
+        ALTER_CONFIGS_API_KEY => {
+            AlterConfigsResponse::decode(buf, version)?;
+        }
+        ALTER_PARTITION_REASSIGNMENTS_API_KEY => {
+            AlterPartitionReassignmentsResponse::decode(buf, version)?;
+        }
+        CREATE_ACLS_API_KEY => {
+            CreateAclsResponse::decode(buf, version)?;
+        }
+        DELETE_ACLS_API_KEY => {
+            DeleteAclsResponse::decode(buf, version)?;
+        }
+        DESCRIBE_ACLS_API_KEY => {
+            DescribeAclsResponse::decode(buf, version)?;
+        }
+        DESCRIBE_CONFIGS_API_KEY => {
+            DescribeConfigsResponse::decode(buf, version)?;
+        }
+        DESCRIBE_GROUPS_API_KEY => {
+            DescribeGroupsResponse::decode(buf, version)?;
+        }
+        ELECT_LEADERS_API_KEY => {
+            ElectLeadersResponse::decode(buf, version)?;
+        }
+        END_TXN_API_KEY => {
+            EndTxnResponse::decode(buf, version)?;
+        }
+        FETCH_API_KEY => {
+            FetchResponse::decode(buf, version)?;
+        }
+        FIND_COORDINATOR_API_KEY => {
+            FindCoordinatorResponse::decode(buf, version)?;
+        }
+        HEARTBEAT_API_KEY => {
+            HeartbeatResponse::decode(buf, version)?;
+        }
+        INIT_PRODUCER_ID_API_KEY => {
+            InitProducerIdResponse::decode(buf, version)?;
+        }
+        JOIN_GROUP_API_KEY => {
+            JoinGroupResponse::decode(buf, version)?;
+        }
+        LEAVE_GROUP_API_KEY => {
+            LeaveGroupResponse::decode(buf, version)?;
+        }
+        LIST_GROUPS_API_KEY => {
+            ListGroupsResponse::decode(buf, version)?;
+        }
+        LIST_OFFSETS_API_KEY => {
+            ListOffsetsResponse::decode(buf, version)?;
+        }
+        LIST_PARTITION_REASSIGNMENTS_API_KEY => {
+            ListPartitionReassignmentsResponse::decode(buf, version)?;
+        }
+        METADATA_API_KEY => {
+            MetadataResponse::decode(buf, version)?;
+        }
+        OFFSET_COMMIT_API_KEY => {
+            OffsetCommitResponse::decode(buf, version)?;
+        }
+        OFFSET_FETCH_API_KEY => {
+            OffsetFetchResponse::decode(buf, version)?;
+        }
+        PRODUCE_API_KEY => {
+            ProduceResponse::decode(buf, version)?;
+        }
+        SASL_AUTHENTICATE_API_KEY => {
+            SaslAuthenticateResponse::decode(buf, version)?;
+        }
+        SASL_HANDSHAKE_API_KEY => {
+            SaslHandshakeResponse::decode(buf, version)?;
+        }
+        SYNC_GROUP_API_KEY => {
+            SyncGroupResponse::decode(buf, version)?;
+        }
+        TXN_OFFSET_COMMIT_API_KEY => {
+            TxnOffsetCommitResponse::decode(buf, version)?;
+        }
+        _ => return Err(format!("Unknown api key {}", api_key)),
+    }
+    Ok(())
+}
+
+/// Decodes a produce or fetch `records` field: a size, then back-to-back batches.
+pub fn decode_record_batches(mut data: &[u8]) -> Result<(), String> {
+    decode_records(&mut data).map(|_| ())
+}
+
+pub fn decode_record_batch(mut data: &[u8]) -> Result<(), String> {
+    RecordBatch::decode(&mut data).map(|_| ())
+}
+
+/// Decodes the payloads the broker and clients keep inside records: consumer protocol
+/// metadata, and the keys and values of the internal topics. The first byte picks the type.
+pub fn decode_embedded(data: &[u8]) -> Result<(), String> {
+    let Some((kind, mut buf)) = data.split_first() else {
+        return Ok(());
+    };
+    let buf = &mut buf;
+    match kind % 9 {
+        0 => Subscription::decode(buf).map(|_| ()),
+        1 => Assignment::decode(buf).map(|_| ()),
+        2 => GroupRecordKey::decode(buf).map(|_| ()),
+        3 => OffsetAndMetadata::decode(buf).map(|_| ()),
+        4 => GroupMetadataValue::decode(buf).map(|_| ()),
+        5 => MetadataRecord::decode(buf).map(|_| ()),
+        6 => TransactionLogKey::decode(buf).map(|_| ()),
+        7 => TransactionLogValue::decode(buf).map(|_| ()),
+        _ => ProducerIdBlock::decode(buf).map(|_| ()),
+    }
+}
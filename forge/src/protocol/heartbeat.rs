@@ -0,0 +1,55 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const HEARTBEAT_API_KEY: i16 = 12;
+pub const HEARTBEAT_MIN_VERSION: i16 = 0;
+/// v3 adds static membership, which is not supported yet.
+pub const HEARTBEAT_MAX_VERSION: i16 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeartbeatRequest {
+    pub group_id: String,
+    pub generation_id: i32,
+    pub member_id: String,
+}
+
+impl HeartbeatRequest {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            group_id: String::decode(buf)?,
+            generation_id: i32::decode(buf)?,
+            member_id: String::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.group_id.encode(buf);
+        self.generation_id.encode(buf);
+        self.member_id.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeartbeatResponse {
+    pub throttle_time_ms: i32,
+    /// REBALANCE_IN_PROGRESS tells the member to rejoin.
+    pub error_code: i16,
+}
+
+impl HeartbeatResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let throttle_time_ms = if version >= 1 { i32::decode(buf)? } else { 0 };
+        Ok(Self {
+            throttle_time_ms,
+            error_code: i16::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        if version >= 1 {
+            self.throttle_time_ms.encode(buf);
+        }
+        self.error_code.encode(buf);
+    }
+}
@@ -0,0 +1,56 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const INIT_PRODUCER_ID_API_KEY: i16 = 22;
+pub const INIT_PRODUCER_ID_MIN_VERSION: i16 = 0;
+/// v2 is the first flexible version, which is not supported yet.
+pub const INIT_PRODUCER_ID_MAX_VERSION: i16 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InitProducerIdRequest {
+    /// None for an idempotent producer that is not transactional.
+    pub transactional_id: Option<String>,
+    pub transaction_timeout_ms: i32,
+}
+
+impl InitProducerIdRequest {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            transactional_id: Option::<String>::decode(buf)?,
+            transaction_timeout_ms: i32::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.transactional_id.encode(buf);
+        self.transaction_timeout_ms.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InitProducerIdResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    /// -1 on error.
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+}
+
+impl InitProducerIdResponse {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            throttle_time_ms: i32::decode(buf)?,
+            error_code: i16::decode(buf)?,
+            producer_id: i64::decode(buf)?,
+            producer_epoch: i16::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.throttle_time_ms.encode(buf);
+        self.error_code.encode(buf);
+        self.producer_id.encode(buf);
+        self.producer_epoch.encode(buf);
+    }
+}
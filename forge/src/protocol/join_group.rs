@@ -0,0 +1,152 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const JOIN_GROUP_API_KEY: i16 = 11;
+pub const JOIN_GROUP_MIN_VERSION: i16 = 0;
+/// v5 adds static membership, which is not supported yet.
+pub const JOIN_GROUP_MAX_VERSION: i16 = 4;
+
+/// The member id a new member joins with; the coordinator assigns one.
+pub const UNKNOWN_MEMBER_ID: &str = "";
+
+fn decode_bytes<B: Buf>(buf: &mut B) -> Result<Vec<u8>, String> {
+    let len = i32::decode(buf)?;
+    if len < 0 {
+        return Ok(Vec::new());
+    }
+    let len = len as usize;
+    if buf.remaining() < len {
+        return Err("Not enough data for protocol metadata".to_string());
+    }
+    let mut bytes = vec![0u8; len];
+    buf.copy_to_slice(&mut bytes);
+    Ok(bytes)
+}
+
+fn encode_bytes<B: BufMut>(bytes: &[u8], buf: &mut B) {
+    buf.put_i32(bytes.len() as i32);
+    buf.put_slice(bytes);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinGroupRequest {
+    pub group_id: String,
+    pub session_timeout_ms: i32,
+    /// v1+; v0 members get their session timeout.
+    pub rebalance_timeout_ms: i32,
+    pub member_id: String,
+    pub protocol_type: String,
+    /// The protocols the member supports, most preferred first.
+    pub protocols: Vec<JoinGroupRequestProtocol>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinGroupRequestProtocol {
+    pub name: String,
+    pub metadata: Vec<u8>,
+}
+
+impl Type for JoinGroupRequestProtocol {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            name: String::decode(buf)?,
+            metadata: decode_bytes(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.name.encode(buf);
+        encode_bytes(&self.metadata, buf);
+    }
+}
+
+impl JoinGroupRequest {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let group_id = String::decode(buf)?;
+        let session_timeout_ms = i32::decode(buf)?;
+        let rebalance_timeout_ms = if version >= 1 {
+            i32::decode(buf)?
+        } else {
+            session_timeout_ms
+        };
+        Ok(Self {
+            group_id,
+            session_timeout_ms,
+            rebalance_timeout_ms,
+            member_id: String::decode(buf)?,
+            protocol_type: String::decode(buf)?,
+            protocols: Vec::<JoinGroupRequestProtocol>::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.group_id.encode(buf);
+        self.session_timeout_ms.encode(buf);
+        if version >= 1 {
+            self.rebalance_timeout_ms.encode(buf);
+        }
+        self.member_id.encode(buf);
+        self.protocol_type.encode(buf);
+        self.protocols.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinGroupResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub generation_id: i32,
+    pub protocol_name: String,
+    pub leader: String,
+    pub member_id: String,
+    /// Every member's metadata for the chosen protocol; only the leader gets them.
+    pub members: Vec<JoinGroupResponseMember>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinGroupResponseMember {
+    pub member_id: String,
+    pub metadata: Vec<u8>,
+}
+
+impl Type for JoinGroupResponseMember {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            member_id: String::decode(buf)?,
+            metadata: decode_bytes(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.member_id.encode(buf);
+        encode_bytes(&self.metadata, buf);
+    }
+}
+
+impl JoinGroupResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let throttle_time_ms = if version >= 2 { i32::decode(buf)? } else { 0 };
+        Ok(Self {
+            throttle_time_ms,
+            error_code: i16::decode(buf)?,
+            generation_id: i32::decode(buf)?,
+            protocol_name: String::decode(buf)?,
+            leader: String::decode(buf)?,
+            member_id: String::decode(buf)?,
+            members: Vec::<JoinGroupResponseMember>::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        if version >= 2 {
+            self.throttle_time_ms.encode(buf);
+        }
+        self.error_code.encode(buf);
+        self.generation_id.encode(buf);
+        self.protocol_name.encode(buf);
+        self.leader.encode(buf);
+        self.member_id.encode(buf);
+        self.members.encode(buf);
+    }
+}
@@ -0,0 +1,52 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const LEAVE_GROUP_API_KEY: i16 = 13;
+pub const LEAVE_GROUP_MIN_VERSION: i16 = 0;
+/// v3 removes several members at once, identified by static membership, which is not
+/// supported yet.
+pub const LEAVE_GROUP_MAX_VERSION: i16 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaveGroupRequest {
+    pub group_id: String,
+    pub member_id: String,
+}
+
+impl LeaveGroupRequest {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            group_id: String::decode(buf)?,
+            member_id: String::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.group_id.encode(buf);
+        self.member_id.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaveGroupResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+}
+
+impl LeaveGroupResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let throttle_time_ms = if version >= 1 { i32::decode(buf)? } else { 0 };
+        Ok(Self {
+            throttle_time_ms,
+            error_code: i16::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        if version >= 1 {
+            self.throttle_time_ms.encode(buf);
+        }
+        self.error_code.encode(buf);
+    }
+}
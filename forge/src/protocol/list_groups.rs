@@ -0,0 +1,66 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const LIST_GROUPS_API_KEY: i16 = 16;
+pub const LIST_GROUPS_MIN_VERSION: i16 = 0;
+/// v3 switches to the flexible encoding, which is not supported yet.
+pub const LIST_GROUPS_MAX_VERSION: i16 = 2;
+
+/// Every version's request body is empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListGroupsRequest;
+
+impl ListGroupsRequest {
+    pub fn decode<B: Buf>(_buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self)
+    }
+
+    pub fn encode<B: BufMut>(&self, _buf: &mut B, _version: i16) {}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListGroupsResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub groups: Vec<ListedGroup>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListedGroup {
+    pub group_id: String,
+    /// Empty for groups that only hold committed offsets.
+    pub protocol_type: String,
+}
+
+impl Type for ListedGroup {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            group_id: String::decode(buf)?,
+            protocol_type: String::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.group_id.encode(buf);
+        self.protocol_type.encode(buf);
+    }
+}
+
+impl ListGroupsResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        Ok(Self {
+            throttle_time_ms: if version >= 1 { i32::decode(buf)? } else { 0 },
+            error_code: i16::decode(buf)?,
+            groups: Vec::<ListedGroup>::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        if version >= 1 {
+            self.throttle_time_ms.encode(buf);
+        }
+        self.error_code.encode(buf);
+        self.groups.encode(buf);
+    }
+}
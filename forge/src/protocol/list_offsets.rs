@@ -0,0 +1,156 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const LIST_OFFSETS_API_KEY: i16 = 2;
+pub const LIST_OFFSETS_MIN_VERSION: i16 = 1;
+/// v6 switches to the flexible encoding, which is not supported yet.
+pub const LIST_OFFSETS_MAX_VERSION: i16 = 5;
+
+/// Asks for the log start offset instead of an offset for a timestamp.
+pub const EARLIEST_TIMESTAMP: i64 = -2;
+/// Asks for the high watermark, or the last stable offset under read_committed.
+pub const LATEST_TIMESTAMP: i64 = -1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListOffsetsRequest {
+    pub replica_id: i32,
+    pub isolation_level: i8,
+    pub topics: Vec<ListOffsetsTopic>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListOffsetsTopic {
+    pub name: String,
+    pub partitions: Vec<ListOffsetsPartition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListOffsetsPartition {
+    pub partition_index: i32,
+    pub current_leader_epoch: i32,
+    pub timestamp: i64,
+}
+
+impl ListOffsetsRequest {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let replica_id = i32::decode(buf)?;
+        let isolation_level = if version >= 2 { i8::decode(buf)? } else { 0 };
+
+        let topic_count = i32::decode(buf)?;
+        let mut topics = Vec::new();
+        for _ in 0..topic_count.max(0) {
+            let name = String::decode(buf)?;
+            let partition_count = i32::decode(buf)?;
+            let mut partitions = Vec::new();
+            for _ in 0..partition_count.max(0) {
+                let partition_index = i32::decode(buf)?;
+                let current_leader_epoch = if version >= 4 { i32::decode(buf)? } else { -1 };
+                partitions.push(ListOffsetsPartition {
+                    partition_index,
+                    current_leader_epoch,
+                    timestamp: i64::decode(buf)?,
+                });
+            }
+            topics.push(ListOffsetsTopic { name, partitions });
+        }
+
+        Ok(Self {
+            replica_id,
+            isolation_level,
+            topics,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.replica_id.encode(buf);
+        if version >= 2 {
+            self.isolation_level.encode(buf);
+        }
+
+        (self.topics.len() as i32).encode(buf);
+        for topic in &self.topics {
+            topic.name.encode(buf);
+            (topic.partitions.len() as i32).encode(buf);
+            for partition in &topic.partitions {
+                partition.partition_index.encode(buf);
+                if version >= 4 {
+                    partition.current_leader_epoch.encode(buf);
+                }
+                partition.timestamp.encode(buf);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListOffsetsResponse {
+    pub throttle_time_ms: i32,
+    pub topics: Vec<ListOffsetsTopicResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListOffsetsTopicResponse {
+    pub name: String,
+    pub partitions: Vec<ListOffsetsPartitionResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListOffsetsPartitionResponse {
+    pub partition_index: i32,
+    pub error_code: i16,
+    /// -1 when answering for `EARLIEST_TIMESTAMP` or `LATEST_TIMESTAMP`.
+    pub timestamp: i64,
+    pub offset: i64,
+    pub leader_epoch: i32,
+}
+
+impl ListOffsetsResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let throttle_time_ms = if version >= 2 { i32::decode(buf)? } else { 0 };
+
+        let topic_count = i32::decode(buf)?;
+        let mut topics = Vec::new();
+        for _ in 0..topic_count.max(0) {
+            let name = String::decode(buf)?;
+            let partition_count = i32::decode(buf)?;
+            let mut partitions = Vec::new();
+            for _ in 0..partition_count.max(0) {
+                partitions.push(ListOffsetsPartitionResponse {
+                    partition_index: i32::decode(buf)?,
+                    error_code: i16::decode(buf)?,
+                    timestamp: i64::decode(buf)?,
+                    offset: i64::decode(buf)?,
+                    leader_epoch: if version >= 4 { i32::decode(buf)? } else { -1 },
+                });
+            }
+            topics.push(ListOffsetsTopicResponse { name, partitions });
+        }
+
+        Ok(Self {
+            throttle_time_ms,
+            topics,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        if version >= 2 {
+            self.throttle_time_ms.encode(buf);
+        }
+
+        (self.topics.len() as i32).encode(buf);
+        for topic in &self.topics {
+            topic.name.encode(buf);
+            (topic.partitions.len() as i32).encode(buf);
+            for partition in &topic.partitions {
+                partition.partition_index.encode(buf);
+                partition.error_code.encode(buf);
+                partition.timestamp.encode(buf);
+                partition.offset.encode(buf);
+                if version >= 4 {
+                    partition.leader_epoch.encode(buf);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::{CompactArray, CompactString, TaggedFields, Type};
+
+pub const LIST_PARTITION_REASSIGNMENTS_API_KEY: i16 = 46;
+/// Only flexible versions exist: requests use header v2 and responses header v1.
+pub const LIST_PARTITION_REASSIGNMENTS_MIN_VERSION: i16 = 0;
+pub const LIST_PARTITION_REASSIGNMENTS_MAX_VERSION: i16 = 0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListPartitionReassignmentsRequest {
+    pub timeout_ms: i32,
+    /// `None` lists every reassignment in progress.
+    pub topics: Option<Vec<ListPartitionReassignmentsTopic>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListPartitionReassignmentsTopic {
+    pub name: String,
+    pub partition_indexes: Vec<i32>,
+}
+
+impl Type for ListPartitionReassignmentsTopic {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let name = CompactString::decode(buf)?.0;
+        let partition_indexes = CompactArray::<i32>::decode(buf)?.0;
+        TaggedFields::decode(buf)?;
+        Ok(Self {
+            name,
+            partition_indexes,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        CompactString(self.name.clone()).encode(buf);
+        CompactArray(self.partition_indexes.clone()).encode(buf);
+        TaggedFields.encode(buf);
+    }
+}
+
+impl ListPartitionReassignmentsRequest {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        let timeout_ms = i32::decode(buf)?;
+        let topics = Option::<CompactArray<ListPartitionReassignmentsTopic>>::decode(buf)?;
+        TaggedFields::decode(buf)?;
+        Ok(Self {
+            timeout_ms,
+            topics: topics.map(|topics| topics.0),
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.timeout_ms.encode(buf);
+        self.topics.clone().map(CompactArray).encode(buf);
+        TaggedFields.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListPartitionReassignmentsResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    pub topics: Vec<OngoingTopicReassignment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OngoingTopicReassignment {
+    pub name: String,
+    pub partitions: Vec<OngoingPartitionReassignment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OngoingPartitionReassignment {
+    pub partition_index: i32,
+    /// Every current replica, the adding and removing ones included.
+    pub replicas: Vec<i32>,
+    pub adding_replicas: Vec<i32>,
+    pub removing_replicas: Vec<i32>,
+}
+
+impl Type for OngoingPartitionReassignment {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let partition_index = i32::decode(buf)?;
+        let replicas = CompactArray::<i32>::decode(buf)?.0;
+        let adding_replicas = CompactArray::<i32>::decode(buf)?.0;
+        let removing_replicas = CompactArray::<i32>::decode(buf)?.0;
+        TaggedFields::decode(buf)?;
+        Ok(Self {
+            partition_index,
+            replicas,
+            adding_replicas,
+            removing_replicas,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.partition_index.encode(buf);
+        CompactArray(self.replicas.clone()).encode(buf);
+        CompactArray(self.adding_replicas.clone()).encode(buf);
+        CompactArray(self.removing_replicas.clone()).encode(buf);
+        TaggedFields.encode(buf);
+    }
+}
+
+impl Type for OngoingTopicReassignment {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let name = CompactString::decode(buf)?.0;
+        let partitions = CompactArray::<OngoingPartitionReassignment>::decode(buf)?.0;
+        TaggedFields::decode(buf)?;
+        Ok(Self { name, partitions })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        CompactString(self.name.clone()).encode(buf);
+        CompactArray(self.partitions.clone()).encode(buf);
+        TaggedFields.encode(buf);
+    }
+}
+
+impl ListPartitionReassignmentsResponse {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        let throttle_time_ms = i32::decode(buf)?;
+        let error_code = i16::decode(buf)?;
+        let error_message = Option::<CompactString>::decode(buf)?;
+        let topics = CompactArray::<OngoingTopicReassignment>::decode(buf)?.0;
+        TaggedFields::decode(buf)?;
+        Ok(Self {
+            throttle_time_ms,
+            error_code,
+            error_message: error_message.map(|message| message.0),
+            topics,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.throttle_time_ms.encode(buf);
+        self.error_code.encode(buf);
+        self.error_message.clone().map(CompactString).encode(buf);
+        CompactArray(self.topics.clone()).encode(buf);
+        TaggedFields.encode(buf);
+    }
+}
@@ -0,0 +1,262 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const METADATA_API_KEY: i16 = 3;
+pub const METADATA_MIN_VERSION: i16 = 0;
+/// v9 switches to the flexible encoding, which is not supported yet.
+pub const METADATA_MAX_VERSION: i16 = 8;
+
+/// Sent in the authorized operations fields when the client did not ask for them.
+pub const AUTHORIZED_OPERATIONS_OMITTED: i32 = i32::MIN;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataRequest {
+    /// `None` asks for every topic; v0 encodes that as an empty list.
+    pub topics: Option<Vec<String>>,
+    /// v4+.
+    pub allow_auto_topic_creation: bool,
+    /// v8+.
+    pub include_cluster_authorized_operations: bool,
+    /// v8+.
+    pub include_topic_authorized_operations: bool,
+}
+
+impl MetadataRequest {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let topics = if version >= 1 {
+            Option::<Vec<String>>::decode(buf)?
+        } else {
+            Some(Vec::<String>::decode(buf)?).filter(|topics| !topics.is_empty())
+        };
+        let allow_auto_topic_creation = version < 4 || bool::decode(buf)?;
+        let (include_cluster_authorized_operations, include_topic_authorized_operations) =
+            if version >= 8 {
+                (bool::decode(buf)?, bool::decode(buf)?)
+            } else {
+                (false, false)
+            };
+        Ok(Self {
+            topics,
+            allow_auto_topic_creation,
+            include_cluster_authorized_operations,
+            include_topic_authorized_operations,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        if version >= 1 {
+            self.topics.encode(buf);
+        } else {
+            self.topics.clone().unwrap_or_default().encode(buf);
+        }
+        if version >= 4 {
+            self.allow_auto_topic_creation.encode(buf);
+        }
+        if version >= 8 {
+            self.include_cluster_authorized_operations.encode(buf);
+            self.include_topic_authorized_operations.encode(buf);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataResponse {
+    /// v3+.
+    pub throttle_time_ms: i32,
+    pub brokers: Vec<MetadataBroker>,
+    /// v2+.
+    pub cluster_id: Option<String>,
+    /// v1+.
+    pub controller_id: i32,
+    pub topics: Vec<MetadataTopic>,
+    /// v8+.
+    pub cluster_authorized_operations: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataBroker {
+    pub node_id: i32,
+    pub host: String,
+    pub port: i32,
+    /// v1+.
+    pub rack: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataTopic {
+    pub error_code: i16,
+    pub name: String,
+    /// v1+.
+    pub is_internal: bool,
+    pub partitions: Vec<MetadataPartition>,
+    /// v8+.
+    pub topic_authorized_operations: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataPartition {
+    pub error_code: i16,
+    pub partition_index: i32,
+    pub leader_id: i32,
+    /// v7+.
+    pub leader_epoch: i32,
+    pub replica_nodes: Vec<i32>,
+    pub isr_nodes: Vec<i32>,
+    /// v5+; replicas on brokers that are down.
+    pub offline_replicas: Vec<i32>,
+}
+
+impl MetadataBroker {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        Ok(Self {
+            node_id: i32::decode(buf)?,
+            host: String::decode(buf)?,
+            port: i32::decode(buf)?,
+            rack: if version >= 1 {
+                Option::<String>::decode(buf)?
+            } else {
+                None
+            },
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.node_id.encode(buf);
+        self.host.encode(buf);
+        self.port.encode(buf);
+        if version >= 1 {
+            self.rack.encode(buf);
+        }
+    }
+}
+
+impl MetadataPartition {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        Ok(Self {
+            error_code: i16::decode(buf)?,
+            partition_index: i32::decode(buf)?,
+            leader_id: i32::decode(buf)?,
+            leader_epoch: if version >= 7 { i32::decode(buf)? } else { -1 },
+            replica_nodes: Vec::<i32>::decode(buf)?,
+            isr_nodes: Vec::<i32>::decode(buf)?,
+            offline_replicas: if version >= 5 {
+                Vec::<i32>::decode(buf)?
+            } else {
+                Vec::new()
+            },
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.error_code.encode(buf);
+        self.partition_index.encode(buf);
+        self.leader_id.encode(buf);
+        if version >= 7 {
+            self.leader_epoch.encode(buf);
+        }
+        self.replica_nodes.encode(buf);
+        self.isr_nodes.encode(buf);
+        if version >= 5 {
+            self.offline_replicas.encode(buf);
+        }
+    }
+}
+
+impl MetadataTopic {
+    fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let error_code = i16::decode(buf)?;
+        let name = String::decode(buf)?;
+        let is_internal = version >= 1 && bool::decode(buf)?;
+        let partition_count = i32::decode(buf)?;
+        let mut partitions = Vec::new();
+        for _ in 0..partition_count.max(0) {
+            partitions.push(MetadataPartition::decode(buf, version)?);
+        }
+        let topic_authorized_operations = if version >= 8 {
+            i32::decode(buf)?
+        } else {
+            AUTHORIZED_OPERATIONS_OMITTED
+        };
+        Ok(Self {
+            error_code,
+            name,
+            is_internal,
+            partitions,
+            topic_authorized_operations,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.error_code.encode(buf);
+        self.name.encode(buf);
+        if version >= 1 {
+            self.is_internal.encode(buf);
+        }
+        (self.partitions.len() as i32).encode(buf);
+        for partition in &self.partitions {
+            partition.encode(buf, version);
+        }
+        if version >= 8 {
+            self.topic_authorized_operations.encode(buf);
+        }
+    }
+}
+
+impl MetadataResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let throttle_time_ms = if version >= 3 { i32::decode(buf)? } else { 0 };
+        let broker_count = i32::decode(buf)?;
+        let mut brokers = Vec::new();
+        for _ in 0..broker_count.max(0) {
+            brokers.push(MetadataBroker::decode(buf, version)?);
+        }
+        let cluster_id = if version >= 2 {
+            Option::<String>::decode(buf)?
+        } else {
+            None
+        };
+        let controller_id = if version >= 1 { i32::decode(buf)? } else { -1 };
+        let topic_count = i32::decode(buf)?;
+        let mut topics = Vec::new();
+        for _ in 0..topic_count.max(0) {
+            topics.push(MetadataTopic::decode(buf, version)?);
+        }
+        let cluster_authorized_operations = if version >= 8 {
+            i32::decode(buf)?
+        } else {
+            AUTHORIZED_OPERATIONS_OMITTED
+        };
+        Ok(Self {
+            throttle_time_ms,
+            brokers,
+            cluster_id,
+            controller_id,
+            topics,
+            cluster_authorized_operations,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        if version >= 3 {
+            self.throttle_time_ms.encode(buf);
+        }
+        (self.brokers.len() as i32).encode(buf);
+        for broker in &self.brokers {
+            broker.encode(buf, version);
+        }
+        if version >= 2 {
+            self.cluster_id.encode(buf);
+        }
+        if version >= 1 {
+            self.controller_id.encode(buf);
+        }
+        (self.topics.len() as i32).encode(buf);
+        for topic in &self.topics {
+            topic.encode(buf, version);
+        }
+        if version >= 8 {
+            self.cluster_authorized_operations.encode(buf);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const OFFSET_COMMIT_API_KEY: i16 = 8;
+pub const OFFSET_COMMIT_MIN_VERSION: i16 = 2;
+/// v8 switches to the flexible encoding, which is not supported yet.
+pub const OFFSET_COMMIT_MAX_VERSION: i16 = 7;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffsetCommitRequest {
+    pub group_id: String,
+    /// -1 with an empty member id commits for a group that has no members.
+    pub generation_id: i32,
+    pub member_id: String,
+    /// Only sent by v2-4 clients; -1 keeps the broker's retention.
+    pub retention_time_ms: i64,
+    pub group_instance_id: Option<String>,
+    pub topics: Vec<OffsetCommitTopic>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffsetCommitTopic {
+    pub name: String,
+    pub partitions: Vec<OffsetCommitPartition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffsetCommitPartition {
+    pub partition_index: i32,
+    pub committed_offset: i64,
+    pub committed_leader_epoch: i32,
+    pub committed_metadata: Option<String>,
+}
+
+impl OffsetCommitRequest {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let group_id = String::decode(buf)?;
+        let generation_id = i32::decode(buf)?;
+        let member_id = String::decode(buf)?;
+        let retention_time_ms = if version <= 4 { i64::decode(buf)? } else { -1 };
+        let group_instance_id = if version >= 7 {
+            Option::<String>::decode(buf)?
+        } else {
+            None
+        };
+
+        let topic_count = i32::decode(buf)?;
+        let mut topics = Vec::new();
+        for _ in 0..topic_count.max(0) {
+            let name = String::decode(buf)?;
+            let partition_count = i32::decode(buf)?;
+            let mut partitions = Vec::new();
+            for _ in 0..partition_count.max(0) {
+                let partition_index = i32::decode(buf)?;
+                let committed_offset = i64::decode(buf)?;
+                let committed_leader_epoch = if version >= 6 { i32::decode(buf)? } else { -1 };
+                partitions.push(OffsetCommitPartition {
+                    partition_index,
+                    committed_offset,
+                    committed_leader_epoch,
+                    committed_metadata: Option::<String>::decode(buf)?,
+                });
+            }
+            topics.push(OffsetCommitTopic { name, partitions });
+        }
+
+        Ok(Self {
+            group_id,
+            generation_id,
+            member_id,
+            retention_time_ms,
+            group_instance_id,
+            topics,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.group_id.encode(buf);
+        self.generation_id.encode(buf);
+        self.member_id.encode(buf);
+        if version <= 4 {
+            self.retention_time_ms.encode(buf);
+        }
+        if version >= 7 {
+            self.group_instance_id.encode(buf);
+        }
+
+        (self.topics.len() as i32).encode(buf);
+        for topic in &self.topics {
+            topic.name.encode(buf);
+            (topic.partitions.len() as i32).encode(buf);
+            for partition in &topic.partitions {
+                partition.partition_index.encode(buf);
+                partition.committed_offset.encode(buf);
+                if version >= 6 {
+                    partition.committed_leader_epoch.encode(buf);
+                }
+                partition.committed_metadata.encode(buf);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffsetCommitResponse {
+    pub throttle_time_ms: i32,
+    pub topics: Vec<OffsetCommitTopicResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffsetCommitTopicResponse {
+    pub name: String,
+    pub partitions: Vec<OffsetCommitPartitionResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffsetCommitPartitionResponse {
+    pub partition_index: i32,
+    pub error_code: i16,
+}
+
+impl Type for OffsetCommitPartitionResponse {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            partition_index: i32::decode(buf)?,
+            error_code: i16::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.partition_index.encode(buf);
+        self.error_code.encode(buf);
+    }
+}
+
+impl Type for OffsetCommitTopicResponse {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            name: String::decode(buf)?,
+            partitions: Vec::<OffsetCommitPartitionResponse>::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.name.encode(buf);
+        self.partitions.encode(buf);
+    }
+}
+
+impl OffsetCommitResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        Ok(Self {
+            throttle_time_ms: if version >= 3 { i32::decode(buf)? } else { 0 },
+            topics: Vec::<OffsetCommitTopicResponse>::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        if version >= 3 {
+            self.throttle_time_ms.encode(buf);
+        }
+        self.topics.encode(buf);
+    }
+}
@@ -0,0 +1,131 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const OFFSET_FETCH_API_KEY: i16 = 9;
+pub const OFFSET_FETCH_MIN_VERSION: i16 = 1;
+/// v6 switches to the flexible encoding, which is not supported yet.
+pub const OFFSET_FETCH_MAX_VERSION: i16 = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffsetFetchRequest {
+    pub group_id: String,
+    /// `None` (v2+) asks for every partition the group has committed.
+    pub topics: Option<Vec<OffsetFetchTopic>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffsetFetchTopic {
+    pub name: String,
+    pub partition_indexes: Vec<i32>,
+}
+
+impl Type for OffsetFetchTopic {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            name: String::decode(buf)?,
+            partition_indexes: Vec::<i32>::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.name.encode(buf);
+        self.partition_indexes.encode(buf);
+    }
+}
+
+impl OffsetFetchRequest {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            group_id: String::decode(buf)?,
+            topics: Option::<Vec<OffsetFetchTopic>>::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.group_id.encode(buf);
+        self.topics.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffsetFetchResponse {
+    pub throttle_time_ms: i32,
+    pub topics: Vec<OffsetFetchTopicResponse>,
+    /// Group-level errors, from v2; older versions repeat them on every partition.
+    pub error_code: i16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffsetFetchTopicResponse {
+    pub name: String,
+    pub partitions: Vec<OffsetFetchPartitionResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffsetFetchPartitionResponse {
+    pub partition_index: i32,
+    /// -1 when the group has not committed an offset for the partition.
+    pub committed_offset: i64,
+    pub committed_leader_epoch: i32,
+    pub metadata: Option<String>,
+    pub error_code: i16,
+}
+
+impl OffsetFetchResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let throttle_time_ms = if version >= 3 { i32::decode(buf)? } else { 0 };
+
+        let topic_count = i32::decode(buf)?;
+        let mut topics = Vec::new();
+        for _ in 0..topic_count.max(0) {
+            let name = String::decode(buf)?;
+            let partition_count = i32::decode(buf)?;
+            let mut partitions = Vec::new();
+            for _ in 0..partition_count.max(0) {
+                let partition_index = i32::decode(buf)?;
+                let committed_offset = i64::decode(buf)?;
+                let committed_leader_epoch = if version >= 5 { i32::decode(buf)? } else { -1 };
+                partitions.push(OffsetFetchPartitionResponse {
+                    partition_index,
+                    committed_offset,
+                    committed_leader_epoch,
+                    metadata: Option::<String>::decode(buf)?,
+                    error_code: i16::decode(buf)?,
+                });
+            }
+            topics.push(OffsetFetchTopicResponse { name, partitions });
+        }
+        let error_code = if version >= 2 { i16::decode(buf)? } else { 0 };
+
+        Ok(Self {
+            throttle_time_ms,
+            topics,
+            error_code,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        if version >= 3 {
+            self.throttle_time_ms.encode(buf);
+        }
+
+        (self.topics.len() as i32).encode(buf);
+        for topic in &self.topics {
+            topic.name.encode(buf);
+            (topic.partitions.len() as i32).encode(buf);
+            for partition in &topic.partitions {
+                partition.partition_index.encode(buf);
+                partition.committed_offset.encode(buf);
+                if version >= 5 {
+                    partition.committed_leader_epoch.encode(buf);
+                }
+                partition.metadata.encode(buf);
+                partition.error_code.encode(buf);
+            }
+        }
+        if version >= 2 {
+            self.error_code.encode(buf);
+        }
+    }
+}
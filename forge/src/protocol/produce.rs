@@ -0,0 +1,200 @@
+use bytes::{Buf, BufMut};
+
+use crate::core::domain::record_batch::{RecordBatch, Records};
+use crate::protocol::fetch::{decode_raw_records, encode_records};
+use crate::protocol::types::Type;
+
+pub const PRODUCE_API_KEY: i16 = 0;
+pub const PRODUCE_MIN_VERSION: i16 = 3;
+pub const PRODUCE_MAX_VERSION: i16 = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProduceRequest {
+    pub transactional_id: Option<String>,
+    pub acks: i16,
+    pub timeout_ms: i32,
+    pub topics: Vec<TopicProduceData>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopicProduceData {
+    pub name: String,
+    pub partitions: Vec<PartitionProduceData>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionProduceData {
+    pub index: i32,
+    pub records: Vec<RecordBatch>,
+    /// The records as sent and why they failed to decode, e.g. a CRC mismatch, in which case
+    /// `records` is empty. Only that partition fails, as in Kafka.
+    pub corrupt: Option<(Records, String)>,
+}
+
+impl ProduceRequest {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        let transactional_id = Option::<String>::decode(buf)?;
+        let acks = i16::decode(buf)?;
+        let timeout_ms = i32::decode(buf)?;
+
+        let topic_count = i32::decode(buf)?;
+        let mut topics = Vec::new();
+        for _ in 0..topic_count.max(0) {
+            let name = String::decode(buf)?;
+            let partition_count = i32::decode(buf)?;
+            let mut partitions = Vec::new();
+            for _ in 0..partition_count.max(0) {
+                let index = i32::decode(buf)?;
+                let raw = decode_raw_records(buf)?;
+                let (records, corrupt) = match raw.batches() {
+                    Ok(records) => (records, None),
+                    Err(e) => (Vec::new(), Some((raw, e))),
+                };
+                partitions.push(PartitionProduceData {
+                    index,
+                    records,
+                    corrupt,
+                });
+            }
+            topics.push(TopicProduceData { name, partitions });
+        }
+
+        Ok(Self {
+            transactional_id,
+            acks,
+            timeout_ms,
+            topics,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.transactional_id.encode(buf);
+        self.acks.encode(buf);
+        self.timeout_ms.encode(buf);
+
+        (self.topics.len() as i32).encode(buf);
+        for topic in &self.topics {
+            topic.name.encode(buf);
+            (topic.partitions.len() as i32).encode(buf);
+            for partition in &topic.partitions {
+                partition.index.encode(buf);
+                match &partition.corrupt {
+                    Some((raw, _)) => {
+                        (raw.size_in_bytes() as i32).encode(buf);
+                        for batch in raw.raw() {
+                            buf.put_slice(batch);
+                        }
+                    }
+                    None => encode_records(buf, &partition.records),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProduceResponse {
+    pub responses: Vec<TopicProduceResponse>,
+    pub throttle_time_ms: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopicProduceResponse {
+    pub name: String,
+    pub partitions: Vec<PartitionProduceResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionProduceResponse {
+    pub index: i32,
+    pub error_code: i16,
+    pub base_offset: i64,
+    pub log_append_time_ms: i64,
+    pub log_start_offset: i64,
+    pub record_errors: Vec<BatchIndexAndErrorMessage>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchIndexAndErrorMessage {
+    pub batch_index: i32,
+    pub batch_index_error_message: Option<String>,
+}
+
+impl Type for BatchIndexAndErrorMessage {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            batch_index: i32::decode(buf)?,
+            batch_index_error_message: Option::<String>::decode(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.batch_index.encode(buf);
+        self.batch_index_error_message.encode(buf);
+    }
+}
+
+impl ProduceResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let topic_count = i32::decode(buf)?;
+        let mut responses = Vec::new();
+        for _ in 0..topic_count.max(0) {
+            let name = String::decode(buf)?;
+            let partition_count = i32::decode(buf)?;
+            let mut partitions = Vec::new();
+            for _ in 0..partition_count.max(0) {
+                let index = i32::decode(buf)?;
+                let error_code = i16::decode(buf)?;
+                let base_offset = i64::decode(buf)?;
+                let log_append_time_ms = i64::decode(buf)?;
+                let log_start_offset = if version >= 5 { i64::decode(buf)? } else { -1 };
+                let (record_errors, error_message) = if version >= 8 {
+                    (
+                        Vec::<BatchIndexAndErrorMessage>::decode(buf)?,
+                        Option::<String>::decode(buf)?,
+                    )
+                } else {
+                    (vec![], None)
+                };
+                partitions.push(PartitionProduceResponse {
+                    index,
+                    error_code,
+                    base_offset,
+                    log_append_time_ms,
+                    log_start_offset,
+                    record_errors,
+                    error_message,
+                });
+            }
+            responses.push(TopicProduceResponse { name, partitions });
+        }
+
+        Ok(Self {
+            responses,
+            throttle_time_ms: i32::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        (self.responses.len() as i32).encode(buf);
+        for topic in &self.responses {
+            topic.name.encode(buf);
+            (topic.partitions.len() as i32).encode(buf);
+            for partition in &topic.partitions {
+                partition.index.encode(buf);
+                partition.error_code.encode(buf);
+                partition.base_offset.encode(buf);
+                partition.log_append_time_ms.encode(buf);
+                if version >= 5 {
+                    partition.log_start_offset.encode(buf);
+                }
+                if version >= 8 {
+                    partition.record_errors.encode(buf);
+                    partition.error_message.encode(buf);
+                }
+            }
+        }
+        self.throttle_time_ms.encode(buf);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::protocol::types::Type;
-use bytes::Buf;
+use bytes::{Buf, BufMut};
 
 #[derive(Debug)]
 pub struct RequestHeader {
@@ -39,4 +39,11 @@ impl RequestHeader {
             client_id,
         })
     }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.api_key.encode(buf);
+        self.api_version.encode(buf);
+        self.correlation_id.encode(buf);
+        self.client_id.encode(buf);
+    }
 }
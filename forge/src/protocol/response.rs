@@ -1,5 +1,5 @@
 use crate::protocol::types::Type;
-use bytes::BufMut;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 
 #[derive(Debug)]
 pub struct ResponseHeader {
@@ -10,4 +10,48 @@ impl ResponseHeader {
     pub fn encode<B: BufMut>(self, buf: &mut B) {
         self.correlation_id.encode(buf);
     }
+
+    pub fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            correlation_id: i32::decode(buf)?,
+        })
+    }
+}
+
+/// An encoded response body: `buf`, with each of `chunks` spliced in where `buf` had the
+/// length it is paired with. Record batches read from the log go in as chunks, so they reach
+/// the socket without being copied into the body.
+#[derive(Debug, Default)]
+pub struct ResponseBody {
+    pub buf: BytesMut,
+    pub chunks: Vec<(usize, Bytes)>,
+}
+
+impl ResponseBody {
+    pub fn len(&self) -> usize {
+        self.buf.len()
+            + self
+                .chunks
+                .iter()
+                .map(|(_, chunk)| chunk.len())
+                .sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.chunks.clear();
+    }
+}
+
+impl From<BytesMut> for ResponseBody {
+    fn from(buf: BytesMut) -> Self {
+        Self {
+            buf,
+            chunks: Vec::new(),
+        }
+    }
 }
@@ -0,0 +1,189 @@
+//! Decode-then-encode of whole frames, for the golden wire fixtures in tests/fixtures/wire.
+//! A recorded frame that does not come back byte for byte means a decoder or an encoder has
+//! drifted from the format real clients and brokers use.
+
+use crate::protocol::add_offsets_to_txn::{
+    ADD_OFFSETS_TO_TXN_API_KEY, AddOffsetsToTxnRequest, AddOffsetsToTxnResponse,
+};
+use crate::protocol::add_partitions_to_txn::{
+    ADD_PARTITIONS_TO_TXN_API_KEY, AddPartitionsToTxnRequest, AddPartitionsToTxnResponse,
+};
+use crate::protocol::alter_configs::{
+    ALTER_CONFIGS_API_KEY, AlterConfigsRequest, AlterConfigsResponse,
+};
+use crate::protocol::alter_partition_reassignments::{
+    ALTER_PARTITION_REASSIGNMENTS_API_KEY, AlterPartitionReassignmentsRequest,
+    AlterPartitionReassignmentsResponse,
+};
+use crate::protocol::api_versions::{API_VERSIONS_API_KEY, ApiVersionsResponse};
+use crate::protocol::create_acls::{CREATE_ACLS_API_KEY, CreateAclsRequest, CreateAclsResponse};
+use crate::protocol::delete_acls::{DELETE_ACLS_API_KEY, DeleteAclsRequest, DeleteAclsResponse};
+use crate::protocol::describe_acls::{
+    DESCRIBE_ACLS_API_KEY, DescribeAclsRequest, DescribeAclsResponse,
+};
+use crate::protocol::describe_configs::{
+    DESCRIBE_CONFIGS_API_KEY, DescribeConfigsRequest, DescribeConfigsResponse,
+};
+use crate::protocol::describe_groups::{
+    DESCRIBE_GROUPS_API_KEY, DescribeGroupsRequest, DescribeGroupsResponse,
+};
+use crate::protocol::elect_leaders::{
+    ELECT_LEADERS_API_KEY, ElectLeadersRequest, ElectLeadersResponse,
+};
+use crate::protocol::end_txn::{END_TXN_API_KEY, EndTxnRequest, EndTxnResponse};
+use crate::protocol::fetch::{FETCH_API_KEY, FetchRequest, FetchResponse};
+use crate::protocol::find_coordinator::{
+    FIND_COORDINATOR_API_KEY, FindCoordinatorRequest, FindCoordinatorResponse,
+};
+use crate::protocol::heartbeat::{HEARTBEAT_API_KEY, HeartbeatRequest, HeartbeatResponse};
+use crate::protocol::init_producer_id::{
+    INIT_PRODUCER_ID_API_KEY, InitProducerIdRequest, InitProducerIdResponse,
+};
+use crate::protocol::join_group::{JOIN_GROUP_API_KEY, JoinGroupRequest, JoinGroupResponse};
+use crate::protocol::leave_group::{LEAVE_GROUP_API_KEY, LeaveGroupRequest, LeaveGroupResponse};
+use crate::protocol::list_groups::{LIST_GROUPS_API_KEY, ListGroupsRequest, ListGroupsResponse};
+use crate::protocol::list_offsets::{
+    LIST_OFFSETS_API_KEY, ListOffsetsRequest, ListOffsetsResponse,
+};
+use crate::protocol::list_partition_reassignments::{
+    LIST_PARTITION_REASSIGNMENTS_API_KEY, ListPartitionReassignmentsRequest,
+    ListPartitionReassignmentsResponse,
+};
+use crate::protocol::metadata::{METADATA_API_KEY, MetadataRequest, MetadataResponse};
+use crate::protocol::offset_commit::{
+    OFFSET_COMMIT_API_KEY, OffsetCommitRequest, OffsetCommitResponse,
+};
+use crate::protocol::offset_fetch::{
+    OFFSET_FETCH_API_KEY, OffsetFetchRequest, OffsetFetchResponse,
+};
+use crate::protocol::produce::{PRODUCE_API_KEY, ProduceRequest, ProduceResponse};
+use crate::protocol::request::RequestHeader;
+use crate::protocol::response::ResponseHeader;
+use crate::protocol::sasl_authenticate::{
+    SASL_AUTHENTICATE_API_KEY, SaslAuthenticateRequest, SaslAuthenticateResponse,
+};
+use crate::protocol::sasl_handshake::{
+    SASL_HANDSHAKE_API_KEY, SaslHandshakeRequest, SaslHandshakeResponse,
+};
+use crate::protocol::sync_group::{SYNC_GROUP_API_KEY, SyncGroupRequest, SyncGroupResponse};
+use crate::protocol::txn_offset_commit::{
+    TXN_OFFSET_COMMIT_API_KEY, TxnOffsetCommitRequest, TxnOffsetCommitResponse,
+};
+use crate::protocol::types::{TaggedFields, Type};
+
+/// Decodes `$ty` from `$buf` and encodes it back into `$out`.
+macro_rules! round_trip_body {
+    ($api_key:expr, $version:expr, $buf:expr, $out:expr, { $($key:ident => $ty:ident),* $(,)? }) => {
+        match $api_key {
+            $($key => $ty::decode($buf, $version)?.encode($out, $version),)*
+            _ => return Err(format!("Unknown api key {}", $api_key)),
+        }
+    };
+}
+
+/// The flexible-only APIs, whose headers carry tagged fields.
+fn has_flexible_header(api_key: i16) -> bool {
+    api_key == ALTER_PARTITION_REASSIGNMENTS_API_KEY
+        || api_key == LIST_PARTITION_REASSIGNMENTS_API_KEY
+}
+
+fn expect_consumed(data: &[u8]) -> Result<(), String> {
+    if data.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} bytes left over after decoding", data.len()))
+    }
+}
+
+/// Decodes a request frame, without its size prefix, and encodes it again.
+pub fn request(mut data: &[u8]) -> Result<Vec<u8>, String> {
+    let buf = &mut data;
+    let header = RequestHeader::decode(buf)?;
+    let version = header.api_version;
+    let mut out = Vec::new();
+    header.encode(&mut out);
+    if has_flexible_header(header.api_key) {
+        TaggedFields::decode(buf)?;
+        TaggedFields.encode(&mut out);
+    }
+    // The supported versions of ApiVersions have an empty body
+    if header.api_key != API_VERSIONS_API_KEY {
+        round_trip_body!(header.api_key, version, buf, &mut out, {
+            ADD_OFFSETS_TO_TXN_API_KEY => AddOffsetsToTxnRequest,
+            ADD_PARTITIONS_TO_TXN_API_KEY => AddPartitionsToTxnRequest,
+            ALTER_CONFIGS_API_KEY => AlterConfigsRequest,
+            ALTER_PARTITION_REASSIGNMENTS_API_KEY => AlterPartitionReassignmentsRequest,
+            CREATE_ACLS_API_KEY => CreateAclsRequest,
+            DELETE_ACLS_API_KEY => DeleteAclsRequest,
+            DESCRIBE_ACLS_API_KEY => DescribeAclsRequest,
+            DESCRIBE_CONFIGS_API_KEY => DescribeConfigsRequest,
+            DESCRIBE_GROUPS_API_KEY => DescribeGroupsRequest,
+            ELECT_LEADERS_API_KEY => ElectLeadersRequest,
+            END_TXN_API_KEY => EndTxnRequest,
+            FETCH_API_KEY => FetchRequest,
+            FIND_COORDINATOR_API_KEY => FindCoordinatorRequest,
+            HEARTBEAT_API_KEY => HeartbeatRequest,
+            INIT_PRODUCER_ID_API_KEY => InitProducerIdRequest,
+            JOIN_GROUP_API_KEY => JoinGroupRequest,
+            LEAVE_GROUP_API_KEY => LeaveGroupRequest,
+            LIST_GROUPS_API_KEY => ListGroupsRequest,
+            LIST_OFFSETS_API_KEY => ListOffsetsRequest,
+            LIST_PARTITION_REASSIGNMENTS_API_KEY => ListPartitionReassignmentsRequest,
+            METADATA_API_KEY => MetadataRequest,
+            OFFSET_COMMIT_API_KEY => OffsetCommitRequest,
+            OFFSET_FETCH_API_KEY => OffsetFetchRequest,
+            PRODUCE_API_KEY => ProduceRequest,
+            SASL_AUTHENTICATE_API_KEY => SaslAuthenticateRequest,
+            SASL_HANDSHAKE_API_KEY => SaslHandshakeRequest,
+            SYNC_GROUP_API_KEY => SyncGroupRequest,
+            TXN_OFFSET_COMMIT_API_KEY => TxnOffsetCommitRequest,
+        });
+    }
+    expect_consumed(data)?;
+    Ok(out)
+}
+
+/// Decodes a response frame, without its size prefix, to a request of `api_key` at
+/// `version`, and encodes it again.
+pub fn response(api_key: i16, version: i16, mut data: &[u8]) -> Result<Vec<u8>, String> {
+    let buf = &mut data;
+    let mut out = Vec::new();
+    ResponseHeader::decode(buf)?.encode(&mut out);
+    if has_flexible_header(api_key) {
+        TaggedFields::decode(buf)?;
+        TaggedFields.encode(&mut out);
+    }
+    round_trip_body!(api_key, version, buf, &mut out, {
+        API_VERSIONS_API_KEY => ApiVersionsResponse,
+        ADD_OFFSETS_TO_TXN_API_KEY => AddOffsetsToTxnResponse,
+        ADD_PARTITIONS_TO_TXN_API_KEY => AddPartitionsToTxnResponse,
+        ALTER_CONFIGS_API_KEY => AlterConfigsResponse,
+        ALTER_PARTITION_REASSIGNMENTS_API_KEY => AlterPartitionReassignmentsResponse,
+        CREATE_ACLS_API_KEY => CreateAclsResponse,
+        DELETE_ACLS_API_KEY => DeleteAclsResponse,
+        DESCRIBE_ACLS_API_KEY => DescribeAclsResponse,
+        DESCRIBE_CONFIGS_API_KEY => DescribeConfigsResponse,
+        DESCRIBE_GROUPS_API_KEY => DescribeGroupsResponse,
+        ELECT_LEADERS_API_KEY => ElectLeadersResponse,
+        END_TXN_API_KEY => EndTxnResponse,
+        FETCH_API_KEY => FetchResponse,
+        FIND_COORDINATOR_API_KEY => FindCoordinatorResponse,
+        HEARTBEAT_API_KEY => HeartbeatResponse,
+        INIT_PRODUCER_ID_API_KEY => InitProducerIdResponse,
+        JOIN_GROUP_API_KEY => JoinGroupResponse,
+        LEAVE_GROUP_API_KEY => LeaveGroupResponse,
+        LIST_GROUPS_API_KEY => ListGroupsResponse,
+        LIST_OFFSETS_API_KEY => ListOffsetsResponse,
+        LIST_PARTITION_REASSIGNMENTS_API_KEY => ListPartitionReassignmentsResponse,
+        METADATA_API_KEY => MetadataResponse,
+        OFFSET_COMMIT_API_KEY => OffsetCommitResponse,
+        OFFSET_FETCH_API_KEY => OffsetFetchResponse,
+        PRODUCE_API_KEY => ProduceResponse,
+        SASL_AUTHENTICATE_API_KEY => SaslAuthenticateResponse,
+        SASL_HANDSHAKE_API_KEY => SaslHandshakeResponse,
+        SYNC_GROUP_API_KEY => SyncGroupResponse,
+        TXN_OFFSET_COMMIT_API_KEY => TxnOffsetCommitResponse,
+    });
+    expect_consumed(data)?;
+    Ok(out)
+}
@@ -0,0 +1,74 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const SASL_AUTHENTICATE_API_KEY: i16 = 36;
+pub const SASL_AUTHENTICATE_MIN_VERSION: i16 = 0;
+/// v2 switches to the flexible encoding, which is not supported yet.
+pub const SASL_AUTHENTICATE_MAX_VERSION: i16 = 1;
+
+fn decode_bytes<B: Buf>(buf: &mut B) -> Result<Vec<u8>, String> {
+    let len = i32::decode(buf)?;
+    if len < 0 {
+        return Ok(Vec::new());
+    }
+    let len = len as usize;
+    if buf.remaining() < len {
+        return Err("Not enough data for SASL auth bytes".to_string());
+    }
+    let mut bytes = vec![0u8; len];
+    buf.copy_to_slice(&mut bytes);
+    Ok(bytes)
+}
+
+fn encode_bytes<B: BufMut>(bytes: &[u8], buf: &mut B) {
+    buf.put_i32(bytes.len() as i32);
+    buf.put_slice(bytes);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaslAuthenticateRequest {
+    /// The next message of the mechanism's exchange.
+    pub auth_bytes: Vec<u8>,
+}
+
+impl SaslAuthenticateRequest {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            auth_bytes: decode_bytes(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        encode_bytes(&self.auth_bytes, buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaslAuthenticateResponse {
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    pub auth_bytes: Vec<u8>,
+    /// How long the session stays valid; 0 when it does not expire.
+    pub session_lifetime_ms: i64,
+}
+
+impl SaslAuthenticateResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        Ok(Self {
+            error_code: i16::decode(buf)?,
+            error_message: Option::<String>::decode(buf)?,
+            auth_bytes: decode_bytes(buf)?,
+            session_lifetime_ms: if version >= 1 { i64::decode(buf)? } else { 0 },
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.error_code.encode(buf);
+        self.error_message.encode(buf);
+        encode_bytes(&self.auth_bytes, buf);
+        if version >= 1 {
+            self.session_lifetime_ms.encode(buf);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const SASL_HANDSHAKE_API_KEY: i16 = 17;
+/// v0 is followed by raw SASL tokens outside Kafka framing, which is not supported.
+pub const SASL_HANDSHAKE_MIN_VERSION: i16 = 1;
+pub const SASL_HANDSHAKE_MAX_VERSION: i16 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaslHandshakeRequest {
+    pub mechanism: String,
+}
+
+impl SaslHandshakeRequest {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            mechanism: String::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.mechanism.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaslHandshakeResponse {
+    pub error_code: i16,
+    /// The mechanisms enabled on the listener.
+    pub mechanisms: Vec<String>,
+}
+
+impl SaslHandshakeResponse {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            error_code: i16::decode(buf)?,
+            mechanisms: Vec::<String>::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.error_code.encode(buf);
+        self.mechanisms.encode(buf);
+    }
+}
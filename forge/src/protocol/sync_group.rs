@@ -0,0 +1,100 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::types::Type;
+
+pub const SYNC_GROUP_API_KEY: i16 = 14;
+pub const SYNC_GROUP_MIN_VERSION: i16 = 0;
+/// v3 adds static membership, which is not supported yet.
+pub const SYNC_GROUP_MAX_VERSION: i16 = 2;
+
+fn decode_bytes<B: Buf>(buf: &mut B) -> Result<Vec<u8>, String> {
+    let len = i32::decode(buf)?;
+    if len < 0 {
+        return Ok(Vec::new());
+    }
+    let len = len as usize;
+    if buf.remaining() < len {
+        return Err("Not enough data for member assignment".to_string());
+    }
+    let mut bytes = vec![0u8; len];
+    buf.copy_to_slice(&mut bytes);
+    Ok(bytes)
+}
+
+fn encode_bytes<B: BufMut>(bytes: &[u8], buf: &mut B) {
+    buf.put_i32(bytes.len() as i32);
+    buf.put_slice(bytes);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncGroupRequest {
+    pub group_id: String,
+    pub generation_id: i32,
+    pub member_id: String,
+    /// Sent by the leader only; followers send none and wait for it.
+    pub assignments: Vec<SyncGroupRequestAssignment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncGroupRequestAssignment {
+    pub member_id: String,
+    pub assignment: Vec<u8>,
+}
+
+impl Type for SyncGroupRequestAssignment {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        Ok(Self {
+            member_id: String::decode(buf)?,
+            assignment: decode_bytes(buf)?,
+        })
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        self.member_id.encode(buf);
+        encode_bytes(&self.assignment, buf);
+    }
+}
+
+impl SyncGroupRequest {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            group_id: String::decode(buf)?,
+            generation_id: i32::decode(buf)?,
+            member_id: String::decode(buf)?,
+            assignments: Vec::<SyncGroupRequestAssignment>::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.group_id.encode(buf);
+        self.generation_id.encode(buf);
+        self.member_id.encode(buf);
+        self.assignments.encode(buf);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncGroupResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub assignment: Vec<u8>,
+}
+
+impl SyncGroupResponse {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let throttle_time_ms = if version >= 1 { i32::decode(buf)? } else { 0 };
+        Ok(Self {
+            throttle_time_ms,
+            error_code: i16::decode(buf)?,
+            assignment: decode_bytes(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        if version >= 1 {
+            self.throttle_time_ms.encode(buf);
+        }
+        self.error_code.encode(buf);
+        encode_bytes(&self.assignment, buf);
+    }
+}
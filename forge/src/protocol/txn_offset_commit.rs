@@ -0,0 +1,112 @@
+use bytes::{Buf, BufMut};
+
+use crate::protocol::offset_commit::OffsetCommitTopicResponse;
+use crate::protocol::types::Type;
+
+pub const TXN_OFFSET_COMMIT_API_KEY: i16 = 28;
+pub const TXN_OFFSET_COMMIT_MIN_VERSION: i16 = 0;
+/// v3 is the first flexible version, which is not supported yet.
+pub const TXN_OFFSET_COMMIT_MAX_VERSION: i16 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxnOffsetCommitRequest {
+    pub transactional_id: String,
+    pub group_id: String,
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+    pub topics: Vec<TxnOffsetCommitTopic>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxnOffsetCommitTopic {
+    pub name: String,
+    pub partitions: Vec<TxnOffsetCommitPartition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxnOffsetCommitPartition {
+    pub partition_index: i32,
+    pub committed_offset: i64,
+    /// Only sent by v2+ clients.
+    pub committed_leader_epoch: i32,
+    pub committed_metadata: Option<String>,
+}
+
+impl TxnOffsetCommitRequest {
+    pub fn decode<B: Buf>(buf: &mut B, version: i16) -> Result<Self, String> {
+        let transactional_id = String::decode(buf)?;
+        let group_id = String::decode(buf)?;
+        let producer_id = i64::decode(buf)?;
+        let producer_epoch = i16::decode(buf)?;
+
+        let topic_count = i32::decode(buf)?;
+        let mut topics = Vec::new();
+        for _ in 0..topic_count.max(0) {
+            let name = String::decode(buf)?;
+            let partition_count = i32::decode(buf)?;
+            let mut partitions = Vec::new();
+            for _ in 0..partition_count.max(0) {
+                let partition_index = i32::decode(buf)?;
+                let committed_offset = i64::decode(buf)?;
+                let committed_leader_epoch = if version >= 2 { i32::decode(buf)? } else { -1 };
+                partitions.push(TxnOffsetCommitPartition {
+                    partition_index,
+                    committed_offset,
+                    committed_leader_epoch,
+                    committed_metadata: Option::<String>::decode(buf)?,
+                });
+            }
+            topics.push(TxnOffsetCommitTopic { name, partitions });
+        }
+
+        Ok(Self {
+            transactional_id,
+            group_id,
+            producer_id,
+            producer_epoch,
+            topics,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, version: i16) {
+        self.transactional_id.encode(buf);
+        self.group_id.encode(buf);
+        self.producer_id.encode(buf);
+        self.producer_epoch.encode(buf);
+
+        (self.topics.len() as i32).encode(buf);
+        for topic in &self.topics {
+            topic.name.encode(buf);
+            (topic.partitions.len() as i32).encode(buf);
+            for partition in &topic.partitions {
+                partition.partition_index.encode(buf);
+                partition.committed_offset.encode(buf);
+                if version >= 2 {
+                    partition.committed_leader_epoch.encode(buf);
+                }
+                partition.committed_metadata.encode(buf);
+            }
+        }
+    }
+}
+
+/// Answers per partition like OffsetCommit does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxnOffsetCommitResponse {
+    pub throttle_time_ms: i32,
+    pub topics: Vec<OffsetCommitTopicResponse>,
+}
+
+impl TxnOffsetCommitResponse {
+    pub fn decode<B: Buf>(buf: &mut B, _version: i16) -> Result<Self, String> {
+        Ok(Self {
+            throttle_time_ms: i32::decode(buf)?,
+            topics: Vec::<OffsetCommitTopicResponse>::decode(buf)?,
+        })
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B, _version: i16) {
+        self.throttle_time_ms.encode(buf);
+        self.topics.encode(buf);
+    }
+}
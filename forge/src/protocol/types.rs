@@ -1,4 +1,8 @@
-use bytes::{Buf, BufMut};
+use bytes::{Buf, BufMut, Bytes};
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
 
 pub trait Type {
     fn decode<B: Buf>(buf: &mut B) -> Result<Self, String>
@@ -7,6 +11,12 @@ pub trait Type {
     fn encode<B: BufMut>(&self, buf: &mut B);
 }
 
+/// Caps the capacity reserved for an array of `len` elements at what the remaining bytes could
+/// hold, so a forged length cannot make a short message allocate gigabytes.
+pub(crate) fn capacity_for<T, B: Buf>(len: usize, buf: &B) -> usize {
+    len.min(buf.remaining() / size_of::<T>().max(1))
+}
+
 impl Type for bool {
     fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
         if buf.remaining() < 1 {
@@ -139,6 +149,15 @@ macro_rules! impl_signed_varint_trait {
                 }
             }
         }
+
+        impl $name {
+            /// The number of bytes `encode` writes.
+            pub fn size(&self) -> usize {
+                let value =
+                    ((self.0 as $unsigned) << 1) ^ ((self.0 >> (<$inner>::BITS - 1)) as $unsigned);
+                (<$unsigned>::BITS - (value | 1).leading_zeros()).div_ceil(7) as usize
+            }
+        }
     };
 }
 
@@ -202,6 +221,152 @@ impl Type for CompactString {
     }
 }
 
+/// A UTF-8 string held in `Bytes`. Decoded from a `Bytes` buffer it shares that buffer
+/// instead of allocating, which adds up for record header keys.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteStr(Bytes);
+
+impl ByteStr {
+    pub const fn from_static(value: &'static str) -> Self {
+        Self(Bytes::from_static(value.as_bytes()))
+    }
+
+    /// `len` bytes off `buf`, which must be UTF-8.
+    fn take<B: Buf>(buf: &mut B, len: usize, what: &str) -> Result<Self, String> {
+        if buf.remaining() < len {
+            return Err(format!("Not enough data for {}", what));
+        }
+        let bytes = buf.copy_to_bytes(len);
+        std::str::from_utf8(&bytes).map_err(|_| format!("Invalid UTF-8 for {}", what))?;
+        Ok(Self(bytes))
+    }
+
+    /// A varint length and then the string, as record header keys are written.
+    pub fn decode_varint<B: Buf>(buf: &mut B, what: &str) -> Result<Self, String> {
+        let len = Varint::decode(buf)?.0;
+        if len < 0 {
+            return Err(format!("Invalid length {} for {}", len, what));
+        }
+        Self::take(buf, len as usize, what)
+    }
+
+    pub fn encode_varint<B: BufMut>(&self, buf: &mut B) {
+        Varint(self.0.len() as i32).encode(buf);
+        buf.put_slice(&self.0);
+    }
+
+    pub fn as_str(&self) -> &str {
+        // SAFETY: every constructor takes a str or checks the bytes are UTF-8
+        unsafe { std::str::from_utf8_unchecked(&self.0) }
+    }
+}
+
+impl Deref for ByteStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for ByteStr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for ByteStr {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+// Hashes as the str it borrows as
+impl Hash for ByteStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl fmt::Debug for ByteStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for ByteStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<String> for ByteStr {
+    fn from(value: String) -> Self {
+        Self(Bytes::from(value.into_bytes()))
+    }
+}
+
+impl From<&str> for ByteStr {
+    fn from(value: &str) -> Self {
+        Self(Bytes::copy_from_slice(value.as_bytes()))
+    }
+}
+
+impl From<ByteStr> for String {
+    fn from(value: ByteStr) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl PartialEq<str> for ByteStr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for ByteStr {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+/// STRING, like `String`, without an allocation of its own when decoded from `Bytes`.
+impl Type for ByteStr {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        if buf.remaining() < 2 {
+            return Err("Not enough data for String length".to_string());
+        }
+        let len = buf.get_i16();
+        if len < 0 {
+            return Ok(Self::default());
+        }
+        Self::take(buf, len as usize, "String")
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        buf.put_i16(self.0.len() as i16);
+        buf.put_slice(&self.0);
+    }
+}
+
+/// COMPACT_STRING, like `CompactString`, held in `Bytes`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CompactByteStr(pub ByteStr);
+impl Type for CompactByteStr {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let n = UnsignedVarint::decode(buf)?.0;
+        if n == 0 {
+            return Ok(Self::default());
+        }
+        ByteStr::take(buf, (n - 1) as usize, "CompactString").map(CompactByteStr)
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        UnsignedVarint(self.0.len() as u32 + 1).encode(buf);
+        buf.put_slice(self.0.as_bytes());
+    }
+}
+
 impl Type for uuid::Uuid {
     fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
         if buf.remaining() < 16 {
@@ -233,7 +398,7 @@ impl<T: Type> Type for CompactArray<T> {
             return Err("Not enough data for CompactArray".to_string());
         }
 
-        let mut vec = Vec::with_capacity(len);
+        let mut vec = Vec::with_capacity(capacity_for::<T, B>(len, buf));
         for _ in 0..len {
             vec.push(T::decode(buf)?);
         }
@@ -250,6 +415,81 @@ impl<T: Type> Type for CompactArray<T> {
     }
 }
 
+/// A compact string that may be null, encoded as length + 1 with 0 for null.
+impl Type for Option<CompactString> {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let n = UnsignedVarint::decode(buf)?.0;
+        if n == 0 {
+            return Ok(None);
+        }
+
+        let len = (n - 1) as usize;
+        if buf.remaining() < len {
+            return Err("Not enough data for nullable CompactString".to_string());
+        }
+        let mut bytes = vec![0u8; len];
+        buf.copy_to_slice(&mut bytes);
+        String::from_utf8(bytes)
+            .map_err(|e| e.to_string())
+            .map(|value| Some(CompactString(value)))
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        match self {
+            Some(value) => value.encode(buf),
+            None => UnsignedVarint(0).encode(buf),
+        }
+    }
+}
+
+/// A compact array that may be null, encoded as length + 1 with 0 for null.
+impl<T: Type> Type for Option<CompactArray<T>> {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let n = UnsignedVarint::decode(buf)?.0;
+        if n == 0 {
+            return Ok(None);
+        }
+
+        let len = (n - 1) as usize;
+        let mut vec = Vec::with_capacity(capacity_for::<T, B>(len, buf));
+        for _ in 0..len {
+            vec.push(T::decode(buf)?);
+        }
+        Ok(Some(CompactArray(vec)))
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        match self {
+            Some(items) => items.encode(buf),
+            None => UnsignedVarint(0).encode(buf),
+        }
+    }
+}
+
+/// The tagged fields ending every structure of a flexible message. None are understood yet:
+/// decoding skips them and encoding writes an empty set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaggedFields;
+
+impl Type for TaggedFields {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let count = UnsignedVarint::decode(buf)?.0;
+        for _ in 0..count {
+            UnsignedVarint::decode(buf)?;
+            let size = UnsignedVarint::decode(buf)?.0 as usize;
+            if buf.remaining() < size {
+                return Err("Not enough data for tagged field".to_string());
+            }
+            buf.advance(size);
+        }
+        Ok(TaggedFields)
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        UnsignedVarint(0).encode(buf);
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CompactBytes(pub Vec<u8>);
 impl Type for CompactBytes {
@@ -277,3 +517,66 @@ impl Type for CompactBytes {
         buf.put_slice(&self.0);
     }
 }
+
+impl Type for Option<String> {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        if buf.remaining() < 2 {
+            return Err("Not enough data for nullable String length".to_string());
+        }
+        if buf.chunk().len() >= 2 && i16::from_be_bytes([buf.chunk()[0], buf.chunk()[1]]) < 0 {
+            buf.advance(2);
+            return Ok(None);
+        }
+        String::decode(buf).map(Some)
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        match self {
+            Some(value) => value.encode(buf),
+            None => buf.put_i16(-1),
+        }
+    }
+}
+
+impl<T: Type> Type for Vec<T> {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        let len = i32::decode(buf)?;
+        if len < 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut vec = Vec::with_capacity(capacity_for::<T, B>(len as usize, buf));
+        for _ in 0..len {
+            vec.push(T::decode(buf)?);
+        }
+        Ok(vec)
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        buf.put_i32(self.len() as i32);
+        for item in self {
+            item.encode(buf);
+        }
+    }
+}
+
+impl<T: Type> Type for Option<Vec<T>> {
+    fn decode<B: Buf>(buf: &mut B) -> Result<Self, String> {
+        if buf.remaining() < 4 {
+            return Err("Not enough data for nullable array length".to_string());
+        }
+        let chunk = buf.chunk();
+        if chunk.len() >= 4 && i32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) < 0 {
+            buf.advance(4);
+            return Ok(None);
+        }
+        Vec::decode(buf).map(Some)
+    }
+
+    fn encode<B: BufMut>(&self, buf: &mut B) {
+        match self {
+            Some(items) => items.encode(buf),
+            None => buf.put_i32(-1),
+        }
+    }
+}
@@ -1,5 +1,14 @@
+pub mod buffer_pool;
 pub mod byte;
 pub mod collections;
 pub mod constants;
 pub mod fs;
+pub mod hash;
 pub mod logging;
+pub mod metrics;
+#[cfg(feature = "otel")]
+pub mod otlp;
+pub mod rolling_file;
+pub mod scheduler;
+pub mod time;
+pub mod timing;
@@ -0,0 +1,60 @@
+//! Reusable `BytesMut` buffers for encoding responses and record batches, so a busy
+//! connection does not allocate and free the same few buffers on every request.
+//!
+//! Each thread keeps its own pool: a buffer taken on one thread and recycled on another just
+//! moves between pools, and no lock is needed. Encoders that only see a `BufMut` reach the
+//! pool through these functions rather than a handle passed down to them.
+
+use bytes::BytesMut;
+use std::cell::RefCell;
+
+/// Buffers kept per thread; more than this are freed when recycled.
+const MAX_POOLED: usize = 64;
+/// Larger buffers are freed rather than kept, so one huge fetch does not pin its memory.
+const MAX_RETAINED_CAPACITY: usize = 1024 * 1024;
+
+thread_local! {
+    static POOL: RefCell<Vec<BytesMut>> = const { RefCell::new(Vec::new()) };
+}
+
+/// An empty buffer, reused from this thread's pool when it has one.
+pub fn take() -> BytesMut {
+    POOL.with_borrow_mut(|pool| pool.pop()).unwrap_or_default()
+}
+
+/// Returns a buffer to this thread's pool, or frees it if it grew too large. Buffers split
+/// off a larger one, such as request frames, would keep all of it alive and are not for here.
+pub fn recycle(mut buf: BytesMut) {
+    if buf.capacity() == 0 || buf.capacity() > MAX_RETAINED_CAPACITY {
+        return;
+    }
+    buf.clear();
+    POOL.with_borrow_mut(|pool| {
+        if pool.len() < MAX_POOLED {
+            pool.push(buf);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BufMut;
+
+    #[test]
+    fn test_recycled_buffers_are_reused_empty() {
+        let mut buf = take();
+        buf.put_slice(&[1; 512]);
+        let capacity = buf.capacity();
+        recycle(buf);
+
+        let buf = take();
+        assert!(buf.is_empty());
+        assert_eq!(buf.capacity(), capacity);
+
+        let mut huge = BytesMut::with_capacity(MAX_RETAINED_CAPACITY + 1);
+        huge.put_u8(3);
+        recycle(huge);
+        assert_eq!(take().capacity(), 0);
+    }
+}
@@ -23,3 +23,17 @@ pub fn encode_nullable_bytes<B: BufMut>(buf: &mut B, bytes: &Option<Vec<u8>>) {
         None => Varint(-1).encode(buf),
     }
 }
+
+/// The number of bytes `encode_nullable_bytes` writes.
+pub fn nullable_bytes_size(bytes: &Option<Vec<u8>>) -> usize {
+    match bytes {
+        Some(bytes) => Varint(bytes.len() as i32).size() + bytes.len(),
+        None => Varint(-1).size(),
+    }
+}
+
+/// Compares in time that depends only on the lengths, so a mismatch does not reveal how
+/// much of a secret was right.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
@@ -5,6 +5,12 @@ pub struct FlatMap<K, V> {
     data: Vec<(K, V)>,
 }
 
+impl<K: Ord, V> Default for FlatMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<K: Ord, V> FlatMap<K, V> {
     pub fn new() -> Self {
         Self { data: Vec::new() }
@@ -34,9 +40,44 @@ impl<K: Ord, V> FlatMap<K, V> {
         }
     }
 
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.data.binary_search_by(|(k, _)| k.cmp(key)).is_ok()
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        match self.data.binary_search_by(|(k, _)| k.cmp(key)) {
+            Ok(idx) => Some(self.data.remove(idx).1),
+            Err(_) => None,
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.data.iter().map(|(k, _)| k)
+    }
+
     pub fn values(&self) -> impl Iterator<Item = &V> {
         self.data.iter().map(|(_, v)| v)
     }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.data.iter_mut().map(|(_, v)| v)
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.data.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.data.iter_mut().map(|(k, v)| (&*k, v))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -44,6 +85,12 @@ pub struct FlatSet<T> {
     data: Vec<T>,
 }
 
+impl<T: Ord> Default for FlatSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T: Ord> FlatSet<T> {
     pub fn new() -> Self {
         Self { data: Vec::new() }
@@ -62,4 +109,30 @@ impl<T: Ord> FlatSet<T> {
     pub fn len(&self) -> usize {
         self.data.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        self.data.binary_search(value).is_ok()
+    }
+
+    pub fn remove(&mut self, value: &T) -> bool {
+        match self.data.binary_search(value) {
+            Ok(idx) => {
+                self.data.remove(idx);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.data.iter()
+    }
 }
@@ -1,4 +1,93 @@
 pub const LOG_EXTENSION: &str = "log";
 pub const INDEX_EXTENSION: &str = "index";
 pub const TIMEINDEX_EXTENSION: &str = "timeindex";
+/// A log being rewritten with CRC-32C checksums, renamed over the log once complete.
+pub const UPGRADE_EXTENSION: &str = "upgrade";
 pub const CLEANED_DIR_NAME: &str = "cleaned";
+
+pub const DEFAULT_LISTENER: &str = "0.0.0.0:9092";
+pub const DEFAULT_LOG_DIR: &str = "/tmp/forge-logs";
+pub const CLUSTER_METADATA_DIR: &str = "__cluster_metadata-0";
+/// Left in the log dir by a clean shutdown; see `ReplicaManager::close_logs`.
+pub const CLEAN_SHUTDOWN_FILE: &str = ".forge_cleanshutdown";
+pub const DEFAULT_SOCKET_REQUEST_MAX_BYTES: u32 = 100 * 1024 * 1024;
+pub const DEFAULT_SOCKET_BUFFER_BYTES: u64 = 100 * 1024;
+pub const DEFAULT_MAX_CONNECTIONS: usize = i32::MAX as usize;
+pub const DEFAULT_MAX_CONNECTION_CREATION_RATE: u32 = i32::MAX as u32;
+pub const DEFAULT_CONNECTIONS_MAX_IDLE_MS: u64 = 10 * 60 * 1000;
+pub const DEFAULT_MAX_QUEUED_REQUESTS_PER_CONNECTION: usize = 32;
+pub const DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_MS: u64 = 30 * 1000;
+pub const DEFAULT_SOCKET_REQUEST_READ_TIMEOUT_MS: u64 = 30 * 1000;
+pub const DEFAULT_NUM_IO_THREADS: usize = 8;
+pub const DEFAULT_NUM_RECOVERY_THREADS_PER_DATA_DIR: usize = 1;
+pub const DEFAULT_FAILED_AUTHENTICATION_DELAY_MS: u64 = 100;
+
+pub const DEFAULT_SEGMENT_BYTES: u32 = 1024 * 1024 * 1024;
+pub const DEFAULT_RETENTION_BYTES: u64 = 0;
+pub const DEFAULT_RETENTION_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+pub const DEFAULT_MIN_INSYNC_REPLICAS: usize = 1;
+
+pub const CLEANUP_POLICY_CONFIG: &str = "cleanup.policy";
+pub const CLEANUP_POLICY_COMPACT: &str = "compact";
+pub const CLEANUP_POLICY_DELETE: &str = "delete";
+
+pub const DEFAULT_AUTO_CREATE_TOPICS_ENABLE: bool = true;
+pub const DEFAULT_NUM_PARTITIONS: i32 = 1;
+pub const DEFAULT_REPLICATION_FACTOR: i16 = 1;
+
+pub const UNCLEAN_LEADER_ELECTION_ENABLE_CONFIG: &str = "unclean.leader.election.enable";
+pub const DEFAULT_UNCLEAN_LEADER_ELECTION_ENABLE: bool = false;
+
+/// Comma-separated names of the record validators a topic runs on produce.
+pub const RECORD_VALIDATORS_CONFIG: &str = "record.validators";
+pub const REQUIRED_HEADERS_CONFIG: &str = "record.validator.required.headers";
+
+pub const CONSUMER_OFFSETS_TOPIC: &str = "__consumer_offsets";
+pub const DEFAULT_OFFSETS_TOPIC_PARTITIONS: i32 = 50;
+pub const DEFAULT_OFFSETS_TOPIC_REPLICATION_FACTOR: i16 = 1;
+pub const DEFAULT_OFFSETS_RETENTION_MINUTES: i64 = 7 * 24 * 60;
+pub const DEFAULT_OFFSETS_RETENTION_CHECK_INTERVAL_MS: u64 = 10 * 60 * 1000;
+
+pub const TRANSACTION_STATE_TOPIC: &str = "__transaction_state";
+pub const DEFAULT_TRANSACTION_STATE_PARTITIONS: i32 = 50;
+pub const DEFAULT_TRANSACTION_STATE_REPLICATION_FACTOR: i16 = 1;
+pub const DEFAULT_TRANSACTION_MAX_TIMEOUT_MS: i32 = 15 * 60 * 1000;
+pub const DEFAULT_TRANSACTION_ABORT_CHECK_INTERVAL_MS: u64 = 10 * 1000;
+pub const DEFAULT_PRODUCER_ID_EXPIRATION_MS: i64 = 24 * 60 * 60 * 1000;
+pub const DEFAULT_PRODUCER_ID_EXPIRATION_CHECK_INTERVAL_MS: u64 = 10 * 60 * 1000;
+
+pub const PRODUCER_ID_BLOCK_FILE: &str = "producer_id_block";
+pub const ACL_FILE: &str = "acls";
+pub const CREDENTIALS_FILE: &str = "credentials";
+pub const META_PROPERTIES_FILE: &str = "meta.properties";
+pub const DEFAULT_SCRAM_ITERATIONS: u32 = 4096;
+pub const DEFAULT_PRODUCER_ID_BLOCK_SIZE: i64 = 1000;
+
+pub const DEFAULT_REPLICA_LAG_TIME_MAX_MS: i64 = 30 * 1000;
+pub const DEFAULT_REPLICA_FETCH_WAIT_MAX_MS: i32 = 500;
+pub const DEFAULT_REPLICA_FETCH_MIN_BYTES: i32 = 1;
+pub const DEFAULT_REPLICA_FETCH_MAX_BYTES: i32 = 10 * 1024 * 1024;
+pub const DEFAULT_REPLICA_FETCH_BACKOFF_MS: u64 = 1000;
+
+pub const DEFAULT_BROKER_HEARTBEAT_INTERVAL_MS: u64 = 2000;
+pub const DEFAULT_BROKER_SESSION_TIMEOUT_MS: i64 = 9000;
+
+pub const PRODUCER_BYTE_RATE_CONFIG: &str = "producer_byte_rate";
+pub const CONSUMER_BYTE_RATE_CONFIG: &str = "consumer_byte_rate";
+pub const REQUEST_PERCENTAGE_CONFIG: &str = "request_percentage";
+pub const DEFAULT_QUOTA_WINDOW_NUM: usize = 11;
+pub const DEFAULT_QUOTA_WINDOW_SIZE_MS: i64 = 1000;
+
+pub const LOG_METRICS_REFRESH_INTERVAL_MS: u64 = 10 * 1000;
+
+pub const DEFAULT_REST_CONSUMER_INSTANCE_TIMEOUT_MS: u64 = 5 * 60 * 1000;
+pub const DEFAULT_REST_MAX_CONNECTIONS: usize = 1000;
+pub const DEFAULT_WASM_INTERCEPTOR_FUEL: u64 = 10_000_000;
+pub const DEFAULT_WASM_INTERCEPTOR_MEMORY_MAX_BYTES: usize = 16 * 1024 * 1024;
+/// The largest record batch a producer may send, as in Kafka.
+pub const DEFAULT_MESSAGE_MAX_BYTES: usize = 1024 * 1024 + 12;
+pub const DEFAULT_DEAD_LETTER_TOPIC_SUFFIX: &str = ".dlq";
+
+pub const DEFAULT_SERVER_LOG_MAX_BYTES: u64 = 100 * 1024 * 1024;
+pub const DEFAULT_SERVER_LOG_ROLL_MS: u64 = 24 * 60 * 60 * 1000;
+pub const DEFAULT_SERVER_LOG_MAX_FILES: u32 = 10;
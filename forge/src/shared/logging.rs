@@ -1,7 +1,172 @@
-use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tracing_subscriber::{
+    EnvFilter, Layer, Registry, layer::SubscriberExt, reload, util::SubscriberInitExt,
+};
 
-pub fn init() {
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("debug"));
+use crate::shared::collections::FlatMap;
+use crate::shared::rolling_file::RollingFile;
+
+const DEFAULT_LOG_LEVEL: &str = "debug";
+
+/// Audit events are logged under this target at info level, whatever the log level, unless a
+/// logger level for it says otherwise. `audit.log.file` gets a copy of them alone.
+pub const AUDIT_TARGET: &str = "forge::audit";
+
+/// A destination for spans and events besides stdout, added once the config is loaded.
+pub type LogOutput = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Swaps the active log filter while the broker runs.
+#[derive(Clone)]
+pub struct LogLevelHandle {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// The filter installed at startup, restored when an override is removed.
+    initial_directives: String,
+    /// A std mutex: held only while a new filter is built and installed.
+    state: Arc<Mutex<LogFilterState>>,
+}
+
+/// What the active filter is built from.
+#[derive(Debug, Default)]
+struct LogFilterState {
+    /// The `log.level` override of the startup filter.
+    directives: Option<String>,
+    /// Levels of single loggers (tracing targets such as `forge::consensus`), applied on top.
+    loggers: FlatMap<String, String>,
+}
+
+impl LogFilterState {
+    fn filter(&self, initial_directives: &str) -> String {
+        let mut filter = self
+            .directives
+            .clone()
+            .unwrap_or_else(|| initial_directives.to_string());
+        filter.push_str(&format!(",{}=info", AUDIT_TARGET));
+        for (logger, level) in self.loggers.iter() {
+            filter.push_str(&format!(",{}={}", logger, level));
+        }
+        filter
+    }
+}
+
+impl LogLevelHandle {
+    /// Installs `directives` (`info`, `forge::consensus=debug,warn`, ...), or the startup filter
+    /// when `None`. Logger levels stay on top.
+    pub fn set(&self, directives: Option<&str>) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = std::mem::replace(&mut state.directives, directives.map(str::to_string));
+        let result = self.install(&state);
+        if result.is_err() {
+            state.directives = previous;
+        }
+        result
+    }
+
+    /// Replaces the logger levels: `loggers` maps tracing targets to `trace`, `debug`, `info`,
+    /// `warn`, `error` or `off`. Invalid entries reject the whole set.
+    pub fn set_loggers(&self, loggers: FlatMap<String, String>) -> Result<(), String> {
+        Self::validate_loggers(&loggers)?;
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = std::mem::replace(&mut state.loggers, loggers);
+        let result = self.install(&state);
+        if result.is_err() {
+            state.loggers = previous;
+        }
+        result
+    }
+
+    pub fn validate_loggers(loggers: &FlatMap<String, String>) -> Result<(), String> {
+        for (logger, level) in loggers.iter() {
+            if logger.is_empty() || logger.contains([',', '=', '[', ']', ' ']) {
+                return Err(format!("Invalid logger name '{}'", logger));
+            }
+            level
+                .parse::<LevelFilter>()
+                .map_err(|_| format!("Invalid level '{}' for logger {}", level, logger))?;
+        }
+        Ok(())
+    }
+
+    /// The logger levels set with `set_loggers`.
+    pub fn loggers(&self) -> FlatMap<String, String> {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .loggers
+            .clone()
+    }
+
+    /// The active filter, as `EnvFilter` directives.
+    pub fn directives(&self) -> String {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .filter(&self.initial_directives)
+    }
+
+    fn install(&self, state: &LogFilterState) -> Result<(), String> {
+        let filter = parse_directives(&state.filter(&self.initial_directives))?;
+        self.handle
+            .reload(filter)
+            .map_err(|e| format!("Failed to reload log filter: {}", e))
+    }
+}
+
+pub fn parse_directives(directives: &str) -> Result<EnvFilter, String> {
+    EnvFilter::try_new(directives).map_err(|e| format!("Invalid log filter {}: {}", directives, e))
+}
+
+/// Adds outputs to the subscriber installed by `init`. The log filter applies to them too.
+pub struct LogOutputs {
+    handle: reload::Handle<Vec<LogOutput>, Registry>,
+}
+
+impl LogOutputs {
+    pub fn add(&self, output: LogOutput) -> Result<(), String> {
+        self.handle
+            .modify(|outputs| outputs.push(output))
+            .map_err(|e| format!("Failed to add log output: {}", e))
+    }
+}
+
+/// Writes the log to `file` as well, formatted as on stdout but without colours.
+pub fn file_output(file: RollingFile) -> LogOutput {
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .with_target(true)
+        .with_thread_ids(true)
+        .with_level(true)
+        .with_file(true)
+        .with_line_number(true)
+        .compact();
+    Box::new(layer)
+}
+
+/// Writes audit events, and nothing else, to `file`.
+pub fn audit_output(file: RollingFile) -> LogOutput {
+    let writer = Mutex::new(file).with_filter(|metadata| metadata.target() == AUDIT_TARGET);
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(false)
+        .with_target(false)
+        .with_level(false);
+    Box::new(layer)
+}
+
+/// Installs the global subscriber, logging to stdout. Runs before the config is loaded so that
+/// loading it can log; outputs the config asks for are added through `LogOutputs`. The log
+/// filter applies to the outputs alone, so that with the `console` feature tokio-console still
+/// sees every task.
+pub fn init() -> (LogLevelHandle, LogOutputs) {
+    let initial_directives = std::env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .filter(|directives| parse_directives(directives).is_ok())
+        .unwrap_or_else(|| DEFAULT_LOG_LEVEL.to_string());
+    let (outputs, outputs_handle) = reload::Layer::new(Vec::new());
+    let state = LogFilterState::default();
+    let (filter, handle) = reload::Layer::new(EnvFilter::new(state.filter(&initial_directives)));
     let fmt_layer = tracing_subscriber::fmt::layer()
         .with_target(true)
         .with_thread_ids(true)
@@ -9,8 +174,54 @@ pub fn init() {
         .with_file(true)
         .with_line_number(true)
         .compact();
-    tracing_subscriber::registry()
-        .with(filter)
-        .with(fmt_layer)
-        .init();
+    let subscriber =
+        tracing_subscriber::registry().with(outputs.and_then(fmt_layer).with_filter(filter));
+    // Listens on TOKIO_CONSOLE_BIND, 127.0.0.1:6669 by default
+    #[cfg(feature = "console")]
+    let subscriber = subscriber.with(
+        console_subscriber::ConsoleLayer::builder()
+            .with_default_env()
+            .spawn(),
+    );
+    subscriber.init();
+
+    let log_level = LogLevelHandle {
+        handle,
+        initial_directives,
+        state: Arc::new(Mutex::new(state)),
+    };
+    let outputs = LogOutputs {
+        handle: outputs_handle,
+    };
+    (log_level, outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logger_levels_apply_on_top_of_the_base_filter() {
+        let mut state = LogFilterState::default();
+        state
+            .loggers
+            .insert("forge::consensus".to_string(), "trace".to_string());
+        assert_eq!(
+            state.filter("info"),
+            "info,forge::audit=info,forge::consensus=trace"
+        );
+        state.directives = Some("warn".to_string());
+        assert_eq!(
+            state.filter("info"),
+            "warn,forge::audit=info,forge::consensus=trace"
+        );
+        assert!(parse_directives(&state.filter("info")).is_ok());
+
+        let mut loggers = FlatMap::new();
+        loggers.insert("forge".to_string(), "loud".to_string());
+        assert!(LogLevelHandle::validate_loggers(&loggers).is_err());
+        loggers.insert("forge".to_string(), "off".to_string());
+        loggers.insert("forge=debug".to_string(), "info".to_string());
+        assert!(LogLevelHandle::validate_loggers(&loggers).is_err());
+    }
 }
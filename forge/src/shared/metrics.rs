@@ -0,0 +1,167 @@
+use async_trait::async_trait;
+use std::fmt::Write;
+
+/// Something that reports metrics when they are scraped.
+#[async_trait]
+pub trait MetricsSource: Send + Sync {
+    async fn write_metrics(&self, metrics: &mut MetricsWriter);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricType {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl MetricType {
+    fn name(self) -> &'static str {
+        match self {
+            MetricType::Counter => "counter",
+            MetricType::Gauge => "gauge",
+            MetricType::Histogram => "histogram",
+        }
+    }
+}
+
+/// Upper bounds, in seconds, for request latencies: 1 ms to 30 s.
+pub const LATENCY_BUCKETS: [f64; 14] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Counts observations into buckets with fixed upper bounds, plus an unbounded one.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    /// Per bucket, not cumulative; the last is the unbounded one.
+    counts: Vec<u64>,
+    sum: f64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len() + 1],
+            sum: 0.0,
+        }
+    }
+
+    pub fn observe(&mut self, value: f64) {
+        let bucket = self.bounds.partition_point(|bound| *bound < value);
+        self.counts[bucket] += 1;
+        self.sum += value;
+    }
+}
+
+/// Renders metrics in the Prometheus text format. Every sample follows the `family` call
+/// that introduced its metric, as the format requires.
+#[derive(Debug, Default)]
+pub struct MetricsWriter {
+    output: String,
+}
+
+impl MetricsWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn family(&mut self, name: &str, metric_type: MetricType, help: &str) {
+        let _ = writeln!(self.output, "# HELP {} {}", name, help);
+        let _ = writeln!(self.output, "# TYPE {} {}", name, metric_type.name());
+    }
+
+    pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.output.push_str(name);
+        if !labels.is_empty() {
+            self.output.push('{');
+            for (index, (label, label_value)) in labels.iter().enumerate() {
+                if index > 0 {
+                    self.output.push(',');
+                }
+                let _ = write!(self.output, "{}=\"", label);
+                Self::escape_label_value(&mut self.output, label_value);
+                self.output.push('"');
+            }
+            self.output.push('}');
+        }
+        let _ = writeln!(self.output, " {}", value);
+    }
+
+    /// Writes `histogram` as the `_bucket`, `_sum` and `_count` samples of `name`.
+    pub fn histogram(&mut self, name: &str, labels: &[(&str, &str)], histogram: &Histogram) {
+        let bucket_name = format!("{}_bucket", name);
+        let mut cumulative = 0;
+        for (index, count) in histogram.counts.iter().enumerate() {
+            cumulative += count;
+            let bound = histogram
+                .bounds
+                .get(index)
+                .map_or("+Inf".to_string(), |bound| bound.to_string());
+            let mut bucket_labels = labels.to_vec();
+            bucket_labels.push(("le", &bound));
+            self.sample(&bucket_name, &bucket_labels, cumulative as f64);
+        }
+        self.sample(&format!("{}_sum", name), labels, histogram.sum);
+        self.sample(&format!("{}_count", name), labels, cumulative as f64);
+    }
+
+    /// A family with a single unlabelled sample.
+    pub fn single(&mut self, name: &str, metric_type: MetricType, help: &str, value: f64) {
+        self.family(name, metric_type, help);
+        self.sample(name, &[], value);
+    }
+
+    fn escape_label_value(output: &mut String, value: &str) {
+        for c in value.chars() {
+            match c {
+                '\\' => output.push_str("\\\\"),
+                '"' => output.push_str("\\\""),
+                '\n' => output.push_str("\\n"),
+                c => output.push(c),
+            }
+        }
+    }
+
+    pub fn finish(self) -> String {
+        self.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renders_families_and_escapes_label_values() {
+        let mut metrics = MetricsWriter::new();
+        metrics.family("requests", MetricType::Counter, "Requests served.");
+        metrics.sample("requests", &[("client", "a\"b\\c"), ("api", "fetch")], 3.0);
+        metrics.single("up", MetricType::Gauge, "Whether the broker runs.", 1.0);
+
+        assert_eq!(
+            metrics.finish(),
+            "# HELP requests Requests served.\n\
+             # TYPE requests counter\n\
+             requests{client=\"a\\\"b\\\\c\",api=\"fetch\"} 3\n\
+             # HELP up Whether the broker runs.\n\
+             # TYPE up gauge\n\
+             up 1\n"
+        );
+
+        let mut histogram = Histogram::new(&[0.1, 1.0]);
+        histogram.observe(0.1);
+        histogram.observe(0.5);
+        histogram.observe(2.0);
+        let mut metrics = MetricsWriter::new();
+        metrics.histogram("latency", &[("api", "Fetch")], &histogram);
+        assert_eq!(
+            metrics.finish(),
+            "latency_bucket{api=\"Fetch\",le=\"0.1\"} 1\n\
+             latency_bucket{api=\"Fetch\",le=\"1\"} 2\n\
+             latency_bucket{api=\"Fetch\",le=\"+Inf\"} 3\n\
+             latency_sum{api=\"Fetch\"} 2.6\n\
+             latency_count{api=\"Fetch\"} 3\n"
+        );
+    }
+}
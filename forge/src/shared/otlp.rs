@@ -0,0 +1,27 @@
+use opentelemetry::KeyValue;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+
+use crate::shared::logging::LogOutput;
+
+/// Exports spans to the OTLP/gRPC collector at `endpoint`, batched in the background. Shut the
+/// returned provider down on exit to send what is still buffered.
+pub fn output(endpoint: &str, node_id: i32) -> Result<(LogOutput, SdkTracerProvider), String> {
+    let exporter = SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| format!("Failed to create OTLP exporter for {}: {}", endpoint, e))?;
+    let resource = Resource::builder()
+        .with_service_name("forge")
+        .with_attribute(KeyValue::new("node.id", node_id as i64))
+        .build();
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource)
+        .build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("forge"));
+    Ok((Box::new(layer), provider))
+}
@@ -0,0 +1,139 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// An append-only file that is rolled once it reaches a size or age: `server.log` becomes
+/// `server.log.1`, the previous `.1` becomes `.2`, and so on up to `max_files`; older ones are
+/// deleted. Writes are assumed to be whole lines, so a line never straddles two files.
+#[derive(Debug)]
+pub struct RollingFile {
+    path: PathBuf,
+    /// 0 never rolls on size.
+    max_bytes: u64,
+    roll_interval: Option<Duration>,
+    max_files: u32,
+    file: File,
+    size: u64,
+    opened: Instant,
+}
+
+impl RollingFile {
+    pub fn open(
+        path: impl AsRef<Path>,
+        max_bytes: u64,
+        roll_interval: Option<Duration>,
+        max_files: u32,
+    ) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let file = Self::open_file(&path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let size = file
+            .metadata()
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+            .len();
+        Ok(Self {
+            path,
+            max_bytes,
+            roll_interval,
+            max_files,
+            file,
+            size,
+            opened: Instant::now(),
+        })
+    }
+
+    fn open_file(path: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    fn rolled_path(&self, index: u32) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    fn should_roll(&self, incoming: usize) -> bool {
+        let full =
+            self.max_bytes > 0 && self.size > 0 && self.size + incoming as u64 > self.max_bytes;
+        let old = self
+            .roll_interval
+            .is_some_and(|interval| self.size > 0 && self.opened.elapsed() >= interval);
+        full || old
+    }
+
+    fn roll(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            match std::fs::remove_file(self.rolled_path(self.max_files)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+            for index in (1..self.max_files).rev() {
+                match std::fs::rename(self.rolled_path(index), self.rolled_path(index + 1)) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            }
+            std::fs::rename(&self.path, self.rolled_path(1))?;
+        }
+        self.file = Self::open_file(&self.path)?;
+        self.size = 0;
+        self.opened = Instant::now();
+        Ok(())
+    }
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.should_roll(buf.len())
+            && let Err(e) = self.roll()
+        {
+            // Keep logging to the current file rather than losing lines
+            eprintln!("Failed to roll {}: {}", self.path.display(), e);
+            self.opened = Instant::now();
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolls_on_size_and_keeps_max_files() {
+        let dir = std::env::temp_dir().join(format!("forge-rolling-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("server.log");
+        let mut file = RollingFile::open(&path, 10, None, 2).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+
+        let read = |path: PathBuf| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(path.clone()), "fourth\n");
+        assert_eq!(read(file.rolled_path(1)), "third\n");
+        assert_eq!(read(file.rolled_path(2)), "second\n");
+        assert!(!file.rolled_path(3).exists());
+
+        // Reopening appends and counts what is already there
+        let mut file = RollingFile::open(&path, 10, None, 2).unwrap();
+        file.write_all(b"fifth\n").unwrap();
+        assert_eq!(read(path.clone()), "fifth\n");
+        assert_eq!(read(file.rolled_path(1)), "fourth\n");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,63 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+
+/// Spawns `task` on the current runtime under `name`, which tokio-console shows for builds
+/// with `--cfg tokio_unstable`.
+pub fn spawn_named<F>(name: &str, task: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    spawn_named_on(name, task, &Handle::current())
+}
+
+pub fn spawn_named_on<F>(name: &str, task: F, runtime: &Handle) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(tokio_unstable)]
+    {
+        tokio::task::Builder::new()
+            .name(name)
+            .spawn_on(task, runtime)
+            .expect("Spawning a task does not fail")
+    }
+    #[cfg(not(tokio_unstable))]
+    {
+        let _ = name;
+        runtime.spawn(task)
+    }
+}
+
+/// Runs `task` every `period` until `cancel_token` is cancelled. A slow run delays the next
+/// tick instead of bursting to catch up.
+pub fn spawn_periodic<F, Fut>(
+    name: &'static str,
+    period: Duration,
+    cancel_token: CancellationToken,
+    mut task: F,
+) -> JoinHandle<()>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    spawn_named(name, async move {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => task().await,
+                _ = cancel_token.cancelled() => {
+                    tracing::debug!("Stopping scheduled task {}", name);
+                    break;
+                }
+            }
+        }
+    })
+}
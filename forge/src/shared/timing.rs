@@ -0,0 +1,31 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+struct BusyTimed<F> {
+    future: Pin<Box<F>>,
+    busy: Duration,
+}
+
+impl<F: Future> Future for BusyTimed<F> {
+    type Output = (F::Output, Duration);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let started = Instant::now();
+        let poll = this.future.as_mut().poll(cx);
+        this.busy += started.elapsed();
+        poll.map(|output| (output, this.busy))
+    }
+}
+
+/// Runs `future` and returns how long it spent being polled, leaving out time parked on timers
+/// or channels. This approximates the worker thread time a request used.
+pub async fn measure_busy_time<F: Future>(future: F) -> (F::Output, Duration) {
+    BusyTimed {
+        future: Box::pin(future),
+        busy: Duration::ZERO,
+    }
+    .await
+}
@@ -0,0 +1,291 @@
+use clap::Args;
+
+use crate::adapters::driven::broker_client::BrokerClient;
+use crate::core::domain::topic_partition::TopicPartition;
+use crate::core::error::ErrorCode;
+use crate::protocol::find_coordinator::{
+    COORDINATOR_TYPE_GROUP, FIND_COORDINATOR_API_KEY, FIND_COORDINATOR_MAX_VERSION,
+    FindCoordinatorRequest, FindCoordinatorResponse,
+};
+use crate::protocol::list_offsets::{
+    LIST_OFFSETS_API_KEY, LIST_OFFSETS_MAX_VERSION, ListOffsetsPartition, ListOffsetsRequest,
+    ListOffsetsResponse, ListOffsetsTopic,
+};
+use crate::protocol::offset_commit::{
+    OFFSET_COMMIT_API_KEY, OFFSET_COMMIT_MAX_VERSION, OffsetCommitPartition, OffsetCommitRequest,
+    OffsetCommitResponse, OffsetCommitTopic,
+};
+use crate::protocol::offset_fetch::{
+    OFFSET_FETCH_API_KEY, OFFSET_FETCH_MAX_VERSION, OffsetFetchRequest, OffsetFetchResponse,
+    OffsetFetchTopic,
+};
+
+/// How the command line tools reach a broker.
+#[derive(Debug, Clone, Args)]
+pub struct ClientArgs {
+    /// Broker to connect to, as host:port.
+    #[arg(long, default_value = "127.0.0.1:9092")]
+    pub bootstrap_server: String,
+
+    /// Client id sent with every request; each tool has its own default.
+    #[arg(long)]
+    pub client_id: Option<String>,
+
+    /// Logs in with SASL: PLAIN, SCRAM-SHA-256 or SCRAM-SHA-512.
+    #[arg(long, requires_all = ["sasl_username", "sasl_password"])]
+    pub sasl_mechanism: Option<String>,
+
+    #[arg(long)]
+    pub sasl_username: Option<String>,
+
+    /// Best passed through the environment, which keeps it out of the process list.
+    #[arg(long, env = "FORGE_SASL_PASSWORD", hide_env_values = true)]
+    pub sasl_password: Option<String>,
+}
+
+impl ClientArgs {
+    /// A client for the bootstrap server; it connects on the first request.
+    pub fn client(&self, default_client_id: &str) -> BrokerClient {
+        self.client_for(&self.bootstrap_server, default_client_id)
+    }
+
+    /// A client for another broker, such as a group coordinator, logging in the same way.
+    pub fn client_for(&self, address: &str, default_client_id: &str) -> BrokerClient {
+        let client_id = self.client_id.as_deref().unwrap_or(default_client_id);
+        let client = BrokerClient::new(address, client_id);
+        match (
+            &self.sasl_mechanism,
+            &self.sasl_username,
+            &self.sasl_password,
+        ) {
+            (Some(mechanism), Some(username), Some(password)) => {
+                client.with_sasl(mechanism.clone(), username.clone(), password.clone())
+            }
+            _ => client,
+        }
+    }
+
+    /// Asks the bootstrap server which broker coordinates `group` and returns a client for it.
+    pub async fn coordinator(
+        &self,
+        client: &mut BrokerClient,
+        group: &str,
+        default_client_id: &str,
+    ) -> Result<BrokerClient, String> {
+        let request = FindCoordinatorRequest {
+            key: group.to_string(),
+            key_type: COORDINATOR_TYPE_GROUP,
+        };
+        let mut response = client
+            .send_request(
+                FIND_COORDINATOR_API_KEY,
+                FIND_COORDINATOR_MAX_VERSION,
+                |buf| request.encode(buf, FIND_COORDINATOR_MAX_VERSION),
+            )
+            .await?;
+        let response =
+            FindCoordinatorResponse::decode(&mut response, FIND_COORDINATOR_MAX_VERSION)?;
+        check(response.error_code, "find the group coordinator")?;
+        let address = format!("{}:{}", response.host, response.port);
+        Ok(self.client_for(&address, default_client_id))
+    }
+}
+
+/// Fails with a message naming `what` when a response carries an error.
+pub fn check(error_code: i16, what: &str) -> Result<(), String> {
+    if error_code != ErrorCode::None.code() {
+        return Err(format!("Failed to {}: error code {}", what, error_code));
+    }
+    Ok(())
+}
+
+/// Prints rows under their headers, each column as wide as its widest cell.
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join(" ")
+            .trim_end()
+            .to_string()
+    };
+    println!("{}", line(headers.to_vec()));
+    for row in rows {
+        println!("{}", line(row.iter().map(String::as_str).collect()));
+    }
+}
+
+/// Groups partitions by topic, keeping the order topics first appear in.
+fn by_topic(partitions: &[TopicPartition]) -> Vec<(String, Vec<i32>)> {
+    let mut topics: Vec<(String, Vec<i32>)> = Vec::new();
+    for topic_partition in partitions {
+        match topics
+            .iter_mut()
+            .find(|(topic, _)| *topic == topic_partition.topic)
+        {
+            Some((_, indexes)) => indexes.push(topic_partition.partition),
+            None => topics.push((
+                topic_partition.topic.clone(),
+                vec![topic_partition.partition],
+            )),
+        }
+    }
+    topics
+}
+
+/// Resolves `timestamp`, or the earliest and latest sentinels, to an offset on each
+/// partition; a partition the broker could not answer for carries the error code instead.
+pub async fn list_offsets(
+    client: &mut BrokerClient,
+    isolation_level: i8,
+    partitions: &[TopicPartition],
+    timestamp: i64,
+) -> Result<Vec<(TopicPartition, Result<i64, i16>)>, String> {
+    let request = ListOffsetsRequest {
+        replica_id: -1,
+        isolation_level,
+        topics: by_topic(partitions)
+            .into_iter()
+            .map(|(name, indexes)| ListOffsetsTopic {
+                name,
+                partitions: indexes
+                    .into_iter()
+                    .map(|partition_index| ListOffsetsPartition {
+                        partition_index,
+                        current_leader_epoch: -1,
+                        timestamp,
+                    })
+                    .collect(),
+            })
+            .collect(),
+    };
+    let mut response = client
+        .send_request(LIST_OFFSETS_API_KEY, LIST_OFFSETS_MAX_VERSION, |buf| {
+            request.encode(buf, LIST_OFFSETS_MAX_VERSION)
+        })
+        .await?;
+    let response = ListOffsetsResponse::decode(&mut response, LIST_OFFSETS_MAX_VERSION)?;
+    Ok(response
+        .topics
+        .into_iter()
+        .flat_map(|topic| {
+            topic.partitions.into_iter().map(move |partition| {
+                let offset = if partition.error_code == ErrorCode::None.code() {
+                    Ok(partition.offset)
+                } else {
+                    Err(partition.error_code)
+                };
+                (
+                    TopicPartition::new(topic.name.clone(), partition.partition_index),
+                    offset,
+                )
+            })
+        })
+        .collect())
+}
+
+/// The group's committed offsets, for `partitions` or, with `None`, every partition it
+/// committed. Partitions without a committed offset are left out.
+pub async fn fetch_committed_offsets(
+    coordinator: &mut BrokerClient,
+    group: &str,
+    partitions: Option<&[TopicPartition]>,
+) -> Result<Vec<(TopicPartition, i64)>, String> {
+    let request = OffsetFetchRequest {
+        group_id: group.to_string(),
+        topics: partitions.map(|partitions| {
+            by_topic(partitions)
+                .into_iter()
+                .map(|(name, partition_indexes)| OffsetFetchTopic {
+                    name,
+                    partition_indexes,
+                })
+                .collect()
+        }),
+    };
+    let mut response = coordinator
+        .send_request(OFFSET_FETCH_API_KEY, OFFSET_FETCH_MAX_VERSION, |buf| {
+            request.encode(buf, OFFSET_FETCH_MAX_VERSION)
+        })
+        .await?;
+    let response = OffsetFetchResponse::decode(&mut response, OFFSET_FETCH_MAX_VERSION)?;
+    check(response.error_code, "fetch committed offsets")?;
+    let mut committed = Vec::new();
+    for topic in response.topics {
+        for partition in topic.partitions {
+            check(partition.error_code, "fetch committed offsets")?;
+            if partition.committed_offset >= 0 {
+                committed.push((
+                    TopicPartition::new(topic.name.clone(), partition.partition_index),
+                    partition.committed_offset,
+                ));
+            }
+        }
+    }
+    Ok(committed)
+}
+
+/// An OffsetCommit of `offsets` on behalf of a member of `generation_id`, or of a
+/// standalone consumer with -1 and an empty member id.
+pub fn offset_commit_request(
+    group: &str,
+    generation_id: i32,
+    member_id: &str,
+    offsets: &[(TopicPartition, i64)],
+) -> OffsetCommitRequest {
+    let mut topics: Vec<OffsetCommitTopic> = Vec::new();
+    for (topic_partition, offset) in offsets {
+        let partition = OffsetCommitPartition {
+            partition_index: topic_partition.partition,
+            committed_offset: *offset,
+            committed_leader_epoch: -1,
+            committed_metadata: None,
+        };
+        match topics
+            .iter_mut()
+            .find(|topic| topic.name == topic_partition.topic)
+        {
+            Some(topic) => topic.partitions.push(partition),
+            None => topics.push(OffsetCommitTopic {
+                name: topic_partition.topic.clone(),
+                partitions: vec![partition],
+            }),
+        }
+    }
+    OffsetCommitRequest {
+        group_id: group.to_string(),
+        generation_id,
+        member_id: member_id.to_string(),
+        retention_time_ms: -1,
+        group_instance_id: None,
+        topics,
+    }
+}
+
+/// Commits without a generation or member id, as a standalone consumer; the coordinator
+/// accepts that only while the group has no members.
+pub async fn commit_offsets(
+    coordinator: &mut BrokerClient,
+    group: &str,
+    offsets: &[(TopicPartition, i64)],
+) -> Result<(), String> {
+    let request = offset_commit_request(group, -1, "", offsets);
+    let mut response = coordinator
+        .send_request(OFFSET_COMMIT_API_KEY, OFFSET_COMMIT_MAX_VERSION, |buf| {
+            request.encode(buf, OFFSET_COMMIT_MAX_VERSION)
+        })
+        .await?;
+    let response = OffsetCommitResponse::decode(&mut response, OFFSET_COMMIT_MAX_VERSION)?;
+    for partition in response.topics.iter().flat_map(|topic| &topic.partitions) {
+        check(partition.error_code, "commit offsets")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,696 @@
+//! Runs real Kafka clients against a Forge broker, so the wire format is checked by code
+//! that was not written alongside it:
+//!
+//!     cargo test --features conformance --test conformance
+//!
+//! Each test starts its own broker on a free port with an empty log dir. The kafka-python
+//! flow runs when `python3` can import `kafka` and is skipped otherwise.
+//!
+//! With `FORGE_RECORD_FIXTURES=tests/fixtures/wire` the clients reach the broker through
+//! `recorder`, which saves the API versions the fixtures do not have yet.
+
+#[path = "conformance/recorder.rs"]
+mod recorder;
+
+use std::ffi::{CStr, c_char};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use rdkafka::admin::{AdminClient, AdminOptions, AlterConfig, ResourceSpecifier};
+use rdkafka::bindings::*;
+use rdkafka::client::DefaultClientContext;
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::message::{Header, Headers, Message, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use rdkafka::{Offset, TopicPartitionList};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A broker process, killed and its log dir removed on drop.
+struct Broker {
+    child: Child,
+    dir: PathBuf,
+    bootstrap: String,
+}
+
+impl Broker {
+    fn start() -> Self {
+        Self::start_with("PLAINTEXT", &[])
+    }
+
+    /// A broker whose listener speaks `protocol`, with `overrides` applied. SASL listeners
+    /// accept `alice` with password `alice-secret` over PLAIN.
+    fn start_with(protocol: &str, overrides: &[(&str, &str)]) -> Self {
+        // The port is free once the listener is dropped, barring a race with another process
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("Failed to find a free port")
+            .port();
+        let listener = format!("127.0.0.1:{}", port);
+        let bootstrap = match std::env::var_os("FORGE_RECORD_FIXTURES") {
+            Some(fixtures) => recorder::start(listener.clone(), fixtures.into()),
+            None => listener.clone(),
+        };
+        let dir = std::env::temp_dir().join(format!("forge-conformance-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("Failed to create the log dir");
+        let log = std::fs::File::create(dir.join("broker.log")).expect("Failed to create log");
+        if protocol.starts_with("SASL") {
+            std::fs::create_dir_all(dir.join("data")).expect("Failed to create the data dir");
+            std::fs::write(dir.join("data/credentials"), "PLAIN alice alice-secret\n")
+                .expect("Failed to write the credentials");
+        }
+
+        let child = Command::new(env!("CARGO_BIN_EXE_forge"))
+            .args(["--node-id", "1"])
+            .args(["--listeners", &format!("{}://{}", protocol, listener)])
+            .args([
+                "--override",
+                &format!("advertised.listeners={}://{}", protocol, bootstrap),
+            ])
+            .arg("--log-dirs")
+            .arg(dir.join("data"))
+            .args(["--override", "auto.create.topics.enable=true"])
+            .args(["--override", "num.partitions=3"])
+            .args(overrides.iter().flat_map(|(name, value)| {
+                ["--override".to_string(), format!("{}={}", name, value)]
+            }))
+            .stdout(log.try_clone().expect("Failed to share the log"))
+            .stderr(log)
+            .spawn()
+            .expect("Failed to start the broker");
+        let broker = Self {
+            child,
+            dir,
+            bootstrap,
+        };
+
+        let deadline = Instant::now() + TIMEOUT;
+        while TcpStream::connect(&listener).is_err() {
+            assert!(
+                Instant::now() < deadline,
+                "The broker did not start listening; see {}",
+                broker.dir.join("broker.log").display()
+            );
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        broker
+    }
+
+    fn config(&self) -> ClientConfig {
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", &self.bootstrap);
+        config
+    }
+
+    fn producer(&self, settings: &[(&str, &str)]) -> FutureProducer {
+        let mut config = self.config();
+        for (name, value) in settings {
+            config.set(*name, *value);
+        }
+        config.create().expect("Failed to create the producer")
+    }
+
+    fn consumer(&self, group: &str, settings: &[(&str, &str)]) -> StreamConsumer {
+        let mut config = self.config();
+        config
+            .set("group.id", group)
+            .set("auto.offset.reset", "earliest")
+            .set("enable.auto.commit", "false");
+        for (name, value) in settings {
+            config.set(*name, *value);
+        }
+        config.create().expect("Failed to create the consumer")
+    }
+}
+
+impl Drop for Broker {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        if !std::thread::panicking() {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+/// Receives `count` messages as (partition, offset, key, value), failing after `TIMEOUT`.
+async fn receive(
+    consumer: &StreamConsumer,
+    count: usize,
+) -> Vec<(i32, i64, Option<Vec<u8>>, Option<Vec<u8>>)> {
+    let mut received = Vec::new();
+    while received.len() < count {
+        let message = tokio::time::timeout(TIMEOUT, consumer.recv())
+            .await
+            .unwrap_or_else(|_| panic!("Received {} of {} messages", received.len(), count))
+            .expect("Failed to consume");
+        received.push((
+            message.partition(),
+            message.offset(),
+            message.key().map(<[u8]>::to_vec),
+            message.payload().map(<[u8]>::to_vec),
+        ));
+    }
+    received
+}
+
+/// Nothing more arrives within a second.
+async fn assert_drained(consumer: &StreamConsumer) {
+    if let Ok(message) = tokio::time::timeout(Duration::from_secs(1), consumer.recv()).await {
+        let message = message.expect("Failed to consume");
+        panic!(
+            "Unexpected message at {}-{}@{}",
+            message.topic(),
+            message.partition(),
+            message.offset()
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_produce_and_consume_with_every_compression() {
+    let broker = Broker::start();
+    for compression in ["none", "gzip", "snappy", "lz4", "zstd"] {
+        let topic = format!("records-{}", compression);
+        let producer = broker.producer(&[("compression.type", compression), ("linger.ms", "5")]);
+        let keys: Vec<String> = (0..100).map(|i| format!("key-{}", i % 10)).collect();
+        let values: Vec<String> = (0..100).map(|i| format!("value-{}", i)).collect();
+        let mut deliveries = Vec::new();
+        for (i, (key, value)) in keys.iter().zip(&values).enumerate() {
+            let headers = OwnedHeaders::new().insert(Header {
+                key: "index",
+                value: Some(&i.to_string()),
+            });
+            let record = FutureRecord::to(&topic)
+                .key(key)
+                .payload(value)
+                .headers(headers);
+            deliveries.push(producer.send(record, TIMEOUT));
+        }
+        let mut written = Vec::new();
+        for delivery in deliveries {
+            written.push(
+                delivery
+                    .await
+                    .map_err(|(e, _)| e)
+                    .expect("Failed to produce"),
+            );
+        }
+        // Offsets are dense and in send order within each partition
+        for partition in 0..3 {
+            let offsets: Vec<i64> = written
+                .iter()
+                .filter(|(p, _)| *p == partition)
+                .map(|(_, offset)| *offset)
+                .collect();
+            assert_eq!(offsets, (0..offsets.len() as i64).collect::<Vec<_>>());
+        }
+
+        let consumer = broker.consumer(&format!("group-{}", compression), &[]);
+        consumer.subscribe(&[&topic]).expect("Failed to subscribe");
+        let mut received = Vec::new();
+        while received.len() < 100 {
+            let message = tokio::time::timeout(TIMEOUT, consumer.recv())
+                .await
+                .expect("Timed out consuming")
+                .expect("Failed to consume");
+            let header = message.headers().expect("No headers").get(0);
+            assert_eq!(header.key, "index");
+            let index: usize = std::str::from_utf8(header.value.expect("No header value"))
+                .unwrap()
+                .parse()
+                .unwrap();
+            assert_eq!(
+                message.key(),
+                Some(format!("key-{}", index % 10).as_bytes())
+            );
+            assert_eq!(
+                message.payload(),
+                Some(format!("value-{}", index).as_bytes())
+            );
+            received.push(index);
+        }
+        received.sort_unstable();
+        assert_eq!(received, (0..100).collect::<Vec<_>>());
+        assert_drained(&consumer).await;
+
+        consumer
+            .commit_consumer_state(CommitMode::Sync)
+            .expect("Failed to commit");
+        let committed = consumer
+            .committed(TIMEOUT)
+            .expect("Failed to fetch committed offsets");
+        let total: i64 = committed
+            .elements()
+            .iter()
+            .map(|element| match element.offset() {
+                Offset::Offset(offset) => offset,
+                offset => panic!("Unexpected committed offset {:?}", offset),
+            })
+            .sum();
+        assert_eq!(total, 100);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_idempotent_producer() {
+    let broker = Broker::start();
+    let producer = broker.producer(&[("enable.idempotence", "true")]);
+    for i in 0..50 {
+        let value = format!("value-{}", i);
+        let record = FutureRecord::to("idempotent").key("key").payload(&value);
+        let (_, offset) = producer
+            .send(record, TIMEOUT)
+            .await
+            .map_err(|(e, _)| e)
+            .expect("Failed to produce");
+        assert_eq!(offset, i);
+    }
+
+    let consumer = broker.consumer("idempotent", &[]);
+    consumer
+        .subscribe(&["idempotent"])
+        .expect("Failed to subscribe");
+    let received = receive(&consumer, 50).await;
+    for (i, (_, offset, _, value)) in received.iter().enumerate() {
+        assert_eq!(*offset, i as i64);
+        assert_eq!(value.as_deref(), Some(format!("value-{}", i).as_bytes()));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transactions_and_read_committed() {
+    let broker = Broker::start();
+
+    // Input for the consume-transform-produce half
+    let producer = broker.producer(&[]);
+    for i in 0..10 {
+        let value = format!("input-{}", i);
+        producer
+            .send(
+                FutureRecord::<(), _>::to("input")
+                    .payload(&value)
+                    .partition(0),
+                TIMEOUT,
+            )
+            .await
+            .map_err(|(e, _)| e)
+            .expect("Failed to produce");
+    }
+
+    let transactional = broker.producer(&[("transactional.id", "conformance")]);
+    transactional
+        .init_transactions(TIMEOUT)
+        .expect("Failed to init transactions");
+    let input = broker.consumer("transform", &[]);
+    input.subscribe(&["input"]).expect("Failed to subscribe");
+    receive(&input, 10).await;
+
+    for (commit, prefix) in [(true, "committed"), (false, "aborted"), (true, "again")] {
+        transactional
+            .begin_transaction()
+            .expect("Failed to begin a transaction");
+        for i in 0..5 {
+            let value = format!("{}-{}", prefix, i);
+            transactional
+                .send(
+                    FutureRecord::<(), _>::to("output")
+                        .payload(&value)
+                        .partition(0),
+                    TIMEOUT,
+                )
+                .await
+                .map_err(|(e, _)| e)
+                .expect("Failed to produce");
+        }
+        let mut offsets = TopicPartitionList::new();
+        let next = if commit { 5 } else { 8 };
+        offsets
+            .add_partition_offset("input", 0, Offset::Offset(next))
+            .unwrap();
+        transactional
+            .send_offsets_to_transaction(&offsets, &input.group_metadata().unwrap(), TIMEOUT)
+            .expect("Failed to send offsets");
+        if commit {
+            transactional.commit_transaction(TIMEOUT)
+        } else {
+            transactional.abort_transaction(TIMEOUT)
+        }
+        .expect("Failed to end the transaction");
+    }
+
+    let read_committed =
+        broker.consumer("read-committed", &[("isolation.level", "read_committed")]);
+    read_committed
+        .subscribe(&["output"])
+        .expect("Failed to subscribe");
+    let values: Vec<Vec<u8>> = receive(&read_committed, 10)
+        .await
+        .into_iter()
+        .filter_map(|(_, _, _, value)| value)
+        .collect();
+    let expected: Vec<Vec<u8>> = ["committed", "again"]
+        .iter()
+        .flat_map(|prefix| (0..5).map(move |i| format!("{}-{}", prefix, i).into_bytes()))
+        .collect();
+    assert_eq!(values, expected);
+    assert_drained(&read_committed).await;
+
+    let read_uncommitted = broker.consumer(
+        "read-uncommitted",
+        &[("isolation.level", "read_uncommitted")],
+    );
+    read_uncommitted
+        .subscribe(&["output"])
+        .expect("Failed to subscribe");
+    assert_eq!(receive(&read_uncommitted, 15).await.len(), 15);
+
+    // Only the committed transactions' offsets count
+    let mut partitions = TopicPartitionList::new();
+    partitions.add_partition("input", 0);
+    let committed = input
+        .committed_offsets(partitions, TIMEOUT)
+        .expect("Failed to fetch committed offsets");
+    assert_eq!(
+        committed.find_partition("input", 0).unwrap().offset(),
+        Offset::Offset(5)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_admin_metadata_configs_and_groups() {
+    let broker = Broker::start();
+    let producer = broker.producer(&[]);
+    producer
+        .send(FutureRecord::<(), _>::to("admin").payload("value"), TIMEOUT)
+        .await
+        .map_err(|(e, _)| e)
+        .expect("Failed to produce");
+
+    let metadata = producer
+        .client()
+        .fetch_metadata(Some("admin"), TIMEOUT)
+        .expect("Failed to fetch metadata");
+    assert_eq!(metadata.brokers().len(), 1);
+    assert_eq!(metadata.brokers()[0].id(), 1);
+    let topic = &metadata.topics()[0];
+    assert_eq!(topic.name(), "admin");
+    assert!(topic.error().is_none());
+    assert_eq!(topic.partitions().len(), 3);
+    for partition in topic.partitions() {
+        assert_eq!(partition.leader(), 1);
+        assert_eq!(partition.replicas(), &[1]);
+        assert_eq!(partition.isr(), &[1]);
+    }
+
+    let admin: AdminClient<DefaultClientContext> = broker
+        .config()
+        .create()
+        .expect("Failed to create the admin client");
+    let options = AdminOptions::new().request_timeout(Some(TIMEOUT));
+    let alter = AlterConfig::new(ResourceSpecifier::Topic("admin")).set("retention.ms", "3600000");
+    let altered = admin
+        .alter_configs(&[alter], &options)
+        .await
+        .expect("Failed to alter configs");
+    assert!(altered.iter().all(Result::is_ok), "{:?}", altered);
+
+    let described = admin
+        .describe_configs(
+            &[
+                ResourceSpecifier::Topic("admin"),
+                ResourceSpecifier::Broker(1),
+            ],
+            &options,
+        )
+        .await
+        .expect("Failed to describe configs");
+    let topic_config = described[0].as_ref().expect("Failed to describe the topic");
+    assert_eq!(
+        topic_config
+            .get("retention.ms")
+            .and_then(|entry| entry.value.as_deref()),
+        Some("3600000")
+    );
+    let broker_config = described[1]
+        .as_ref()
+        .expect("Failed to describe the broker");
+    assert!(broker_config.get("num.partitions").is_some());
+
+    let consumer = broker.consumer("listed", &[]);
+    consumer.subscribe(&["admin"]).expect("Failed to subscribe");
+    receive(&consumer, 1).await;
+    let groups = consumer
+        .fetch_group_list(Some("listed"), TIMEOUT)
+        .expect("Failed to list groups");
+    let group = &groups.groups()[0];
+    assert_eq!(group.name(), "listed");
+    assert_eq!(group.state(), "Stable");
+    assert_eq!(group.members().len(), 1);
+}
+
+/// Sends one admin request through librdkafka's C API, for the admin calls rdkafka does not
+/// wrap, and returns what `inspect` reads from the result event.
+fn admin_call<T>(
+    admin: &AdminClient<DefaultClientContext>,
+    call: impl FnOnce(*mut rd_kafka_t, *mut rd_kafka_queue_t),
+    inspect: impl FnOnce(*mut rd_kafka_event_t) -> T,
+) -> T {
+    let rk = admin.inner().native_ptr();
+    // SAFETY: the queue and event are created and destroyed here, while the client lives
+    unsafe {
+        let queue = rd_kafka_queue_new(rk);
+        call(rk, queue);
+        let event = rd_kafka_queue_poll(queue, TIMEOUT.as_millis() as i32);
+        assert!(!event.is_null(), "No admin result within {:?}", TIMEOUT);
+        let error = rd_kafka_event_error(event);
+        assert_eq!(
+            error,
+            rd_kafka_resp_err_t::RD_KAFKA_RESP_ERR_NO_ERROR,
+            "{}",
+            CStr::from_ptr(rd_kafka_event_error_string(event)).to_string_lossy()
+        );
+        let result = inspect(event);
+        rd_kafka_event_destroy(event);
+        rd_kafka_queue_destroy(queue);
+        result
+    }
+}
+
+/// An ACL binding, or a filter when `filter` allows `ANY` values, for reads of the `acls`
+/// topic by `User:alice`.
+fn alice_reads_acls(
+    filter: bool,
+    resource: rd_kafka_ResourceType_t,
+    pattern: rd_kafka_ResourcePatternType_t,
+    operation: rd_kafka_AclOperation_t,
+    permission: rd_kafka_AclPermissionType_t,
+) -> *mut rd_kafka_AclBinding_t {
+    let mut errstr = [0 as c_char; 512];
+    // SAFETY: the strings are copied by librdkafka
+    let binding = unsafe {
+        let new = if filter {
+            rd_kafka_AclBindingFilter_new
+        } else {
+            rd_kafka_AclBinding_new
+        };
+        new(
+            resource,
+            c"acls".as_ptr(),
+            pattern,
+            c"User:alice".as_ptr(),
+            c"*".as_ptr(),
+            operation,
+            permission,
+            errstr.as_mut_ptr(),
+            errstr.len(),
+        )
+    };
+    assert!(
+        !binding.is_null(),
+        "Invalid ACL binding: {}",
+        // SAFETY: librdkafka leaves a NUL-terminated message
+        unsafe { CStr::from_ptr(errstr.as_ptr()) }.to_string_lossy()
+    );
+    binding
+}
+
+fn describe_alice_acls(admin: &AdminClient<DefaultClientContext>) -> usize {
+    admin_call(
+        admin,
+        |rk, queue| unsafe {
+            let filter = alice_reads_acls(
+                true,
+                rd_kafka_ResourceType_t::RD_KAFKA_RESOURCE_ANY,
+                rd_kafka_ResourcePatternType_t::RD_KAFKA_RESOURCE_PATTERN_ANY,
+                rd_kafka_AclOperation_t::RD_KAFKA_ACL_OPERATION_ANY,
+                rd_kafka_AclPermissionType_t::RD_KAFKA_ACL_PERMISSION_TYPE_ANY,
+            );
+            rd_kafka_DescribeAcls(rk, filter, std::ptr::null(), queue);
+            rd_kafka_AclBinding_destroy(filter);
+        },
+        |event| unsafe {
+            let mut count = 0;
+            rd_kafka_DescribeAcls_result_acls(
+                rd_kafka_event_DescribeAcls_result(event),
+                &mut count,
+            );
+            count
+        },
+    )
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sasl_plain() {
+    let broker = Broker::start_with(
+        "SASL_PLAINTEXT",
+        &[
+            ("sasl.inter.broker.username", "alice"),
+            ("sasl.inter.broker.password", "alice-secret"),
+        ],
+    );
+    let sasl = [
+        ("security.protocol", "SASL_PLAINTEXT"),
+        ("sasl.mechanisms", "PLAIN"),
+        ("sasl.username", "alice"),
+        ("sasl.password", "alice-secret"),
+    ];
+    let producer = broker.producer(&sasl);
+    producer
+        .send(FutureRecord::<(), _>::to("sasl").payload("value"), TIMEOUT)
+        .await
+        .map_err(|(e, _)| e)
+        .expect("Failed to produce");
+
+    let consumer = broker.consumer("sasl", &sasl);
+    consumer.subscribe(&["sasl"]).expect("Failed to subscribe");
+    let received = receive(&consumer, 1).await;
+    assert_eq!(received[0].3.as_deref(), Some(&b"value"[..]));
+
+    let wrong = [
+        sasl[0],
+        sasl[1],
+        sasl[2],
+        ("sasl.password", "wrong"),
+        ("message.timeout.ms", "5000"),
+    ];
+    let producer = broker.producer(&wrong);
+    let record = FutureRecord::<(), _>::to("sasl").payload("value");
+    assert!(producer.send(record, TIMEOUT).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_acls_and_leader_election() {
+    let broker = Broker::start_with(
+        "PLAINTEXT",
+        &[
+            ("authorizer.enable", "true"),
+            ("super.users", "User:ANONYMOUS"),
+        ],
+    );
+    let producer = broker.producer(&[]);
+    producer
+        .send(FutureRecord::<(), _>::to("acls").payload("value"), TIMEOUT)
+        .await
+        .map_err(|(e, _)| e)
+        .expect("Failed to produce");
+    let admin: AdminClient<DefaultClientContext> = broker
+        .config()
+        .create()
+        .expect("Failed to create the admin client");
+
+    admin_call(
+        &admin,
+        |rk, queue| unsafe {
+            let mut binding = alice_reads_acls(
+                false,
+                rd_kafka_ResourceType_t::RD_KAFKA_RESOURCE_TOPIC,
+                rd_kafka_ResourcePatternType_t::RD_KAFKA_RESOURCE_PATTERN_LITERAL,
+                rd_kafka_AclOperation_t::RD_KAFKA_ACL_OPERATION_READ,
+                rd_kafka_AclPermissionType_t::RD_KAFKA_ACL_PERMISSION_TYPE_ALLOW,
+            );
+            rd_kafka_CreateAcls(rk, &mut binding, 1, std::ptr::null(), queue);
+            rd_kafka_AclBinding_destroy(binding);
+        },
+        |_| (),
+    );
+    assert_eq!(describe_alice_acls(&admin), 1);
+
+    admin_call(
+        &admin,
+        |rk, queue| unsafe {
+            let mut filter = alice_reads_acls(
+                true,
+                rd_kafka_ResourceType_t::RD_KAFKA_RESOURCE_TOPIC,
+                rd_kafka_ResourcePatternType_t::RD_KAFKA_RESOURCE_PATTERN_LITERAL,
+                rd_kafka_AclOperation_t::RD_KAFKA_ACL_OPERATION_ANY,
+                rd_kafka_AclPermissionType_t::RD_KAFKA_ACL_PERMISSION_TYPE_ANY,
+            );
+            rd_kafka_DeleteAcls(rk, &mut filter, 1, std::ptr::null(), queue);
+            rd_kafka_AclBinding_destroy(filter);
+        },
+        |_| (),
+    );
+    assert_eq!(describe_alice_acls(&admin), 0);
+
+    // The only replica is already the leader, so each partition reports that
+    let errors = admin_call(
+        &admin,
+        |rk, queue| unsafe {
+            let partitions = rd_kafka_topic_partition_list_new(3);
+            for partition in 0..3 {
+                rd_kafka_topic_partition_list_add(partitions, c"acls".as_ptr(), partition);
+            }
+            let request = rd_kafka_ElectLeaders_new(
+                rd_kafka_ElectionType_t::RD_KAFKA_ELECTION_TYPE_PREFERRED,
+                partitions,
+            );
+            rd_kafka_ElectLeaders(rk, request, std::ptr::null(), queue);
+            rd_kafka_ElectLeaders_destroy(request);
+            rd_kafka_topic_partition_list_destroy(partitions);
+        },
+        |event| unsafe {
+            let mut count = 0;
+            let results = rd_kafka_ElectLeaders_result_partitions(
+                rd_kafka_event_ElectLeaders_result(event),
+                &mut count,
+            );
+            (0..count)
+                .map(|i| {
+                    let error = rd_kafka_topic_partition_result_error(*results.add(i));
+                    rd_kafka_error_code(error)
+                })
+                .collect::<Vec<_>>()
+        },
+    );
+    assert_eq!(
+        errors,
+        vec![rd_kafka_resp_err_t::RD_KAFKA_RESP_ERR_ELECTION_NOT_NEEDED; 3]
+    );
+}
+
+#[test]
+fn test_kafka_python() {
+    let importable = Command::new("python3")
+        .args(["-c", "import kafka"])
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !importable {
+        eprintln!("Skipping the kafka-python flow: python3 cannot import kafka");
+        return;
+    }
+
+    let broker = Broker::start();
+    let script =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/conformance/kafka_python.py");
+    let status = Command::new("python3")
+        .arg(script)
+        .arg(&broker.bootstrap)
+        .status()
+        .expect("Failed to run python3");
+    assert!(status.success(), "The kafka-python flow failed");
+}
@@ -0,0 +1,83 @@
+"""Produces, consumes and administers a Forge broker with kafka-python.
+
+Run by tests/conformance.rs as `python3 kafka_python.py <bootstrap>`; exits non-zero on the
+first deviation.
+"""
+
+import sys
+
+from kafka import KafkaAdminClient, KafkaConsumer, KafkaProducer
+from kafka.admin import ConfigResource, ConfigResourceType
+
+TIMEOUT_MS = 30000
+
+
+def main(bootstrap):
+    for compression in [None, "gzip", "snappy", "lz4", "zstd"]:
+        try:
+            produce_and_consume(bootstrap, compression)
+        except AssertionError as e:
+            # kafka-python needs extra packages for some codecs
+            if "Libraries for" in str(e):
+                print("Skipping %s: %s" % (compression, e))
+                continue
+            raise
+
+    admin = KafkaAdminClient(bootstrap_servers=bootstrap, request_timeout_ms=TIMEOUT_MS)
+    admin.alter_configs(
+        [ConfigResource(ConfigResourceType.TOPIC, "python-none", configs={"retention.ms": "3600000"})]
+    )
+    described = admin.describe_configs([ConfigResource(ConfigResourceType.TOPIC, "python-none")])
+    entries = {entry[0]: entry[1] for entry in described[0].resources[0][4]}
+    assert entries["retention.ms"] == "3600000", entries
+
+    groups = [group[0] for group in admin.list_consumer_groups()]
+    assert "python-none" in groups, groups
+    admin.close()
+
+
+def produce_and_consume(bootstrap, compression):
+    topic = "python-%s" % (compression or "none")
+    producer = KafkaProducer(bootstrap_servers=bootstrap, compression_type=compression, linger_ms=5)
+    futures = [
+        producer.send(
+            topic,
+            key=b"key-%d" % (i % 10),
+            value=b"value-%d" % i,
+            headers=[("index", str(i).encode())],
+        )
+        for i in range(100)
+    ]
+    producer.flush()
+    written = [future.get(timeout=TIMEOUT_MS / 1000) for future in futures]
+    for partition in {metadata.partition for metadata in written}:
+        offsets = [metadata.offset for metadata in written if metadata.partition == partition]
+        assert offsets == list(range(len(offsets))), offsets
+    producer.close()
+
+    consumer = KafkaConsumer(
+        topic,
+        bootstrap_servers=bootstrap,
+        group_id=topic,
+        auto_offset_reset="earliest",
+        enable_auto_commit=False,
+        consumer_timeout_ms=TIMEOUT_MS,
+    )
+    received = []
+    for message in consumer:
+        index = int(dict(message.headers)["index"])
+        assert message.key == b"key-%d" % (index % 10), message
+        assert message.value == b"value-%d" % index, message
+        received.append(index)
+        if len(received) == 100:
+            break
+    assert sorted(received) == list(range(100)), received
+    consumer.commit()
+    partitions = consumer.assignment()
+    committed = sum(consumer.committed(partition) for partition in partitions)
+    assert committed == 100, committed
+    consumer.close()
+
+
+if __name__ == "__main__":
+    main(sys.argv[1])
@@ -0,0 +1,128 @@
+//! A TCP proxy that saves the first request and response it sees for each API and version as
+//! golden fixtures, in the format tests/wire_fixtures.rs reads. Put it between any client
+//! and any broker, Forge or Kafka, to record what they really send.
+
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use forge::adapters::driving::request_dispatcher::RequestDispatcher;
+use forge::shared::collections::FlatMap;
+
+/// Requests waiting for their response, by correlation id: name, client id and frame.
+type Pending = Arc<Mutex<FlatMap<i32, (String, String, Vec<u8>)>>>;
+
+/// Listens on a free port and relays every connection to `upstream`, writing fixtures into
+/// `dir`. Returns the address to connect to.
+pub fn start(upstream: String, dir: PathBuf) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind the recorder");
+    let address = listener
+        .local_addr()
+        .expect("No recorder address")
+        .to_string();
+    std::fs::create_dir_all(&dir).expect("Failed to create the fixture dir");
+    std::thread::spawn(move || {
+        for client in listener.incoming().flatten() {
+            let Ok(broker) = TcpStream::connect(&upstream) else {
+                continue;
+            };
+            let pending = Pending::default();
+            let (client_read, broker_read) = match (client.try_clone(), broker.try_clone()) {
+                (Ok(client_read), Ok(broker_read)) => (client_read, broker_read),
+                _ => continue,
+            };
+            let requests = pending.clone();
+            std::thread::spawn(move || relay_requests(client_read, broker, requests));
+            let dir = dir.clone();
+            std::thread::spawn(move || relay_responses(broker_read, client, pending, &dir));
+        }
+    });
+    address
+}
+
+fn read_frame(stream: &mut TcpStream) -> Option<Vec<u8>> {
+    let mut size = [0; 4];
+    stream.read_exact(&mut size).ok()?;
+    let mut frame = vec![0; i32::from_be_bytes(size).max(0) as usize];
+    stream.read_exact(&mut frame).ok()?;
+    Some(frame)
+}
+
+fn write_frame(stream: &mut TcpStream, frame: &[u8]) -> Option<()> {
+    stream.write_all(&(frame.len() as i32).to_be_bytes()).ok()?;
+    stream.write_all(frame).ok()
+}
+
+fn relay_requests(mut client: TcpStream, mut broker: TcpStream, pending: Pending) {
+    while let Some(frame) = read_frame(&mut client) {
+        if frame.len() >= 8 {
+            let api_key = i16::from_be_bytes([frame[0], frame[1]]);
+            let version = i16::from_be_bytes([frame[2], frame[3]]);
+            let correlation_id = i32::from_be_bytes([frame[4], frame[5], frame[6], frame[7]]);
+            // Probes with versions the broker rejects are not worth keeping
+            let supported = RequestDispatcher::supported_apis().iter().any(|api| {
+                api.api_key == api_key && (api.min_version..=api.max_version).contains(&version)
+            });
+            if let (true, Some(name)) = (supported, RequestDispatcher::api_name(api_key)) {
+                let name = format!("{}-v{}", name, version);
+                let client_id = client_id(&frame[8..]).unwrap_or_default();
+                pending
+                    .lock()
+                    .unwrap()
+                    .insert(correlation_id, (name, client_id, frame.clone()));
+            }
+        }
+        if write_frame(&mut broker, &frame).is_none() {
+            break;
+        }
+    }
+    let _ = broker.shutdown(std::net::Shutdown::Write);
+}
+
+fn relay_responses(mut broker: TcpStream, mut client: TcpStream, pending: Pending, dir: &Path) {
+    while let Some(frame) = read_frame(&mut broker) {
+        if frame.len() >= 4 {
+            let correlation_id = i32::from_be_bytes([frame[0], frame[1], frame[2], frame[3]]);
+            let request = pending.lock().unwrap().remove(&correlation_id);
+            if let Some((name, client_id, request)) = request {
+                save(dir, &name, &client_id, &request, &frame);
+            }
+        }
+        if write_frame(&mut client, &frame).is_none() {
+            break;
+        }
+    }
+    let _ = client.shutdown(std::net::Shutdown::Write);
+}
+
+fn client_id(header: &[u8]) -> Option<String> {
+    let len = i16::from_be_bytes([*header.first()?, *header.get(1)?]);
+    let bytes = header.get(2..2 + usize::try_from(len).ok()?)?;
+    Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Writes the pair unless this API version was recorded already.
+fn save(dir: &Path, name: &str, client_id: &str, request: &[u8], response: &[u8]) {
+    let path = dir.join(format!("{}.request.hex", name));
+    // Creating the request file claims the pair for this connection
+    let Ok(mut file) = OpenOptions::new().write(true).create_new(true).open(&path) else {
+        return;
+    };
+    let comment = format!("{}, recorded by tests/conformance/recorder.rs", name);
+    let request_comment = format!("{} request from client {}", comment, client_id);
+    let _ = file.write_all(to_hex(&request_comment, request).as_bytes());
+    let path = dir.join(format!("{}.response.hex", name));
+    let _ = std::fs::write(path, to_hex(&format!("{} response", comment), response));
+}
+
+fn to_hex(comment: &str, bytes: &[u8]) -> String {
+    let mut hex = format!("# {}\n", comment);
+    for line in bytes.chunks(16) {
+        let line: Vec<String> = line.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let _ = writeln!(hex, "{}", line.join(" "));
+    }
+    hex
+}
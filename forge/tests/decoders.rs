@@ -0,0 +1,177 @@
+//! Property tests for the wire decoders: arbitrary bytes must decode to a value or an error,
+//! never a panic, and the varints must round-trip. The fuzz targets in fuzz/ drive the same
+//! entry points for longer runs.
+
+use bytes::BufMut;
+use forge::core::domain::compression::CompressionType;
+use forge::core::domain::record::Record;
+use forge::core::domain::record_batch::RecordBatch;
+use forge::protocol::fuzzing::{
+    APIS, decode_embedded, decode_record_batch, decode_record_batches, decode_request,
+    decode_request_body, decode_response,
+};
+use forge::protocol::types::{
+    CompactArray, CompactBytes, CompactString, TaggedFields, Type, UnsignedVarint, UnsignedVarlong,
+    Varint, Varlong,
+};
+use proptest::prelude::*;
+
+fn api() -> impl Strategy<Value = (i16, i16)> {
+    proptest::sample::select(APIS).prop_flat_map(|(api_key, min, max)| (Just(api_key), min..=max))
+}
+
+/// A batch header with a correct length and CRC around `payload`, so decoding gets past the
+/// checksum to the records.
+fn framed_batch(attributes: i16, records_count: i32, payload: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.put_i16(attributes);
+    body.put_i32(0);
+    body.put_i64(0);
+    body.put_i64(0);
+    body.put_i64(-1);
+    body.put_i16(-1);
+    body.put_i32(-1);
+    body.put_i32(records_count);
+    body.put_slice(payload);
+
+    let mut batch = Vec::new();
+    batch.put_i64(0);
+    batch.put_i32((4 + 1 + 4 + body.len()) as i32);
+    batch.put_i32(0);
+    batch.put_i8(2);
+    batch.put_u32(crc32c::crc32c(&body));
+    batch.put_slice(&body);
+    batch
+}
+
+fn decode<T: Type>(mut data: &[u8]) {
+    let _ = T::decode(&mut data);
+}
+
+proptest! {
+    #[test]
+    fn test_request_frames_never_panic(data in proptest::collection::vec(any::<u8>(), 0..512)) {
+        let _ = decode_request(&data);
+    }
+
+    #[test]
+    fn test_request_bodies_never_panic(
+        (api_key, version) in api(),
+        data in proptest::collection::vec(any::<u8>(), 0..512),
+    ) {
+        let _ = decode_request_body(api_key, version, &data);
+    }
+
+    #[test]
+    fn test_responses_never_panic(
+        (api_key, version) in api(),
+        data in proptest::collection::vec(any::<u8>(), 0..512),
+    ) {
+        let _ = decode_response(api_key, version, &data);
+    }
+
+    #[test]
+    fn test_records_never_panic(data in proptest::collection::vec(any::<u8>(), 0..512)) {
+        decode::<Record>(&data);
+        let _ = decode_record_batch(&data);
+        let _ = decode_record_batches(&data);
+    }
+
+    #[test]
+    fn test_batches_with_valid_crc_never_panic(
+        codec in 0i16..5,
+        records_count in any::<i32>(),
+        payload in proptest::collection::vec(any::<u8>(), 0..512),
+    ) {
+        let _ = decode_record_batch(&framed_batch(codec, records_count, &payload));
+    }
+
+    #[test]
+    fn test_compressed_garbage_never_panics(
+        codec in 1i16..5,
+        data in proptest::collection::vec(any::<u8>(), 0..512),
+    ) {
+        let compression = CompressionType::from_attributes(codec).unwrap();
+        let _ = compression.decompress(&data);
+    }
+
+    #[test]
+    fn test_embedded_records_never_panic(data in proptest::collection::vec(any::<u8>(), 0..512)) {
+        let _ = decode_embedded(&data);
+    }
+
+    #[test]
+    fn test_wire_types_never_panic(data in proptest::collection::vec(any::<u8>(), 0..256)) {
+        decode::<String>(&data);
+        decode::<Option<String>>(&data);
+        decode::<CompactString>(&data);
+        decode::<Option<CompactString>>(&data);
+        decode::<CompactBytes>(&data);
+        decode::<CompactArray<i32>>(&data);
+        decode::<Option<CompactArray<CompactString>>>(&data);
+        decode::<Vec<String>>(&data);
+        decode::<Option<Vec<i64>>>(&data);
+        decode::<TaggedFields>(&data);
+        decode::<Varint>(&data);
+        decode::<Varlong>(&data);
+        decode::<UnsignedVarint>(&data);
+        decode::<UnsignedVarlong>(&data);
+        decode::<uuid::Uuid>(&data);
+    }
+
+    #[test]
+    fn test_varints_round_trip(a in any::<i32>(), b in any::<i64>(), c in any::<u32>(), d in any::<u64>()) {
+        let mut buf = Vec::new();
+        Varint(a).encode(&mut buf);
+        Varlong(b).encode(&mut buf);
+        UnsignedVarint(c).encode(&mut buf);
+        UnsignedVarlong(d).encode(&mut buf);
+
+        let mut data = &buf[..];
+        prop_assert_eq!(Varint::decode(&mut data).unwrap(), Varint(a));
+        prop_assert_eq!(Varlong::decode(&mut data).unwrap(), Varlong(b));
+        prop_assert_eq!(UnsignedVarint::decode(&mut data).unwrap(), UnsignedVarint(c));
+        prop_assert_eq!(UnsignedVarlong::decode(&mut data).unwrap(), UnsignedVarlong(d));
+        prop_assert!(data.is_empty());
+    }
+
+    #[test]
+    fn test_batches_round_trip(
+        codec in 0i16..5,
+        values in proptest::collection::vec(proptest::collection::vec(any::<u8>(), 0..64), 1..16),
+    ) {
+        let records = values
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| Record::new(i as i32, None, Some(value)))
+            .collect();
+        let mut batch = RecordBatch::new(0, records);
+        batch.attributes = codec;
+
+        let mut buf = Vec::new();
+        batch.encode(&mut buf);
+        let decoded = RecordBatch::decode(&mut &buf[..]).unwrap();
+        let values = |batch: &RecordBatch| {
+            batch.records.iter().map(|r| r.value.clone()).collect::<Vec<_>>()
+        };
+        prop_assert_eq!(values(&decoded), values(&batch));
+    }
+}
+
+#[test]
+fn test_forged_lengths_fail_without_allocating() {
+    // Each claims billions of elements, backed by a few bytes
+    let mut array = Vec::new();
+    UnsignedVarint(u32::MAX).encode(&mut array);
+    assert!(CompactArray::<i64>::decode(&mut &array[..]).is_err());
+    assert!(Option::<CompactArray<i64>>::decode(&mut &array[..]).is_err());
+    assert!(Vec::<i64>::decode(&mut &i32::MAX.to_be_bytes()[..]).is_err());
+
+    let batch = framed_batch(0, i32::MAX, &[0; 8]);
+    assert!(decode_record_batch(&batch).is_err());
+    assert!(decode_record_batch(&framed_batch(0, -1, &[])).is_err());
+
+    let mut short = framed_batch(0, 0, &[]);
+    short[8..12].copy_from_slice(&2i32.to_be_bytes());
+    assert!(decode_record_batch(&short).is_err());
+}
@@ -0,0 +1,107 @@
+//! Golden wire fixtures: request and response frames recorded off the wire by
+//! tests/conformance/recorder.rs, which must decode and encode back to the very same bytes.
+//! Each API version has a `<Api>-v<version>.request.hex` and `.response.hex` in
+//! tests/fixtures/wire: hex bytes without the size prefix, `#` starting a comment.
+
+use std::path::{Path, PathBuf};
+
+use forge::adapters::driving::request_dispatcher::RequestDispatcher;
+use forge::protocol::round_trip;
+
+const REQUEST_SUFFIX: &str = ".request.hex";
+
+/// APIs neither librdkafka nor kafka-python can send, so the conformance suite cannot record
+/// them.
+const NOT_RECORDED: [&str; 2] = ["AlterPartitionReassignments", "ListPartitionReassignments"];
+
+fn fixture_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/wire")
+}
+
+/// The fixture names, `<Api>-v<version>`, in order.
+fn fixtures() -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(fixture_dir())
+        .expect("Failed to list the fixtures")
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter_map(|file| file.strip_suffix(REQUEST_SUFFIX).map(str::to_string))
+        .collect();
+    names.sort();
+    names
+}
+
+fn read_hex(path: &Path) -> Vec<u8> {
+    let text = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Failed to read {}: {}", path.display(), e));
+    let digits: String = text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(|line| line.chars().filter(|c| !c.is_whitespace()))
+        .collect();
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(digits.get(i..i + 2).unwrap_or("?"), 16)
+                .unwrap_or_else(|_| panic!("{} is not hex at digit {}", path.display(), i))
+        })
+        .collect()
+}
+
+/// Why `encoded` is not `expected`, if it is not.
+fn compare(result: Result<Vec<u8>, String>, expected: &[u8]) -> Option<String> {
+    match result {
+        Ok(encoded) if encoded == expected => None,
+        Ok(encoded) => {
+            let at = encoded
+                .iter()
+                .zip(expected)
+                .position(|(a, b)| a != b)
+                .unwrap_or(encoded.len().min(expected.len()));
+            Some(format!(
+                "encodes to {} bytes instead of {}, differing from byte {}",
+                encoded.len(),
+                expected.len(),
+                at
+            ))
+        }
+        Err(e) => Some(format!("does not decode: {}", e)),
+    }
+}
+
+#[test]
+fn test_fixtures_round_trip() {
+    let mut failures = Vec::new();
+    for name in fixtures() {
+        let request = read_hex(&fixture_dir().join(format!("{}{}", name, REQUEST_SUFFIX)));
+        assert!(request.len() >= 4, "{}: request has no header", name);
+        let api_key = i16::from_be_bytes([request[0], request[1]]);
+        let version = i16::from_be_bytes([request[2], request[3]]);
+        let api = RequestDispatcher::api_name(api_key).unwrap_or("Unknown");
+        assert_eq!(name, format!("{}-v{}", api, version), "misnamed fixture");
+
+        if let Some(problem) = compare(round_trip::request(&request), &request) {
+            failures.push(format!("{} request {}", name, problem));
+        }
+        let response = read_hex(&fixture_dir().join(format!("{}.response.hex", name)));
+        if let Some(problem) = compare(round_trip::response(api_key, version, &response), &response)
+        {
+            failures.push(format!("{} response {}", name, problem));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn test_every_api_has_fixtures() {
+    let names = fixtures();
+    let missing: Vec<&str> = RequestDispatcher::supported_apis()
+        .iter()
+        .filter_map(|api| RequestDispatcher::api_name(api.api_key))
+        .filter(|api| !NOT_RECORDED.contains(api))
+        .filter(|api| {
+            !names
+                .iter()
+                .any(|name| name.starts_with(&format!("{}-v", api)))
+        })
+        .collect();
+    assert!(missing.is_empty(), "No fixtures for {}", missing.join(", "));
+}